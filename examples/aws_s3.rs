@@ -8,6 +8,14 @@ async fn main() {
         region: "us-east-1".to_string(),
         bucket: "test-bucket".to_string(),
         credentials: None,
+        credentials_provider: None,
+        anonymous: false,
+        public_url: None,
+        requester_pays: false,
+        accelerate: false,
+        read_endpoint: None,
+        write_endpoint: None,
+        pool: Default::default(),
     };
     let s3_driver = StoreConfig::AwsS3(config).build().await.unwrap();
 
@@ -8,6 +8,9 @@ async fn main() {
         account: "account".to_string(),
         container: "test".to_string(),
         credentials: drivers::azure::ClientCredentials::AccessKey("key".to_string()),
+        credentials_provider: None,
+        public_url: None,
+        pool: Default::default(),
     };
     let azure_driver = StoreConfig::Azure(config).build().await.unwrap();
 
@@ -6,6 +6,7 @@ use active_storage::{drivers, StoreConfig};
 async fn main() {
     let config = drivers::disk::Config {
         location: PathBuf::from("tmp"),
+        public_url: None,
     };
     let disk_driver = StoreConfig::Disk(config).build().await.unwrap();
 
@@ -6,11 +6,13 @@ use active_storage::{drivers, multi_store::MultiStore, StoreConfig};
 async fn main() {
     let config = drivers::disk::Config {
         location: PathBuf::from("tmp").join("primary-storage"),
+        public_url: None,
     };
     let store_one = StoreConfig::Disk(config).build().await.unwrap();
 
     let config = drivers::disk::Config {
         location: PathBuf::from("tmp").join("backups"),
+        public_url: None,
     };
     let secondary_store = StoreConfig::Disk(config).build().await.unwrap();
 
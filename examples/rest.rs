@@ -0,0 +1,20 @@
+use std::path::PathBuf;
+
+use active_storage::{drivers, rest::RestServer, transfer::CancellationToken, StoreConfig};
+use tokio::net::TcpListener;
+
+#[tokio::main]
+async fn main() {
+    let config = drivers::disk::Config {
+        location: PathBuf::from("tmp"),
+        public_url: None,
+    };
+    let disk_driver = StoreConfig::Disk(config).build().await.unwrap();
+
+    let token = std::env::var("ACTIVE_STORAGE_TOKEN").expect("ACTIVE_STORAGE_TOKEN is not set");
+    let listener = TcpListener::bind("127.0.0.1:8080").await.unwrap();
+    RestServer::new(disk_driver, token)
+        .serve(listener, CancellationToken::new())
+        .await
+        .unwrap();
+}
@@ -0,0 +1,750 @@
+//! # Archive Module
+//!
+//! The `archive` module streams many stored files as a single archive, e.g.
+//! for "download all attachments" endpoints, and exports or imports a whole
+//! prefix as a tar archive, e.g. for environment seeding and cold backups.
+//!
+//! Archives are built lazily: each file is read only when the stream reaches
+//! it, so only one file is held in memory at a time.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::PathBuf;
+//! use active_storage::{archive::ArchiveSource, StoreConfig};
+//! use futures::TryStreamExt;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!     store
+//!         .write(PathBuf::from("docs").join("a.txt").as_path(), "a")
+//!         .await
+//!         .unwrap();
+//!
+//!     let zip: Vec<Vec<u8>> = store
+//!         .zip(ArchiveSource::Prefix("docs".into()))
+//!         .await
+//!         .unwrap()
+//!         .try_collect()
+//!         .await
+//!         .unwrap();
+//!     assert!(zip.concat().starts_with(b"PK\x03\x04"));
+//! }
+//! ```
+use std::{
+    io::{self, Write},
+    path::Path,
+    time::SystemTime,
+};
+#[cfg(feature = "zstd")]
+use std::{
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use futures::stream;
+#[cfg(feature = "zstd")]
+use tokio::io::{AsyncBufRead, ReadBuf};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+
+use crate::{
+    errors::{DriverError, DriverResult},
+    key::{Key, ObjectPath},
+    serve::ByteStream,
+    store::Store,
+};
+
+/// Enum representing the files to include in an archive.
+#[derive(Clone, Debug)]
+pub enum ArchiveSource {
+    /// The given files, stored in the archive under their full path.
+    Paths(Vec<ObjectPath>),
+    /// All the files under the given prefix, stored in the archive under their
+    /// path relative to the prefix.
+    Prefix(ObjectPath),
+}
+
+/// A file to write in the archive: its path in the store and its name in the
+/// archive.
+struct Entry {
+    path: ObjectPath,
+    name: String,
+}
+
+/// Resolves the archive entries of the given source.
+async fn entries(store: &Store, source: ArchiveSource) -> DriverResult<Vec<Entry>> {
+    match source {
+        ArchiveSource::Paths(paths) => paths
+            .into_iter()
+            .map(|path| {
+                let name = path.to_key()?.to_string();
+                Ok(Entry { path, name })
+            })
+            .collect(),
+        ArchiveSource::Prefix(prefix) => store
+            .list(&prefix)
+            .await?
+            .into_iter()
+            .map(|path| {
+                let name = path
+                    .strip_prefix(&prefix)
+                    .unwrap_or_else(|| path.clone())
+                    .to_key()?
+                    .to_string();
+                Ok(Entry { path, name })
+            })
+            .collect(),
+    }
+}
+
+/// Streams the files of the given source as a ZIP archive.
+pub(crate) async fn zip(store: &Store, source: ArchiveSource) -> DriverResult<ByteStream> {
+    let entries = entries(store, source).await?;
+    if entries.len() > usize::from(u16::MAX) {
+        return Err(DriverError::Unsupported("zip64 archives"));
+    }
+
+    let state = (
+        store.clone(),
+        entries.into_iter(),
+        Some(ZipWriter::default()),
+    );
+    let stream = stream::try_unfold(state, |(store, mut entries, writer)| async move {
+        let Some(mut writer) = writer else {
+            return Ok(None);
+        };
+
+        if let Some(entry) = entries.next() {
+            let content: Vec<u8> = store.read(&entry.path).await?;
+            let last_modified = store.last_modified(&entry.path).await?;
+            let chunk = writer.write_entry(&entry.name, &content, last_modified)?;
+            Ok(Some((chunk, (store, entries, Some(writer)))))
+        } else {
+            Ok(Some((writer.finish()?, (store, entries, None))))
+        }
+    });
+
+    Ok(Box::pin(stream))
+}
+
+/// Struct tracking the state of a ZIP archive being written.
+///
+/// Entries are stored uncompressed; since each file is read entirely before
+/// being written, its CRC and size are known up front and no data descriptor
+/// is needed.
+#[derive(Default)]
+struct ZipWriter {
+    offset: u64,
+    central_directory: Vec<u8>,
+    count: u16,
+}
+
+impl ZipWriter {
+    /// Returns the local header and content of the given entry, and records
+    /// its central directory header.
+    fn write_entry(
+        &mut self,
+        name: &str,
+        content: &[u8],
+        last_modified: SystemTime,
+    ) -> DriverResult<Vec<u8>> {
+        let size =
+            u32::try_from(content.len()).map_err(|_| DriverError::Unsupported("zip64 archives"))?;
+        let offset =
+            u32::try_from(self.offset).map_err(|_| DriverError::Unsupported("zip64 archives"))?;
+        let name_len =
+            u16::try_from(name.len()).map_err(|_| DriverError::Unsupported("zip64 archives"))?;
+        let crc = crc32fast::hash(content);
+        let (time, date) = dos_date_time(last_modified);
+
+        let mut chunk = Vec::with_capacity(30 + name.len() + content.len());
+        chunk.extend_from_slice(&0x0403_4b50_u32.to_le_bytes());
+        chunk.extend_from_slice(&20_u16.to_le_bytes()); // version needed
+        chunk.extend_from_slice(&0x0800_u16.to_le_bytes()); // UTF-8 names
+        chunk.extend_from_slice(&0_u16.to_le_bytes()); // stored
+        chunk.extend_from_slice(&time.to_le_bytes());
+        chunk.extend_from_slice(&date.to_le_bytes());
+        chunk.extend_from_slice(&crc.to_le_bytes());
+        chunk.extend_from_slice(&size.to_le_bytes());
+        chunk.extend_from_slice(&size.to_le_bytes());
+        chunk.extend_from_slice(&name_len.to_le_bytes());
+        chunk.extend_from_slice(&0_u16.to_le_bytes()); // extra field length
+        chunk.extend_from_slice(name.as_bytes());
+        chunk.extend_from_slice(content);
+
+        let header = &mut self.central_directory;
+        header.extend_from_slice(&0x0201_4b50_u32.to_le_bytes());
+        header.extend_from_slice(&20_u16.to_le_bytes()); // version made by
+        header.extend_from_slice(&20_u16.to_le_bytes()); // version needed
+        header.extend_from_slice(&0x0800_u16.to_le_bytes());
+        header.extend_from_slice(&0_u16.to_le_bytes());
+        header.extend_from_slice(&time.to_le_bytes());
+        header.extend_from_slice(&date.to_le_bytes());
+        header.extend_from_slice(&crc.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&name_len.to_le_bytes());
+        header.extend_from_slice(&[0; 12]); // extra, comment, disk, attributes
+        header.extend_from_slice(&offset.to_le_bytes());
+        header.extend_from_slice(name.as_bytes());
+
+        self.offset += chunk.len() as u64;
+        self.count += 1;
+        Ok(chunk)
+    }
+
+    /// Returns the central directory and its end record.
+    fn finish(self) -> DriverResult<Vec<u8>> {
+        let size = u32::try_from(self.central_directory.len())
+            .map_err(|_| DriverError::Unsupported("zip64 archives"))?;
+        let offset =
+            u32::try_from(self.offset).map_err(|_| DriverError::Unsupported("zip64 archives"))?;
+
+        let mut chunk = self.central_directory;
+        chunk.extend_from_slice(&0x0605_4b50_u32.to_le_bytes());
+        chunk.extend_from_slice(&[0; 4]); // disk numbers
+        chunk.extend_from_slice(&self.count.to_le_bytes());
+        chunk.extend_from_slice(&self.count.to_le_bytes());
+        chunk.extend_from_slice(&size.to_le_bytes());
+        chunk.extend_from_slice(&offset.to_le_bytes());
+        chunk.extend_from_slice(&0_u16.to_le_bytes()); // comment length
+        Ok(chunk)
+    }
+}
+
+/// The size of a tar block.
+const TAR_BLOCK: usize = 512;
+
+/// The magic number prefixing a zstd frame.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+fn io_error(err: io::Error) -> DriverError {
+    DriverError::Any(Box::new(err))
+}
+
+/// The destination of an exported tar archive, compressing it on the fly
+/// when exported with zstd.
+struct TarWriter<'a, W: ?Sized> {
+    writer: &'a mut W,
+    #[cfg(feature = "zstd")]
+    encoder: Option<zstd::stream::write::Encoder<'static, Vec<u8>>>,
+}
+
+impl<W: AsyncWrite + Unpin + Send + ?Sized> TarWriter<'_, W> {
+    /// Writes the given bytes of the archive, or the part of them already
+    /// compressed.
+    async fn write(&mut self, bytes: &[u8]) -> DriverResult<()> {
+        #[cfg(feature = "zstd")]
+        if let Some(encoder) = &mut self.encoder {
+            encoder.write_all(bytes).map_err(io_error)?;
+            let compressed = std::mem::take(encoder.get_mut());
+            return self.writer.write_all(&compressed).await.map_err(io_error);
+        }
+        self.writer.write_all(bytes).await.map_err(io_error)
+    }
+
+    /// Writes the end of archive marker and flushes the writer.
+    async fn finish(mut self) -> DriverResult<()> {
+        self.write(&[0; TAR_BLOCK * 2]).await?;
+        #[cfg(feature = "zstd")]
+        if let Some(encoder) = self.encoder.take() {
+            let compressed = encoder.finish().map_err(io_error)?;
+            self.writer.write_all(&compressed).await.map_err(io_error)?;
+        }
+        self.writer.flush().await.map_err(io_error)
+    }
+}
+
+/// Writes all the files under the given prefix to the writer as a tar
+/// archive, returning the number of exported files.
+pub(crate) async fn export_tar<W: AsyncWrite + Unpin + Send + ?Sized>(
+    store: &Store,
+    prefix: &Path,
+    writer: &mut W,
+) -> DriverResult<usize> {
+    write_tar(
+        store,
+        prefix,
+        TarWriter {
+            writer,
+            #[cfg(feature = "zstd")]
+            encoder: None,
+        },
+    )
+    .await
+}
+
+/// Writes all the files under the given prefix to the writer as a zstd
+/// compressed tar archive, returning the number of exported files.
+#[cfg(feature = "zstd")]
+pub(crate) async fn export_tar_zstd<W: AsyncWrite + Unpin + Send + ?Sized>(
+    store: &Store,
+    prefix: &Path,
+    writer: &mut W,
+    level: i32,
+) -> DriverResult<usize> {
+    let encoder = zstd::stream::write::Encoder::new(Vec::new(), level).map_err(io_error)?;
+    write_tar(
+        store,
+        prefix,
+        TarWriter {
+            writer,
+            encoder: Some(encoder),
+        },
+    )
+    .await
+}
+
+/// Writes the files under the given prefix one entry at a time, so only one
+/// file is held in memory.
+async fn write_tar<W: AsyncWrite + Unpin + Send + ?Sized>(
+    store: &Store,
+    prefix: &Path,
+    mut writer: TarWriter<'_, W>,
+) -> DriverResult<usize> {
+    let entries = entries(store, ArchiveSource::Prefix(prefix.into())).await?;
+    for entry in &entries {
+        let content: Vec<u8> = store.read(&entry.path).await?;
+        let last_modified = store.last_modified(&entry.path).await?;
+        let mut block = Vec::with_capacity(content.len() + TAR_BLOCK * 3);
+        write_tar_entry(&mut block, &entry.name, &content, last_modified)?;
+        writer.write(&block).await?;
+    }
+    writer.finish().await?;
+
+    Ok(entries.len())
+}
+
+/// Writes the files of the given tar archive under the given prefix,
+/// returning the number of imported files. zstd compressed archives are
+/// detected and decompressed when the `zstd` feature is enabled.
+pub(crate) async fn import_tar<R: AsyncRead + Unpin + Send + ?Sized>(
+    store: &Store,
+    reader: &mut R,
+    prefix: &Path,
+) -> DriverResult<usize> {
+    let mut reader = BufReader::new(reader);
+    let is_zstd = reader
+        .fill_buf()
+        .await
+        .map_err(io_error)?
+        .starts_with(&ZSTD_MAGIC);
+
+    if !is_zstd {
+        return read_tar(store, &mut reader, prefix).await;
+    }
+
+    #[cfg(feature = "zstd")]
+    {
+        let mut decoder = ZstdReader {
+            reader,
+            decoder: zstd::stream::raw::Decoder::new().map_err(io_error)?,
+        };
+        read_tar(store, &mut decoder, prefix).await
+    }
+    #[cfg(not(feature = "zstd"))]
+    Err(DriverError::Unsupported("zstd archives"))
+}
+
+/// Decompresses a zstd stream as it is read.
+#[cfg(feature = "zstd")]
+struct ZstdReader<R> {
+    reader: R,
+    decoder: zstd::stream::raw::Decoder<'static>,
+}
+
+#[cfg(feature = "zstd")]
+impl<R: AsyncBufRead + Unpin> AsyncRead for ZstdReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        use zstd::stream::raw::Operation;
+
+        let this = self.get_mut();
+        loop {
+            let input = ready!(Pin::new(&mut this.reader).poll_fill_buf(cx))?;
+            // the decoder may still hold decoded bytes at the end of the input
+            let end = input.is_empty();
+            let status = this
+                .decoder
+                .run_on_buffers(input, buf.initialize_unfilled())?;
+            Pin::new(&mut this.reader).consume(status.bytes_read);
+            buf.advance(status.bytes_written);
+            if status.bytes_written > 0 || end || buf.remaining() == 0 {
+                return Poll::Ready(Ok(()));
+            }
+        }
+    }
+}
+
+/// Reads the regular files of a tar archive and writes them under the given
+/// prefix. Entry names are normalized as [`Key`]s, so entries can't escape the
+/// prefix.
+///
+/// The content of an entry is read as it arrives rather than preallocated
+/// from the size in its header, so a forged size fails on the truncated
+/// input instead of exhausting the memory.
+async fn read_tar<R: AsyncRead + Unpin + Send + ?Sized>(
+    store: &Store,
+    reader: &mut R,
+    prefix: &Path,
+) -> DriverResult<usize> {
+    let mut count = 0;
+    let mut long_name = None;
+    let mut header = [0; TAR_BLOCK];
+
+    loop {
+        reader.read_exact(&mut header).await.map_err(io_error)?;
+        if header.iter().all(|byte| *byte == 0) {
+            return Ok(count);
+        }
+
+        let size = parse_octal(&header[124..136])?;
+        let mut content = Vec::new();
+        (&mut *reader)
+            .take(size)
+            .read_to_end(&mut content)
+            .await
+            .map_err(io_error)?;
+        if content.len() as u64 != size {
+            return Err(DriverError::DecodeError);
+        }
+        let padding = (TAR_BLOCK - content.len() % TAR_BLOCK) % TAR_BLOCK;
+        tokio::io::copy(
+            &mut (&mut *reader).take(padding as u64),
+            &mut tokio::io::sink(),
+        )
+        .await
+        .map_err(io_error)?;
+
+        match header[156] {
+            // GNU long name
+            b'L' => long_name = Some(c_string(&content)),
+            // pax extended header
+            b'x' => long_name = pax_path(&content).or(long_name),
+            b'0' | 0 => {
+                let name = long_name.take().unwrap_or_else(|| header_name(&header));
+                let path = prefix.join(Key::new(&name)?.to_path_buf());
+                store.write(&path, content).await?;
+                count += 1;
+            }
+            _ => long_name = None,
+        }
+    }
+}
+
+/// Writes a tar entry of a regular file, preceded by a pax header when the
+/// name doesn't fit in the ustar header.
+fn write_tar_entry<W: Write + ?Sized>(
+    writer: &mut W,
+    name: &str,
+    content: &[u8],
+    last_modified: SystemTime,
+) -> DriverResult<()> {
+    let mtime = last_modified
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    if name.len() > 100 {
+        let record = pax_record("path", name);
+        write_tar_block(writer, "././@PaxHeader", b'x', record.as_bytes(), mtime)?;
+    }
+
+    write_tar_block(writer, name, b'0', content, mtime)
+}
+
+/// Writes a ustar header followed by the padded content.
+fn write_tar_block<W: Write + ?Sized>(
+    writer: &mut W,
+    name: &str,
+    kind: u8,
+    content: &[u8],
+    mtime: u64,
+) -> DriverResult<()> {
+    // 11 octal digits
+    if content.len() as u64 >= 1 << 33 {
+        return Err(DriverError::Unsupported("tar entries over 8 GiB"));
+    }
+
+    let mut header = [0; TAR_BLOCK];
+    let name = &name.as_bytes()[..name.len().min(100)];
+    header[..name.len()].copy_from_slice(name);
+    header[100..108].copy_from_slice(b"0000644\0");
+    header[108..116].copy_from_slice(b"0000000\0");
+    header[116..124].copy_from_slice(b"0000000\0");
+    header[124..136].copy_from_slice(format!("{:011o}\0", content.len()).as_bytes());
+    header[136..148].copy_from_slice(format!("{:011o}\0", mtime.min(0o777_7777_7777)).as_bytes());
+    header[148..156].copy_from_slice(b"        ");
+    header[156] = kind;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    let checksum: u32 = header.iter().map(|byte| u32::from(*byte)).sum();
+    header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+
+    let padding = (TAR_BLOCK - content.len() % TAR_BLOCK) % TAR_BLOCK;
+    writer
+        .write_all(&header)
+        .and_then(|()| writer.write_all(content))
+        .and_then(|()| writer.write_all(&[0; TAR_BLOCK][..padding]))
+        .map_err(io_error)
+}
+
+/// Returns a pax extended header record, prefixed with its own length.
+fn pax_record(key: &str, value: &str) -> String {
+    let record = format!(" {key}={value}\n");
+    let mut len = record.len() + 1;
+    while len != record.len() + len.to_string().len() {
+        len += 1;
+    }
+    format!("{len}{record}")
+}
+
+/// Returns the `path` value of a pax extended header.
+fn pax_path(content: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(content)
+        .lines()
+        .filter_map(|line| line.split_once(' ').map(|(_, record)| record))
+        .find_map(|record| record.strip_prefix("path=").map(ToString::to_string))
+}
+
+/// Returns the entry name of a ustar header, joined with its prefix.
+fn header_name(header: &[u8; TAR_BLOCK]) -> String {
+    let name = c_string(&header[..100]);
+    if &header[257..262] == b"ustar" && header[345] != 0 {
+        format!("{}/{name}", c_string(&header[345..500]))
+    } else {
+        name
+    }
+}
+
+/// Returns the string of a nul terminated field.
+fn c_string(field: &[u8]) -> String {
+    let end = field
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).to_string()
+}
+
+/// Parses a nul or space terminated octal field.
+fn parse_octal(field: &[u8]) -> DriverResult<u64> {
+    let value = c_string(field);
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(value, 8).map_err(|_| DriverError::DecodeError)
+}
+
+/// Converts the given time to the MS-DOS `(time, date)` format, in UTC.
+/// Times before 1980 are clamped to the DOS epoch.
+fn dos_date_time(time: SystemTime) -> (u16, u16) {
+    const DOS_EPOCH: u64 = 315_532_800;
+
+    let secs = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+        .max(DOS_EPOCH);
+    let days = secs / 86_400;
+    let secs_of_day = secs % 86_400;
+
+    // civil date from days since the unix epoch
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    #[allow(clippy::cast_possible_truncation)]
+    let time = (((secs_of_day / 3600) << 11)
+        | ((secs_of_day % 3600 / 60) << 5)
+        | ((secs_of_day % 60) / 2)) as u16;
+    #[allow(clippy::cast_possible_truncation)]
+    let date = ((year.saturating_sub(1980).min(127) << 9) | (month << 5) | day) as u16;
+    (time, date)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{
+        path::{Path, PathBuf},
+        time::Duration,
+    };
+
+    use futures::TryStreamExt;
+
+    use super::*;
+    use crate::StoreConfig;
+
+    #[test]
+    fn can_convert_dos_date_time() {
+        // 2024-02-29 13:45:30 UTC
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_709_214_330);
+        assert_eq!(
+            dos_date_time(time),
+            ((13 << 11) | (45 << 5) | 15, (44 << 9) | (2 << 5) | 29)
+        );
+        assert_eq!(dos_date_time(SystemTime::UNIX_EPOCH), (0, (1 << 5) | 1));
+    }
+
+    #[tokio::test]
+    async fn can_zip_prefix() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        store
+            .write(&PathBuf::from("docs").join("a.txt"), "aaa")
+            .await
+            .unwrap();
+        store
+            .write(&PathBuf::from("docs").join("sub").join("b.txt"), "b")
+            .await
+            .unwrap();
+        store.write(Path::new("other.txt"), "other").await.unwrap();
+
+        let zip = store
+            .zip(ArchiveSource::Prefix("docs".into()))
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap()
+            .concat();
+
+        // two local headers, two central headers and the end record
+        let eocd = &zip[zip.len() - 22..];
+        assert_eq!(&eocd[..4], b"PK\x05\x06");
+        assert_eq!(u16::from_le_bytes([eocd[10], eocd[11]]), 2);
+        assert_eq!(&zip[30..35], b"a.txt");
+        assert_eq!(&zip[35..38], b"aaa");
+        assert_eq!(
+            u32::from_le_bytes([zip[14], zip[15], zip[16], zip[17]]),
+            crc32fast::hash(b"aaa")
+        );
+        assert!(!zip.windows(9).any(|window| window == b"other.txt"));
+    }
+
+    async fn seeded_store() -> Store {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        store
+            .write(&PathBuf::from("seed").join("a.txt"), "aaa")
+            .await
+            .unwrap();
+        store
+            .write(&PathBuf::from("seed").join("b".repeat(120)), "b")
+            .await
+            .unwrap();
+        store
+    }
+
+    #[tokio::test]
+    async fn can_export_and_import_tar() {
+        let store = seeded_store().await;
+
+        let mut archive = Vec::new();
+        assert_eq!(
+            store
+                .export_tar(Path::new("seed"), &mut archive)
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(archive.len() % TAR_BLOCK, 0);
+
+        assert_eq!(
+            store
+                .import_tar(&mut archive.as_slice(), Path::new("restored"))
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            store
+                .read::<String>(&PathBuf::from("restored").join("a.txt"))
+                .await
+                .unwrap(),
+            "aaa"
+        );
+        assert!(store
+            .file_exists(&PathBuf::from("restored").join("b".repeat(120)))
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn can_reject_escaping_tar_entries() {
+        let mut archive = Vec::new();
+        write_tar_entry(&mut archive, "../escape.txt", b"content", SystemTime::now()).unwrap();
+        archive.extend_from_slice(&[0; TAR_BLOCK * 2]);
+
+        let store = StoreConfig::InMem().build().await.unwrap();
+        assert!(store
+            .import_tar(&mut archive.as_slice(), Path::new("restored"))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn can_reject_truncated_tar_entries() {
+        let mut archive = Vec::new();
+        write_tar_entry(&mut archive, "big.bin", b"content", SystemTime::now()).unwrap();
+        // claims an entry of almost 8 GiB backed by 512 bytes
+        archive[124..136].copy_from_slice(b"77777777777\0");
+
+        let store = StoreConfig::InMem().build().await.unwrap();
+        assert!(matches!(
+            store
+                .import_tar(&mut archive.as_slice(), Path::new("restored"))
+                .await,
+            Err(DriverError::DecodeError)
+        ));
+        assert!(!store
+            .file_exists(&PathBuf::from("restored").join("big.bin"))
+            .await
+            .unwrap());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[tokio::test]
+    async fn can_export_and_import_tar_zstd() {
+        let store = seeded_store().await;
+        // spans many reads of the decompressed stream
+        let large: Vec<u8> = (0_u32..100_000)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+            .collect();
+        store
+            .write(&PathBuf::from("seed").join("large.bin"), large.clone())
+            .await
+            .unwrap();
+
+        let mut archive = Vec::new();
+        store
+            .export_tar_zstd(Path::new("seed"), &mut archive, 3)
+            .await
+            .unwrap();
+        assert!(archive.starts_with(&ZSTD_MAGIC));
+
+        assert_eq!(
+            store
+                .import_tar(&mut archive.as_slice(), Path::new("restored"))
+                .await
+                .unwrap(),
+            3
+        );
+        assert_eq!(
+            store
+                .read::<Vec<u8>>(&PathBuf::from("restored").join("large.bin"))
+                .await
+                .unwrap(),
+            large
+        );
+    }
+}
@@ -0,0 +1,384 @@
+//! # Backup Module
+//!
+//! The `backup` module defines [`Backup`], which takes incremental backups of
+//! the files under a prefix into a backup repository, any other store, and
+//! restores them as of a chosen snapshot.
+//!
+//! Each snapshot is a JSON manifest listing every file with its size,
+//! modification time and the location of its content in the repository. Only
+//! the files created or changed since the previous snapshot are copied: the
+//! entries of unchanged files point to the content copied by an earlier
+//! snapshot. The repository layout is:
+//!
+//! - `snapshots/<id>.json`: the manifest of each snapshot;
+//! - `data/<id>/<path>`: the files copied by each snapshot.
+//!
+//! Snapshot ids sort in the order the snapshots were taken.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::Path;
+//! use active_storage::{backup::Backup, StoreConfig};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!     let repository = StoreConfig::InMem().build().await.unwrap();
+//!     let backup = Backup::new(store.clone(), repository).with_prefix("docs");
+//!
+//!     store.write(Path::new("docs/a.txt"), "v1").await.unwrap();
+//!     let first = backup.snapshot().await.unwrap();
+//!     store.write(Path::new("docs/a.txt"), "v2").await.unwrap();
+//!     backup.snapshot().await.unwrap();
+//!
+//!     let restored = StoreConfig::InMem().build().await.unwrap();
+//!     backup.restore(&first.id, &restored).await.unwrap();
+//!     assert_eq!(
+//!         restored.read::<String>(Path::new("docs/a.txt")).await.unwrap(),
+//!         "v1"
+//!     );
+//! }
+//! ```
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    clock, drivers,
+    errors::{DriverError, DriverResult},
+    key::ObjectPath,
+    store::Store,
+    transfer::TransferOptions,
+};
+
+/// The folder of the snapshot manifests in the repository.
+const SNAPSHOTS_FOLDER: &str = "snapshots";
+
+/// The folder of the copied files in the repository.
+const DATA_FOLDER: &str = "data";
+
+/// The extension of the snapshot manifests.
+const MANIFEST_EXTENSION: &str = "json";
+
+/// Struct representing a file in a snapshot.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotEntry {
+    /// The size of the file in bytes.
+    pub size: u64,
+    /// The last modification time of the file, to the millisecond.
+    pub last_modified: SystemTime,
+    /// The path of the file content in the repository.
+    pub data: ObjectPath,
+}
+
+/// Struct representing a snapshot of the files under the backed up prefix.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    /// The id of the snapshot.
+    pub id: String,
+    /// The time the snapshot was taken, to the millisecond.
+    pub taken_at: SystemTime,
+    /// The files of the snapshot, keyed by path.
+    pub entries: BTreeMap<ObjectPath, SnapshotEntry>,
+    /// The number of files copied by the snapshot.
+    pub copied: usize,
+}
+
+impl Snapshot {
+    fn to_manifest(&self) -> serde_json::Value {
+        let entries = self
+            .entries
+            .iter()
+            .map(|(path, entry)| {
+                serde_json::json!({
+                    "path": path.as_str(),
+                    "size": entry.size,
+                    "last_modified": millis(entry.last_modified),
+                    "data": entry.data.as_str(),
+                })
+            })
+            .collect::<Vec<_>>();
+        serde_json::json!({
+            "id": self.id,
+            "taken_at": millis(self.taken_at),
+            "copied": self.copied,
+            "entries": entries,
+        })
+    }
+
+    /// Parses a stored manifest, returning `None` when it is malformed.
+    fn from_manifest(manifest: &serde_json::Value) -> Option<Self> {
+        let entries = manifest["entries"]
+            .as_array()?
+            .iter()
+            .map(|entry| {
+                Some((
+                    ObjectPath::new(entry["path"].as_str()?),
+                    SnapshotEntry {
+                        size: entry["size"].as_u64()?,
+                        last_modified: from_millis(entry["last_modified"].as_u64()?),
+                        data: ObjectPath::new(entry["data"].as_str()?),
+                    },
+                ))
+            })
+            .collect::<Option<_>>()?;
+        Some(Self {
+            id: manifest["id"].as_str()?.to_string(),
+            taken_at: from_millis(manifest["taken_at"].as_u64()?),
+            entries,
+            copied: usize::try_from(manifest["copied"].as_u64()?).ok()?,
+        })
+    }
+}
+
+/// Struct taking incremental backups of a store into a backup repository.
+#[derive(Clone)]
+pub struct Backup {
+    store: Store,
+    repository: Store,
+    prefix: ObjectPath,
+    transfer: TransferOptions,
+}
+
+impl Backup {
+    /// Creates a backup of every file of the store into the given repository.
+    #[must_use]
+    pub fn new(store: Store, repository: Store) -> Self {
+        Self {
+            store,
+            repository,
+            prefix: ObjectPath::default(),
+            transfer: TransferOptions::default(),
+        }
+    }
+
+    /// Backs up only the files under the given prefix.
+    #[must_use]
+    pub fn with_prefix(mut self, prefix: impl Into<ObjectPath>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Sets the options of the file transfers, e.g. a bandwidth limit.
+    #[must_use]
+    pub fn with_transfer_options(mut self, options: TransferOptions) -> Self {
+        self.transfer = options;
+        self
+    }
+
+    /// Takes a snapshot of the files under the prefix, copying the files
+    /// created or changed since the latest snapshot to the repository.
+    ///
+    /// The manifest is written last, so an interrupted snapshot leaves no
+    /// manifest and its copied files are copied again by the next one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the files could not be listed or copied, or the
+    /// manifest could not be written.
+    pub async fn snapshot(&self) -> DriverResult<Snapshot> {
+        let previous = match self.snapshots().await?.last() {
+            Some(id) => self.load(id).await?.entries,
+            None => BTreeMap::new(),
+        };
+
+        let taken_at = from_millis(millis(clock::now()));
+        let id = format!("{:016x}-{}", millis(taken_at), drivers::unique_id());
+        let mut snapshot = Snapshot {
+            id,
+            taken_at,
+            entries: BTreeMap::new(),
+            copied: 0,
+        };
+
+        for file in self.store.list(&self.prefix).await? {
+            // files deleted since the listing are left out
+            let Some(metadata) = self.store.stat(&file).await? else {
+                continue;
+            };
+            let last_modified = from_millis(millis(metadata.last_modified));
+            let entry = match previous.get(&file) {
+                Some(entry)
+                    if entry.size == metadata.size && entry.last_modified == last_modified =>
+                {
+                    entry.clone()
+                }
+                _ => {
+                    let data = ObjectPath::new(DATA_FOLDER).join(&snapshot.id).join(&file);
+                    let chunks = self.store.read_stream(&file, &self.transfer).await?;
+                    self.repository
+                        .write_stream(&data, chunks, &self.transfer)
+                        .await?;
+                    snapshot.copied += 1;
+                    SnapshotEntry {
+                        size: metadata.size,
+                        last_modified,
+                        data,
+                    }
+                }
+            };
+            snapshot.entries.insert(file, entry);
+        }
+
+        self.repository
+            .write(
+                manifest_path(&snapshot.id),
+                snapshot.to_manifest().to_string(),
+            )
+            .await?;
+        Ok(snapshot)
+    }
+
+    /// Lists the ids of the snapshots, oldest first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the manifests could not be listed.
+    pub async fn snapshots(&self) -> DriverResult<Vec<String>> {
+        let mut ids = self
+            .repository
+            .list(Path::new(SNAPSHOTS_FOLDER))
+            .await?
+            .into_iter()
+            .filter_map(|path| {
+                let id = path
+                    .file_name()?
+                    .strip_suffix(MANIFEST_EXTENSION)?
+                    .strip_suffix('.')?;
+                Some(id.to_string())
+            })
+            .collect::<Vec<_>>();
+        ids.sort();
+        Ok(ids)
+    }
+
+    /// Reads the manifest of the snapshot with the given id.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::ResourceNotFound`] if the snapshot doesn't
+    /// exist, and [`DriverError::DecodeError`] if its manifest is malformed.
+    pub async fn load(&self, id: &str) -> DriverResult<Snapshot> {
+        let manifest = self.repository.read::<String>(manifest_path(id)).await?;
+        serde_json::from_str(&manifest)
+            .ok()
+            .as_ref()
+            .and_then(Snapshot::from_manifest)
+            .ok_or(DriverError::DecodeError)
+    }
+
+    /// Restores the files of the snapshot with the given id into the target
+    /// store, under their original paths. Returns the number of restored
+    /// files.
+    ///
+    /// Files created after the snapshot are not deleted from the target, so
+    /// restore into an empty store, or a [`Store::scoped`] view of one, for
+    /// an exact point-in-time copy.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the snapshot could not be loaded, or a file could
+    /// not be copied.
+    pub async fn restore(&self, id: &str, target: &Store) -> DriverResult<usize> {
+        let snapshot = self.load(id).await?;
+        for (file, entry) in &snapshot.entries {
+            let chunks = self
+                .repository
+                .read_stream(&entry.data, &self.transfer)
+                .await?;
+            target.write_stream(file, chunks, &self.transfer).await?;
+        }
+        Ok(snapshot.entries.len())
+    }
+}
+
+/// Returns the path of the manifest of the snapshot with the given id.
+fn manifest_path(id: &str) -> PathBuf {
+    Path::new(SNAPSHOTS_FOLDER).join(format!("{id}.{MANIFEST_EXTENSION}"))
+}
+
+/// Returns the milliseconds since the Unix epoch of the given time.
+fn millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |duration| {
+        u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+    })
+}
+
+/// Returns the time of the given milliseconds since the Unix epoch.
+fn from_millis(millis: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(millis)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::StoreConfig;
+
+    #[tokio::test]
+    async fn can_take_incremental_snapshots() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let repository = StoreConfig::InMem().build().await.unwrap();
+        let backup = Backup::new(store.clone(), repository.clone()).with_prefix("docs");
+        store.write(Path::new("docs/a.txt"), "a").await.unwrap();
+        store.write(Path::new("docs/b.txt"), "b").await.unwrap();
+        store.write(Path::new("other.txt"), "other").await.unwrap();
+
+        let first = backup.snapshot().await.unwrap();
+        assert_eq!(first.copied, 2);
+        assert_eq!(backup.load(&first.id).await.unwrap(), first);
+
+        store.write(Path::new("docs/b.txt"), "bb").await.unwrap();
+        store.delete(Path::new("docs/a.txt")).await.unwrap();
+        store.write(Path::new("docs/c.txt"), "c").await.unwrap();
+        let second = backup.snapshot().await.unwrap();
+        assert_eq!(second.copied, 2);
+        assert_eq!(
+            second.entries.keys().collect::<Vec<_>>(),
+            vec![
+                &ObjectPath::new("docs/b.txt"),
+                &ObjectPath::new("docs/c.txt")
+            ]
+        );
+
+        let third = backup.snapshot().await.unwrap();
+        assert_eq!(third.copied, 0);
+        assert_eq!(third.entries, second.entries);
+        assert_eq!(
+            backup.snapshots().await.unwrap(),
+            vec![first.id.clone(), second.id.clone(), third.id.clone()]
+        );
+
+        let restored = StoreConfig::InMem().build().await.unwrap();
+        assert_eq!(backup.restore(&first.id, &restored).await.unwrap(), 2);
+        assert_eq!(
+            restored
+                .read::<String>(Path::new("docs/b.txt"))
+                .await
+                .unwrap(),
+            "b"
+        );
+
+        let restored = StoreConfig::InMem().build().await.unwrap();
+        assert_eq!(backup.restore(&third.id, &restored).await.unwrap(), 2);
+        assert_eq!(
+            restored.list(Path::new("")).await.unwrap(),
+            vec![ObjectPath::new("docs/b.txt"), ObjectPath::new("docs/c.txt")]
+        );
+        assert_eq!(
+            restored
+                .read::<String>(Path::new("docs/b.txt"))
+                .await
+                .unwrap(),
+            "bb"
+        );
+
+        assert!(matches!(
+            backup.restore("missing", &restored).await,
+            Err(DriverError::ResourceNotFound)
+        ));
+    }
+}
@@ -0,0 +1,662 @@
+//! # Blobs Module
+//!
+//! The `blobs` module defines [`Blobs`], the server side of the direct upload
+//! handshake, where the browser uploads the file bytes straight to the
+//! storage backend.
+//!
+//! [`Blobs::create_before_direct_upload`] answers the first request of the
+//! handshake with the blob record, a signed id and a presigned upload URL with
+//! its headers. [`DirectUpload::to_json`] renders the response expected by the
+//! Rails `@rails/activestorage` direct upload JavaScript, so the same frontend
+//! libraries can be used against a Rust backend. When the form is submitted,
+//! [`Blobs::find_signed`] resolves the signed id back to the blob path.
+//!
+//! Once the client reports the upload done, [`Blobs::finalize`] checks the
+//! uploaded object against the size and checksum the client declared and
+//! marks the blob as uploaded. The blob records are kept as JSON files in the
+//! store, under [`BLOB_RECORDS_PREFIX`].
+//!
+//! [`Blobs::reconcile`] compares the blob records with the stored objects,
+//! e.g. from a periodic job, and [`Blobs::repair`] applies the safe fixes of
+//! its findings.
+//!
+//! The store driver must support presigned URLs, see
+//! [`crate::drivers::capabilities::PresignExt`].
+//!
+//! ## Example
+//!
+//! ```rust
+//! use active_storage::{blobs::Blobs, signing::BlobSigner, store::Store};
+//!
+//! /// Answers `POST /rails/active_storage/direct_uploads`.
+//! async fn direct_upload(store: Store, filename: &str, byte_size: u64) -> serde_json::Value {
+//!     let blobs = Blobs::new(store, BlobSigner::new("my secret"));
+//!     blobs
+//!         .create_before_direct_upload(filename, byte_size, "mKVqlSGgS+VtvL4ErrY9aw==", "image/png")
+//!         .await
+//!         .unwrap()
+//!         .to_json()
+//! }
+//! ```
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::Duration,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use md5::{Digest, Md5};
+
+use crate::{
+    clock, drivers,
+    errors::{DriverError, DriverResult},
+    filename::{self, SanitizeOptions},
+    hex,
+    key::ObjectPath,
+    signing::BlobSigner,
+    store::Store,
+};
+
+/// The prefix of the blob records, which are stored next to the blobs.
+pub const BLOB_RECORDS_PREFIX: &str = ".active-storage-blobs";
+
+/// Struct representing a blob created before its bytes are uploaded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Blob {
+    /// The storage key the blob is uploaded to.
+    pub key: String,
+    /// The sanitized file name of the blob.
+    pub filename: String,
+    /// The content type declared by the client.
+    pub content_type: String,
+    /// The size of the blob in bytes declared by the client.
+    pub byte_size: u64,
+    /// The base64 encoded MD5 checksum declared by the client, checked by the
+    /// backend on upload.
+    pub checksum: String,
+    /// Whether the upload was verified by [`Blobs::finalize`].
+    pub uploaded: bool,
+}
+
+/// Returns the path of the record of the blob at the given path.
+fn record_path(path: impl Into<ObjectPath>) -> ObjectPath {
+    ObjectPath::new(BLOB_RECORDS_PREFIX).join(path)
+}
+
+impl Blob {
+    /// Returns the stored record of the blob.
+    fn to_record(&self) -> serde_json::Value {
+        serde_json::json!({
+            "key": self.key,
+            "filename": self.filename,
+            "content_type": self.content_type,
+            "byte_size": self.byte_size,
+            "checksum": self.checksum,
+            "uploaded": self.uploaded,
+        })
+    }
+
+    /// Parses a stored record, returning `None` when it is malformed.
+    fn from_record(record: &serde_json::Value) -> Option<Self> {
+        Some(Self {
+            key: record["key"].as_str()?.to_string(),
+            filename: record["filename"].as_str()?.to_string(),
+            content_type: record["content_type"].as_str()?.to_string(),
+            byte_size: record["byte_size"].as_u64()?,
+            checksum: record["checksum"].as_str()?.to_string(),
+            uploaded: record["uploaded"].as_bool()?,
+        })
+    }
+
+    /// Returns whether the given content and etag match the declared
+    /// checksum. The etag is trusted only when it is the MD5 of the declared
+    /// checksum, otherwise the content is read and hashed.
+    async fn matches_checksum(
+        &self,
+        store: &Store,
+        path: &ObjectPath,
+        etag: Option<&str>,
+    ) -> DriverResult<bool> {
+        let Ok(declared) = STANDARD.decode(&self.checksum) else {
+            return Ok(false);
+        };
+        let declared_hex = hex::encode(&declared);
+        if etag.is_some_and(|etag| etag.trim_matches('"').eq_ignore_ascii_case(&declared_hex)) {
+            return Ok(true);
+        }
+
+        let content = store.read_bytes(path).await?;
+        Ok(Md5::digest(&content).as_slice() == declared.as_slice())
+    }
+}
+
+/// Struct representing the answer to a direct upload request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirectUpload {
+    /// The created blob.
+    pub blob: Blob,
+    /// The signed id the client submits with its form to reference the blob.
+    pub signed_id: String,
+    /// The presigned URL the client uploads the bytes to with a `PUT`.
+    pub url: String,
+    /// The headers the client must send with the upload.
+    pub headers: BTreeMap<String, String>,
+}
+
+impl DirectUpload {
+    /// Returns the JSON response expected by the Rails direct upload
+    /// JavaScript.
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "key": self.blob.key,
+            "filename": self.blob.filename,
+            "content_type": self.blob.content_type,
+            "metadata": {},
+            "byte_size": self.blob.byte_size,
+            "checksum": self.blob.checksum,
+            "signed_id": self.signed_id,
+            "direct_upload": {
+                "url": self.url,
+                "headers": self.headers,
+            },
+        })
+    }
+}
+
+/// Struct representing the findings of [`Blobs::reconcile`], as blob paths
+/// in key order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Reconciliation {
+    /// Uploaded blobs whose object is missing.
+    pub missing_objects: Vec<ObjectPath>,
+    /// Objects without a blob record.
+    pub orphaned_objects: Vec<ObjectPath>,
+    /// Blobs never uploaded, whose signed id has expired.
+    pub abandoned_uploads: Vec<ObjectPath>,
+    /// Uploaded blobs whose object size differs from the recorded one.
+    pub size_mismatches: Vec<ObjectPath>,
+}
+
+impl Reconciliation {
+    /// Returns true when the records and the objects match.
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.missing_objects.is_empty()
+            && self.orphaned_objects.is_empty()
+            && self.abandoned_uploads.is_empty()
+            && self.size_mismatches.is_empty()
+    }
+}
+
+/// Struct creating the blobs of direct uploads.
+#[derive(Clone)]
+pub struct Blobs {
+    store: Store,
+    signer: BlobSigner,
+    prefix: ObjectPath,
+    url_expires_in: Duration,
+    signed_id_expires_in: Duration,
+}
+
+impl Blobs {
+    /// Creates a new [`Blobs`] uploading to the given store and signing the
+    /// blob ids with the given signer.
+    ///
+    /// Upload URLs expire after 5 minutes and signed ids after a day.
+    #[must_use]
+    pub fn new(store: Store, signer: BlobSigner) -> Self {
+        Self {
+            store,
+            signer,
+            prefix: ObjectPath::default(),
+            url_expires_in: Duration::from_secs(5 * 60),
+            signed_id_expires_in: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+
+    /// Creates the blobs under the given prefix instead of the storage root.
+    #[must_use]
+    pub fn with_prefix(mut self, prefix: impl Into<ObjectPath>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Sets how long the upload URLs are valid.
+    #[must_use]
+    pub const fn with_url_expires_in(mut self, expires_in: Duration) -> Self {
+        self.url_expires_in = expires_in;
+        self
+    }
+
+    /// Sets how long the signed ids are valid, which bounds the time between
+    /// the upload and the form submission.
+    #[must_use]
+    pub const fn with_signed_id_expires_in(mut self, expires_in: Duration) -> Self {
+        self.signed_id_expires_in = expires_in;
+        self
+    }
+
+    /// Creates a blob for the given file and returns it with its signed id
+    /// and the presigned URL and headers the client uploads the bytes with.
+    ///
+    /// Each blob gets a new unique key, under which the sanitized file name
+    /// is kept. The blob record is stored until the upload is finalized.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::Unsupported`] when the store driver can't sign
+    /// URLs, or an error if the URL could not be signed or the record could
+    /// not be stored.
+    pub async fn create_before_direct_upload(
+        &self,
+        filename: &str,
+        byte_size: u64,
+        checksum: &str,
+        content_type: &str,
+    ) -> DriverResult<DirectUpload> {
+        let presign = self
+            .store
+            .as_presign()
+            .ok_or(DriverError::Unsupported("presigned URLs"))?;
+
+        let filename = filename::sanitize(filename, &SanitizeOptions::default());
+        let path = self
+            .prefix
+            .join(drivers::unique_id())
+            .join(filename.as_str());
+        let key = path.to_key()?;
+
+        let url = presign.presign_write(&path, self.url_expires_in).await?;
+        let signed_id = self.signer.sign(&path, self.signed_id_expires_in)?;
+        let headers = BTreeMap::from([
+            ("Content-Type".to_string(), content_type.to_string()),
+            ("Content-MD5".to_string(), checksum.to_string()),
+        ]);
+
+        let blob = Blob {
+            key: key.to_string(),
+            filename,
+            content_type: content_type.to_string(),
+            byte_size,
+            checksum: checksum.to_string(),
+            uploaded: false,
+        };
+        self.store
+            .records()
+            .write(record_path(&path), blob.to_record().to_string())
+            .await?;
+
+        Ok(DirectUpload {
+            blob,
+            signed_id,
+            url,
+            headers,
+        })
+    }
+
+    /// Verifies the signed id submitted by the client, returning the path of
+    /// the blob it references. The blob may not have been uploaded yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::InvalidToken`] if the signed id is malformed or
+    /// forged, and [`DriverError::TokenExpired`] if it is past its expiry.
+    pub fn find_signed(&self, signed_id: &str) -> DriverResult<ObjectPath> {
+        self.signer.verify(signed_id)
+    }
+
+    /// Verifies the upload of the blob referenced by the signed id against
+    /// the size and checksum the client declared, and marks the blob as
+    /// uploaded. An object that doesn't match is deleted, so the client can
+    /// upload it again.
+    ///
+    /// Finalizing an uploaded blob returns it unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::InvalidToken`] or [`DriverError::TokenExpired`]
+    /// if the signed id is not valid, [`DriverError::ResourceNotFound`] if
+    /// the blob or its object doesn't exist, and
+    /// [`DriverError::IntegrityCheckFailed`] if the object doesn't match.
+    pub async fn finalize(&self, signed_id: &str) -> DriverResult<Blob> {
+        let path = self.find_signed(signed_id)?;
+        let record_path = record_path(&path);
+        let record = self.store.records().read::<String>(&record_path).await?;
+        let mut blob = serde_json::from_str(&record)
+            .ok()
+            .as_ref()
+            .and_then(Blob::from_record)
+            .ok_or(DriverError::ResourceNotFound)?;
+        if blob.uploaded {
+            return Ok(blob);
+        }
+
+        let metadata = self
+            .store
+            .stat(&path)
+            .await?
+            .ok_or(DriverError::ResourceNotFound)?;
+        if metadata.size != blob.byte_size
+            || !blob
+                .matches_checksum(&self.store, &path, metadata.etag.as_deref())
+                .await?
+        {
+            self.store.delete(&path).await?;
+            return Err(DriverError::IntegrityCheckFailed);
+        }
+
+        blob.uploaded = true;
+        self.store
+            .records()
+            .write(&record_path, blob.to_record().to_string())
+            .await?;
+        Ok(blob)
+    }
+
+    /// Compares the blob records with the objects under the blobs prefix.
+    ///
+    /// Objects and pending blobs younger than the signed id expiry are
+    /// skipped, as their direct upload may still be in progress. Other files
+    /// under the prefix are reported as orphaned objects, so set a dedicated
+    /// prefix with [`Self::with_prefix`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the records or the objects could not be listed or
+    /// looked up.
+    pub async fn reconcile(&self) -> DriverResult<Reconciliation> {
+        let settled = clock::now()
+            .checked_sub(self.signed_id_expires_in)
+            .unwrap_or(std::time::UNIX_EPOCH);
+        let mut reconciliation = Reconciliation::default();
+
+        let mut records = BTreeSet::new();
+        for record_file in self.store.records().list(record_path(&self.prefix)).await? {
+            let Some(path) = record_file.strip_prefix(BLOB_RECORDS_PREFIX) else {
+                continue;
+            };
+            let record = self.store.records().read::<String>(&record_file).await?;
+            let Some(blob) = serde_json::from_str(&record)
+                .ok()
+                .as_ref()
+                .and_then(Blob::from_record)
+            else {
+                continue;
+            };
+
+            match self.store.stat(&path).await? {
+                Some(metadata) if blob.uploaded && metadata.size != blob.byte_size => {
+                    reconciliation.size_mismatches.push(path.clone());
+                }
+                Some(_) => {}
+                None if blob.uploaded => reconciliation.missing_objects.push(path.clone()),
+                None => {
+                    if self.store.records().last_modified(&record_file).await? <= settled {
+                        reconciliation.abandoned_uploads.push(path.clone());
+                    }
+                }
+            }
+            records.insert(path);
+        }
+
+        for object in self.store.list(&self.prefix).await? {
+            if records.contains(&object) {
+                continue;
+            }
+            if self.store.last_modified(&object).await? <= settled {
+                reconciliation.orphaned_objects.push(object);
+            }
+        }
+
+        Ok(reconciliation)
+    }
+
+    /// Applies the safe fixes of the given findings: deletes the records of
+    /// missing objects and abandoned uploads, and the orphaned objects. Size
+    /// mismatches are left for review. Returns the number of deleted files.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file could not be deleted.
+    pub async fn repair(&self, reconciliation: &Reconciliation) -> DriverResult<usize> {
+        let mut deleted = 0;
+        for path in reconciliation
+            .missing_objects
+            .iter()
+            .chain(&reconciliation.abandoned_uploads)
+        {
+            if self
+                .store
+                .records()
+                .delete_if_exists(record_path(path))
+                .await?
+            {
+                deleted += 1;
+            }
+        }
+        for path in &reconciliation.orphaned_objects {
+            if self.store.delete_if_exists(path).await? {
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::time::SystemTime;
+
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::{
+        drivers::{capabilities::PresignExt, Driver},
+        StoreConfig,
+    };
+
+    /// A driver over an in-memory store, records included, signing fake upload
+    /// URLs.
+    #[derive(Clone)]
+    struct PresigningDriver {
+        inner: Store,
+    }
+
+    #[async_trait::async_trait]
+    impl Driver for PresigningDriver {
+        async fn read(&self, path: &ObjectPath) -> DriverResult<Bytes> {
+            self.inner.read_bytes(path).await
+        }
+
+        async fn file_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+            self.inner.file_exists(path).await
+        }
+
+        async fn write(&self, path: &ObjectPath, content: Bytes) -> DriverResult<()> {
+            self.inner.write(path, content).await
+        }
+
+        async fn delete(&self, path: &ObjectPath) -> DriverResult<()> {
+            self.inner.delete(path).await
+        }
+
+        async fn delete_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+            self.inner.delete_directory(path).await
+        }
+
+        async fn last_modified(&self, path: &ObjectPath) -> DriverResult<SystemTime> {
+            self.inner.last_modified(path).await
+        }
+
+        async fn list(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+            self.inner.list(path).await
+        }
+
+        fn as_presign(&self) -> Option<&dyn PresignExt> {
+            Some(self)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl PresignExt for PresigningDriver {
+        async fn presign_read(&self, path: &ObjectPath, _: Duration) -> DriverResult<String> {
+            Ok(format!("https://storage.test/{path}"))
+        }
+
+        async fn presign_write(&self, path: &ObjectPath, _: Duration) -> DriverResult<String> {
+            Ok(format!("https://storage.test/{path}?upload"))
+        }
+    }
+
+    #[tokio::test]
+    async fn can_create_before_direct_upload() {
+        let store = StoreConfig::with_driver(Box::new(PresigningDriver {
+            inner: StoreConfig::InMem().build().await.unwrap().records(),
+        }));
+        let blobs = Blobs::new(store, BlobSigner::new("secret")).with_prefix("uploads");
+
+        let direct_upload = blobs
+            .create_before_direct_upload("../My Avatar.png", 1024, "checksum==", "image/png")
+            .await
+            .unwrap();
+        let key = &direct_upload.blob.key;
+        assert!(key.starts_with("uploads/"));
+        assert!(key.ends_with("/My Avatar.png"));
+        assert_eq!(
+            blobs.find_signed(&direct_upload.signed_id).unwrap(),
+            ObjectPath::new(key)
+        );
+
+        let json = direct_upload.to_json();
+        assert_eq!(json["byte_size"], 1024);
+        assert_eq!(json["signed_id"], direct_upload.signed_id.as_str());
+        assert_eq!(
+            json["direct_upload"]["url"],
+            format!("https://storage.test/{key}?upload")
+        );
+        assert_eq!(
+            json["direct_upload"]["headers"]["Content-MD5"],
+            "checksum=="
+        );
+    }
+
+    #[tokio::test]
+    async fn can_finalize_direct_uploads() {
+        let store = StoreConfig::with_driver(Box::new(PresigningDriver {
+            inner: StoreConfig::InMem().build().await.unwrap().records(),
+        }));
+        let blobs = Blobs::new(store.clone(), BlobSigner::new("secret"));
+        // the MD5 of `hello`
+        let checksum = "XUFAKrxLKna5cZ2REBfFkg==";
+
+        let direct_upload = blobs
+            .create_before_direct_upload("hello.txt", 5, checksum, "text/plain")
+            .await
+            .unwrap();
+        let signed_id = &direct_upload.signed_id;
+        assert!(matches!(
+            blobs.finalize(signed_id).await,
+            Err(DriverError::ResourceNotFound)
+        ));
+
+        assert!(store
+            .write(direct_upload.blob.key.as_str(), "hello")
+            .await
+            .is_ok());
+        let blob = blobs.finalize(signed_id).await.unwrap();
+        assert!(blob.uploaded);
+        assert_eq!(
+            blob,
+            Blob {
+                uploaded: true,
+                ..direct_upload.blob
+            }
+        );
+        assert_eq!(blobs.finalize(signed_id).await.unwrap(), blob);
+
+        for (content, byte_size) in [("hello", 6), ("world", 5)] {
+            let direct_upload = blobs
+                .create_before_direct_upload("hello.txt", byte_size, checksum, "text/plain")
+                .await
+                .unwrap();
+            let key = direct_upload.blob.key.as_str();
+            assert!(store.write(key, content).await.is_ok());
+
+            assert!(matches!(
+                blobs.finalize(&direct_upload.signed_id).await,
+                Err(DriverError::IntegrityCheckFailed)
+            ));
+            assert!(!store.file_exists(key).await.unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn can_reconcile_records_and_objects() {
+        let store = StoreConfig::with_driver(Box::new(PresigningDriver {
+            inner: StoreConfig::InMem().build().await.unwrap().records(),
+        }));
+        let blobs = Blobs::new(store.clone(), BlobSigner::new("secret")).with_prefix("uploads");
+        // every pending upload has expired for the reconciliation
+        let auditor = blobs.clone().with_signed_id_expires_in(Duration::ZERO);
+        // the MD5 of `hello`
+        let checksum = "XUFAKrxLKna5cZ2REBfFkg==";
+
+        let mut keys = Vec::new();
+        for _ in 0..3 {
+            let direct_upload = blobs
+                .create_before_direct_upload("hello.txt", 5, checksum, "text/plain")
+                .await
+                .unwrap();
+            let key = ObjectPath::new(&direct_upload.blob.key);
+            store.write(&key, "hello").await.unwrap();
+            blobs.finalize(&direct_upload.signed_id).await.unwrap();
+            keys.push(key);
+        }
+        let abandoned = blobs
+            .create_before_direct_upload("later.txt", 5, checksum, "text/plain")
+            .await
+            .unwrap();
+        let abandoned = ObjectPath::new(&abandoned.blob.key);
+        let orphan = ObjectPath::new("uploads/orphan.txt");
+        store.write(&orphan, "orphan").await.unwrap();
+        store.delete(&keys[0]).await.unwrap();
+        store.write(&keys[1], "hello world").await.unwrap();
+
+        let reconciliation = auditor.reconcile().await.unwrap();
+        assert_eq!(
+            reconciliation,
+            Reconciliation {
+                missing_objects: vec![keys[0].clone()],
+                orphaned_objects: vec![orphan.clone()],
+                abandoned_uploads: vec![abandoned],
+                size_mismatches: vec![keys[1].clone()],
+            }
+        );
+
+        assert_eq!(auditor.repair(&reconciliation).await.unwrap(), 3);
+        assert!(!store.file_exists(&orphan).await.unwrap());
+        let reconciliation = auditor.reconcile().await.unwrap();
+        assert!(!reconciliation.is_clean());
+        assert_eq!(
+            reconciliation,
+            Reconciliation {
+                size_mismatches: vec![keys[1].clone()],
+                ..Reconciliation::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn can_reject_stores_without_presign() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let blobs = Blobs::new(store, BlobSigner::new("secret"));
+
+        assert!(matches!(
+            blobs
+                .create_before_direct_upload("file.txt", 1, "checksum==", "text/plain")
+                .await,
+            Err(DriverError::Unsupported(_))
+        ));
+    }
+}
@@ -0,0 +1,295 @@
+//! # Blocking Module
+//!
+//! The `blocking` module defines [`BlockingStore`], a synchronous facade over
+//! [`Store`] for applications without an async runtime, such as CLIs, build
+//! scripts and tests.
+//!
+//! Every call is run to completion on a runtime owned by the store, so the
+//! methods must not be called from within an async context.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::PathBuf;
+//! use active_storage::{blocking::BlockingStore, StoreConfig};
+//!
+//! let store = BlockingStore::from_config(StoreConfig::InMem()).unwrap();
+//! let file_path = PathBuf::from("test.txt");
+//! store.write(file_path.as_path(), "my content").unwrap();
+//!
+//! let content: String = store.read(file_path.as_path()).unwrap();
+//! assert_eq!(content, "my content");
+//! ```
+use std::{future::Future, ops::Range, sync::Arc, time::SystemTime};
+
+use bytes::Bytes;
+use tokio::runtime::{Builder, Runtime};
+
+use crate::{
+    contents::{Contents, IntoBytes},
+    drivers::{ObjectMetadata, ReadOptions, WriteOptions},
+    errors::{DriverError, DriverResult},
+    key::ObjectPath,
+    store::Store,
+    StoreConfig,
+};
+
+/// Struct representing a [`Store`] with blocking methods. Clones share the
+/// same runtime.
+#[derive(Clone)]
+pub struct BlockingStore {
+    store: Store,
+    runtime: Arc<Runtime>,
+}
+
+impl BlockingStore {
+    /// Creates a new [`BlockingStore`] wrapping the given store.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the runtime could not be created.
+    pub fn new(store: Store) -> DriverResult<Self> {
+        Ok(Self {
+            store,
+            runtime: Arc::new(runtime()?),
+        })
+    }
+
+    /// Builds the store of the given configuration and wraps it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the runtime could not be created or the driver
+    /// could not be initialized.
+    pub fn from_config(config: StoreConfig) -> DriverResult<Self> {
+        let runtime = runtime()?;
+        let store = runtime.block_on(config.build())?;
+
+        Ok(Self {
+            store,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// Returns the wrapped async store.
+    #[must_use]
+    pub const fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// Runs the given future to completion, e.g. to call an async [`Store`]
+    /// method without a blocking counterpart.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    /// Checks if a file exists at the specified path.
+    ///
+    /// # Errors
+    ///
+    /// See [`Store::file_exists`].
+    pub fn file_exists(&self, path: impl Into<ObjectPath>) -> DriverResult<bool> {
+        self.block_on(self.store.file_exists(path))
+    }
+
+    /// Writes the contents to a file at the specified path.
+    ///
+    /// # Errors
+    ///
+    /// See [`Store::write`].
+    pub fn write<C: IntoBytes + Send>(
+        &self,
+        path: impl Into<ObjectPath>,
+        content: C,
+    ) -> DriverResult<()> {
+        self.block_on(self.store.write(path, content))
+    }
+
+    /// Writes the contents to a file with the given [`WriteOptions`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Store::write_with_options`].
+    pub fn write_with_options<C: IntoBytes + Send>(
+        &self,
+        path: impl Into<ObjectPath>,
+        content: C,
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        self.block_on(self.store.write_with_options(path, content, options))
+    }
+
+    /// Reads the contents of a file at the specified path.
+    ///
+    /// # Errors
+    ///
+    /// See [`Store::read`].
+    pub fn read<T: TryFrom<Contents>>(&self, path: impl Into<ObjectPath>) -> DriverResult<T> {
+        self.block_on(self.store.read(path))
+    }
+
+    /// Reads the contents of a file with the given [`ReadOptions`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Store::read_with_options`].
+    pub fn read_with_options<T: TryFrom<Contents>>(
+        &self,
+        path: impl Into<ObjectPath>,
+        options: &ReadOptions,
+    ) -> DriverResult<T> {
+        self.block_on(self.store.read_with_options(path, options))
+    }
+
+    /// Reads the given byte range of the file at the specified path.
+    ///
+    /// # Errors
+    ///
+    /// See [`Store::read_range`].
+    pub fn read_range(
+        &self,
+        path: impl Into<ObjectPath>,
+        range: Range<u64>,
+    ) -> DriverResult<Vec<u8>> {
+        self.block_on(self.store.read_range(path, range))
+    }
+
+    /// Returns the size in bytes of the file at the specified path.
+    ///
+    /// # Errors
+    ///
+    /// See [`Store::size`].
+    pub fn size(&self, path: impl Into<ObjectPath>) -> DriverResult<u64> {
+        self.block_on(self.store.size(path))
+    }
+
+    /// Deletes the file at the specified path.
+    ///
+    /// # Errors
+    ///
+    /// See [`Store::delete`].
+    pub fn delete(&self, path: impl Into<ObjectPath>) -> DriverResult<()> {
+        self.block_on(self.store.delete(path))
+    }
+
+    /// Deletes the directory at the specified path.
+    ///
+    /// # Errors
+    ///
+    /// See [`Store::delete_directory`].
+    pub fn delete_directory(&self, path: impl Into<ObjectPath>) -> DriverResult<()> {
+        self.block_on(self.store.delete_directory(path))
+    }
+
+    /// Creates an empty directory at the specified path.
+    ///
+    /// # Errors
+    ///
+    /// See [`Store::create_directory`].
+    pub fn create_directory(&self, path: impl Into<ObjectPath>) -> DriverResult<()> {
+        self.block_on(self.store.create_directory(path))
+    }
+
+    /// Checks whether a directory exists at the specified path.
+    ///
+    /// # Errors
+    ///
+    /// See [`Store::directory_exists`].
+    pub fn directory_exists(&self, path: impl Into<ObjectPath>) -> DriverResult<bool> {
+        self.block_on(self.store.directory_exists(path))
+    }
+
+    /// Deletes every file of the storage.
+    ///
+    /// # Errors
+    ///
+    /// See [`Store::purge_all`].
+    pub fn purge_all(&self) -> DriverResult<()> {
+        self.block_on(self.store.purge_all())
+    }
+
+    /// Returns the last modified time of the file at the specified path.
+    ///
+    /// # Errors
+    ///
+    /// See [`Store::last_modified`].
+    pub fn last_modified(&self, path: impl Into<ObjectPath>) -> DriverResult<SystemTime> {
+        self.block_on(self.store.last_modified(path))
+    }
+
+    /// Reads the contents of a file when it was modified after the given
+    /// time.
+    ///
+    /// # Errors
+    ///
+    /// See [`Store::read_if_modified_since`].
+    pub fn read_if_modified_since(
+        &self,
+        path: impl Into<ObjectPath>,
+        since: SystemTime,
+    ) -> DriverResult<Option<Bytes>> {
+        self.block_on(self.store.read_if_modified_since(path, since))
+    }
+
+    /// Returns the metadata of the file at the specified path, or `None` when
+    /// it does not exist.
+    ///
+    /// # Errors
+    ///
+    /// See [`Store::stat`].
+    pub fn stat(&self, path: impl Into<ObjectPath>) -> DriverResult<Option<ObjectMetadata>> {
+        self.block_on(self.store.stat(path))
+    }
+
+    /// Lists the files under the specified path.
+    ///
+    /// # Errors
+    ///
+    /// See [`Store::list`].
+    pub fn list(&self, path: impl Into<ObjectPath>) -> DriverResult<Vec<ObjectPath>> {
+        self.block_on(self.store.list(path))
+    }
+
+    /// Returns a stable public URL of the file at the given path.
+    ///
+    /// # Errors
+    ///
+    /// See [`Store::public_url`].
+    pub fn public_url(&self, path: impl Into<ObjectPath>) -> DriverResult<String> {
+        self.block_on(self.store.public_url(path))
+    }
+}
+
+/// Returns a single threaded runtime running the store futures.
+fn runtime() -> DriverResult<Runtime> {
+    Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|err| DriverError::Any(Box::new(err)))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn can_use_store_without_runtime() {
+        let store = BlockingStore::from_config(StoreConfig::InMem()).unwrap();
+        let path = Path::new("folder/file.txt");
+
+        store.write(path, "content").unwrap();
+        assert!(store.file_exists(path).unwrap());
+        assert_eq!(store.read::<String>(path).unwrap(), "content");
+        assert_eq!(store.size(path).unwrap(), 7);
+        assert_eq!(
+            store.list(Path::new("folder")).unwrap(),
+            vec![ObjectPath::from(path)]
+        );
+
+        store.delete(path).unwrap();
+        assert!(!store.file_exists(path).unwrap());
+    }
+}
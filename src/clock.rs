@@ -0,0 +1,28 @@
+//! # Clock Module
+//!
+//! The `clock` module reads the wall clock and the process id on every
+//! target. In browsers (`wasm32-unknown-unknown`), `SystemTime::now` and
+//! `std::process::id` panic, so the time is read from the JavaScript `Date`
+//! instead.
+use std::time::SystemTime;
+
+/// Returns the current time.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) fn now() -> SystemTime {
+    SystemTime::now()
+}
+
+/// Returns the current time.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub(crate) fn now() -> SystemTime {
+    SystemTime::UNIX_EPOCH + std::time::Duration::from_secs_f64(js_sys::Date::now() / 1000.0)
+}
+
+/// Returns the id of the current process, or `0` in browsers.
+pub(crate) fn process_id() -> u32 {
+    if cfg!(all(target_arch = "wasm32", target_os = "unknown")) {
+        0
+    } else {
+        std::process::id()
+    }
+}
@@ -1,11 +1,15 @@
 //! # Contents Module
 //!
 //! The [`Contents`] module provides a simple struct to hold byte data
-//! conversions.
+//! conversions, and the [`IntoBytes`] trait converting written contents into
+//! [`Bytes`] without copying owned buffers.
+use std::{borrow::Cow, str::Utf8Error};
+
+use bytes::Bytes;
 
 /// The `Contents` struct represents a container for byte data.
 pub struct Contents {
-    data: Vec<u8>,
+    data: Bytes,
 }
 
 impl Contents {
@@ -21,9 +25,24 @@ impl Contents {
         bytes: aws_smithy_types::byte_stream::ByteStream,
     ) -> Result<Self, aws_smithy_types::byte_stream::error::Error> {
         Ok(Self {
-            data: bytes.collect().await?.to_vec(),
+            data: bytes.collect().await?.into_bytes(),
         })
     }
+
+    /// Returns the byte data without copying it.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the byte data as a string slice without copying it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the byte data is not valid UTF-8.
+    pub fn as_str(&self) -> Result<&str, Utf8Error> {
+        std::str::from_utf8(&self.data)
+    }
 }
 
 impl From<Contents> for Vec<u8> {
@@ -33,6 +52,13 @@ impl From<Contents> for Vec<u8> {
     ///
     /// Returns a `Vec<u8>` containing the byte data from the `Contents`
     /// instance.
+    fn from(contents: Contents) -> Self {
+        contents.data.into()
+    }
+}
+
+impl From<Contents> for Bytes {
+    /// Converts a `Contents` instance into [`Bytes`] without copying.
     fn from(contents: Contents) -> Self {
         contents.data
     }
@@ -45,6 +71,13 @@ impl From<Vec<u8>> for Contents {
     ///
     /// Returns a `Contents` instance with the provided byte data.
     fn from(data: Vec<u8>) -> Self {
+        Self { data: data.into() }
+    }
+}
+
+impl From<Bytes> for Contents {
+    /// Converts [`Bytes`] into a `Contents` instance without copying.
+    fn from(data: Bytes) -> Self {
         Self { data }
     }
 }
@@ -59,6 +92,68 @@ impl TryFrom<Contents> for String {
     /// Returns a `Result` containing a `String` with the UTF-8 representation
     /// of the byte data, or an error if the conversion fails.
     fn try_from(contents: Contents) -> Result<Self, Self::Error> {
-        Self::from_utf8(contents.data)
+        Self::from_utf8(contents.data.into())
+    }
+}
+
+impl TryFrom<Contents> for Cow<'static, str> {
+    type Error = std::string::FromUtf8Error;
+
+    /// Tries to convert a `Contents` instance into an owned [`Cow`] string.
+    fn try_from(contents: Contents) -> Result<Self, Self::Error> {
+        String::try_from(contents).map(Cow::Owned)
+    }
+}
+
+/// Trait converting written contents into [`Bytes`].
+///
+/// Owned buffers ([`Bytes`], `Vec<u8>`, `String`) are moved without copying,
+/// borrowed ones (`&[u8]`, `&str`, `&Vec<u8>`, ...) are copied once.
+pub trait IntoBytes {
+    fn into_bytes(self) -> Bytes;
+}
+
+impl IntoBytes for Bytes {
+    fn into_bytes(self) -> Bytes {
+        self
+    }
+}
+
+impl IntoBytes for Vec<u8> {
+    fn into_bytes(self) -> Bytes {
+        self.into()
+    }
+}
+
+impl IntoBytes for String {
+    fn into_bytes(self) -> Bytes {
+        self.into()
+    }
+}
+
+impl<T: AsRef<[u8]> + ?Sized> IntoBytes for &T {
+    fn into_bytes(self) -> Bytes {
+        Bytes::copy_from_slice(self.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn can_convert_contents() {
+        let contents = Contents::from(Bytes::from_static(b"my content"));
+        assert_eq!(contents.as_bytes(), b"my content");
+        assert_eq!(contents.as_str().unwrap(), "my content");
+        assert_eq!(
+            Cow::<str>::try_from(contents).unwrap(),
+            Cow::<str>::Borrowed("my content")
+        );
+
+        let contents = Contents::from(vec![0xff, 0xfe]);
+        assert!(contents.as_str().is_err());
+        assert_eq!(Bytes::from(contents), Bytes::from_static(&[0xff, 0xfe]));
     }
 }
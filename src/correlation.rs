@@ -0,0 +1,106 @@
+//! # Correlation Module
+//!
+//! The `correlation` module propagates a correlation id to the backend
+//! requests sent while a future runs, so they can be matched with the
+//! application logs. The S3 and HTTP drivers send it in the
+//! [`CORRELATION_ID_HEADER`] header, and the Azure driver in the
+//! `x-ms-client-request-id` header recorded by the storage logs.
+//!
+//! With the `tracing` feature, requests sent outside of [`scope`] use the id
+//! of the current tracing span, and the drivers record the provider request
+//! id of failed requests in a `tracing` event.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::Path;
+//! use active_storage::{correlation, StoreConfig};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!
+//!     correlation::scope("request-42", async {
+//!         assert_eq!(correlation::current().as_deref(), Some("request-42"));
+//!         store
+//!             .write(Path::new("file.txt"), "my content")
+//!             .await
+//!             .unwrap();
+//!     })
+//!     .await;
+//! }
+//! ```
+use std::future::Future;
+
+/// The header the correlation id is sent in to S3 and HTTP backends.
+pub const CORRELATION_ID_HEADER: &str = "x-correlation-id";
+
+tokio::task_local! {
+    static CORRELATION_ID: String;
+}
+
+/// Runs the future with the given correlation id, sent with every backend
+/// request of the future. The id must be a valid HTTP header value.
+pub async fn scope<S: Into<String>, F: Future>(correlation_id: S, future: F) -> F::Output {
+    CORRELATION_ID.scope(correlation_id.into(), future).await
+}
+
+/// Returns the correlation id of the current task, set with [`scope`].
+///
+/// With the `tracing` feature, the id of the current tracing span is returned
+/// when none was set.
+#[must_use]
+pub fn current() -> Option<String> {
+    let correlation_id = CORRELATION_ID.try_with(Clone::clone).ok();
+    #[cfg(feature = "tracing")]
+    let correlation_id = correlation_id.or_else(|| {
+        tracing::Span::current()
+            .id()
+            .map(|id| format!("{:016x}", id.into_u64()))
+    });
+    correlation_id
+}
+
+/// Records a failed backend request in a `tracing` event of the current span,
+/// with the provider request id needed to look it up on the provider side.
+#[cfg(all(
+    feature = "tracing",
+    any(feature = "aws_s3", feature = "azure", feature = "http")
+))]
+pub(crate) fn record_backend_error(
+    provider: &'static str,
+    status: Option<u16>,
+    code: Option<&str>,
+    request_id: Option<&str>,
+) {
+    tracing::warn!(
+        provider,
+        status,
+        code,
+        request_id,
+        correlation_id = current(),
+        "storage backend request failed"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[tokio::test]
+    async fn can_scope_correlation_id() {
+        assert_eq!(current(), None);
+
+        let nested = scope("outer", async {
+            let inner = scope("inner", async { current() }).await;
+            (current(), inner)
+        })
+        .await;
+        assert_eq!(
+            nested,
+            (Some("outer".to_string()), Some("inner".to_string()))
+        );
+        assert_eq!(current(), None);
+    }
+}
@@ -0,0 +1,530 @@
+//! # Dedup Module
+//!
+//! The `dedup` module defines [`DedupStore`], a [`Store`] wrapper that keeps a
+//! single copy of identical contents.
+//!
+//! Contents are split into chunks following a [`Chunking`] strategy, and each
+//! chunk is hashed with SHA-256 and stored once under a content-addressed key.
+//! Each logical path is a lightweight manifest object holding the content hash
+//! and its chunk hashes, and every chunk keeps a reference count so it is
+//! deleted with its last manifest.
+//!
+//! With [`Chunking::ContentDefined`], chunk boundaries depend on the content
+//! itself, so large files that differ slightly share most of their chunks.
+//!
+//! ## Layout
+//!
+//! - `pointers/<path>`: the manifest of each logical path.
+//! - `objects/<hash>`: the chunks.
+//! - `refs/<hash>`: the number of manifest entries referencing a chunk.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::PathBuf;
+//! use active_storage::{dedup::DedupStore, StoreConfig};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!     let dedup = DedupStore::new(store).unwrap();
+//!
+//!     let first = dedup.write(PathBuf::from("a.txt").as_path(), "my content").await.unwrap();
+//!     let second = dedup.write(PathBuf::from("b.txt").as_path(), "my content").await.unwrap();
+//!     assert_eq!(first, second);
+//!
+//!     let content: String = dedup.read(PathBuf::from("b.txt").as_path()).await.unwrap();
+//!     assert_eq!(content, "my content");
+//! }
+//! ```
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use futures::{lock::Mutex, stream};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    contents::Contents,
+    errors::{DriverError, DriverResult},
+    hex,
+    key::ObjectPath,
+    serve::ByteStream,
+    store::Store,
+};
+
+/// The prefix of the content hash in manifest objects.
+const HASH_PREFIX: &str = "sha256:";
+
+/// Enum representing how contents are split into deduplicated chunks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Chunking {
+    /// The whole contents are a single chunk.
+    #[default]
+    None,
+    /// Chunks of the given size in bytes.
+    Fixed(usize),
+    /// Content-defined chunks, cut where a rolling hash of the content matches,
+    /// and bounded by the given sizes in bytes.
+    ContentDefined { min: usize, avg: usize, max: usize },
+}
+
+impl Chunking {
+    /// Splits the content into chunks. Empty contents are a single empty
+    /// chunk.
+    fn split<'a>(&self, content: &'a [u8]) -> Vec<&'a [u8]> {
+        let mut chunks = match *self {
+            Self::None => vec![content],
+            Self::Fixed(size) => content.chunks(size.max(1)).collect(),
+            Self::ContentDefined { min, avg, max } => {
+                let mut chunks = Vec::new();
+                let mut rest = content;
+                while !rest.is_empty() {
+                    let (chunk, tail) = rest.split_at(cut_point(rest, min, avg, max));
+                    chunks.push(chunk);
+                    rest = tail;
+                }
+                chunks
+            }
+        };
+        if chunks.is_empty() {
+            chunks.push(content);
+        }
+        chunks
+    }
+}
+
+/// Struct representing the manifest of a logical path.
+#[derive(Debug, PartialEq, Eq)]
+struct Manifest {
+    /// The hash of the whole contents.
+    hash: String,
+    /// The hashes of the contents chunks, in order.
+    chunks: Vec<String>,
+}
+
+impl Manifest {
+    fn parse(manifest: &str) -> DriverResult<Self> {
+        let mut lines = manifest
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty());
+        let hash = lines
+            .next()
+            .and_then(|line| line.strip_prefix(HASH_PREFIX))
+            .ok_or(DriverError::DecodeError)?
+            .to_string();
+        let mut chunks = lines.map(ToString::to_string).collect::<Vec<_>>();
+        // single chunk manifests only hold the content hash
+        if chunks.is_empty() {
+            chunks.push(hash.clone());
+        }
+
+        Ok(Self { hash, chunks })
+    }
+
+    fn serialize(&self) -> String {
+        let mut manifest = format!("{HASH_PREFIX}{}\n", self.hash);
+        if self.chunks != [self.hash.as_str()] {
+            for chunk in &self.chunks {
+                manifest.push_str(chunk);
+                manifest.push('\n');
+            }
+        }
+        manifest
+    }
+}
+
+/// Struct representing a content deduplicating store.
+#[derive(Clone)]
+pub struct DedupStore {
+    pointers: Store,
+    objects: Store,
+    refs: Store,
+    chunking: Chunking,
+    /// Serializes reference count updates within the process.
+    lock: Arc<Mutex<()>>,
+}
+
+impl DedupStore {
+    /// Creates a new [`DedupStore`] keeping its pointers, contents and
+    /// reference counts in the given store.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the internal prefixes could not be scoped.
+    pub fn new(store: Store) -> DriverResult<Self> {
+        Ok(Self {
+            pointers: store.scoped(Path::new("pointers"))?,
+            objects: store.scoped(Path::new("objects"))?,
+            refs: store.scoped(Path::new("refs"))?,
+            chunking: Chunking::default(),
+            lock: Arc::new(Mutex::new(())),
+        })
+    }
+
+    /// Sets the chunking strategy of the contents written from now on.
+    /// Existing contents keep their chunks and remain readable.
+    #[must_use]
+    pub const fn with_chunking(mut self, chunking: Chunking) -> Self {
+        self.chunking = chunking;
+        self
+    }
+
+    /// Writes the contents to the given logical path, storing only the chunks
+    /// no other path holds. Returns the content hash.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the underlying store fails.
+    pub async fn write<C: AsRef<[u8]> + Send>(
+        &self,
+        path: &Path,
+        content: C,
+    ) -> DriverResult<String> {
+        let content = content.as_ref();
+        let hash = hash(content);
+
+        let _guard = self.lock.lock().await;
+        let previous = self.manifest(path).await?;
+        if previous
+            .as_ref()
+            .is_some_and(|previous| previous.hash == hash)
+        {
+            return Ok(hash);
+        }
+
+        let mut chunks = Vec::new();
+        for chunk in self.chunking.split(content) {
+            let chunk_hash = self::hash(chunk);
+            let object_path = object_path(&chunk_hash);
+            if !self.objects.file_exists(&object_path).await? {
+                self.objects.write(&object_path, chunk).await?;
+            }
+            self.add_ref(&chunk_hash, 1).await?;
+            chunks.push(chunk_hash);
+        }
+
+        let manifest = Manifest {
+            hash: hash.clone(),
+            chunks,
+        };
+        self.pointers.write(path, manifest.serialize()).await?;
+
+        if let Some(previous) = previous {
+            for chunk in &previous.chunks {
+                self.add_ref(chunk, -1).await?;
+            }
+        }
+
+        Ok(hash)
+    }
+
+    /// Reads the contents of the given logical path.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::ResourceNotFound`] if the path doesn't exist, or
+    /// an error if the contents could not be read or decoded.
+    pub async fn read<T: TryFrom<Contents>>(&self, path: &Path) -> DriverResult<T> {
+        let manifest = self
+            .manifest(path)
+            .await?
+            .ok_or(DriverError::ResourceNotFound)?;
+
+        let mut content = Vec::new();
+        for chunk in &manifest.chunks {
+            content.extend(self.objects.read::<Vec<u8>>(&object_path(chunk)).await?);
+        }
+
+        Contents::from(content)
+            .try_into()
+            .map_err(|_| DriverError::DecodeError)
+    }
+
+    /// Reads the contents of the given logical path as a stream, reading each
+    /// chunk only when the stream reaches it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::ResourceNotFound`] if the path doesn't exist.
+    /// Errors reading a chunk are returned by the stream.
+    pub async fn read_stream(&self, path: &Path) -> DriverResult<ByteStream> {
+        let manifest = self
+            .manifest(path)
+            .await?
+            .ok_or(DriverError::ResourceNotFound)?;
+
+        let objects = self.objects.clone();
+        let stream = stream::try_unfold(
+            (objects, manifest.chunks.into_iter()),
+            |(objects, mut chunks)| async move {
+                let Some(chunk) = chunks.next() else {
+                    return Ok(None);
+                };
+                let content = objects.read::<Vec<u8>>(&object_path(&chunk)).await?;
+                Ok(Some((content, (objects, chunks))))
+            },
+        );
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Returns the content hash of the given logical path.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::ResourceNotFound`] if the path doesn't exist.
+    pub async fn content_hash(&self, path: &Path) -> DriverResult<String> {
+        self.manifest(path)
+            .await?
+            .map(|manifest| manifest.hash)
+            .ok_or(DriverError::ResourceNotFound)
+    }
+
+    /// Checks if the given logical path exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the underlying store fails.
+    pub async fn file_exists(&self, path: &Path) -> DriverResult<bool> {
+        self.pointers.file_exists(path).await
+    }
+
+    /// Lists the logical paths under the given path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the underlying store fails.
+    pub async fn list(&self, path: &Path) -> DriverResult<Vec<ObjectPath>> {
+        self.pointers.list(path).await
+    }
+
+    /// Deletes the given logical path, and its chunks when no other path
+    /// references them.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::ResourceNotFound`] if the path doesn't exist, or
+    /// an error if the underlying store fails.
+    pub async fn delete(&self, path: &Path) -> DriverResult<()> {
+        let _guard = self.lock.lock().await;
+        let manifest = self
+            .manifest(path)
+            .await?
+            .ok_or(DriverError::ResourceNotFound)?;
+
+        self.pointers.delete(path).await?;
+        for chunk in &manifest.chunks {
+            self.add_ref(chunk, -1).await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the number of manifest entries referencing the given chunk
+    /// hash.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the underlying store fails.
+    pub async fn ref_count(&self, hash: &str) -> DriverResult<u64> {
+        let ref_path = PathBuf::from(hash);
+        if !self.refs.file_exists(&ref_path).await? {
+            return Ok(0);
+        }
+
+        self.refs
+            .read::<String>(&ref_path)
+            .await?
+            .trim()
+            .parse()
+            .map_err(|_| DriverError::DecodeError)
+    }
+
+    /// Returns the manifest of the given path, if any.
+    async fn manifest(&self, path: &Path) -> DriverResult<Option<Manifest>> {
+        if !self.pointers.file_exists(path).await? {
+            return Ok(None);
+        }
+
+        let manifest: String = self.pointers.read(path).await?;
+        Manifest::parse(&manifest).map(Some)
+    }
+
+    /// Adds the given delta to the reference count of the chunk hash, deleting
+    /// the chunk once it reaches zero.
+    async fn add_ref(&self, hash: &str, delta: i64) -> DriverResult<()> {
+        let count = self.ref_count(hash).await?.saturating_add_signed(delta);
+        let ref_path = PathBuf::from(hash);
+
+        if count == 0 {
+            self.objects.delete(&object_path(hash)).await?;
+            self.refs.delete(&ref_path).await
+        } else {
+            self.refs.write(&ref_path, count.to_string()).await
+        }
+    }
+}
+
+/// Returns the hex encoded SHA-256 hash of the contents.
+fn hash(content: &[u8]) -> String {
+    hex::encode(&Sha256::digest(content))
+}
+
+/// Returns the length of the next content-defined chunk, using a gear rolling
+/// hash. A boundary is cut when the low bits of the hash are zero, so chunks
+/// average `avg` bytes.
+fn cut_point(content: &[u8], min: usize, avg: usize, max: usize) -> usize {
+    let max = max.max(1).min(content.len());
+    let min = min.min(max);
+    let mask = u64::MAX >> (64 - avg.max(2).ilog2());
+
+    let mut hash = 0_u64;
+    for (index, byte) in content.iter().enumerate().take(max).skip(min) {
+        hash = (hash << 1).wrapping_add(GEAR[usize::from(*byte)]);
+        if hash & mask == 0 {
+            return index + 1;
+        }
+    }
+    max
+}
+
+/// The gear hash table, 256 pseudo random values generated with splitmix64.
+const GEAR: [u64; 256] = {
+    let mut table = [0; 256];
+    let mut state = 0_u64;
+    let mut index = 0;
+    while index < 256 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut value = state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[index] = value ^ (value >> 31);
+        index += 1;
+    }
+    table
+};
+
+/// Returns the path of the contents with the given hash, fanned out by the
+/// first hash byte to keep directories small.
+fn object_path(hash: &str) -> PathBuf {
+    PathBuf::from(&hash[..2]).join(hash)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use futures::TryStreamExt;
+
+    use super::*;
+    use crate::StoreConfig;
+
+    #[tokio::test]
+    async fn can_deduplicate_contents() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let dedup = DedupStore::new(store.clone()).unwrap();
+        let a = PathBuf::from("a.txt");
+        let b = PathBuf::from("b.txt");
+
+        let hash = dedup.write(&a, "content").await.unwrap();
+        assert_eq!(dedup.write(&b, "content").await.unwrap(), hash);
+        assert_eq!(dedup.ref_count(&hash).await.unwrap(), 2);
+        assert_eq!(
+            store.list(Path::new("objects")).await.unwrap(),
+            vec![ObjectPath::new("objects").join(object_path(&hash))]
+        );
+
+        dedup.delete(&a).await.unwrap();
+        assert_eq!(dedup.ref_count(&hash).await.unwrap(), 1);
+        assert_eq!(dedup.read::<String>(&b).await.unwrap(), "content");
+
+        dedup.delete(&b).await.unwrap();
+        assert_eq!(dedup.ref_count(&hash).await.unwrap(), 0);
+        assert!(store.list(Path::new("objects")).await.unwrap().is_empty());
+        assert!(matches!(
+            dedup.read::<String>(&b).await,
+            Err(DriverError::ResourceNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn can_overwrite_logical_path() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let dedup = DedupStore::new(store).unwrap();
+        let path = PathBuf::from("a.txt");
+
+        let first = dedup.write(&path, "first").await.unwrap();
+        let second = dedup.write(&path, "second").await.unwrap();
+        assert_eq!(dedup.ref_count(&first).await.unwrap(), 0);
+        assert_eq!(dedup.ref_count(&second).await.unwrap(), 1);
+        assert_eq!(dedup.content_hash(&path).await.unwrap(), second);
+        assert_eq!(
+            dedup.list(Path::new("")).await.unwrap(),
+            vec![ObjectPath::from(path)]
+        );
+    }
+
+    #[test]
+    fn can_split_chunks() {
+        let content = (0..10_000_u32)
+            .map(|index| (index.wrapping_mul(2_654_435_761) >> 24) as u8)
+            .collect::<Vec<_>>();
+
+        assert_eq!(Chunking::None.split(&content), vec![content.as_slice()]);
+        assert_eq!(Chunking::Fixed(4096).split(&content).len(), 3);
+
+        let chunking = Chunking::ContentDefined {
+            min: 256,
+            avg: 1024,
+            max: 4096,
+        };
+        let chunks = chunking.split(&content);
+        assert_eq!(chunks.concat(), content);
+        assert!(chunks[..chunks.len() - 1]
+            .iter()
+            .all(|chunk| (256..=4096).contains(&chunk.len())));
+    }
+
+    #[tokio::test]
+    async fn can_share_content_defined_chunks() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let dedup =
+            DedupStore::new(store.clone())
+                .unwrap()
+                .with_chunking(Chunking::ContentDefined {
+                    min: 256,
+                    avg: 1024,
+                    max: 4096,
+                });
+
+        let original = (0..50_000_u32)
+            .map(|index| (index.wrapping_mul(2_654_435_761) >> 24) as u8)
+            .collect::<Vec<_>>();
+        let mut edited = original.clone();
+        edited.splice(100..100, b"inserted".iter().copied());
+
+        dedup.write(Path::new("original"), &original).await.unwrap();
+        let chunks = store.list(Path::new("objects")).await.unwrap().len();
+        dedup.write(Path::new("edited"), &edited).await.unwrap();
+        let new_chunks = store.list(Path::new("objects")).await.unwrap().len() - chunks;
+        assert!(new_chunks <= 2, "edited file added {new_chunks} chunks");
+
+        assert_eq!(
+            dedup.read::<Vec<u8>>(Path::new("edited")).await.unwrap(),
+            edited
+        );
+        let streamed = dedup
+            .read_stream(Path::new("original"))
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(streamed.concat(), original);
+
+        dedup.delete(Path::new("original")).await.unwrap();
+        dedup.delete(Path::new("edited")).await.unwrap();
+        assert!(store.list(Path::new("")).await.unwrap().is_empty());
+    }
+}
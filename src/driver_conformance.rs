@@ -0,0 +1,735 @@
+//! # Driver Conformance Module
+//!
+//! The `driver_conformance` module exposes the suite the built-in drivers are
+//! tested with, so authors of third-party [`crate::drivers::Driver`]
+//! implementations can verify they match the crate semantics. [`run`] checks
+//! the documented behaviors, and [`run_randomized`] round-trips files with
+//! generated paths and contents.
+//!
+//! The suites panic on the first mismatch, like assertions in a test.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::PathBuf;
+//! use active_storage::{driver_conformance, StoreConfig};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!
+//!     driver_conformance::run(&store, PathBuf::new()).await;
+//!     driver_conformance::run_randomized(&store, PathBuf::from("random"), 20, 42).await;
+//!     driver_conformance::assert_strict_delete(&store).await;
+//! }
+//! ```
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    drivers::{
+        capabilities::Capabilities, unique_id, ContainerOptions, Precondition, ReadOptions,
+        WriteOptions,
+    },
+    errors::DriverError,
+    key::ObjectPath,
+    store::Store,
+    transfer::TransferOptions,
+};
+
+/// The characters of the generated path segments.
+const PATH_CHARACTERS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789-_";
+
+/// The characters mixed into a quarter of the generated path segments: ones
+/// the drivers must encode in URLs or file names, non ASCII ones, and `%`
+/// sequences reading as percent-encoded bytes.
+const SPECIAL_PATH_CHARACTERS: &[&str] = &[
+    " ", "%", "%41", "%2F", "#", "?", "&", "+", "=", "@", "!", "$", ",", ";", "'", "(", "~", ":",
+    "*", "\"", "<", ">", "|", "\\", "é", "中", "🦀",
+];
+
+/// The maximum size of the generated contents.
+const MAX_CONTENT_SIZE: u64 = 4096;
+
+/// Runs the conformance suite against the store.
+///
+/// The suite writes and reads files, deletes files and directories, checks
+/// file existence and last modified timestamps, and runs multipart uploads.
+///
+/// # Parameters
+///
+/// - `driver`: A reference to a `Store` containing the `Driver` implementation
+///   under test.
+/// - `location`: The base path for testing operations.
+///
+/// # Panics
+///
+/// Panics when the driver doesn't behave like the built-in drivers.
+pub async fn run(driver: &Store, location: PathBuf) {
+    let foo_directory = location.join("foo");
+    let foo_directory_file_1 = foo_directory.join("foo_file-1.txt");
+
+    assert!(
+        driver.health_check().await.is_healthy(),
+        "store should be healthy"
+    );
+
+    assert_container(driver).await;
+
+    assert_unknown_files(driver).await;
+
+    assert_relative_keys(driver).await;
+
+    assert_write_file(driver, &foo_directory_file_1).await;
+
+    assert_conditional_write(driver, &foo_directory_file_1).await;
+
+    assert_last_modified(driver, location.as_path()).await;
+
+    assert_set_last_modified(driver, location.as_path()).await;
+
+    assert_delete_file(driver, foo_directory_file_1.as_path()).await;
+
+    assert_upload(driver, location.as_path()).await;
+
+    assert_directories(driver, location.as_path()).await;
+
+    assert_empty_directory(driver, location.as_path()).await;
+}
+
+/// Asserts that an empty directory can be created, is found without being
+/// listed as a file, and can be deleted.
+async fn assert_empty_directory(driver: &Store, path: &Path) {
+    let directory = path.join("empty").join("nested");
+
+    assert!(
+        !driver.directory_exists(directory.as_path()).await.unwrap(),
+        "directory should not exists"
+    );
+    assert!(
+        driver.create_directory(directory.as_path()).await.is_ok(),
+        "directory should be created"
+    );
+    assert!(
+        driver.directory_exists(directory.as_path()).await.unwrap(),
+        "directory should be found"
+    );
+    assert!(
+        driver
+            .directory_exists(path.join("empty").as_path())
+            .await
+            .unwrap(),
+        "parent directory should be found"
+    );
+    assert!(
+        driver.list(directory.as_path()).await.unwrap().is_empty(),
+        "empty directory should not list files"
+    );
+
+    assert!(driver
+        .delete_directory(path.join("empty").as_path())
+        .await
+        .is_ok());
+    assert!(
+        !driver.directory_exists(directory.as_path()).await.unwrap(),
+        "directory should be deleted"
+    );
+}
+
+/// Asserts that a write keeps the given last modification time when the
+/// driver supports it, and is refused otherwise.
+async fn assert_set_last_modified(driver: &Store, path: &Path) {
+    let file = path.join("preserved-mtime.txt");
+    let mtime = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    let options = WriteOptions::default().with_last_modified(mtime);
+
+    let written = driver
+        .write_with_options(file.as_path(), "content", &options)
+        .await;
+    if !driver
+        .capabilities()
+        .contains(Capabilities::SET_LAST_MODIFIED)
+    {
+        assert!(matches!(written, Err(DriverError::Unsupported(_))));
+        return;
+    }
+
+    assert!(written.is_ok(), "file should be written");
+    assert_eq!(
+        driver.last_modified(file.as_path()).await.unwrap(),
+        mtime,
+        "last modification time should be preserved"
+    );
+    assert!(driver.delete(file.as_path()).await.is_ok());
+}
+
+/// Asserts that relative keys are resolved under the store root rather than
+/// the process working directory.
+async fn assert_relative_keys(driver: &Store) {
+    let directory = PathBuf::from(format!("relative-{}", unique_id()));
+    let file = directory.join("file.txt");
+
+    assert!(
+        !driver.file_exists(file.as_path()).await.unwrap(),
+        "relative file should not exists"
+    );
+    assert!(
+        driver.write(file.as_path(), b"content").await.is_ok(),
+        "relative file should be written"
+    );
+    assert!(
+        driver.file_exists(file.as_path()).await.unwrap(),
+        "relative file should be found"
+    );
+    assert!(
+        !driver.file_exists(directory.as_path()).await.unwrap(),
+        "a directory should not be reported as a file"
+    );
+
+    assert!(driver.delete(file.as_path()).await.is_ok());
+    assert!(
+        !driver.file_exists(file.as_path()).await.unwrap(),
+        "relative file should be deleted"
+    );
+}
+
+/// Asserts behaviors related to writing a file, checking its existence, and
+/// reading its content.
+///
+/// This function tests a generic `Driver` implementation's behavior when
+/// writing a file, checking its existence, and reading its content. It verifies
+/// that the file is successfully written, can be found, and its content matches
+/// the expected value.
+async fn assert_write_file(driver: &Store, file: &Path) {
+    // write a file
+    assert!(
+        driver.write(file, b"content").await.is_ok(),
+        "file should be written"
+    );
+
+    // file should found
+    assert!(
+        driver.file_exists(file).await.unwrap(),
+        "file should be found"
+    );
+
+    // read file content
+    assert_eq!(
+        driver.read::<String>(file).await.unwrap(),
+        "content".to_string(),
+        "invalid file content"
+    );
+
+    assert_eq!(
+        driver
+            .read_with_options::<String>(file, &ReadOptions::default())
+            .await
+            .unwrap(),
+        "content".to_string(),
+        "invalid file content read with options"
+    );
+
+    assert_eq!(driver.size(file).await.unwrap(), 7, "invalid file size");
+
+    let metadata = driver
+        .stat(file)
+        .await
+        .unwrap()
+        .expect("file should be found");
+    assert_eq!(metadata.size, 7, "invalid stat size");
+    assert_eq!(
+        metadata.last_modified,
+        driver.last_modified(file).await.unwrap(),
+        "invalid stat last modified"
+    );
+
+    assert_eq!(
+        driver
+            .read_or_write_with::<String, _, _>(file, || "default")
+            .await
+            .unwrap(),
+        "content".to_string(),
+        "existing file should not be replaced by the default"
+    );
+
+    // read file range
+    assert_eq!(
+        driver.read_range(file, 1..4).await.unwrap(),
+        b"ont",
+        "invalid file range content"
+    );
+
+    // download file with parallel ranged reads
+    let mut content = Vec::new();
+    assert_eq!(
+        driver
+            .download_parallel(file, &mut content, 2, 3)
+            .await
+            .unwrap(),
+        7
+    );
+    assert_eq!(content, b"content", "invalid downloaded content");
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    assert_local_transfers(driver, file).await;
+}
+
+/// Asserts that the file at the given path, holding `content`, is downloaded
+/// to and uploaded from a local file.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+async fn assert_local_transfers(driver: &Store, file: &Path) {
+    // download file to a local file
+    let local = std::env::temp_dir().join(format!("active-storage-{}", unique_id()));
+    let local_file = local.join("download.txt");
+    assert_eq!(driver.download_to(file, &local_file).await.unwrap(), 7);
+    assert_eq!(std::fs::read(&local_file).unwrap(), b"content");
+    assert_eq!(
+        std::fs::metadata(&local_file).unwrap().modified().unwrap(),
+        driver.last_modified(file).await.unwrap(),
+        "downloaded file should keep the last modified time"
+    );
+
+    // upload a local file, at once and as a multipart upload
+    let uploaded = file.with_extension("uploaded");
+    assert_eq!(driver.upload_from(&local_file, &uploaded).await.unwrap(), 7);
+    assert_eq!(driver.read::<String>(&uploaded).await.unwrap(), "content");
+    let options = TransferOptions::default().with_part_size(3);
+    assert_eq!(
+        driver
+            .upload_from_with_options(&local_file, &uploaded, &options)
+            .await
+            .unwrap(),
+        7
+    );
+    assert_eq!(driver.read::<String>(&uploaded).await.unwrap(), "content");
+    if driver
+        .capabilities()
+        .contains(Capabilities::SET_LAST_MODIFIED)
+    {
+        assert_eq!(
+            driver.last_modified(&uploaded).await.unwrap(),
+            std::fs::metadata(&local_file).unwrap().modified().unwrap(),
+            "multipart uploads should keep the local last modified time"
+        );
+    }
+    driver.delete(&uploaded).await.unwrap();
+    let _ = std::fs::remove_dir_all(&local);
+}
+
+/// Asserts that deleting a missing file fails with
+/// [`DriverError::ResourceNotFound`], for the drivers whose deletes are not
+/// idempotent (all but S3).
+///
+/// # Panics
+///
+/// Panics when deleting a missing file succeeds.
+pub async fn assert_strict_delete(driver: &Store) {
+    assert!(matches!(
+        driver
+            .delete(PathBuf::from("unknown").join("file.txt").as_path())
+            .await,
+        Err(DriverError::ResourceNotFound)
+    ));
+}
+
+/// Asserts behaviors related to deleting a file.
+///
+/// This function tests a generic `Driver` implementation's behavior when
+/// deleting a file. It verifies that the file is successfully deleted, and
+/// subsequent checks for its existence return false.
+async fn assert_delete_file(driver: &Store, path: &Path) {
+    //delete file
+    assert!(driver.delete(path).await.is_ok(), "file should be deleted");
+
+    driver.write(path, b"content").await.unwrap();
+    assert!(
+        driver.delete_if_exists(path).await.unwrap(),
+        "existing file should be reported as deleted"
+    );
+
+    // file should not exists after deletion
+    assert!(
+        !driver.file_exists(path).await.unwrap(),
+        "file should not exist after deletion"
+    );
+}
+
+/// Asserts behaviors related to the last modified timestamp of a file.
+async fn assert_last_modified(driver: &Store, path: &Path) {
+    let file = path.join("file.txt");
+    // write a file
+    assert!(
+        driver.write(file.as_path(), b"content").await.is_ok(),
+        "file should be written"
+    );
+
+    assert!(
+        driver
+            .last_modified(file.as_path())
+            .await
+            .unwrap()
+            .elapsed()
+            .unwrap()
+            .as_secs()
+            < 1,
+        "last modified file should be less then 1 second"
+    );
+
+    assert_eq!(
+        driver
+            .read_if_modified_since(file.as_path(), UNIX_EPOCH)
+            .await
+            .unwrap()
+            .as_deref(),
+        Some(b"content".as_slice()),
+        "modified file should be read"
+    );
+    assert!(
+        driver
+            .read_if_modified_since(
+                file.as_path(),
+                SystemTime::now() + Duration::from_secs(3600)
+            )
+            .await
+            .unwrap()
+            .is_none(),
+        "unmodified file should not be read"
+    );
+}
+
+/// Asserts behaviors related to directories, file creation, and deletion.
+///
+/// This function tests a generic `Driver` implementation's behavior when
+/// creating, writing, and deleting directories and files. It verifies that
+/// files under deleted directories are also deleted while files in other
+/// directories remain unaffected.
+async fn assert_directories(driver: &Store, path: &Path) {
+    let foo_directory = path.join("foo");
+    let bar_directory = path.join("bar");
+    let foo_directory_file_1 = foo_directory.join("foo_file-1.txt");
+    let bar_directory_file_1 = bar_directory.join("bar_file-1.txt");
+    let bar_directory_file_2 = bar_directory.join("bar_file-2.txt");
+
+    driver
+        .write(foo_directory_file_1.as_path(), b"content")
+        .await
+        .unwrap();
+
+    // crate bar file 1 under bar folder
+    driver
+        .write(bar_directory_file_1.as_path(), b"content")
+        .await
+        .unwrap();
+
+    // crate bar file 2 under bar folder
+    driver
+        .write(bar_directory_file_2.as_path(), b"content")
+        .await
+        .unwrap();
+
+    // list bar directory
+    let mut listed_files = driver
+        .list(bar_directory.as_path())
+        .await
+        .unwrap()
+        .into_iter()
+        .filter_map(|path| path.file_name().map(ToString::to_string))
+        .collect::<Vec<_>>();
+    listed_files.sort();
+    assert_eq!(
+        listed_files,
+        vec!["bar_file-1.txt", "bar_file-2.txt"],
+        "bar directory should list its files"
+    );
+
+    // delete bar directory
+    assert!(
+        driver
+            .delete_directory(bar_directory.as_path())
+            .await
+            .is_ok(),
+        "expected bar directory to be deleted"
+    );
+
+    // foo file should be exits
+    assert!(
+        driver
+            .file_exists(foo_directory_file_1.as_path())
+            .await
+            .unwrap(),
+        "foo file should be exists after bar directory deletion"
+    );
+
+    // bar files under bar folder should be deleted
+    assert!(
+        !driver
+            .file_exists(bar_directory_file_1.as_path())
+            .await
+            .unwrap(),
+        "bar file should be deleted after bar directory deletion"
+    );
+
+    assert!(
+        !driver
+            .file_exists(bar_directory_file_2.as_path())
+            .await
+            .unwrap(),
+        "bar file should be deleted after bar directory deletion"
+    );
+}
+
+/// Asserts behaviors related to multipart upload sessions.
+///
+/// This function verifies that an upload can be resumed from its upload id
+/// and that completing it writes the file.
+async fn assert_upload(driver: &Store, path: &Path) {
+    let file = path.join("upload.txt");
+
+    let mut session = driver.begin_upload(file.as_path()).await.unwrap();
+    let part = session.write_part(1, b"content").await.unwrap();
+
+    let session = driver
+        .resume_upload(file.as_path(), session.upload_id())
+        .await
+        .unwrap();
+    assert_eq!(
+        session.parts(),
+        vec![part],
+        "resumed upload should keep its parts"
+    );
+
+    assert!(session.complete().await.is_ok(), "upload should complete");
+    assert_eq!(
+        driver.read::<String>(file.as_path()).await.unwrap(),
+        "content".to_string(),
+        "invalid uploaded file content"
+    );
+}
+
+/// Asserts that a write with the [`Precondition::IfNotExists`] precondition
+/// creates a missing file and never overwrites an existing one.
+async fn assert_conditional_write(driver: &Store, file: &Path) {
+    let options = WriteOptions::default().with_precondition(Precondition::IfNotExists);
+
+    assert!(
+        matches!(
+            driver.write_with_options(file, b"other", &options).await,
+            Err(DriverError::PreconditionFailed)
+        ),
+        "existing file should not be overwritten"
+    );
+    assert_eq!(
+        driver.read::<String>(file).await.unwrap(),
+        "content".to_string(),
+        "existing file content should be kept"
+    );
+
+    let new_file = file.with_extension("new");
+    assert!(
+        driver
+            .write_with_options(new_file.as_path(), b"content", &options)
+            .await
+            .is_ok(),
+        "missing file should be written"
+    );
+    assert!(driver.delete(new_file.as_path()).await.is_ok());
+}
+
+/// Asserts behaviors related to the storage container.
+///
+/// This function verifies that the container exists and that ensuring an
+/// existing container is a no-op.
+async fn assert_container(driver: &Store) {
+    assert!(
+        driver.container_exists().await.unwrap(),
+        "container should exist"
+    );
+
+    assert!(
+        driver
+            .ensure_container(&ContainerOptions::default())
+            .await
+            .is_ok(),
+        "ensure existing container should succeed"
+    );
+}
+
+/// Asserts behaviors related to unknown files and directories.
+///
+/// This function checks the behavior of a generic `Driver` implementation when
+/// interacting with files and directories that do not exist. It verifies that
+/// file existence checks return false, and deletion attempts result in specific
+/// `DriverError` variants.
+async fn assert_unknown_files(driver: &Store) {
+    let path = PathBuf::from("unknown").join("file.txt");
+    // file should not exists
+    assert!(
+        !driver.file_exists(path.as_path()).await.unwrap(),
+        "file should not exists"
+    );
+
+    // deleting a file that doesn't exist is tolerated
+    assert!(
+        !driver.delete_if_exists(path.as_path()).await.unwrap(),
+        "missing file should not be reported as deleted"
+    );
+
+    // validate error when deleting file that doesn't exist
+    assert!(matches!(
+        driver.delete_directory(path.as_path()).await,
+        Err(DriverError::ResourceNotFound)
+    ));
+
+    // the storage root is only deleted with `purge_all`
+    for root in ["", "."] {
+        assert!(
+            matches!(
+                driver.delete_directory(Path::new(root)).await,
+                Err(DriverError::RefusingToDeleteRoot)
+            ),
+            "deleting `{root}` should be refused"
+        );
+    }
+
+    // validate error when deleting file that doesn't exist
+    assert!(matches!(
+        driver.last_modified(path.as_path()).await,
+        Err(DriverError::ResourceNotFound)
+    ));
+
+    assert!(matches!(
+        driver
+            .read_if_modified_since(path.as_path(), UNIX_EPOCH)
+            .await,
+        Err(DriverError::ResourceNotFound)
+    ));
+
+    assert!(
+        driver.stat(path.as_path()).await.unwrap().is_none(),
+        "missing file should have no metadata"
+    );
+
+    assert_eq!(
+        driver.read_opt::<String>(path.as_path()).await.unwrap(),
+        None,
+        "missing file should be read as none"
+    );
+}
+
+/// Round-trips `cases` files with paths and contents generated from the seed
+/// under the given location: each file is written, listed, read whole and by
+/// range, queried and deleted. Paths include characters the drivers have to
+/// encode, which must be listed back unchanged.
+///
+/// The same seed always generates the same cases, so a failure is replayed
+/// by running the suite again with the seed it reports.
+///
+/// # Panics
+///
+/// Panics when the driver doesn't return what was written.
+pub async fn run_randomized(driver: &Store, location: PathBuf, cases: usize, seed: u64) {
+    let mut rng = Rng::new(seed);
+
+    let location = ObjectPath::from(location.as_path());
+    for case in 0..cases {
+        let mut directory = location.clone();
+        for _ in 0..=rng.below(3) {
+            directory = directory.join(rng.segment().as_str());
+        }
+        let path = directory.join(format!("{}-{case}.bin", rng.segment()).as_str());
+
+        let size = rng.below(MAX_CONTENT_SIZE + 1);
+        let content = (0..size).map(|_| rng.next() as u8).collect::<Vec<_>>();
+        let context = format!("case {case} of seed {seed} ({path})");
+
+        driver
+            .write(&path, content.as_slice())
+            .await
+            .unwrap_or_else(|err| panic!("{context}: write failed: {err}"));
+        assert!(
+            driver.list(&directory).await.unwrap().contains(&path),
+            "{context}: file should be listed under its path"
+        );
+        assert_eq!(
+            driver.read_bytes(&path).await.unwrap(),
+            content,
+            "{context}: invalid content"
+        );
+        assert_eq!(
+            driver.size(&path).await.unwrap(),
+            size,
+            "{context}: invalid size"
+        );
+
+        let start = rng.below(size + 1);
+        let end = start + rng.below(size - start + 1);
+        assert_eq!(
+            driver.read_range(&path, start..end).await.unwrap(),
+            &content[usize::try_from(start).unwrap()..usize::try_from(end).unwrap()],
+            "{context}: invalid range {start}..{end}"
+        );
+
+        assert!(
+            driver.delete_if_exists(&path).await.unwrap(),
+            "{context}: file should be deleted"
+        );
+        assert!(
+            !driver.file_exists(&path).await.unwrap(),
+            "{context}: file should not exist after deletion"
+        );
+    }
+}
+
+/// A xorshift64* generator, deterministic for a given seed.
+struct Rng(u64);
+
+impl Rng {
+    const fn new(seed: u64) -> Self {
+        // the state must never be zero
+        Self(seed ^ 0x9E37_79B9_7F4A_7C15)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns a number in `0..bound`, or `0` when the bound is `0`.
+    fn below(&mut self, bound: u64) -> u64 {
+        if bound == 0 {
+            0
+        } else {
+            self.next() % bound
+        }
+    }
+
+    /// Returns a path segment of 1 to 12 characters, with special characters
+    /// in a quarter of the segments.
+    fn segment(&mut self) -> String {
+        let special = self.below(4) == 0;
+        let mut segment: String = (0..=self.below(12))
+            .map(|_| {
+                let index = usize::try_from(self.below(PATH_CHARACTERS.len() as u64)).unwrap();
+                char::from(PATH_CHARACTERS[index])
+            })
+            .collect();
+        if special {
+            for _ in 0..=self.below(3) {
+                let index =
+                    usize::try_from(self.below(SPECIAL_PATH_CHARACTERS.len() as u64)).unwrap();
+                let at = segment.chars().count() as u64 + 1;
+                let at = segment
+                    .char_indices()
+                    .map(|(at, _)| at)
+                    .nth(usize::try_from(self.below(at)).unwrap())
+                    .unwrap_or(segment.len());
+                segment.insert_str(at, SPECIAL_PATH_CHARACTERS[index]);
+            }
+        }
+        segment
+    }
+}
@@ -1,23 +1,77 @@
 use std::{
-    path::{Path, PathBuf},
-    str::FromStr,
-    time::SystemTime,
+    collections::BTreeMap,
+    ops::Range,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use async_trait::async_trait;
+use aws_credential_types::provider::{error::CredentialsError, future, ProvideCredentials};
 use aws_sdk_s3::{
-    config::Credentials,
-    error::SdkError,
+    config::{
+        interceptors::{BeforeTransmitInterceptorContextMut, InterceptorContext},
+        retry::{ClassifyRetry, RetryAction},
+        ConfigBag, Credentials, IdentityCache, Intercept, RuntimeComponents,
+    },
+    error::{ProvideErrorMetadata, SdkError},
+    operation::RequestId,
+    presigning::PresigningConfig,
     primitives::ByteStream,
-    types::{Delete, ObjectIdentifier},
+    types::{
+        BucketCannedAcl, BucketLifecycleConfiguration, BucketLocationConstraint,
+        CompletedMultipartUpload, CompletedPart, CreateBucketConfiguration, Delete,
+        ExpirationStatus, LifecycleExpiration, LifecycleRule, LifecycleRuleFilter, ObjectCannedAcl,
+        ObjectIdentifier, ObjectLockLegalHold, ObjectLockLegalHoldStatus, ServerSideEncryption,
+        StorageClass,
+    },
     Client,
 };
+use aws_smithy_http_client::tls;
+use aws_smithy_runtime_api::{
+    box_error::BoxError,
+    client::{http::SharedHttpClient, orchestrator::HttpRequest},
+};
+use aws_smithy_types::retry::ErrorKind;
 use aws_types::region::Region;
+use bytes::Bytes;
+use futures::{stream, StreamExt};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+use super::{
+    capabilities::{Capabilities, ObjectVersion, PresignExt, VersioningExt},
+    directory_key, validate_headers, Acl, BlobType, ContainerOptions, CredentialsProvider, Driver,
+    DriverError, DriverResult, Encryption, ObjectMetadata, PoolOptions, Precondition, ReadOptions,
+    RetentionPolicy, WriteOptions, MTIME_METADATA_KEY,
+};
+use crate::{
+    contents::Contents,
+    correlation::{self, CORRELATION_ID_HEADER},
+    errors::retry_after,
+    hex,
+    key::{KeyRules, ObjectPath},
+    upload::{PendingUpload, UploadPart},
+};
 
-use super::{Driver, DriverError, DriverResult};
-use crate::contents::Contents;
+/// The maximum number of keys a `DeleteObjects` request accepts.
+const DELETE_BATCH_SIZE: usize = 1000;
+
+/// The number of `DeleteObjects` requests sent concurrently by
+/// `delete_directory`.
+const DELETE_CONCURRENCY: usize = 4;
+
+/// The header acknowledging that the requester pays the request and data
+/// transfer costs.
+const REQUEST_PAYER_HEADER: &str = "x-amz-request-payer";
+
+/// Characters left unencoded in custom query parameters.
+const QUERY_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
 
 /// Configuration parameters for initializing an `AwsS3` driver instance.
+#[derive(Clone)]
 pub struct Config {
     /// The name of the S3 bucket .
     pub bucket: String,
@@ -25,15 +79,60 @@ pub struct Config {
     pub region: String,
     /// Optional credentials for authenticating with the AWS S3 service.
     pub credentials: Option<ClientCredentials>,
+    /// Optional provider of rotating credentials, taking precedence over
+    /// `credentials`. Requests rejected with expired or invalid credentials
+    /// are retried with fresh ones.
+    pub credentials_provider: Option<Arc<dyn CredentialsProvider<ClientCredentials>>>,
+    /// Whether requests are sent unsigned, to read public buckets such as
+    /// open datasets without credentials. Takes precedence over
+    /// `credentials` and `credentials_provider`.
+    pub anonymous: bool,
+    /// Optional base URL public files are served from, e.g. a `CloudFront`
+    /// distribution or an S3 website endpoint. Defaults to the bucket under
+    /// the read or write endpoint when one is configured, and to the bucket
+    /// virtual hosted URL otherwise.
+    pub public_url: Option<String>,
+    /// Whether the requester pays the request and data transfer costs, as
+    /// required by requester pays buckets, e.g. public datasets. S3 denies
+    /// requests to those buckets that don't acknowledge the charges.
+    pub requester_pays: bool,
+    /// Whether requests go through the S3 Transfer Acceleration endpoint,
+    /// which routes them over the AWS edge network. The bucket must have
+    /// acceleration enabled, and it can't be combined with custom endpoints.
+    pub accelerate: bool,
+    /// Optional endpoint object downloads and presigned read URLs are sent
+    /// to, e.g. a regional endpoint close to the readers. Defaults to the
+    /// bucket endpoint.
+    pub read_endpoint: Option<String>,
+    /// Optional endpoint every other request is sent to. Defaults to the
+    /// bucket endpoint.
+    pub write_endpoint: Option<String>,
+    /// The options of the HTTP connection pool, shared by the read and write
+    /// clients.
+    pub pool: PoolOptions,
 }
 
 /// Credentials for authenticating with the AWS S3 service.
+#[derive(Clone)]
 pub struct ClientCredentials {
     pub access_key: String,
     pub secret_key: String,
     pub session_token: Option<String>,
 }
 
+impl ClientCredentials {
+    /// Converts the credentials into SDK credentials.
+    fn into_sdk(self) -> Credentials {
+        Credentials::new(
+            self.access_key,
+            self.secret_key,
+            self.session_token,
+            None,
+            "active-store",
+        )
+    }
+}
+
 /// The `AwsS3` struct represents an S3-based implementation of the `Driver`
 /// trait.
 ///
@@ -43,8 +142,16 @@ pub struct ClientCredentials {
 pub struct AwsS3 {
     /// The S3 client used for communication with the AWS service.
     client: Client,
+    /// The S3 client object downloads are sent with, bound to the read
+    /// endpoint.
+    read_client: Client,
     /// The name of the S3 bucket.
     bucket: String,
+    /// The base URL public files are served from.
+    public_url: Option<String>,
+    /// The custom endpoint public URLs default to, addressing the bucket by
+    /// path.
+    endpoint: Option<String>,
 }
 
 impl AwsS3 {
@@ -55,25 +162,50 @@ impl AwsS3 {
     /// A `Result` containing the initialized `AwsS3`.
     #[must_use]
     pub fn new(config: Config) -> Self {
+        // acceleration endpoints only support virtual hosted addressing
         let mut client_builder = aws_sdk_s3::Config::builder()
-            .force_path_style(true)
+            .force_path_style(!config.accelerate)
+            .accelerate(config.accelerate)
             .region(Region::new(config.region));
 
-        if let Some(credentials) = config.credentials {
-            let cred = Credentials::new(
-                credentials.access_key,
-                credentials.secret_key,
-                credentials.session_token,
-                None,
-                "active-store",
-            );
+        if config.anonymous {
+            client_builder = client_builder.allow_no_auth();
+        } else if let Some(provider) = config.credentials_provider {
+            // credentials are cached by the provider, and must be requested
+            // again when retrying a rejected request
+            client_builder = client_builder
+                .credentials_provider(ProviderCredentials(provider.clone()))
+                .identity_cache(IdentityCache::no_cache())
+                .retry_classifier(Reauthenticate(provider));
+        } else if let Some(credentials) = config.credentials {
+            client_builder = client_builder.credentials_provider(credentials.into_sdk());
+        }
+        client_builder = client_builder.interceptor(CorrelationId);
+        if config.requester_pays {
+            client_builder = client_builder.interceptor(RequesterPays);
+        }
+        if config.pool.max_idle_per_host.is_some() || config.pool.idle_timeout.is_some() {
+            client_builder = client_builder.http_client(http_client(&config.pool));
+        }
 
-            client_builder = client_builder.credentials_provider(cred);
+        let endpoint = config
+            .read_endpoint
+            .clone()
+            .or_else(|| config.write_endpoint.clone());
+        let mut read_builder = client_builder.clone();
+        if let Some(endpoint) = config.read_endpoint {
+            read_builder = read_builder.endpoint_url(endpoint);
+        }
+        if let Some(endpoint) = config.write_endpoint {
+            client_builder = client_builder.endpoint_url(endpoint);
         }
 
         Self {
             bucket: config.bucket,
             client: Client::from_conf(client_builder.build()),
+            read_client: Client::from_conf(read_builder.build()),
+            public_url: config.public_url,
+            endpoint,
         }
     }
 
@@ -82,11 +214,48 @@ impl AwsS3 {
     #[must_use]
     pub fn with_client(client: Client, bucket: &str) -> Self {
         Self {
+            read_client: client.clone(),
             client,
             bucket: bucket.to_string(),
+            public_url: None,
+            endpoint: None,
         }
     }
 
+    /// Sends object downloads and presigned read URLs with the given client,
+    /// e.g. one bound to an endpoint close to the readers.
+    #[must_use]
+    pub fn with_read_client(mut self, client: Client) -> Self {
+        self.read_client = client;
+        self
+    }
+
+    /// Acknowledges that the requester pays the request and data transfer
+    /// costs on every request, as required by requester pays buckets.
+    #[must_use]
+    pub fn with_requester_pays(mut self) -> Self {
+        let with_interceptor = |client: &Client| {
+            Client::from_conf(
+                client
+                    .config()
+                    .to_builder()
+                    .interceptor(RequesterPays)
+                    .build(),
+            )
+        };
+        self.client = with_interceptor(&self.client);
+        self.read_client = with_interceptor(&self.read_client);
+        self
+    }
+
+    /// Sets the base URL public files are served from, e.g. a `CloudFront`
+    /// distribution or an S3 website endpoint.
+    #[must_use]
+    pub fn with_public_url(mut self, public_url: &str) -> Self {
+        self.public_url = Some(public_url.to_string());
+        self
+    }
+
     /// Get all files in a specified path on S3.
     ///
     /// # Errors
@@ -95,18 +264,14 @@ impl AwsS3 {
     ///
     /// # Returns
     ///
-    /// A `Result` containing a vector of `PathBuf` representing the file paths,
-    /// or an error.
-    async fn get_all_files_in_path(&self, path: &Path) -> DriverResult<Vec<std::path::PathBuf>> {
+    /// A `Result` containing a vector of `ObjectPath` representing the file
+    /// paths, or an error.
+    async fn get_all_files_in_path(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
         let mut paths = Vec::new();
-        let request = self
-            .client
-            .list_objects_v2()
-            .bucket(&self.bucket)
-            .prefix(format!(
-                "{}/",
-                path.to_str().ok_or(DriverError::InvalidPath)?
-            ));
+        let mut request = self.client.list_objects_v2().bucket(&self.bucket);
+        if let Some(prefix) = path.to_prefix()? {
+            request = request.prefix(format!("{}/", prefix.encode(KeyRules::AwsS3)?));
+        }
 
         let mut response = request.into_paginator().send();
 
@@ -120,12 +285,64 @@ impl AwsS3 {
                 contents
                     .iter()
                     .filter_map(|content| content.key())
-                    .map(|s| PathBuf::from_str(s).unwrap()),
+                    .map(|key| super::decode_key(key, KeyRules::AwsS3)),
             );
         }
 
         Ok(paths)
     }
+
+    /// Deletes a batch of at most [`DELETE_BATCH_SIZE`] objects with a single
+    /// `DeleteObjects` request, returning the keys S3 failed to delete with
+    /// their error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request itself fails.
+    async fn delete_batch(&self, paths: Vec<ObjectPath>) -> DriverResult<BTreeMap<String, String>> {
+        let objects = paths
+            .iter()
+            .map(|path| {
+                ObjectIdentifier::builder()
+                    .key(object_key(path)?)
+                    .build()
+                    .map_err(|err| DriverError::Any(Box::new(err)))
+            })
+            .collect::<DriverResult<Vec<_>>>()?;
+        let delete = Delete::builder()
+            .set_objects(Some(objects))
+            .quiet(true)
+            .build()
+            .map_err(|err| DriverError::Any(Box::new(err)))?;
+
+        let output = self
+            .client
+            .delete_objects()
+            .bucket(&self.bucket)
+            .delete(delete)
+            .send()
+            .await?;
+
+        Ok(output
+            .errors()
+            .iter()
+            .map(|error| {
+                (
+                    error.key().unwrap_or_default().to_string(),
+                    error
+                        .message()
+                        .or_else(|| error.code())
+                        .unwrap_or("unknown error")
+                        .to_string(),
+                )
+            })
+            .collect())
+    }
+}
+
+/// Converts the given path into an S3 object key.
+fn object_key(path: &ObjectPath) -> DriverResult<String> {
+    Ok(path.to_key()?.encode(KeyRules::AwsS3)?)
 }
 
 #[async_trait]
@@ -137,12 +354,29 @@ impl Driver for AwsS3 {
     ///
     /// Returns an error if there is an issue reading from the file or decoding
     /// its contents.
-    async fn read(&self, path: &Path) -> DriverResult<Vec<u8>> {
+    async fn read(&self, path: &ObjectPath) -> DriverResult<Bytes> {
+        self.read_with_options(path, &ReadOptions::default()).await
+    }
+
+    /// Reads the object, sending the custom headers and query parameters of
+    /// the options with the `GET` request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a custom header is invalid, or the object does not
+    /// exist or could not be read.
+    async fn read_with_options(
+        &self,
+        path: &ObjectPath,
+        options: &ReadOptions,
+    ) -> DriverResult<Bytes> {
         let request = match self
-            .client
+            .read_client
             .get_object()
             .bucket(&self.bucket)
-            .key(path.to_str().ok_or(DriverError::InvalidPath)?)
+            .key(object_key(path)?)
+            .customize()
+            .mutate_request(passthrough(&options.headers, &options.query_params)?)
             .send()
             .await
         {
@@ -158,6 +392,46 @@ impl Driver for AwsS3 {
             .into())
     }
 
+    /// Reads the object with a `GET` request conditioned on
+    /// `If-Modified-Since`, returning `None` when S3 answers `304 Not
+    /// Modified`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the object does not exist or could not be read.
+    async fn read_if_modified_since(
+        &self,
+        path: &ObjectPath,
+        since: SystemTime,
+    ) -> DriverResult<Option<Bytes>> {
+        let response = match self
+            .read_client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(object_key(path)?)
+            .if_modified_since(aws_smithy_types::DateTime::from(since))
+            .send()
+            .await
+        {
+            Ok(response) => response,
+            Err(error)
+                if error
+                    .raw_response()
+                    .is_some_and(|response| response.status().as_u16() == 304) =>
+            {
+                return Ok(None);
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        Ok(Some(
+            Contents::from_bytestream(response.body)
+                .await
+                .map_err(|_| DriverError::DecodeError)?
+                .into(),
+        ))
+    }
+
     /// Checks if a file exists at the specified path within the AWS S3 storage.
     ///
     /// If the path does not point to a file, the method returns `Ok(false)`.
@@ -167,26 +441,8 @@ impl Driver for AwsS3 {
     ///
     /// Returns an error if there is an issue checking the existence of the
     /// file.
-    async fn file_exists(&self, path: &Path) -> DriverResult<bool> {
-        let request = self
-            .client
-            .head_object()
-            .bucket(&self.bucket)
-            .key(path.to_str().ok_or(DriverError::InvalidPath)?)
-            .send()
-            .await;
-
-        match request {
-            Ok(_) => Ok(true),
-            Err(SdkError::ServiceError(error)) => {
-                if error.err().is_not_found() {
-                    return Ok(false);
-                }
-
-                Err(SdkError::ServiceError(error).into())
-            }
-            Err(e) => Err(e.into()),
-        }
+    async fn file_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        Ok(self.stat(path).await?.is_some())
     }
 
     /// Writes the provided content to a file at the specified path within the
@@ -199,12 +455,12 @@ impl Driver for AwsS3 {
     ///
     /// Returns an error if there is any issue creating directories or writing
     /// to the file
-    async fn write(&self, path: &Path, content: Vec<u8>) -> DriverResult<()> {
+    async fn write(&self, path: &ObjectPath, content: Bytes) -> DriverResult<()> {
         match self
             .client
             .put_object()
             .bucket(&self.bucket)
-            .key(path.to_str().ok_or(DriverError::InvalidPath)?)
+            .key(object_key(path)?)
             .body(ByteStream::from(content))
             .send()
             .await
@@ -214,25 +470,129 @@ impl Driver for AwsS3 {
         }
     }
 
-    /// Deletes the file at the specified path within the AWS S3 storage.
+    /// Writes the object with the given options. Every option is supported,
+    /// and preconditions are checked atomically by S3.
     ///
     /// # Errors
     ///
-    /// Returns an error if the file does not exist or if there is any issue
-    /// deleting the file.
+    /// Returns [`DriverError::PreconditionFailed`] if the precondition is not
+    /// satisfied, or an error if the object could not be written, e.g. when
+    /// an ACL is set and the bucket has ACLs disabled.
+    async fn write_with_options(
+        &self,
+        path: &ObjectPath,
+        content: Bytes,
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        if matches!(
+            options.blob_type,
+            Some(BlobType::AppendBlob | BlobType::PageBlob)
+        ) {
+            return Err(DriverError::Unsupported("append and page blobs"));
+        }
+        let mut request = self
+            .client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(object_key(path)?)
+            .set_content_type(options.content_type.clone())
+            .set_cache_control(options.cache_control.clone())
+            .set_storage_class(options.storage_class.as_deref().map(StorageClass::from))
+            .set_acl(options.acl.as_ref().map(object_canned_acl))
+            .body(ByteStream::from(content));
+
+        let mut metadata = options.metadata.clone();
+        if let Some(last_modified) = options.last_modified {
+            let mtime = last_modified
+                .duration_since(UNIX_EPOCH)
+                .map_err(|err| DriverError::Any(Box::new(err)))?;
+            metadata.insert(MTIME_METADATA_KEY.to_string(), mtime.as_secs().to_string());
+        }
+        if !metadata.is_empty() {
+            request = request.set_metadata(Some(metadata.into_iter().collect()));
+        }
+
+        request = match &options.encryption {
+            Some(Encryption::Managed) => {
+                request.server_side_encryption(ServerSideEncryption::Aes256)
+            }
+            Some(Encryption::Kms { key_id }) => request
+                .server_side_encryption(ServerSideEncryption::AwsKms)
+                .set_ssekms_key_id(key_id.clone()),
+            None => request,
+        };
+
+        request = match &options.precondition {
+            Some(Precondition::IfNotExists) => request.if_none_match("*"),
+            Some(Precondition::IfMatch(etag)) => request.if_match(etag),
+            None => request,
+        };
+
+        match request
+            .customize()
+            .mutate_request(passthrough(&options.headers, &options.query_params)?)
+            .send()
+            .await
+        {
+            Ok(_put) => Ok(()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Writes the object with the given canned ACL.
     ///
-    /// If the file does not exist, the error variant
-    /// `DriverError::ResourceNotFound` is returned.
-    async fn delete(&self, path: &Path) -> DriverResult<()> {
-        if !self.file_exists(path).await? {
-            return Err(DriverError::ResourceNotFound);
+    /// # Errors
+    ///
+    /// Returns an error if the object could not be written, e.g. when the
+    /// bucket has ACLs disabled.
+    async fn write_with_acl(
+        &self,
+        path: &ObjectPath,
+        content: Bytes,
+        acl: &Acl,
+    ) -> DriverResult<()> {
+        self.write_with_options(
+            path,
+            content,
+            &WriteOptions::default().with_acl(acl.clone()),
+        )
+        .await
+    }
+
+    /// Replaces the object ACL with the given canned ACL.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the object does not exist or the bucket has ACLs
+    /// disabled.
+    async fn set_acl(&self, path: &ObjectPath, acl: &Acl) -> DriverResult<()> {
+        match self
+            .client
+            .put_object_acl()
+            .bucket(&self.bucket)
+            .key(object_key(path)?)
+            .acl(object_canned_acl(acl))
+            .send()
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(error) => Err(error.into()),
         }
+    }
 
+    /// Deletes the file at the specified path within the AWS S3 storage with
+    /// a single `DeleteObject` request. S3 deletes are idempotent, so
+    /// deleting a missing file succeeds.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is any issue deleting the file.
+    async fn delete(&self, path: &ObjectPath) -> DriverResult<()> {
         if let Err(err) = self
             .client
             .delete_object()
             .bucket(&self.bucket)
-            .key(path.to_str().ok_or(DriverError::InvalidPath)?)
+            .key(object_key(path)?)
             .send()
             .await
         {
@@ -242,6 +602,21 @@ impl Driver for AwsS3 {
         Ok(())
     }
 
+    /// Deletes the file when it exists. As S3 doesn't report whether a
+    /// deleted object existed, the object is looked up first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file could not be looked up or deleted.
+    async fn delete_if_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        if self.stat(path).await?.is_none() {
+            return Ok(false);
+        }
+
+        self.delete(path).await?;
+        Ok(true)
+    }
+
     /// Deletes all the files under the given path within the AWS S3 storage.
     ///
     /// # Errors
@@ -251,40 +626,29 @@ impl Driver for AwsS3 {
     ///
     /// If the files not found under the given path, the error variant
     /// `DriverError::DirectoryNotFound` is returned.
-    async fn delete_directory(&self, path: &Path) -> DriverResult<()> {
+    async fn delete_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        directory_key(path)?;
         let paths_to_delete = self.get_all_files_in_path(path).await?;
 
         if paths_to_delete.is_empty() {
             return Err(DriverError::ResourceNotFound);
         }
 
-        if let Err(err) = self
-            .client
-            .delete_objects()
-            .bucket(&self.bucket)
-            .delete(
-                Delete::builder()
-                    .set_objects(Some(
-                        paths_to_delete
-                            .iter()
-                            .map(|path| {
-                                ObjectIdentifier::builder()
-                                    .key(path.to_str().unwrap().to_string())
-                                    .build()
-                                    .unwrap()
-                            })
-                            .collect(),
-                    ))
-                    .build()
-                    .unwrap(),
-            )
-            .send()
-            .await
-        {
-            return Err(err.into());
+        let mut batches =
+            stream::iter(paths_to_delete.chunks(DELETE_BATCH_SIZE).map(<[_]>::to_vec))
+                .map(|batch| self.delete_batch(batch))
+                .buffer_unordered(DELETE_CONCURRENCY);
+
+        let mut failed = BTreeMap::new();
+        while let Some(result) = batches.next().await {
+            failed.extend(result?);
         }
 
-        Ok(())
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(DriverError::DeleteFailed(failed))
+        }
     }
 
     /// Retrieves the last modification time of the file at the specified path
@@ -295,188 +659,1422 @@ impl Driver for AwsS3 {
     ///
     /// If the file does not exist, the error variant
     /// `DriverError::ResourceNotFound` is returned.
-    async fn last_modified(&self, path: &Path) -> DriverResult<SystemTime> {
-        let response = self
+    async fn last_modified(&self, path: &ObjectPath) -> DriverResult<SystemTime> {
+        self.stat(path)
+            .await?
+            .map(|metadata| metadata.last_modified)
+            .ok_or(DriverError::ResourceNotFound)
+    }
+
+    /// Returns the object size from a `HEAD` request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the object does not exist.
+    async fn size(&self, path: &ObjectPath) -> DriverResult<u64> {
+        self.stat(path)
+            .await?
+            .map(|metadata| metadata.size)
+            .ok_or(DriverError::ResourceNotFound)
+    }
+
+    /// Returns the object metadata from a single `HEAD` request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    async fn stat(&self, path: &ObjectPath) -> DriverResult<Option<ObjectMetadata>> {
+        let response = match self
             .client
             .head_object()
             .bucket(&self.bucket)
-            .key(path.to_str().ok_or(DriverError::InvalidPath)?)
+            .key(object_key(path)?)
             .send()
-            .await;
+            .await
+        {
+            Ok(response) => response,
+            Err(SdkError::ServiceError(error)) if error.err().is_not_found() => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
 
-        match response {
-            Ok(response) => Ok(SystemTime::try_from(
-                response
+        let size = response
+            .content_length()
+            .and_then(|length| u64::try_from(length).ok())
+            .unwrap_or_default();
+        // a preserved modification time takes precedence over the write time
+        let mtime = response
+            .metadata()
+            .and_then(|metadata| metadata.get(MTIME_METADATA_KEY))
+            .and_then(|mtime| mtime.parse().ok())
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+        let last_modified = match mtime {
+            Some(mtime) => mtime,
+            None => {
+                let last_modified = response
                     .last_modified
-                    .ok_or(DriverError::Any("last modify is missing".into()))?,
-            )
-            .map_err(Box::from)?),
-            Err(e) => Err(e.into()),
+                    .ok_or(DriverError::Any("last modify is missing".into()))?;
+                SystemTime::try_from(last_modified).map_err(Box::from)?
+            }
+        };
+        let content_md5 = etag_md5(
+            response.e_tag(),
+            response.server_side_encryption(),
+            response.sse_customer_algorithm().is_some(),
+        );
+        Ok(Some(ObjectMetadata {
+            size,
+            last_modified,
+            content_type: response.content_type,
+            etag: response.e_tag,
+            storage_class: response
+                .storage_class
+                .map(|storage_class| storage_class.as_str().to_string()),
+            content_md5,
+        }))
+    }
+
+    /// Reads the given byte range of the object with a ranged `GET` request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the object does not exist or could not be read.
+    async fn read_range(&self, path: &ObjectPath, range: Range<u64>) -> DriverResult<Vec<u8>> {
+        if range.is_empty() {
+            return Ok(Vec::new());
         }
+
+        let request = match self
+            .read_client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(object_key(path)?)
+            .range(format!("bytes={}-{}", range.start, range.end - 1))
+            .send()
+            .await
+        {
+            Ok(request) => request,
+            Err(error) => return Err(error.into()),
+        };
+
+        Ok(Contents::from_bytestream(request.body)
+            .await
+            .map_err(|_| DriverError::DecodeError)?
+            .into())
     }
-}
 
-// Errors conventions
-type AwsApiError<T> = aws_smithy_runtime_api::client::result::SdkError<
-    T,
-    aws_smithy_runtime_api::client::orchestrator::HttpResponse,
->;
+    /// Returns every capability. Versioning must also be enabled on the
+    /// bucket for previous versions to be kept.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::all()
+    }
 
-impl From<AwsApiError<aws_sdk_s3::operation::get_object::GetObjectError>> for DriverError {
-    fn from(kind: AwsApiError<aws_sdk_s3::operation::get_object::GetObjectError>) -> Self {
-        match kind {
-            aws_smithy_runtime_api::client::result::SdkError::ConstructionFailure(_)
-            | aws_smithy_runtime_api::client::result::SdkError::TimeoutError(_)
-            | aws_smithy_runtime_api::client::result::SdkError::DispatchFailure(_) => {
-                Self::Network()
-            }
-            aws_smithy_runtime_api::client::result::SdkError::ResponseError(err) => {
-                let raw = err.raw();
-                if raw.status().as_u16() == 404 {
-                    Self::ResourceNotFound
-                } else {
-                    Self::Network()
-                }
-            }
-            aws_smithy_runtime_api::client::result::SdkError::ServiceError(err) => {
-                match err.err() {
-                    aws_sdk_s3::operation::get_object::GetObjectError::NoSuchKey(_) => {
-                        Self::ResourceNotFound
-                    }
-                    _ => Self::Any(err.err().to_string().into()),
-                }
-            }
-            _ => Self::Any(Box::new(kind) as Box<_>),
-        }
+    fn as_presign(&self) -> Option<&dyn PresignExt> {
+        Some(self)
     }
-}
 
-impl From<AwsApiError<aws_sdk_s3::operation::head_object::HeadObjectError>> for DriverError {
-    fn from(kind: AwsApiError<aws_sdk_s3::operation::head_object::HeadObjectError>) -> Self {
-        match kind {
-            aws_smithy_runtime_api::client::result::SdkError::ConstructionFailure(_)
-            | aws_smithy_runtime_api::client::result::SdkError::TimeoutError(_)
-            | aws_smithy_runtime_api::client::result::SdkError::DispatchFailure(_) => {
-                Self::Network()
-            }
-            aws_smithy_runtime_api::client::result::SdkError::ResponseError(err) => {
-                let raw = err.raw();
-                if raw.status().as_u16() == 404 {
-                    Self::ResourceNotFound
-                } else {
-                    Self::Network()
-                }
-            }
-            aws_smithy_runtime_api::client::result::SdkError::ServiceError(err) => {
-                match err.err() {
-                    aws_sdk_s3::operation::head_object::HeadObjectError::NotFound(_) => {
-                        Self::ResourceNotFound
-                    }
-                    _ => Self::Any(err.err().to_string().into()),
-                }
-            }
-            _ => Self::Any(Box::new(kind)),
-        }
+    fn as_versioning(&self) -> Option<&dyn VersioningExt> {
+        Some(self)
     }
-}
 
-impl From<AwsApiError<aws_sdk_s3::operation::put_object::PutObjectError>> for DriverError {
-    fn from(kind: AwsApiError<aws_sdk_s3::operation::put_object::PutObjectError>) -> Self {
-        match kind {
-            aws_smithy_runtime_api::client::result::SdkError::ConstructionFailure(_)
-            | aws_smithy_runtime_api::client::result::SdkError::TimeoutError(_)
-            | aws_smithy_runtime_api::client::result::SdkError::DispatchFailure(_) => {
-                Self::Network()
-            }
-            aws_smithy_runtime_api::client::result::SdkError::ResponseError(err) => {
-                let raw = err.raw();
-                if raw.status().as_u16() == 404 {
-                    Self::ResourceNotFound
-                } else {
-                    Self::Network()
-                }
-            }
-            aws_smithy_runtime_api::client::result::SdkError::ServiceError(err) => {
-                Self::Any(err.err().to_string().into())
+    /// Returns the public URL of the file, under the configured public URL,
+    /// the bucket under the configured endpoint, or the bucket virtual hosted
+    /// URL.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is invalid or no public URL or endpoint is
+    /// configured and the client has no region.
+    async fn public_url(&self, path: &ObjectPath) -> DriverResult<String> {
+        let base_url = match (&self.public_url, &self.endpoint) {
+            (Some(public_url), _) => public_url.clone(),
+            (None, Some(endpoint)) => format!("{}/{}", endpoint.trim_end_matches('/'), self.bucket),
+            (None, None) => {
+                let region = self
+                    .client
+                    .config()
+                    .region()
+                    .ok_or(DriverError::Unsupported("public url"))?;
+                format!("https://{}.s3.{region}.amazonaws.com", self.bucket)
             }
-            _ => Self::Any(Box::new(kind)),
+        };
+
+        super::join_url(&base_url, path, KeyRules::AwsS3)
+    }
+
+    /// Lists all the files under the given path within the AWS S3 storage.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is an issue listing s3 data.
+    async fn list(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        self.get_all_files_in_path(path).await
+    }
+
+    /// Checks whether the bucket exists with a `HEAD` request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bucket is not reachable with the configured
+    /// credentials.
+    async fn container_exists(&self) -> DriverResult<bool> {
+        match self.health_check().await {
+            Ok(()) => Ok(true),
+            Err(DriverError::ResourceNotFound) => Ok(false),
+            Err(err) => Err(err),
         }
     }
-}
 
-impl From<AwsApiError<aws_sdk_s3::operation::delete_object::DeleteObjectError>> for DriverError {
-    fn from(kind: AwsApiError<aws_sdk_s3::operation::delete_object::DeleteObjectError>) -> Self {
-        match kind {
-            aws_smithy_runtime_api::client::result::SdkError::ConstructionFailure(_)
-            | aws_smithy_runtime_api::client::result::SdkError::TimeoutError(_)
-            | aws_smithy_runtime_api::client::result::SdkError::DispatchFailure(_) => {
-                Self::Network()
-            }
-            aws_smithy_runtime_api::client::result::SdkError::ResponseError(err) => {
-                let raw = err.raw();
-                if raw.status().as_u16() == 404 {
-                    Self::ResourceNotFound
-                } else {
-                    Self::Network()
-                }
-            }
-            aws_smithy_runtime_api::client::result::SdkError::ServiceError(err) => {
-                Self::Any(err.err().to_string().into())
+    /// Creates the bucket when it doesn't exist.
+    ///
+    /// The bucket is created in the region given by the options, falling back
+    /// to the client region.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bucket could not be created.
+    async fn ensure_container(&self, options: &ContainerOptions) -> DriverResult<()> {
+        if self.container_exists().await? {
+            return Ok(());
+        }
+
+        let region = options
+            .region
+            .clone()
+            .or_else(|| self.client.config().region().map(ToString::to_string));
+
+        let mut request = self.client.create_bucket().bucket(&self.bucket);
+
+        // `us-east-1` is the default location and must not be sent as a
+        // location constraint.
+        if let Some(region) = region.filter(|region| region != "us-east-1") {
+            request = request.create_bucket_configuration(
+                CreateBucketConfiguration::builder()
+                    .location_constraint(BucketLocationConstraint::from(region.as_str()))
+                    .build(),
+            );
+        }
+
+        if let Some(acl) = &options.acl {
+            request = request.acl(match acl {
+                Acl::Private => BucketCannedAcl::Private,
+                Acl::PublicRead => BucketCannedAcl::PublicRead,
+            });
+        }
+
+        match request.send().await {
+            Ok(_) => Ok(()),
+            Err(SdkError::ServiceError(error)) if error.err().is_bucket_already_owned_by_you() => {
+                Ok(())
             }
-            _ => Self::Any(Box::new(kind)),
+            Err(e) => Err(e.into()),
         }
     }
-}
 
-impl From<AwsApiError<aws_sdk_s3::operation::delete_objects::DeleteObjectsError>> for DriverError {
-    fn from(kind: AwsApiError<aws_sdk_s3::operation::delete_objects::DeleteObjectsError>) -> Self {
-        match kind {
-            aws_smithy_runtime_api::client::result::SdkError::ConstructionFailure(_)
-            | aws_smithy_runtime_api::client::result::SdkError::TimeoutError(_)
-            | aws_smithy_runtime_api::client::result::SdkError::DispatchFailure(_) => {
-                Self::Network()
-            }
-            aws_smithy_runtime_api::client::result::SdkError::ResponseError(err) => {
-                let raw = err.raw();
-                if raw.status().as_u16() == 404 {
-                    Self::ResourceNotFound
-                } else {
-                    Self::Network()
-                }
-            }
-            aws_smithy_runtime_api::client::result::SdkError::ServiceError(err) => {
-                Self::Any(err.err().to_string().into())
-            }
-            _ => Self::Any(Box::new(kind)),
+    /// Probes the bucket with a `HEAD` request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bucket does not exist or is not reachable with
+    /// the configured credentials.
+    async fn health_check(&self) -> DriverResult<()> {
+        match self.client.head_bucket().bucket(&self.bucket).send().await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.into()),
         }
     }
-}
 
-impl From<AwsApiError<aws_sdk_s3::operation::list_objects_v2::ListObjectsV2Error>> for DriverError {
-    fn from(kind: AwsApiError<aws_sdk_s3::operation::list_objects_v2::ListObjectsV2Error>) -> Self {
-        match kind {
-            aws_smithy_runtime_api::client::result::SdkError::ConstructionFailure(_)
-            | aws_smithy_runtime_api::client::result::SdkError::TimeoutError(_)
-            | aws_smithy_runtime_api::client::result::SdkError::DispatchFailure(_) => {
-                Self::Network()
-            }
-            aws_smithy_runtime_api::client::result::SdkError::ResponseError(err) => {
-                let raw = err.raw();
-                if raw.status().as_u16() == 404 {
-                    Self::ResourceNotFound
-                } else {
-                    Self::Network()
-                }
+    /// Replaces the bucket lifecycle configuration with an expiration rule
+    /// per policy with a maximum retention, rounded up to whole days and
+    /// extended to the minimum retention. Minimum retentions alone need S3
+    /// Object Lock, which is configured on bucket creation, and are not
+    /// mapped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lifecycle configuration could not be replaced.
+    async fn apply_retention_policies(&self, policies: &[RetentionPolicy]) -> DriverResult<()> {
+        let rules = policies
+            .iter()
+            .filter_map(|policy| {
+                let retention = policy
+                    .max_retention?
+                    .max(policy.min_retention.unwrap_or_default());
+                Some((policy, retention))
+            })
+            .enumerate()
+            .map(|(index, (policy, retention))| {
+                let prefix = policy
+                    .prefix
+                    .to_prefix()?
+                    .map(|key| Ok::<_, DriverError>(format!("{}/", key.encode(KeyRules::AwsS3)?)))
+                    .transpose()?
+                    .unwrap_or_default();
+                let days = i32::try_from(retention.as_secs().div_ceil(24 * 60 * 60))
+                    .unwrap_or(i32::MAX)
+                    .max(1);
+                LifecycleRule::builder()
+                    .id(format!("active-storage-retention-{index}"))
+                    .filter(LifecycleRuleFilter::builder().prefix(prefix).build())
+                    .expiration(LifecycleExpiration::builder().days(days).build())
+                    .status(ExpirationStatus::Enabled)
+                    .build()
+                    .map_err(|err| DriverError::Any(Box::new(err)))
+            })
+            .collect::<DriverResult<Vec<_>>>()?;
+
+        if rules.is_empty() {
+            return match self
+                .client
+                .delete_bucket_lifecycle()
+                .bucket(&self.bucket)
+                .send()
+                .await
+            {
+                Ok(_) => Ok(()),
+                Err(e) => Err(e.into()),
+            };
+        }
+
+        let configuration = BucketLifecycleConfiguration::builder()
+            .set_rules(Some(rules))
+            .build()
+            .map_err(|err| DriverError::Any(Box::new(err)))?;
+        match self
+            .client
+            .put_bucket_lifecycle_configuration()
+            .bucket(&self.bucket)
+            .lifecycle_configuration(configuration)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Places or lifts an Object Lock legal hold on the object. The bucket
+    /// must have Object Lock enabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the object doesn't exist or the hold could not be
+    /// changed.
+    async fn set_legal_hold(&self, path: &ObjectPath, hold: bool) -> DriverResult<()> {
+        let status = if hold {
+            ObjectLockLegalHoldStatus::On
+        } else {
+            ObjectLockLegalHoldStatus::Off
+        };
+        match self
+            .client
+            .put_object_legal_hold()
+            .bucket(&self.bucket)
+            .key(object_key(path)?)
+            .legal_hold(ObjectLockLegalHold::builder().status(status).build())
+            .send()
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Returns whether the object has an Object Lock legal hold. Objects of
+    /// buckets without Object Lock are never held.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the object doesn't exist or the hold could not be
+    /// read.
+    async fn legal_hold(&self, path: &ObjectPath) -> DriverResult<bool> {
+        match self
+            .client
+            .get_object_legal_hold()
+            .bucket(&self.bucket)
+            .key(object_key(path)?)
+            .send()
+            .await
+        {
+            Ok(output) => Ok(output.legal_hold().and_then(ObjectLockLegalHold::status)
+                == Some(&ObjectLockLegalHoldStatus::On)),
+            Err(SdkError::ServiceError(error))
+                if matches!(
+                    error.err().code(),
+                    Some("InvalidRequest" | "NoSuchObjectLockConfiguration")
+                ) =>
+            {
+                Ok(false)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Checks the legal hold of every object under the prefix.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the objects could not be listed or their holds
+    /// could not be read.
+    async fn list_legal_holds(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        let held = stream::iter(self.list(path).await?)
+            .map(|file| async move {
+                let held = self.legal_hold(&file).await?;
+                Ok::<_, DriverError>(held.then_some(file))
+            })
+            .buffered(DELETE_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await;
+        held.into_iter().filter_map(Result::transpose).collect()
+    }
+
+    /// Starts an S3 multipart upload.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the multipart upload could not be created.
+    async fn create_upload(&self, path: &ObjectPath) -> DriverResult<String> {
+        self.create_upload_with_options(path, &WriteOptions::default())
+            .await
+    }
+
+    /// Starts an S3 multipart upload with the content type, cache control,
+    /// metadata, last modification time, storage class, ACL and encryption
+    /// of the given options, which S3 only accepts when the upload is
+    /// created.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the multipart upload could not be created.
+    async fn create_upload_with_options(
+        &self,
+        path: &ObjectPath,
+        options: &WriteOptions,
+    ) -> DriverResult<String> {
+        let mut request = self
+            .client
+            .create_multipart_upload()
+            .bucket(&self.bucket)
+            .key(object_key(path)?)
+            .set_content_type(options.content_type.clone())
+            .set_cache_control(options.cache_control.clone())
+            .set_storage_class(options.storage_class.as_deref().map(StorageClass::from))
+            .set_acl(options.acl.as_ref().map(object_canned_acl));
+
+        let mut metadata = options.metadata.clone();
+        if let Some(last_modified) = options.last_modified {
+            let mtime = last_modified
+                .duration_since(UNIX_EPOCH)
+                .map_err(|err| DriverError::Any(Box::new(err)))?;
+            metadata.insert(MTIME_METADATA_KEY.to_string(), mtime.as_secs().to_string());
+        }
+        if !metadata.is_empty() {
+            request = request.set_metadata(Some(metadata.into_iter().collect()));
+        }
+
+        request = match &options.encryption {
+            Some(Encryption::Managed) => {
+                request.server_side_encryption(ServerSideEncryption::Aes256)
+            }
+            Some(Encryption::Kms { key_id }) => request
+                .server_side_encryption(ServerSideEncryption::AwsKms)
+                .set_ssekms_key_id(key_id.clone()),
+            None => request,
+        };
+
+        let output = match request.send().await {
+            Ok(output) => output,
+            Err(err) => return Err(err.into()),
+        };
+
+        output
+            .upload_id()
+            .map(ToString::to_string)
+            .ok_or(DriverError::DecodeError)
+    }
+
+    /// Uploads a part of an S3 multipart upload. Every part except the last
+    /// must be at least 5 MiB.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the part could not be uploaded.
+    async fn upload_part(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        part_number: u32,
+        content: Bytes,
+    ) -> DriverResult<UploadPart> {
+        let output = match self
+            .client
+            .upload_part()
+            .bucket(&self.bucket)
+            .key(object_key(path)?)
+            .upload_id(upload_id)
+            .part_number(part_number_to_i32(part_number)?)
+            .body(ByteStream::from(content))
+            .send()
+            .await
+        {
+            Ok(output) => output,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(UploadPart {
+            part_number,
+            etag: output.e_tag().unwrap_or_default().to_string(),
+        })
+    }
+
+    /// Lists the parts of an S3 multipart upload.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the upload doesn't exist or could not be listed.
+    async fn list_upload_parts(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+    ) -> DriverResult<Vec<UploadPart>> {
+        let key = object_key(path)?;
+        let mut parts = Vec::new();
+        let mut marker = None;
+
+        loop {
+            let output = match self
+                .client
+                .list_parts()
+                .bucket(&self.bucket)
+                .key(&key)
+                .upload_id(upload_id)
+                .set_part_number_marker(marker)
+                .send()
+                .await
+            {
+                Ok(output) => output,
+                Err(err) => return Err(err.into()),
+            };
+
+            for part in output.parts() {
+                let part_number = part
+                    .part_number()
+                    .and_then(|number| u32::try_from(number).ok())
+                    .ok_or(DriverError::DecodeError)?;
+                parts.push(UploadPart {
+                    part_number,
+                    etag: part.e_tag().unwrap_or_default().to_string(),
+                });
+            }
+
+            if output.is_truncated() != Some(true) {
+                break;
+            }
+            marker = output.next_part_number_marker().map(ToString::to_string);
+        }
+
+        Ok(parts)
+    }
+
+    /// Completes an S3 multipart upload.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a part is missing or too small.
+    async fn complete_upload(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+    ) -> DriverResult<()> {
+        let mut completed_parts = Vec::with_capacity(parts.len());
+        for part in parts {
+            completed_parts.push(
+                CompletedPart::builder()
+                    .part_number(part_number_to_i32(part.part_number)?)
+                    .e_tag(&part.etag)
+                    .build(),
+            );
+        }
+
+        match self
+            .client
+            .complete_multipart_upload()
+            .bucket(&self.bucket)
+            .key(object_key(path)?)
+            .upload_id(upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(completed_parts))
+                    .build(),
+            )
+            .send()
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Completes an S3 multipart upload. The options were already applied
+    /// when the upload was created.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a part is missing or too small.
+    async fn complete_upload_with_options(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+        _options: &WriteOptions,
+    ) -> DriverResult<()> {
+        self.complete_upload(path, upload_id, parts).await
+    }
+
+    /// Lists the in progress S3 multipart uploads under the given prefix.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the uploads could not be listed.
+    async fn list_uploads(&self, prefix: &ObjectPath) -> DriverResult<Vec<PendingUpload>> {
+        let prefix = match prefix.to_prefix()? {
+            Some(prefix) => Some(format!("{}/", prefix.encode(KeyRules::AwsS3)?)),
+            None => None,
+        };
+        let mut uploads = Vec::new();
+        let mut key_marker = None;
+        let mut upload_id_marker = None;
+
+        loop {
+            let output = match self
+                .client
+                .list_multipart_uploads()
+                .bucket(&self.bucket)
+                .set_prefix(prefix.clone())
+                .set_key_marker(key_marker)
+                .set_upload_id_marker(upload_id_marker)
+                .send()
+                .await
+            {
+                Ok(output) => output,
+                Err(err) => return Err(err.into()),
+            };
+
+            for upload in output.uploads() {
+                let (Some(key), Some(upload_id)) = (upload.key(), upload.upload_id()) else {
+                    continue;
+                };
+                let initiated = upload
+                    .initiated()
+                    .ok_or(DriverError::Any("upload initiation time is missing".into()))?;
+                uploads.push(PendingUpload {
+                    path: ObjectPath::from(key),
+                    upload_id: upload_id.to_string(),
+                    initiated: SystemTime::try_from(*initiated).map_err(Box::from)?,
+                });
+            }
+
+            if output.is_truncated() != Some(true) {
+                break;
             }
-            aws_smithy_runtime_api::client::result::SdkError::ServiceError(err) => {
-                match err.err() {
-                    aws_sdk_s3::operation::list_objects_v2::ListObjectsV2Error::NoSuchBucket(_) => {
-                        Self::ResourceNotFound
-                    }
-                    _ => Self::Any(err.err().to_string().into()),
+            key_marker = output.next_key_marker().map(ToString::to_string);
+            upload_id_marker = output.next_upload_id_marker().map(ToString::to_string);
+        }
+
+        Ok(uploads)
+    }
+
+    /// Aborts an S3 multipart upload, discarding its parts.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the upload could not be aborted.
+    async fn abort_upload(&self, path: &ObjectPath, upload_id: &str) -> DriverResult<()> {
+        match self
+            .client
+            .abort_multipart_upload()
+            .bucket(&self.bucket)
+            .key(object_key(path)?)
+            .upload_id(upload_id)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[async_trait]
+impl PresignExt for AwsS3 {
+    /// Returns a presigned `GetObject` URL.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the expiry exceeds a week or the URL could not be
+    /// signed.
+    async fn presign_read(&self, path: &ObjectPath, expires_in: Duration) -> DriverResult<String> {
+        match self
+            .read_client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(object_key(path)?)
+            .presigned(presigning_config(expires_in)?)
+            .await
+        {
+            Ok(request) => Ok(request.uri().to_string()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Returns a presigned `PutObject` URL.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the expiry exceeds a week or the URL could not be
+    /// signed.
+    async fn presign_write(&self, path: &ObjectPath, expires_in: Duration) -> DriverResult<String> {
+        match self
+            .client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(object_key(path)?)
+            .presigned(presigning_config(expires_in)?)
+            .await
+        {
+            Ok(request) => Ok(request.uri().to_string()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[async_trait]
+impl VersioningExt for AwsS3 {
+    /// Lists the object versions, skipping delete markers.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the versions could not be listed.
+    async fn list_versions(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectVersion>> {
+        let key = object_key(path)?;
+        let mut versions = Vec::new();
+        let mut key_marker = None;
+        let mut version_id_marker = None;
+
+        loop {
+            let output = match self
+                .client
+                .list_object_versions()
+                .bucket(&self.bucket)
+                .prefix(&key)
+                .set_key_marker(key_marker)
+                .set_version_id_marker(version_id_marker)
+                .send()
+                .await
+            {
+                Ok(output) => output,
+                Err(err) => return Err(err.into()),
+            };
+
+            for version in output.versions() {
+                // the prefix also matches longer keys
+                if version.key() != Some(key.as_str()) {
+                    continue;
                 }
+                let (Some(version_id), Some(last_modified)) =
+                    (version.version_id(), version.last_modified())
+                else {
+                    continue;
+                };
+                versions.push(ObjectVersion {
+                    version_id: version_id.to_string(),
+                    last_modified: SystemTime::try_from(*last_modified).map_err(Box::from)?,
+                    size: version
+                        .size()
+                        .and_then(|size| u64::try_from(size).ok())
+                        .unwrap_or_default(),
+                    is_latest: version.is_latest().unwrap_or_default(),
+                });
+            }
+
+            if output.is_truncated() != Some(true) {
+                break;
+            }
+            key_marker = output.next_key_marker().map(ToString::to_string);
+            version_id_marker = output.next_version_id_marker().map(ToString::to_string);
+        }
+
+        versions.sort_by_key(|version| std::cmp::Reverse(version.last_modified));
+        Ok(versions)
+    }
+
+    /// Reads the given object version.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the version does not exist or could not be read.
+    async fn read_version(&self, path: &ObjectPath, version_id: &str) -> DriverResult<Vec<u8>> {
+        let request = match self
+            .read_client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(object_key(path)?)
+            .version_id(version_id)
+            .send()
+            .await
+        {
+            Ok(request) => request,
+            Err(error) => return Err(error.into()),
+        };
+
+        Ok(Contents::from_bytestream(request.body)
+            .await
+            .map_err(|_| DriverError::DecodeError)?
+            .into())
+    }
+
+    /// Deletes the given object version. Unlike a plain delete, no delete
+    /// marker is left behind.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the version could not be deleted.
+    async fn delete_version(&self, path: &ObjectPath, version_id: &str) -> DriverResult<()> {
+        if let Err(err) = self
+            .client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(object_key(path)?)
+            .version_id(version_id)
+            .send()
+            .await
+        {
+            return Err(err.into());
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the presigning configuration of the given expiry.
+fn presigning_config(expires_in: Duration) -> DriverResult<PresigningConfig> {
+    PresigningConfig::expires_in(expires_in).map_err(|err| DriverError::Any(Box::new(err)))
+}
+
+/// Builds the HTTP client of the S3 clients with the pool options. The
+/// client negotiates HTTP/2 with the endpoint, so `http1_only` is ignored.
+fn http_client(pool: &PoolOptions) -> SharedHttpClient {
+    let mut builder = aws_smithy_http_client::Builder::new();
+    if let Some(max_idle_per_host) = pool.max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max_idle_per_host);
+    }
+    if let Some(idle_timeout) = pool.idle_timeout {
+        builder = builder.pool_idle_timeout(idle_timeout);
+    }
+    builder
+        .tls_provider(tls::Provider::Rustls(
+            tls::rustls_provider::CryptoMode::AwsLc,
+        ))
+        .build_https()
+}
+
+/// Adapter resolving the SDK credentials with a [`CredentialsProvider`].
+#[derive(Clone)]
+struct ProviderCredentials(Arc<dyn CredentialsProvider<ClientCredentials>>);
+
+impl std::fmt::Debug for ProviderCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProviderCredentials")
+            .finish_non_exhaustive()
+    }
+}
+
+impl ProvideCredentials for ProviderCredentials {
+    fn provide_credentials<'a>(&'a self) -> future::ProvideCredentials<'a>
+    where
+        Self: 'a,
+    {
+        future::ProvideCredentials::new(async move {
+            self.0
+                .credentials()
+                .await
+                .map(ClientCredentials::into_sdk)
+                .map_err(CredentialsError::provider_error)
+        })
+    }
+}
+
+/// Retry classifier invalidating the credentials of a [`CredentialsProvider`]
+/// when S3 rejects them, and retrying the request with fresh ones.
+struct Reauthenticate(Arc<dyn CredentialsProvider<ClientCredentials>>);
+
+impl std::fmt::Debug for Reauthenticate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Reauthenticate").finish_non_exhaustive()
+    }
+}
+
+impl ClassifyRetry for Reauthenticate {
+    fn classify_retry(&self, ctx: &InterceptorContext) -> RetryAction {
+        let Some(response) = ctx.response() else {
+            return RetryAction::NoActionIndicated;
+        };
+        let code = response.body().bytes().and_then(error_code);
+        match auth_error(response.status().as_u16(), code) {
+            Some(DriverError::AuthenticationFailed) => {
+                self.0.invalidate();
+                RetryAction::retryable_error(ErrorKind::TransientError)
             }
-            _ => Self::Any(Box::new(kind)),
+            _ => RetryAction::NoActionIndicated,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "Reauthenticate"
+    }
+}
+
+/// Returns the code of an S3 XML error response body.
+fn error_code(body: &[u8]) -> Option<&str> {
+    let body = std::str::from_utf8(body).ok()?;
+    let start = body.find("<Code>")? + "<Code>".len();
+    let end = start + body[start..].find("</Code>")?;
+    Some(&body[start..end])
+}
+
+/// Interceptor acknowledging the requester pays charges on every request.
+#[derive(Debug)]
+struct RequesterPays;
+
+impl Intercept for RequesterPays {
+    fn name(&self) -> &'static str {
+        "RequesterPays"
+    }
+
+    fn modify_before_signing(
+        &self,
+        context: &mut BeforeTransmitInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        context
+            .request_mut()
+            .headers_mut()
+            .insert(REQUEST_PAYER_HEADER, "requester");
+        Ok(())
+    }
+}
+
+/// Interceptor sending the correlation id of the current task with every
+/// request.
+#[derive(Debug)]
+struct CorrelationId;
+
+impl Intercept for CorrelationId {
+    fn name(&self) -> &'static str {
+        "CorrelationId"
+    }
+
+    fn modify_before_signing(
+        &self,
+        context: &mut BeforeTransmitInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        if let Some(correlation_id) = correlation::current() {
+            context
+                .request_mut()
+                .headers_mut()
+                .try_insert(CORRELATION_ID_HEADER, correlation_id)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns a mutation attaching the custom headers and query parameters to a
+/// request, applied before the request is signed.
+///
+/// # Errors
+///
+/// Returns an error if a custom header is not a valid HTTP header.
+fn passthrough(
+    headers: &BTreeMap<String, String>,
+    query_params: &BTreeMap<String, String>,
+) -> DriverResult<impl Fn(&mut HttpRequest) + Send + Sync + 'static> {
+    validate_headers(headers)?;
+    let headers = headers.clone();
+    let query = query_params
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "{}={}",
+                utf8_percent_encode(name, QUERY_COMPONENT),
+                utf8_percent_encode(value, QUERY_COMPONENT)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+
+    Ok(move |request: &mut HttpRequest| {
+        for (name, value) in &headers {
+            request.headers_mut().insert(name.clone(), value.clone());
+        }
+        if !query.is_empty() {
+            let separator = if request.uri().contains('?') {
+                '&'
+            } else {
+                '?'
+            };
+            let uri = format!("{}{separator}{query}", request.uri());
+            // the parameters are percent-encoded, so the URI stays valid
+            let _ = request.set_uri(uri);
+        }
+    })
+}
+
+/// Converts an access level to the S3 object canned ACL.
+const fn object_canned_acl(acl: &Acl) -> ObjectCannedAcl {
+    match acl {
+        Acl::Private => ObjectCannedAcl::Private,
+        Acl::PublicRead => ObjectCannedAcl::PublicRead,
+    }
+}
+
+/// Converts an upload part number to the S3 representation.
+fn part_number_to_i32(part_number: u32) -> DriverResult<i32> {
+    i32::try_from(part_number).map_err(|_| DriverError::InvalidPartNumber(part_number))
+}
+
+// Errors conventions
+type AwsApiError<T> = aws_smithy_runtime_api::client::result::SdkError<
+    T,
+    aws_smithy_runtime_api::client::orchestrator::HttpResponse,
+>;
+
+/// The provider name reported in [`DriverError::Backend`] errors.
+const PROVIDER: &str = "aws_s3";
+
+/// S3 error codes returned when the request credentials are missing, invalid
+/// or expired.
+const AUTHENTICATION_ERROR_CODES: &[&str] = &[
+    "InvalidAccessKeyId",
+    "SignatureDoesNotMatch",
+    "ExpiredToken",
+    "InvalidToken",
+    "TokenRefreshRequired",
+    "MissingSecurityHeader",
+];
+
+/// Maps authentication and authorization failures by HTTP status and S3 error
+/// code. Returns `None` when the failure is not auth related.
+fn auth_error(status: u16, code: Option<&str>) -> Option<DriverError> {
+    if code.is_some_and(|code| AUTHENTICATION_ERROR_CODES.contains(&code)) || status == 401 {
+        Some(DriverError::AuthenticationFailed)
+    } else if status == 403 {
+        Some(DriverError::PermissionDenied)
+    } else {
+        None
+    }
+}
+
+/// Converts an S3 SDK error into a [`DriverError`].
+///
+/// `is_not_found` identifies the operation specific modeled error that means
+/// the requested resource does not exist.
+fn from_sdk_error<E>(kind: AwsApiError<E>, is_not_found: fn(&E) -> bool) -> DriverError
+where
+    E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
+{
+    let request_id = kind.request_id().map(ToString::to_string);
+    match kind {
+        aws_smithy_runtime_api::client::result::SdkError::ConstructionFailure(_)
+        | aws_smithy_runtime_api::client::result::SdkError::TimeoutError(_)
+        | aws_smithy_runtime_api::client::result::SdkError::DispatchFailure(_) => {
+            DriverError::Network()
+        }
+        aws_smithy_runtime_api::client::result::SdkError::ResponseError(ref err) => {
+            let status = err.raw().status().as_u16();
+            let retry_after = retry_after(status, err.raw().headers().get("retry-after"));
+            match status {
+                404 => DriverError::ResourceNotFound,
+                412 => DriverError::PreconditionFailed,
+                _ => auth_error(status, None).unwrap_or_else(|| {
+                    backend_error(status, None, request_id, retry_after, Box::new(kind))
+                }),
+            }
+        }
+        aws_smithy_runtime_api::client::result::SdkError::ServiceError(err) => {
+            if is_not_found(err.err()) {
+                return DriverError::ResourceNotFound;
+            }
+
+            let status = err.raw().status().as_u16();
+            if status == 412 {
+                return DriverError::PreconditionFailed;
+            }
+            let code = err.err().code().map(ToString::to_string);
+            let retry_after = retry_after(status, err.raw().headers().get("retry-after"));
+            auth_error(status, code.as_deref()).unwrap_or_else(|| {
+                backend_error(
+                    status,
+                    code,
+                    request_id,
+                    retry_after,
+                    Box::new(err.into_err()),
+                )
+            })
+        }
+        _ => DriverError::Any(Box::new(kind)),
+    }
+}
+
+/// Builds the [`DriverError::Backend`] of a failed S3 response, keeping its
+/// status, error code, request id and `Retry-After` delay. Throttled (429)
+/// and unavailable (503) responses are retryable with the requested delay.
+fn backend_error(
+    status: u16,
+    code: Option<String>,
+    request_id: Option<String>,
+    retry_after: Option<Duration>,
+    source: Box<dyn std::error::Error + Send + Sync>,
+) -> DriverError {
+    #[cfg(feature = "tracing")]
+    correlation::record_backend_error(
+        PROVIDER,
+        Some(status),
+        code.as_deref(),
+        request_id.as_deref(),
+    );
+    DriverError::Backend {
+        provider: PROVIDER,
+        code,
+        status: Some(status),
+        request_id,
+        retry_after,
+        source,
+    }
+}
+
+impl From<AwsApiError<aws_sdk_s3::operation::get_object::GetObjectError>> for DriverError {
+    fn from(kind: AwsApiError<aws_sdk_s3::operation::get_object::GetObjectError>) -> Self {
+        from_sdk_error(
+            kind,
+            aws_sdk_s3::operation::get_object::GetObjectError::is_no_such_key,
+        )
+    }
+}
+
+impl From<AwsApiError<aws_sdk_s3::operation::list_object_versions::ListObjectVersionsError>>
+    for DriverError
+{
+    fn from(
+        kind: AwsApiError<aws_sdk_s3::operation::list_object_versions::ListObjectVersionsError>,
+    ) -> Self {
+        from_sdk_error(kind, |_| false)
+    }
+}
+
+impl From<AwsApiError<aws_sdk_s3::operation::head_object::HeadObjectError>> for DriverError {
+    fn from(kind: AwsApiError<aws_sdk_s3::operation::head_object::HeadObjectError>) -> Self {
+        from_sdk_error(
+            kind,
+            aws_sdk_s3::operation::head_object::HeadObjectError::is_not_found,
+        )
+    }
+}
+
+impl From<AwsApiError<aws_sdk_s3::operation::put_object::PutObjectError>> for DriverError {
+    fn from(kind: AwsApiError<aws_sdk_s3::operation::put_object::PutObjectError>) -> Self {
+        from_sdk_error(kind, |_| false)
+    }
+}
+
+impl From<AwsApiError<aws_sdk_s3::operation::put_object_acl::PutObjectAclError>> for DriverError {
+    fn from(kind: AwsApiError<aws_sdk_s3::operation::put_object_acl::PutObjectAclError>) -> Self {
+        from_sdk_error(
+            kind,
+            aws_sdk_s3::operation::put_object_acl::PutObjectAclError::is_no_such_key,
+        )
+    }
+}
+
+impl From<AwsApiError<aws_sdk_s3::operation::delete_object::DeleteObjectError>> for DriverError {
+    fn from(kind: AwsApiError<aws_sdk_s3::operation::delete_object::DeleteObjectError>) -> Self {
+        from_sdk_error(kind, |_| false)
+    }
+}
+
+impl From<AwsApiError<aws_sdk_s3::operation::delete_objects::DeleteObjectsError>> for DriverError {
+    fn from(kind: AwsApiError<aws_sdk_s3::operation::delete_objects::DeleteObjectsError>) -> Self {
+        from_sdk_error(kind, |_| false)
+    }
+}
+
+impl From<AwsApiError<aws_sdk_s3::operation::list_objects_v2::ListObjectsV2Error>> for DriverError {
+    fn from(kind: AwsApiError<aws_sdk_s3::operation::list_objects_v2::ListObjectsV2Error>) -> Self {
+        from_sdk_error(
+            kind,
+            aws_sdk_s3::operation::list_objects_v2::ListObjectsV2Error::is_no_such_bucket,
+        )
+    }
+}
+
+impl From<AwsApiError<aws_sdk_s3::operation::head_bucket::HeadBucketError>> for DriverError {
+    fn from(kind: AwsApiError<aws_sdk_s3::operation::head_bucket::HeadBucketError>) -> Self {
+        from_sdk_error(
+            kind,
+            aws_sdk_s3::operation::head_bucket::HeadBucketError::is_not_found,
+        )
+    }
+}
+
+impl From<AwsApiError<aws_sdk_s3::operation::create_bucket::CreateBucketError>> for DriverError {
+    fn from(kind: AwsApiError<aws_sdk_s3::operation::create_bucket::CreateBucketError>) -> Self {
+        from_sdk_error(kind, |_| false)
+    }
+}
+
+impl
+    From<
+        AwsApiError<
+            aws_sdk_s3::operation::put_bucket_lifecycle_configuration::PutBucketLifecycleConfigurationError,
+        >,
+    > for DriverError
+{
+    fn from(
+        kind: AwsApiError<
+            aws_sdk_s3::operation::put_bucket_lifecycle_configuration::PutBucketLifecycleConfigurationError,
+        >,
+    ) -> Self {
+        from_sdk_error(kind, |_| false)
+    }
+}
+
+impl From<AwsApiError<aws_sdk_s3::operation::put_object_legal_hold::PutObjectLegalHoldError>>
+    for DriverError
+{
+    fn from(
+        kind: AwsApiError<aws_sdk_s3::operation::put_object_legal_hold::PutObjectLegalHoldError>,
+    ) -> Self {
+        from_sdk_error(kind, |err| err.code() == Some("NoSuchKey"))
+    }
+}
+
+impl From<AwsApiError<aws_sdk_s3::operation::get_object_legal_hold::GetObjectLegalHoldError>>
+    for DriverError
+{
+    fn from(
+        kind: AwsApiError<aws_sdk_s3::operation::get_object_legal_hold::GetObjectLegalHoldError>,
+    ) -> Self {
+        from_sdk_error(kind, |err| err.code() == Some("NoSuchKey"))
+    }
+}
+
+impl From<AwsApiError<aws_sdk_s3::operation::delete_bucket_lifecycle::DeleteBucketLifecycleError>>
+    for DriverError
+{
+    fn from(
+        kind: AwsApiError<
+            aws_sdk_s3::operation::delete_bucket_lifecycle::DeleteBucketLifecycleError,
+        >,
+    ) -> Self {
+        from_sdk_error(kind, |_| false)
+    }
+}
+
+impl From<AwsApiError<aws_sdk_s3::operation::create_multipart_upload::CreateMultipartUploadError>>
+    for DriverError
+{
+    fn from(
+        kind: AwsApiError<
+            aws_sdk_s3::operation::create_multipart_upload::CreateMultipartUploadError,
+        >,
+    ) -> Self {
+        from_sdk_error(kind, |_| false)
+    }
+}
+
+impl From<AwsApiError<aws_sdk_s3::operation::upload_part::UploadPartError>> for DriverError {
+    fn from(kind: AwsApiError<aws_sdk_s3::operation::upload_part::UploadPartError>) -> Self {
+        from_sdk_error(kind, |err| err.code() == Some("NoSuchUpload"))
+    }
+}
+
+impl From<AwsApiError<aws_sdk_s3::operation::list_parts::ListPartsError>> for DriverError {
+    fn from(kind: AwsApiError<aws_sdk_s3::operation::list_parts::ListPartsError>) -> Self {
+        from_sdk_error(kind, |err| err.code() == Some("NoSuchUpload"))
+    }
+}
+
+impl
+    From<
+        AwsApiError<aws_sdk_s3::operation::complete_multipart_upload::CompleteMultipartUploadError>,
+    > for DriverError
+{
+    fn from(
+        kind: AwsApiError<
+            aws_sdk_s3::operation::complete_multipart_upload::CompleteMultipartUploadError,
+        >,
+    ) -> Self {
+        from_sdk_error(kind, |err| err.code() == Some("NoSuchUpload"))
+    }
+}
+
+impl From<AwsApiError<aws_sdk_s3::operation::list_multipart_uploads::ListMultipartUploadsError>>
+    for DriverError
+{
+    fn from(
+        kind: AwsApiError<aws_sdk_s3::operation::list_multipart_uploads::ListMultipartUploadsError>,
+    ) -> Self {
+        from_sdk_error(kind, |_| false)
+    }
+}
+
+impl From<AwsApiError<aws_sdk_s3::operation::abort_multipart_upload::AbortMultipartUploadError>>
+    for DriverError
+{
+    fn from(
+        kind: AwsApiError<aws_sdk_s3::operation::abort_multipart_upload::AbortMultipartUploadError>,
+    ) -> Self {
+        from_sdk_error(
+            kind,
+            aws_sdk_s3::operation::abort_multipart_upload::AbortMultipartUploadError::is_no_such_upload,
+        )
+    }
+}
+
+/// Returns the MD5 digest of the content from the `ETag` of an object. The
+/// `ETag` is the hex MD5 of the content only for objects written at once and
+/// not encrypted with KMS or a customer key.
+fn etag_md5(
+    etag: Option<&str>,
+    encryption: Option<&ServerSideEncryption>,
+    customer_key: bool,
+) -> Option<[u8; 16]> {
+    if customer_key
+        || encryption.is_some_and(|encryption| *encryption != ServerSideEncryption::Aes256)
+    {
+        return None;
+    }
+    hex::decode(etag?.trim_matches('"'))?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn config() -> Config {
+        Config {
+            bucket: "bucket".to_string(),
+            region: "us-east-1".to_string(),
+            credentials: None,
+            credentials_provider: None,
+            anonymous: true,
+            public_url: None,
+            requester_pays: false,
+            accelerate: false,
+            read_endpoint: None,
+            write_endpoint: None,
+            pool: PoolOptions::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn can_build_public_urls() {
+        let path = ObjectPath::from("docs/a b%3A.txt");
+
+        let driver = AwsS3::new(config());
+        assert_eq!(
+            driver.public_url(&path).await.unwrap(),
+            "https://bucket.s3.us-east-1.amazonaws.com/docs/a%20b%25253A.txt"
+        );
+
+        let driver = AwsS3::new(Config {
+            write_endpoint: Some("http://localhost:9000/".to_string()),
+            ..config()
+        });
+        assert_eq!(
+            driver.public_url(&path).await.unwrap(),
+            "http://localhost:9000/bucket/docs/a%20b%25253A.txt"
+        );
+
+        let driver = AwsS3::new(Config {
+            read_endpoint: Some("http://reads:9000".to_string()),
+            public_url: Some("https://cdn.example.com".to_string()),
+            ..config()
+        });
+        assert_eq!(
+            driver.public_url(&path).await.unwrap(),
+            "https://cdn.example.com/docs/a%20b%25253A.txt"
+        );
+    }
+
+    fn response_error(
+        status: u16,
+        headers: &[(&'static str, &'static str)],
+    ) -> AwsApiError<aws_sdk_s3::operation::head_object::HeadObjectError> {
+        let mut response = aws_smithy_runtime_api::http::Response::new(
+            status.try_into().unwrap(),
+            aws_smithy_types::body::SdkBody::empty(),
+        );
+        for (name, value) in headers {
+            response.headers_mut().insert(*name, *value);
         }
+        AwsApiError::response_error("unparsed response", response)
+    }
+
+    #[test]
+    fn can_map_unparsed_responses() {
+        let err = DriverError::from(response_error(
+            503,
+            &[("retry-after", "7"), ("x-amz-request-id", "request")],
+        ));
+        assert!(err.is_retryable());
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(7)));
+        assert_eq!(err.request_id(), Some("request"));
+        assert!(matches!(
+            err,
+            DriverError::Backend {
+                status: Some(503),
+                ..
+            }
+        ));
+
+        let err = DriverError::from(response_error(500, &[("retry-after", "7")]));
+        assert!(err.is_retryable());
+        assert_eq!(err.retry_after(), None);
+        assert!(matches!(
+            err,
+            DriverError::Backend {
+                status: Some(500),
+                ..
+            }
+        ));
+
+        assert!(matches!(
+            DriverError::from(response_error(404, &[])),
+            DriverError::ResourceNotFound
+        ));
+    }
+
+    #[test]
+    fn can_read_md5_from_etags() {
+        let etag = Some("\"5d41402abc4b2a76b9719d911017c592\"");
+        assert_eq!(
+            etag_md5(etag, None, false).map(Vec::from),
+            hex::decode("5d41402abc4b2a76b9719d911017c592")
+        );
+        assert!(etag_md5(etag, Some(&ServerSideEncryption::Aes256), false).is_some());
+        assert_eq!(
+            etag_md5(etag, Some(&ServerSideEncryption::AwsKms), false),
+            None
+        );
+        assert_eq!(etag_md5(etag, None, true), None);
+        assert_eq!(
+            etag_md5(Some("\"5d41402abc4b2a76b9719d911017c592-2\""), None, false),
+            None
+        );
+        assert_eq!(etag_md5(None, None, false), None);
     }
 }
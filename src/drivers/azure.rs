@@ -1,27 +1,58 @@
-use std::{
-    path::{Path, PathBuf},
-    time::SystemTime,
-};
+use std::{ops::Range, path::PathBuf, sync::Arc, time::SystemTime};
 
-use azure_storage::prelude::*;
+use azure_core::{
+    headers::CLIENT_REQUEST_ID, ClientOptions, Context, Policy, PolicyResult, Request,
+    TransportOptions,
+};
+use azure_storage::{prelude::*, CloudLocation};
 use azure_storage_blobs::{blob::operations::DeleteBlobResponse, prelude::*};
+use bytes::Bytes;
 use dyn_clone::DynClone;
-use futures::StreamExt;
+use futures::{stream, StreamExt};
+use md5::{Digest, Md5};
+
+use super::{
+    capabilities::Capabilities, check_legal_hold, check_no_legal_holds, directory_key,
+    write_with_default_options, Acl, BlobType, ContainerOptions, CredentialsProvider, Driver,
+    DriverError, ObjectMetadata, PoolOptions, WriteOptions,
+};
+use crate::{
+    correlation,
+    errors::DriverResult,
+    key::{KeyRules, ObjectPath},
+    upload::{PendingUpload, UploadPart},
+};
+
+/// The number of blobs deleted concurrently by `delete_directory`.
+const DELETE_CONCURRENCY: usize = 16;
+
+/// The largest block appended to an append blob, and the largest range of
+/// pages written to a page blob, in a single request.
+const MAX_APPEND_SIZE: usize = 4 * 1024 * 1024;
 
-use super::{Driver, DriverError};
-use crate::errors::DriverResult;
+/// The size of a page of a page blob.
+const PAGE_SIZE: u64 = 512;
 
 // Define a trait for Azure Storage client builders
 #[async_trait::async_trait]
 pub trait ClientBuilderTrait: DynClone + Sync + Send {
     async fn get_blob_content(&self, container: &str, path: &str) -> azure_core::Result<Vec<u8>>;
+    async fn get_blob_range(
+        &self,
+        container: &str,
+        path: &str,
+        range: Range<u64>,
+    ) -> azure_core::Result<Vec<u8>>;
     async fn blob_exists(&self, container: &str, path: &str) -> azure_core::Result<bool>;
+    /// Uploads the blob with its `Content-MD5`, returning the MD5 reported by
+    /// Azure.
     async fn put_block_blob(
         &self,
         container: &str,
         path: &str,
-        content: Vec<u8>,
-    ) -> azure_core::Result<()>;
+        content: Bytes,
+        md5: [u8; 16],
+    ) -> azure_core::Result<Option<[u8; 16]>>;
     async fn delete(&self, container: &str, path: &str) -> azure_core::Result<DeleteBlobResponse>;
     async fn get_properties(
         &self,
@@ -30,34 +61,230 @@ pub trait ClientBuilderTrait: DynClone + Sync + Send {
     ) -> azure_core::Result<BlobProperties>;
 
     async fn list_blobs(&self, container: &str) -> azure_core::Result<Vec<PathBuf>>;
+
+    /// Lists the blobs whose name starts with the given prefix. The default
+    /// implementation filters the whole container listing; clients should
+    /// override it with a prefix query.
+    async fn list_blobs_with_prefix(
+        &self,
+        container: &str,
+        prefix: &str,
+    ) -> azure_core::Result<Vec<PathBuf>> {
+        Ok(self
+            .list_blobs(container)
+            .await?
+            .into_iter()
+            .filter(|path| path.to_string_lossy().starts_with(prefix))
+            .collect())
+    }
+
+    async fn container_exists(&self, container: &str) -> azure_core::Result<bool>;
+
+    async fn create_container(
+        &self,
+        container: &str,
+        public_access: PublicAccess,
+    ) -> azure_core::Result<()>;
+
+    /// Stages the block with its `Content-MD5`, returning the MD5 reported by
+    /// Azure.
+    async fn put_block(
+        &self,
+        container: &str,
+        path: &str,
+        block_id: &str,
+        content: Bytes,
+        md5: [u8; 16],
+    ) -> azure_core::Result<Option<[u8; 16]>>;
+
+    async fn put_block_list(
+        &self,
+        container: &str,
+        path: &str,
+        block_ids: Vec<String>,
+    ) -> azure_core::Result<()>;
+
+    /// Moves the block blob to the given access tier.
+    async fn set_blob_tier(
+        &self,
+        container: &str,
+        path: &str,
+        tier: AccessTier,
+    ) -> azure_core::Result<()>;
+
+    /// Creates an empty append blob, replacing an existing blob.
+    async fn put_append_blob(&self, container: &str, path: &str) -> azure_core::Result<()>;
+
+    /// Appends the block to the append blob with its `Content-MD5`, returning
+    /// the MD5 reported by Azure.
+    async fn append_block(
+        &self,
+        container: &str,
+        path: &str,
+        content: Bytes,
+        md5: [u8; 16],
+    ) -> azure_core::Result<Option<[u8; 16]>>;
+
+    /// Creates a zeroed page blob of the given length, replacing an existing
+    /// blob.
+    async fn put_page_blob(
+        &self,
+        container: &str,
+        path: &str,
+        length: u64,
+    ) -> azure_core::Result<()>;
+
+    /// Writes the pages at the given 512 byte aligned offset with their
+    /// `Content-MD5`, returning the MD5 reported by Azure.
+    async fn put_page(
+        &self,
+        container: &str,
+        path: &str,
+        offset: u64,
+        content: Bytes,
+        md5: [u8; 16],
+    ) -> azure_core::Result<Option<[u8; 16]>>;
+
+    async fn get_uncommitted_blocks(
+        &self,
+        container: &str,
+        path: &str,
+    ) -> azure_core::Result<Vec<String>>;
 }
 
-// Define a structure representing Azure Storage client
+// Define a structure representing Azure Storage client, built once and shared
+// by its clones
 #[derive(Clone)]
 struct Client {
-    client_builder: ClientBuilder,
+    service: BlobServiceClient,
 }
 
 // Define a structure representing Blob properties
 pub struct BlobProperties {
     pub date: SystemTime,
+    pub content_length: u64,
+    /// The access tier of a block blob, e.g. `Cool`.
+    pub access_tier: Option<String>,
+    /// The `Content-MD5` of the blob, when it was written with one.
+    pub content_md5: Option<[u8; 16]>,
+}
+
+/// Enum representing the access tier of a block blob, the Azure counterpart
+/// of the S3 storage classes.
+///
+/// The tier converts into the storage class of [`WriteOptions`], so it can be
+/// chosen per write:
+///
+/// ```rust
+/// use active_storage::drivers::{azure::BlobTier, WriteOptions};
+///
+/// let options = WriteOptions::default().with_storage_class(BlobTier::Cool);
+/// assert_eq!(options.storage_class.as_deref(), Some("Cool"));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlobTier {
+    /// Frequently accessed blobs, the account default.
+    Hot,
+    /// Infrequently accessed blobs, kept for at least 30 days.
+    Cool,
+    /// Offline blobs, which must be rehydrated to another tier before being
+    /// read.
+    Archive,
+}
+
+impl BlobTier {
+    /// Returns the name of the tier as reported by Azure.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Hot => "Hot",
+            Self::Cool => "Cool",
+            Self::Archive => "Archive",
+        }
+    }
+
+    /// Parses the name of a tier, ignoring its case.
+    fn parse(name: &str) -> Option<Self> {
+        [Self::Hot, Self::Cool, Self::Archive]
+            .into_iter()
+            .find(|tier| tier.as_str().eq_ignore_ascii_case(name))
+    }
+
+    const fn access_tier(self) -> AccessTier {
+        match self {
+            Self::Hot => AccessTier::Hot,
+            Self::Cool => AccessTier::Cool,
+            Self::Archive => AccessTier::Archive,
+        }
+    }
+}
+
+impl From<BlobTier> for String {
+    fn from(tier: BlobTier) -> Self {
+        tier.as_str().to_string()
+    }
+}
+
+impl Client {
+    /// Lists the blobs of the container, optionally under a prefix, following
+    /// the continuation of every page.
+    async fn list_blob_pages(
+        &self,
+        container: &str,
+        prefix: Option<&str>,
+    ) -> azure_core::Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+
+        let mut builder = self.service.container_client(container).list_blobs();
+        if let Some(prefix) = prefix {
+            builder = builder.prefix(prefix.to_string());
+        }
+
+        let mut blob_stream = builder.into_stream();
+        while let Some(blob_entry) = blob_stream.next().await {
+            for blob in blob_entry?.blobs.blobs() {
+                paths.push(PathBuf::from(blob.name.to_string()));
+            }
+        }
+        Ok(paths)
+    }
 }
 
 // Implement the trait for the Azure Storage client builder
 #[async_trait::async_trait]
 impl ClientBuilderTrait for Client {
     async fn get_blob_content(&self, container: &str, path: &str) -> azure_core::Result<Vec<u8>> {
-        self.client_builder
-            .clone()
-            .blob_client(container.to_string(), path)
+        self.service
+            .container_client(container)
+            .blob_client(path)
             .get_content()
             .await
     }
 
+    async fn get_blob_range(
+        &self,
+        container: &str,
+        path: &str,
+        range: Range<u64>,
+    ) -> azure_core::Result<Vec<u8>> {
+        let mut content = Vec::new();
+        let mut stream = self
+            .service
+            .container_client(container)
+            .blob_client(path)
+            .get()
+            .range(range)
+            .into_stream();
+        while let Some(chunk) = stream.next().await {
+            content.extend(&chunk?.data.collect().await?);
+        }
+        Ok(content)
+    }
+
     async fn blob_exists(&self, container: &str, path: &str) -> azure_core::Result<bool> {
-        self.client_builder
-            .clone()
-            .blob_client(container.to_string(), path)
+        self.service
+            .container_client(container)
+            .blob_client(path)
             .exists()
             .await
     }
@@ -65,19 +292,22 @@ impl ClientBuilderTrait for Client {
         &self,
         container: &str,
         path: &str,
-        content: Vec<u8>,
-    ) -> azure_core::Result<()> {
-        self.client_builder
-            .clone()
-            .blob_client(container.to_string(), path)
+        content: Bytes,
+        md5: [u8; 16],
+    ) -> azure_core::Result<Option<[u8; 16]>> {
+        let response = self
+            .service
+            .container_client(container)
+            .blob_client(path)
             .put_block_blob(content)
+            .hash(Hash::MD5(md5))
             .await?;
-        Ok(())
+        Ok(response.content_md5.map(|md5| *md5.as_slice()))
     }
     async fn delete(&self, container: &str, path: &str) -> azure_core::Result<DeleteBlobResponse> {
-        self.client_builder
-            .clone()
-            .blob_client(container.to_string(), path)
+        self.service
+            .container_client(container)
+            .blob_client(path)
             .delete()
             .await
     }
@@ -87,33 +317,192 @@ impl ClientBuilderTrait for Client {
         path: &str,
     ) -> azure_core::Result<BlobProperties> {
         let properties = self
-            .client_builder
-            .clone()
-            .blob_client(container.to_string(), path)
+            .service
+            .container_client(container)
+            .blob_client(path)
             .get_properties()
             .await?;
 
         Ok(BlobProperties {
-            date: properties.date.into(),
+            date: properties.blob.properties.last_modified.into(),
+            content_length: properties.blob.properties.content_length,
+            access_tier: properties
+                .blob
+                .properties
+                .access_tier
+                .map(|tier| tier.as_ref().to_string()),
+            content_md5: properties
+                .blob
+                .properties
+                .content_md5
+                .map(|md5| *md5.as_slice()),
         })
     }
 
     async fn list_blobs(&self, container: &str) -> azure_core::Result<Vec<PathBuf>> {
-        let mut paths = Vec::new();
+        self.list_blob_pages(container, None).await
+    }
 
-        let mut blob_stream = self
-            .client_builder
-            .clone()
-            .container_client(container.to_string())
-            .list_blobs()
-            .into_stream();
+    async fn list_blobs_with_prefix(
+        &self,
+        container: &str,
+        prefix: &str,
+    ) -> azure_core::Result<Vec<PathBuf>> {
+        self.list_blob_pages(container, Some(prefix)).await
+    }
 
-        while let Some(Ok(blob_entry)) = blob_stream.next().await {
-            for blob in blob_entry.blobs.blobs() {
-                paths.push(PathBuf::from(blob.name.to_string()));
-            }
-        }
-        Ok(paths)
+    async fn container_exists(&self, container: &str) -> azure_core::Result<bool> {
+        self.service.container_client(container).exists().await
+    }
+
+    async fn create_container(
+        &self,
+        container: &str,
+        public_access: PublicAccess,
+    ) -> azure_core::Result<()> {
+        self.service
+            .container_client(container)
+            .create()
+            .public_access(public_access)
+            .await
+    }
+
+    async fn put_block(
+        &self,
+        container: &str,
+        path: &str,
+        block_id: &str,
+        content: Bytes,
+        md5: [u8; 16],
+    ) -> azure_core::Result<Option<[u8; 16]>> {
+        let response = self
+            .service
+            .container_client(container)
+            .blob_client(path)
+            .put_block(block_id.to_string(), content)
+            .hash(Hash::MD5(md5))
+            .await?;
+        Ok(response.content_md5.map(|md5| *md5.as_slice()))
+    }
+
+    async fn put_block_list(
+        &self,
+        container: &str,
+        path: &str,
+        block_ids: Vec<String>,
+    ) -> azure_core::Result<()> {
+        let block_list = BlockList {
+            blocks: block_ids
+                .into_iter()
+                .map(BlobBlockType::new_uncommitted)
+                .collect(),
+        };
+
+        self.service
+            .container_client(container)
+            .blob_client(path)
+            .put_block_list(block_list)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_blob_tier(
+        &self,
+        container: &str,
+        path: &str,
+        tier: AccessTier,
+    ) -> azure_core::Result<()> {
+        self.service
+            .container_client(container)
+            .blob_client(path)
+            .set_blob_tier(tier)
+            .await?;
+        Ok(())
+    }
+
+    async fn put_append_blob(&self, container: &str, path: &str) -> azure_core::Result<()> {
+        self.service
+            .container_client(container)
+            .blob_client(path)
+            .put_append_blob()
+            .await?;
+        Ok(())
+    }
+
+    async fn append_block(
+        &self,
+        container: &str,
+        path: &str,
+        content: Bytes,
+        md5: [u8; 16],
+    ) -> azure_core::Result<Option<[u8; 16]>> {
+        let response = self
+            .service
+            .container_client(container)
+            .blob_client(path)
+            .append_block(content)
+            .hash(Hash::MD5(md5))
+            .await?;
+        Ok(response.content_md5.map(|md5| *md5.as_slice()))
+    }
+
+    async fn put_page_blob(
+        &self,
+        container: &str,
+        path: &str,
+        length: u64,
+    ) -> azure_core::Result<()> {
+        self.service
+            .container_client(container)
+            .blob_client(path)
+            .put_page_blob(u128::from(length))
+            .await?;
+        Ok(())
+    }
+
+    async fn put_page(
+        &self,
+        container: &str,
+        path: &str,
+        offset: u64,
+        content: Bytes,
+        md5: [u8; 16],
+    ) -> azure_core::Result<Option<[u8; 16]>> {
+        let range = BA512Range::new(offset, offset + content.len() as u64 - 1)?;
+        let response = self
+            .service
+            .container_client(container)
+            .blob_client(path)
+            .put_page(range, content)
+            .hash(Hash::MD5(md5))
+            .await?;
+        Ok(response.content_md5.map(|md5| *md5.as_slice()))
+    }
+
+    async fn get_uncommitted_blocks(
+        &self,
+        container: &str,
+        path: &str,
+    ) -> azure_core::Result<Vec<String>> {
+        let response = self
+            .service
+            .container_client(container)
+            .blob_client(path)
+            .get_block_list()
+            .block_list_type(BlockListType::Uncommitted)
+            .await?;
+
+        Ok(response
+            .block_with_size_list
+            .blocks
+            .into_iter()
+            .filter_map(|block| match block.block_list_type {
+                BlobBlockType::Uncommitted(block_id) => {
+                    Some(String::from_utf8_lossy(block_id.as_ref()).to_string())
+                }
+                _ => None,
+            })
+            .collect())
     }
 }
 
@@ -122,12 +511,23 @@ pub struct Config {
     pub account: String,
     pub container: String,
     pub credentials: ClientCredentials,
+    /// Optional provider of rotating credentials, refreshing `credentials`
+    /// before every request. Requests rejected with invalid credentials are
+    /// retried once with fresh ones.
+    pub credentials_provider: Option<Arc<dyn CredentialsProvider<ClientCredentials>>>,
+    /// Optional base URL public files are served from, e.g. an Azure CDN
+    /// endpoint. Defaults to the container URL.
+    pub public_url: Option<String>,
+    /// The options of the HTTP connection pool. Idle connections are not
+    /// kept alive unless [`PoolOptions::max_idle_per_host`] is set.
+    pub pool: PoolOptions,
 }
 
 #[allow(clippy::module_name_repetitions)]
 pub struct AzureDriver {
     pub container: String,
     client: Box<dyn ClientBuilderTrait>,
+    public_url: Option<String>,
 }
 
 impl Clone for AzureDriver {
@@ -135,6 +535,7 @@ impl Clone for AzureDriver {
         Self {
             container: self.container.clone(),
             client: dyn_clone::clone_box(&*self.client),
+            public_url: self.public_url.clone(),
         }
     }
 }
@@ -144,26 +545,212 @@ pub enum ClientCredentials {
     AccessKey(String),
 }
 
+impl ClientCredentials {
+    /// Converts the credentials into storage credentials of the given
+    /// account.
+    fn into_storage(self, account: &str) -> StorageCredentials {
+        match self {
+            Self::AccessKey(access_key) => {
+                StorageCredentials::access_key(account.to_string(), access_key)
+            }
+        }
+    }
+}
+
+/// Returns the client options sending requests with the given transport and
+/// the correlation id of the current task.
+fn client_options(transport: TransportOptions) -> ClientOptions {
+    let mut options = ClientOptions::new(transport);
+    options
+        .per_call_policies_mut()
+        .push(Arc::new(CorrelationId));
+    options
+}
+
+/// Pipeline policy sending the correlation id of the current task in the
+/// `x-ms-client-request-id` header, recorded by the storage logs.
+#[derive(Debug)]
+struct CorrelationId;
+
+#[async_trait::async_trait]
+impl Policy for CorrelationId {
+    async fn send(
+        &self,
+        ctx: &Context,
+        request: &mut Request,
+        next: &[Arc<dyn Policy>],
+    ) -> PolicyResult {
+        if let Some(correlation_id) = correlation::current() {
+            request.insert_header(CLIENT_REQUEST_ID, correlation_id);
+        }
+        next[0].send(ctx, request, &next[1..]).await
+    }
+}
+
+/// Pipeline policy refreshing the storage credentials with a
+/// [`CredentialsProvider`] before every request, and retrying requests
+/// rejected with invalid credentials once with fresh ones.
+struct RefreshCredentials {
+    account: String,
+    credentials: StorageCredentials,
+    provider: Arc<dyn CredentialsProvider<ClientCredentials>>,
+}
+
+impl std::fmt::Debug for RefreshCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RefreshCredentials")
+            .field("account", &self.account)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RefreshCredentials {
+    /// Replaces the storage credentials with the provider ones.
+    async fn refresh(&self) -> azure_core::Result<()> {
+        let credentials =
+            self.provider.credentials().await.map_err(|err| {
+                azure_core::Error::new(azure_core::error::ErrorKind::Credential, err)
+            })?;
+        self.credentials
+            .replace(credentials.into_storage(&self.account))
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl Policy for RefreshCredentials {
+    async fn send(
+        &self,
+        ctx: &Context,
+        request: &mut Request,
+        next: &[Arc<dyn Policy>],
+    ) -> PolicyResult {
+        self.refresh().await?;
+        match next[0].send(ctx, request, &next[1..]).await {
+            Err(err)
+                if matches!(
+                    DriverError::from(err.kind()),
+                    DriverError::AuthenticationFailed
+                ) =>
+            {
+                self.provider.invalidate();
+                self.refresh().await?;
+                next[0].send(ctx, request, &next[1..]).await
+            }
+            result => result,
+        }
+    }
+}
+
 impl AzureDriver {
+    /// Appends the content to the append blob in blocks of at most 4 MiB,
+    /// creating the blob when missing.
+    async fn append(&self, blob_name: &str, content: Bytes) -> DriverResult<()> {
+        if !self.client.blob_exists(&self.container, blob_name).await? {
+            self.client
+                .put_append_blob(&self.container, blob_name)
+                .await?;
+        }
+
+        for block in content.chunks(MAX_APPEND_SIZE) {
+            let md5 = content_md5(block);
+            let reported = self
+                .client
+                .append_block(&self.container, blob_name, content.slice_ref(block), md5)
+                .await?;
+            verify_md5(md5, reported)?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the blob with a page blob of the content, written in ranges of
+    /// at most 4 MiB.
+    async fn write_pages(&self, blob_name: &str, content: Bytes) -> DriverResult<()> {
+        let length = content.len() as u64;
+        if !length.is_multiple_of(PAGE_SIZE) {
+            return Err(DriverError::UnalignedPageBlob(length));
+        }
+        self.client
+            .put_page_blob(&self.container, blob_name, length)
+            .await?;
+
+        let mut offset = 0;
+        for pages in content.chunks(MAX_APPEND_SIZE) {
+            let md5 = content_md5(pages);
+            let reported = self
+                .client
+                .put_page(
+                    &self.container,
+                    blob_name,
+                    offset,
+                    content.slice_ref(pages),
+                    md5,
+                )
+                .await?;
+            verify_md5(md5, reported)?;
+            offset += pages.len() as u64;
+        }
+        Ok(())
+    }
+
     /// Create a new instance of [`AzureDriver`] with the provided
     /// configuration.
     #[must_use]
     pub fn new(config: Config) -> Self {
-        let storage_credentials = match config.credentials {
-            ClientCredentials::AccessKey(access_key) => {
-                StorageCredentials::access_key(config.account.to_string(), access_key)
-            }
-        };
+        let storage_credentials = config.credentials.into_storage(&config.account);
 
+        // azure keeps no idle connections by default, working around
+        // https://github.com/hyperium/hyper/issues/2312
+        let pool = PoolOptions {
+            max_idle_per_host: config.pool.max_idle_per_host.or(Some(0)),
+            ..config.pool
+        };
+        let mut options = client_options(TransportOptions::new(Arc::new(pool.reqwest_client())));
+        if let Some(provider) = config.credentials_provider {
+            options
+                .per_call_policies_mut()
+                .push(Arc::new(RefreshCredentials {
+                    account: config.account.clone(),
+                    credentials: storage_credentials.clone(),
+                    provider,
+                }));
+        }
         let client = Box::new(Client {
-            client_builder: ClientBuilder::new(config.account.to_string(), storage_credentials),
+            service: ClientBuilder::new(config.account.to_string(), storage_credentials)
+                .client_options(options)
+                .blob_service_client(),
         });
+        let public_url = config.public_url.unwrap_or_else(|| {
+            format!(
+                "https://{}.blob.core.windows.net/{}",
+                config.account, config.container
+            )
+        });
+
         Self {
             container: config.container,
             client,
+            public_url: Some(public_url),
         }
     }
 
+    /// Creates a new [`AzureDriver`] instance connected to an Azure storage
+    /// emulator such as Azurite, with its well-known account and key.
+    #[must_use]
+    pub fn with_emulator(container: &str, address: &str, port: u16) -> Self {
+        let location = CloudLocation::Emulator {
+            address: address.to_string(),
+            port,
+        };
+        let client = Box::new(Client {
+            service: ClientBuilder::with_location(location, StorageCredentials::emulator())
+                .client_options(client_options(TransportOptions::default()))
+                .blob_service_client(),
+        });
+
+        Self::with_client(container, client)
+    }
+
     /// Creates a new [`AzureDriver`] instance with the provided azure client
     /// and container name.
     #[must_use]
@@ -171,9 +758,32 @@ impl AzureDriver {
         Self {
             container: container.to_string(),
             client,
+            public_url: None,
         }
     }
 
+    /// Sets the base URL public files are served from, e.g. an Azure CDN
+    /// endpoint.
+    #[must_use]
+    pub fn with_public_url(mut self, public_url: &str) -> Self {
+        self.public_url = Some(public_url.to_string());
+        self
+    }
+
+    /// Moves the block blob at the given path to the given access tier. A blob
+    /// moved out of the archive tier is readable once Azure rehydrated it,
+    /// which takes hours.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the blob does not exist or is not a block blob.
+    pub async fn set_blob_tier(&self, path: &ObjectPath, tier: BlobTier) -> DriverResult<()> {
+        self.client
+            .set_blob_tier(&self.container, &blob_name(path)?, tier.access_tier())
+            .await?;
+        Ok(())
+    }
+
     /// Get all files in a specified path.
     ///
     /// # Errors
@@ -182,25 +792,37 @@ impl AzureDriver {
     ///
     /// # Returns
     ///
-    /// A `Result` containing a vector of `PathBuf` representing the file paths,
-    /// or an error.
+    /// A `Result` containing a vector of `ObjectPath` representing the file
+    /// paths, or an error.
     async fn get_all_files_in_path(
         &self,
         container: &str,
-        dir_path: &Path,
-    ) -> DriverResult<Vec<std::path::PathBuf>> {
-        let prefix_folder = dir_path.to_path_buf();
-        let mut paths = Vec::new();
-
-        let container_paths = match self.client.list_blobs(container).await {
+        dir_path: &ObjectPath,
+    ) -> DriverResult<Vec<ObjectPath>> {
+        let prefix = dir_path.to_prefix()?;
+        let listing = match &prefix {
+            Some(prefix) => {
+                let prefix = format!("{}/", prefix.encode(KeyRules::Azure)?);
+                self.client.list_blobs_with_prefix(container, &prefix).await
+            }
+            None => self.client.list_blobs(container).await,
+        };
+        let container_paths = match listing {
             Ok(paths) => paths,
             Err(error) => {
-                return Err(error.kind().into());
+                return Err(error.into());
             }
         };
 
+        let prefix_folder = prefix.as_ref().map(ObjectPath::from);
+        let mut paths = Vec::new();
+
         for path in container_paths {
-            if path.starts_with(&prefix_folder) {
+            let path = super::decode_key(&path.to_string_lossy(), KeyRules::Azure);
+            if prefix_folder
+                .as_ref()
+                .is_none_or(|prefix| path.starts_with(prefix))
+            {
                 paths.push(path);
             }
         }
@@ -208,6 +830,34 @@ impl AzureDriver {
     }
 }
 
+/// Returns the block id of an upload part. Block ids of a blob must all have
+/// the same length, so part numbers are zero padded.
+fn block_id(part_number: u32) -> String {
+    format!("{part_number:05}")
+}
+
+/// Returns the MD5 digest of the content, sent as its `Content-MD5`.
+fn content_md5(content: &[u8]) -> [u8; 16] {
+    let mut md5 = [0; 16];
+    md5.copy_from_slice(Md5::digest(content).as_slice());
+    md5
+}
+
+/// Checks the MD5 reported by Azure against the one sent with the content.
+/// Azure already rejects a content not matching its `Content-MD5` with an
+/// `Md5Mismatch` error, this also catches a header altered on the way.
+fn verify_md5(sent: [u8; 16], reported: Option<[u8; 16]>) -> DriverResult<()> {
+    match reported {
+        Some(reported) if reported != sent => Err(DriverError::ChecksumMismatch),
+        _ => Ok(()),
+    }
+}
+
+/// Converts the given path into an Azure blob name.
+fn blob_name(path: &ObjectPath) -> DriverResult<String> {
+    Ok(path.to_key()?.encode(KeyRules::Azure)?)
+}
+
 #[async_trait::async_trait]
 impl Driver for AzureDriver {
     /// Reads the contents of a file at the specified path within the storage.
@@ -216,17 +866,14 @@ impl Driver for AzureDriver {
     ///
     /// Returns an error if there is an issue reading from the file or decoding
     /// its contents.
-    async fn read(&self, path: &Path) -> DriverResult<Vec<u8>> {
+    async fn read(&self, path: &ObjectPath) -> DriverResult<Bytes> {
         match self
             .client
-            .get_blob_content(
-                &self.container,
-                path.to_str().ok_or(DriverError::InvalidPath)?,
-            )
+            .get_blob_content(&self.container, &blob_name(path)?)
             .await
         {
-            Ok(blob) => Ok(blob),
-            Err(err) => return Err(err.kind().into()),
+            Ok(blob) => Ok(blob.into()),
+            Err(err) => return Err(err.into()),
         }
     }
 
@@ -239,17 +886,14 @@ impl Driver for AzureDriver {
     ///
     /// Returns an error if there is an issue checking the existence of the
     /// file.
-    async fn file_exists(&self, path: &Path) -> DriverResult<bool> {
+    async fn file_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
         match self
             .client
-            .blob_exists(
-                &self.container,
-                path.to_str().ok_or(DriverError::InvalidPath)?,
-            )
+            .blob_exists(&self.container, &blob_name(path)?)
             .await
         {
             Ok(is_exists) => Ok(is_exists),
-            Err(err) => Err(err.kind().into()),
+            Err(err) => Err(err.into()),
         }
     }
 
@@ -263,18 +907,72 @@ impl Driver for AzureDriver {
     ///
     /// Returns an error if there is any issue creating directories or writing
     /// to the file
-    async fn write(&self, path: &Path, content: Vec<u8>) -> DriverResult<()> {
+    async fn write(&self, path: &ObjectPath, content: Bytes) -> DriverResult<()> {
+        check_legal_hold(self, path).await?;
+        let md5 = content_md5(&content);
         match self
             .client
-            .put_block_blob(
-                &self.container,
-                path.to_str().ok_or(DriverError::InvalidPath)?,
-                content,
-            )
+            .put_block_blob(&self.container, &blob_name(path)?, content, md5)
             .await
         {
-            Ok(()) => Ok(()),
-            Err(error) => Err(error.kind().into()),
+            Ok(reported) => verify_md5(md5, reported),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Writes the content with the given options. An append blob type
+    /// appends the content to the blob, creating it when missing, and a page
+    /// blob type replaces the blob with a page blob of the content.
+    ///
+    /// Block blobs are written like [`Driver::write_with_options`] does by
+    /// default, and then moved to the access tier named by the storage class,
+    /// see [`BlobTier`]. Append and page blobs support no other option than the
+    /// ignored content type and cache control.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::UnalignedPageBlob`] if a page blob content is
+    /// not a multiple of 512 bytes, or an error if an option is not supported
+    /// or the blob could not be written.
+    async fn write_with_options(
+        &self,
+        path: &ObjectPath,
+        content: Bytes,
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        let blob_type = options.blob_type.unwrap_or_default();
+        if blob_type == BlobType::BlockBlob {
+            let Some(storage_class) = &options.storage_class else {
+                return write_with_default_options(self, path, content, options).await;
+            };
+            let tier = BlobTier::parse(storage_class).ok_or(DriverError::Unsupported(
+                "access tiers other than Hot, Cool and Archive",
+            ))?;
+            let options = WriteOptions {
+                storage_class: None,
+                ..options.clone()
+            };
+            write_with_default_options(self, path, content, &options).await?;
+            return self.set_blob_tier(path, tier).await;
+        }
+        if !options.metadata.is_empty()
+            || options.storage_class.is_some()
+            || options.acl.is_some()
+            || options.encryption.is_some()
+            || options.precondition.is_some()
+            || options.last_modified.is_some()
+            || !options.headers.is_empty()
+            || !options.query_params.is_empty()
+        {
+            return Err(DriverError::Unsupported("options on append and page blobs"));
+        }
+
+        check_legal_hold(self, path).await?;
+        let blob_name = blob_name(path)?;
+        if blob_type == BlobType::AppendBlob {
+            self.append(&blob_name, content).await
+        } else {
+            self.write_pages(&blob_name, content).await
         }
     }
 
@@ -287,17 +985,11 @@ impl Driver for AzureDriver {
     ///
     /// If the file does not exist, the error variant
     /// `DriverError::ResourceNotFound` is returned.
-    async fn delete(&self, path: &Path) -> DriverResult<()> {
-        match self
-            .client
-            .delete(
-                &self.container,
-                path.to_str().ok_or(DriverError::InvalidPath)?,
-            )
-            .await
-        {
+    async fn delete(&self, path: &ObjectPath) -> DriverResult<()> {
+        check_legal_hold(self, path).await?;
+        match self.client.delete(&self.container, &blob_name(path)?).await {
             Ok(_) => Ok(()),
-            Err(error) => Err(error.kind().into()),
+            Err(error) => Err(error.into()),
         }
     }
 
@@ -310,24 +1002,33 @@ impl Driver for AzureDriver {
     ///
     /// If the files not found under the given path, the error variant
     /// `DriverError::DirectoryNotFound` is returned.
-    async fn delete_directory(&self, path: &Path) -> DriverResult<()> {
+    async fn delete_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        directory_key(path)?;
         let paths_to_delete = self.get_all_files_in_path(&self.container, path).await?;
 
         if paths_to_delete.is_empty() {
             return Err(DriverError::ResourceNotFound);
         }
+        check_no_legal_holds(self, path).await?;
 
-        for blob_path in paths_to_delete {
-            if let Err(err) = self
-                .client
-                .delete(
-                    &self.container,
-                    blob_path.to_str().ok_or(DriverError::InvalidPath)?,
-                )
-                .await
-            {
-                return Err(err.kind().into());
-            }
+        let mut deletes = stream::iter(paths_to_delete)
+            .map(|blob_path| async move {
+                match self
+                    .client
+                    .delete(&self.container, &blob_name(&blob_path)?)
+                    .await
+                {
+                    Ok(_) => Ok(()),
+                    Err(err) => match DriverError::from(err) {
+                        // deleted concurrently
+                        DriverError::ResourceNotFound => Ok(()),
+                        err => Err(err),
+                    },
+                }
+            })
+            .buffer_unordered(DELETE_CONCURRENCY);
+        while let Some(result) = deletes.next().await {
+            result?;
         }
 
         Ok(())
@@ -343,36 +1044,392 @@ impl Driver for AzureDriver {
     ///
     /// If the file does not exist, the error variant
     /// `DriverError::ResourceNotFound` is returned.
-    async fn last_modified(&self, path: &Path) -> DriverResult<SystemTime> {
-        let properties = match self
+    async fn last_modified(&self, path: &ObjectPath) -> DriverResult<SystemTime> {
+        self.stat(path)
+            .await?
+            .map(|metadata| metadata.last_modified)
+            .ok_or(DriverError::ResourceNotFound)
+    }
+
+    /// Returns the blob size from its properties.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the blob does not exist.
+    async fn size(&self, path: &ObjectPath) -> DriverResult<u64> {
+        self.stat(path)
+            .await?
+            .map(|metadata| metadata.size)
+            .ok_or(DriverError::ResourceNotFound)
+    }
+
+    /// Returns the blob metadata from a single properties request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    async fn stat(&self, path: &ObjectPath) -> DriverResult<Option<ObjectMetadata>> {
+        match self
             .client
-            .get_properties(
-                &self.container,
-                path.to_str().ok_or(DriverError::InvalidPath)?,
-            )
+            .get_properties(&self.container, &blob_name(path)?)
+            .await
+        {
+            Ok(properties) => Ok(Some(ObjectMetadata {
+                size: properties.content_length,
+                last_modified: properties.date,
+                content_type: None,
+                etag: None,
+                storage_class: properties.access_tier,
+                content_md5: properties.content_md5,
+            })),
+            Err(err) => match DriverError::from(err) {
+                DriverError::ResourceNotFound => Ok(None),
+                err => Err(err),
+            },
+        }
+    }
+
+    /// Reads the given byte range of the blob.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the blob does not exist or could not be read.
+    async fn read_range(&self, path: &ObjectPath, range: Range<u64>) -> DriverResult<Vec<u8>> {
+        if range.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        match self
+            .client
+            .get_blob_range(&self.container, &blob_name(path)?, range)
+            .await
+        {
+            Ok(content) => Ok(content),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::RANGE_READS | Capabilities::MULTIPART_UPLOADS | Capabilities::PUBLIC_URLS
+    }
+
+    /// Returns the public URL of the file, under the configured public URL or
+    /// the container URL.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::Unsupported`] if the driver was created with a
+    /// custom client and no public URL.
+    async fn public_url(&self, path: &ObjectPath) -> DriverResult<String> {
+        let base_url = self
+            .public_url
+            .as_deref()
+            .ok_or(DriverError::Unsupported("public url"))?;
+
+        super::join_url(base_url, path, KeyRules::Azure)
+    }
+
+    /// Lists all the files under the given path within the storage.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is an issue listing data.
+    async fn list(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        self.get_all_files_in_path(&self.container, path).await
+    }
+
+    /// Checks whether the container exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the container could not be queried.
+    async fn container_exists(&self) -> DriverResult<bool> {
+        match self.client.container_exists(&self.container).await {
+            Ok(is_exists) => Ok(is_exists),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Creates the container when it doesn't exist. The region option is
+    /// ignored since Azure containers live in the storage account region.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the container could not be created.
+    async fn ensure_container(&self, options: &ContainerOptions) -> DriverResult<()> {
+        if self.container_exists().await? {
+            return Ok(());
+        }
+
+        let public_access = match options.acl {
+            Some(Acl::PublicRead) => PublicAccess::Blob,
+            Some(Acl::Private) | None => PublicAccess::None,
+        };
+
+        match self
+            .client
+            .create_container(&self.container, public_access)
             .await
         {
-            Ok(metadata) => metadata,
-            Err(err) => return Err(err.kind().into()),
+            Ok(()) => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Starts a block list upload. Blocks are staged on the blob itself, so
+    /// concurrent uploads of the same blob share their blocks.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is invalid.
+    async fn create_upload(&self, path: &ObjectPath) -> DriverResult<String> {
+        blob_name(path)?;
+        Ok(super::unique_id())
+    }
+
+    /// Stages a block of the blob.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the block could not be staged.
+    async fn upload_part(
+        &self,
+        path: &ObjectPath,
+        _upload_id: &str,
+        part_number: u32,
+        content: Bytes,
+    ) -> DriverResult<UploadPart> {
+        let block_id = block_id(part_number);
+        let md5 = content_md5(&content);
+        match self
+            .client
+            .put_block(&self.container, &blob_name(path)?, &block_id, content, md5)
+            .await
+        {
+            Ok(reported) => verify_md5(md5, reported).map(|()| UploadPart {
+                part_number,
+                etag: block_id,
+            }),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Lists the uncommitted blocks of the blob.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the block list could not be retrieved.
+    async fn list_upload_parts(
+        &self,
+        path: &ObjectPath,
+        _upload_id: &str,
+    ) -> DriverResult<Vec<UploadPart>> {
+        let block_ids = match self
+            .client
+            .get_uncommitted_blocks(&self.container, &blob_name(path)?)
+            .await
+        {
+            Ok(block_ids) => block_ids,
+            Err(err) => return Err(err.into()),
         };
 
-        Ok(properties.date)
+        let mut parts = block_ids
+            .into_iter()
+            .filter_map(|block_id| {
+                block_id.parse().ok().map(|part_number| UploadPart {
+                    part_number,
+                    etag: block_id,
+                })
+            })
+            .collect::<Vec<_>>();
+        parts.sort_by_key(|part| part.part_number);
+
+        Ok(parts)
+    }
+
+    /// Commits the staged blocks of the given parts as the blob content.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a block is missing or the list could not be
+    /// committed.
+    async fn complete_upload(
+        &self,
+        path: &ObjectPath,
+        _upload_id: &str,
+        parts: &[UploadPart],
+    ) -> DriverResult<()> {
+        let block_ids = parts
+            .iter()
+            .map(|part| block_id(part.part_number))
+            .collect();
+
+        match self
+            .client
+            .put_block_list(&self.container, &blob_name(path)?, block_ids)
+            .await
+        {
+            Ok(()) => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Lists no uploads: uncommitted blocks are staged on the blob itself
+    /// and Azure discards them on its own after a week.
+    async fn list_uploads(&self, prefix: &ObjectPath) -> DriverResult<Vec<PendingUpload>> {
+        prefix.to_prefix()?;
+        Ok(Vec::new())
+    }
+
+    /// Aborts a block list upload. Azure discards uncommitted blocks on its
+    /// own after a week, so nothing is deleted.
+    async fn abort_upload(&self, path: &ObjectPath, _upload_id: &str) -> DriverResult<()> {
+        blob_name(path)?;
+        Ok(())
+    }
+}
+
+impl From<azure_core::Error> for DriverError {
+    fn from(error: azure_core::Error) -> Self {
+        #[cfg(feature = "tracing")]
+        if let azure_storage::ErrorKind::HttpResponse { status, error_code } = error.kind() {
+            if *status != azure_core::StatusCode::NotFound {
+                // the `x-ms-request-id` header is not exposed by azure_core
+                crate::correlation::record_backend_error(
+                    "azure",
+                    Some(*status as u16),
+                    error_code.as_deref(),
+                    None,
+                );
+            }
+        }
+        match Self::from(error.kind()) {
+            Self::Any(_) => match error.kind() {
+                azure_storage::ErrorKind::HttpResponse { status, error_code } => Self::Backend {
+                    provider: "azure",
+                    code: error_code.clone(),
+                    status: Some(*status as u16),
+                    request_id: None,
+                    // the `Retry-After` header is not exposed by azure_core
+                    retry_after: None,
+                    source: Box::new(error),
+                },
+                _ => Self::Any(Box::new(error)),
+            },
+            mapped => mapped,
+        }
     }
 }
 
 impl From<&azure_storage::ErrorKind> for DriverError {
     fn from(kind: &azure_storage::ErrorKind) -> Self {
         match kind {
-            azure_storage::ErrorKind::HttpResponse {
-                status: _,
-                error_code,
-            } => match error_code.as_ref().map(String::as_str) {
-                Some("ContainerNotFound" | "BlobNotFound") => Self::ResourceNotFound,
-                Some("AuthenticationFailed") => Self::AuthenticationFailed,
-                _ => Self::Any(Box::new(kind.clone().into_error())),
-            },
+            azure_storage::ErrorKind::HttpResponse { status, error_code } => {
+                match error_code.as_ref().map(String::as_str) {
+                    Some("ContainerNotFound" | "BlobNotFound") => Self::ResourceNotFound,
+                    Some("Md5Mismatch") => Self::ChecksumMismatch,
+                    Some("AuthenticationFailed" | "InvalidAuthenticationInfo") => {
+                        Self::AuthenticationFailed
+                    }
+                    Some(
+                        "AuthorizationFailure"
+                        | "AuthorizationPermissionMismatch"
+                        | "AuthorizationSourceIPMismatch"
+                        | "AuthorizationProtocolMismatch"
+                        | "AuthorizationResourceTypeMismatch"
+                        | "AuthorizationServiceMismatch"
+                        | "InsufficientAccountPermissions",
+                    ) => Self::PermissionDenied,
+                    _ => match status {
+                        azure_core::StatusCode::Unauthorized => Self::AuthenticationFailed,
+                        azure_core::StatusCode::Forbidden => Self::PermissionDenied,
+                        _ => Self::Any(Box::new(kind.clone().into_error())),
+                    },
+                }
+            }
             azure_storage::ErrorKind::Credential => Self::AuthenticationFailed,
+            azure_storage::ErrorKind::Io => Self::Network(),
             _ => Self::Any(Box::new(kind.clone().into_error())),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn http_error(status: azure_core::StatusCode, error_code: Option<&str>) -> DriverError {
+        DriverError::from(&azure_storage::ErrorKind::HttpResponse {
+            status,
+            error_code: error_code.map(ToString::to_string),
+        })
+    }
+
+    #[test]
+    fn can_map_error_kinds() {
+        use azure_core::StatusCode;
+
+        assert!(matches!(
+            http_error(StatusCode::NotFound, Some("BlobNotFound")),
+            DriverError::ResourceNotFound
+        ));
+        assert!(matches!(
+            http_error(StatusCode::BadRequest, Some("Md5Mismatch")),
+            DriverError::ChecksumMismatch
+        ));
+        assert!(matches!(
+            http_error(StatusCode::Forbidden, Some("AuthorizationFailure")),
+            DriverError::PermissionDenied
+        ));
+        assert!(matches!(
+            http_error(StatusCode::Unauthorized, None),
+            DriverError::AuthenticationFailed
+        ));
+        assert!(matches!(
+            DriverError::from(&azure_storage::ErrorKind::Io),
+            DriverError::Network()
+        ));
+
+        let error = azure_core::Error::from(azure_storage::ErrorKind::HttpResponse {
+            status: StatusCode::ServiceUnavailable,
+            error_code: Some("ServerBusy".to_string()),
+        });
+        assert!(matches!(
+            DriverError::from(error),
+            DriverError::Backend {
+                provider: "azure",
+                status: Some(503),
+                ref code,
+                ..
+            } if code.as_deref() == Some("ServerBusy")
+        ));
+    }
+
+    #[test]
+    fn can_build_blob_names_and_block_ids() {
+        assert_eq!(
+            blob_name(&ObjectPath::new("docs/a\\b%41.txt")).unwrap(),
+            "docs/a%5Cb%2541.txt"
+        );
+        assert!(matches!(
+            blob_name(&ObjectPath::new("docs/file.")),
+            Err(DriverError::InvalidPath)
+        ));
+        assert_eq!(block_id(7), "00007");
+        assert_eq!(block_id(10_000).len(), block_id(1).len());
+    }
+
+    #[test]
+    fn can_verify_md5() {
+        let md5 = content_md5(b"hello");
+        assert!(verify_md5(md5, None).is_ok());
+        assert!(verify_md5(md5, Some(md5)).is_ok());
+        assert!(matches!(
+            verify_md5(md5, Some(content_md5(b"hello world"))),
+            Err(DriverError::ChecksumMismatch)
+        ));
+        assert_eq!(BlobTier::parse("cool"), Some(BlobTier::Cool));
+        assert_eq!(BlobTier::parse("warm"), None);
+    }
+}
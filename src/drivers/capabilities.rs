@@ -0,0 +1,146 @@
+//! # Capabilities Module
+//!
+//! The `capabilities` module defines [`Capabilities`], the optional features a
+//! driver supports natively, and the extension traits exposing features that
+//! only some backends have, such as [`PresignExt`] and [`VersioningExt`].
+//!
+//! Extension traits are reached through [`super::Driver::as_presign`] and
+//! [`super::Driver::as_versioning`], which return `None` when the backend
+//! lacks the feature, so callers can fall back gracefully instead of matching
+//! on runtime errors.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use active_storage::{drivers::capabilities::Capabilities, key::ObjectPath, StoreConfig};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!     assert!(!store.capabilities().contains(Capabilities::PRESIGN));
+//!
+//!     let url = match store.as_presign() {
+//!         Some(presign) => presign
+//!             .presign_read(&ObjectPath::new("test.txt"), Duration::from_secs(60))
+//!             .await
+//!             .unwrap(),
+//!         None => "/files/test.txt".to_string(),
+//!     };
+//!     assert_eq!(url, "/files/test.txt");
+//! }
+//! ```
+use std::time::{Duration, SystemTime};
+
+use crate::{errors::DriverResult, key::ObjectPath};
+
+bitflags::bitflags! {
+    /// The optional features a driver supports natively. Features missing
+    /// from the set are either emulated by the default [`super::Driver`]
+    /// implementations or return [`crate::errors::DriverError::Unsupported`].
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct Capabilities: u32 {
+        /// Byte ranges are read without reading the whole file.
+        const RANGE_READS = 1;
+        /// Multipart uploads use the backend native mechanism.
+        const MULTIPART_UPLOADS = 1 << 1;
+        /// Files have a stable public URL.
+        const PUBLIC_URLS = 1 << 2;
+        /// Files have their own access level.
+        const OBJECT_ACL = 1 << 3;
+        /// Write preconditions are checked atomically.
+        const CONDITIONAL_WRITES = 1 << 4;
+        /// Files keep custom metadata, storage classes and encryption
+        /// settings.
+        const WRITE_METADATA = 1 << 5;
+        /// Expiring URLs can be signed, see [`PresignExt`].
+        const PRESIGN = 1 << 6;
+        /// Previous versions of files are kept, see [`VersioningExt`].
+        const VERSIONING = 1 << 7;
+        /// The last modification time of written files can be set with
+        /// [`super::WriteOptions::last_modified`].
+        const SET_LAST_MODIFIED = 1 << 8;
+    }
+}
+
+/// Extension trait of the drivers signing expiring URLs, so clients transfer
+/// files directly with the backend.
+#[async_trait::async_trait]
+pub trait PresignExt: Send + Sync {
+    /// Returns a URL granting read access to the file at the given path for
+    /// the given duration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is invalid or the URL could not be
+    /// signed.
+    async fn presign_read(&self, path: &ObjectPath, expires_in: Duration) -> DriverResult<String>;
+
+    /// Returns a URL granting write access to the file at the given path for
+    /// the given duration, with an HTTP `PUT` request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is invalid or the URL could not be
+    /// signed.
+    async fn presign_write(&self, path: &ObjectPath, expires_in: Duration) -> DriverResult<String>;
+}
+
+/// Struct representing a version of a file in a versioned container.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ObjectVersion {
+    /// The backend identifier of the version.
+    pub version_id: String,
+    /// The time the version was written.
+    pub last_modified: SystemTime,
+    /// The size of the version in bytes.
+    pub size: u64,
+    /// Whether the version is the current content of the file.
+    pub is_latest: bool,
+}
+
+/// Extension trait of the drivers keeping previous versions of files.
+#[async_trait::async_trait]
+pub trait VersioningExt: Send + Sync {
+    /// Lists the versions of the file at the given path, newest first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the versions could not be listed.
+    async fn list_versions(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectVersion>>;
+
+    /// Reads the given version of the file at the given path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the version does not exist or could not be read.
+    async fn read_version(&self, path: &ObjectPath, version_id: &str) -> DriverResult<Vec<u8>>;
+
+    /// Permanently deletes the given version of the file at the given path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the version could not be deleted.
+    async fn delete_version(&self, path: &ObjectPath, version_id: &str) -> DriverResult<()>;
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    use super::*;
+    use crate::StoreConfig;
+
+    #[tokio::test]
+    async fn can_discover_capabilities() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        assert_eq!(store.capabilities(), Capabilities::SET_LAST_MODIFIED);
+        assert!(store.as_presign().is_none());
+        assert!(store.as_versioning().is_none());
+
+        let tenant = store.scoped(Path::new("tenant")).unwrap();
+        assert_eq!(tenant.capabilities(), Capabilities::SET_LAST_MODIFIED);
+        assert!(tenant.as_presign().is_none());
+    }
+}
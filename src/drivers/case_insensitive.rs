@@ -0,0 +1,403 @@
+use std::{
+    ops::Range,
+    time::{Duration, SystemTime},
+};
+
+use bytes::Bytes;
+use percent_encoding::{utf8_percent_encode, CONTROLS};
+
+use super::{
+    capabilities::{Capabilities, ObjectVersion, PresignExt, VersioningExt},
+    Acl, ContainerOptions, Driver, DriverError, DriverResult, ObjectMetadata, ReadOptions,
+    RetentionPolicy, WriteOptions,
+};
+use crate::{
+    key::ObjectPath,
+    upload::{PendingUpload, UploadPart},
+};
+
+/// The metadata key holding the path a file was written with, before its
+/// case was normalized. Non ASCII characters are percent-encoded.
+pub const ORIGINAL_PATH_METADATA: &str = "original-path";
+
+/// The `CaseInsensitiveDriver` struct wraps a [`Driver`] and lowercases every
+/// path, so `Report.PDF` and `report.pdf` are the same file, as on Windows
+/// shares.
+///
+/// Files are stored and listed under their lowercased path. Backends with
+/// [`Capabilities::WRITE_METADATA`] keep the original path in the
+/// [`ORIGINAL_PATH_METADATA`] metadata of the file.
+#[allow(clippy::module_name_repetitions)]
+pub struct CaseInsensitiveDriver {
+    inner: Box<dyn Driver>,
+}
+
+impl Clone for CaseInsensitiveDriver {
+    fn clone(&self) -> Self {
+        Self {
+            inner: dyn_clone::clone_box(&*self.inner),
+        }
+    }
+}
+
+impl CaseInsensitiveDriver {
+    /// Creates a new [`CaseInsensitiveDriver`] wrapping the given driver.
+    #[must_use]
+    pub fn new(inner: Box<dyn Driver>) -> Self {
+        Self { inner }
+    }
+
+    /// Returns the write options recording the original path, when the
+    /// backend keeps metadata.
+    fn original_path_options(&self, path: &ObjectPath, options: &WriteOptions) -> WriteOptions {
+        let mut options = options.clone();
+        if self
+            .inner
+            .capabilities()
+            .contains(Capabilities::WRITE_METADATA)
+        {
+            options.metadata.insert(
+                ORIGINAL_PATH_METADATA.to_string(),
+                utf8_percent_encode(path.as_str(), CONTROLS).to_string(),
+            );
+        }
+        options
+    }
+}
+
+/// Returns the given path lowercased.
+fn fold(path: &ObjectPath) -> ObjectPath {
+    ObjectPath::new(&path.as_str().to_lowercase())
+}
+
+#[async_trait::async_trait]
+impl Driver for CaseInsensitiveDriver {
+    async fn read(&self, path: &ObjectPath) -> DriverResult<Bytes> {
+        self.inner.read(&fold(path)).await
+    }
+
+    async fn file_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.file_exists(&fold(path)).await
+    }
+
+    async fn write(&self, path: &ObjectPath, content: Bytes) -> DriverResult<()> {
+        let options = self.original_path_options(path, &WriteOptions::default());
+        if options.metadata.is_empty() {
+            self.inner.write(&fold(path), content).await
+        } else {
+            self.inner
+                .write_with_options(&fold(path), content, &options)
+                .await
+        }
+    }
+
+    async fn write_with_options(
+        &self,
+        path: &ObjectPath,
+        content: Bytes,
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        self.inner
+            .write_with_options(
+                &fold(path),
+                content,
+                &self.original_path_options(path, options),
+            )
+            .await
+    }
+
+    async fn write_with_acl(
+        &self,
+        path: &ObjectPath,
+        content: Bytes,
+        acl: &Acl,
+    ) -> DriverResult<()> {
+        self.inner.write_with_acl(&fold(path), content, acl).await
+    }
+
+    async fn set_acl(&self, path: &ObjectPath, acl: &Acl) -> DriverResult<()> {
+        self.inner.set_acl(&fold(path), acl).await
+    }
+
+    async fn delete(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.inner.delete(&fold(path)).await
+    }
+
+    async fn delete_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.inner.delete_directory(&fold(path)).await
+    }
+
+    async fn last_modified(&self, path: &ObjectPath) -> DriverResult<SystemTime> {
+        self.inner.last_modified(&fold(path)).await
+    }
+
+    async fn size(&self, path: &ObjectPath) -> DriverResult<u64> {
+        self.inner.size(&fold(path)).await
+    }
+
+    async fn create_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.inner.create_directory(&fold(path)).await
+    }
+
+    async fn directory_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.directory_exists(&fold(path)).await
+    }
+
+    async fn purge_all(&self) -> DriverResult<()> {
+        self.inner.purge_all().await
+    }
+
+    async fn set_last_modified(
+        &self,
+        path: &ObjectPath,
+        last_modified: SystemTime,
+    ) -> DriverResult<()> {
+        self.inner
+            .set_last_modified(&fold(path), last_modified)
+            .await
+    }
+
+    async fn delete_if_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.delete_if_exists(&fold(path)).await
+    }
+
+    async fn stat(&self, path: &ObjectPath) -> DriverResult<Option<ObjectMetadata>> {
+        self.inner.stat(&fold(path)).await
+    }
+
+    async fn read_if_modified_since(
+        &self,
+        path: &ObjectPath,
+        since: SystemTime,
+    ) -> DriverResult<Option<Bytes>> {
+        self.inner.read_if_modified_since(&fold(path), since).await
+    }
+
+    async fn read_with_options(
+        &self,
+        path: &ObjectPath,
+        options: &ReadOptions,
+    ) -> DriverResult<Bytes> {
+        self.inner.read_with_options(&fold(path), options).await
+    }
+
+    async fn read_range(&self, path: &ObjectPath, range: Range<u64>) -> DriverResult<Vec<u8>> {
+        self.inner.read_range(&fold(path), range).await
+    }
+
+    async fn list(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        self.inner.list(&fold(path)).await
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn as_presign(&self) -> Option<&dyn PresignExt> {
+        self.inner.as_presign().map(|_| self as &dyn PresignExt)
+    }
+
+    fn as_versioning(&self) -> Option<&dyn VersioningExt> {
+        self.inner
+            .as_versioning()
+            .map(|_| self as &dyn VersioningExt)
+    }
+
+    async fn public_url(&self, path: &ObjectPath) -> DriverResult<String> {
+        self.inner.public_url(&fold(path)).await
+    }
+
+    async fn container_exists(&self) -> DriverResult<bool> {
+        self.inner.container_exists().await
+    }
+
+    async fn ensure_container(&self, options: &ContainerOptions) -> DriverResult<()> {
+        self.inner.ensure_container(options).await
+    }
+
+    async fn health_check(&self) -> DriverResult<()> {
+        self.inner.health_check().await
+    }
+
+    async fn apply_retention_policies(&self, policies: &[RetentionPolicy]) -> DriverResult<()> {
+        let policies = policies
+            .iter()
+            .map(|policy| RetentionPolicy {
+                prefix: fold(&policy.prefix),
+                ..policy.clone()
+            })
+            .collect::<Vec<_>>();
+        self.inner.apply_retention_policies(&policies).await
+    }
+
+    async fn set_legal_hold(&self, path: &ObjectPath, hold: bool) -> DriverResult<()> {
+        self.inner.set_legal_hold(&fold(path), hold).await
+    }
+
+    async fn legal_hold(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.legal_hold(&fold(path)).await
+    }
+
+    async fn list_legal_holds(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        self.inner.list_legal_holds(&fold(path)).await
+    }
+
+    async fn create_upload(&self, path: &ObjectPath) -> DriverResult<String> {
+        self.inner.create_upload(&fold(path)).await
+    }
+
+    async fn create_upload_with_options(
+        &self,
+        path: &ObjectPath,
+        options: &WriteOptions,
+    ) -> DriverResult<String> {
+        self.inner
+            .create_upload_with_options(&fold(path), &self.original_path_options(path, options))
+            .await
+    }
+
+    async fn upload_part(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        part_number: u32,
+        content: Bytes,
+    ) -> DriverResult<UploadPart> {
+        self.inner
+            .upload_part(&fold(path), upload_id, part_number, content)
+            .await
+    }
+
+    async fn list_upload_parts(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+    ) -> DriverResult<Vec<UploadPart>> {
+        self.inner.list_upload_parts(&fold(path), upload_id).await
+    }
+
+    async fn complete_upload(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+    ) -> DriverResult<()> {
+        self.inner
+            .complete_upload(&fold(path), upload_id, parts)
+            .await
+    }
+
+    async fn complete_upload_with_options(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        self.inner
+            .complete_upload_with_options(
+                &fold(path),
+                upload_id,
+                parts,
+                &self.original_path_options(path, options),
+            )
+            .await
+    }
+
+    async fn list_uploads(&self, prefix: &ObjectPath) -> DriverResult<Vec<PendingUpload>> {
+        self.inner.list_uploads(&fold(prefix)).await
+    }
+
+    async fn abort_upload(&self, path: &ObjectPath, upload_id: &str) -> DriverResult<()> {
+        self.inner.abort_upload(&fold(path), upload_id).await
+    }
+}
+
+#[async_trait::async_trait]
+impl PresignExt for CaseInsensitiveDriver {
+    async fn presign_read(&self, path: &ObjectPath, expires_in: Duration) -> DriverResult<String> {
+        self.inner
+            .as_presign()
+            .ok_or(DriverError::Unsupported("presigned URLs"))?
+            .presign_read(&fold(path), expires_in)
+            .await
+    }
+
+    async fn presign_write(&self, path: &ObjectPath, expires_in: Duration) -> DriverResult<String> {
+        self.inner
+            .as_presign()
+            .ok_or(DriverError::Unsupported("presigned URLs"))?
+            .presign_write(&fold(path), expires_in)
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl VersioningExt for CaseInsensitiveDriver {
+    async fn list_versions(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectVersion>> {
+        self.inner
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .list_versions(&fold(path))
+            .await
+    }
+
+    async fn read_version(&self, path: &ObjectPath, version_id: &str) -> DriverResult<Vec<u8>> {
+        self.inner
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .read_version(&fold(path), version_id)
+            .await
+    }
+
+    async fn delete_version(&self, path: &ObjectPath, version_id: &str) -> DriverResult<()> {
+        self.inner
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .delete_version(&fold(path), version_id)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    use super::*;
+    use crate::StoreConfig;
+
+    #[tokio::test]
+    async fn can_ignore_key_case() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let shares = store.case_insensitive();
+
+        assert!(shares
+            .write(Path::new("Reports/Report.PDF"), "content")
+            .await
+            .is_ok());
+
+        assert_eq!(
+            shares
+                .read::<String>(Path::new("reports/report.pdf"))
+                .await
+                .unwrap(),
+            "content"
+        );
+        assert!(shares
+            .write(Path::new("REPORTS/report.pdf"), "updated")
+            .await
+            .is_ok());
+        assert_eq!(
+            shares.list(Path::new("Reports")).await.unwrap(),
+            vec![ObjectPath::new("reports/report.pdf")]
+        );
+        assert_eq!(
+            store
+                .read::<String>(Path::new("reports/report.pdf"))
+                .await
+                .unwrap(),
+            "updated"
+        );
+    }
+}
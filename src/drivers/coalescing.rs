@@ -0,0 +1,453 @@
+use std::{
+    collections::HashMap,
+    ops::Range,
+    path::PathBuf,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, SystemTime},
+};
+
+use bytes::Bytes;
+use futures::{
+    future::{BoxFuture, Shared},
+    FutureExt,
+};
+
+use super::{
+    capabilities::{Capabilities, ObjectVersion, PresignExt, VersioningExt},
+    Acl, ContainerOptions, Driver, DriverError, DriverResult, ObjectMetadata, ReadOptions,
+    RetentionPolicy, WriteOptions,
+};
+use crate::{
+    key::ObjectPath,
+    upload::{PendingUpload, UploadPart},
+};
+
+/// A backend read shared by the concurrent reads of a file.
+type Flight = Shared<BoxFuture<'static, Result<Bytes, Arc<DriverError>>>>;
+
+/// The `CoalescingDriver` struct wraps a [`Driver`] and coalesces the
+/// concurrent reads of a file into a single backend read, whose result is
+/// shared by all of them, e.g. to protect the backend from a stampede when a
+/// popular file is requested by many clients at once.
+///
+/// Only reads in flight are shared: a read started after the previous one
+/// completed reaches the backend again. When the shared read fails with
+/// another error than [`DriverError::ResourceNotFound`], each reader reads
+/// the file again on its own, as errors can't be shared. Writing or deleting a
+/// file through the driver detaches the reads in flight, so later reads see the
+/// change.
+#[allow(clippy::module_name_repetitions)]
+pub struct CoalescingDriver {
+    inner: Box<dyn Driver>,
+    flights: Arc<Mutex<HashMap<PathBuf, Flight>>>,
+}
+
+impl Clone for CoalescingDriver {
+    fn clone(&self) -> Self {
+        Self {
+            inner: dyn_clone::clone_box(&*self.inner),
+            flights: self.flights.clone(),
+        }
+    }
+}
+
+impl CoalescingDriver {
+    /// Creates a new [`CoalescingDriver`] coalescing the reads of the given
+    /// driver.
+    #[must_use]
+    pub fn new(inner: Box<dyn Driver>) -> Self {
+        Self {
+            inner,
+            flights: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn flights(&self) -> MutexGuard<'_, HashMap<PathBuf, Flight>> {
+        self.flights
+            .lock()
+            .expect("coalescing driver failed getting a lock")
+    }
+
+    fn forget(&self, path: &ObjectPath) {
+        self.flights().remove(&path.to_path_buf());
+    }
+}
+
+#[async_trait::async_trait]
+impl Driver for CoalescingDriver {
+    async fn read(&self, path: &ObjectPath) -> DriverResult<Bytes> {
+        let file = path.to_path_buf();
+        let flight = self
+            .flights()
+            .entry(file.clone())
+            .or_insert_with(|| {
+                let inner = dyn_clone::clone_box(&*self.inner);
+                let path = path.clone();
+                async move { inner.read(&path).await.map_err(Arc::new) }
+                    .boxed()
+                    .shared()
+            })
+            .clone();
+
+        let result = flight.clone().await;
+        {
+            let mut flights = self.flights();
+            if flights
+                .get(&file)
+                .is_some_and(|current| current.ptr_eq(&flight))
+            {
+                flights.remove(&file);
+            }
+        }
+        match result {
+            Ok(content) => Ok(content),
+            Err(error) if matches!(*error, DriverError::ResourceNotFound) => {
+                Err(DriverError::ResourceNotFound)
+            }
+            // errors can't be cloned, so each reader reads again to get its own
+            Err(_) => self.inner.read(path).await,
+        }
+    }
+
+    async fn file_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.file_exists(path).await
+    }
+
+    async fn write(&self, path: &ObjectPath, content: Bytes) -> DriverResult<()> {
+        self.forget(path);
+        self.inner.write(path, content).await
+    }
+
+    async fn write_with_options(
+        &self,
+        path: &ObjectPath,
+        content: Bytes,
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        self.forget(path);
+        self.inner.write_with_options(path, content, options).await
+    }
+
+    async fn write_with_acl(
+        &self,
+        path: &ObjectPath,
+        content: Bytes,
+        acl: &Acl,
+    ) -> DriverResult<()> {
+        self.forget(path);
+        self.inner.write_with_acl(path, content, acl).await
+    }
+
+    async fn set_acl(&self, path: &ObjectPath, acl: &Acl) -> DriverResult<()> {
+        self.inner.set_acl(path, acl).await
+    }
+
+    async fn delete(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.forget(path);
+        self.inner.delete(path).await
+    }
+
+    async fn delete_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.flights().clear();
+        self.inner.delete_directory(path).await
+    }
+
+    async fn last_modified(&self, path: &ObjectPath) -> DriverResult<SystemTime> {
+        self.inner.last_modified(path).await
+    }
+
+    async fn size(&self, path: &ObjectPath) -> DriverResult<u64> {
+        self.inner.size(path).await
+    }
+
+    async fn create_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.inner.create_directory(path).await
+    }
+
+    async fn directory_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.directory_exists(path).await
+    }
+
+    async fn purge_all(&self) -> DriverResult<()> {
+        self.flights().clear();
+        self.inner.purge_all().await
+    }
+
+    async fn set_last_modified(
+        &self,
+        path: &ObjectPath,
+        last_modified: SystemTime,
+    ) -> DriverResult<()> {
+        self.inner.set_last_modified(path, last_modified).await
+    }
+
+    async fn delete_if_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.forget(path);
+        self.inner.delete_if_exists(path).await
+    }
+
+    async fn stat(&self, path: &ObjectPath) -> DriverResult<Option<ObjectMetadata>> {
+        self.inner.stat(path).await
+    }
+
+    async fn read_if_modified_since(
+        &self,
+        path: &ObjectPath,
+        since: SystemTime,
+    ) -> DriverResult<Option<Bytes>> {
+        self.inner.read_if_modified_since(path, since).await
+    }
+
+    async fn read_with_options(
+        &self,
+        path: &ObjectPath,
+        options: &ReadOptions,
+    ) -> DriverResult<Bytes> {
+        self.inner.read_with_options(path, options).await
+    }
+
+    async fn read_range(&self, path: &ObjectPath, range: Range<u64>) -> DriverResult<Vec<u8>> {
+        self.inner.read_range(path, range).await
+    }
+
+    async fn list(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        self.inner.list(path).await
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn as_presign(&self) -> Option<&dyn PresignExt> {
+        self.inner.as_presign().map(|_| self as &dyn PresignExt)
+    }
+
+    fn as_versioning(&self) -> Option<&dyn VersioningExt> {
+        self.inner
+            .as_versioning()
+            .map(|_| self as &dyn VersioningExt)
+    }
+
+    async fn public_url(&self, path: &ObjectPath) -> DriverResult<String> {
+        self.inner.public_url(path).await
+    }
+
+    async fn container_exists(&self) -> DriverResult<bool> {
+        self.inner.container_exists().await
+    }
+
+    async fn ensure_container(&self, options: &ContainerOptions) -> DriverResult<()> {
+        self.inner.ensure_container(options).await
+    }
+
+    async fn health_check(&self) -> DriverResult<()> {
+        self.inner.health_check().await
+    }
+
+    async fn apply_retention_policies(&self, policies: &[RetentionPolicy]) -> DriverResult<()> {
+        self.inner.apply_retention_policies(policies).await
+    }
+
+    async fn set_legal_hold(&self, path: &ObjectPath, hold: bool) -> DriverResult<()> {
+        self.inner.set_legal_hold(path, hold).await
+    }
+
+    async fn legal_hold(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.legal_hold(path).await
+    }
+
+    async fn list_legal_holds(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        self.inner.list_legal_holds(path).await
+    }
+
+    async fn create_upload(&self, path: &ObjectPath) -> DriverResult<String> {
+        self.inner.create_upload(path).await
+    }
+
+    async fn create_upload_with_options(
+        &self,
+        path: &ObjectPath,
+        options: &WriteOptions,
+    ) -> DriverResult<String> {
+        self.inner.create_upload_with_options(path, options).await
+    }
+
+    async fn upload_part(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        part_number: u32,
+        content: Bytes,
+    ) -> DriverResult<UploadPart> {
+        self.inner
+            .upload_part(path, upload_id, part_number, content)
+            .await
+    }
+
+    async fn list_upload_parts(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+    ) -> DriverResult<Vec<UploadPart>> {
+        self.inner.list_upload_parts(path, upload_id).await
+    }
+
+    async fn complete_upload(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+    ) -> DriverResult<()> {
+        self.forget(path);
+        self.inner.complete_upload(path, upload_id, parts).await
+    }
+
+    async fn complete_upload_with_options(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        self.forget(path);
+        self.inner
+            .complete_upload_with_options(path, upload_id, parts, options)
+            .await
+    }
+
+    async fn list_uploads(&self, prefix: &ObjectPath) -> DriverResult<Vec<PendingUpload>> {
+        self.inner.list_uploads(prefix).await
+    }
+
+    async fn abort_upload(&self, path: &ObjectPath, upload_id: &str) -> DriverResult<()> {
+        self.inner.abort_upload(path, upload_id).await
+    }
+}
+
+#[async_trait::async_trait]
+impl PresignExt for CoalescingDriver {
+    async fn presign_read(&self, path: &ObjectPath, expires_in: Duration) -> DriverResult<String> {
+        self.inner
+            .as_presign()
+            .ok_or(DriverError::Unsupported("presigned URLs"))?
+            .presign_read(path, expires_in)
+            .await
+    }
+
+    async fn presign_write(&self, path: &ObjectPath, expires_in: Duration) -> DriverResult<String> {
+        self.inner
+            .as_presign()
+            .ok_or(DriverError::Unsupported("presigned URLs"))?
+            .presign_write(path, expires_in)
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl VersioningExt for CoalescingDriver {
+    async fn list_versions(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectVersion>> {
+        self.inner
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .list_versions(path)
+            .await
+    }
+
+    async fn read_version(&self, path: &ObjectPath, version_id: &str) -> DriverResult<Vec<u8>> {
+        self.inner
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .read_version(path, version_id)
+            .await
+    }
+
+    async fn delete_version(&self, path: &ObjectPath, version_id: &str) -> DriverResult<()> {
+        self.inner
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .delete_version(path, version_id)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{
+        path::Path,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+    use crate::{store::Store, StoreConfig};
+
+    /// A driver over an in-memory store, records included, counting its
+    /// reads, which are slowed down so concurrent reads overlap.
+    #[derive(Clone)]
+    struct SlowDriver {
+        inner: Store,
+        reads: Arc<AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl Driver for SlowDriver {
+        async fn read(&self, path: &ObjectPath) -> DriverResult<Bytes> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.inner.read_bytes(path).await
+        }
+
+        async fn file_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+            self.inner.file_exists(path).await
+        }
+
+        async fn write(&self, path: &ObjectPath, content: Bytes) -> DriverResult<()> {
+            self.inner.write(path, content).await
+        }
+
+        async fn delete(&self, path: &ObjectPath) -> DriverResult<()> {
+            self.inner.delete(path).await
+        }
+
+        async fn delete_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+            self.inner.delete_directory(path).await
+        }
+
+        async fn last_modified(&self, path: &ObjectPath) -> DriverResult<SystemTime> {
+            self.inner.last_modified(path).await
+        }
+
+        async fn list(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+            self.inner.list(path).await
+        }
+    }
+
+    #[tokio::test]
+    async fn can_coalesce_concurrent_reads() {
+        let reads = Arc::new(AtomicUsize::new(0));
+        let store = StoreConfig::with_driver(Box::new(SlowDriver {
+            inner: StoreConfig::InMem().build().await.unwrap().records(),
+            reads: reads.clone(),
+        }))
+        .with_coalescing();
+        let path = Path::new("popular.txt");
+        store.write(path, "content").await.unwrap();
+
+        let contents = futures::future::join_all((0..10).map(|_| store.read::<String>(path))).await;
+        assert!(contents
+            .iter()
+            .all(|content| content.as_deref().ok() == Some("content")));
+        assert_eq!(reads.load(Ordering::SeqCst), 1);
+
+        assert_eq!(store.read::<String>(path).await.unwrap(), "content");
+        assert_eq!(reads.load(Ordering::SeqCst), 2);
+
+        let missing =
+            futures::future::join_all((0..3).map(|_| store.read::<String>(Path::new("missing"))))
+                .await;
+        assert!(missing
+            .iter()
+            .all(|result| matches!(result, Err(DriverError::ResourceNotFound))));
+        assert_eq!(reads.load(Ordering::SeqCst), 3);
+    }
+}
@@ -1,18 +1,33 @@
 use std::{
-    io::ErrorKind,
+    io::{ErrorKind, SeekFrom},
+    ops::Range,
     path::{Path, PathBuf},
     time::SystemTime,
 };
 
 use async_trait::async_trait;
-use tokio::fs;
+use bytes::Bytes;
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+};
 
-use super::{Driver, DriverError, DriverResult};
-use crate::contents::Contents;
+use super::{
+    capabilities::Capabilities, check_legal_hold, check_no_legal_holds, directory_key,
+    ContainerOptions, Driver, DriverError, DriverResult, ObjectMetadata,
+};
+use crate::{
+    key::{KeyRules, ObjectPath},
+    upload::UploadPart,
+};
 
 /// Configuration parameters for initializing a `DiskDriver`.
+#[derive(Clone)]
 pub struct Config {
     pub location: PathBuf,
+    /// Optional URL prefix the storage root is served under, e.g. `/static`,
+    /// used to build public URLs.
+    pub public_url: Option<String>,
 }
 
 /// The `DiskDriver` struct represents a disk-based implementation of the
@@ -24,15 +39,8 @@ pub struct Config {
 pub struct DiskDriver {
     /// The location on the disk where the `DiskDriver` will operate.
     location: PathBuf,
-}
-
-impl From<ErrorKind> for DriverError {
-    fn from(kind: ErrorKind) -> Self {
-        match kind {
-            ErrorKind::NotFound => Self::ResourceNotFound,
-            _ => kind.into(),
-        }
-    }
+    /// The URL prefix the storage root is served under.
+    public_url: Option<String>,
 }
 
 impl DiskDriver {
@@ -55,8 +63,25 @@ impl DiskDriver {
 
         Ok(Self {
             location: config.location,
+            public_url: config.public_url,
         })
     }
+
+    /// Resolves the given path under the storage root, rejecting paths that
+    /// are empty or escape the root with `..` components. The key is encoded
+    /// with [`KeyRules::Disk`], so it is stored under the same name whatever
+    /// the platform.
+    fn resolve(&self, path: &ObjectPath) -> DriverResult<PathBuf> {
+        let encoded = path.to_key()?.encode(KeyRules::Disk)?;
+        Ok(self.location.join(encoded.split('/').collect::<PathBuf>()))
+    }
+
+    /// Returns the key of the file at the given location under the storage
+    /// root, the reverse of [`Self::resolve`].
+    fn key_of(&self, location: &Path) -> ObjectPath {
+        let relative = location.strip_prefix(&self.location).unwrap_or(location);
+        super::decode_key(ObjectPath::from(relative).as_str(), KeyRules::Disk)
+    }
 }
 
 #[async_trait]
@@ -68,14 +93,14 @@ impl Driver for DiskDriver {
     ///
     /// Returns an error if there is an issue reading from the file or decoding
     /// its contents.
-    async fn read(&self, path: &Path) -> DriverResult<Vec<u8>> {
-        let path = self.location.join(path);
+    async fn read(&self, path: &ObjectPath) -> DriverResult<Bytes> {
+        let path = self.resolve(path)?;
 
         let content = match fs::read(path).await {
             Ok(content) => content,
             Err(err) => return Err(err.kind().into()),
         };
-        Ok(Contents::from(content).into())
+        Ok(content.into())
     }
 
     /// Checks if a file exists at the specified path within the disk-based
@@ -88,12 +113,11 @@ impl Driver for DiskDriver {
     ///
     /// Returns an error if there is an issue checking the existence of the
     /// file.
-    async fn file_exists(&self, path: &Path) -> DriverResult<bool> {
-        if !path.is_file() {
-            return Ok(false);
-        }
-
-        Ok(path.exists())
+    async fn file_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        let path = self.resolve(path)?;
+        Ok(fs::metadata(path)
+            .await
+            .is_ok_and(|metadata| metadata.is_file()))
     }
 
     /// Writes the provided content to a file at the specified path within the
@@ -106,8 +130,9 @@ impl Driver for DiskDriver {
     ///
     /// Returns an error if there is any issue creating directories, writing to
     /// the file, or handling other I/O-related errors.
-    async fn write(&self, path: &Path, content: Vec<u8>) -> DriverResult<()> {
-        let path = self.location.join(path);
+    async fn write(&self, path: &ObjectPath, content: Bytes) -> DriverResult<()> {
+        check_legal_hold(self, path).await?;
+        let path = self.resolve(path)?;
         if let Some(parent) = path.parent() {
             if !parent.exists() {
                 if let Err(err) = fs::create_dir_all(parent).await {
@@ -131,13 +156,14 @@ impl Driver for DiskDriver {
     ///
     /// If the file does not exist, the error variant
     /// `DriverError::ResourceNotFound` is returned.
-    async fn delete(&self, path: &Path) -> DriverResult<()> {
-        let path = self.location.join(path);
-        if !path.exists() {
+    async fn delete(&self, path: &ObjectPath) -> DriverResult<()> {
+        let location = self.resolve(path)?;
+        if !location.exists() {
             return Err(DriverError::ResourceNotFound);
         };
+        check_legal_hold(self, path).await?;
 
-        match fs::remove_file(path).await {
+        match fs::remove_file(location).await {
             Ok(()) => Ok(()),
             Err(err) => Err(err.kind().into()),
         }
@@ -153,19 +179,112 @@ impl Driver for DiskDriver {
     ///
     /// If the directory does not exist, the error variant
     /// `DriverError::DirectoryNotFound` is returned.
-    async fn delete_directory(&self, path: &Path) -> DriverResult<()> {
-        let path = self.location.join(path);
+    async fn delete_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        directory_key(path)?;
+        let location = self.resolve(path)?;
 
-        if !path.exists() {
+        if !location.exists() {
             return Err(DriverError::ResourceNotFound);
         };
+        check_no_legal_holds(self, path).await?;
 
-        match fs::remove_dir_all(path).await {
+        // a symbolic link may still point at the root or at one of its parents
+        if let (Ok(path), Ok(root)) = (
+            fs::canonicalize(&location).await,
+            fs::canonicalize(&self.location).await,
+        ) {
+            if root.starts_with(path) {
+                return Err(DriverError::RefusingToDeleteRoot);
+            }
+        }
+
+        match fs::remove_dir_all(location).await {
             Ok(()) => Ok(()),
             Err(err) => Err(err.kind().into()),
         }
     }
 
+    /// Sets the modification time of the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist or its modification time
+    /// could not be set.
+    async fn set_last_modified(
+        &self,
+        path: &ObjectPath,
+        last_modified: SystemTime,
+    ) -> DriverResult<()> {
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .open(self.resolve(path)?)
+            .await
+            .map_err(|err| DriverError::from(err.kind()))?
+            .into_std()
+            .await;
+        tokio::task::spawn_blocking(move || file.set_modified(last_modified))
+            .await
+            .map_err(|err| DriverError::Any(Box::new(err)))?
+            .map_err(|err| DriverError::from(err.kind()))
+    }
+
+    /// Creates the directory and its missing parents under the storage root.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory could not be created.
+    async fn create_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        let path = match path.to_prefix()? {
+            Some(_) => self.resolve(path)?,
+            None => self.location.clone(),
+        };
+        fs::create_dir_all(path)
+            .await
+            .map_err(|err| DriverError::from(err.kind()))
+    }
+
+    /// Checks whether a directory exists at the given path under the storage
+    /// root.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is invalid.
+    async fn directory_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        let path = match path.to_prefix()? {
+            Some(_) => self.resolve(path)?,
+            None => self.location.clone(),
+        };
+        Ok(fs::metadata(path)
+            .await
+            .is_ok_and(|metadata| metadata.is_dir()))
+    }
+
+    /// Deletes every file and directory under the storage root, keeping the
+    /// root itself.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the root could not be read or an entry could not
+    /// be deleted.
+    async fn purge_all(&self) -> DriverResult<()> {
+        check_no_legal_holds(self, &ObjectPath::default()).await?;
+        let mut entries = match fs::read_dir(&self.location).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.kind().into()),
+        };
+
+        while let Some(entry) = entries.next_entry().await.map_err(|err| err.kind())? {
+            let removed = if entry.file_type().await.map_err(|err| err.kind())?.is_dir() {
+                fs::remove_dir_all(entry.path()).await
+            } else {
+                fs::remove_file(entry.path()).await
+            };
+            removed.map_err(|err| err.kind())?;
+        }
+        Ok(())
+    }
+
     /// Retrieves the last modification time of the file at the specified path
     /// within the disk-based storage. # Errors
     ///
@@ -174,8 +293,8 @@ impl Driver for DiskDriver {
     ///
     /// If the file does not exist, the error variant
     /// `DriverError::ResourceNotFound` is returned.
-    async fn last_modified(&self, path: &Path) -> DriverResult<SystemTime> {
-        let path = self.location.join(path);
+    async fn last_modified(&self, path: &ObjectPath) -> DriverResult<SystemTime> {
+        let path = self.resolve(path)?;
         if !path.exists() {
             return Err(DriverError::ResourceNotFound);
         }
@@ -190,4 +309,247 @@ impl Driver for DiskDriver {
             Err(err) => Err(err.kind().into()),
         }
     }
+
+    /// Returns the size of the file from its metadata.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist.
+    async fn size(&self, path: &ObjectPath) -> DriverResult<u64> {
+        match fs::metadata(self.resolve(path)?).await {
+            Ok(metadata) => Ok(metadata.len()),
+            Err(err) => Err(err.kind().into()),
+        }
+    }
+
+    /// Returns the size and the modification time of the file from a single
+    /// metadata lookup. Directories are reported as missing.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the metadata could not be read.
+    async fn stat(&self, path: &ObjectPath) -> DriverResult<Option<ObjectMetadata>> {
+        let metadata = match fs::metadata(self.resolve(path)?).await {
+            Ok(metadata) if metadata.is_file() => metadata,
+            Ok(_) => return Ok(None),
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.kind().into()),
+        };
+
+        Ok(Some(ObjectMetadata {
+            size: metadata.len(),
+            last_modified: metadata.modified().map_err(|err| err.kind())?,
+            content_type: None,
+            etag: None,
+            storage_class: None,
+            content_md5: None,
+        }))
+    }
+
+    /// Reads the given byte range of the file, seeking to the range start.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist or could not be read.
+    async fn read_range(&self, path: &ObjectPath, range: Range<u64>) -> DriverResult<Vec<u8>> {
+        let mut file = fs::File::open(self.resolve(path)?)
+            .await
+            .map_err(|err| DriverError::from(err.kind()))?;
+        file.seek(SeekFrom::Start(range.start))
+            .await
+            .map_err(|err| DriverError::from(err.kind()))?;
+
+        let mut content = Vec::new();
+        file.take(range.end.saturating_sub(range.start))
+            .read_to_end(&mut content)
+            .await
+            .map_err(|err| DriverError::from(err.kind()))?;
+        Ok(content)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        let mut capabilities = Capabilities::RANGE_READS | Capabilities::SET_LAST_MODIFIED;
+        capabilities.set(Capabilities::PUBLIC_URLS, self.public_url.is_some());
+        capabilities
+    }
+
+    /// Returns the URL of the file under the configured public URL prefix.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::Unsupported`] if no public URL prefix is
+    /// configured.
+    async fn public_url(&self, path: &ObjectPath) -> DriverResult<String> {
+        let base_url = self
+            .public_url
+            .as_deref()
+            .ok_or(DriverError::Unsupported("public url"))?;
+
+        super::join_url(base_url, path, KeyRules::Disk)
+    }
+
+    /// Lists all the files under the given directory within the disk-based
+    /// storage, relative to the storage root.
+    ///
+    /// A missing directory is listed as empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is any issue reading the directories.
+    async fn list(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        let directory = if path.to_prefix()?.is_some() {
+            self.resolve(path)?
+        } else {
+            self.location.clone()
+        };
+
+        if !directory.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut files = Vec::new();
+        let mut pending = vec![directory];
+        while let Some(directory) = pending.pop() {
+            let mut entries = fs::read_dir(&directory)
+                .await
+                .map_err(|err| DriverError::from(err.kind()))?;
+
+            while let Some(entry) = entries
+                .next_entry()
+                .await
+                .map_err(|err| DriverError::from(err.kind()))?
+            {
+                let path = entry.path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else {
+                    files.push(self.key_of(&path));
+                }
+            }
+        }
+
+        files.sort();
+        Ok(files)
+    }
+
+    /// Checks whether the storage root directory exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the storage root metadata could not be read.
+    async fn container_exists(&self) -> DriverResult<bool> {
+        match fs::metadata(&self.location).await {
+            Ok(metadata) => Ok(metadata.is_dir()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err.kind().into()),
+        }
+    }
+
+    /// Creates the storage root directory when it doesn't exist. The region and
+    /// ACL options are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory could not be created.
+    async fn ensure_container(&self, _options: &ContainerOptions) -> DriverResult<()> {
+        match fs::create_dir_all(&self.location).await {
+            Ok(()) => Ok(()),
+            Err(err) => Err(err.kind().into()),
+        }
+    }
+
+    /// Verifies that the storage root exists and is a directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the storage root is missing or is not a directory.
+    async fn health_check(&self) -> DriverResult<()> {
+        let metadata = match fs::metadata(&self.location).await {
+            Ok(metadata) => metadata,
+            Err(err) => return Err(err.kind().into()),
+        };
+
+        if metadata.is_dir() {
+            Ok(())
+        } else {
+            Err(DriverError::ResourceNotFound)
+        }
+    }
+
+    /// Completes a multipart upload by appending the parts to a temporary file
+    /// one at a time, then moving it into place.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a part is missing or the file could not be written.
+    async fn complete_upload(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+    ) -> DriverResult<()> {
+        check_legal_hold(self, path).await?;
+        let target = self.resolve(path)?;
+        let assembled = self
+            .resolve(&super::upload_path(upload_id)?)?
+            .join("assembled");
+
+        let mut file = fs::File::create(&assembled)
+            .await
+            .map_err(|err| DriverError::from(err.kind()))?;
+        for part in parts {
+            let part_path = self.resolve(&super::upload_part_path(upload_id, part.part_number)?)?;
+            let content = fs::read(part_path)
+                .await
+                .map_err(|err| DriverError::from(err.kind()))?;
+            file.write_all(&content)
+                .await
+                .map_err(|err| DriverError::from(err.kind()))?;
+        }
+        file.flush()
+            .await
+            .map_err(|err| DriverError::from(err.kind()))?;
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|err| DriverError::from(err.kind()))?;
+        }
+        fs::rename(&assembled, &target)
+            .await
+            .map_err(|err| DriverError::from(err.kind()))?;
+
+        self.abort_upload(path, upload_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn can_resolve_encoded_keys() {
+        let driver = DiskDriver {
+            location: PathBuf::from("storage"),
+            public_url: None,
+        };
+
+        let path = ObjectPath::new("docs/a:b%41.txt");
+        let location = driver.resolve(&path).unwrap();
+        assert_eq!(
+            location,
+            PathBuf::from("storage").join("docs").join("a%3Ab%2541.txt")
+        );
+        assert_eq!(driver.key_of(&location), path);
+
+        assert!(matches!(
+            driver.resolve(&ObjectPath::new("../escape.txt")),
+            Err(DriverError::InvalidPath)
+        ));
+        assert!(matches!(
+            driver.resolve(&ObjectPath::default()),
+            Err(DriverError::InvalidPath)
+        ));
+    }
 }
@@ -0,0 +1,209 @@
+//! # Disk Signer Module
+//!
+//! The `disk_signer` module defines [`DiskSigner`], which generates and
+//! verifies expiring HMAC-SHA256 tokens for files of the disk driver.
+//!
+//! Cloud drivers hand out expiring URLs signed by the provider. With the disk
+//! driver the application serves the files itself, so it signs a token with
+//! [`DiskSigner::sign`] when building the URL and checks it with
+//! [`DiskSigner::verify`] before serving the file, e.g. with
+//! [`crate::store::Store::serve`].
+//!
+//! A token has the form `<expiry>.<signature>.<key>`, where the expiry is a
+//! unix timestamp, the signature is hex encoded and the key is
+//! percent-encoded, so the token is safe to embed in a URL.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::{path::Path, time::Duration};
+//! use active_storage::{drivers::disk_signer::DiskSigner, key::ObjectPath};
+//!
+//! let signer = DiskSigner::new("my secret");
+//! let token = signer
+//!     .sign(Path::new("images/logo.png"), Duration::from_secs(300))
+//!     .unwrap();
+//!
+//! assert_eq!(signer.verify(&token).unwrap(), ObjectPath::new("images/logo.png"));
+//! ```
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use sha2::Sha256;
+
+use crate::{
+    clock,
+    errors::{DriverError, DriverResult},
+    hex,
+    key::{Key, ObjectPath},
+    mac,
+};
+
+/// Characters left unencoded in the token key.
+const TOKEN_KEY: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~')
+    .remove(b'/');
+
+/// Struct signing and verifying expiring access tokens of disk files.
+#[derive(Clone)]
+pub struct DiskSigner {
+    secret: Vec<u8>,
+}
+
+impl std::fmt::Debug for DiskSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DiskSigner").finish_non_exhaustive()
+    }
+}
+
+impl DiskSigner {
+    /// Creates a new [`DiskSigner`] with the given secret. The secret must be
+    /// kept private and shared by every server verifying the tokens.
+    #[must_use]
+    pub fn new<S: AsRef<[u8]>>(secret: S) -> Self {
+        Self {
+            secret: secret.as_ref().to_vec(),
+        }
+    }
+
+    /// Returns a token granting access to the file at the given path for the
+    /// given duration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is invalid.
+    pub fn sign(&self, path: impl Into<ObjectPath>, expires_in: Duration) -> DriverResult<String> {
+        self.sign_until(path, clock::now() + expires_in)
+    }
+
+    /// Returns a token granting access to the file at the given path until the
+    /// given time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is invalid.
+    pub fn sign_until(
+        &self,
+        path: impl Into<ObjectPath>,
+        expires_at: SystemTime,
+    ) -> DriverResult<String> {
+        let key = path.into().to_key()?;
+        let expires = expires_at
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+
+        Ok(format!(
+            "{expires}.{}.{}",
+            hex::encode(&self.mac(key.as_str(), expires).finalize().into_bytes()),
+            utf8_percent_encode(key.as_str(), TOKEN_KEY)
+        ))
+    }
+
+    /// Verifies the given token, returning the path of the file it grants
+    /// access to.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::InvalidToken`] if the token is malformed or its
+    /// signature doesn't match, and [`DriverError::TokenExpired`] if it is
+    /// past its expiry.
+    pub fn verify(&self, token: &str) -> DriverResult<ObjectPath> {
+        let mut parts = token.splitn(3, '.');
+        let (Some(expires), Some(signature), Some(key)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(DriverError::InvalidToken);
+        };
+        let expires = expires
+            .parse::<u64>()
+            .map_err(|_| DriverError::InvalidToken)?;
+        let signature = hex::decode(signature).ok_or(DriverError::InvalidToken)?;
+        let key = percent_decode_str(key)
+            .decode_utf8()
+            .map_err(|_| DriverError::InvalidToken)?;
+
+        // the signature is checked first, so a forged token never reports
+        // whether it expired
+        self.mac(&key, expires)
+            .verify_slice(&signature)
+            .map_err(|_| DriverError::InvalidToken)?;
+
+        let now = clock::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        if now > expires {
+            return Err(DriverError::TokenExpired);
+        }
+
+        Ok(ObjectPath::from(&Key::new(&key)?))
+    }
+
+    /// Returns the MAC of the given key and expiry.
+    fn mac(&self, key: &str, expires: u64) -> Hmac<Sha256> {
+        mac::hmac_sha256(
+            &self.secret,
+            &[key.as_bytes(), expires.to_string().as_bytes()],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn can_sign_and_verify() {
+        let signer = DiskSigner::new("secret");
+        let path = Path::new("folder/my file.txt");
+
+        let token = signer.sign(path, Duration::from_secs(60)).unwrap();
+        assert!(!token.contains(' '));
+        assert_eq!(signer.verify(&token).unwrap(), ObjectPath::from(path));
+
+        assert!(matches!(
+            DiskSigner::new("other").verify(&token),
+            Err(DriverError::InvalidToken)
+        ));
+        assert!(matches!(
+            signer.verify(&token.replace("my%20file", "other")),
+            Err(DriverError::InvalidToken)
+        ));
+        assert!(matches!(
+            signer.verify("invalid"),
+            Err(DriverError::InvalidToken)
+        ));
+    }
+
+    #[test]
+    fn can_verify_percent_encoded_names() {
+        let signer = DiskSigner::new("secret");
+        let token = signer
+            .sign(Path::new("a%20b.txt"), Duration::from_secs(60))
+            .unwrap();
+
+        assert_eq!(
+            signer.verify(&token).unwrap(),
+            ObjectPath::from("a%20b.txt")
+        );
+    }
+
+    #[test]
+    fn can_reject_expired_tokens() {
+        let signer = DiskSigner::new("secret");
+        let token = signer
+            .sign_until(Path::new("file.txt"), UNIX_EPOCH + Duration::from_secs(1))
+            .unwrap();
+
+        assert!(matches!(
+            signer.verify(&token),
+            Err(DriverError::TokenExpired)
+        ));
+    }
+}
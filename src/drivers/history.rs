@@ -0,0 +1,578 @@
+use std::{
+    ops::Range,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bytes::Bytes;
+use sha2::{Digest, Sha256};
+
+use super::{
+    capabilities::{Capabilities, ObjectVersion, PresignExt, VersioningExt},
+    Acl, ContainerOptions, Driver, DriverError, DriverResult, ObjectMetadata, ReadOptions,
+    RetentionPolicy, WriteOptions,
+};
+use crate::{
+    clock, hex,
+    key::ObjectPath,
+    upload::{PendingUpload, UploadPart},
+};
+
+/// The prefix of the history records, see [`HistoryDriver`].
+pub const HISTORY_PREFIX: &str = ".active-storage-history";
+
+/// Enum representing the kind of a recorded mutation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    /// The file was written or overwritten.
+    Write,
+    /// The file was deleted.
+    Delete,
+}
+
+impl Operation {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Write => "write",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+/// Struct representing a mutation of a file recorded by [`HistoryDriver`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HistoryEntry {
+    /// The kind of the mutation.
+    pub operation: Operation,
+    /// The time of the mutation, to the millisecond.
+    pub at: SystemTime,
+    /// The hex encoded SHA-256 of the content before the mutation, `None`
+    /// when the file didn't exist.
+    pub before: Option<String>,
+    /// The hex encoded SHA-256 of the content after the mutation, `None`
+    /// after a delete, and after a multipart upload, whose content is not
+    /// read back.
+    pub after: Option<String>,
+}
+
+impl HistoryEntry {
+    /// Returns the stored record of the entry, one `name=value` line per
+    /// field.
+    fn to_record(&self) -> String {
+        format!(
+            "operation={}\nat={}\nbefore={}\nafter={}\n",
+            self.operation.as_str(),
+            millis(self.at),
+            self.before.as_deref().unwrap_or_default(),
+            self.after.as_deref().unwrap_or_default(),
+        )
+    }
+
+    /// Parses a stored record, returning `None` when it is malformed.
+    fn from_record(record: &str) -> Option<Self> {
+        let fields = record
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .collect::<std::collections::BTreeMap<_, _>>();
+        let checksum = |name: &str| {
+            fields
+                .get(name)
+                .filter(|value| !value.is_empty())
+                .map(ToString::to_string)
+        };
+        Some(Self {
+            operation: match *fields.get("operation")? {
+                "write" => Operation::Write,
+                "delete" => Operation::Delete,
+                _ => return None,
+            },
+            at: UNIX_EPOCH + Duration::from_millis(fields.get("at")?.parse().ok()?),
+            before: checksum("before"),
+            after: checksum("after"),
+        })
+    }
+}
+
+/// The `HistoryDriver` struct wraps a [`Driver`] and records every mutation
+/// of a file, with the checksums of its content before and after, under
+/// [`HISTORY_PREFIX`]. This gives an audit trail of the changes even on
+/// backends without versioning; the previous contents are not kept.
+///
+/// The before checksum is taken from the latest record of the file, so
+/// changes made without the driver are not seen. The records are hidden from
+/// [`Driver::list`] and kept when the files are deleted, including by
+/// [`Driver::purge_all`].
+#[allow(clippy::module_name_repetitions)]
+pub struct HistoryDriver {
+    inner: Box<dyn Driver>,
+}
+
+impl Clone for HistoryDriver {
+    fn clone(&self) -> Self {
+        Self {
+            inner: dyn_clone::clone_box(&*self.inner),
+        }
+    }
+}
+
+impl HistoryDriver {
+    /// Creates a new [`HistoryDriver`] recording the mutations of the given
+    /// driver.
+    #[must_use]
+    pub fn new(inner: Box<dyn Driver>) -> Self {
+        Self { inner }
+    }
+
+    /// Returns the checksum of the current content of the file, or `None`
+    /// when it doesn't exist.
+    async fn checksum(&self, path: &ObjectPath) -> DriverResult<Option<String>> {
+        match entries(&*self.inner, path).await?.pop() {
+            Some(entry) if entry.operation == Operation::Delete => return Ok(None),
+            Some(HistoryEntry {
+                after: Some(after), ..
+            }) => return Ok(Some(after)),
+            _ => {}
+        }
+        match self.inner.read(path).await {
+            Ok(content) => Ok(Some(checksum(&content))),
+            Err(DriverError::ResourceNotFound) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns the files under the given directory with their checksums.
+    async fn checksums(
+        &self,
+        path: &ObjectPath,
+    ) -> DriverResult<Vec<(ObjectPath, Option<String>)>> {
+        let mut checksums = Vec::new();
+        for file in self.list(path).await? {
+            if super::is_directory_marker(&file) {
+                continue;
+            }
+            let before = self.checksum(&file).await?;
+            checksums.push((file, before));
+        }
+        Ok(checksums)
+    }
+
+    async fn record(
+        &self,
+        path: &ObjectPath,
+        operation: Operation,
+        before: Option<String>,
+        after: Option<String>,
+    ) -> DriverResult<()> {
+        let at = clock::now();
+        let entry = HistoryEntry {
+            operation,
+            at: UNIX_EPOCH + Duration::from_millis(millis(at)),
+            before,
+            after,
+        };
+        let id = format!("{:016x}-{}", millis(at), super::unique_id());
+        self.inner
+            .write(
+                &history_folder(path).join(id),
+                Bytes::from(entry.to_record()),
+            )
+            .await
+    }
+
+    async fn record_deletes(&self, deleted: Vec<(ObjectPath, Option<String>)>) -> DriverResult<()> {
+        for (file, before) in deleted {
+            self.record(&file, Operation::Delete, before, None).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the recorded mutations of the file at the given path, oldest first.
+pub(crate) async fn entries(
+    driver: &dyn Driver,
+    path: &ObjectPath,
+) -> DriverResult<Vec<HistoryEntry>> {
+    let folder = history_folder(path);
+    let mut records = driver
+        .list(&folder)
+        .await?
+        .into_iter()
+        // records of the files under a folder of the same name are skipped
+        .filter(|record| record.parent().as_ref() == Some(&folder))
+        .collect::<Vec<_>>();
+    records.sort();
+
+    let mut entries = Vec::with_capacity(records.len());
+    for record in records {
+        let content = driver.read(&record).await?;
+        if let Some(entry) = std::str::from_utf8(&content)
+            .ok()
+            .and_then(HistoryEntry::from_record)
+        {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// Returns the folder of the history records of the file at the given path.
+fn history_folder(path: &ObjectPath) -> ObjectPath {
+    ObjectPath::new(HISTORY_PREFIX).join(path)
+}
+
+/// Returns the hex encoded SHA-256 of the content.
+fn checksum(content: &[u8]) -> String {
+    hex::encode(&Sha256::digest(content))
+}
+
+/// Returns the milliseconds since the Unix epoch of the given time.
+fn millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |duration| {
+        u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+    })
+}
+
+#[async_trait::async_trait]
+impl Driver for HistoryDriver {
+    async fn read(&self, path: &ObjectPath) -> DriverResult<Bytes> {
+        self.inner.read(path).await
+    }
+
+    async fn file_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.file_exists(path).await
+    }
+
+    async fn write(&self, path: &ObjectPath, content: Bytes) -> DriverResult<()> {
+        let before = self.checksum(path).await?;
+        let after = Some(checksum(&content));
+        self.inner.write(path, content).await?;
+        self.record(path, Operation::Write, before, after).await
+    }
+
+    async fn write_with_options(
+        &self,
+        path: &ObjectPath,
+        content: Bytes,
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        let before = self.checksum(path).await?;
+        let after = Some(checksum(&content));
+        self.inner
+            .write_with_options(path, content, options)
+            .await?;
+        self.record(path, Operation::Write, before, after).await
+    }
+
+    async fn write_with_acl(
+        &self,
+        path: &ObjectPath,
+        content: Bytes,
+        acl: &Acl,
+    ) -> DriverResult<()> {
+        let before = self.checksum(path).await?;
+        let after = Some(checksum(&content));
+        self.inner.write_with_acl(path, content, acl).await?;
+        self.record(path, Operation::Write, before, after).await
+    }
+
+    async fn set_acl(&self, path: &ObjectPath, acl: &Acl) -> DriverResult<()> {
+        self.inner.set_acl(path, acl).await
+    }
+
+    async fn delete(&self, path: &ObjectPath) -> DriverResult<()> {
+        let before = self.checksum(path).await?;
+        self.inner.delete(path).await?;
+        self.record(path, Operation::Delete, before, None).await
+    }
+
+    async fn delete_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        let deleted = self.checksums(path).await?;
+        self.inner.delete_directory(path).await?;
+        self.record_deletes(deleted).await
+    }
+
+    async fn last_modified(&self, path: &ObjectPath) -> DriverResult<SystemTime> {
+        self.inner.last_modified(path).await
+    }
+
+    async fn size(&self, path: &ObjectPath) -> DriverResult<u64> {
+        self.inner.size(path).await
+    }
+
+    async fn create_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.inner.create_directory(path).await
+    }
+
+    async fn directory_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.directory_exists(path).await
+    }
+
+    async fn purge_all(&self) -> DriverResult<()> {
+        // the files are deleted one by one to keep the records
+        let deleted = self.checksums(&ObjectPath::default()).await?;
+        for file in self.list(&ObjectPath::default()).await? {
+            self.inner.delete_if_exists(&file).await?;
+        }
+        self.record_deletes(deleted).await
+    }
+
+    async fn set_last_modified(
+        &self,
+        path: &ObjectPath,
+        last_modified: SystemTime,
+    ) -> DriverResult<()> {
+        self.inner.set_last_modified(path, last_modified).await
+    }
+
+    async fn delete_if_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        let before = self.checksum(path).await?;
+        if !self.inner.delete_if_exists(path).await? {
+            return Ok(false);
+        }
+        self.record(path, Operation::Delete, before, None).await?;
+        Ok(true)
+    }
+
+    async fn stat(&self, path: &ObjectPath) -> DriverResult<Option<ObjectMetadata>> {
+        self.inner.stat(path).await
+    }
+
+    async fn read_if_modified_since(
+        &self,
+        path: &ObjectPath,
+        since: SystemTime,
+    ) -> DriverResult<Option<Bytes>> {
+        self.inner.read_if_modified_since(path, since).await
+    }
+
+    async fn read_with_options(
+        &self,
+        path: &ObjectPath,
+        options: &ReadOptions,
+    ) -> DriverResult<Bytes> {
+        self.inner.read_with_options(path, options).await
+    }
+
+    async fn read_range(&self, path: &ObjectPath, range: Range<u64>) -> DriverResult<Vec<u8>> {
+        self.inner.read_range(path, range).await
+    }
+
+    async fn list(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        let mut files = self.inner.list(path).await?;
+        if !path.to_path_buf().starts_with(HISTORY_PREFIX) {
+            files.retain(|file| !file.starts_with(HISTORY_PREFIX));
+        }
+        Ok(files)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn as_presign(&self) -> Option<&dyn PresignExt> {
+        self.inner.as_presign().map(|_| self as &dyn PresignExt)
+    }
+
+    fn as_versioning(&self) -> Option<&dyn VersioningExt> {
+        self.inner
+            .as_versioning()
+            .map(|_| self as &dyn VersioningExt)
+    }
+
+    async fn public_url(&self, path: &ObjectPath) -> DriverResult<String> {
+        self.inner.public_url(path).await
+    }
+
+    async fn container_exists(&self) -> DriverResult<bool> {
+        self.inner.container_exists().await
+    }
+
+    async fn ensure_container(&self, options: &ContainerOptions) -> DriverResult<()> {
+        self.inner.ensure_container(options).await
+    }
+
+    async fn health_check(&self) -> DriverResult<()> {
+        self.inner.health_check().await
+    }
+
+    async fn apply_retention_policies(&self, policies: &[RetentionPolicy]) -> DriverResult<()> {
+        self.inner.apply_retention_policies(policies).await
+    }
+
+    async fn set_legal_hold(&self, path: &ObjectPath, hold: bool) -> DriverResult<()> {
+        self.inner.set_legal_hold(path, hold).await
+    }
+
+    async fn legal_hold(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.legal_hold(path).await
+    }
+
+    async fn list_legal_holds(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        self.inner.list_legal_holds(path).await
+    }
+
+    async fn create_upload(&self, path: &ObjectPath) -> DriverResult<String> {
+        self.inner.create_upload(path).await
+    }
+
+    async fn create_upload_with_options(
+        &self,
+        path: &ObjectPath,
+        options: &WriteOptions,
+    ) -> DriverResult<String> {
+        self.inner.create_upload_with_options(path, options).await
+    }
+
+    async fn upload_part(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        part_number: u32,
+        content: Bytes,
+    ) -> DriverResult<UploadPart> {
+        self.inner
+            .upload_part(path, upload_id, part_number, content)
+            .await
+    }
+
+    async fn list_upload_parts(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+    ) -> DriverResult<Vec<UploadPart>> {
+        self.inner.list_upload_parts(path, upload_id).await
+    }
+
+    async fn complete_upload(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+    ) -> DriverResult<()> {
+        let before = self.checksum(path).await?;
+        self.inner.complete_upload(path, upload_id, parts).await?;
+        self.record(path, Operation::Write, before, None).await
+    }
+
+    async fn complete_upload_with_options(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        let before = self.checksum(path).await?;
+        self.inner
+            .complete_upload_with_options(path, upload_id, parts, options)
+            .await?;
+        self.record(path, Operation::Write, before, None).await
+    }
+
+    async fn list_uploads(&self, prefix: &ObjectPath) -> DriverResult<Vec<PendingUpload>> {
+        self.inner.list_uploads(prefix).await
+    }
+
+    async fn abort_upload(&self, path: &ObjectPath, upload_id: &str) -> DriverResult<()> {
+        self.inner.abort_upload(path, upload_id).await
+    }
+}
+
+#[async_trait::async_trait]
+impl PresignExt for HistoryDriver {
+    async fn presign_read(&self, path: &ObjectPath, expires_in: Duration) -> DriverResult<String> {
+        self.inner
+            .as_presign()
+            .ok_or(DriverError::Unsupported("presigned URLs"))?
+            .presign_read(path, expires_in)
+            .await
+    }
+
+    async fn presign_write(&self, path: &ObjectPath, expires_in: Duration) -> DriverResult<String> {
+        self.inner
+            .as_presign()
+            .ok_or(DriverError::Unsupported("presigned URLs"))?
+            .presign_write(path, expires_in)
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl VersioningExt for HistoryDriver {
+    async fn list_versions(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectVersion>> {
+        self.inner
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .list_versions(path)
+            .await
+    }
+
+    async fn read_version(&self, path: &ObjectPath, version_id: &str) -> DriverResult<Vec<u8>> {
+        self.inner
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .read_version(path, version_id)
+            .await
+    }
+
+    async fn delete_version(&self, path: &ObjectPath, version_id: &str) -> DriverResult<()> {
+        self.inner
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .delete_version(path, version_id)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    use super::*;
+    use crate::StoreConfig;
+
+    #[tokio::test]
+    async fn can_record_history() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        store.write(Path::new("docs/a.txt"), "v0").await.unwrap();
+        let audited = store.with_history();
+
+        audited.write(Path::new("docs/a.txt"), "v1").await.unwrap();
+        audited.write(Path::new("docs/a.txt"), "v2").await.unwrap();
+        audited.delete(Path::new("docs/a.txt")).await.unwrap();
+        audited.write(Path::new("docs/b.txt"), "b").await.unwrap();
+        audited.delete_directory(Path::new("docs")).await.unwrap();
+
+        let history = audited.history(Path::new("docs/a.txt")).await.unwrap();
+        assert_eq!(
+            history
+                .iter()
+                .map(|entry| entry.operation)
+                .collect::<Vec<_>>(),
+            vec![Operation::Write, Operation::Write, Operation::Delete]
+        );
+        assert_eq!(history[0].before, Some(checksum(b"v0")));
+        assert_eq!(history[0].after, Some(checksum(b"v1")));
+        assert_eq!(history[1].before, history[0].after);
+        assert_eq!(history[2].before, Some(checksum(b"v2")));
+        assert_eq!(history[2].after, None);
+
+        let history = audited.history(Path::new("docs/b.txt")).await.unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].operation, Operation::Delete);
+        assert_eq!(history[1].before, Some(checksum(b"b")));
+
+        audited.write(Path::new("c.txt"), "c").await.unwrap();
+        audited.purge_all().await.unwrap();
+        assert_eq!(
+            audited.history(Path::new("c.txt")).await.unwrap()[1].before,
+            Some(checksum(b"c"))
+        );
+
+        assert!(audited.list(Path::new("")).await.unwrap().is_empty());
+        assert!(audited
+            .history(Path::new("docs/missing.txt"))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,481 @@
+use std::{future::Future, ops::Range, sync::Arc, time::SystemTime};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use reqwest::{header, Client, Method, RequestBuilder};
+
+use super::{
+    capabilities::Capabilities, validate_headers, Driver, DriverError, ObjectMetadata, PoolOptions,
+    ReadOptions,
+};
+use crate::{
+    correlation::{self, CORRELATION_ID_HEADER},
+    errors::DriverResult,
+    key::{KeyRules, ObjectPath},
+};
+
+/// Trait resolving the URL of a request, e.g. by asking the application
+/// backend for a presigned URL.
+#[async_trait]
+pub trait UrlSigner: Send + Sync {
+    /// Returns the URL to send a request with the given method for the file
+    /// at the given path to.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL could not be signed, e.g. when the caller
+    /// is not allowed to access the file.
+    async fn sign(&self, method: &Method, path: &ObjectPath) -> DriverResult<String>;
+}
+
+/// Configuration parameters for initializing an `HttpDriver`.
+#[derive(Clone, Default)]
+pub struct Config {
+    /// The base URL the file paths are joined to, e.g. a public bucket
+    /// endpoint or an application proxy.
+    pub base_url: String,
+    /// The headers sent with every request, e.g. an `Authorization` header.
+    pub headers: Vec<(String, String)>,
+    /// The signer resolving request URLs. When set, the base URL is only used
+    /// for public URLs.
+    pub signer: Option<Arc<dyn UrlSigner>>,
+    /// The options of the HTTP connection pool.
+    pub pool: PoolOptions,
+}
+
+impl std::fmt::Debug for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Config")
+            .field("base_url", &self.base_url)
+            .field("signer", &self.signer.is_some())
+            .field("pool", &self.pool)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The `HttpDriver` struct represents a driver reading and writing files with
+/// plain HTTP `GET`, `HEAD`, `PUT` and `DELETE` requests, such as presigned
+/// URLs or a static file server.
+///
+/// It works in browsers (`wasm32-unknown-unknown`), where requests go through
+/// `fetch`. HTTP has no listing, so listing and deleting directories return
+/// [`DriverError::Unsupported`].
+#[derive(Clone)]
+pub struct HttpDriver {
+    client: Client,
+    base_url: String,
+    headers: Vec<(String, String)>,
+    signer: Option<Arc<dyn UrlSigner>>,
+}
+
+/// A fully read HTTP response.
+struct Response {
+    status: u16,
+    headers: header::HeaderMap,
+    body: Bytes,
+}
+
+impl HttpDriver {
+    /// Initializes a new `HttpDriver` instance with the specified
+    /// configuration.
+    #[must_use]
+    pub fn new(config: Config) -> Self {
+        Self {
+            client: config.pool.reqwest_client(),
+            base_url: config.base_url,
+            headers: config.headers,
+            signer: config.signer,
+        }
+    }
+
+    /// Returns the URL of a request with the given method for the file at the
+    /// given path.
+    async fn url(&self, method: &Method, path: &ObjectPath) -> DriverResult<String> {
+        match &self.signer {
+            Some(signer) => signer.sign(method, path).await,
+            None => super::join_url(&self.base_url, path, KeyRules::InMem),
+        }
+    }
+
+    /// Sends a request with the given method for the file at the given path.
+    async fn send(
+        &self,
+        method: Method,
+        path: &ObjectPath,
+        build: impl FnOnce(RequestBuilder) -> RequestBuilder + Send,
+    ) -> DriverResult<Response> {
+        let url = self.url(&method, path).await?;
+        let mut request = self.client.request(method, url);
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        if let Some(correlation_id) = correlation::current() {
+            request = request.header(CORRELATION_ID_HEADER, correlation_id);
+        }
+
+        execute(build(request)).await
+    }
+}
+
+/// Sends the request and reads the whole response.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn execute(request: RequestBuilder) -> impl Future<Output = DriverResult<Response>> + Send {
+    read_response(request)
+}
+
+/// Sends the request and reads the whole response.
+///
+/// `fetch` futures are not `Send`, so the request runs on the browser task
+/// queue and the response is sent back over a channel.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn execute(request: RequestBuilder) -> impl Future<Output = DriverResult<Response>> + Send {
+    let (sender, receiver) = futures::channel::oneshot::channel();
+    wasm_bindgen_futures::spawn_local(async move {
+        let _ = sender.send(read_response(request).await);
+    });
+
+    async move { receiver.await.map_err(|_| DriverError::Network())? }
+}
+
+/// Sends the request and reads the whole response.
+async fn read_response(request: RequestBuilder) -> DriverResult<Response> {
+    let response = request.send().await.map_err(from_reqwest_error)?;
+    let status = response.status().as_u16();
+    let headers = response.headers().clone();
+    let body = response.bytes().await.map_err(from_reqwest_error)?;
+
+    Ok(Response {
+        status,
+        headers,
+        body,
+    })
+}
+
+/// Converts a request failure into a [`DriverError`].
+fn from_reqwest_error(error: reqwest::Error) -> DriverError {
+    if error.is_timeout() || error.is_request() || error.is_connect() {
+        DriverError::Network()
+    } else {
+        DriverError::Any(Box::new(error))
+    }
+}
+
+/// The response header conventionally carrying the id of the request.
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Returns the error of an unsuccessful response status.
+fn status_error(response: &Response) -> DriverError {
+    let request_id = response
+        .headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok());
+    #[cfg(feature = "tracing")]
+    if response.status != 404 {
+        crate::correlation::record_backend_error("http", Some(response.status), None, request_id);
+    }
+
+    match response.status {
+        401 => DriverError::AuthenticationFailed,
+        403 => DriverError::PermissionDenied,
+        404 => DriverError::ResourceNotFound,
+        412 => DriverError::PreconditionFailed,
+        status => DriverError::Backend {
+            provider: "http",
+            code: None,
+            status: Some(status),
+            request_id: request_id.map(ToString::to_string),
+            retry_after: crate::errors::retry_after(
+                status,
+                response
+                    .headers
+                    .get(header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok()),
+            ),
+            source: String::from_utf8_lossy(&response.body).into(),
+        },
+    }
+}
+
+/// Returns the response when its status is successful.
+fn success(response: Response) -> DriverResult<Response> {
+    if (200..300).contains(&response.status) {
+        Ok(response)
+    } else {
+        Err(status_error(&response))
+    }
+}
+
+/// Returns the value of the given response header.
+fn header_value<'a>(response: &'a Response, name: &header::HeaderName) -> DriverResult<&'a str> {
+    response
+        .headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| DriverError::Any(format!("{name} header is missing").into()))
+}
+
+#[async_trait]
+impl Driver for HttpDriver {
+    /// Reads the file with a `GET` request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist or the request fails.
+    async fn read(&self, path: &ObjectPath) -> DriverResult<Bytes> {
+        path.to_key()?;
+        Ok(success(self.send(Method::GET, path, |request| request).await?)?.body)
+    }
+
+    /// Reads the file with a `GET` request carrying the custom headers and
+    /// query parameters of the options.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a custom header is invalid, or the file does not
+    /// exist or the request fails.
+    async fn read_with_options(
+        &self,
+        path: &ObjectPath,
+        options: &ReadOptions,
+    ) -> DriverResult<Bytes> {
+        path.to_key()?;
+        validate_headers(&options.headers)?;
+        let response = self
+            .send(Method::GET, path, |mut request| {
+                for (name, value) in &options.headers {
+                    request = request.header(name, value);
+                }
+                request.query(&options.query_params)
+            })
+            .await?;
+        Ok(success(response)?.body)
+    }
+
+    /// Reads the file with a `GET` request conditioned on
+    /// `If-Modified-Since`, returning `None` on `304 Not Modified`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist or the request fails.
+    async fn read_if_modified_since(
+        &self,
+        path: &ObjectPath,
+        since: SystemTime,
+    ) -> DriverResult<Option<Bytes>> {
+        path.to_key()?;
+        let response = self
+            .send(Method::GET, path, |request| {
+                request.header(header::IF_MODIFIED_SINCE, httpdate::fmt_http_date(since))
+            })
+            .await?;
+        if response.status == 304 {
+            return Ok(None);
+        }
+        Ok(Some(success(response)?.body))
+    }
+
+    /// Checks whether the file exists with a `HEAD` request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    async fn file_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        Ok(self.stat(path).await?.is_some())
+    }
+
+    /// Writes the file with a `PUT` request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    async fn write(&self, path: &ObjectPath, content: Bytes) -> DriverResult<()> {
+        path.to_key()?;
+        success(
+            self.send(Method::PUT, path, |request| request.body(content))
+                .await?,
+        )
+        .map(|_| ())
+    }
+
+    /// Deletes the file with a `DELETE` request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist or the request fails.
+    async fn delete(&self, path: &ObjectPath) -> DriverResult<()> {
+        path.to_key()?;
+        success(self.send(Method::DELETE, path, |request| request).await?).map(|_| ())
+    }
+
+    /// Returns [`DriverError::Unsupported`], as HTTP has no listing.
+    async fn delete_directory(&self, _path: &ObjectPath) -> DriverResult<()> {
+        Err(DriverError::Unsupported("deleting directories over HTTP"))
+    }
+
+    /// Returns the `Last-Modified` header of a `HEAD` request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist or the header is missing.
+    async fn last_modified(&self, path: &ObjectPath) -> DriverResult<SystemTime> {
+        self.stat(path)
+            .await?
+            .map(|metadata| metadata.last_modified)
+            .ok_or(DriverError::ResourceNotFound)
+    }
+
+    /// Returns the `Content-Length` header of a `HEAD` request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist or the header is missing.
+    async fn size(&self, path: &ObjectPath) -> DriverResult<u64> {
+        self.stat(path)
+            .await?
+            .map(|metadata| metadata.size)
+            .ok_or(DriverError::ResourceNotFound)
+    }
+
+    /// Returns the `Content-Length`, `Last-Modified`, `Content-Type` and
+    /// `ETag` headers of a single `HEAD` request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or a required header is missing.
+    async fn stat(&self, path: &ObjectPath) -> DriverResult<Option<ObjectMetadata>> {
+        path.to_key()?;
+        let response = self.send(Method::HEAD, path, |request| request).await?;
+        if response.status == 404 {
+            return Ok(None);
+        }
+        let response = success(response)?;
+
+        let optional_header =
+            |name: &header::HeaderName| header_value(&response, name).ok().map(ToString::to_string);
+        Ok(Some(ObjectMetadata {
+            size: header_value(&response, &header::CONTENT_LENGTH)?
+                .parse()
+                .map_err(|_| DriverError::DecodeError)?,
+            last_modified: httpdate::parse_http_date(header_value(
+                &response,
+                &header::LAST_MODIFIED,
+            )?)
+            .map_err(|_| DriverError::DecodeError)?,
+            content_type: optional_header(&header::CONTENT_TYPE),
+            etag: optional_header(&header::ETAG),
+            storage_class: None,
+            content_md5: None,
+        }))
+    }
+
+    /// Reads the range with a `Range` request, falling back to slicing the
+    /// content when the server ignores the header.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist or the request fails.
+    async fn read_range(&self, path: &ObjectPath, range: Range<u64>) -> DriverResult<Vec<u8>> {
+        path.to_key()?;
+        if range.start >= range.end {
+            return Ok(Vec::new());
+        }
+
+        let value = format!("bytes={}-{}", range.start, range.end - 1);
+        let response = self
+            .send(Method::GET, path, |request| {
+                request.header(header::RANGE, value)
+            })
+            .await?;
+
+        match response.status {
+            206 => Ok(response.body.into()),
+            416 => Ok(Vec::new()),
+            _ => {
+                let content = success(response)?.body;
+                let len = content.len();
+                let start = usize::try_from(range.start).map_or(len, |start| start.min(len));
+                let end = usize::try_from(range.end).map_or(len, |end| end.min(len));
+                Ok(content[start..end].to_vec())
+            }
+        }
+    }
+
+    /// Returns [`DriverError::Unsupported`], as HTTP has no listing.
+    async fn list(&self, _path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        Err(DriverError::Unsupported("listing over HTTP"))
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::RANGE_READS | Capabilities::PUBLIC_URLS
+    }
+
+    /// Returns the path joined to the base URL.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is invalid.
+    async fn public_url(&self, path: &ObjectPath) -> DriverResult<String> {
+        super::join_url(&self.base_url, path, KeyRules::InMem)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::time::Duration;
+
+    use super::*;
+
+    fn response(status: u16, headers: &[(&'static str, &'static str)]) -> Response {
+        let mut map = header::HeaderMap::new();
+        for (name, value) in headers {
+            map.insert(*name, header::HeaderValue::from_static(value));
+        }
+        Response {
+            status,
+            headers: map,
+            body: Bytes::from_static(b"failure"),
+        }
+    }
+
+    #[test]
+    fn can_map_status_errors() {
+        assert!(success(response(204, &[])).is_ok());
+        assert!(matches!(
+            success(response(404, &[])),
+            Err(DriverError::ResourceNotFound)
+        ));
+        assert!(matches!(
+            success(response(403, &[])),
+            Err(DriverError::PermissionDenied)
+        ));
+        assert!(matches!(
+            success(response(412, &[])),
+            Err(DriverError::PreconditionFailed)
+        ));
+
+        let err = success(response(
+            503,
+            &[("retry-after", "5"), (REQUEST_ID_HEADER, "abc")],
+        ))
+        .err()
+        .unwrap();
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(5)));
+        assert_eq!(err.request_id(), Some("abc"));
+        assert!(matches!(
+            err,
+            DriverError::Backend {
+                provider: "http",
+                status: Some(503),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn can_read_header_values() {
+        let response = response(200, &[("etag", "\"abc\"")]);
+        assert_eq!(header_value(&response, &header::ETAG).unwrap(), "\"abc\"");
+        assert!(header_value(&response, &header::LAST_MODIFIED).is_err());
+    }
+}
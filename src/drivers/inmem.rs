@@ -1,32 +1,33 @@
 use std::{
     collections::BTreeMap,
-    path::{Path, PathBuf},
-    sync::Mutex,
+    path::PathBuf,
+    sync::{Arc, Mutex},
     time::SystemTime,
 };
 
-use super::{Driver, DriverError};
-use crate::{contents::Contents, errors::DriverResult};
+use bytes::Bytes;
+
+use super::{
+    capabilities::Capabilities, check_legal_hold, check_no_legal_holds, directory_key, Driver,
+    DriverError, ObjectMetadata,
+};
+use crate::{clock, errors::DriverResult, key::ObjectPath};
 
 #[derive(Debug, Clone)]
 pub struct File {
-    pub content: Vec<u8>,
+    pub content: Bytes,
     pub last_modified: SystemTime,
 }
 
-#[derive(Debug, Default)]
+/// The `InMemoryDriver` struct represents an in-memory implementation of the
+/// `Driver` trait.
+///
+/// Clones share the same underlying storage, like clones of the other drivers
+/// share the same backend.
+#[derive(Debug, Default, Clone)]
 pub struct InMemoryDriver {
-    pub files: Mutex<BTreeMap<PathBuf, File>>,
-    directory: Mutex<BTreeMap<PathBuf, Vec<PathBuf>>>,
-}
-
-impl Clone for InMemoryDriver {
-    fn clone(&self) -> Self {
-        Self {
-            files: Mutex::new(self.files.lock().unwrap().clone()),
-            directory: Mutex::new(self.directory.lock().unwrap().clone()),
-        }
-    }
+    pub files: Arc<Mutex<BTreeMap<PathBuf, File>>>,
+    directory: Arc<Mutex<BTreeMap<PathBuf, Vec<PathBuf>>>>,
 }
 
 impl InMemoryDriver {
@@ -38,24 +39,33 @@ impl InMemoryDriver {
     }
 }
 
+/// Converts the given path into the normalized key files are stored under.
+fn key(path: &ObjectPath) -> DriverResult<PathBuf> {
+    Ok(path.to_key()?.to_path_buf())
+}
+
 #[async_trait::async_trait]
 impl Driver for InMemoryDriver {
-    async fn read(&self, path: &Path) -> DriverResult<Vec<u8>> {
+    async fn read(&self, path: &ObjectPath) -> DriverResult<Bytes> {
         let files = self.get_files();
-        let file = files.get(path).ok_or(DriverError::ResourceNotFound)?;
+        let file = files
+            .get(&key(path)?)
+            .ok_or(DriverError::ResourceNotFound)?;
 
-        Ok(Contents::from(file.content.clone()).into())
+        Ok(file.content.clone())
     }
 
-    async fn file_exists(&self, path: &Path) -> DriverResult<bool> {
-        Ok(self.get_files().contains_key(path))
+    async fn file_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        Ok(self.get_files().contains_key(&key(path)?))
     }
 
-    async fn write(&self, path: &Path, content: Vec<u8>) -> DriverResult<()> {
+    async fn write(&self, path: &ObjectPath, content: Bytes) -> DriverResult<()> {
+        check_legal_hold(self, path).await?;
+        let path = key(path)?;
         self.files.lock().unwrap().insert(
-            path.to_path_buf(),
+            path.clone(),
             File {
-                last_modified: SystemTime::now(),
+                last_modified: clock::now(),
                 content,
             },
         );
@@ -66,14 +76,16 @@ impl Driver for InMemoryDriver {
                 .unwrap()
                 .entry(parent.to_path_buf())
                 .or_default()
-                .push(path.to_path_buf());
+                .push(path.clone());
         }
 
         Ok(())
     }
 
-    async fn delete(&self, path: &Path) -> DriverResult<()> {
-        if self.files.lock().unwrap().remove(path).is_none() {
+    async fn delete(&self, path: &ObjectPath) -> DriverResult<()> {
+        check_legal_hold(self, path).await?;
+        let path = key(path)?;
+        if self.files.lock().unwrap().remove(&path).is_none() {
             return Err(DriverError::ResourceNotFound);
         }
 
@@ -82,35 +94,122 @@ impl Driver for InMemoryDriver {
             .unwrap()
             .entry(path.parent().unwrap().to_path_buf())
             .or_default()
-            .retain(|file_path| file_path != path);
+            .retain(|file_path| file_path != &path);
 
         Ok(())
     }
 
-    async fn delete_directory(&self, path: &Path) -> DriverResult<()> {
-        if !self.directory.lock().unwrap().contains_key(path) {
+    async fn delete_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        let key = directory_key(path)?;
+        if !self.directory_exists(&(&key).into()).await? {
             return Err(DriverError::ResourceNotFound);
         }
+        check_no_legal_holds(self, path).await?;
+
+        let path = key.to_path_buf();
 
         self.directory
             .lock()
             .unwrap()
-            .retain(|file_path, _| !file_path.starts_with(path));
+            .retain(|file_path, _| !file_path.starts_with(&path));
 
         self.files
             .lock()
             .unwrap()
-            .retain(|file_path, _| !file_path.starts_with(path));
+            .retain(|file_path, _| !file_path.starts_with(&path));
+
+        Ok(())
+    }
+
+    async fn create_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        if let Some(path) = path.to_prefix()? {
+            self.directory
+                .lock()
+                .unwrap()
+                .entry(path.to_path_buf())
+                .or_default();
+        }
+        Ok(())
+    }
+
+    async fn directory_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        let Some(path) = path.to_prefix()? else {
+            return Ok(true);
+        };
+        let path = path.to_path_buf();
+
+        Ok(self
+            .directory
+            .lock()
+            .unwrap()
+            .keys()
+            .any(|directory| directory.starts_with(&path)))
+    }
 
+    async fn purge_all(&self) -> DriverResult<()> {
+        check_no_legal_holds(self, &ObjectPath::default()).await?;
+        self.directory.lock().unwrap().clear();
+        self.files.lock().unwrap().clear();
         Ok(())
     }
 
-    async fn last_modified(&self, path: &Path) -> DriverResult<SystemTime> {
+    async fn last_modified(&self, path: &ObjectPath) -> DriverResult<SystemTime> {
         let file = self.get_files();
-        let file = file.get(path).ok_or(DriverError::ResourceNotFound)?;
+        let file = file.get(&key(path)?).ok_or(DriverError::ResourceNotFound)?;
 
         Ok(file.last_modified)
     }
+
+    async fn set_last_modified(
+        &self,
+        path: &ObjectPath,
+        last_modified: SystemTime,
+    ) -> DriverResult<()> {
+        self.files
+            .lock()
+            .unwrap()
+            .get_mut(&key(path)?)
+            .ok_or(DriverError::ResourceNotFound)?
+            .last_modified = last_modified;
+        Ok(())
+    }
+
+    async fn stat(&self, path: &ObjectPath) -> DriverResult<Option<ObjectMetadata>> {
+        Ok(self
+            .get_files()
+            .get(&key(path)?)
+            .map(|file| ObjectMetadata {
+                size: file.content.len() as u64,
+                last_modified: file.last_modified,
+                content_type: None,
+                etag: None,
+                storage_class: None,
+                content_md5: None,
+            }))
+    }
+
+    async fn list(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        let prefix = path.to_prefix()?.map(|key| key.to_path_buf());
+
+        Ok(self
+            .get_files()
+            .into_keys()
+            .filter(|file_path| {
+                prefix
+                    .as_ref()
+                    .is_none_or(|prefix| file_path.starts_with(prefix))
+            })
+            .map(ObjectPath::from)
+            .collect())
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::SET_LAST_MODIFIED
+    }
+
+    async fn health_check(&self) -> DriverResult<()> {
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -134,30 +233,18 @@ mod tests {
 
         // cerate state
         let _ = driver
-            .write(
-                PathBuf::from("foo").join("file-1.txt").as_path(),
-                b"".into(),
-            )
+            .write(&ObjectPath::new("foo/file-1.txt"), Bytes::new())
             .await;
 
         let _ = driver
-            .write(
-                PathBuf::from("foo").join("file-2.txt").as_path(),
-                b"".into(),
-            )
+            .write(&ObjectPath::new("foo/file-2.txt"), Bytes::new())
             .await;
 
         let _ = driver
-            .write(
-                PathBuf::from("bar").join("file-1.txt").as_path(),
-                b"".into(),
-            )
+            .write(&ObjectPath::new("bar/file-1.txt"), Bytes::new())
             .await;
         let _ = driver
-            .write(
-                PathBuf::from("bar").join("file-2.txt").as_path(),
-                b"".into(),
-            )
+            .write(&ObjectPath::new("bar/file-2.txt"), Bytes::new())
             .await;
 
         // snapshot the state
@@ -169,13 +256,13 @@ mod tests {
 
         // delete folder
         assert!(driver
-            .delete_directory(PathBuf::from("foo").as_path())
+            .delete_directory(&ObjectPath::new("foo"))
             .await
             .is_ok());
 
         // delete file
         assert!(driver
-            .delete(PathBuf::from("bar").join("file-1.txt").as_path())
+            .delete(&ObjectPath::new("bar/file-1.txt"))
             .await
             .is_ok());
 
@@ -185,4 +272,44 @@ mod tests {
         assert_debug_snapshot!(driver);
         });
     }
+
+    #[tokio::test]
+    async fn can_list_readable_paths() {
+        let driver = InMemoryDriver::default();
+        let path = ObjectPath::new("docs/a%41.txt");
+        driver.write(&path, Bytes::from("content")).await.unwrap();
+
+        let listed = driver.list(&ObjectPath::new("docs")).await.unwrap();
+        assert_eq!(listed, vec![path]);
+        assert_eq!(driver.read(&listed[0]).await.unwrap(), "content");
+    }
+
+    #[tokio::test]
+    async fn can_refuse_deleting_held_files() {
+        let driver = InMemoryDriver::default();
+        let path = ObjectPath::new("contracts/1.pdf");
+        driver.write(&path, Bytes::from("content")).await.unwrap();
+        driver.set_legal_hold(&path, true).await.unwrap();
+
+        assert!(matches!(
+            driver.delete(&path).await,
+            Err(DriverError::LegalHold)
+        ));
+        assert!(matches!(
+            driver.delete_if_exists(&path).await,
+            Err(DriverError::LegalHold)
+        ));
+        assert!(matches!(
+            driver.delete_directory(&ObjectPath::new("contracts")).await,
+            Err(DriverError::LegalHold)
+        ));
+        assert!(matches!(
+            driver.purge_all().await,
+            Err(DriverError::LegalHold)
+        ));
+        assert!(driver.file_exists(&path).await.unwrap());
+
+        driver.set_legal_hold(&path, false).await.unwrap();
+        assert!(driver.delete(&path).await.is_ok());
+    }
 }
@@ -0,0 +1,393 @@
+use std::{
+    ops::Range,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use bytes::Bytes;
+use tokio::sync::OnceCell;
+
+use super::{
+    capabilities::{Capabilities, ObjectVersion, PresignExt, VersioningExt},
+    Acl, ContainerOptions, Driver, DriverError, DriverResult, ObjectMetadata, ReadOptions,
+    RetentionPolicy, WriteOptions,
+};
+use crate::{
+    key::ObjectPath,
+    upload::{PendingUpload, UploadPart},
+    StoreConfig,
+};
+
+/// The `LazyDriver` struct defers building the driver of a [`StoreConfig`]
+/// until its first use, so applications can boot while the backend is
+/// briefly unreachable.
+///
+/// A failed initialization is returned by the operation that triggered it and
+/// retried by the next one. Clones share the initialized driver. Until then,
+/// [`Driver::capabilities`] is empty.
+#[allow(clippy::module_name_repetitions)]
+#[derive(Clone)]
+pub struct LazyDriver {
+    config: Arc<StoreConfig>,
+    driver: Arc<OnceCell<Box<dyn Driver>>>,
+}
+
+impl LazyDriver {
+    /// Creates a new [`LazyDriver`] building the driver of the given
+    /// configuration on first use.
+    #[must_use]
+    pub fn new(config: StoreConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            driver: Arc::new(OnceCell::new()),
+        }
+    }
+
+    /// Returns the driver, building it when it is not initialized yet.
+    async fn driver(&self) -> DriverResult<&dyn Driver> {
+        let driver = self
+            .driver
+            .get_or_try_init(|| StoreConfig::clone(&self.config).build_driver())
+            .await?;
+        Ok(driver.as_ref())
+    }
+}
+
+#[async_trait::async_trait]
+impl Driver for LazyDriver {
+    async fn read(&self, path: &ObjectPath) -> DriverResult<Bytes> {
+        self.driver().await?.read(path).await
+    }
+
+    async fn file_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.driver().await?.file_exists(path).await
+    }
+
+    async fn write(&self, path: &ObjectPath, content: Bytes) -> DriverResult<()> {
+        self.driver().await?.write(path, content).await
+    }
+
+    async fn write_with_options(
+        &self,
+        path: &ObjectPath,
+        content: Bytes,
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        self.driver()
+            .await?
+            .write_with_options(path, content, options)
+            .await
+    }
+
+    async fn write_with_acl(
+        &self,
+        path: &ObjectPath,
+        content: Bytes,
+        acl: &Acl,
+    ) -> DriverResult<()> {
+        self.driver()
+            .await?
+            .write_with_acl(path, content, acl)
+            .await
+    }
+
+    async fn set_acl(&self, path: &ObjectPath, acl: &Acl) -> DriverResult<()> {
+        self.driver().await?.set_acl(path, acl).await
+    }
+
+    async fn delete(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.driver().await?.delete(path).await
+    }
+
+    async fn delete_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.driver().await?.delete_directory(path).await
+    }
+
+    async fn last_modified(&self, path: &ObjectPath) -> DriverResult<SystemTime> {
+        self.driver().await?.last_modified(path).await
+    }
+
+    async fn size(&self, path: &ObjectPath) -> DriverResult<u64> {
+        self.driver().await?.size(path).await
+    }
+
+    async fn create_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.driver().await?.create_directory(path).await
+    }
+
+    async fn directory_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.driver().await?.directory_exists(path).await
+    }
+
+    async fn purge_all(&self) -> DriverResult<()> {
+        self.driver().await?.purge_all().await
+    }
+
+    async fn set_last_modified(
+        &self,
+        path: &ObjectPath,
+        last_modified: SystemTime,
+    ) -> DriverResult<()> {
+        self.driver()
+            .await?
+            .set_last_modified(path, last_modified)
+            .await
+    }
+
+    async fn delete_if_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.driver().await?.delete_if_exists(path).await
+    }
+
+    async fn stat(&self, path: &ObjectPath) -> DriverResult<Option<ObjectMetadata>> {
+        self.driver().await?.stat(path).await
+    }
+
+    async fn read_if_modified_since(
+        &self,
+        path: &ObjectPath,
+        since: SystemTime,
+    ) -> DriverResult<Option<Bytes>> {
+        self.driver()
+            .await?
+            .read_if_modified_since(path, since)
+            .await
+    }
+
+    async fn read_with_options(
+        &self,
+        path: &ObjectPath,
+        options: &ReadOptions,
+    ) -> DriverResult<Bytes> {
+        self.driver().await?.read_with_options(path, options).await
+    }
+
+    async fn read_range(&self, path: &ObjectPath, range: Range<u64>) -> DriverResult<Vec<u8>> {
+        self.driver().await?.read_range(path, range).await
+    }
+
+    async fn list(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        self.driver().await?.list(path).await
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.driver
+            .get()
+            .map_or_else(Capabilities::empty, |driver| driver.capabilities())
+    }
+
+    /// Presigning initializes the driver, so it is offered until the driver
+    /// is known not to support it.
+    fn as_presign(&self) -> Option<&dyn PresignExt> {
+        match self.driver.get() {
+            Some(driver) if driver.as_presign().is_none() => None,
+            _ => Some(self),
+        }
+    }
+
+    /// Versioning initializes the driver, so it is offered until the driver
+    /// is known not to support it.
+    fn as_versioning(&self) -> Option<&dyn VersioningExt> {
+        match self.driver.get() {
+            Some(driver) if driver.as_versioning().is_none() => None,
+            _ => Some(self),
+        }
+    }
+
+    async fn public_url(&self, path: &ObjectPath) -> DriverResult<String> {
+        self.driver().await?.public_url(path).await
+    }
+
+    async fn container_exists(&self) -> DriverResult<bool> {
+        self.driver().await?.container_exists().await
+    }
+
+    async fn ensure_container(&self, options: &ContainerOptions) -> DriverResult<()> {
+        self.driver().await?.ensure_container(options).await
+    }
+
+    async fn health_check(&self) -> DriverResult<()> {
+        self.driver().await?.health_check().await
+    }
+
+    async fn apply_retention_policies(&self, policies: &[RetentionPolicy]) -> DriverResult<()> {
+        self.driver()
+            .await?
+            .apply_retention_policies(policies)
+            .await
+    }
+
+    async fn set_legal_hold(&self, path: &ObjectPath, hold: bool) -> DriverResult<()> {
+        self.driver().await?.set_legal_hold(path, hold).await
+    }
+
+    async fn legal_hold(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.driver().await?.legal_hold(path).await
+    }
+
+    async fn list_legal_holds(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        self.driver().await?.list_legal_holds(path).await
+    }
+
+    async fn create_upload(&self, path: &ObjectPath) -> DriverResult<String> {
+        self.driver().await?.create_upload(path).await
+    }
+
+    async fn create_upload_with_options(
+        &self,
+        path: &ObjectPath,
+        options: &WriteOptions,
+    ) -> DriverResult<String> {
+        self.driver()
+            .await?
+            .create_upload_with_options(path, options)
+            .await
+    }
+
+    async fn upload_part(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        part_number: u32,
+        content: Bytes,
+    ) -> DriverResult<UploadPart> {
+        self.driver()
+            .await?
+            .upload_part(path, upload_id, part_number, content)
+            .await
+    }
+
+    async fn list_upload_parts(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+    ) -> DriverResult<Vec<UploadPart>> {
+        self.driver()
+            .await?
+            .list_upload_parts(path, upload_id)
+            .await
+    }
+
+    async fn complete_upload(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+    ) -> DriverResult<()> {
+        self.driver()
+            .await?
+            .complete_upload(path, upload_id, parts)
+            .await
+    }
+
+    async fn complete_upload_with_options(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        self.driver()
+            .await?
+            .complete_upload_with_options(path, upload_id, parts, options)
+            .await
+    }
+
+    async fn list_uploads(&self, prefix: &ObjectPath) -> DriverResult<Vec<PendingUpload>> {
+        self.driver().await?.list_uploads(prefix).await
+    }
+
+    async fn abort_upload(&self, path: &ObjectPath, upload_id: &str) -> DriverResult<()> {
+        self.driver().await?.abort_upload(path, upload_id).await
+    }
+}
+
+#[async_trait::async_trait]
+impl PresignExt for LazyDriver {
+    async fn presign_read(&self, path: &ObjectPath, expires_in: Duration) -> DriverResult<String> {
+        self.driver()
+            .await?
+            .as_presign()
+            .ok_or(DriverError::Unsupported("presigned URLs"))?
+            .presign_read(path, expires_in)
+            .await
+    }
+
+    async fn presign_write(&self, path: &ObjectPath, expires_in: Duration) -> DriverResult<String> {
+        self.driver()
+            .await?
+            .as_presign()
+            .ok_or(DriverError::Unsupported("presigned URLs"))?
+            .presign_write(path, expires_in)
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl VersioningExt for LazyDriver {
+    async fn list_versions(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectVersion>> {
+        self.driver()
+            .await?
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .list_versions(path)
+            .await
+    }
+
+    async fn read_version(&self, path: &ObjectPath, version_id: &str) -> DriverResult<Vec<u8>> {
+        self.driver()
+            .await?
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .read_version(path, version_id)
+            .await
+    }
+
+    async fn delete_version(&self, path: &ObjectPath, version_id: &str) -> DriverResult<()> {
+        self.driver()
+            .await?
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .delete_version(path, version_id)
+            .await
+    }
+}
+
+#[cfg(all(test, feature = "disk"))]
+mod tests {
+
+    use std::path::Path;
+
+    use super::*;
+    use crate::drivers::disk;
+
+    #[tokio::test]
+    async fn can_retry_initialization() {
+        let root = std::env::temp_dir().join(format!(
+            "active-storage-lazy-{}",
+            crate::drivers::unique_id()
+        ));
+        // the storage root can't be created while a file takes its parent
+        std::fs::write(&root, "blocking").unwrap();
+        let store = StoreConfig::Disk(disk::Config {
+            location: root.join("data"),
+            public_url: None,
+        })
+        .build_lazy();
+
+        assert_eq!(store.capabilities(), Capabilities::empty());
+        assert!(store.write(Path::new("file.txt"), "content").await.is_err());
+
+        std::fs::remove_file(&root).unwrap();
+        let clone = store.clone();
+        assert!(clone.write(Path::new("file.txt"), "content").await.is_ok());
+        assert_eq!(
+            store.read::<String>(Path::new("file.txt")).await.unwrap(),
+            "content"
+        );
+        assert!(store
+            .capabilities()
+            .contains(Capabilities::SET_LAST_MODIFIED));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,517 @@
+use std::{
+    num::NonZeroUsize,
+    ops::Range,
+    path::PathBuf,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant, SystemTime},
+};
+
+use bytes::Bytes;
+use lru::LruCache;
+
+use super::{
+    capabilities::{Capabilities, ObjectVersion, PresignExt, VersioningExt},
+    Acl, ContainerOptions, Driver, DriverError, DriverResult, ObjectMetadata, ReadOptions,
+    RetentionPolicy, WriteOptions,
+};
+use crate::{
+    key::ObjectPath,
+    upload::{PendingUpload, UploadPart},
+};
+
+/// Struct representing the limits of a [`MemoryCacheDriver`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemoryCacheOptions {
+    /// The maximum number of cached files.
+    pub max_entries: usize,
+    /// The maximum total size of the cached files in bytes.
+    pub max_bytes: u64,
+    /// The size in bytes above which a file is not cached.
+    pub max_object_size: u64,
+    /// How long a cached file is served before it is read again.
+    pub ttl: Duration,
+}
+
+impl Default for MemoryCacheOptions {
+    /// Caches up to 1000 files of at most 1 MiB, 64 MiB in total, for a
+    /// minute.
+    fn default() -> Self {
+        Self {
+            max_entries: 1000,
+            max_bytes: 64 * 1024 * 1024,
+            max_object_size: 1024 * 1024,
+            ttl: Duration::from_secs(60),
+        }
+    }
+}
+
+impl MemoryCacheOptions {
+    /// Sets the maximum number of cached files.
+    #[must_use]
+    pub const fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Sets the maximum total size of the cached files.
+    #[must_use]
+    pub const fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Sets the size above which a file is not cached.
+    #[must_use]
+    pub const fn with_max_object_size(mut self, max_object_size: u64) -> Self {
+        self.max_object_size = max_object_size;
+        self
+    }
+
+    /// Sets how long a cached file is served.
+    #[must_use]
+    pub const fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+}
+
+/// A cached file with its expiry.
+struct Entry {
+    content: Bytes,
+    expires_at: Instant,
+}
+
+/// The cached files, least recently used first, with their total size.
+struct Cache {
+    entries: LruCache<PathBuf, Entry>,
+    bytes: u64,
+}
+
+impl Cache {
+    fn remove(&mut self, file: &PathBuf) {
+        if let Some(entry) = self.entries.pop(file) {
+            self.bytes -= entry.content.len() as u64;
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.bytes = 0;
+    }
+}
+
+/// The `MemoryCacheDriver` struct wraps a [`Driver`] and keeps the content of
+/// small files in memory for a short time, e.g. for the configuration files
+/// and templates read on every request.
+///
+/// Only whole file reads are cached. The least recently used files are
+/// evicted once the [`MemoryCacheOptions`] limits are reached. A file written
+/// or deleted through the driver is evicted at once; a change made by another
+/// process is seen once the entry expires.
+#[allow(clippy::module_name_repetitions)]
+pub struct MemoryCacheDriver {
+    inner: Box<dyn Driver>,
+    options: MemoryCacheOptions,
+    cache: Arc<Mutex<Cache>>,
+}
+
+impl Clone for MemoryCacheDriver {
+    fn clone(&self) -> Self {
+        Self {
+            inner: dyn_clone::clone_box(&*self.inner),
+            options: self.options.clone(),
+            cache: self.cache.clone(),
+        }
+    }
+}
+
+impl MemoryCacheDriver {
+    /// Creates a new [`MemoryCacheDriver`] caching the files of the given
+    /// driver within the given limits.
+    #[must_use]
+    pub fn new(inner: Box<dyn Driver>, options: MemoryCacheOptions) -> Self {
+        let capacity = NonZeroUsize::new(options.max_entries).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            inner,
+            options,
+            cache: Arc::new(Mutex::new(Cache {
+                entries: LruCache::new(capacity),
+                bytes: 0,
+            })),
+        }
+    }
+
+    fn cache(&self) -> MutexGuard<'_, Cache> {
+        self.cache
+            .lock()
+            .expect("memory cache failed getting a lock")
+    }
+
+    /// Returns the cached content of the file, unless it expired.
+    fn cached(&self, path: &ObjectPath) -> Option<Bytes> {
+        let file = path.to_path_buf();
+        let mut cache = self.cache();
+        match cache.entries.get(&file) {
+            Some(entry) if Instant::now() < entry.expires_at => Some(entry.content.clone()),
+            Some(_) => {
+                cache.remove(&file);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, path: &ObjectPath, content: &Bytes) {
+        let size = content.len() as u64;
+        if size > self.options.max_object_size || size > self.options.max_bytes {
+            return;
+        }
+
+        let file = path.to_path_buf();
+        let mut cache = self.cache();
+        cache.remove(&file);
+        while cache.bytes + size > self.options.max_bytes {
+            let Some((_, evicted)) = cache.entries.pop_lru() else {
+                break;
+            };
+            cache.bytes -= evicted.content.len() as u64;
+        }
+        let entry = Entry {
+            content: content.clone(),
+            expires_at: Instant::now() + self.options.ttl,
+        };
+        if let Some((_, evicted)) = cache.entries.push(file, entry) {
+            cache.bytes -= evicted.content.len() as u64;
+        }
+        cache.bytes += size;
+    }
+
+    fn forget(&self, path: &ObjectPath) {
+        self.cache().remove(&path.to_path_buf());
+    }
+}
+
+#[async_trait::async_trait]
+impl Driver for MemoryCacheDriver {
+    async fn read(&self, path: &ObjectPath) -> DriverResult<Bytes> {
+        if let Some(content) = self.cached(path) {
+            return Ok(content);
+        }
+        let content = self.inner.read(path).await?;
+        self.insert(path, &content);
+        Ok(content)
+    }
+
+    async fn file_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.file_exists(path).await
+    }
+
+    async fn write(&self, path: &ObjectPath, content: Bytes) -> DriverResult<()> {
+        self.forget(path);
+        self.inner.write(path, content).await
+    }
+
+    async fn write_with_options(
+        &self,
+        path: &ObjectPath,
+        content: Bytes,
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        self.forget(path);
+        self.inner.write_with_options(path, content, options).await
+    }
+
+    async fn write_with_acl(
+        &self,
+        path: &ObjectPath,
+        content: Bytes,
+        acl: &Acl,
+    ) -> DriverResult<()> {
+        self.forget(path);
+        self.inner.write_with_acl(path, content, acl).await
+    }
+
+    async fn set_acl(&self, path: &ObjectPath, acl: &Acl) -> DriverResult<()> {
+        self.inner.set_acl(path, acl).await
+    }
+
+    async fn delete(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.forget(path);
+        self.inner.delete(path).await
+    }
+
+    async fn delete_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.cache().clear();
+        self.inner.delete_directory(path).await
+    }
+
+    async fn last_modified(&self, path: &ObjectPath) -> DriverResult<SystemTime> {
+        self.inner.last_modified(path).await
+    }
+
+    async fn size(&self, path: &ObjectPath) -> DriverResult<u64> {
+        self.inner.size(path).await
+    }
+
+    async fn create_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.inner.create_directory(path).await
+    }
+
+    async fn directory_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.directory_exists(path).await
+    }
+
+    async fn purge_all(&self) -> DriverResult<()> {
+        self.cache().clear();
+        self.inner.purge_all().await
+    }
+
+    async fn set_last_modified(
+        &self,
+        path: &ObjectPath,
+        last_modified: SystemTime,
+    ) -> DriverResult<()> {
+        self.inner.set_last_modified(path, last_modified).await
+    }
+
+    async fn delete_if_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.forget(path);
+        self.inner.delete_if_exists(path).await
+    }
+
+    async fn stat(&self, path: &ObjectPath) -> DriverResult<Option<ObjectMetadata>> {
+        self.inner.stat(path).await
+    }
+
+    async fn read_if_modified_since(
+        &self,
+        path: &ObjectPath,
+        since: SystemTime,
+    ) -> DriverResult<Option<Bytes>> {
+        self.inner.read_if_modified_since(path, since).await
+    }
+
+    async fn read_with_options(
+        &self,
+        path: &ObjectPath,
+        options: &ReadOptions,
+    ) -> DriverResult<Bytes> {
+        self.inner.read_with_options(path, options).await
+    }
+
+    async fn read_range(&self, path: &ObjectPath, range: Range<u64>) -> DriverResult<Vec<u8>> {
+        self.inner.read_range(path, range).await
+    }
+
+    async fn list(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        self.inner.list(path).await
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn as_presign(&self) -> Option<&dyn PresignExt> {
+        self.inner.as_presign().map(|_| self as &dyn PresignExt)
+    }
+
+    fn as_versioning(&self) -> Option<&dyn VersioningExt> {
+        self.inner
+            .as_versioning()
+            .map(|_| self as &dyn VersioningExt)
+    }
+
+    async fn public_url(&self, path: &ObjectPath) -> DriverResult<String> {
+        self.inner.public_url(path).await
+    }
+
+    async fn container_exists(&self) -> DriverResult<bool> {
+        self.inner.container_exists().await
+    }
+
+    async fn ensure_container(&self, options: &ContainerOptions) -> DriverResult<()> {
+        self.inner.ensure_container(options).await
+    }
+
+    async fn health_check(&self) -> DriverResult<()> {
+        self.inner.health_check().await
+    }
+
+    async fn apply_retention_policies(&self, policies: &[RetentionPolicy]) -> DriverResult<()> {
+        self.inner.apply_retention_policies(policies).await
+    }
+
+    async fn set_legal_hold(&self, path: &ObjectPath, hold: bool) -> DriverResult<()> {
+        self.inner.set_legal_hold(path, hold).await
+    }
+
+    async fn legal_hold(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.legal_hold(path).await
+    }
+
+    async fn list_legal_holds(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        self.inner.list_legal_holds(path).await
+    }
+
+    async fn create_upload(&self, path: &ObjectPath) -> DriverResult<String> {
+        self.inner.create_upload(path).await
+    }
+
+    async fn create_upload_with_options(
+        &self,
+        path: &ObjectPath,
+        options: &WriteOptions,
+    ) -> DriverResult<String> {
+        self.inner.create_upload_with_options(path, options).await
+    }
+
+    async fn upload_part(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        part_number: u32,
+        content: Bytes,
+    ) -> DriverResult<UploadPart> {
+        self.inner
+            .upload_part(path, upload_id, part_number, content)
+            .await
+    }
+
+    async fn list_upload_parts(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+    ) -> DriverResult<Vec<UploadPart>> {
+        self.inner.list_upload_parts(path, upload_id).await
+    }
+
+    async fn complete_upload(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+    ) -> DriverResult<()> {
+        self.forget(path);
+        self.inner.complete_upload(path, upload_id, parts).await
+    }
+
+    async fn complete_upload_with_options(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        self.forget(path);
+        self.inner
+            .complete_upload_with_options(path, upload_id, parts, options)
+            .await
+    }
+
+    async fn list_uploads(&self, prefix: &ObjectPath) -> DriverResult<Vec<PendingUpload>> {
+        self.inner.list_uploads(prefix).await
+    }
+
+    async fn abort_upload(&self, path: &ObjectPath, upload_id: &str) -> DriverResult<()> {
+        self.inner.abort_upload(path, upload_id).await
+    }
+}
+
+#[async_trait::async_trait]
+impl PresignExt for MemoryCacheDriver {
+    async fn presign_read(&self, path: &ObjectPath, expires_in: Duration) -> DriverResult<String> {
+        self.inner
+            .as_presign()
+            .ok_or(DriverError::Unsupported("presigned URLs"))?
+            .presign_read(path, expires_in)
+            .await
+    }
+
+    async fn presign_write(&self, path: &ObjectPath, expires_in: Duration) -> DriverResult<String> {
+        self.inner
+            .as_presign()
+            .ok_or(DriverError::Unsupported("presigned URLs"))?
+            .presign_write(path, expires_in)
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl VersioningExt for MemoryCacheDriver {
+    async fn list_versions(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectVersion>> {
+        self.inner
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .list_versions(path)
+            .await
+    }
+
+    async fn read_version(&self, path: &ObjectPath, version_id: &str) -> DriverResult<Vec<u8>> {
+        self.inner
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .read_version(path, version_id)
+            .await
+    }
+
+    async fn delete_version(&self, path: &ObjectPath, version_id: &str) -> DriverResult<()> {
+        self.inner
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .delete_version(path, version_id)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    use super::*;
+    use crate::StoreConfig;
+
+    #[tokio::test]
+    async fn can_cache_small_files() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let cached = store.with_memory_cache(
+            MemoryCacheOptions::default()
+                .with_max_entries(2)
+                .with_max_object_size(4),
+        );
+        for (path, content) in [("a", "a1"), ("b", "b1"), ("c", "c1"), ("big", "big file")] {
+            store.write(Path::new(path), content).await.unwrap();
+        }
+        for path in ["a", "b", "big"] {
+            cached.read::<Vec<u8>>(Path::new(path)).await.unwrap();
+        }
+
+        // changed by another process, the cached content is served
+        store.write(Path::new("a"), "a2").await.unwrap();
+        store.write(Path::new("big"), "big file 2").await.unwrap();
+        assert_eq!(cached.read::<String>(Path::new("a")).await.unwrap(), "a1");
+        assert_eq!(
+            cached.read::<String>(Path::new("big")).await.unwrap(),
+            "big file 2"
+        );
+
+        // `b` is the least recently used file
+        cached.read::<Vec<u8>>(Path::new("c")).await.unwrap();
+        store.write(Path::new("b"), "b2").await.unwrap();
+        assert_eq!(cached.read::<String>(Path::new("b")).await.unwrap(), "b2");
+
+        cached.write(Path::new("c"), "c3").await.unwrap();
+        assert_eq!(cached.read::<String>(Path::new("c")).await.unwrap(), "c3");
+        cached.delete(Path::new("c")).await.unwrap();
+        assert!(matches!(
+            cached.read::<String>(Path::new("c")).await,
+            Err(DriverError::ResourceNotFound)
+        ));
+
+        let expiring =
+            store.with_memory_cache(MemoryCacheOptions::default().with_ttl(Duration::ZERO));
+        assert_eq!(expiring.read::<String>(Path::new("a")).await.unwrap(), "a2");
+        store.write(Path::new("a"), "a3").await.unwrap();
+        assert_eq!(expiring.read::<String>(Path::new("a")).await.unwrap(), "a3");
+    }
+}
@@ -2,15 +2,31 @@
 //!
 //! The `storage_driver` module defines a trait `Driver` that represents a
 //! storage driver, providing methods.
-use std::{path::Path, time::SystemTime};
+use std::{
+    collections::BTreeMap,
+    ops::Range,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
+use bytes::Bytes;
 use dyn_clone::DynClone;
 
-use crate::errors::{DriverError, DriverResult};
+use self::capabilities::{Capabilities, PresignExt, VersioningExt};
+use crate::{
+    clock,
+    errors::{DriverError, DriverResult},
+    health::HEALTH_CHECK_PREFIX,
+    key::{Key, ObjectPath},
+    upload::{PendingUpload, UploadPart, UPLOADS_PREFIX},
+};
 
 #[cfg(feature = "disk")]
 pub mod disk;
 
+#[cfg(feature = "disk_signer")]
+pub mod disk_signer;
+
 #[cfg(feature = "inmem")]
 pub mod inmem;
 
@@ -20,17 +36,1387 @@ pub mod aws_s3;
 #[cfg(feature = "azure")]
 pub mod azure;
 
+#[cfg(feature = "http")]
+pub mod http;
+
+pub mod capabilities;
+
+pub mod case_insensitive;
+
+pub mod coalescing;
+
+#[cfg(feature = "history")]
+pub mod history;
+
+pub mod lazy;
+
+#[cfg(feature = "memory_cache")]
+pub mod memory_cache;
+
+pub mod negative_cache;
+
+pub mod retention;
+
+pub mod scoped;
+
+/// Enum representing the access level granted to anonymous readers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Acl {
+    /// Only authenticated principals can read the data.
+    Private,
+    /// Anyone can read the data.
+    PublicRead,
+}
+
+/// Options applied when creating the storage container (S3 bucket, Azure
+/// container or disk root) with [`Driver::ensure_container`].
+#[derive(Clone, Debug, Default)]
+pub struct ContainerOptions {
+    /// The region to create the container in. When not set, the driver
+    /// configured region is used.
+    pub region: Option<String>,
+    /// The access level of the created container. When not set, the backend
+    /// default is used.
+    pub acl: Option<Acl>,
+}
+
+/// Struct representing how long the files under a prefix are retained,
+/// counted from their last modification.
+///
+/// See [`retention::RetentionDriver`] and [`Driver::apply_retention_policies`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// The prefix of the files the policy applies to. The empty prefix
+    /// applies to every file.
+    pub prefix: ObjectPath,
+    /// How long a file must be kept before it can be deleted.
+    pub min_retention: Option<Duration>,
+    /// How long a file is kept before it is deleted.
+    pub max_retention: Option<Duration>,
+}
+
+impl RetentionPolicy {
+    /// Creates a new [`RetentionPolicy`] for the files under the given
+    /// prefix, without any retention.
+    #[must_use]
+    pub fn new(prefix: impl Into<ObjectPath>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            min_retention: None,
+            max_retention: None,
+        }
+    }
+
+    /// Sets how long a file must be kept before it can be deleted.
+    #[must_use]
+    pub const fn with_min_retention(mut self, min_retention: Duration) -> Self {
+        self.min_retention = Some(min_retention);
+        self
+    }
+
+    /// Sets how long a file is kept before it is deleted.
+    #[must_use]
+    pub const fn with_max_retention(mut self, max_retention: Duration) -> Self {
+        self.max_retention = Some(max_retention);
+        self
+    }
+
+    /// Returns whether the policy applies to the file at the given path.
+    #[must_use]
+    pub fn applies_to(&self, path: &ObjectPath) -> bool {
+        path.starts_with(&self.prefix)
+    }
+}
+
+/// Options of the HTTP connection pool of the cloud drivers.
+///
+/// The pool is created with the driver and shared by all the clones of the
+/// [`crate::Store`], so connections are kept alive and reused across them.
+#[derive(Clone, Debug, Default)]
+pub struct PoolOptions {
+    /// The maximum number of idle connections kept alive per host. When not
+    /// set, the client default is used.
+    pub max_idle_per_host: Option<usize>,
+    /// How long an idle connection is kept alive before it is closed. When
+    /// not set, the client default is used.
+    pub idle_timeout: Option<Duration>,
+    /// Whether requests only use HTTP/1.1, disabling HTTP/2. The S3 client
+    /// doesn't support it and negotiates the protocol with the endpoint.
+    pub http1_only: bool,
+}
+
+impl PoolOptions {
+    /// Sets the maximum number of idle connections kept alive per host.
+    #[must_use]
+    pub const fn with_max_idle_per_host(mut self, max_idle_per_host: usize) -> Self {
+        self.max_idle_per_host = Some(max_idle_per_host);
+        self
+    }
+
+    /// Sets how long an idle connection is kept alive before it is closed.
+    #[must_use]
+    pub const fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = Some(idle_timeout);
+        self
+    }
+
+    /// Only uses HTTP/1.1, disabling HTTP/2.
+    #[must_use]
+    pub const fn with_http1_only(mut self) -> Self {
+        self.http1_only = true;
+        self
+    }
+
+    /// Builds a `reqwest` client with the pool options. Browsers manage the
+    /// connections of `fetch` themselves, so they are ignored there.
+    #[cfg(any(feature = "azure", feature = "http"))]
+    pub(crate) fn reqwest_client(&self) -> reqwest::Client {
+        #[allow(unused_mut)]
+        let mut builder = reqwest::Client::builder();
+        #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+        {
+            if let Some(max_idle_per_host) = self.max_idle_per_host {
+                builder = builder.pool_max_idle_per_host(max_idle_per_host);
+            }
+            if let Some(idle_timeout) = self.idle_timeout {
+                builder = builder.pool_idle_timeout(idle_timeout);
+            }
+            if self.http1_only {
+                builder = builder.http1_only();
+            }
+        }
+        builder.build().expect("HTTP client should be built")
+    }
+}
+
+/// Trait providing the credentials of a cloud driver at runtime, e.g. from
+/// Vault or the instance metadata service, so they can be rotated without
+/// rebuilding the [`crate::Store`].
+///
+/// The credentials are requested before every request, so implementations
+/// should cache them. When the backend rejects them, the driver calls
+/// [`CredentialsProvider::invalidate`] and retries with fresh ones.
+#[cfg(any(feature = "aws_s3", feature = "azure"))]
+#[async_trait::async_trait]
+pub trait CredentialsProvider<C>: Send + Sync {
+    /// Returns the current credentials.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the credentials could not be fetched.
+    async fn credentials(&self) -> DriverResult<C>;
+
+    /// Drops the cached credentials after the backend rejected them, so the
+    /// next call fetches fresh ones.
+    fn invalidate(&self) {}
+}
+
+/// Enum representing the server side encryption of a written file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Encryption {
+    /// Encryption with keys managed by the backend (S3 `AES256`).
+    Managed,
+    /// Encryption with a KMS key. When no key id is set, the backend default
+    /// KMS key is used.
+    Kms { key_id: Option<String> },
+}
+
+/// Enum representing a precondition a write must satisfy to be applied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Precondition {
+    /// The file must not exist yet.
+    IfNotExists,
+    /// The file must exist with the given `ETag`.
+    IfMatch(String),
+}
+
+/// Enum representing the type of an Azure blob, see
+/// [`WriteOptions::with_blob_type`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlobType {
+    /// A blob replaced as a whole on every write, the default.
+    #[default]
+    BlockBlob,
+    /// A blob every write appends to, e.g. for logs. The blob is created on
+    /// the first write.
+    AppendBlob,
+    /// A blob of 512 byte pages, e.g. for virtual disks. The content length
+    /// must be a multiple of 512 bytes.
+    PageBlob,
+}
+
+/// The metadata key holding the modification time of an uploaded local file,
+/// in seconds since the Unix epoch.
+pub const MTIME_METADATA_KEY: &str = "mtime";
+
+/// The prefix of the marker files emulating legal holds on the backends
+/// without native ones, see [`Driver::set_legal_hold`].
+pub const LEGAL_HOLDS_PREFIX: &str = ".active-storage-legal-holds";
+
+/// Struct representing the metadata of a file returned by [`Driver::stat`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ObjectMetadata {
+    /// The size of the file in bytes.
+    pub size: u64,
+    /// The last modification time of the file.
+    pub last_modified: SystemTime,
+    /// The content type of the file, when the backend stores one.
+    pub content_type: Option<String>,
+    /// The `ETag` of the file, when the backend computes one.
+    pub etag: Option<String>,
+    /// The backend specific storage class of the file, when the backend
+    /// reports one, e.g. `GLACIER` with S3.
+    pub storage_class: Option<String>,
+    /// The MD5 digest of the content, when the backend reports one, e.g. the
+    /// `Content-MD5` of an Azure blob.
+    pub content_md5: Option<[u8; 16]>,
+}
+
+/// Options of a single write with [`Driver::write_with_options`].
+///
+/// New per-write capabilities are added here rather than as new trait
+/// methods, so drivers that don't support them keep compiling.
+#[derive(Clone, Debug, Default)]
+pub struct WriteOptions {
+    /// The content type of the file. When not set, the backend default is
+    /// used.
+    pub content_type: Option<String>,
+    /// The `Cache-Control` header returned when the file is served.
+    pub cache_control: Option<String>,
+    /// Custom metadata stored with the file.
+    pub metadata: BTreeMap<String, String>,
+    /// The backend specific storage class, e.g. `STANDARD_IA` with S3.
+    pub storage_class: Option<String>,
+    /// The access level of the file. When not set, the container default is
+    /// used.
+    pub acl: Option<Acl>,
+    /// The server side encryption of the file.
+    pub encryption: Option<Encryption>,
+    /// A precondition the write must satisfy.
+    pub precondition: Option<Precondition>,
+    /// The last modification time reported for the file instead of the write
+    /// time, e.g. to keep the original time of copied files.
+    pub last_modified: Option<SystemTime>,
+    /// Custom headers sent with the request, for provider features the
+    /// options don't cover, e.g. `x-amz-expected-bucket-owner`.
+    pub headers: BTreeMap<String, String>,
+    /// Custom query parameters sent with the request.
+    pub query_params: BTreeMap<String, String>,
+    /// The type of the written blob. Only Azure supports the append and page
+    /// blob types.
+    pub blob_type: Option<BlobType>,
+}
+
+impl WriteOptions {
+    /// Sets the content type of the file.
+    #[must_use]
+    pub fn with_content_type<S: Into<String>>(mut self, content_type: S) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    /// Sets the `Cache-Control` header returned when the file is served.
+    #[must_use]
+    pub fn with_cache_control<S: Into<String>>(mut self, cache_control: S) -> Self {
+        self.cache_control = Some(cache_control.into());
+        self
+    }
+
+    /// Adds a custom metadata entry.
+    #[must_use]
+    pub fn with_metadata<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets the backend specific storage class.
+    #[must_use]
+    pub fn with_storage_class<S: Into<String>>(mut self, storage_class: S) -> Self {
+        self.storage_class = Some(storage_class.into());
+        self
+    }
+
+    /// Sets the access level of the file.
+    #[must_use]
+    pub fn with_acl(mut self, acl: Acl) -> Self {
+        self.acl = Some(acl);
+        self
+    }
+
+    /// Sets the server side encryption of the file.
+    #[must_use]
+    pub fn with_encryption(mut self, encryption: Encryption) -> Self {
+        self.encryption = Some(encryption);
+        self
+    }
+
+    /// Sets a precondition the write must satisfy.
+    #[must_use]
+    pub fn with_precondition(mut self, precondition: Precondition) -> Self {
+        self.precondition = Some(precondition);
+        self
+    }
+
+    /// Sets the last modification time reported for the file.
+    #[must_use]
+    pub const fn with_last_modified(mut self, last_modified: SystemTime) -> Self {
+        self.last_modified = Some(last_modified);
+        self
+    }
+
+    /// Adds a custom header sent with the request.
+    #[must_use]
+    pub fn with_header<K: Into<String>, V: Into<String>>(mut self, name: K, value: V) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+
+    /// Adds a custom query parameter sent with the request.
+    #[must_use]
+    pub fn with_query_param<K: Into<String>, V: Into<String>>(mut self, name: K, value: V) -> Self {
+        self.query_params.insert(name.into(), value.into());
+        self
+    }
+
+    /// Sets the type of the written blob.
+    #[must_use]
+    pub const fn with_blob_type(mut self, blob_type: BlobType) -> Self {
+        self.blob_type = Some(blob_type);
+        self
+    }
+}
+
+/// Options of a single read with [`Driver::read_with_options`].
+#[derive(Clone, Debug, Default)]
+pub struct ReadOptions {
+    /// Custom headers sent with the request, for provider features the
+    /// driver doesn't cover, e.g. `x-amz-request-payer`.
+    pub headers: BTreeMap<String, String>,
+    /// Custom query parameters sent with the request.
+    pub query_params: BTreeMap<String, String>,
+}
+
+impl ReadOptions {
+    /// Adds a custom header sent with the request.
+    #[must_use]
+    pub fn with_header<K: Into<String>, V: Into<String>>(mut self, name: K, value: V) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+
+    /// Adds a custom query parameter sent with the request.
+    #[must_use]
+    pub fn with_query_param<K: Into<String>, V: Into<String>>(mut self, name: K, value: V) -> Self {
+        self.query_params.insert(name.into(), value.into());
+        self
+    }
+}
+
+/// Writes the content with the given options as the default
+/// [`Driver::write_with_options`] does, for drivers overriding it only for
+/// some options.
+pub(crate) async fn write_with_default_options<D: Driver + ?Sized>(
+    driver: &D,
+    path: &ObjectPath,
+    content: Bytes,
+    options: &WriteOptions,
+) -> DriverResult<()> {
+    if !options.metadata.is_empty() {
+        return Err(DriverError::Unsupported("object metadata"));
+    }
+    if options.storage_class.is_some() {
+        return Err(DriverError::Unsupported("storage classes"));
+    }
+    if options.encryption.is_some() {
+        return Err(DriverError::Unsupported("server side encryption"));
+    }
+    check_passthrough_supported(&options.headers, &options.query_params)?;
+    if options.last_modified.is_some()
+        && !driver
+            .capabilities()
+            .contains(Capabilities::SET_LAST_MODIFIED)
+    {
+        return Err(DriverError::Unsupported(
+            "setting the last modification time",
+        ));
+    }
+    match &options.precondition {
+        Some(Precondition::IfNotExists) if driver.file_exists(path).await? => {
+            return Err(DriverError::PreconditionFailed);
+        }
+        Some(Precondition::IfMatch(_)) => {
+            return Err(DriverError::Unsupported("conditional writes on ETag"));
+        }
+        _ => {}
+    }
+
+    match &options.acl {
+        Some(acl) => driver.write_with_acl(path, content, acl).await?,
+        None => driver.write(path, content).await?,
+    }
+    match options.last_modified {
+        Some(last_modified) => driver.set_last_modified(path, last_modified).await,
+        None => Ok(()),
+    }
+}
+
 #[async_trait::async_trait]
 pub trait Driver: DynClone + Sync + Send {
-    async fn read(&self, path: &Path) -> DriverResult<Vec<u8>>;
+    async fn read(&self, path: &ObjectPath) -> DriverResult<Bytes>;
+
+    async fn file_exists(&self, path: &ObjectPath) -> DriverResult<bool>;
+
+    async fn write(&self, path: &ObjectPath, content: Bytes) -> DriverResult<()>;
+
+    async fn delete(&self, path: &ObjectPath) -> DriverResult<()>;
+
+    async fn delete_directory(&self, path: &ObjectPath) -> DriverResult<()>;
+
+    async fn last_modified(&self, path: &ObjectPath) -> DriverResult<SystemTime>;
+
+    /// Returns the size in bytes of the file at the given path.
+    ///
+    /// The default implementation reads the whole file; drivers should
+    /// override it with a metadata lookup.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist or could not be queried.
+    async fn size(&self, path: &ObjectPath) -> DriverResult<u64> {
+        Ok(self.read(path).await?.len() as u64)
+    }
+
+    /// Returns the metadata of the file at the given path, or `None` when it
+    /// does not exist.
+    ///
+    /// The default implementation checks the existence, the modification
+    /// time and the size separately; drivers should override it with a single
+    /// metadata lookup.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file could not be queried.
+    async fn stat(&self, path: &ObjectPath) -> DriverResult<Option<ObjectMetadata>> {
+        if !self.file_exists(path).await? {
+            return Ok(None);
+        }
+
+        Ok(Some(ObjectMetadata {
+            size: self.size(path).await?,
+            last_modified: self.last_modified(path).await?,
+            content_type: None,
+            etag: None,
+            storage_class: None,
+            content_md5: None,
+        }))
+    }
+
+    /// Deletes the file at the given path, returning whether it existed.
+    ///
+    /// The default implementation maps [`DriverError::ResourceNotFound`]
+    /// returned by [`Self::delete`] to `false`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists and could not be deleted.
+    async fn delete_if_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        match self.delete(path).await {
+            Ok(()) => Ok(true),
+            Err(DriverError::ResourceNotFound) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Creates an empty directory at the given path. Creating the storage
+    /// root is a no-op.
+    ///
+    /// The default implementation emulates the directory on object stores by
+    /// writing an empty [`DIRECTORY_MARKER`] file in it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory could not be created.
+    async fn create_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        match path.to_prefix()? {
+            Some(key) => {
+                self.write(&ObjectPath::from(&key).join(DIRECTORY_MARKER), Bytes::new())
+                    .await
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Checks whether a directory exists at the given path. The storage root
+    /// always exists.
+    ///
+    /// The default implementation checks whether any file, including a
+    /// [`DIRECTORY_MARKER`], is stored under the path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory could not be queried.
+    async fn directory_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        if path.to_prefix()?.is_none() {
+            return Ok(true);
+        }
+        Ok(!self.list(path).await?.is_empty())
+    }
+
+    /// Deletes every file of the storage. Unlike [`Self::delete_directory`],
+    /// which refuses the storage root, it empties the whole container.
+    ///
+    /// The default implementation refuses to purge a storage holding files
+    /// under legal hold, then lists and deletes the files one by one; drivers
+    /// should override it with a bulk delete.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::LegalHold`] if a file is under legal hold, or an
+    /// error if the files could not be listed or deleted.
+    async fn purge_all(&self) -> DriverResult<()> {
+        check_no_legal_holds(self, &ObjectPath::default()).await?;
+        for path in self.list(&ObjectPath::default()).await? {
+            self.delete_if_exists(&path).await?;
+        }
+        Ok(())
+    }
+
+    /// Reads the file at the given path when it was modified after the given
+    /// time, returning `None` otherwise.
+    ///
+    /// The default implementation compares the [`Self::stat`] modification
+    /// time before reading; drivers should override it with a conditional
+    /// `GET`, which compares whole seconds.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist or could not be read.
+    async fn read_if_modified_since(
+        &self,
+        path: &ObjectPath,
+        since: SystemTime,
+    ) -> DriverResult<Option<Bytes>> {
+        match self.stat(path).await? {
+            Some(metadata) if metadata.last_modified > since => Ok(Some(self.read(path).await?)),
+            Some(_) => Ok(None),
+            None => Err(DriverError::ResourceNotFound),
+        }
+    }
+
+    /// Reads the file at the given path with the given options.
+    ///
+    /// The default implementation reads the file with [`Self::read`], and
+    /// returns [`DriverError::Unsupported`] for custom headers and query
+    /// parameters, which only drivers sending HTTP requests can attach.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an option is not supported, or the file does not
+    /// exist or could not be read.
+    async fn read_with_options(
+        &self,
+        path: &ObjectPath,
+        options: &ReadOptions,
+    ) -> DriverResult<Bytes> {
+        check_passthrough_supported(&options.headers, &options.query_params)?;
+        self.read(path).await
+    }
+
+    /// Reads the given byte range of the file at the given path. The range end
+    /// is clamped to the file size.
+    ///
+    /// The default implementation reads the whole file and slices it; drivers
+    /// should override it with a native ranged read.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist or could not be read.
+    async fn read_range(&self, path: &ObjectPath, range: Range<u64>) -> DriverResult<Vec<u8>> {
+        let content = self.read(path).await?;
+        let end = usize::try_from(range.end)
+            .unwrap_or(usize::MAX)
+            .min(content.len());
+        let start = usize::try_from(range.start).unwrap_or(usize::MAX).min(end);
+
+        Ok(content[start..end].to_vec())
+    }
+
+    /// Lists all the files under the given directory, recursively. The
+    /// returned paths are relative to the storage root. An empty path lists
+    /// the whole storage.
+    ///
+    /// The default implementation returns [`DriverError::Unsupported`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the driver can't list files or the path is invalid.
+    async fn list(&self, _path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        Err(DriverError::Unsupported("listing"))
+    }
+
+    /// Returns the optional features the driver supports natively.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::empty()
+    }
+
+    /// Returns the driver as a [`PresignExt`] when it signs expiring URLs.
+    fn as_presign(&self) -> Option<&dyn PresignExt> {
+        None
+    }
+
+    /// Returns the driver as a [`VersioningExt`] when it keeps previous
+    /// versions of files.
+    fn as_versioning(&self) -> Option<&dyn VersioningExt> {
+        None
+    }
+
+    /// Writes the content to the file at the specified path with the given
+    /// options.
+    ///
+    /// The default implementation checks [`Precondition::IfNotExists`] with
+    /// a separate existence check, so it is not atomic, and ignores the
+    /// content type and cache control, which drivers without metadata derive
+    /// when serving the file. The last modification time is set after the
+    /// write with [`Self::set_last_modified`]. Metadata, storage classes,
+    /// encryption, custom headers and query parameters, append and page blobs,
+    /// and [`Precondition::IfMatch`] return [`DriverError::Unsupported`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::PreconditionFailed`] if the precondition is not
+    /// satisfied, or an error if an option is not supported or the file could
+    /// not be written.
+    async fn write_with_options(
+        &self,
+        path: &ObjectPath,
+        content: Bytes,
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        if matches!(
+            options.blob_type,
+            Some(BlobType::AppendBlob | BlobType::PageBlob)
+        ) {
+            return Err(DriverError::Unsupported("append and page blobs"));
+        }
+        write_with_default_options(self, path, content, options).await
+    }
+
+    /// Sets the last modification time of the file at the given path, for
+    /// drivers with [`Capabilities::SET_LAST_MODIFIED`] that can change it
+    /// after the write.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::Unsupported`] by default, or an error if the
+    /// file does not exist.
+    async fn set_last_modified(
+        &self,
+        _path: &ObjectPath,
+        _last_modified: SystemTime,
+    ) -> DriverResult<()> {
+        Err(DriverError::Unsupported(
+            "setting the last modification time",
+        ))
+    }
+
+    /// Writes the content to the file at the specified path with the given
+    /// access level, overriding the container default. Use [`Driver::write`]
+    /// to keep the container default.
+    ///
+    /// The default implementation returns [`DriverError::Unsupported`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the driver has no per object access control or the
+    /// file could not be written.
+    async fn write_with_acl(
+        &self,
+        _path: &ObjectPath,
+        _content: Bytes,
+        _acl: &Acl,
+    ) -> DriverResult<()> {
+        Err(DriverError::Unsupported("object ACLs"))
+    }
+
+    /// Changes the access level of the existing file at the specified path.
+    ///
+    /// The default implementation returns [`DriverError::Unsupported`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the driver has no per object access control or the
+    /// file does not exist.
+    async fn set_acl(&self, _path: &ObjectPath, _acl: &Acl) -> DriverResult<()> {
+        Err(DriverError::Unsupported("object ACLs"))
+    }
+
+    /// Returns a stable public URL of the file at the given path, built from
+    /// the driver configured public URL (CDN domain, website endpoint or local
+    /// static prefix).
+    ///
+    /// The default implementation returns [`DriverError::Unsupported`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the driver has no public URL configured or the path
+    /// is invalid.
+    async fn public_url(&self, _path: &ObjectPath) -> DriverResult<String> {
+        Err(DriverError::Unsupported("public url"))
+    }
+
+    /// Checks whether the storage container (S3 bucket, Azure container or
+    /// disk root) exists.
+    ///
+    /// The default implementation returns `Ok(true)` for drivers that don't
+    /// have a container concept.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the backend could not be queried.
+    async fn container_exists(&self) -> DriverResult<bool> {
+        Ok(true)
+    }
+
+    /// Creates the storage container when it doesn't exist.
+    ///
+    /// The default implementation does nothing for drivers that don't have a
+    /// container concept.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the container could not be created.
+    async fn ensure_container(&self, _options: &ContainerOptions) -> DriverResult<()> {
+        Ok(())
+    }
+
+    /// Performs a cheap probe against the backend to verify it is reachable
+    /// and usable.
+    ///
+    /// The default implementation writes and deletes a canary file under the
+    /// [`HEALTH_CHECK_PREFIX`] folder. Drivers that have a cheaper native
+    /// probe should override it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the backend could not answer the probe.
+    async fn health_check(&self) -> DriverResult<()> {
+        let canary = health_check_canary_path();
+        self.write(&canary, Bytes::new()).await?;
+        self.delete(&canary).await
+    }
+
+    /// Maps the retention policies to the native lifecycle rules of the
+    /// backend, replacing the existing ones, so the backend deletes the files
+    /// past their maximum retention by itself.
+    ///
+    /// The default implementation returns [`DriverError::Unsupported`]; the
+    /// policies are then enforced with [`retention::RetentionDriver`] and
+    /// [`crate::store::Store::sweep_expired`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the rules could not be applied.
+    async fn apply_retention_policies(&self, _policies: &[RetentionPolicy]) -> DriverResult<()> {
+        Err(DriverError::Unsupported("native retention policies"))
+    }
+
+    /// Places or lifts a legal hold on the file at the given path.
+    /// [`crate::store::Store`] refuses to delete held files with
+    /// [`DriverError::LegalHold`].
+    ///
+    /// The default implementation emulates the hold with an empty marker file
+    /// under the [`LEGAL_HOLDS_PREFIX`] folder, which the disk, in-memory and
+    /// Azure drivers check before deleting or overwriting files.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::ResourceNotFound`] if the file doesn't exist, or
+    /// an error if the hold could not be changed.
+    async fn set_legal_hold(&self, path: &ObjectPath, hold: bool) -> DriverResult<()> {
+        if !self.file_exists(path).await? {
+            return Err(DriverError::ResourceNotFound);
+        }
+        let marker = legal_hold_path(path)?;
+        if hold {
+            self.write(&marker, Bytes::new()).await
+        } else {
+            self.delete_if_exists(&marker).await.map(|_| ())
+        }
+    }
+
+    /// Returns whether the file at the given path is under legal hold.
+    ///
+    /// The default implementation checks the marker file written by
+    /// [`Self::set_legal_hold`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the hold could not be checked.
+    async fn legal_hold(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.file_exists(&legal_hold_path(path)?).await
+    }
+
+    /// Lists the files under legal hold in the given directory, recursively.
+    ///
+    /// The default implementation lists the marker files written by
+    /// [`Self::set_legal_hold`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the holds could not be listed.
+    async fn list_legal_holds(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        let markers = self
+            .list(&ObjectPath::new(LEGAL_HOLDS_PREFIX).join(path))
+            .await?;
+        Ok(markers
+            .into_iter()
+            .filter_map(|marker| marker.strip_prefix(LEGAL_HOLDS_PREFIX))
+            .collect())
+    }
+
+    /// Starts a multipart upload of the file at the given path, returning the
+    /// upload id.
+    ///
+    /// The default implementation emulates multipart uploads by writing each
+    /// part as a file under the [`UPLOADS_PREFIX`] folder and assembling them
+    /// on completion.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the upload could not be started.
+    async fn create_upload(&self, path: &ObjectPath) -> DriverResult<String> {
+        let key = path.to_key()?;
+        let upload_id = unique_id();
+
+        // the target path is recorded so stale uploads can be listed
+        self.write(
+            &upload_target_path(&upload_id)?,
+            Bytes::copy_from_slice(key.as_str().as_bytes()),
+        )
+        .await?;
+
+        Ok(upload_id)
+    }
+
+    /// Starts a multipart upload of the file at the given path, written with
+    /// the given options once completed.
+    ///
+    /// The default implementation starts the upload with
+    /// [`Self::create_upload`], leaving the options to
+    /// [`Self::complete_upload_with_options`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the upload could not be started.
+    async fn create_upload_with_options(
+        &self,
+        path: &ObjectPath,
+        _options: &WriteOptions,
+    ) -> DriverResult<String> {
+        self.create_upload(path).await
+    }
+
+    /// Uploads a part of a multipart upload, returning the uploaded part.
+    /// Uploading the same part number again replaces the part.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the part could not be uploaded.
+    async fn upload_part(
+        &self,
+        _path: &ObjectPath,
+        upload_id: &str,
+        part_number: u32,
+        content: Bytes,
+    ) -> DriverResult<UploadPart> {
+        let etag = format!("{part_number}-{}", content.len());
+        self.write(&upload_part_path(upload_id, part_number)?, content)
+            .await?;
+
+        Ok(UploadPart { part_number, etag })
+    }
+
+    /// Lists the parts uploaded so far to a multipart upload, ordered by part
+    /// number, so an interrupted upload can be resumed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the upload doesn't exist or could not be listed.
+    async fn list_upload_parts(
+        &self,
+        _path: &ObjectPath,
+        upload_id: &str,
+    ) -> DriverResult<Vec<UploadPart>> {
+        let mut parts = Vec::new();
+        for part_path in self.list(&upload_path(upload_id)?).await? {
+            let Some(part_number) = part_path
+                .file_name()
+                .and_then(|name| name.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            let size = self.read(&part_path).await?.len();
+            parts.push(UploadPart {
+                part_number,
+                etag: format!("{part_number}-{size}"),
+            });
+        }
+        parts.sort_by_key(|part| part.part_number);
+
+        Ok(parts)
+    }
+
+    /// Completes a multipart upload, assembling the given parts in order into
+    /// the file at the given path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a part is missing or the file could not be written.
+    async fn complete_upload(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+    ) -> DriverResult<()> {
+        let mut content = Vec::new();
+        for part in parts {
+            content.extend(
+                self.read(&upload_part_path(upload_id, part.part_number)?)
+                    .await?,
+            );
+        }
+
+        self.write(path, content.into()).await?;
+        self.abort_upload(path, upload_id).await
+    }
+
+    /// Completes a multipart upload started with
+    /// [`Self::create_upload_with_options`] and the same options.
+    ///
+    /// The default implementation completes the upload with
+    /// [`Self::complete_upload`] and only applies the last modification
+    /// time, with [`Self::set_last_modified`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a part is missing, the file could not be written
+    /// or an option is not supported.
+    async fn complete_upload_with_options(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        self.complete_upload(path, upload_id, parts).await?;
+        match options.last_modified {
+            Some(last_modified) => self.set_last_modified(path, last_modified).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Lists the multipart uploads under the given prefix that were started
+    /// and neither completed nor aborted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the uploads could not be listed.
+    async fn list_uploads(&self, prefix: &ObjectPath) -> DriverResult<Vec<PendingUpload>> {
+        let prefix = prefix.to_prefix()?.map(|key| ObjectPath::from(&key));
+
+        let mut uploads = Vec::new();
+        for target_path in self.list(&UPLOADS_PREFIX.into()).await? {
+            if target_path.file_name() != Some(UPLOAD_TARGET) {
+                continue;
+            }
+            let Some(upload_id) = target_path
+                .parent()
+                .and_then(|parent| parent.file_name().map(ToString::to_string))
+            else {
+                continue;
+            };
+            let path = ObjectPath::from(
+                String::from_utf8(self.read(&target_path).await?.into())
+                    .map_err(|_| DriverError::DecodeError)?,
+            );
+            if prefix
+                .as_ref()
+                .is_some_and(|prefix| !path.starts_with(prefix))
+            {
+                continue;
+            }
+
+            uploads.push(PendingUpload {
+                path,
+                upload_id,
+                initiated: self.last_modified(&target_path).await?,
+            });
+        }
+
+        Ok(uploads)
+    }
+
+    /// Aborts a multipart upload, discarding its uploaded parts.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the parts could not be discarded.
+    async fn abort_upload(&self, _path: &ObjectPath, upload_id: &str) -> DriverResult<()> {
+        match self.delete_directory(&upload_path(upload_id)?).await {
+            Ok(()) | Err(DriverError::ResourceNotFound) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Characters that must be percent-encoded in a URL path segment.
+#[cfg(any(
+    feature = "disk",
+    feature = "aws_s3",
+    feature = "azure",
+    feature = "http"
+))]
+const URL_PATH_SEGMENT: &percent_encoding::AsciiSet = &percent_encoding::CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Joins the given path to a base URL, encoding its key with the backend
+/// rules and percent-encoding every path segment.
+#[cfg(any(
+    feature = "disk",
+    feature = "aws_s3",
+    feature = "azure",
+    feature = "http"
+))]
+pub(crate) fn join_url(
+    base_url: &str,
+    path: &ObjectPath,
+    rules: crate::key::KeyRules,
+) -> DriverResult<String> {
+    let key = path.to_key()?.encode(rules)?;
+    let encoded = key
+        .split('/')
+        .map(|segment| percent_encoding::utf8_percent_encode(segment, URL_PATH_SEGMENT).to_string())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    Ok(format!("{}/{encoded}", base_url.trim_end_matches('/')))
+}
+
+/// Returns the key of an object listed under its name encoded with the given
+/// rules, the reverse of [`Key::encode`]. Names that aren't valid encodings,
+/// e.g. of objects written by other tools, are returned as is.
+#[cfg(any(feature = "disk", feature = "aws_s3", feature = "azure"))]
+pub(crate) fn decode_key(encoded: &str, rules: crate::key::KeyRules) -> ObjectPath {
+    Key::decode(encoded, rules).map_or_else(|_| ObjectPath::new(encoded), |key| (&key).into())
+}
+
+/// The name of the empty file emulating a directory created with
+/// [`Driver::create_directory`] on object stores. Markers are hidden from
+/// [`crate::store::Store::list`].
+pub const DIRECTORY_MARKER: &str = ".active-storage-keep";
+
+/// Returns `true` when the path is a [`DIRECTORY_MARKER`].
+pub(crate) fn is_directory_marker(path: &ObjectPath) -> bool {
+    path.file_name() == Some(DIRECTORY_MARKER)
+}
+
+/// The prefix of the names under which the crate keeps its own records, e.g.
+/// [`LEGAL_HOLDS_PREFIX`] or the staged parts of multipart uploads.
+pub const RESERVED_PREFIX: &str = ".active-storage-";
+
+/// Returns `true` when the path belongs to the records of the crate: it lives
+/// under a [`RESERVED_PREFIX`] folder or is a [`DIRECTORY_MARKER`]. Reserved
+/// paths are hidden from [`crate::store::Store::list`].
+pub(crate) fn is_reserved(path: &ObjectPath) -> bool {
+    path.as_str()
+        .split('/')
+        .next()
+        .is_some_and(|segment| segment.starts_with(RESERVED_PREFIX))
+        || is_directory_marker(path)
+}
+
+/// Converts the path of a directory to delete into its key, refusing the
+/// storage root.
+#[cfg(any(
+    feature = "disk",
+    feature = "inmem",
+    feature = "aws_s3",
+    feature = "azure"
+))]
+pub(crate) fn directory_key(path: &ObjectPath) -> DriverResult<Key> {
+    path.to_prefix()?.ok_or(DriverError::RefusingToDeleteRoot)
+}
+
+/// Returns the marker file emulating the legal hold of the file at the given
+/// path.
+pub(crate) fn legal_hold_path(path: &ObjectPath) -> DriverResult<ObjectPath> {
+    Ok(ObjectPath::new(LEGAL_HOLDS_PREFIX).join(&path.to_key()?))
+}
+
+/// Returns [`DriverError::LegalHold`] when the file at the given path is under
+/// legal hold, for the drivers emulating holds with the default
+/// [`Driver::set_legal_hold`].
+pub(crate) async fn check_legal_hold<D: Driver + ?Sized>(
+    driver: &D,
+    path: &ObjectPath,
+) -> DriverResult<()> {
+    match driver.legal_hold(path).await {
+        Ok(true) => Err(DriverError::LegalHold),
+        Ok(false) | Err(DriverError::ResourceNotFound) => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Returns [`DriverError::LegalHold`] when a file in the given directory,
+/// recursively, is under legal hold.
+pub(crate) async fn check_no_legal_holds<D: Driver + ?Sized>(
+    driver: &D,
+    path: &ObjectPath,
+) -> DriverResult<()> {
+    if driver.list_legal_holds(path).await?.is_empty() {
+        Ok(())
+    } else {
+        Err(DriverError::LegalHold)
+    }
+}
+
+/// Returns the folder holding the parts of an emulated multipart upload.
+pub(crate) fn upload_path(upload_id: &str) -> DriverResult<ObjectPath> {
+    Ok(ObjectPath::new(UPLOADS_PREFIX).join(&Key::new(upload_id)?))
+}
+
+/// Returns [`DriverError::Unsupported`] when custom headers or query
+/// parameters are set, for drivers that can't send them.
+fn check_passthrough_supported(
+    headers: &BTreeMap<String, String>,
+    query_params: &BTreeMap<String, String>,
+) -> DriverResult<()> {
+    if !headers.is_empty() {
+        return Err(DriverError::Unsupported("custom request headers"));
+    }
+    if !query_params.is_empty() {
+        return Err(DriverError::Unsupported("custom query parameters"));
+    }
+    Ok(())
+}
+
+/// Checks that the custom headers are valid HTTP header names and values,
+/// before they are attached to a request.
+#[cfg(any(feature = "aws_s3", feature = "http"))]
+pub(crate) fn validate_headers(headers: &BTreeMap<String, String>) -> DriverResult<()> {
+    for (name, value) in headers {
+        let is_valid_name = !name.is_empty()
+            && name
+                .bytes()
+                .all(|c| c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&c));
+        let is_valid_value = value
+            .bytes()
+            .all(|c| c == b'\t' || (b' '..=b'~').contains(&c));
+        if !is_valid_name || !is_valid_value {
+            return Err(DriverError::InvalidHeader(name.clone()));
+        }
+    }
+    Ok(())
+}
+
+/// The name of the file recording the target path of an emulated multipart
+/// upload.
+const UPLOAD_TARGET: &str = "target";
+
+/// Returns the path of the file recording the target path of an emulated
+/// multipart upload.
+pub(crate) fn upload_target_path(upload_id: &str) -> DriverResult<ObjectPath> {
+    Ok(upload_path(upload_id)?.join(UPLOAD_TARGET))
+}
+
+/// Returns the path of a part of an emulated multipart upload.
+pub(crate) fn upload_part_path(upload_id: &str, part_number: u32) -> DriverResult<ObjectPath> {
+    Ok(upload_path(upload_id)?.join(format!("{part_number:05}")))
+}
+
+/// Returns an id unique across processes, e.g. for emulated multipart
+/// uploads.
+pub(crate) fn unique_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let nanos = clock::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos());
+    format!(
+        "{:x}-{nanos:x}-{:x}",
+        clock::process_id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Returns a unique canary path under the [`HEALTH_CHECK_PREFIX`] folder, so
+/// concurrent probes from different processes do not collide.
+fn health_check_canary_path() -> ObjectPath {
+    let nanos = clock::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos());
+
+    ObjectPath::new(HEALTH_CHECK_PREFIX).join(format!("{}-{nanos}", clock::process_id()))
+}
+
+#[cfg(all(test, feature = "inmem"))]
+mod tests {
+
+    use super::*;
+    use crate::{drivers::inmem::InMemoryDriver, store::Store};
+
+    #[test]
+    fn can_detect_reserved_paths() {
+        assert!(is_reserved(
+            &ObjectPath::new(LEGAL_HOLDS_PREFIX).join("a.txt")
+        ));
+        assert!(is_reserved(&upload_part_path("id", 1).unwrap()));
+        assert!(is_reserved(&ObjectPath::new("docs").join(DIRECTORY_MARKER)));
+        assert!(!is_reserved(&ObjectPath::new("docs/.active-storage-a.txt")));
+        assert!(!is_reserved(&ObjectPath::new(
+            "active-storage-uploads/a.txt"
+        )));
+        assert!(!is_reserved(&ObjectPath::new("docs/a.txt")));
+    }
+
+    #[tokio::test]
+    async fn can_assemble_emulated_uploads_in_part_order() {
+        let driver = InMemoryDriver::default();
+        let path = ObjectPath::new("docs/a.txt");
+        let upload_id = driver.create_upload(&path).await.unwrap();
+
+        for (part_number, content) in [(3, "c"), (1, "stale"), (2, "b"), (1, "a")] {
+            driver
+                .upload_part(&path, &upload_id, part_number, Bytes::from(content))
+                .await
+                .unwrap();
+        }
+        let parts = driver.list_upload_parts(&path, &upload_id).await.unwrap();
+        assert_eq!(
+            parts
+                .iter()
+                .map(|part| part.part_number)
+                .collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+
+        driver
+            .complete_upload(&path, &upload_id, &parts)
+            .await
+            .unwrap();
+        assert_eq!(driver.read(&path).await.unwrap(), "abc");
+        assert!(driver
+            .list_uploads(&ObjectPath::default())
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn can_clean_up_aborted_emulated_uploads() {
+        let driver = InMemoryDriver::default();
+        let path = ObjectPath::new("docs/a.txt");
+        let upload_id = driver.create_upload(&path).await.unwrap();
+        driver
+            .upload_part(&path, &upload_id, 1, Bytes::from("a"))
+            .await
+            .unwrap();
+
+        let uploads = driver.list_uploads(&ObjectPath::default()).await.unwrap();
+        assert_eq!(uploads.len(), 1);
+        assert_eq!(uploads[0].path, path);
+        assert_eq!(uploads[0].upload_id, upload_id);
+
+        driver.abort_upload(&path, &upload_id).await.unwrap();
+        assert!(driver
+            .list(&UPLOADS_PREFIX.into())
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(driver
+            .list_uploads(&ObjectPath::default())
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(!driver.file_exists(&path).await.unwrap());
+
+        // aborting twice is not an error
+        assert!(driver.abort_upload(&path, &upload_id).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn can_refuse_deleting_and_overwriting_held_files() {
+        let driver = InMemoryDriver::default();
+        let path = ObjectPath::new("contracts/1.pdf");
+        driver.write(&path, Bytes::from("content")).await.unwrap();
+        driver.set_legal_hold(&path, true).await.unwrap();
+
+        assert!(matches!(
+            driver.delete(&path).await,
+            Err(DriverError::LegalHold)
+        ));
+        assert!(matches!(
+            driver.write(&path, Bytes::from("other")).await,
+            Err(DriverError::LegalHold)
+        ));
 
-    async fn file_exists(&self, path: &Path) -> DriverResult<bool>;
+        let upload_id = driver.create_upload(&path).await.unwrap();
+        let part = driver
+            .upload_part(&path, &upload_id, 1, Bytes::from("other"))
+            .await
+            .unwrap();
+        assert!(matches!(
+            driver.complete_upload(&path, &upload_id, &[part]).await,
+            Err(DriverError::LegalHold)
+        ));
+        assert_eq!(driver.read(&path).await.unwrap(), "content");
+        assert_eq!(
+            driver
+                .list_upload_parts(&path, &upload_id)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
 
-    async fn write(&self, path: &Path, content: Vec<u8>) -> DriverResult<()>;
+        driver.set_legal_hold(&path, false).await.unwrap();
+        assert!(driver.write(&path, Bytes::from("other")).await.is_ok());
+        assert!(driver.delete(&path).await.is_ok());
+    }
 
-    async fn delete(&self, path: &Path) -> DriverResult<()>;
+    #[tokio::test]
+    async fn can_filter_reserved_paths_from_listing() {
+        let driver = InMemoryDriver::default();
+        let path = ObjectPath::new("a.txt");
+        driver.write(&path, Bytes::from("a")).await.unwrap();
+        driver.set_legal_hold(&path, true).await.unwrap();
+        let upload_id = driver.create_upload(&path).await.unwrap();
+        driver
+            .upload_part(&path, &upload_id, 1, Bytes::from("b"))
+            .await
+            .unwrap();
 
-    async fn delete_directory(&self, path: &Path) -> DriverResult<()>;
+        let listed = driver.list(&ObjectPath::default()).await.unwrap();
+        assert_eq!(listed.len(), 4);
+        assert_eq!(
+            listed
+                .iter()
+                .filter(|listed| !is_reserved(listed))
+                .collect::<Vec<_>>(),
+            vec![&path]
+        );
 
-    async fn last_modified(&self, path: &Path) -> DriverResult<SystemTime>;
+        let store = Store::new(Box::new(driver));
+        assert_eq!(store.list(ObjectPath::default()).await.unwrap(), vec![path]);
+    }
 }
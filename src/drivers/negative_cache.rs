@@ -0,0 +1,429 @@
+use std::{
+    collections::HashMap,
+    ops::Range,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+use bytes::Bytes;
+
+use super::{
+    capabilities::{Capabilities, ObjectVersion, PresignExt, VersioningExt},
+    Acl, ContainerOptions, Driver, DriverError, DriverResult, ObjectMetadata, ReadOptions,
+    RetentionPolicy, WriteOptions,
+};
+use crate::{
+    key::ObjectPath,
+    upload::{PendingUpload, UploadPart},
+};
+
+/// The number of cached keys above which the expired ones are evicted.
+const EVICTION_THRESHOLD: usize = 10_000;
+
+/// The `NegativeCacheDriver` struct wraps a [`Driver`] and remembers the
+/// files it found missing for a short time, answering the lookups of those
+/// files with [`DriverError::ResourceNotFound`] without a request, e.g. to
+/// protect the backend from code repeatedly probing optional per-user files.
+///
+/// A file written through the driver is forgotten at once. A file created by
+/// another process is seen once its entry expires, so keep the time to live
+/// short.
+#[allow(clippy::module_name_repetitions)]
+pub struct NegativeCacheDriver {
+    inner: Box<dyn Driver>,
+    ttl: Duration,
+    missing: Arc<Mutex<HashMap<PathBuf, Instant>>>,
+}
+
+impl Clone for NegativeCacheDriver {
+    fn clone(&self) -> Self {
+        Self {
+            inner: dyn_clone::clone_box(&*self.inner),
+            ttl: self.ttl,
+            missing: self.missing.clone(),
+        }
+    }
+}
+
+impl NegativeCacheDriver {
+    /// Creates a new [`NegativeCacheDriver`] remembering the missing files of
+    /// the given driver for the given time.
+    #[must_use]
+    pub fn new(inner: Box<dyn Driver>, ttl: Duration) -> Self {
+        Self {
+            inner,
+            ttl,
+            missing: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn missing(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, Instant>> {
+        self.missing
+            .lock()
+            .expect("negative cache failed getting a lock")
+    }
+
+    /// Returns whether the file at the given path was recently found missing.
+    fn is_missing(&self, path: &ObjectPath) -> bool {
+        let mut missing = self.missing();
+        let file = path.to_path_buf();
+        match missing.get(&file) {
+            Some(expires_at) if Instant::now() < *expires_at => true,
+            Some(_) => {
+                missing.remove(&file);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Returns [`DriverError::ResourceNotFound`] if the file at the given
+    /// path was recently found missing.
+    fn check(&self, path: &ObjectPath) -> DriverResult<()> {
+        if self.is_missing(path) {
+            Err(DriverError::ResourceNotFound)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn insert(&self, path: &ObjectPath) {
+        let now = Instant::now();
+        let mut missing = self.missing();
+        if missing.len() >= EVICTION_THRESHOLD {
+            missing.retain(|_, expires_at| now < *expires_at);
+        }
+        missing.insert(path.to_path_buf(), now + self.ttl);
+    }
+
+    fn forget(&self, path: &ObjectPath) {
+        self.missing().remove(&path.to_path_buf());
+    }
+
+    /// Remembers the file as missing when the result is
+    /// [`DriverError::ResourceNotFound`].
+    fn remember<T>(&self, path: &ObjectPath, result: DriverResult<T>) -> DriverResult<T> {
+        if matches!(result, Err(DriverError::ResourceNotFound)) {
+            self.insert(path);
+        }
+        result
+    }
+}
+
+#[async_trait::async_trait]
+impl Driver for NegativeCacheDriver {
+    async fn read(&self, path: &ObjectPath) -> DriverResult<Bytes> {
+        self.check(path)?;
+        self.remember(path, self.inner.read(path).await)
+    }
+
+    async fn file_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        if self.is_missing(path) {
+            return Ok(false);
+        }
+        let exists = self.inner.file_exists(path).await?;
+        if !exists {
+            self.insert(path);
+        }
+        Ok(exists)
+    }
+
+    async fn write(&self, path: &ObjectPath, content: Bytes) -> DriverResult<()> {
+        self.forget(path);
+        self.inner.write(path, content).await
+    }
+
+    async fn write_with_options(
+        &self,
+        path: &ObjectPath,
+        content: Bytes,
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        self.forget(path);
+        self.inner.write_with_options(path, content, options).await
+    }
+
+    async fn write_with_acl(
+        &self,
+        path: &ObjectPath,
+        content: Bytes,
+        acl: &Acl,
+    ) -> DriverResult<()> {
+        self.forget(path);
+        self.inner.write_with_acl(path, content, acl).await
+    }
+
+    async fn set_acl(&self, path: &ObjectPath, acl: &Acl) -> DriverResult<()> {
+        self.inner.set_acl(path, acl).await
+    }
+
+    async fn delete(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.inner.delete(path).await
+    }
+
+    async fn delete_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.inner.delete_directory(path).await
+    }
+
+    async fn last_modified(&self, path: &ObjectPath) -> DriverResult<SystemTime> {
+        self.check(path)?;
+        self.remember(path, self.inner.last_modified(path).await)
+    }
+
+    async fn size(&self, path: &ObjectPath) -> DriverResult<u64> {
+        self.check(path)?;
+        self.remember(path, self.inner.size(path).await)
+    }
+
+    async fn create_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.inner.create_directory(path).await
+    }
+
+    async fn directory_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.directory_exists(path).await
+    }
+
+    async fn purge_all(&self) -> DriverResult<()> {
+        self.inner.purge_all().await
+    }
+
+    async fn set_last_modified(
+        &self,
+        path: &ObjectPath,
+        last_modified: SystemTime,
+    ) -> DriverResult<()> {
+        self.inner.set_last_modified(path, last_modified).await
+    }
+
+    async fn delete_if_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.delete_if_exists(path).await
+    }
+
+    async fn stat(&self, path: &ObjectPath) -> DriverResult<Option<ObjectMetadata>> {
+        if self.is_missing(path) {
+            return Ok(None);
+        }
+        let metadata = self.inner.stat(path).await?;
+        if metadata.is_none() {
+            self.insert(path);
+        }
+        Ok(metadata)
+    }
+
+    async fn read_if_modified_since(
+        &self,
+        path: &ObjectPath,
+        since: SystemTime,
+    ) -> DriverResult<Option<Bytes>> {
+        self.check(path)?;
+        self.remember(path, self.inner.read_if_modified_since(path, since).await)
+    }
+
+    async fn read_with_options(
+        &self,
+        path: &ObjectPath,
+        options: &ReadOptions,
+    ) -> DriverResult<Bytes> {
+        self.check(path)?;
+        self.remember(path, self.inner.read_with_options(path, options).await)
+    }
+
+    async fn read_range(&self, path: &ObjectPath, range: Range<u64>) -> DriverResult<Vec<u8>> {
+        self.check(path)?;
+        self.remember(path, self.inner.read_range(path, range).await)
+    }
+
+    async fn list(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        self.inner.list(path).await
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn as_presign(&self) -> Option<&dyn PresignExt> {
+        self.inner.as_presign().map(|_| self as &dyn PresignExt)
+    }
+
+    fn as_versioning(&self) -> Option<&dyn VersioningExt> {
+        self.inner
+            .as_versioning()
+            .map(|_| self as &dyn VersioningExt)
+    }
+
+    async fn public_url(&self, path: &ObjectPath) -> DriverResult<String> {
+        self.inner.public_url(path).await
+    }
+
+    async fn container_exists(&self) -> DriverResult<bool> {
+        self.inner.container_exists().await
+    }
+
+    async fn ensure_container(&self, options: &ContainerOptions) -> DriverResult<()> {
+        self.inner.ensure_container(options).await
+    }
+
+    async fn health_check(&self) -> DriverResult<()> {
+        self.inner.health_check().await
+    }
+
+    async fn apply_retention_policies(&self, policies: &[RetentionPolicy]) -> DriverResult<()> {
+        self.inner.apply_retention_policies(policies).await
+    }
+
+    async fn set_legal_hold(&self, path: &ObjectPath, hold: bool) -> DriverResult<()> {
+        self.inner.set_legal_hold(path, hold).await
+    }
+
+    async fn legal_hold(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.legal_hold(path).await
+    }
+
+    async fn list_legal_holds(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        self.inner.list_legal_holds(path).await
+    }
+
+    async fn create_upload(&self, path: &ObjectPath) -> DriverResult<String> {
+        self.inner.create_upload(path).await
+    }
+
+    async fn create_upload_with_options(
+        &self,
+        path: &ObjectPath,
+        options: &WriteOptions,
+    ) -> DriverResult<String> {
+        self.inner.create_upload_with_options(path, options).await
+    }
+
+    async fn upload_part(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        part_number: u32,
+        content: Bytes,
+    ) -> DriverResult<UploadPart> {
+        self.inner
+            .upload_part(path, upload_id, part_number, content)
+            .await
+    }
+
+    async fn list_upload_parts(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+    ) -> DriverResult<Vec<UploadPart>> {
+        self.inner.list_upload_parts(path, upload_id).await
+    }
+
+    async fn complete_upload(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+    ) -> DriverResult<()> {
+        self.forget(path);
+        self.inner.complete_upload(path, upload_id, parts).await
+    }
+
+    async fn complete_upload_with_options(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        self.forget(path);
+        self.inner
+            .complete_upload_with_options(path, upload_id, parts, options)
+            .await
+    }
+
+    async fn list_uploads(&self, prefix: &ObjectPath) -> DriverResult<Vec<PendingUpload>> {
+        self.inner.list_uploads(prefix).await
+    }
+
+    async fn abort_upload(&self, path: &ObjectPath, upload_id: &str) -> DriverResult<()> {
+        self.inner.abort_upload(path, upload_id).await
+    }
+}
+
+#[async_trait::async_trait]
+impl PresignExt for NegativeCacheDriver {
+    async fn presign_read(&self, path: &ObjectPath, expires_in: Duration) -> DriverResult<String> {
+        self.inner
+            .as_presign()
+            .ok_or(DriverError::Unsupported("presigned URLs"))?
+            .presign_read(path, expires_in)
+            .await
+    }
+
+    async fn presign_write(&self, path: &ObjectPath, expires_in: Duration) -> DriverResult<String> {
+        self.inner
+            .as_presign()
+            .ok_or(DriverError::Unsupported("presigned URLs"))?
+            .presign_write(path, expires_in)
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl VersioningExt for NegativeCacheDriver {
+    async fn list_versions(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectVersion>> {
+        self.inner
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .list_versions(path)
+            .await
+    }
+
+    async fn read_version(&self, path: &ObjectPath, version_id: &str) -> DriverResult<Vec<u8>> {
+        self.inner
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .read_version(path, version_id)
+            .await
+    }
+
+    async fn delete_version(&self, path: &ObjectPath, version_id: &str) -> DriverResult<()> {
+        self.inner
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .delete_version(path, version_id)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    use super::*;
+    use crate::StoreConfig;
+
+    #[tokio::test]
+    async fn can_cache_missing_files() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let cached = store.with_negative_cache(Duration::from_secs(60));
+        let avatar = Path::new("avatars/1.png");
+
+        assert!(matches!(
+            cached.read::<Vec<u8>>(avatar).await,
+            Err(DriverError::ResourceNotFound)
+        ));
+        // written by another process, unseen until the entry expires
+        store.write(avatar, "png").await.unwrap();
+        assert!(!cached.file_exists(avatar).await.unwrap());
+        assert!(cached.stat(avatar).await.unwrap().is_none());
+
+        cached.write(avatar, "png").await.unwrap();
+        assert_eq!(cached.read::<String>(avatar).await.unwrap(), "png");
+
+        let expiring = store.with_negative_cache(Duration::ZERO);
+        let other = Path::new("avatars/2.png");
+        assert!(!expiring.file_exists(other).await.unwrap());
+        store.write(other, "png").await.unwrap();
+        assert!(expiring.file_exists(other).await.unwrap());
+    }
+}
@@ -0,0 +1,452 @@
+use std::{
+    ops::Range,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use bytes::Bytes;
+
+use super::{
+    capabilities::{Capabilities, ObjectVersion, PresignExt, VersioningExt},
+    Acl, ContainerOptions, Driver, DriverError, DriverResult, ObjectMetadata, ReadOptions,
+    RetentionPolicy, WriteOptions,
+};
+use crate::{
+    clock,
+    key::ObjectPath,
+    upload::{PendingUpload, UploadPart},
+};
+
+/// The `RetentionDriver` struct wraps a [`Driver`] and refuses to delete the
+/// files a [`RetentionPolicy`] still retains, with
+/// [`DriverError::RetentionNotExpired`].
+///
+/// Files are retained for the longest minimum retention of the policies
+/// matching their path, counted from their last modification. Deleting a
+/// directory is refused when any file under it is retained. Overwrites are
+/// not checked.
+#[allow(clippy::module_name_repetitions)]
+pub struct RetentionDriver {
+    inner: Box<dyn Driver>,
+    policies: Arc<[RetentionPolicy]>,
+}
+
+impl Clone for RetentionDriver {
+    fn clone(&self) -> Self {
+        Self {
+            inner: dyn_clone::clone_box(&*self.inner),
+            policies: self.policies.clone(),
+        }
+    }
+}
+
+impl RetentionDriver {
+    /// Creates a new [`RetentionDriver`] enforcing the given policies on the
+    /// given driver.
+    #[must_use]
+    pub fn new(inner: Box<dyn Driver>, policies: Vec<RetentionPolicy>) -> Self {
+        Self {
+            inner,
+            policies: policies.into(),
+        }
+    }
+
+    /// Returns [`DriverError::RetentionNotExpired`] if the file at the given
+    /// path is still retained.
+    async fn check(&self, path: &ObjectPath) -> DriverResult<()> {
+        if !self
+            .policies
+            .iter()
+            .any(|policy| policy.min_retention.is_some() && policy.applies_to(path))
+        {
+            return Ok(());
+        }
+
+        let last_modified = match self.inner.last_modified(path).await {
+            Ok(last_modified) => last_modified,
+            Err(DriverError::ResourceNotFound) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        match retained_until(&self.policies, path, last_modified) {
+            Some(until) if clock::now() < until => Err(DriverError::RetentionNotExpired(until)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns [`DriverError::RetentionNotExpired`] if a file under the given
+    /// directory is still retained.
+    async fn check_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        if self
+            .policies
+            .iter()
+            .all(|policy| policy.min_retention.is_none())
+        {
+            return Ok(());
+        }
+        for file in self.inner.list(path).await? {
+            self.check(&file).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns until when the file at the given path, last modified at the given
+/// time, must be kept, when a policy sets a minimum retention for it.
+pub(crate) fn retained_until(
+    policies: &[RetentionPolicy],
+    path: &ObjectPath,
+    last_modified: SystemTime,
+) -> Option<SystemTime> {
+    policies
+        .iter()
+        .filter(|policy| policy.applies_to(path))
+        .filter_map(|policy| policy.min_retention)
+        .max()
+        .map(|min_retention| last_modified + min_retention)
+}
+
+/// Returns when the file at the given path, last modified at the given time,
+/// expires, after the longest retention of the policies matching it, when
+/// one of them sets a maximum retention.
+pub(crate) fn expires_at(
+    policies: &[RetentionPolicy],
+    path: &ObjectPath,
+    last_modified: SystemTime,
+) -> Option<SystemTime> {
+    let max_retention = policies
+        .iter()
+        .filter(|policy| policy.applies_to(path))
+        .filter_map(|policy| policy.max_retention)
+        .max()?;
+    Some(
+        retained_until(policies, path, last_modified)
+            .map_or(last_modified + max_retention, |until| {
+                until.max(last_modified + max_retention)
+            }),
+    )
+}
+
+#[async_trait::async_trait]
+impl Driver for RetentionDriver {
+    async fn read(&self, path: &ObjectPath) -> DriverResult<Bytes> {
+        self.inner.read(path).await
+    }
+
+    async fn file_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.file_exists(path).await
+    }
+
+    async fn write(&self, path: &ObjectPath, content: Bytes) -> DriverResult<()> {
+        self.inner.write(path, content).await
+    }
+
+    async fn write_with_options(
+        &self,
+        path: &ObjectPath,
+        content: Bytes,
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        self.inner.write_with_options(path, content, options).await
+    }
+
+    async fn write_with_acl(
+        &self,
+        path: &ObjectPath,
+        content: Bytes,
+        acl: &Acl,
+    ) -> DriverResult<()> {
+        self.inner.write_with_acl(path, content, acl).await
+    }
+
+    async fn set_acl(&self, path: &ObjectPath, acl: &Acl) -> DriverResult<()> {
+        self.inner.set_acl(path, acl).await
+    }
+
+    async fn delete(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.check(path).await?;
+        self.inner.delete(path).await
+    }
+
+    async fn delete_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.check_directory(path).await?;
+        self.inner.delete_directory(path).await
+    }
+
+    async fn last_modified(&self, path: &ObjectPath) -> DriverResult<SystemTime> {
+        self.inner.last_modified(path).await
+    }
+
+    async fn size(&self, path: &ObjectPath) -> DriverResult<u64> {
+        self.inner.size(path).await
+    }
+
+    async fn create_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.inner.create_directory(path).await
+    }
+
+    async fn directory_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.directory_exists(path).await
+    }
+
+    async fn purge_all(&self) -> DriverResult<()> {
+        self.check_directory(&ObjectPath::default()).await?;
+        self.inner.purge_all().await
+    }
+
+    async fn set_last_modified(
+        &self,
+        path: &ObjectPath,
+        last_modified: SystemTime,
+    ) -> DriverResult<()> {
+        self.inner.set_last_modified(path, last_modified).await
+    }
+
+    async fn delete_if_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.check(path).await?;
+        self.inner.delete_if_exists(path).await
+    }
+
+    async fn stat(&self, path: &ObjectPath) -> DriverResult<Option<ObjectMetadata>> {
+        self.inner.stat(path).await
+    }
+
+    async fn read_if_modified_since(
+        &self,
+        path: &ObjectPath,
+        since: SystemTime,
+    ) -> DriverResult<Option<Bytes>> {
+        self.inner.read_if_modified_since(path, since).await
+    }
+
+    async fn read_with_options(
+        &self,
+        path: &ObjectPath,
+        options: &ReadOptions,
+    ) -> DriverResult<Bytes> {
+        self.inner.read_with_options(path, options).await
+    }
+
+    async fn read_range(&self, path: &ObjectPath, range: Range<u64>) -> DriverResult<Vec<u8>> {
+        self.inner.read_range(path, range).await
+    }
+
+    async fn list(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        self.inner.list(path).await
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn as_presign(&self) -> Option<&dyn PresignExt> {
+        self.inner.as_presign().map(|_| self as &dyn PresignExt)
+    }
+
+    fn as_versioning(&self) -> Option<&dyn VersioningExt> {
+        self.inner
+            .as_versioning()
+            .map(|_| self as &dyn VersioningExt)
+    }
+
+    async fn public_url(&self, path: &ObjectPath) -> DriverResult<String> {
+        self.inner.public_url(path).await
+    }
+
+    async fn container_exists(&self) -> DriverResult<bool> {
+        self.inner.container_exists().await
+    }
+
+    async fn ensure_container(&self, options: &ContainerOptions) -> DriverResult<()> {
+        self.inner.ensure_container(options).await
+    }
+
+    async fn health_check(&self) -> DriverResult<()> {
+        self.inner.health_check().await
+    }
+
+    async fn apply_retention_policies(&self, policies: &[RetentionPolicy]) -> DriverResult<()> {
+        self.inner.apply_retention_policies(policies).await
+    }
+
+    async fn set_legal_hold(&self, path: &ObjectPath, hold: bool) -> DriverResult<()> {
+        self.inner.set_legal_hold(path, hold).await
+    }
+
+    async fn legal_hold(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.legal_hold(path).await
+    }
+
+    async fn list_legal_holds(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        self.inner.list_legal_holds(path).await
+    }
+
+    async fn create_upload(&self, path: &ObjectPath) -> DriverResult<String> {
+        self.inner.create_upload(path).await
+    }
+
+    async fn create_upload_with_options(
+        &self,
+        path: &ObjectPath,
+        options: &WriteOptions,
+    ) -> DriverResult<String> {
+        self.inner.create_upload_with_options(path, options).await
+    }
+
+    async fn upload_part(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        part_number: u32,
+        content: Bytes,
+    ) -> DriverResult<UploadPart> {
+        self.inner
+            .upload_part(path, upload_id, part_number, content)
+            .await
+    }
+
+    async fn list_upload_parts(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+    ) -> DriverResult<Vec<UploadPart>> {
+        self.inner.list_upload_parts(path, upload_id).await
+    }
+
+    async fn complete_upload(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+    ) -> DriverResult<()> {
+        self.inner.complete_upload(path, upload_id, parts).await
+    }
+
+    async fn complete_upload_with_options(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        self.inner
+            .complete_upload_with_options(path, upload_id, parts, options)
+            .await
+    }
+
+    async fn list_uploads(&self, prefix: &ObjectPath) -> DriverResult<Vec<PendingUpload>> {
+        self.inner.list_uploads(prefix).await
+    }
+
+    async fn abort_upload(&self, path: &ObjectPath, upload_id: &str) -> DriverResult<()> {
+        self.inner.abort_upload(path, upload_id).await
+    }
+}
+
+#[async_trait::async_trait]
+impl PresignExt for RetentionDriver {
+    async fn presign_read(&self, path: &ObjectPath, expires_in: Duration) -> DriverResult<String> {
+        self.inner
+            .as_presign()
+            .ok_or(DriverError::Unsupported("presigned URLs"))?
+            .presign_read(path, expires_in)
+            .await
+    }
+
+    async fn presign_write(&self, path: &ObjectPath, expires_in: Duration) -> DriverResult<String> {
+        self.inner
+            .as_presign()
+            .ok_or(DriverError::Unsupported("presigned URLs"))?
+            .presign_write(path, expires_in)
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl VersioningExt for RetentionDriver {
+    async fn list_versions(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectVersion>> {
+        self.inner
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .list_versions(path)
+            .await
+    }
+
+    async fn read_version(&self, path: &ObjectPath, version_id: &str) -> DriverResult<Vec<u8>> {
+        self.inner
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .read_version(path, version_id)
+            .await
+    }
+
+    async fn delete_version(&self, path: &ObjectPath, version_id: &str) -> DriverResult<()> {
+        self.check(path).await?;
+        self.inner
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .delete_version(path, version_id)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    use super::*;
+    use crate::{errors::DriverError, StoreConfig};
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    #[tokio::test]
+    async fn can_enforce_retention_policies() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let policies = vec![
+            RetentionPolicy::new("invoices").with_min_retention(30 * DAY),
+            RetentionPolicy::new("logs").with_max_retention(7 * DAY),
+            RetentionPolicy::new("logs/audit").with_min_retention(90 * DAY),
+        ];
+        let aged = |age: Duration| WriteOptions::default().with_last_modified(clock::now() - age);
+        for (path, age) in [
+            ("invoices/new.pdf", DAY),
+            ("invoices/old.pdf", 31 * DAY),
+            ("logs/new.log", DAY),
+            ("logs/old.log", 8 * DAY),
+            ("logs/audit/old.log", 8 * DAY),
+        ] {
+            assert!(store
+                .write_with_options(Path::new(path), "content", &aged(age))
+                .await
+                .is_ok());
+        }
+
+        let retained = store.with_retention(policies.clone());
+        assert!(matches!(
+            retained.delete(Path::new("invoices/new.pdf")).await,
+            Err(DriverError::RetentionNotExpired(_))
+        ));
+        assert!(matches!(
+            retained.delete_directory(Path::new("invoices")).await,
+            Err(DriverError::RetentionNotExpired(_))
+        ));
+        assert!(retained.delete(Path::new("invoices/old.pdf")).await.is_ok());
+        assert!(retained.delete(Path::new("logs/new.log")).await.is_ok());
+
+        assert_eq!(
+            store.sweep_expired(&policies).await.unwrap(),
+            vec![ObjectPath::new("logs/old.log")]
+        );
+        assert_eq!(
+            store.list(Path::new("")).await.unwrap(),
+            vec![
+                ObjectPath::new("invoices/new.pdf"),
+                ObjectPath::new("logs/audit/old.log"),
+            ]
+        );
+        assert!(matches!(
+            store.apply_retention_policies(&policies).await,
+            Err(DriverError::Unsupported(_))
+        ));
+    }
+}
@@ -0,0 +1,402 @@
+use std::{
+    ops::Range,
+    time::{Duration, SystemTime},
+};
+
+use bytes::Bytes;
+
+use super::{
+    capabilities::{Capabilities, ObjectVersion, PresignExt, VersioningExt},
+    Acl, ContainerOptions, Driver, DriverError, DriverResult, ObjectMetadata, ReadOptions,
+    RetentionPolicy, WriteOptions,
+};
+use crate::{
+    key::{Key, ObjectPath},
+    upload::{PendingUpload, UploadPart},
+};
+
+/// The `ScopedDriver` struct wraps a [`Driver`] and prepends a prefix to every
+/// path, sandboxing all the operations under that prefix.
+///
+/// Paths are normalized before being prefixed, so absolute paths and `..`
+/// components can't escape the scope.
+#[allow(clippy::module_name_repetitions)]
+pub struct ScopedDriver {
+    inner: Box<dyn Driver>,
+    prefix: Key,
+}
+
+impl Clone for ScopedDriver {
+    fn clone(&self) -> Self {
+        Self {
+            inner: dyn_clone::clone_box(&*self.inner),
+            prefix: self.prefix.clone(),
+        }
+    }
+}
+
+impl ScopedDriver {
+    /// Creates a new [`ScopedDriver`] scoping the given driver under the given
+    /// prefix.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prefix is empty or contains `..` components.
+    pub fn new(inner: Box<dyn Driver>, prefix: &ObjectPath) -> DriverResult<Self> {
+        Ok(Self {
+            inner,
+            prefix: prefix.to_key()?,
+        })
+    }
+
+    /// Returns the given path prefixed with the scope.
+    fn scoped_path(&self, path: &ObjectPath) -> DriverResult<ObjectPath> {
+        let prefix = ObjectPath::from(&self.prefix);
+        Ok(path
+            .to_prefix()?
+            .map_or_else(|| prefix.clone(), |key| prefix.join(&key)))
+    }
+}
+
+#[async_trait::async_trait]
+impl Driver for ScopedDriver {
+    async fn read(&self, path: &ObjectPath) -> DriverResult<Bytes> {
+        self.inner.read(&self.scoped_path(path)?).await
+    }
+
+    async fn file_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.file_exists(&self.scoped_path(path)?).await
+    }
+
+    async fn write(&self, path: &ObjectPath, content: Bytes) -> DriverResult<()> {
+        self.inner.write(&self.scoped_path(path)?, content).await
+    }
+
+    async fn write_with_options(
+        &self,
+        path: &ObjectPath,
+        content: Bytes,
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        self.inner
+            .write_with_options(&self.scoped_path(path)?, content, options)
+            .await
+    }
+
+    async fn write_with_acl(
+        &self,
+        path: &ObjectPath,
+        content: Bytes,
+        acl: &Acl,
+    ) -> DriverResult<()> {
+        self.inner
+            .write_with_acl(&self.scoped_path(path)?, content, acl)
+            .await
+    }
+
+    async fn set_acl(&self, path: &ObjectPath, acl: &Acl) -> DriverResult<()> {
+        self.inner.set_acl(&self.scoped_path(path)?, acl).await
+    }
+
+    async fn delete(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.inner.delete(&self.scoped_path(path)?).await
+    }
+
+    async fn delete_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.inner.delete_directory(&self.scoped_path(path)?).await
+    }
+
+    async fn last_modified(&self, path: &ObjectPath) -> DriverResult<SystemTime> {
+        self.inner.last_modified(&self.scoped_path(path)?).await
+    }
+
+    async fn size(&self, path: &ObjectPath) -> DriverResult<u64> {
+        self.inner.size(&self.scoped_path(path)?).await
+    }
+
+    async fn create_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.inner.create_directory(&self.scoped_path(path)?).await
+    }
+
+    async fn directory_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.directory_exists(&self.scoped_path(path)?).await
+    }
+
+    async fn set_last_modified(
+        &self,
+        path: &ObjectPath,
+        last_modified: SystemTime,
+    ) -> DriverResult<()> {
+        self.inner
+            .set_last_modified(&self.scoped_path(path)?, last_modified)
+            .await
+    }
+
+    async fn delete_if_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.delete_if_exists(&self.scoped_path(path)?).await
+    }
+
+    async fn stat(&self, path: &ObjectPath) -> DriverResult<Option<ObjectMetadata>> {
+        self.inner.stat(&self.scoped_path(path)?).await
+    }
+
+    async fn read_if_modified_since(
+        &self,
+        path: &ObjectPath,
+        since: SystemTime,
+    ) -> DriverResult<Option<Bytes>> {
+        self.inner
+            .read_if_modified_since(&self.scoped_path(path)?, since)
+            .await
+    }
+
+    async fn read_with_options(
+        &self,
+        path: &ObjectPath,
+        options: &ReadOptions,
+    ) -> DriverResult<Bytes> {
+        self.inner
+            .read_with_options(&self.scoped_path(path)?, options)
+            .await
+    }
+
+    async fn read_range(&self, path: &ObjectPath, range: Range<u64>) -> DriverResult<Vec<u8>> {
+        self.inner.read_range(&self.scoped_path(path)?, range).await
+    }
+
+    /// Lists the files under the given path, with the scope prefix stripped
+    /// from the returned paths.
+    async fn list(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        Ok(self
+            .inner
+            .list(&self.scoped_path(path)?)
+            .await?
+            .into_iter()
+            .filter_map(|path| path.strip_prefix(&self.prefix))
+            .collect())
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn as_presign(&self) -> Option<&dyn PresignExt> {
+        self.inner.as_presign().map(|_| self as &dyn PresignExt)
+    }
+
+    fn as_versioning(&self) -> Option<&dyn VersioningExt> {
+        self.inner
+            .as_versioning()
+            .map(|_| self as &dyn VersioningExt)
+    }
+
+    async fn public_url(&self, path: &ObjectPath) -> DriverResult<String> {
+        self.inner.public_url(&self.scoped_path(path)?).await
+    }
+
+    async fn container_exists(&self) -> DriverResult<bool> {
+        self.inner.container_exists().await
+    }
+
+    async fn ensure_container(&self, options: &ContainerOptions) -> DriverResult<()> {
+        self.inner.ensure_container(options).await
+    }
+
+    async fn health_check(&self) -> DriverResult<()> {
+        self.inner.health_check().await
+    }
+
+    async fn apply_retention_policies(&self, policies: &[RetentionPolicy]) -> DriverResult<()> {
+        let policies = policies
+            .iter()
+            .map(|policy| {
+                Ok(RetentionPolicy {
+                    prefix: self.scoped_path(&policy.prefix)?,
+                    ..policy.clone()
+                })
+            })
+            .collect::<DriverResult<Vec<_>>>()?;
+        self.inner.apply_retention_policies(&policies).await
+    }
+
+    async fn set_legal_hold(&self, path: &ObjectPath, hold: bool) -> DriverResult<()> {
+        self.inner
+            .set_legal_hold(&self.scoped_path(path)?, hold)
+            .await
+    }
+
+    async fn legal_hold(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.inner.legal_hold(&self.scoped_path(path)?).await
+    }
+
+    async fn list_legal_holds(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        Ok(self
+            .inner
+            .list_legal_holds(&self.scoped_path(path)?)
+            .await?
+            .into_iter()
+            .filter_map(|path| path.strip_prefix(&self.prefix))
+            .collect())
+    }
+
+    async fn create_upload(&self, path: &ObjectPath) -> DriverResult<String> {
+        self.inner.create_upload(&self.scoped_path(path)?).await
+    }
+
+    async fn create_upload_with_options(
+        &self,
+        path: &ObjectPath,
+        options: &WriteOptions,
+    ) -> DriverResult<String> {
+        self.inner
+            .create_upload_with_options(&self.scoped_path(path)?, options)
+            .await
+    }
+
+    async fn upload_part(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        part_number: u32,
+        content: Bytes,
+    ) -> DriverResult<UploadPart> {
+        self.inner
+            .upload_part(&self.scoped_path(path)?, upload_id, part_number, content)
+            .await
+    }
+
+    async fn list_upload_parts(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+    ) -> DriverResult<Vec<UploadPart>> {
+        self.inner
+            .list_upload_parts(&self.scoped_path(path)?, upload_id)
+            .await
+    }
+
+    async fn complete_upload(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+    ) -> DriverResult<()> {
+        self.inner
+            .complete_upload(&self.scoped_path(path)?, upload_id, parts)
+            .await
+    }
+
+    async fn complete_upload_with_options(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        self.inner
+            .complete_upload_with_options(&self.scoped_path(path)?, upload_id, parts, options)
+            .await
+    }
+
+    /// Lists the uploads under the given path, with the scope prefix
+    /// stripped from the returned paths.
+    async fn list_uploads(&self, prefix: &ObjectPath) -> DriverResult<Vec<PendingUpload>> {
+        Ok(self
+            .inner
+            .list_uploads(&self.scoped_path(prefix)?)
+            .await?
+            .into_iter()
+            .filter_map(|upload| {
+                let path = upload.path.strip_prefix(&self.prefix)?;
+                Some(PendingUpload { path, ..upload })
+            })
+            .collect())
+    }
+
+    async fn abort_upload(&self, path: &ObjectPath, upload_id: &str) -> DriverResult<()> {
+        self.inner
+            .abort_upload(&self.scoped_path(path)?, upload_id)
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl PresignExt for ScopedDriver {
+    async fn presign_read(&self, path: &ObjectPath, expires_in: Duration) -> DriverResult<String> {
+        self.inner
+            .as_presign()
+            .ok_or(DriverError::Unsupported("presigned URLs"))?
+            .presign_read(&self.scoped_path(path)?, expires_in)
+            .await
+    }
+
+    async fn presign_write(&self, path: &ObjectPath, expires_in: Duration) -> DriverResult<String> {
+        self.inner
+            .as_presign()
+            .ok_or(DriverError::Unsupported("presigned URLs"))?
+            .presign_write(&self.scoped_path(path)?, expires_in)
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl VersioningExt for ScopedDriver {
+    async fn list_versions(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectVersion>> {
+        self.inner
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .list_versions(&self.scoped_path(path)?)
+            .await
+    }
+
+    async fn read_version(&self, path: &ObjectPath, version_id: &str) -> DriverResult<Vec<u8>> {
+        self.inner
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .read_version(&self.scoped_path(path)?, version_id)
+            .await
+    }
+
+    async fn delete_version(&self, path: &ObjectPath, version_id: &str) -> DriverResult<()> {
+        self.inner
+            .as_versioning()
+            .ok_or(DriverError::Unsupported("versioning"))?
+            .delete_version(&self.scoped_path(path)?, version_id)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::{Path, PathBuf};
+
+    use super::*;
+    use crate::StoreConfig;
+
+    #[tokio::test]
+    async fn can_scope_store() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let tenant = store.scoped(Path::new("tenant-1")).unwrap();
+
+        assert!(tenant
+            .write(Path::new("/foo/file.txt"), b"content")
+            .await
+            .is_ok());
+
+        assert!(store
+            .file_exists(&PathBuf::from("tenant-1").join("foo").join("file.txt"))
+            .await
+            .unwrap());
+
+        assert_eq!(
+            tenant.list(Path::new("")).await.unwrap(),
+            vec![ObjectPath::new("foo/file.txt")]
+        );
+
+        assert!(tenant
+            .read::<String>(&PathBuf::from("..").join("tenant-2").join("file.txt"))
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,332 @@
+//! # Erasure Module
+//!
+//! The `erasure` module defines [`ErasureSubject`] and [`ErasureReport`], used
+//! by [`MultiStore::erase_subject`] to delete every file of a data subject,
+//! e.g. for a GDPR erasure request, across the primary store and all the
+//! added stores.
+//!
+//! On stores keeping previous versions of files (see
+//! [`crate::drivers::capabilities::VersioningExt`]), every version is deleted
+//! too, so nothing of the subject can be restored. Files under a legal hold
+//! are kept and reported as failures.
+//!
+//! The report lists what was erased from each store and is signed with an
+//! HMAC-SHA256 of its content, so it can be kept as evidence and later checked
+//! with [`ErasureReport::verify`].
+//!
+//! ## Example
+//!
+//! ```rust
+//! use active_storage::{erasure::ErasureSubject, multi_store::MultiStore};
+//!
+//! async fn forget(multi_store: &MultiStore) {
+//!     let subject = ErasureSubject::new("user-42")
+//!         .with_prefix("users/42")
+//!         .with_key("invoices/2024/user-42.pdf");
+//!
+//!     let report = multi_store.erase_subject(&subject, "my secret").await;
+//!     assert!(report.is_complete());
+//!     assert!(report.verify("my secret"));
+//! }
+//! ```
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use hmac::Mac;
+
+use crate::{
+    clock,
+    errors::{DriverError, DriverResult},
+    hex,
+    key::ObjectPath,
+    mac,
+    multi_store::MultiStore,
+    store::Store,
+};
+
+/// Struct representing the files of a data subject to erase.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ErasureSubject {
+    /// The identifier of the subject, recorded in the report.
+    pub id: String,
+    /// The folders holding only files of the subject.
+    pub prefixes: Vec<ObjectPath>,
+    /// The files of the subject.
+    pub keys: Vec<ObjectPath>,
+}
+
+impl ErasureSubject {
+    /// Creates a subject with the given identifier and no files.
+    #[must_use]
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Adds a folder whose files are all erased.
+    #[must_use]
+    pub fn with_prefix(mut self, prefix: impl Into<ObjectPath>) -> Self {
+        self.prefixes.push(prefix.into());
+        self
+    }
+
+    /// Adds a file to erase.
+    #[must_use]
+    pub fn with_key(mut self, key: impl Into<ObjectPath>) -> Self {
+        self.keys.push(key.into());
+        self
+    }
+}
+
+/// Struct representing what was erased from a single store.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StoreErasure {
+    /// The erased files.
+    pub deleted: Vec<ObjectPath>,
+    /// The number of erased previous versions of the files.
+    pub versions: usize,
+    /// The files that could not be erased, with the failure message.
+    pub failures: BTreeMap<ObjectPath, String>,
+}
+
+/// Struct representing the signed outcome of
+/// [`MultiStore::erase_subject`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ErasureReport {
+    /// The identifier of the erased subject.
+    pub subject: String,
+    /// The time the erasure completed.
+    pub erased_at: SystemTime,
+    /// The outcome of each store, keyed by store name. The primary store is
+    /// reported under the `primary` name.
+    pub stores: BTreeMap<String, StoreErasure>,
+    /// The hex encoded HMAC-SHA256 of [`Self::payload`].
+    pub signature: String,
+}
+
+impl ErasureReport {
+    /// Returns true when every file of the subject was erased from every
+    /// store.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.stores.values().all(|store| store.failures.is_empty())
+    }
+
+    /// Returns the canonical text of the report covered by the signature, one
+    /// `name=value` line per entry.
+    #[must_use]
+    pub fn payload(&self) -> String {
+        let mut payload = String::new();
+        let _ = writeln!(payload, "subject={}", self.subject);
+        let _ = writeln!(payload, "erased_at={}", seconds(self.erased_at));
+        for (name, store) in &self.stores {
+            let _ = writeln!(payload, "store={name}");
+            for file in &store.deleted {
+                let _ = writeln!(payload, "deleted={file}");
+            }
+            let _ = writeln!(payload, "versions={}", store.versions);
+            for (file, failure) in &store.failures {
+                let _ = writeln!(payload, "failed={file}\t{failure}");
+            }
+        }
+        payload
+    }
+
+    /// Returns true when the signature matches the content of the report for
+    /// the given secret.
+    #[must_use]
+    pub fn verify(&self, secret: impl AsRef<[u8]>) -> bool {
+        hex::decode(&self.signature).is_some_and(|signature| {
+            mac::hmac_sha256(secret.as_ref(), &[self.payload().as_bytes()])
+                .verify_slice(&signature)
+                .is_ok()
+        })
+    }
+
+    fn sign(&mut self, secret: &[u8]) {
+        self.signature = hex::encode(
+            &mac::hmac_sha256(secret, &[self.payload().as_bytes()])
+                .finalize()
+                .into_bytes(),
+        );
+    }
+}
+
+impl MultiStore {
+    /// Erases every file of the given subject from the primary store and all
+    /// the added stores, including the previous versions kept by versioned
+    /// stores, and returns a report signed with the given secret.
+    ///
+    /// Failures don't stop the erasure: they are recorded in the report, so
+    /// check [`ErasureReport::is_complete`] and retry once the cause, e.g. a
+    /// legal hold, is lifted.
+    pub async fn erase_subject(
+        &self,
+        subject: &ErasureSubject,
+        secret: impl AsRef<[u8]>,
+    ) -> ErasureReport {
+        let mut stores = BTreeMap::from([(
+            "primary".to_string(),
+            erase_from_store(&self.primary, subject).await,
+        )]);
+        for (name, store) in self.stores() {
+            stores.insert(name.clone(), erase_from_store(store, subject).await);
+        }
+
+        let mut report = ErasureReport {
+            subject: subject.id.clone(),
+            erased_at: clock::now(),
+            stores,
+            signature: String::new(),
+        };
+        report.sign(secret.as_ref());
+        report
+    }
+}
+
+/// Erases the files of the subject from a single store.
+async fn erase_from_store(store: &Store, subject: &ErasureSubject) -> StoreErasure {
+    let mut erasure = StoreErasure::default();
+
+    let mut files = subject.keys.iter().cloned().collect::<BTreeSet<_>>();
+    for prefix in &subject.prefixes {
+        match store.list(prefix).await {
+            Ok(listed) => files.extend(listed),
+            Err(error) => {
+                erasure.failures.insert(prefix.clone(), error.to_string());
+            }
+        }
+    }
+
+    for file in files {
+        match erase_file(store, &file).await {
+            Ok(None) => {}
+            Ok(Some(versions)) => {
+                erasure.deleted.push(file);
+                erasure.versions += versions;
+            }
+            Err(error) => {
+                erasure.failures.insert(file, error.to_string());
+            }
+        }
+    }
+
+    erasure
+}
+
+/// Erases a file, returning the number of erased previous versions, or `None`
+/// when the store doesn't hold the file.
+async fn erase_file(store: &Store, path: &ObjectPath) -> DriverResult<Option<usize>> {
+    let Some(versioning) = store.as_versioning() else {
+        return Ok(store.delete_if_exists(path).await?.then_some(0));
+    };
+
+    if store.legal_hold(path).await? {
+        return Err(DriverError::LegalHold);
+    }
+    let versions = versioning.list_versions(path).await?;
+    if versions.is_empty() {
+        return Ok(None);
+    }
+    for version in &versions {
+        versioning.delete_version(path, &version.version_id).await?;
+    }
+    Ok(Some(versions.len() - 1))
+}
+
+/// Returns the seconds since the Unix epoch of the given time.
+fn seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{collections::HashMap, path::Path};
+
+    use super::*;
+    use crate::StoreConfig;
+
+    #[tokio::test]
+    async fn can_erase_subject() {
+        let primary = StoreConfig::InMem().build().await.unwrap();
+        let replica = StoreConfig::InMem().build().await.unwrap();
+        let mut multi_store = MultiStore::new(primary);
+        multi_store.add_stores(HashMap::from([("replica", replica)]));
+
+        let mirror = multi_store.mirror_stores_from_primary();
+        for file in [
+            "users/42/avatar.png",
+            "users/42/docs/id.pdf",
+            "users/7/avatar.png",
+            "invoices/42.pdf",
+        ] {
+            mirror.write(Path::new(file), b"content").await.unwrap();
+        }
+
+        let subject = ErasureSubject::new("user-42")
+            .with_prefix(Path::new("users/42"))
+            .with_key(Path::new("invoices/42.pdf"))
+            .with_key(Path::new("invoices/missing.pdf"));
+        let report = multi_store.erase_subject(&subject, "secret").await;
+
+        assert!(report.is_complete());
+        assert_eq!(report.subject, "user-42");
+        for name in ["primary", "replica"] {
+            assert_eq!(
+                report.stores[name].deleted,
+                vec![
+                    ObjectPath::new("invoices/42.pdf"),
+                    ObjectPath::new("users/42/avatar.png"),
+                    ObjectPath::new("users/42/docs/id.pdf"),
+                ]
+            );
+        }
+        assert_eq!(
+            multi_store.primary.list(Path::new("")).await.unwrap(),
+            vec![ObjectPath::new("users/7/avatar.png")]
+        );
+
+        assert!(report.verify("secret"));
+        assert!(!report.verify("other secret"));
+        let mut tampered = report.clone();
+        tampered.stores.get_mut("replica").unwrap().deleted.pop();
+        assert!(!tampered.verify("secret"));
+    }
+
+    #[tokio::test]
+    async fn can_report_held_files() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        store
+            .write(Path::new("users/42/a.txt"), b"a")
+            .await
+            .unwrap();
+        store
+            .write(Path::new("users/42/b.txt"), b"b")
+            .await
+            .unwrap();
+        store
+            .set_legal_hold(Path::new("users/42/b.txt"), true)
+            .await
+            .unwrap();
+        let multi_store = MultiStore::new(store);
+
+        let subject = ErasureSubject::new("user-42").with_prefix(Path::new("users/42"));
+        let report = multi_store.erase_subject(&subject, "secret").await;
+
+        assert!(!report.is_complete());
+        let primary = &report.stores["primary"];
+        assert_eq!(primary.deleted, vec![ObjectPath::new("users/42/a.txt")]);
+        assert_eq!(
+            primary.failures.keys().collect::<Vec<_>>(),
+            vec![&ObjectPath::new("users/42/b.txt")]
+        );
+        assert!(report.verify("secret"));
+    }
+}
@@ -2,13 +2,19 @@
 //!
 //! The `storage_errors` module defines custom error types for storage-related
 //! operations.
-use std::collections::BTreeMap;
+use std::{
+    collections::BTreeMap,
+    time::{Duration, SystemTime},
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum DriverError {
     #[error("Authentication failed")]
     AuthenticationFailed,
 
+    #[error("Permission denied")]
+    PermissionDenied,
+
     #[error("Resource not found")]
     ResourceNotFound,
 
@@ -18,9 +24,89 @@ pub enum DriverError {
     #[error("Failed to decode file contents")]
     DecodeError,
 
+    #[error("{0} is not supported by the driver")]
+    Unsupported(&'static str),
+
+    #[error("Upload part number {0} is out of the 1..=10000 range")]
+    InvalidPartNumber(u32),
+
+    #[error("The write precondition was not satisfied")]
+    PreconditionFailed,
+
+    #[error("The access token is invalid")]
+    InvalidToken,
+
+    #[error("The access token has expired")]
+    TokenExpired,
+
+    #[error("The operation was cancelled")]
+    Cancelled,
+
+    #[error("The content is infected with {0}")]
+    Infected(String),
+
+    #[error("The downloaded content does not match the stored file")]
+    IntegrityCheckFailed,
+
+    /// The checksum of the content reported by the backend differs from the
+    /// one computed before sending it, the content was corrupted on the wire.
+    #[error("The stored content does not match the checksum of the sent content")]
+    ChecksumMismatch,
+
+    #[error("The page blob content of {0} bytes is not a multiple of 512 bytes")]
+    UnalignedPageBlob(u64),
+
+    #[error("Refusing to delete the storage root, use `purge_all` instead")]
+    RefusingToDeleteRoot,
+
+    #[error("The file is under legal hold")]
+    LegalHold,
+
+    /// A path under the prefixes the crate keeps its own records in, e.g.
+    /// the legal hold markers, which the store doesn't expose.
+    #[error("The path is reserved for the records of the store")]
+    ReservedPath,
+
+    /// A delete refused by a retention policy, holding when the file can be
+    /// deleted.
+    #[error("The file is retained until {}", httpdate::fmt_http_date(*.0))]
+    RetentionNotExpired(SystemTime),
+
+    #[error("The custom request header `{0}` is not a valid HTTP header")]
+    InvalidHeader(String),
+
+    /// A batch delete that removed some objects but failed on others, keyed
+    /// by the object key with the error reported for it.
+    #[error("Failed to delete {} objects", .0.len())]
+    DeleteFailed(BTreeMap<String, String>),
+
+    #[error(transparent)]
+    Validation(#[from] crate::validators::ValidationError),
+
     #[error("network error")]
     Network(),
 
+    /// A failure reported by the storage backend, preserving the provider
+    /// diagnostics for logging and precise handling.
+    #[error(
+        "{provider} backend error (status: {status:?}, code: {code:?}, request id: {request_id:?})"
+    )]
+    Backend {
+        /// The name of the provider that returned the error, e.g. `aws_s3`.
+        provider: &'static str,
+        /// The provider specific error code, e.g. `SlowDown`.
+        code: Option<String>,
+        /// The HTTP status code of the failed response.
+        status: Option<u16>,
+        /// The provider request id, needed when contacting provider support.
+        request_id: Option<String>,
+        /// The delay the provider asked to wait before retrying, from the
+        /// `Retry-After` header of a throttled (429) or unavailable (503)
+        /// response.
+        retry_after: Option<Duration>,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
     #[error(transparent)]
     Any(#[from] Box<dyn std::error::Error + Send + Sync>),
 }
@@ -34,5 +120,170 @@ pub enum MirrorError {
     MirrorFailedOnStore(String, DriverError),
 }
 
+impl From<std::io::ErrorKind> for DriverError {
+    fn from(kind: std::io::ErrorKind) -> Self {
+        match kind {
+            std::io::ErrorKind::NotFound => Self::ResourceNotFound,
+            std::io::ErrorKind::PermissionDenied => Self::PermissionDenied,
+            _ => Self::Any(Box::new(std::io::Error::from(kind))),
+        }
+    }
+}
+
+impl DriverError {
+    /// Returns `true` when the failure is transient and the operation may
+    /// succeed if retried, such as network failures, throttling and backend
+    /// server errors.
+    ///
+    /// A content corrupted on the wire ([`Self::ChecksumMismatch`]) is sent
+    /// again on retry.
+    ///
+    /// Authentication, authorization, missing resources and invalid input
+    /// failures are terminal and are never retryable.
+    #[must_use]
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Network() | Self::ChecksumMismatch => true,
+            Self::Backend { status, code, .. } => {
+                matches!(status, Some(408 | 429 | 500 | 502 | 503 | 504))
+                    || matches!(
+                        code.as_deref(),
+                        Some(
+                            "SlowDown"
+                                | "RequestTimeout"
+                                | "InternalError"
+                                | "ServiceUnavailable"
+                                | "ServerBusy"
+                                | "OperationTimedOut"
+                        )
+                    )
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the delay the backend asked to wait before retrying, when it
+    /// sent one with a throttled or unavailable response.
+    #[must_use]
+    pub const fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::Backend { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Returns the class of the failure, grouping the variants for
+    /// statistics and dashboards: `authentication`, `permission`,
+    /// `not_found`, `invalid_input`, `precondition`, `unsupported`,
+    /// `network`, `backend` or `other`.
+    #[must_use]
+    pub const fn class(&self) -> &'static str {
+        match self {
+            Self::AuthenticationFailed | Self::InvalidToken | Self::TokenExpired => {
+                "authentication"
+            }
+            Self::PermissionDenied
+            | Self::RefusingToDeleteRoot
+            | Self::LegalHold
+            | Self::ReservedPath => "permission",
+            Self::ResourceNotFound => "not_found",
+            Self::InvalidPath
+            | Self::InvalidPartNumber(_)
+            | Self::InvalidHeader(_)
+            | Self::UnalignedPageBlob(_)
+            | Self::Validation(_)
+            | Self::Infected(_) => "invalid_input",
+            Self::PreconditionFailed | Self::RetentionNotExpired(_) => "precondition",
+            Self::Unsupported(_) => "unsupported",
+            Self::Network() | Self::ChecksumMismatch => "network",
+            Self::Backend { .. } => "backend",
+            Self::DecodeError
+            | Self::Cancelled
+            | Self::IntegrityCheckFailed
+            | Self::DeleteFailed(_)
+            | Self::Any(_) => "other",
+        }
+    }
+
+    /// Returns the provider request id of a failed backend request, needed
+    /// when contacting provider support.
+    #[must_use]
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            Self::Backend { request_id, .. } => request_id.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// Parses the `Retry-After` header of a throttled (429) or unavailable (503)
+/// response, given either as a number of seconds or as an HTTP date. The
+/// header of any other status is ignored.
+#[cfg(any(feature = "aws_s3", feature = "http"))]
+pub(crate) fn retry_after(status: u16, value: Option<&str>) -> Option<Duration> {
+    if !matches!(status, 429 | 503) {
+        return None;
+    }
+
+    let value = value?.trim();
+    value.parse().map(Duration::from_secs).ok().or_else(|| {
+        httpdate::parse_http_date(value)
+            .ok()
+            .map(|date| date.duration_since(crate::clock::now()).unwrap_or_default())
+    })
+}
+
 pub type DriverResult<T> = std::result::Result<T, DriverError>;
 pub type MirrorResult<T> = std::result::Result<T, MirrorError>;
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn can_classify_retryable_errors() {
+        assert!(DriverError::Network().is_retryable());
+        assert!(DriverError::ChecksumMismatch.is_retryable());
+        assert!(!DriverError::AuthenticationFailed.is_retryable());
+        assert!(!DriverError::PermissionDenied.is_retryable());
+        assert!(!DriverError::ResourceNotFound.is_retryable());
+        assert!(!DriverError::Cancelled.is_retryable());
+        assert!(!DriverError::Any("error".into()).is_retryable());
+        assert!(backend_error(Some(503), None).is_retryable());
+        assert!(backend_error(Some(400), Some("SlowDown")).is_retryable());
+        assert!(!backend_error(Some(400), Some("InvalidRequest")).is_retryable());
+    }
+
+    #[cfg(any(feature = "aws_s3", feature = "http"))]
+    #[test]
+    fn can_parse_retry_after() {
+        assert_eq!(
+            retry_after(429, Some(" 120 ")),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            retry_after(503, Some("Wed, 21 Oct 2015 07:28:00 GMT")),
+            Some(Duration::ZERO)
+        );
+        let date = httpdate::fmt_http_date(crate::clock::now() + Duration::from_secs(3600));
+        assert!(retry_after(503, Some(&date)).unwrap() > Duration::from_secs(3500));
+        assert_eq!(retry_after(500, Some("120")), None);
+        assert_eq!(retry_after(429, Some("soon")), None);
+        assert_eq!(retry_after(429, None), None);
+
+        assert_eq!(backend_error(Some(429), None).retry_after(), None);
+        assert_eq!(DriverError::Network().retry_after(), None);
+    }
+
+    fn backend_error(status: Option<u16>, code: Option<&str>) -> DriverError {
+        DriverError::Backend {
+            provider: "test",
+            code: code.map(ToString::to_string),
+            status,
+            request_id: None,
+            retry_after: None,
+            source: "error".into(),
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! # Filename Module
+//!
+//! The `filename` module turns user provided file names into safe storage
+//! keys with [`sanitize`], and defines the [`Collision`] policies applied by
+//! [`Store::write_named`] when the key is already taken.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::Path;
+//! use active_storage::{filename::Collision, key::ObjectPath, StoreConfig};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!     let dir = Path::new("uploads");
+//!
+//!     let first = store
+//!         .write_named(dir, "Résumé.pdf", "v1", Collision::Suffix)
+//!         .await
+//!         .unwrap();
+//!     let second = store
+//!         .write_named(dir, "Résumé.pdf", "v2", Collision::Suffix)
+//!         .await
+//!         .unwrap();
+//!     assert_eq!(first, ObjectPath::new("uploads/Resume.pdf"));
+//!     assert_eq!(second, ObjectPath::new("uploads/Resume (1).pdf"));
+//! }
+//! ```
+//!
+//! [`Store::write_named`]: crate::store::Store::write_named
+use unicode_normalization::UnicodeNormalization;
+
+/// The file name used when nothing is left after sanitizing.
+const FALLBACK_NAME: &str = "file";
+
+/// Enum representing what happens when a file already exists at the key
+/// derived from a file name.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Collision {
+    /// Fails with [`crate::errors::DriverError::PreconditionFailed`].
+    #[default]
+    Error,
+    /// Replaces the existing file.
+    Overwrite,
+    /// Appends the first free ` (n)` suffix before the extension, e.g.
+    /// `file (1).png`.
+    Suffix,
+}
+
+/// Options of [`sanitize`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SanitizeOptions {
+    /// The maximum length of the file name in bytes, the extension is kept
+    /// when truncating.
+    pub max_length: usize,
+    /// Whether non ASCII characters are transliterated, e.g. `é` to `e`.
+    /// Characters without an ASCII equivalent are replaced with `_`.
+    pub transliterate: bool,
+}
+
+impl Default for SanitizeOptions {
+    fn default() -> Self {
+        Self {
+            max_length: 255,
+            transliterate: true,
+        }
+    }
+}
+
+/// Returns a safe file name derived from a user provided one.
+///
+/// Directories are stripped, control characters removed, characters reserved
+/// by file systems replaced with `_`, whitespace collapsed and leading dots
+/// removed so the result is never hidden, `.` or `..`.
+///
+/// # Examples
+///
+/// ```rust
+/// use active_storage::filename::{sanitize, SanitizeOptions};
+///
+/// let options = SanitizeOptions::default();
+/// assert_eq!(sanitize("../../etc/pass\u{0}wd", &options), "passwd");
+/// assert_eq!(sanitize("Straße  <1>.txt", &options), "Strasse _1_.txt");
+/// ```
+#[must_use]
+pub fn sanitize(name: &str, options: &SanitizeOptions) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+
+    let mut sanitized = String::with_capacity(name.len());
+    for c in name.nfkd().filter(|c| !c.is_control() || c.is_whitespace()) {
+        if is_combining_mark(c) && options.transliterate {
+            continue;
+        }
+        match c {
+            '<' | '>' | ':' | '"' | '|' | '?' | '*' => sanitized.push('_'),
+            c if c.is_whitespace() => {
+                if !sanitized.ends_with(' ') {
+                    sanitized.push(' ');
+                }
+            }
+            c if c.is_ascii() || !options.transliterate => sanitized.push(c),
+            c => sanitized.push_str(transliterate(c)),
+        }
+    }
+    let sanitized: String = sanitized.nfc().collect();
+    let sanitized = sanitized.trim_start_matches(['.', ' ']).trim_end();
+
+    if sanitized.is_empty() {
+        FALLBACK_NAME.to_string()
+    } else {
+        truncate(sanitized, options.max_length)
+    }
+}
+
+/// Returns the file name with the ` (n)` collision suffix.
+#[must_use]
+pub(crate) fn with_suffix(name: &str, n: usize) -> String {
+    let (stem, extension) = split_extension(name);
+    format!("{stem} ({n}){extension}")
+}
+
+/// Splits the file name into its stem and its extension, including the dot.
+fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(index) if index > 0 => name.split_at(index),
+        _ => (name, ""),
+    }
+}
+
+/// Truncates the file name to the given length in bytes, keeping the
+/// extension when it fits.
+fn truncate(name: &str, max_length: usize) -> String {
+    if name.len() <= max_length {
+        return name.to_string();
+    }
+
+    let (stem, extension) = split_extension(name);
+    let (stem, extension) = if extension.len() < max_length {
+        (stem, extension)
+    } else {
+        (name, "")
+    };
+    let mut end = max_length - extension.len();
+    while !stem.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{extension}", stem[..end].trim_end())
+}
+
+/// Returns `true` for the combining diacritical marks left by the NFKD
+/// decomposition.
+const fn is_combining_mark(c: char) -> bool {
+    matches!(c, '\u{0300}'..='\u{036F}' | '\u{1AB0}'..='\u{1AFF}' | '\u{1DC0}'..='\u{1DFF}')
+}
+
+/// Returns the ASCII transliteration of letters that don't decompose.
+const fn transliterate(c: char) -> &'static str {
+    match c {
+        'ß' => "ss",
+        'æ' => "ae",
+        'Æ' => "AE",
+        'œ' => "oe",
+        'Œ' => "OE",
+        'ø' => "o",
+        'Ø' => "O",
+        'ł' => "l",
+        'Ł' => "L",
+        'đ' | 'ð' => "d",
+        'Đ' | 'Ð' => "D",
+        'þ' => "th",
+        'Þ' => "TH",
+        _ => "_",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn can_sanitize_filenames() {
+        let options = SanitizeOptions::default();
+        assert_eq!(sanitize("Résumé Æsop.PDF", &options), "Resume AEsop.PDF");
+        assert_eq!(sanitize("C:\\Users\\me\\photo.png", &options), "photo.png");
+        assert_eq!(sanitize(" ..hidden\t\tfile ", &options), "hidden file");
+        assert_eq!(sanitize("a\u{7}b?.txt", &options), "ab_.txt");
+        assert_eq!(sanitize("日本.txt", &options), "__.txt");
+        assert_eq!(sanitize("..", &options), "file");
+        assert_eq!(sanitize("", &options), "file");
+
+        let options = SanitizeOptions {
+            max_length: 8,
+            transliterate: false,
+        };
+        assert_eq!(sanitize("日本語のファイル.txt", &options), "日.txt");
+        assert_eq!(sanitize("abcdefghij", &options), "abcdefgh");
+        assert_eq!(sanitize("résumé", &options), "résumé");
+
+        assert_eq!(with_suffix("file.png", 1), "file (1).png");
+        assert_eq!(with_suffix("archive.tar.gz", 2), "archive.tar (2).gz");
+        assert_eq!(with_suffix("README", 3), "README (3)");
+    }
+}
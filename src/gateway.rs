@@ -0,0 +1,1043 @@
+//! # Gateway Module
+//!
+//! The `gateway` module defines [`S3Gateway`], a facade implementing a
+//! minimal subset of the S3 API on top of a [`Store`], so tools that only
+//! speak S3 can read and write disk, Azure or in-memory stores, e.g. for local
+//! development parity with production buckets.
+//!
+//! The gateway serves a single bucket with path-style addressing
+//! (`/{bucket}/{key}`), and answers `GetObject`, `HeadObject`, `PutObject`,
+//! `DeleteObject`, `HeadBucket` and `ListObjectsV2`. Requests must be signed
+//! with AWS Signature Version 4 in the `Authorization` header, which is
+//! checked before the body is read. Bodies are limited to
+//! [`S3Gateway::with_max_body_size`] bytes, and objects are streamed.
+//! Presigned URLs, multipart uploads and `aws-chunked` streaming payloads are
+//! not supported.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use active_storage::{
+//!     gateway::{Credentials, S3Gateway},
+//!     StoreConfig,
+//! };
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!     let gateway = S3Gateway::new(
+//!         store,
+//!         "test-bucket",
+//!         Credentials::new("access-key", "secret-key"),
+//!     );
+//!
+//!     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+//!     let cancellation = active_storage::transfer::CancellationToken::new();
+//!     cancellation.cancel();
+//!     gateway.serve(listener, cancellation).await.unwrap();
+//! }
+//! ```
+use std::{
+    collections::BTreeSet,
+    fmt::Write,
+    time::{Duration, SystemTime},
+};
+
+use bytes::Bytes;
+use hmac::Mac;
+use http_body_util::{BodyExt, LengthLimitError, Limited};
+use hyper::{
+    body::Body,
+    header::{self, HeaderValue},
+    http::request::Parts,
+    HeaderMap, Method, Request, Response, StatusCode,
+};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use sha2::{Digest, Sha256};
+use tokio::net::TcpListener;
+
+use crate::{
+    clock,
+    errors::DriverError,
+    hex,
+    key::{Key, ObjectPath},
+    mac,
+    rest::{
+        accept_loop, constant_time_eq, full_body, status_response, stream_body, ResponseBody,
+        DEFAULT_MAX_BODY_SIZE,
+    },
+    serve::{metadata_etag, ServeRequest},
+    store::Store,
+    transfer::CancellationToken,
+};
+
+/// The signing algorithm of AWS Signature Version 4.
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+
+/// The payload hash of requests whose body is not signed.
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// How far the request date may be from the gateway clock.
+const MAX_CLOCK_SKEW: Duration = Duration::from_secs(15 * 60);
+
+/// The number of keys listed per page when the request sets no `max-keys`.
+const DEFAULT_MAX_KEYS: usize = 1000;
+
+/// Characters left unencoded in canonical query strings.
+const UNRESERVED: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// Struct representing the access key pair requests must be signed with.
+#[derive(Clone)]
+pub struct Credentials {
+    /// The access key id, sent in clear in the request credential scope.
+    pub access_key_id: String,
+    /// The secret access key, which never leaves the client.
+    pub secret_access_key: String,
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("access_key_id", &self.access_key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Credentials {
+    /// Creates new [`Credentials`] from an access key id and its secret.
+    #[must_use]
+    pub fn new<A: Into<String>, S: Into<String>>(access_key_id: A, secret_access_key: S) -> Self {
+        Self {
+            access_key_id: access_key_id.into(),
+            secret_access_key: secret_access_key.into(),
+        }
+    }
+}
+
+/// Struct representing an S3 API facade serving a store as a single bucket.
+#[derive(Clone)]
+pub struct S3Gateway {
+    store: Store,
+    bucket: String,
+    credentials: Credentials,
+    max_body_size: usize,
+}
+
+/// Struct representing an S3 error response.
+#[derive(Debug)]
+struct S3Error {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+}
+
+impl S3Error {
+    fn new<M: Into<String>>(status: StatusCode, code: &'static str, message: M) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn access_denied<M: Into<String>>(message: M) -> Self {
+        Self::new(StatusCode::FORBIDDEN, "AccessDenied", message)
+    }
+}
+
+impl From<DriverError> for S3Error {
+    fn from(err: DriverError) -> Self {
+        let (status, code) = match err {
+            DriverError::ResourceNotFound => (StatusCode::NOT_FOUND, "NoSuchKey"),
+            DriverError::InvalidPath => (StatusCode::BAD_REQUEST, "InvalidArgument"),
+            DriverError::PermissionDenied | DriverError::ReservedPath => {
+                (StatusCode::FORBIDDEN, "AccessDenied")
+            }
+            DriverError::PreconditionFailed => {
+                (StatusCode::PRECONDITION_FAILED, "PreconditionFailed")
+            }
+            DriverError::Unsupported(_) => (StatusCode::NOT_IMPLEMENTED, "NotImplemented"),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, "InternalError"),
+        };
+        Self::new(status, code, err.to_string())
+    }
+}
+
+impl S3Gateway {
+    /// Creates a new [`S3Gateway`] serving the given store as the given
+    /// bucket, to the requests signed with the given credentials.
+    #[must_use]
+    pub fn new<S: Into<String>>(store: Store, bucket: S, credentials: Credentials) -> Self {
+        Self {
+            store,
+            bucket: bucket.into(),
+            credentials,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+        }
+    }
+
+    /// Sets the maximum size of a request body, [`DEFAULT_MAX_BODY_SIZE`] by
+    /// default. Larger bodies are answered with an `EntityTooLarge` error.
+    #[must_use]
+    pub const fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Accepts HTTP/1 connections on the listener until the token is
+    /// cancelled, answering each connection on its own task.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a connection could not be accepted.
+    pub async fn serve(
+        self,
+        listener: TcpListener,
+        cancellation: CancellationToken,
+    ) -> std::io::Result<()> {
+        accept_loop(listener, cancellation, move |request| {
+            let gateway = self.clone();
+            async move { gateway.handle(request).await }
+        })
+        .await
+    }
+
+    /// Answers a single request, e.g. from a server built with another
+    /// framework.
+    pub async fn handle<B>(&self, request: Request<B>) -> Response<ResponseBody>
+    where
+        B: Body + Send,
+        B::Data: Send,
+        B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let (parts, body) = request.into_parts();
+        let resource = parts.uri.path().to_string();
+        // the body of an unauthenticated request is never read
+        let result = match self.verify_signature(&parts) {
+            Ok(payload_hash) => match Limited::new(body, self.max_body_size).collect().await {
+                Ok(body) => self.dispatch(&parts, payload_hash, body.to_bytes()).await,
+                Err(err) if err.is::<LengthLimitError>() => Err(S3Error::new(
+                    StatusCode::BAD_REQUEST,
+                    "EntityTooLarge",
+                    "The request body exceeds the maximum allowed size",
+                )),
+                Err(_) => Err(S3Error::new(
+                    StatusCode::BAD_REQUEST,
+                    "IncompleteBody",
+                    "The request body could not be read",
+                )),
+            },
+            Err(err) => Err(err),
+        };
+
+        result.unwrap_or_else(|err| error_response(&err, &resource))
+    }
+
+    /// Verifies the body against the signed payload hash and runs the
+    /// requested operation.
+    async fn dispatch(
+        &self,
+        parts: &Parts,
+        payload_hash: &str,
+        body: Bytes,
+    ) -> Result<Response<ResponseBody>, S3Error> {
+        if payload_hash != UNSIGNED_PAYLOAD && payload_hash != hex::encode(&Sha256::digest(&body)) {
+            return Err(S3Error::new(
+                StatusCode::BAD_REQUEST,
+                "XAmzContentSHA256Mismatch",
+                "The payload hash does not match the body",
+            ));
+        }
+
+        let path = parts.uri.path().trim_start_matches('/');
+        let (bucket, key) = path.split_once('/').unwrap_or((path, ""));
+        if bucket != self.bucket {
+            return Err(S3Error::new(
+                StatusCode::NOT_FOUND,
+                "NoSuchBucket",
+                "The specified bucket does not exist",
+            ));
+        }
+
+        if key.is_empty() {
+            return match parts.method {
+                Method::GET => {
+                    self.list_objects(parts.uri.query().unwrap_or_default())
+                        .await
+                }
+                Method::HEAD => Ok(status_response(StatusCode::OK)),
+                _ => Err(method_not_allowed()),
+            };
+        }
+
+        let key = percent_decode_str(key)
+            .decode_utf8()
+            .map_err(|_| S3Error::from(DriverError::InvalidPath))?;
+        let path = ObjectPath::from(&Key::new(&key)?);
+        match parts.method {
+            Method::GET => self.get_object(&path, &parts.headers).await,
+            Method::HEAD => self.head_object(&path).await,
+            Method::PUT if parts.headers.contains_key("x-amz-copy-source") => Err(S3Error::new(
+                StatusCode::NOT_IMPLEMENTED,
+                "NotImplemented",
+                "CopyObject is not supported by the gateway",
+            )),
+            // folder placeholders created by S3 consoles and tools
+            Method::PUT if key.ends_with('/') && body.is_empty() => {
+                self.store.create_directory(&path).await?;
+                Ok(status_response(StatusCode::OK))
+            }
+            Method::PUT => {
+                self.store.write(&path, body).await?;
+                let mut response = status_response(StatusCode::OK);
+                if let Some(metadata) = self.store.stat(&path).await? {
+                    insert_header(&mut response, header::ETAG, &metadata_etag(&metadata));
+                }
+                Ok(response)
+            }
+            Method::DELETE => {
+                self.store.delete_if_exists(&path).await?;
+                Ok(status_response(StatusCode::NO_CONTENT))
+            }
+            _ => Err(method_not_allowed()),
+        }
+    }
+
+    /// Answers `GetObject`, honoring the `Range`, `If-None-Match` and
+    /// `If-Range` headers.
+    async fn get_object(
+        &self,
+        path: &ObjectPath,
+        headers: &HeaderMap,
+    ) -> Result<Response<ResponseBody>, S3Error> {
+        let header_value = |name| headers.get(name).and_then(|value| value.to_str().ok());
+        let served = self
+            .store
+            .serve_with(
+                path,
+                &ServeRequest {
+                    range: header_value(header::RANGE),
+                    if_none_match: header_value(header::IF_NONE_MATCH),
+                    if_range: header_value(header::IF_RANGE),
+                },
+            )
+            .await?;
+
+        let mut response = Response::new(stream_body(served.body));
+        *response.status_mut() =
+            StatusCode::from_u16(served.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        for (name, value) in served.headers {
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                response.headers_mut().insert(name, value);
+            }
+        }
+        Ok(response)
+    }
+
+    /// Answers `HeadObject`.
+    async fn head_object(&self, path: &ObjectPath) -> Result<Response<ResponseBody>, S3Error> {
+        let metadata = self
+            .store
+            .stat(path)
+            .await?
+            .ok_or(DriverError::ResourceNotFound)?;
+        let content_type = metadata.content_type.clone().unwrap_or_else(|| {
+            mime_guess::from_path(path.as_str())
+                .first_or_octet_stream()
+                .to_string()
+        });
+
+        let mut response = status_response(StatusCode::OK);
+        insert_header(
+            &mut response,
+            header::CONTENT_LENGTH,
+            &metadata.size.to_string(),
+        );
+        insert_header(&mut response, header::CONTENT_TYPE, &content_type);
+        insert_header(
+            &mut response,
+            header::LAST_MODIFIED,
+            &httpdate::fmt_http_date(metadata.last_modified),
+        );
+        insert_header(&mut response, header::ETAG, &metadata_etag(&metadata));
+        Ok(response)
+    }
+
+    /// Answers `ListObjectsV2`, supporting the `prefix`, `delimiter`,
+    /// `max-keys`, `start-after` and `continuation-token` parameters.
+    async fn list_objects(&self, query: &str) -> Result<Response<ResponseBody>, S3Error> {
+        let params = query_params(query);
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+        };
+        let prefix = param("prefix").unwrap_or_default();
+        let delimiter = param("delimiter").filter(|delimiter| !delimiter.is_empty());
+        let max_keys = param("max-keys")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_MAX_KEYS);
+        let after = param("continuation-token")
+            .or_else(|| param("start-after"))
+            .unwrap_or_default();
+
+        // the files are listed from the deepest directory enclosing the prefix
+        let directory = prefix.rfind('/').map_or("", |index| &prefix[..index]);
+        let files = match self
+            .store
+            .list(Key::new(directory).map_or_else(|_| ObjectPath::default(), |key| (&key).into()))
+            .await
+        {
+            Ok(files) => files,
+            Err(DriverError::ResourceNotFound) => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+        let keys = files
+            .iter()
+            .filter_map(|file| file.to_key().ok())
+            .map(|key| key.as_str().to_string())
+            .filter(|key| key.starts_with(prefix))
+            .collect::<BTreeSet<_>>();
+
+        // keys under a delimiter are rolled up into their common prefix
+        let mut entries = BTreeSet::new();
+        for key in keys {
+            let common_prefix = delimiter.and_then(|delimiter| {
+                key[prefix.len()..]
+                    .find(delimiter)
+                    .map(|index| key[..prefix.len() + index + delimiter.len()].to_string())
+            });
+            entries.insert(common_prefix.map_or(ListEntry::Object(key), ListEntry::Prefix));
+        }
+        let mut page = entries
+            .into_iter()
+            .filter(|entry| entry.name() > after)
+            .take(max_keys.saturating_add(1))
+            .collect::<Vec<_>>();
+        let is_truncated = page.len() > max_keys;
+        page.truncate(max_keys);
+
+        let mut xml = String::from(
+            r#"<?xml version="1.0" encoding="UTF-8"?><ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">"#,
+        );
+        let _ = write!(
+            xml,
+            "<Name>{}</Name><Prefix>{}</Prefix><KeyCount>{}</KeyCount><MaxKeys>{max_keys}</\
+             MaxKeys><IsTruncated>{is_truncated}</IsTruncated>",
+            xml_escape(&self.bucket),
+            xml_escape(prefix),
+            page.len(),
+        );
+        if let Some(delimiter) = delimiter {
+            let _ = write!(xml, "<Delimiter>{}</Delimiter>", xml_escape(delimiter));
+        }
+        if is_truncated {
+            if let Some(last) = page.last() {
+                let _ = write!(
+                    xml,
+                    "<NextContinuationToken>{}</NextContinuationToken>",
+                    xml_escape(last.name())
+                );
+            }
+        }
+        for entry in &page {
+            match entry {
+                ListEntry::Object(key) => {
+                    let Some(metadata) = self.store.stat(&Key::new(key)?).await? else {
+                        continue;
+                    };
+                    let _ = write!(
+                        xml,
+                        "<Contents><Key>{}</Key><LastModified>{}</LastModified><ETag>{}</\
+                         ETag><Size>{}</Size><StorageClass>STANDARD</StorageClass></Contents>",
+                        xml_escape(key),
+                        iso8601(metadata.last_modified),
+                        xml_escape(&metadata_etag(&metadata)),
+                        metadata.size,
+                    );
+                }
+                ListEntry::Prefix(prefix) => {
+                    let _ = write!(
+                        xml,
+                        "<CommonPrefixes><Prefix>{}</Prefix></CommonPrefixes>",
+                        xml_escape(prefix)
+                    );
+                }
+            }
+        }
+        xml.push_str("</ListBucketResult>");
+
+        Ok(xml_response(StatusCode::OK, xml))
+    }
+
+    /// Verifies the AWS Signature Version 4 of the request headers, returning
+    /// the signed payload hash the body must match.
+    fn verify_signature<'a>(&self, parts: &'a Parts) -> Result<&'a str, S3Error> {
+        let authorization = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix(ALGORITHM))
+            .ok_or_else(|| S3Error::access_denied("The request is not signed with SigV4"))?;
+
+        let mut credential = None;
+        let mut signed_headers = None;
+        let mut signature = None;
+        for field in authorization.split(',') {
+            match field.trim().split_once('=') {
+                Some(("Credential", value)) => credential = Some(value),
+                Some(("SignedHeaders", value)) => signed_headers = Some(value),
+                Some(("Signature", value)) => signature = Some(value),
+                _ => {}
+            }
+        }
+        let (Some(credential), Some(signed_headers), Some(signature)) =
+            (credential, signed_headers, signature)
+        else {
+            return Err(S3Error::new(
+                StatusCode::BAD_REQUEST,
+                "AuthorizationHeaderMalformed",
+                "The authorization header is malformed",
+            ));
+        };
+
+        let (access_key_id, scope) = credential.split_once('/').unwrap_or((credential, ""));
+        if access_key_id != self.credentials.access_key_id {
+            return Err(S3Error::new(
+                StatusCode::FORBIDDEN,
+                "InvalidAccessKeyId",
+                "The access key id does not exist",
+            ));
+        }
+        let scope_parts = scope.split('/').collect::<Vec<_>>();
+        let [date, region, service, "aws4_request"] = scope_parts.as_slice() else {
+            return Err(S3Error::new(
+                StatusCode::BAD_REQUEST,
+                "AuthorizationHeaderMalformed",
+                "The credential scope is malformed",
+            ));
+        };
+
+        let header_value = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        let amz_date = header_value("x-amz-date")
+            .ok_or_else(|| S3Error::access_denied("The x-amz-date header is missing"))?;
+        let is_in_window = parse_amz_date(amz_date).is_some_and(|time| {
+            let now = clock::now();
+            now.duration_since(time)
+                .or_else(|_| time.duration_since(now))
+                .is_ok_and(|skew| skew <= MAX_CLOCK_SKEW)
+        });
+        if !is_in_window || !amz_date.starts_with(date) {
+            return Err(S3Error::new(
+                StatusCode::FORBIDDEN,
+                "RequestTimeTooSkewed",
+                "The request time is too far from the server time",
+            ));
+        }
+
+        let payload_hash = match header_value("x-amz-content-sha256") {
+            Some(value) if value.starts_with("STREAMING-") => {
+                return Err(S3Error::new(
+                    StatusCode::NOT_IMPLEMENTED,
+                    "NotImplemented",
+                    "Streaming payloads are not supported by the gateway",
+                ));
+            }
+            Some(value) => value,
+            None => {
+                return Err(S3Error::new(
+                    StatusCode::BAD_REQUEST,
+                    "InvalidRequest",
+                    "The x-amz-content-sha256 header is missing",
+                ));
+            }
+        };
+
+        let mut canonical_headers = String::new();
+        for name in signed_headers.split(';') {
+            let values = parts
+                .headers
+                .get_all(name)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .map(|value| value.split_whitespace().collect::<Vec<_>>().join(" "))
+                .collect::<Vec<_>>();
+            let value = if values.is_empty() && name == "host" {
+                parts
+                    .uri
+                    .authority()
+                    .map(ToString::to_string)
+                    .unwrap_or_default()
+            } else {
+                values.join(",")
+            };
+            let _ = writeln!(canonical_headers, "{name}:{value}");
+        }
+
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{canonical_headers}\n{signed_headers}\n{payload_hash}",
+            parts.method,
+            parts.uri.path(),
+            canonical_query(parts.uri.query().unwrap_or_default()),
+        );
+        let string_to_sign = format!(
+            "{ALGORITHM}\n{amz_date}\n{scope}\n{}",
+            hex::encode(&Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let mut key = format!("AWS4{}", self.credentials.secret_access_key).into_bytes();
+        for part in [*date, *region, *service, "aws4_request"] {
+            key = hmac(&key, part.as_bytes());
+        }
+        let expected = hex::encode(&hmac(&key, string_to_sign.as_bytes()));
+
+        if constant_time_eq(expected.as_bytes(), signature.as_bytes()) {
+            Ok(payload_hash)
+        } else {
+            Err(S3Error::new(
+                StatusCode::FORBIDDEN,
+                "SignatureDoesNotMatch",
+                "The request signature does not match the signature the gateway calculated",
+            ))
+        }
+    }
+}
+
+/// Enum representing an entry of a listing page, ordered by name.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum ListEntry {
+    Object(String),
+    Prefix(String),
+}
+
+impl ListEntry {
+    fn name(&self) -> &str {
+        match self {
+            Self::Object(name) | Self::Prefix(name) => name,
+        }
+    }
+}
+
+impl From<crate::key::KeyError> for S3Error {
+    fn from(err: crate::key::KeyError) -> Self {
+        DriverError::from(err).into()
+    }
+}
+
+/// Returns the error answered to unsupported methods.
+fn method_not_allowed() -> S3Error {
+    S3Error::new(
+        StatusCode::METHOD_NOT_ALLOWED,
+        "MethodNotAllowed",
+        "The specified method is not allowed against this resource",
+    )
+}
+
+/// Returns the XML error response of an S3 error.
+fn error_response(err: &S3Error, resource: &str) -> Response<ResponseBody> {
+    xml_response(
+        err.status,
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?><Error><Code>{}</Code><Message>{}</Message><Resource>{}</Resource></Error>"#,
+            err.code,
+            xml_escape(&err.message),
+            xml_escape(resource),
+        ),
+    )
+}
+
+/// Returns an XML response with the given status.
+fn xml_response(status: StatusCode, xml: String) -> Response<ResponseBody> {
+    let mut response = Response::new(full_body(xml));
+    *response.status_mut() = status;
+    insert_header(&mut response, header::CONTENT_TYPE, "application/xml");
+    response
+}
+
+/// Inserts a header, skipping values that are not valid header values.
+fn insert_header(response: &mut Response<ResponseBody>, name: header::HeaderName, value: &str) {
+    if let Ok(value) = HeaderValue::from_str(value) {
+        response.headers_mut().insert(name, value);
+    }
+}
+
+/// Returns the decoded parameters of a query string.
+fn query_params(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            let decode = |value: &str| percent_decode_str(value).decode_utf8_lossy().to_string();
+            (decode(key), decode(value))
+        })
+        .collect()
+}
+
+/// Returns the canonical query string of a request: the parameters sorted,
+/// with their names and values encoded the way SigV4 expects.
+fn canonical_query(query: &str) -> String {
+    let mut params = query_params(query)
+        .into_iter()
+        .map(|(key, value)| {
+            (
+                utf8_percent_encode(&key, UNRESERVED).to_string(),
+                utf8_percent_encode(&value, UNRESERVED).to_string(),
+            )
+        })
+        .collect::<Vec<_>>();
+    params.sort();
+    params
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Returns the HMAC-SHA256 of the data, chaining the SigV4 signing keys.
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    mac::hmac_sha256(key, &[data])
+        .finalize()
+        .into_bytes()
+        .to_vec()
+}
+
+/// Escapes the XML special characters.
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Parses an `x-amz-date` value, e.g. `20240101T120000Z`.
+fn parse_amz_date(value: &str) -> Option<SystemTime> {
+    if value.len() != 16
+        || !value.is_char_boundary(8)
+        || &value[8..9] != "T"
+        || !value.ends_with('Z')
+    {
+        return None;
+    }
+    let number = |range: std::ops::Range<usize>| value.get(range)?.parse::<u64>().ok();
+    let (year, month, day) = (number(0..4)?, number(4..6)?, number(6..8)?);
+    let (hour, minute, second) = (number(9..11)?, number(11..13)?, number(13..15)?);
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+
+    // days since the unix epoch from the civil date
+    let year = year - u64::from(month <= 2);
+    let era = year / 400;
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = (era * 146_097 + doe).checked_sub(719_468)?;
+
+    Some(
+        SystemTime::UNIX_EPOCH
+            + Duration::from_secs(days * 86_400 + hour * 3600 + minute * 60 + second),
+    )
+}
+
+/// Formats the time as an ISO 8601 UTC timestamp, e.g.
+/// `2024-01-01T12:00:00.000Z`.
+fn iso8601(time: SystemTime) -> String {
+    let since_epoch = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let days = secs / 86_400;
+    let secs_of_day = secs % 86_400;
+
+    // civil date from days since the unix epoch
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    use http_body_util::Full;
+
+    use super::*;
+    use crate::StoreConfig;
+
+    /// Signs the request the way S3 clients do.
+    fn signed(method: Method, uri: &str, secret: &str, body: &'static str) -> Request<Full<Bytes>> {
+        let uri = uri.parse::<hyper::Uri>().unwrap();
+        let amz_date = format!("{}Z", iso8601(clock::now())[..19].replace(['-', ':'], ""));
+        let date = &amz_date[..8];
+        let payload_hash = hex::encode(&Sha256::digest(body.as_bytes()));
+        let canonical_request = format!(
+            "{method}\n{}\n{}\nhost:localhost\nx-amz-content-sha256:{payload_hash}\nx-amz-date:\
+             {amz_date}\n\nhost;x-amz-content-sha256;x-amz-date\n{payload_hash}",
+            uri.path(),
+            canonical_query(uri.query().unwrap_or_default()),
+        );
+        let scope = format!("{date}/us-east-1/s3/aws4_request");
+        let string_to_sign = format!(
+            "{ALGORITHM}\n{amz_date}\n{scope}\n{}",
+            hex::encode(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let mut key = format!("AWS4{secret}").into_bytes();
+        for part in [date, "us-east-1", "s3", "aws4_request"] {
+            key = hmac(&key, part.as_bytes());
+        }
+        let signature = hex::encode(&hmac(&key, string_to_sign.as_bytes()));
+
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::HOST, "localhost")
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", &amz_date)
+            .header(
+                header::AUTHORIZATION,
+                format!(
+                    "{ALGORITHM} Credential=access-key/{scope}, \
+                     SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={signature}"
+                ),
+            )
+            .body(Full::new(Bytes::from(body)))
+            .unwrap()
+    }
+
+    async fn body(response: Response<ResponseBody>) -> String {
+        String::from_utf8(
+            response
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .unwrap()
+    }
+
+    async fn gateway() -> S3Gateway {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        S3Gateway::new(
+            store,
+            "bucket",
+            Credentials::new("access-key", "secret-key"),
+        )
+    }
+
+    #[test]
+    fn can_format_dates() {
+        let time = parse_amz_date("20240101T120000Z").unwrap();
+        assert_eq!(
+            time.duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            1_704_110_400
+        );
+        assert_eq!(iso8601(time), "2024-01-01T12:00:00.000Z");
+        assert_eq!(parse_amz_date("2024-01-01T12:00:00Z"), None);
+    }
+
+    #[test]
+    fn can_build_canonical_query() {
+        assert_eq!(
+            canonical_query("prefix=docs%2F&list-type=2&delimiter=%2F"),
+            "delimiter=%2F&list-type=2&prefix=docs%2F"
+        );
+    }
+
+    #[tokio::test]
+    async fn can_serve_objects() {
+        let gateway = gateway().await;
+
+        let response = gateway
+            .handle(signed(
+                Method::PUT,
+                "/bucket/docs/a%20b.txt",
+                "secret-key",
+                "hello",
+            ))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().contains_key(header::ETAG));
+
+        let response = gateway
+            .handle(signed(
+                Method::GET,
+                "/bucket/docs/a%20b.txt",
+                "secret-key",
+                "",
+            ))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body(response).await, "hello");
+
+        let response = gateway
+            .handle(signed(
+                Method::HEAD,
+                "/bucket/docs/a%20b.txt",
+                "secret-key",
+                "",
+            ))
+            .await;
+        assert_eq!(response.headers().get(header::CONTENT_LENGTH).unwrap(), "5");
+
+        let response = gateway
+            .handle(signed(
+                Method::DELETE,
+                "/bucket/docs/a%20b.txt",
+                "secret-key",
+                "",
+            ))
+            .await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let response = gateway
+            .handle(signed(
+                Method::GET,
+                "/bucket/docs/a%20b.txt",
+                "secret-key",
+                "",
+            ))
+            .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(body(response).await.contains("<Code>NoSuchKey</Code>"));
+    }
+
+    #[tokio::test]
+    async fn can_list_objects() {
+        let gateway = gateway().await;
+        for key in ["docs/a.txt", "docs/b.txt", "docs/nested/c.txt", "other.txt"] {
+            gateway
+                .store
+                .write(Path::new(key), "content")
+                .await
+                .unwrap();
+        }
+
+        let response = gateway
+            .handle(signed(
+                Method::GET,
+                "/bucket?list-type=2&prefix=docs%2F&delimiter=%2F",
+                "secret-key",
+                "",
+            ))
+            .await;
+        let xml = body(response).await;
+        assert!(xml.contains("<Key>docs/a.txt</Key>"));
+        assert!(xml.contains("<Key>docs/b.txt</Key>"));
+        assert!(xml.contains("<CommonPrefixes><Prefix>docs/nested/</Prefix></CommonPrefixes>"));
+        assert!(!xml.contains("other.txt"));
+
+        let response = gateway
+            .handle(signed(
+                Method::GET,
+                "/bucket?list-type=2&max-keys=1&continuation-token=docs%2Fa.txt",
+                "secret-key",
+                "",
+            ))
+            .await;
+        let xml = body(response).await;
+        assert!(xml.contains("<Key>docs/b.txt</Key>"));
+        assert!(xml.contains("<IsTruncated>true</IsTruncated>"));
+        assert!(xml.contains("<NextContinuationToken>docs/b.txt</NextContinuationToken>"));
+    }
+
+    #[tokio::test]
+    async fn can_reject_unsigned_requests() {
+        let gateway = gateway().await;
+
+        let response = gateway
+            .handle(signed(Method::GET, "/bucket/a.txt", "wrong-secret", ""))
+            .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(body(response)
+            .await
+            .contains("<Code>SignatureDoesNotMatch</Code>"));
+
+        let mut request = signed(Method::PUT, "/bucket/a.txt", "secret-key", "hello");
+        *request.body_mut() = Full::new(Bytes::from("tampered"));
+        let response = gateway.handle(request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(body(response)
+            .await
+            .contains("<Code>XAmzContentSHA256Mismatch</Code>"));
+
+        let response = gateway
+            .handle(signed(Method::GET, "/other-bucket/a.txt", "secret-key", ""))
+            .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn can_authenticate_before_reading_bodies() {
+        use std::sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        };
+
+        use http_body_util::StreamBody;
+        use hyper::body::Frame;
+
+        let gateway = gateway().await.with_max_body_size(3);
+
+        let polled = Arc::new(AtomicBool::new(false));
+        let request = signed(Method::PUT, "/bucket/a.txt", "wrong-secret", "hello").map(|_| {
+            let polled = polled.clone();
+            StreamBody::new(futures::stream::once(async move {
+                polled.store(true, Ordering::SeqCst);
+                Ok::<_, std::convert::Infallible>(Frame::data(Bytes::from("hello")))
+            }))
+        });
+        let response = gateway.handle(request).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(!polled.load(Ordering::SeqCst));
+
+        let mut request = signed(Method::PUT, "/bucket/a.txt", "secret-key", "hello");
+        request.headers_mut().remove("x-amz-content-sha256");
+        let response = gateway.handle(request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(body(response).await.contains("<Code>InvalidRequest</Code>"));
+
+        let response = gateway
+            .handle(signed(Method::PUT, "/bucket/a.txt", "secret-key", "hello"))
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(body(response).await.contains("<Code>EntityTooLarge</Code>"));
+        assert!(!gateway.store.file_exists("a.txt").await.unwrap());
+    }
+}
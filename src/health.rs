@@ -0,0 +1,64 @@
+//! # Health Module
+//!
+//! The `health` module defines the types returned by
+//! [`crate::store::Store::health_check`] and
+//! [`crate::multi_store::MultiStore::health_report`], allowing services to wire
+//! their storage into readiness endpoints.
+use std::{collections::BTreeMap, time::Duration};
+
+use crate::errors::DriverError;
+
+/// The key prefix used by drivers that probe their backend by writing and
+/// deleting a canary object.
+pub const HEALTH_CHECK_PREFIX: &str = ".active-storage-health";
+
+/// Enum representing the outcome of a store health probe.
+#[derive(Debug)]
+pub enum HealthStatus {
+    /// The backend answered the probe successfully.
+    Healthy,
+    /// The backend failed the probe with the given error.
+    Unhealthy(DriverError),
+}
+
+/// Struct representing the result of a single store health probe.
+#[derive(Debug)]
+pub struct HealthCheck {
+    /// The probe outcome.
+    pub status: HealthStatus,
+    /// The time it took the backend to answer the probe.
+    pub latency: Duration,
+}
+
+impl HealthCheck {
+    /// Returns `true` when the probe succeeded.
+    #[must_use]
+    pub const fn is_healthy(&self) -> bool {
+        matches!(self.status, HealthStatus::Healthy)
+    }
+}
+
+/// Struct representing the health of every store managed by a
+/// [`crate::multi_store::MultiStore`], keyed by store name.
+#[derive(Debug)]
+pub struct HealthReport {
+    pub stores: BTreeMap<String, HealthCheck>,
+}
+
+impl HealthReport {
+    /// Returns `true` when all the stores in the report are healthy.
+    #[must_use]
+    pub fn is_healthy(&self) -> bool {
+        self.stores.values().all(HealthCheck::is_healthy)
+    }
+
+    /// Returns the names of the stores that failed their probe.
+    #[must_use]
+    pub fn unhealthy_stores(&self) -> Vec<&str> {
+        self.stores
+            .iter()
+            .filter(|(_, check)| !check.is_healthy())
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
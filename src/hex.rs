@@ -0,0 +1,37 @@
+//! # Hex Module
+//!
+//! The `hex` module encodes the checksums, signatures and ids written by the
+//! crate as lowercase hex strings.
+
+/// Returns the lowercase hex encoding of the bytes.
+#[cfg(any(
+    feature = "blobs",
+    feature = "dedup",
+    feature = "disk_signer",
+    feature = "erasure",
+    feature = "gateway",
+    feature = "history",
+    feature = "signing",
+    feature = "tokens"
+))]
+pub(crate) fn encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Decodes a hex string, returning `None` when it is malformed.
+#[cfg(any(
+    feature = "aws_s3",
+    feature = "disk_signer",
+    feature = "erasure",
+    feature = "signing",
+    feature = "tokens"
+))]
+pub(crate) fn decode(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(value.get(index..index + 2)?, 16).ok())
+        .collect()
+}
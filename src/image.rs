@@ -0,0 +1,362 @@
+//! # Image Module
+//!
+//! The `image` module defines [`ImageSanitizer`], which strips the privacy
+//! sensitive metadata of uploaded images, such as the EXIF camera details and
+//! GPS position of photos, before [`Store::write_sanitized`] writes them.
+//!
+//! JPEG, PNG and WebP images are rewritten without their metadata segments.
+//! The pixel data is copied as is, it is neither decoded nor re-encoded. Images
+//! with more pixels than the limit are rejected from their headers, which
+//! guards the image processing down the line against decompression bombs. Other
+//! contents are written unchanged.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::PathBuf;
+//! use active_storage::{image::ImageSanitizer, StoreConfig};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!     let sanitizer = ImageSanitizer::default().with_max_pixels(4096 * 4096);
+//!
+//!     let file_path = PathBuf::from("notes.txt");
+//!     assert!(store
+//!         .write_sanitized(file_path.as_path(), "my content", &sanitizer)
+//!         .await
+//!         .is_ok());
+//! }
+//! ```
+//!
+//! [`Store::write_sanitized`]: crate::store::Store::write_sanitized
+use bytes::Bytes;
+
+use crate::validators::{be_u32, sniff_content_type, ValidationFailure};
+
+/// The PNG chunks holding metadata: EXIF, text and modification time.
+const PNG_METADATA_CHUNKS: [&[u8]; 5] = [b"eXIf", b"tEXt", b"zTXt", b"iTXt", b"tIME"];
+
+/// The JPEG markers of the metadata segments: APP1 (EXIF and XMP), APP13
+/// (IPTC) and comments.
+const JPEG_METADATA_MARKERS: [u8; 3] = [0xE1, 0xED, 0xFE];
+
+/// The flags of the WebP `VP8X` chunk announcing EXIF and XMP metadata.
+const WEBP_METADATA_FLAGS: u8 = 0x08 | 0x04;
+
+/// Struct stripping the metadata of uploaded images and rejecting the ones
+/// with too many pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageSanitizer {
+    /// The maximum number of pixels, width times height, of an image.
+    pub max_pixels: u64,
+}
+
+impl Default for ImageSanitizer {
+    /// Allows images of up to 50 megapixels.
+    fn default() -> Self {
+        Self {
+            max_pixels: 50_000_000,
+        }
+    }
+}
+
+impl ImageSanitizer {
+    /// Sets the maximum number of pixels of an image.
+    #[must_use]
+    pub const fn with_max_pixels(mut self, max_pixels: u64) -> Self {
+        self.max_pixels = max_pixels;
+        self
+    }
+
+    /// Returns the content without its image metadata. The content type is
+    /// sniffed from the content, so renaming a file does not bypass the
+    /// sanitization.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ValidationFailure::InvalidImage`] if the image is malformed
+    /// or truncated, and [`ValidationFailure::TooManyPixels`] if it exceeds
+    /// the pixel limit.
+    pub fn sanitize(&self, content: Bytes) -> Result<Bytes, ValidationFailure> {
+        let (sanitized, (width, height)) = match sniff_content_type(&content) {
+            Some("image/jpeg") => strip_jpeg(&content)?,
+            Some("image/png") => strip_png(&content)?,
+            Some("image/webp") => strip_webp(&content)?,
+            Some("image/gif") => {
+                let header = content
+                    .get(6..10)
+                    .ok_or(ValidationFailure::InvalidImage("missing GIF header"))?;
+                let dimensions = (
+                    u32::from(u16::from_le_bytes([header[0], header[1]])),
+                    u32::from(u16::from_le_bytes([header[2], header[3]])),
+                );
+                (content.to_vec(), dimensions)
+            }
+            _ => return Ok(content),
+        };
+
+        let pixels = u64::from(width) * u64::from(height);
+        if pixels > self.max_pixels {
+            return Err(ValidationFailure::TooManyPixels {
+                pixels,
+                max: self.max_pixels,
+            });
+        }
+        Ok(Bytes::from(sanitized))
+    }
+}
+
+/// Returns the JPEG without its metadata segments, and its dimensions.
+fn strip_jpeg(content: &[u8]) -> Result<(Vec<u8>, (u32, u32)), ValidationFailure> {
+    const TRUNCATED: ValidationFailure = ValidationFailure::InvalidImage("truncated JPEG");
+
+    let mut output = content[..2].to_vec();
+    let mut dimensions = None;
+    let mut offset = 2;
+    loop {
+        // markers may be padded with fill bytes
+        while content.get(offset..offset + 2) == Some(&[0xFF, 0xFF]) {
+            offset += 1;
+        }
+        let marker = match content.get(offset..offset + 2) {
+            Some(&[0xFF, marker]) => marker,
+            _ => return Err(TRUNCATED),
+        };
+        match marker {
+            // the start of scan is followed by the compressed image data,
+            // which is kept with everything up to the end of image
+            0xDA => {
+                output.extend_from_slice(&content[offset..]);
+                break;
+            }
+            0xD9 => {
+                output.extend_from_slice(&content[offset..offset + 2]);
+                break;
+            }
+            0x01 | 0xD0..=0xD7 => {
+                output.extend_from_slice(&content[offset..offset + 2]);
+                offset += 2;
+                continue;
+            }
+            _ => {}
+        }
+
+        let length = content
+            .get(offset + 2..offset + 4)
+            .map(|length| usize::from(u16::from_be_bytes([length[0], length[1]])))
+            .filter(|length| *length >= 2)
+            .ok_or(TRUNCATED)?;
+        let segment = content.get(offset..offset + 2 + length).ok_or(TRUNCATED)?;
+        // the start of frame markers, except the huffman and arithmetic
+        // coding tables
+        if matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            let header = segment.get(5..9).ok_or(TRUNCATED)?;
+            dimensions = Some((
+                u32::from(u16::from_be_bytes([header[2], header[3]])),
+                u32::from(u16::from_be_bytes([header[0], header[1]])),
+            ));
+        }
+        if !JPEG_METADATA_MARKERS.contains(&marker) {
+            output.extend_from_slice(segment);
+        }
+        offset += 2 + length;
+    }
+
+    let dimensions =
+        dimensions.ok_or(ValidationFailure::InvalidImage("missing JPEG frame header"))?;
+    Ok((output, dimensions))
+}
+
+/// Returns the PNG without its metadata chunks, and its dimensions.
+fn strip_png(content: &[u8]) -> Result<(Vec<u8>, (u32, u32)), ValidationFailure> {
+    const TRUNCATED: ValidationFailure = ValidationFailure::InvalidImage("truncated PNG");
+
+    let mut output = content[..8].to_vec();
+    let mut dimensions = None;
+    let mut offset = 8;
+    loop {
+        let length = content
+            .get(offset..offset + 4)
+            .and_then(|length| usize::try_from(be_u32(length)).ok())
+            .ok_or(TRUNCATED)?;
+        let end = length.checked_add(offset + 12).ok_or(TRUNCATED)?;
+        let chunk = content.get(offset..end).ok_or(TRUNCATED)?;
+        let kind = &chunk[4..8];
+
+        if kind == b"IHDR" {
+            let header = chunk.get(8..16).ok_or(TRUNCATED)?;
+            dimensions = Some((be_u32(&header[..4]), be_u32(&header[4..])));
+        }
+        if !PNG_METADATA_CHUNKS.contains(&kind) {
+            output.extend_from_slice(chunk);
+        }
+        offset = end;
+        if kind == b"IEND" {
+            break;
+        }
+    }
+
+    let dimensions = dimensions.ok_or(ValidationFailure::InvalidImage("missing PNG header"))?;
+    Ok((output, dimensions))
+}
+
+/// Returns the WebP without its metadata chunks, and its dimensions.
+fn strip_webp(content: &[u8]) -> Result<(Vec<u8>, (u32, u32)), ValidationFailure> {
+    const TRUNCATED: ValidationFailure = ValidationFailure::InvalidImage("truncated WebP");
+
+    let riff_end = usize::try_from(le_u32(&content[4..8]))
+        .ok()
+        .and_then(|size| size.checked_add(8))
+        .ok_or(TRUNCATED)?;
+    let content = content.get(..riff_end).ok_or(TRUNCATED)?;
+
+    let mut output = content[..12].to_vec();
+    let mut dimensions = None;
+    let mut offset = 12;
+    while offset < content.len() {
+        let header = content.get(offset..offset + 8).ok_or(TRUNCATED)?;
+        let size = usize::try_from(le_u32(&header[4..])).map_err(|_| TRUNCATED)?;
+        // chunks are padded to an even size
+        let end = size.checked_add(offset + 8 + size % 2).ok_or(TRUNCATED)?;
+        let chunk = content.get(offset..end).ok_or(TRUNCATED)?;
+        let data = &chunk[8..8 + size];
+
+        match &chunk[..4] {
+            b"VP8X" => {
+                let canvas = data.get(4..10).ok_or(TRUNCATED)?;
+                dimensions = Some((
+                    1 + le_u32(&[canvas[0], canvas[1], canvas[2], 0]),
+                    1 + le_u32(&[canvas[3], canvas[4], canvas[5], 0]),
+                ));
+                let flags = output.len() + 8;
+                output.extend_from_slice(chunk);
+                output[flags] &= !WEBP_METADATA_FLAGS;
+            }
+            b"VP8 " => {
+                let frame = data.get(6..10).ok_or(TRUNCATED)?;
+                dimensions.get_or_insert((
+                    u32::from(u16::from_le_bytes([frame[0], frame[1]]) & 0x3FFF),
+                    u32::from(u16::from_le_bytes([frame[2], frame[3]]) & 0x3FFF),
+                ));
+                output.extend_from_slice(chunk);
+            }
+            b"VP8L" => {
+                let bits = le_u32(data.get(1..5).ok_or(TRUNCATED)?);
+                dimensions.get_or_insert(((bits & 0x3FFF) + 1, (bits >> 14 & 0x3FFF) + 1));
+                output.extend_from_slice(chunk);
+            }
+            b"EXIF" | b"XMP " => {}
+            _ => output.extend_from_slice(chunk),
+        }
+        offset = end;
+    }
+
+    let riff_size = u32::try_from(output.len() - 8).map_err(|_| TRUNCATED)?;
+    output[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    let dimensions = dimensions.ok_or(ValidationFailure::InvalidImage("missing WebP header"))?;
+    Ok((output, dimensions))
+}
+
+fn le_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    use super::*;
+    use crate::{errors::DriverError, StoreConfig};
+
+    /// A 32x16 JPEG with EXIF GPS metadata and a comment.
+    const JPEG: &[u8] = b"\xFF\xD8\
+        \xFF\xE0\x00\x07JFIF\x00\
+        \xFF\xE1\x00\x0CExif\x00\x00GPS\x00\
+        \xFF\xFE\x00\x06Hi!\x00\
+        \xFF\xC0\x00\x0B\x08\x00\x10\x00\x20\x01\x01\x11\x00\
+        \xFF\xDA\x00\x08\x01\x01\x00\x00\x3F\x00\xAB\xCD\
+        \xFF\xD9";
+
+    /// A 16x16 PNG with a text chunk and trailing data.
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\
+        \x00\x00\x00\x0DIHDR\x00\x00\x00\x10\x00\x00\x00\x10\x08\x02\x00\x00\x00CRC!\
+        \x00\x00\x00\x07tEXtGPS\x00123CRC!\
+        \x00\x00\x00\x00IENDCRC!\
+        appended";
+
+    /// A 2x3 WebP announcing and holding EXIF metadata.
+    const WEBP: &[u8] = b"RIFF\x30\x00\x00\x00WEBP\
+        VP8X\x0A\x00\x00\x00\x08\x00\x00\x00\x01\x00\x00\x02\x00\x00\
+        EXIF\x03\x00\x00\x00GPS\x00\
+        VP8L\x05\x00\x00\x00\x2F\x01\x80\x00\x00\x00";
+
+    #[test]
+    fn can_strip_image_metadata() {
+        let sanitizer = ImageSanitizer::default();
+
+        let jpeg = sanitizer.sanitize(Bytes::from_static(JPEG)).unwrap();
+        assert_eq!(
+            jpeg,
+            &b"\xFF\xD8\
+            \xFF\xE0\x00\x07JFIF\x00\
+            \xFF\xC0\x00\x0B\x08\x00\x10\x00\x20\x01\x01\x11\x00\
+            \xFF\xDA\x00\x08\x01\x01\x00\x00\x3F\x00\xAB\xCD\
+            \xFF\xD9"[..]
+        );
+
+        let png = sanitizer.sanitize(Bytes::from_static(PNG)).unwrap();
+        assert_eq!(png, [&PNG[..33], b"\x00\x00\x00\x00IENDCRC!"].concat());
+
+        let webp = sanitizer.sanitize(Bytes::from_static(WEBP)).unwrap();
+        assert!(!webp.windows(4).any(|window| window == b"EXIF"));
+        assert_eq!(webp[20], 0);
+        assert_eq!(le_u32(&webp[4..8]) as usize, webp.len() - 8);
+
+        assert_eq!(
+            sanitizer.sanitize(Bytes::from_static(b"content")).unwrap(),
+            "content"
+        );
+        assert_eq!(
+            sanitizer.sanitize(Bytes::copy_from_slice(&JPEG[..40])),
+            Err(ValidationFailure::InvalidImage("truncated JPEG"))
+        );
+    }
+
+    #[tokio::test]
+    async fn can_reject_oversized_images() {
+        let sanitizer = ImageSanitizer::default().with_max_pixels(256);
+        for (image, pixels) in [(JPEG, 512), (WEBP, 6), (PNG, 256)] {
+            let result = sanitizer.sanitize(Bytes::from_static(image));
+            if pixels > 256 {
+                assert_eq!(
+                    result,
+                    Err(ValidationFailure::TooManyPixels { pixels, max: 256 })
+                );
+            } else {
+                assert!(result.is_ok());
+            }
+        }
+
+        let store = StoreConfig::InMem().build().await.unwrap();
+        assert!(matches!(
+            store
+                .write_sanitized(Path::new("photo.jpg"), JPEG, &sanitizer)
+                .await,
+            Err(DriverError::Validation(_))
+        ));
+        assert!(store
+            .write_sanitized(Path::new("photo.png"), PNG, &sanitizer)
+            .await
+            .is_ok());
+        assert_eq!(
+            store
+                .read_bytes(Path::new("photo.png"))
+                .await
+                .unwrap()
+                .len(),
+            45
+        );
+    }
+}
@@ -0,0 +1,201 @@
+//! # Inventory Module
+//!
+//! The `inventory` module writes a manifest of the files under a prefix, one
+//! entry per file with its key, size, checksum, modification time and storage
+//! class, e.g. as the input of audits and reconciliation jobs comparing two
+//! stores.
+//!
+//! The checksum is the `ETag` reported by the backend, empty on backends
+//! without one, and the modification time is in seconds since the Unix epoch.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::PathBuf;
+//! use active_storage::{inventory::InventoryFormat, StoreConfig};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!     store
+//!         .write(PathBuf::from("docs").join("a.txt").as_path(), "a")
+//!         .await
+//!         .unwrap();
+//!
+//!     let mut manifest = Vec::new();
+//!     let files = store
+//!         .inventory(PathBuf::from("docs"), InventoryFormat::Csv, &mut manifest)
+//!         .await
+//!         .unwrap();
+//!     assert_eq!(files, 1);
+//!     assert!(String::from_utf8(manifest)
+//!         .unwrap()
+//!         .starts_with("key,size,checksum,mtime,storage_class\n"));
+//! }
+//! ```
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use futures::{stream, StreamExt, TryStreamExt};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::{
+    drivers::ObjectMetadata,
+    errors::{DriverError, DriverResult},
+    key::ObjectPath,
+    store::Store,
+};
+
+/// The number of files looked up at once.
+const STAT_CONCURRENCY: usize = 8;
+
+/// The columns of the CSV manifest.
+const CSV_HEADER: &str = "key,size,checksum,mtime,storage_class";
+
+/// Enum representing the format of a manifest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InventoryFormat {
+    /// Comma separated values, with a header row.
+    Csv,
+    /// One JSON object per line.
+    JsonLines,
+}
+
+/// Writes the manifest of the files under the prefix to the writer, in key
+/// order, returning the number of listed files.
+pub(crate) async fn write_inventory<W: AsyncWrite + Unpin + Send + ?Sized>(
+    store: &Store,
+    prefix: &ObjectPath,
+    format: InventoryFormat,
+    writer: &mut W,
+) -> DriverResult<usize> {
+    let mut files = store.list(prefix).await?;
+    files.sort();
+
+    let entries: Vec<(ObjectPath, Option<ObjectMetadata>)> = stream::iter(files)
+        .map(|file| async move {
+            let metadata = store.stat(&file).await?;
+            Ok::<_, DriverError>((file, metadata))
+        })
+        .buffered(STAT_CONCURRENCY)
+        .try_collect()
+        .await?;
+
+    if format == InventoryFormat::Csv {
+        write_line(writer, CSV_HEADER).await?;
+    }
+    let mut count = 0;
+    // files deleted since the listing are left out
+    for (file, metadata) in entries {
+        let Some(metadata) = metadata else {
+            continue;
+        };
+        let line = match format {
+            InventoryFormat::Csv => csv_line(&file, &metadata),
+            InventoryFormat::JsonLines => json_line(&file, &metadata),
+        };
+        write_line(writer, &line).await?;
+        count += 1;
+    }
+    writer.flush().await.map_err(|err| err.kind())?;
+    Ok(count)
+}
+
+async fn write_line<W: AsyncWrite + Unpin + Send + ?Sized>(
+    writer: &mut W,
+    line: &str,
+) -> DriverResult<()> {
+    writer
+        .write_all(format!("{line}\n").as_bytes())
+        .await
+        .map_err(|err| err.kind().into())
+}
+
+fn csv_line(file: &ObjectPath, metadata: &ObjectMetadata) -> String {
+    [
+        csv_field(file.as_str()),
+        metadata.size.to_string(),
+        csv_field(metadata.etag.as_deref().unwrap_or_default()),
+        seconds(metadata.last_modified).to_string(),
+        csv_field(metadata.storage_class.as_deref().unwrap_or_default()),
+    ]
+    .join(",")
+}
+
+fn json_line(file: &ObjectPath, metadata: &ObjectMetadata) -> String {
+    serde_json::json!({
+        "key": file.as_str(),
+        "size": metadata.size,
+        "checksum": metadata.etag,
+        "mtime": seconds(metadata.last_modified),
+        "storage_class": metadata.storage_class,
+    })
+    .to_string()
+}
+
+/// Quotes a CSV field when it holds a delimiter, a quote or a line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Returns the seconds since the Unix epoch of the given time.
+fn seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    use super::*;
+    use crate::StoreConfig;
+
+    #[tokio::test]
+    async fn can_write_inventory() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        store.write(Path::new("docs/b.txt"), b"bb").await.unwrap();
+        store.write(Path::new("docs/a,1.txt"), b"a").await.unwrap();
+        store.write(Path::new("other.txt"), b"other").await.unwrap();
+
+        let mut csv = Vec::new();
+        let count = write_inventory(
+            &store,
+            &ObjectPath::new("docs"),
+            InventoryFormat::Csv,
+            &mut csv,
+        )
+        .await
+        .unwrap();
+        assert_eq!(count, 2);
+        let csv = String::from_utf8(csv).unwrap();
+        let rows = csv.lines().collect::<Vec<_>>();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0], CSV_HEADER);
+        assert!(rows[1].starts_with("\"docs/a,1.txt\",1,,"));
+        assert!(rows[2].starts_with("docs/b.txt,2,,"));
+
+        let mut jsonl = Vec::new();
+        write_inventory(
+            &store,
+            &ObjectPath::new("docs"),
+            InventoryFormat::JsonLines,
+            &mut jsonl,
+        )
+        .await
+        .unwrap();
+        let entries = String::from_utf8(jsonl)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1]["key"], "docs/b.txt");
+        assert_eq!(entries[1]["size"], 2);
+        assert!(entries[1]["storage_class"].is_null());
+    }
+}
@@ -0,0 +1,198 @@
+//! # Jobs Module
+//!
+//! The `jobs` module defines [`JobEnqueuer`], an abstraction over background
+//! job queues, so expensive storage work such as purging runs outside of the
+//! request that triggered it, like Rails Active Storage `purge_later`.
+//!
+//! [`TokioEnqueuer`] runs the jobs on the tokio runtime of the application.
+//! Adapters for external queues implement [`JobEnqueuer`] by serializing the
+//! [`Job`] and calling [`Job::perform`] from their worker.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::PathBuf;
+//! use active_storage::{
+//!     jobs::{Job, JobEnqueuer, TokioEnqueuer},
+//!     StoreConfig,
+//! };
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!     let file_path = PathBuf::from("test.txt");
+//!     store.write(file_path.as_path(), "my content").await.unwrap();
+//!
+//!     let enqueuer = TokioEnqueuer::new(store.clone(), 4);
+//!     enqueuer.enqueue(Job::Purge(file_path.clone())).await.unwrap();
+//!     enqueuer.drain().await;
+//!
+//!     assert!(!store.file_exists(file_path.as_path()).await.unwrap());
+//! }
+//! ```
+use std::{path::PathBuf, sync::Arc};
+
+use tokio::sync::Semaphore;
+
+use crate::{
+    errors::{DriverError, DriverResult},
+    store::Store,
+};
+
+/// Enum representing a unit of background storage work.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Job {
+    /// Deletes the file at the path.
+    Purge(PathBuf),
+    /// Deletes the directory at the path and everything under it.
+    PurgeDirectory(PathBuf),
+}
+
+impl Job {
+    /// Performs the job against the given store. Purging a missing file
+    /// succeeds, so retried jobs are idempotent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store operation fails.
+    pub async fn perform(&self, store: &Store) -> DriverResult<()> {
+        match self {
+            Self::Purge(path) => store.delete_if_exists(path).await.map(|_| ()),
+            Self::PurgeDirectory(path) => match store.delete_directory(path).await {
+                Err(DriverError::ResourceNotFound) => Ok(()),
+                result => result,
+            },
+        }
+    }
+}
+
+/// Trait representing a background job queue.
+#[async_trait::async_trait]
+pub trait JobEnqueuer: Send + Sync {
+    /// Enqueues the job, returning once it is accepted by the queue rather
+    /// than once it is performed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the queue rejected the job.
+    async fn enqueue(&self, job: Job) -> DriverResult<()>;
+}
+
+/// A callback invoked when a background job fails.
+pub type JobErrorHandler = Arc<dyn Fn(&Job, &DriverError) + Send + Sync>;
+
+/// Struct representing a [`JobEnqueuer`] performing the jobs as tokio tasks,
+/// with a bounded number of jobs running at once.
+///
+/// Jobs are lost if the process exits before they are performed, use an
+/// external queue when jobs must survive restarts.
+#[derive(Clone)]
+pub struct TokioEnqueuer {
+    store: Store,
+    concurrency: u32,
+    permits: Arc<Semaphore>,
+    on_error: Option<JobErrorHandler>,
+}
+
+impl TokioEnqueuer {
+    /// Creates a new [`TokioEnqueuer`] performing the jobs against the given
+    /// store, running up to `concurrency` jobs at once.
+    #[must_use]
+    pub fn new(store: Store, concurrency: u32) -> Self {
+        let concurrency = concurrency.max(1);
+        Self {
+            store,
+            concurrency,
+            permits: Arc::new(Semaphore::new(concurrency as usize)),
+            on_error: None,
+        }
+    }
+
+    /// Sets a callback invoked when a job fails.
+    #[must_use]
+    pub fn with_error_handler<F>(mut self, on_error: F) -> Self
+    where
+        F: Fn(&Job, &DriverError) + Send + Sync + 'static,
+    {
+        self.on_error = Some(Arc::new(on_error));
+        self
+    }
+
+    /// Waits until every enqueued job was performed.
+    pub async fn drain(&self) {
+        // holding every permit means no job is running or waiting
+        let _permits = self.permits.acquire_many(self.concurrency).await;
+    }
+}
+
+#[async_trait::async_trait]
+impl JobEnqueuer for TokioEnqueuer {
+    /// Spawns a task performing the job once a slot is free.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the enqueuer was closed.
+    async fn enqueue(&self, job: Job) -> DriverResult<()> {
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|err| DriverError::Any(Box::new(err)))?;
+        let store = self.store.clone();
+        let on_error = self.on_error.clone();
+
+        tokio::spawn(async move {
+            if let Err(err) = job.perform(&store).await {
+                if let Some(on_error) = on_error {
+                    on_error(&job, &err);
+                }
+            }
+            drop(permit);
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{path::Path, sync::Mutex};
+
+    use super::*;
+    use crate::StoreConfig;
+
+    #[tokio::test]
+    async fn can_perform_jobs_in_background() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        store.write(Path::new("a/1.txt"), "1").await.unwrap();
+        store.write(Path::new("a/2.txt"), "2").await.unwrap();
+        store.write(Path::new("b.txt"), "b").await.unwrap();
+
+        let failed = Arc::new(Mutex::new(Vec::new()));
+        let failures = failed.clone();
+        let enqueuer = TokioEnqueuer::new(store.clone(), 1)
+            .with_error_handler(move |job, _| failures.lock().unwrap().push(job.clone()));
+
+        enqueuer
+            .enqueue(Job::PurgeDirectory(PathBuf::from("a")))
+            .await
+            .unwrap();
+        enqueuer
+            .enqueue(Job::Purge(PathBuf::from("b.txt")))
+            .await
+            .unwrap();
+        enqueuer
+            .enqueue(Job::Purge(PathBuf::from("../invalid")))
+            .await
+            .unwrap();
+        enqueuer.drain().await;
+
+        assert!(store.list(Path::new("")).await.unwrap().is_empty());
+        assert_eq!(
+            *failed.lock().unwrap(),
+            vec![Job::Purge(PathBuf::from("../invalid"))]
+        );
+    }
+}
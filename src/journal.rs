@@ -0,0 +1,590 @@
+//! # Journal Module
+//!
+//! The `journal` module defines [`JournaledStore`], which keeps the mutations
+//! a flaky or offline backend could not apply in a local disk journal and
+//! replays them in order once the backend is reachable again, e.g. on edge
+//! devices syncing to S3.
+//!
+//! A mutation is journaled when the backend fails with a retryable error, see
+//! [`DriverError::is_retryable`], and every later mutation is journaled
+//! behind it to keep their order. The journal survives restarts.
+//!
+//! When the file changed on the backend after a mutation was journaled, the
+//! [`ConflictHandler`] decides whether the mutation is still applied.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::Path;
+//! use active_storage::{journal::JournaledStore, StoreConfig};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!     let journal = std::env::temp_dir().join("active-storage-journal-example");
+//!     let journaled = JournaledStore::new(store, &journal).await.unwrap();
+//!
+//!     journaled.write(Path::new("test.txt"), "my content").await.unwrap();
+//!
+//!     let report = journaled.replay().await.unwrap();
+//!     assert_eq!(report.pending, 0);
+//!     # std::fs::remove_dir_all(&journal).unwrap();
+//! }
+//! ```
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bytes::Bytes;
+use tokio::{fs, sync::Mutex};
+
+use crate::{
+    clock,
+    contents::IntoBytes,
+    drivers::ObjectMetadata,
+    errors::{DriverError, DriverResult},
+    key::Key,
+    store::Store,
+};
+
+/// The extension of a journal entry being written, renamed once complete.
+const PARTIAL_EXTENSION: &str = "partial";
+
+/// Enum representing a mutation kept in the journal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Mutation {
+    /// Writes the content to the file at the path.
+    Write { path: PathBuf, content: Bytes },
+    /// Deletes the file at the path.
+    Delete(PathBuf),
+    /// Deletes the directory at the path and everything under it.
+    DeleteDirectory(PathBuf),
+}
+
+impl Mutation {
+    /// Returns the path the mutation applies to.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Write { path, .. } | Self::Delete(path) | Self::DeleteDirectory(path) => path,
+        }
+    }
+
+    /// Returns the name of the operation stored in the journal.
+    const fn operation(&self) -> &'static str {
+        match self {
+            Self::Write { .. } => "write",
+            Self::Delete(_) => "delete",
+            Self::DeleteDirectory(_) => "delete_directory",
+        }
+    }
+
+    /// Applies the mutation to the store. Deleting a missing file or
+    /// directory succeeds, so replayed mutations are idempotent.
+    async fn apply(&self, store: &Store) -> DriverResult<()> {
+        match self {
+            Self::Write { path, content } => store.write(path, content.clone()).await,
+            Self::Delete(path) => store.delete_if_exists(path).await.map(|_| ()),
+            Self::DeleteDirectory(path) => match store.delete_directory(path).await {
+                Err(DriverError::ResourceNotFound) => Ok(()),
+                result => result,
+            },
+        }
+    }
+}
+
+/// Struct representing a mutation with the time it was journaled.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JournalEntry {
+    pub mutation: Mutation,
+    pub journaled_at: SystemTime,
+}
+
+impl JournalEntry {
+    /// Serializes the entry as a header line holding the operation, the
+    /// journal time and the path length, followed by the path and content.
+    fn encode(&self) -> Vec<u8> {
+        let path = self.mutation.path().to_string_lossy();
+        let nanos = self
+            .journaled_at
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| {
+                u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+            });
+
+        let mut encoded =
+            format!("{} {nanos} {}\n", self.mutation.operation(), path.len()).into_bytes();
+        encoded.extend_from_slice(path.as_bytes());
+        if let Mutation::Write { content, .. } = &self.mutation {
+            encoded.extend_from_slice(content);
+        }
+        encoded
+    }
+
+    /// Parses an entry serialized with [`Self::encode`].
+    fn decode(encoded: &[u8]) -> DriverResult<Self> {
+        let header_end = encoded
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .ok_or(DriverError::DecodeError)?;
+        let header =
+            std::str::from_utf8(&encoded[..header_end]).map_err(|_| DriverError::DecodeError)?;
+        let mut fields = header.split(' ');
+        let (Some(operation), Some(nanos), Some(path_length), None) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            return Err(DriverError::DecodeError);
+        };
+        let nanos: u64 = nanos.parse().map_err(|_| DriverError::DecodeError)?;
+        let path_length: usize = path_length.parse().map_err(|_| DriverError::DecodeError)?;
+
+        let body = &encoded[header_end + 1..];
+        if body.len() < path_length {
+            return Err(DriverError::DecodeError);
+        }
+        let path = std::str::from_utf8(&body[..path_length])
+            .map_err(|_| DriverError::DecodeError)?
+            .into();
+        let mutation = match operation {
+            "write" => Mutation::Write {
+                path,
+                content: Bytes::copy_from_slice(&body[path_length..]),
+            },
+            "delete" => Mutation::Delete(path),
+            "delete_directory" => Mutation::DeleteDirectory(path),
+            _ => return Err(DriverError::DecodeError),
+        };
+
+        Ok(Self {
+            mutation,
+            journaled_at: UNIX_EPOCH + Duration::from_nanos(nanos),
+        })
+    }
+}
+
+/// Enum representing how a conflicting journaled mutation is resolved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictResolution {
+    /// Applies the journaled mutation, overriding the backend change.
+    Apply,
+    /// Drops the journaled mutation, keeping the backend change.
+    Skip,
+}
+
+/// A callback resolving a journaled mutation whose file changed on the
+/// backend after it was journaled, given the backend metadata of the file.
+pub type ConflictHandler =
+    Arc<dyn Fn(&JournalEntry, &ObjectMetadata) -> ConflictResolution + Send + Sync>;
+
+/// Struct representing the outcome of [`JournaledStore::replay`].
+#[derive(Debug, Default)]
+pub struct ReplayReport {
+    /// The number of mutations applied to the backend.
+    pub applied: usize,
+    /// The number of mutations dropped by the [`ConflictHandler`].
+    pub skipped: usize,
+    /// The mutations the backend rejected with a non retryable error. They
+    /// are removed from the journal.
+    pub failed: Vec<(JournalEntry, DriverError)>,
+    /// The number of mutations left in the journal because the backend is
+    /// still unreachable.
+    pub pending: usize,
+}
+
+/// Struct representing a [`Store`] journaling the mutations its backend
+/// could not apply.
+#[derive(Clone)]
+pub struct JournaledStore {
+    store: Store,
+    journal: PathBuf,
+    sequence: Arc<AtomicU64>,
+    lock: Arc<Mutex<()>>,
+    on_conflict: Option<ConflictHandler>,
+}
+
+impl JournaledStore {
+    /// Creates a new [`JournaledStore`] applying the mutations to the given
+    /// store and journaling them in the given local directory, which is
+    /// created when missing. Entries left by a previous run are kept and
+    /// replayed first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the journal directory could not be created or
+    /// read.
+    pub async fn new(store: Store, journal: &Path) -> DriverResult<Self> {
+        fs::create_dir_all(journal).await.map_err(io_error)?;
+        let next = entry_paths(journal)
+            .await?
+            .last()
+            .and_then(|(sequence, _)| sequence.checked_add(1))
+            .unwrap_or_default();
+
+        Ok(Self {
+            store,
+            journal: journal.to_path_buf(),
+            sequence: Arc::new(AtomicU64::new(next)),
+            lock: Arc::new(Mutex::new(())),
+            on_conflict: None,
+        })
+    }
+
+    /// Sets the callback resolving the mutations whose file changed on the
+    /// backend after they were journaled. Without one, the journaled
+    /// mutations are applied.
+    #[must_use]
+    pub fn with_conflict_handler<F>(mut self, on_conflict: F) -> Self
+    where
+        F: Fn(&JournalEntry, &ObjectMetadata) -> ConflictResolution + Send + Sync + 'static,
+    {
+        self.on_conflict = Some(Arc::new(on_conflict));
+        self
+    }
+
+    /// Returns the store the mutations are applied to.
+    #[must_use]
+    pub const fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// Writes the content to the file at the given path, journaling the write
+    /// when the backend is unreachable.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the backend rejected the write with a non
+    /// retryable error, or the journal could not be written.
+    pub async fn write<C: IntoBytes + Send>(&self, path: &Path, content: C) -> DriverResult<()> {
+        self.mutate(Mutation::Write {
+            path: path.to_path_buf(),
+            content: content.into_bytes(),
+        })
+        .await
+    }
+
+    /// Deletes the file at the given path, journaling the delete when the
+    /// backend is unreachable. Deleting a missing file succeeds.
+    ///
+    /// # Errors
+    ///
+    /// See [`Self::write`].
+    pub async fn delete(&self, path: &Path) -> DriverResult<()> {
+        self.mutate(Mutation::Delete(path.to_path_buf())).await
+    }
+
+    /// Deletes the directory at the given path, journaling the delete when
+    /// the backend is unreachable. Deleting a missing directory succeeds.
+    ///
+    /// # Errors
+    ///
+    /// See [`Self::write`].
+    pub async fn delete_directory(&self, path: &Path) -> DriverResult<()> {
+        self.mutate(Mutation::DeleteDirectory(path.to_path_buf()))
+            .await
+    }
+
+    /// Returns the mutations waiting in the journal, oldest first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the journal could not be read.
+    pub async fn pending(&self) -> DriverResult<Vec<JournalEntry>> {
+        let mut entries = Vec::new();
+        for (_, path) in entry_paths(&self.journal).await? {
+            entries.push(read_entry(&path).await?);
+        }
+        Ok(entries)
+    }
+
+    /// Applies the journaled mutations in order, stopping at the first one
+    /// the backend still can't be reached for.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the journal could not be read or updated.
+    pub async fn replay(&self) -> DriverResult<ReplayReport> {
+        let _guard = self.lock.lock().await;
+        let mut report = ReplayReport::default();
+
+        let entries = entry_paths(&self.journal).await?;
+        for (index, (_, entry_path)) in entries.iter().enumerate() {
+            let entry = read_entry(entry_path).await?;
+            match self.replay_entry(&entry).await {
+                Ok(true) => report.applied += 1,
+                Ok(false) => report.skipped += 1,
+                Err(err) if err.is_retryable() => {
+                    report.pending = entries.len() - index;
+                    return Ok(report);
+                }
+                Err(err) => report.failed.push((entry, err)),
+            }
+            fs::remove_file(entry_path).await.map_err(io_error)?;
+        }
+
+        Ok(report)
+    }
+
+    /// Applies the mutation right away when the journal is empty, and
+    /// journals it when the backend is unreachable or older mutations are
+    /// still waiting.
+    async fn mutate(&self, mutation: Mutation) -> DriverResult<()> {
+        Key::from_path(mutation.path())?;
+        let _guard = self.lock.lock().await;
+
+        if entry_paths(&self.journal).await?.is_empty() {
+            match mutation.apply(&self.store).await {
+                Err(err) if err.is_retryable() => {}
+                result => return result,
+            }
+        }
+
+        self.append(&JournalEntry {
+            mutation,
+            journaled_at: clock::now(),
+        })
+        .await
+    }
+
+    /// Replays a single entry, returning `false` when the conflict handler
+    /// skipped it.
+    async fn replay_entry(&self, entry: &JournalEntry) -> DriverResult<bool> {
+        if let (Some(on_conflict), Mutation::Write { path, .. } | Mutation::Delete(path)) =
+            (&self.on_conflict, &entry.mutation)
+        {
+            if let Some(metadata) = self.store.stat(path).await? {
+                if metadata.last_modified > entry.journaled_at
+                    && on_conflict(entry, &metadata) == ConflictResolution::Skip
+                {
+                    return Ok(false);
+                }
+            }
+        }
+
+        entry.mutation.apply(&self.store).await?;
+        Ok(true)
+    }
+
+    /// Appends the entry to the journal. The entry is written under a
+    /// partial name first, so a crash never leaves a truncated entry.
+    async fn append(&self, entry: &JournalEntry) -> DriverResult<()> {
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        let path = self.journal.join(format!("{sequence:020}"));
+        let partial_path = path.with_extension(PARTIAL_EXTENSION);
+
+        fs::write(&partial_path, entry.encode())
+            .await
+            .map_err(io_error)?;
+        fs::rename(&partial_path, &path).await.map_err(io_error)
+    }
+}
+
+/// Returns the complete entries of the journal with their sequence number,
+/// oldest first.
+async fn entry_paths(journal: &Path) -> DriverResult<Vec<(u64, PathBuf)>> {
+    let mut entries = Vec::new();
+    let mut directory = fs::read_dir(journal).await.map_err(io_error)?;
+    while let Some(entry) = directory.next_entry().await.map_err(io_error)? {
+        let path = entry.path();
+        let sequence = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.parse().ok());
+        if let Some(sequence) = sequence {
+            entries.push((sequence, path));
+        }
+    }
+
+    entries.sort_unstable();
+    Ok(entries)
+}
+
+/// Reads and parses the journal entry at the given path.
+async fn read_entry(path: &Path) -> DriverResult<JournalEntry> {
+    JournalEntry::decode(&fs::read(path).await.map_err(io_error)?)
+}
+
+/// Converts a journal I/O error.
+fn io_error(err: std::io::Error) -> DriverError {
+    DriverError::Any(Box::new(err))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{ops::Range, sync::atomic::AtomicBool};
+
+    use super::*;
+    use crate::{drivers::Driver, key::ObjectPath, StoreConfig};
+
+    /// A driver over an in-memory store, records included, failing with
+    /// network errors while offline.
+    #[derive(Clone)]
+    struct FlakyDriver {
+        inner: Store,
+        offline: Arc<AtomicBool>,
+    }
+
+    impl FlakyDriver {
+        fn check(&self) -> DriverResult<()> {
+            if self.offline.load(Ordering::Relaxed) {
+                Err(DriverError::Network())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Driver for FlakyDriver {
+        async fn read(&self, path: &ObjectPath) -> DriverResult<Bytes> {
+            self.check()?;
+            self.inner.read_bytes(path).await
+        }
+
+        async fn file_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+            self.check()?;
+            self.inner.file_exists(path).await
+        }
+
+        async fn write(&self, path: &ObjectPath, content: Bytes) -> DriverResult<()> {
+            self.check()?;
+            self.inner.write(path, content).await
+        }
+
+        async fn delete(&self, path: &ObjectPath) -> DriverResult<()> {
+            self.check()?;
+            self.inner.delete(path).await
+        }
+
+        async fn delete_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+            self.check()?;
+            self.inner.delete_directory(path).await
+        }
+
+        async fn last_modified(&self, path: &ObjectPath) -> DriverResult<SystemTime> {
+            self.check()?;
+            self.inner.last_modified(path).await
+        }
+
+        async fn read_range(&self, path: &ObjectPath, range: Range<u64>) -> DriverResult<Vec<u8>> {
+            self.check()?;
+            self.inner.read_range(path, range).await
+        }
+
+        async fn list(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+            self.check()?;
+            self.inner.list(path).await
+        }
+    }
+
+    async fn journaled_store(name: &str) -> (JournaledStore, Store, Arc<AtomicBool>, PathBuf) {
+        let inner = StoreConfig::InMem().build().await.unwrap().records();
+        let offline = Arc::new(AtomicBool::new(false));
+        let store = StoreConfig::with_driver(Box::new(FlakyDriver {
+            inner: inner.clone(),
+            offline: offline.clone(),
+        }));
+        let journal = std::env::temp_dir().join(format!(
+            "active-storage-journal-{name}-{}",
+            crate::drivers::unique_id()
+        ));
+
+        let journaled = JournaledStore::new(store, &journal).await.unwrap();
+        (journaled, inner, offline, journal)
+    }
+
+    #[test]
+    fn can_encode_entries() {
+        let entry = JournalEntry {
+            mutation: Mutation::Write {
+                path: PathBuf::from("foo").join("bar baz.txt"),
+                content: Bytes::from_static(b"line 1\nline 2"),
+            },
+            journaled_at: UNIX_EPOCH + Duration::from_nanos(1_700_000_000_123_456_789),
+        };
+        assert_eq!(JournalEntry::decode(&entry.encode()).unwrap(), entry);
+
+        let entry = JournalEntry {
+            mutation: Mutation::DeleteDirectory(PathBuf::from("foo")),
+            journaled_at: UNIX_EPOCH,
+        };
+        assert_eq!(JournalEntry::decode(&entry.encode()).unwrap(), entry);
+
+        assert!(JournalEntry::decode(b"unknown 0 3\nfoo").is_err());
+    }
+
+    #[tokio::test]
+    async fn can_replay_journaled_mutations() {
+        let (journaled, inner, offline, journal) = journaled_store("replay").await;
+
+        journaled
+            .write(Path::new("online.txt"), "online")
+            .await
+            .unwrap();
+        assert!(journaled.pending().await.unwrap().is_empty());
+
+        offline.store(true, Ordering::Relaxed);
+        journaled
+            .write(Path::new("offline.txt"), "first")
+            .await
+            .unwrap();
+        journaled.delete(Path::new("online.txt")).await.unwrap();
+        journaled
+            .write(Path::new("offline.txt"), "second")
+            .await
+            .unwrap();
+        assert_eq!(journaled.pending().await.unwrap().len(), 3);
+
+        let report = journaled.replay().await.unwrap();
+        assert_eq!(report.applied, 0);
+        assert_eq!(report.pending, 3);
+
+        // the journal survives a restart
+        offline.store(false, Ordering::Relaxed);
+        let journaled = JournaledStore::new(journaled.store().clone(), &journal)
+            .await
+            .unwrap();
+        let report = journaled.replay().await.unwrap();
+        assert_eq!(report.applied, 3);
+        assert_eq!(report.pending, 0);
+        assert!(journaled.pending().await.unwrap().is_empty());
+
+        assert!(!inner.file_exists(Path::new("online.txt")).await.unwrap());
+        assert_eq!(
+            inner
+                .read::<String>(Path::new("offline.txt"))
+                .await
+                .unwrap(),
+            "second"
+        );
+        std::fs::remove_dir_all(&journal).unwrap();
+    }
+
+    #[tokio::test]
+    async fn can_resolve_conflicts() {
+        let (journaled, inner, offline, journal) = journaled_store("conflict").await;
+        let journaled = journaled.with_conflict_handler(|_, _| ConflictResolution::Skip);
+
+        offline.store(true, Ordering::Relaxed);
+        journaled
+            .write(Path::new("file.txt"), "local")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        inner.write(Path::new("file.txt"), "remote").await.unwrap();
+
+        offline.store(false, Ordering::Relaxed);
+        let report = journaled.replay().await.unwrap();
+        assert_eq!(report.skipped, 1);
+        assert_eq!(
+            inner.read::<String>(Path::new("file.txt")).await.unwrap(),
+            "remote"
+        );
+        std::fs::remove_dir_all(&journal).unwrap();
+    }
+}
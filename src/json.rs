@@ -0,0 +1,166 @@
+//! # JSON Module
+//!
+//! The `json` module serializes values to JSON objects with
+//! [`Store::write_json`] and deserializes them back with
+//! [`Store::read_json`].
+//!
+//! The [`Json`] wrapper extends the [`Store::read`] conversions to any
+//! deserializable type, and [`serde_json::Value`] can be read directly.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::{collections::BTreeMap, path::PathBuf};
+//! use active_storage::{json::JsonFormat, StoreConfig};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!     let file_path = PathBuf::from("settings.json");
+//!     let settings = BTreeMap::from([("theme", "dark")]);
+//!
+//!     store
+//!         .write_json_with(file_path.as_path(), &settings, JsonFormat::Pretty)
+//!         .await
+//!         .unwrap();
+//!     let read: BTreeMap<String, String> = store.read_json(file_path.as_path()).await.unwrap();
+//!     assert_eq!(read["theme"], "dark");
+//! }
+//! ```
+//!
+//! [`Store::write_json`]: crate::store::Store::write_json
+//! [`Store::read_json`]: crate::store::Store::read_json
+//! [`Store::read`]: crate::store::Store::read
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    contents::Contents,
+    errors::{DriverError, DriverResult},
+};
+
+/// The magic number prefixing a zstd frame.
+#[cfg(feature = "zstd")]
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Enum representing how a value is serialized to JSON.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum JsonFormat {
+    /// Compact JSON without whitespace.
+    #[default]
+    Compact,
+    /// Indented JSON, easier to read and diff.
+    Pretty,
+    /// Compact JSON compressed with zstd at the given level. Compressed
+    /// objects are detected and decompressed when read.
+    #[cfg(feature = "zstd")]
+    Zstd(i32),
+}
+
+/// Struct wrapping a value read from a JSON object, e.g.
+/// `store.read::<Json<T>>(path)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Json<T>(pub T);
+
+impl<T: DeserializeOwned> TryFrom<Contents> for Json<T> {
+    type Error = DriverError;
+
+    /// Deserializes the JSON contents, decompressing them first when they
+    /// are zstd compressed.
+    fn try_from(contents: Contents) -> DriverResult<Self> {
+        from_slice(&Vec::from(contents)).map(Self)
+    }
+}
+
+impl TryFrom<Contents> for serde_json::Value {
+    type Error = DriverError;
+
+    /// Deserializes the JSON contents into an untyped value.
+    fn try_from(contents: Contents) -> DriverResult<Self> {
+        from_slice(&Vec::from(contents))
+    }
+}
+
+/// Serializes the value to JSON in the given format.
+pub(crate) fn to_vec<T: Serialize + ?Sized>(
+    value: &T,
+    format: JsonFormat,
+) -> DriverResult<Vec<u8>> {
+    let json_error = |err: serde_json::Error| DriverError::Any(Box::new(err));
+    match format {
+        JsonFormat::Compact => serde_json::to_vec(value).map_err(json_error),
+        JsonFormat::Pretty => serde_json::to_vec_pretty(value).map_err(json_error),
+        #[cfg(feature = "zstd")]
+        JsonFormat::Zstd(level) => {
+            let json = serde_json::to_vec(value).map_err(json_error)?;
+            zstd::encode_all(json.as_slice(), level).map_err(|err| DriverError::Any(Box::new(err)))
+        }
+    }
+}
+
+/// Deserializes the JSON content, decompressing it first when it is zstd
+/// compressed.
+pub(crate) fn from_slice<T: DeserializeOwned>(content: &[u8]) -> DriverResult<T> {
+    #[cfg(feature = "zstd")]
+    if content.starts_with(&ZSTD_MAGIC) {
+        let json = zstd::decode_all(content).map_err(|_| DriverError::DecodeError)?;
+        return serde_json::from_slice(&json).map_err(|_| DriverError::DecodeError);
+    }
+
+    serde_json::from_slice(content).map_err(|_| DriverError::DecodeError)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{collections::BTreeMap, path::Path};
+
+    use super::*;
+    use crate::StoreConfig;
+
+    #[tokio::test]
+    async fn can_write_and_read_json() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let path = Path::new("value.json");
+        let value = BTreeMap::from([("a", 1), ("b", 2)]);
+
+        store.write_json(path, &value).await.unwrap();
+        assert_eq!(
+            store.read::<String>(path).await.unwrap(),
+            r#"{"a":1,"b":2}"#
+        );
+        assert_eq!(
+            store
+                .read::<Json<BTreeMap<String, u32>>>(path)
+                .await
+                .unwrap(),
+            Json(BTreeMap::from([("a".to_string(), 1), ("b".to_string(), 2)]))
+        );
+        assert_eq!(store.read::<serde_json::Value>(path).await.unwrap()["b"], 2);
+
+        store
+            .write_json_with(path, &value, JsonFormat::Pretty)
+            .await
+            .unwrap();
+        assert!(store
+            .read::<String>(path)
+            .await
+            .unwrap()
+            .contains("\n  \"a\": 1"));
+
+        #[cfg(feature = "zstd")]
+        {
+            store
+                .write_json_with(path, &value, JsonFormat::Zstd(3))
+                .await
+                .unwrap();
+            let read: BTreeMap<String, u32> = store.read_json(path).await.unwrap();
+            assert_eq!(read.len(), 2);
+        }
+
+        store.write(path, "not json").await.unwrap();
+        assert!(matches!(
+            store.read_json::<serde_json::Value>(path).await,
+            Err(DriverError::DecodeError)
+        ));
+    }
+}
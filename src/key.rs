@@ -0,0 +1,628 @@
+//! # Key Module
+//!
+//! The `key` module defines [`ObjectPath`], the path of an object given to the
+//! [`crate::store::Store`] and the drivers, and [`Key`], the normalized
+//! representation of an object key shared by all the drivers.
+//!
+//! An [`ObjectPath`] always uses `/` as separator, whatever the platform. It
+//! is created from a string, where `\` is a regular character, or from a
+//! [`Path`], split into its components with the platform semantics.
+//!
+//! A [`Key`] always uses `/` as separator, is NFC unicode-normalized and never
+//! contains empty, `.` or `..` segments. Non UTF-8 path bytes are
+//! percent-encoded instead of being rejected, so the same [`Path`] maps to the
+//! same key on disk and on cloud backends. A [`Path`] and a string with the
+//! same UTF-8 spelling always map to the same key.
+//!
+//! When a key is encoded for a backend, a `%` starting what reads as a
+//! percent-encoded sequence (`%` followed by two hex digits) is itself escaped
+//! as `%25`, so two distinct keys never share the same encoded form.
+use std::{
+    fmt,
+    path::{Component, Path, PathBuf},
+};
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::errors::DriverError;
+
+/// Enum representing the backend specific rules a [`Key`] is validated and
+/// encoded against.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyRules {
+    /// Local file system keys. Characters reserved by Windows file systems
+    /// are not allowed.
+    Disk,
+    /// In memory keys, no restriction applies.
+    InMem,
+    /// AWS S3 object keys, limited to 1024 bytes.
+    AwsS3,
+    /// Azure blob names, limited to 1024 characters and must not end with a
+    /// dot.
+    Azure,
+}
+
+impl KeyRules {
+    /// The maximum key length, in bytes for S3 and in characters for Azure.
+    const fn max_length(self) -> Option<usize> {
+        match self {
+            Self::AwsS3 | Self::Azure => Some(1024),
+            Self::Disk | Self::InMem => None,
+        }
+    }
+
+    /// Returns `true` when the character can't be stored as is by the backend
+    /// and must be percent-encoded.
+    const fn must_encode(self, c: char) -> bool {
+        match self {
+            Self::Disk => {
+                c.is_ascii_control() || matches!(c, '<' | '>' | ':' | '"' | '\\' | '|' | '?' | '*')
+            }
+            Self::AwsS3 | Self::Azure => c.is_ascii_control() || c == '\\',
+            Self::InMem => false,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum KeyError {
+    #[error("key is empty")]
+    Empty,
+
+    #[error("key segment `{0}` is not allowed")]
+    InvalidSegment(String),
+
+    #[error("key length exceeds the maximum of {max}")]
+    TooLong { max: usize },
+
+    #[error("key must not end with `{0}`")]
+    InvalidSuffix(char),
+}
+
+impl From<KeyError> for DriverError {
+    fn from(_: KeyError) -> Self {
+        Self::InvalidPath
+    }
+}
+
+/// Struct representing a normalized object key.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Key(String);
+
+impl Key {
+    /// Creates a new [`Key`] from a `/` separated string.
+    ///
+    /// Leading, trailing and repeated separators are removed and `.` segments
+    /// are dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the key is empty or contains a `..` segment.
+    pub fn new(key: &str) -> Result<Self, KeyError> {
+        let mut segments = Vec::new();
+        for segment in key.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." => return Err(KeyError::InvalidSegment(segment.to_string())),
+                segment => segments.push(segment.nfc().collect::<String>()),
+            }
+        }
+
+        if segments.is_empty() {
+            return Err(KeyError::Empty);
+        }
+
+        Ok(Self(segments.join("/")))
+    }
+
+    /// Creates a new [`Key`] from a file system path.
+    ///
+    /// Root and prefix components are ignored, and bytes that are not valid
+    /// UTF-8 are percent-encoded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the path has no normal components or contains a
+    /// `..` component.
+    pub fn from_path(path: &Path) -> Result<Self, KeyError> {
+        ObjectPath::from(path).to_key()
+    }
+
+    /// Creates a new [`Key`] prefix from a directory path. Unlike
+    /// [`Key::from_path`], an empty path is allowed and returns `None`, which
+    /// means the storage root.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the path contains a `..` component.
+    pub fn prefix_from_path(path: &Path) -> Result<Option<Self>, KeyError> {
+        ObjectPath::from(path).to_prefix()
+    }
+
+    /// Returns the key as a `/` separated string.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns the key as a relative [`PathBuf`].
+    #[must_use]
+    pub fn to_path_buf(&self) -> PathBuf {
+        self.0.split('/').collect()
+    }
+
+    /// Validates the key against the given backend rules.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the key can't be stored by the backend.
+    pub fn validate(&self, rules: KeyRules) -> Result<(), KeyError> {
+        if let Some(max) = rules.max_length() {
+            let length = match rules {
+                KeyRules::Azure => self.0.chars().count(),
+                _ => self.0.len(),
+            };
+            if length > max {
+                return Err(KeyError::TooLong { max });
+            }
+        }
+
+        if rules == KeyRules::Azure && self.0.ends_with('.') {
+            return Err(KeyError::InvalidSuffix('.'));
+        }
+
+        Ok(())
+    }
+
+    /// Validates the key and returns its representation for the given
+    /// backend, percent-encoding the characters the backend can't store.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the key can't be stored by the backend.
+    pub fn encode(&self, rules: KeyRules) -> Result<String, KeyError> {
+        let mut encoded = String::with_capacity(self.0.len());
+        for (index, c) in self.0.char_indices() {
+            if rules != KeyRules::InMem && starts_escape(&self.0[index..]) {
+                encoded.push_str("%25");
+            } else if rules.must_encode(c) {
+                let mut buf = [0; 4];
+                for byte in c.encode_utf8(&mut buf).bytes() {
+                    encoded.push_str(&format!("%{byte:02X}"));
+                }
+            } else {
+                encoded.push(c);
+            }
+        }
+
+        let encoded = Self(encoded);
+        encoded.validate(rules)?;
+        Ok(encoded.0)
+    }
+
+    /// Decodes a key returned by a backend, the reverse of [`Key::encode`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the decoded key is not valid.
+    pub fn decode(encoded: &str, rules: KeyRules) -> Result<Self, KeyError> {
+        if rules == KeyRules::InMem {
+            return Self::new(encoded);
+        }
+
+        let mut decoded = String::with_capacity(encoded.len());
+        let mut rest = encoded;
+        while let Some(c) = rest.chars().next() {
+            let byte = starts_escape(rest)
+                .then(|| u8::from_str_radix(&rest[1..3], 16).ok())
+                .flatten();
+            match byte {
+                // only ASCII characters are ever encoded
+                Some(byte) if byte == b'%' || rules.must_encode(char::from(byte)) => {
+                    decoded.push(char::from(byte));
+                    rest = &rest[3..];
+                }
+                _ => {
+                    decoded.push(c);
+                    rest = &rest[c.len_utf8()..];
+                }
+            }
+        }
+        Self::new(&decoded)
+    }
+}
+
+/// Returns `true` when the string starts with a `%` followed by two hex
+/// digits, which reads as a percent-encoded byte.
+fn starts_escape(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    bytes.len() >= 3
+        && bytes[0] == b'%'
+        && bytes[1].is_ascii_hexdigit()
+        && bytes[2].is_ascii_hexdigit()
+}
+
+/// Struct representing the path of an object, with `/` separators whatever
+/// the platform. An empty path is the storage root.
+///
+/// Empty and `.` segments are dropped. `..` segments are kept, and rejected
+/// when the path is converted into a [`Key`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ObjectPath(String);
+
+impl ObjectPath {
+    /// Creates a new [`ObjectPath`] from a `/` separated string.
+    #[must_use]
+    pub fn new(path: &str) -> Self {
+        Self(
+            path.split('/')
+                .filter(|segment| !matches!(*segment, "" | "."))
+                .collect::<Vec<_>>()
+                .join("/"),
+        )
+    }
+
+    /// Returns the path as a `/` separated string.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns `true` when the path is the storage root.
+    #[must_use]
+    pub fn is_root(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the path with the given segments appended.
+    #[must_use]
+    pub fn join<P: Into<Self>>(&self, path: P) -> Self {
+        let path = path.into();
+        match (self.is_root(), path.is_root()) {
+            (true, _) => path,
+            (_, true) => self.clone(),
+            _ => Self(format!("{}/{}", self.0, path.0)),
+        }
+    }
+
+    /// Returns `true` when the path starts with the given path, comparing
+    /// whole segments like [`Path::starts_with`].
+    #[must_use]
+    pub fn starts_with<P: Into<Self>>(&self, base: P) -> bool {
+        self.strip_prefix(base).is_some()
+    }
+
+    /// Returns the path relative to the given path, or `None` when it doesn't
+    /// start with it.
+    #[must_use]
+    pub fn strip_prefix<P: Into<Self>>(&self, base: P) -> Option<Self> {
+        let base = base.into();
+        if base.is_root() {
+            return Some(self.clone());
+        }
+        match self.0.strip_prefix(&base.0)? {
+            "" => Some(Self::default()),
+            rest => rest.strip_prefix('/').map(|rest| Self(rest.to_string())),
+        }
+    }
+
+    /// Returns the path without its last segment, or `None` for the root.
+    #[must_use]
+    pub fn parent(&self) -> Option<Self> {
+        if self.is_root() {
+            return None;
+        }
+        Some(
+            self.0
+                .rsplit_once('/')
+                .map_or_else(Self::default, |(parent, _)| Self(parent.to_string())),
+        )
+    }
+
+    /// Returns the last segment of the path, or `None` for the root.
+    #[must_use]
+    pub fn file_name(&self) -> Option<&str> {
+        self.0.rsplit('/').next().filter(|name| !name.is_empty())
+    }
+
+    /// Returns the path as a relative [`PathBuf`], with the platform
+    /// separators.
+    #[must_use]
+    pub fn to_path_buf(&self) -> PathBuf {
+        self.0.split('/').collect()
+    }
+
+    /// Converts the path into a [`Key`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the path is the root or contains a `..` segment.
+    pub fn to_key(&self) -> Result<Key, KeyError> {
+        Key::new(&self.0)
+    }
+
+    /// Converts the path into a [`Key`] prefix. Unlike
+    /// [`ObjectPath::to_key`], the root is allowed and returns `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the path contains a `..` segment.
+    pub fn to_prefix(&self) -> Result<Option<Key>, KeyError> {
+        match self.to_key() {
+            Ok(key) => Ok(Some(key)),
+            Err(KeyError::Empty) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl fmt::Display for ObjectPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for ObjectPath {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for ObjectPath {
+    fn from(path: &str) -> Self {
+        Self::new(path)
+    }
+}
+
+impl From<String> for ObjectPath {
+    fn from(path: String) -> Self {
+        Self::new(&path)
+    }
+}
+
+impl From<&String> for ObjectPath {
+    fn from(path: &String) -> Self {
+        Self::new(path)
+    }
+}
+
+/// Root and prefix components are ignored, and bytes that are not valid UTF-8
+/// are percent-encoded. A valid UTF-8 path maps to the same [`ObjectPath`] as
+/// the same string, so a path returned by a listing can be given back as a
+/// [`Path`] or a string alike.
+impl From<&Path> for ObjectPath {
+    fn from(path: &Path) -> Self {
+        let segments = path
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(segment) => {
+                    Some(percent_encode_invalid_utf8(segment.as_encoded_bytes()))
+                }
+                Component::ParentDir => Some("..".to_string()),
+                Component::Prefix(_) | Component::RootDir | Component::CurDir => None,
+            })
+            .collect::<Vec<_>>();
+        Self::new(&segments.join("/"))
+    }
+}
+
+impl From<&PathBuf> for ObjectPath {
+    fn from(path: &PathBuf) -> Self {
+        Self::from(path.as_path())
+    }
+}
+
+impl From<PathBuf> for ObjectPath {
+    fn from(path: PathBuf) -> Self {
+        Self::from(path.as_path())
+    }
+}
+
+impl From<&Self> for ObjectPath {
+    fn from(path: &Self) -> Self {
+        path.clone()
+    }
+}
+
+impl From<&Key> for ObjectPath {
+    fn from(key: &Key) -> Self {
+        Self(key.as_str().to_string())
+    }
+}
+
+/// Converts the given bytes into a string, percent-encoding the byte sequences
+/// that are not valid UTF-8.
+///
+/// Valid UTF-8 is kept as is, so the non UTF-8 path `\xff` maps to the same
+/// key as the path spelled `%FF`.
+fn percent_encode_invalid_utf8(bytes: &[u8]) -> String {
+    let mut result = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        result.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            result.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    result
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for Key {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<&str> for Key {
+    type Error = KeyError;
+
+    fn try_from(key: &str) -> Result<Self, Self::Error> {
+        Self::new(key)
+    }
+}
+
+impl TryFrom<&Path> for Key {
+    type Error = KeyError;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        Self::from_path(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn can_normalize_keys() {
+        assert_eq!(
+            Key::new("/foo//bar/./baz/").unwrap().as_str(),
+            "foo/bar/baz"
+        );
+        assert_eq!(
+            Key::from_path(&PathBuf::from("/foo").join("bar.txt"))
+                .unwrap()
+                .as_str(),
+            "foo/bar.txt"
+        );
+        // decomposed `é` is normalized to its composed form
+        assert_eq!(
+            Key::new("cafe\u{301}").unwrap(),
+            Key::new("caf\u{e9}").unwrap()
+        );
+    }
+
+    #[test]
+    fn can_normalize_object_paths() {
+        assert_eq!(ObjectPath::new("/foo//./bar/").as_str(), "foo/bar");
+        assert_eq!(
+            ObjectPath::from("foo").join("bar/baz.txt").as_str(),
+            "foo/bar/baz.txt"
+        );
+        assert_eq!(
+            ObjectPath::from(PathBuf::from("/foo").join("..").join("bar.txt")).as_str(),
+            "foo/../bar.txt"
+        );
+        // strings are object keys, so `\` is not a separator on any platform
+        assert_eq!(ObjectPath::from("foo\\bar").file_name(), Some("foo\\bar"));
+        assert!(ObjectPath::from("/").is_root());
+        assert_eq!(ObjectPath::from("").to_prefix(), Ok(None));
+        assert!(ObjectPath::from("foo/../bar").to_key().is_err());
+    }
+
+    #[test]
+    fn can_navigate_object_paths() {
+        let path = ObjectPath::new("docs/sub/a.txt");
+        assert!(path.starts_with("docs"));
+        assert!(path.starts_with(""));
+        assert!(!path.starts_with("doc"));
+        assert_eq!(
+            path.strip_prefix("docs"),
+            Some(ObjectPath::new("sub/a.txt"))
+        );
+        assert_eq!(
+            path.strip_prefix("docs/sub/a.txt"),
+            Some(ObjectPath::default())
+        );
+        assert_eq!(path.strip_prefix("other"), None);
+        assert_eq!(path.parent(), Some(ObjectPath::new("docs/sub")));
+        assert_eq!(
+            ObjectPath::new("a.txt").parent(),
+            Some(ObjectPath::default())
+        );
+        assert_eq!(ObjectPath::default().parent(), None);
+    }
+
+    #[test]
+    fn can_reject_invalid_keys() {
+        assert_eq!(Key::new("/"), Err(KeyError::Empty));
+        assert_eq!(
+            Key::new("foo/../bar"),
+            Err(KeyError::InvalidSegment("..".to_string()))
+        );
+        assert_eq!(
+            Key::new(&"a".repeat(1025))
+                .unwrap()
+                .validate(KeyRules::AwsS3),
+            Err(KeyError::TooLong { max: 1024 })
+        );
+        assert_eq!(
+            Key::new("foo.").unwrap().validate(KeyRules::Azure),
+            Err(KeyError::InvalidSuffix('.'))
+        );
+    }
+
+    #[test]
+    fn can_encode_keys() {
+        let key = Key::new("foo/a:b\u{1}").unwrap();
+        assert_eq!(key.encode(KeyRules::Disk).unwrap(), "foo/a%3Ab%01");
+        assert_eq!(key.encode(KeyRules::AwsS3).unwrap(), "foo/a:b%01");
+        assert_eq!(key.encode(KeyRules::InMem).unwrap(), "foo/a:b\u{1}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn can_encode_non_utf8_paths() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let path = Path::new(OsStr::from_bytes(b"foo/b\xffr"));
+        assert_eq!(Key::from_path(path).unwrap().as_str(), "foo/b%FFr");
+        assert_eq!(
+            Key::from_path(Path::new(OsStr::from_bytes(b"\xff"))),
+            Key::from_path(Path::new("%FF"))
+        );
+    }
+
+    #[test]
+    fn can_encode_without_collisions() {
+        let keys =
+            ["a:b", "a%3Ab", "a%253Ab", "50%.txt", "%FF", "a%:b"].map(|key| Key::new(key).unwrap());
+        for rules in [KeyRules::Disk, KeyRules::AwsS3, KeyRules::Azure] {
+            let encoded = keys
+                .iter()
+                .map(|key| key.encode(rules).unwrap())
+                .collect::<std::collections::BTreeSet<_>>();
+            assert_eq!(encoded.len(), keys.len());
+            for key in &keys {
+                assert_eq!(
+                    &Key::decode(&key.encode(rules).unwrap(), rules).unwrap(),
+                    key
+                );
+            }
+        }
+        assert_eq!(
+            Key::new("a%3Ab").unwrap().encode(KeyRules::Disk).unwrap(),
+            "a%253Ab"
+        );
+        assert_eq!(
+            Key::new("50%.txt").unwrap().encode(KeyRules::Disk).unwrap(),
+            "50%.txt"
+        );
+    }
+
+    #[test]
+    fn can_convert_paths_and_strings_alike() {
+        for name in ["a%20b.txt", "docs/%FF/50%.txt", "a%253Ab", "caf\u{e9}"] {
+            let from_path = ObjectPath::from(Path::new(name));
+            assert_eq!(from_path, ObjectPath::from(name));
+            assert_eq!(ObjectPath::from(from_path.to_path_buf()), from_path);
+
+            let key = from_path.to_key().unwrap();
+            assert_eq!(key, Key::new(name).unwrap());
+            assert_eq!(ObjectPath::from(&key), from_path);
+            assert_eq!(Key::from_path(&key.to_path_buf()).unwrap(), key);
+            for rules in [KeyRules::Disk, KeyRules::AwsS3, KeyRules::Azure] {
+                assert_eq!(
+                    Key::decode(&key.encode(rules).unwrap(), rules),
+                    Ok(key.clone())
+                );
+            }
+        }
+    }
+}
@@ -12,6 +12,14 @@
 //! * [Disk](./examples/disk.rs)
 //! * [In Memory](./examples/in_memory.rs)
 //! * [AWS S3](./examples/aws_s3.rs) - Requires enabling the `aws_s3` feature.
+//! * HTTP - Requires enabling the `http` feature. Reads and writes files with
+//!   plain HTTP requests, e.g. presigned URLs.
+//!
+//! ## WebAssembly
+//!
+//! With `default-features = false` and the `inmem` and `http` features, the
+//! crate compiles for `wasm32-unknown-unknown`, so front-end and back-end code
+//! can share the same [`store::Store`] abstraction.
 //!
 //! ## Examples
 //!
@@ -28,11 +36,82 @@
 //! # }
 //! ```
 
-mod contents;
+#[cfg(feature = "archive")]
+pub mod archive;
+#[cfg(feature = "backup")]
+pub mod backup;
+#[cfg(feature = "blobs")]
+pub mod blobs;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod clock;
+pub mod contents;
+pub mod correlation;
+#[cfg(feature = "dedup")]
+pub mod dedup;
+pub mod driver_conformance;
 pub mod drivers;
+#[cfg(feature = "erasure")]
+pub mod erasure;
 pub mod errors;
+pub mod filename;
+#[cfg(feature = "gateway")]
+pub mod gateway;
+pub mod health;
+#[cfg(any(
+    feature = "aws_s3",
+    feature = "dedup",
+    feature = "disk_signer",
+    feature = "erasure",
+    feature = "gateway",
+    feature = "history",
+    feature = "signing",
+    feature = "tokens"
+))]
+mod hex;
+pub mod image;
+#[cfg(feature = "inventory")]
+pub mod inventory;
+#[cfg(feature = "jobs")]
+pub mod jobs;
+#[cfg(feature = "journal")]
+pub mod journal;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod key;
+#[cfg(any(
+    feature = "disk_signer",
+    feature = "erasure",
+    feature = "gateway",
+    feature = "signing",
+    feature = "tokens"
+))]
+mod mac;
+#[cfg(feature = "migration")]
+pub mod migration;
 pub mod multi_store;
+#[cfg(feature = "rest")]
+pub mod rest;
+pub mod scanner;
+pub mod serve;
+#[cfg(feature = "tower")]
+pub mod service;
+#[cfg(feature = "signing")]
+pub mod signing;
+pub mod stats;
 pub mod store;
+pub mod tempfile;
+pub mod tenant;
+#[cfg(all(feature = "test-util", any(feature = "aws_s3", feature = "azure")))]
+pub mod test_support;
+pub mod text;
+#[cfg(feature = "tokens")]
+pub mod tokens;
+pub mod transfer;
+pub mod upload;
+pub mod validators;
+#[cfg(feature = "watch")]
+pub mod watch;
 
 /// The [`StoreConfig`] enum represents configuration options for building a
 /// storage system. It includes different variants for various storage options,
@@ -52,6 +131,12 @@ pub mod store;
 ///
 /// - `Azure`: Azure storage variant. This variant is available when the `azure`
 ///   feature is enabled. It includes a configuration parameter.
+///
+/// - `Http`: HTTP storage variant, e.g. presigned URLs used from a browser.
+///   This variant is available when the `http` feature is enabled. It includes
+///   a configuration parameter.
+#[derive(Clone)]
+#[allow(clippy::large_enum_variant)]
 pub enum StoreConfig {
     #[cfg(feature = "inmem")]
     InMem(),
@@ -61,6 +146,8 @@ pub enum StoreConfig {
     AwsS3(drivers::aws_s3::Config),
     #[cfg(feature = "azure")]
     Azure(drivers::azure::Config),
+    #[cfg(feature = "http")]
+    Http(drivers::http::Config),
 }
 
 /// `StoreConfig` represents the configuration for creating a [`store::Store`]
@@ -88,8 +175,37 @@ impl StoreConfig {
     ///
     /// Returns a [`errors::DriverResult`] when could not initialize the driver
     /// store
-    #[allow(clippy::unused_async)]
     pub async fn build(self) -> errors::DriverResult<store::Store> {
+        Ok(store::Store::new(self.build_driver().await?))
+    }
+
+    /// Builds a [`store::Store`] instance whose driver is built on first use
+    /// instead, so applications can boot while the backend is briefly
+    /// unreachable. A failed initialization is retried by the next operation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::path::PathBuf;
+    /// use active_storage::StoreConfig;
+    ///
+    /// async fn example() {
+    ///     let inmem_driver = StoreConfig::InMem().build_lazy();
+    ///     let file_path = PathBuf::from("test.txt");
+    ///     inmem_driver
+    ///         .write(file_path.as_path(), "my content")
+    ///         .await
+    ///         .unwrap();
+    /// }
+    /// ```
+    #[must_use]
+    pub fn build_lazy(self) -> store::Store {
+        store::Store::new(Box::new(drivers::lazy::LazyDriver::new(self)))
+    }
+
+    /// Builds the driver of the configured storage type.
+    #[allow(clippy::unused_async)]
+    pub(crate) async fn build_driver(self) -> errors::DriverResult<Box<dyn drivers::Driver>> {
         let driver = match self {
             #[cfg(feature = "inmem")]
             Self::InMem() => {
@@ -107,9 +223,13 @@ impl StoreConfig {
             Self::Azure(config) => {
                 Box::new(drivers::azure::AzureDriver::new(config)) as Box<dyn drivers::Driver>
             }
+            #[cfg(feature = "http")]
+            Self::Http(config) => {
+                Box::new(drivers::http::HttpDriver::new(config)) as Box<dyn drivers::Driver>
+            }
         };
 
-        Ok(store::Store::new(driver))
+        Ok(driver)
     }
 
     /// Creates a [`store::Store`] instance with the provided storage driver.
@@ -0,0 +1,19 @@
+//! # MAC Module
+//!
+//! The `mac` module computes the HMAC-SHA256 signatures of the tokens, ids
+//! and requests signed by the crate.
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Returns a HMAC-SHA256 keyed with the secret and fed with the given parts,
+/// separated by new lines.
+pub(crate) fn hmac_sha256(secret: &[u8], parts: &[&[u8]]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any size");
+    for (index, part) in parts.iter().enumerate() {
+        if index > 0 {
+            mac.update(b"\n");
+        }
+        mac.update(part);
+    }
+    mac
+}
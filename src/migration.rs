@@ -0,0 +1,479 @@
+//! # Migration Module
+//!
+//! The `migration` module defines [`Migration`], which moves the files under
+//! a prefix from one store to another, e.g. when moving to another provider,
+//! with the usual playbook for minimal downtime:
+//!
+//! 1. a full copy pass, while the application keeps writing to the source;
+//! 2. delta passes copying the files created or changed since the previous
+//!    pass, until a pass finds nothing left to copy;
+//! 3. once the writes to the source are stopped, a last delta pass and a verify
+//!    pass comparing the content of every file, before the application is
+//!    switched to the target.
+//!
+//! A file is copied again when it is missing on the target, its size differs,
+//! or it was modified on the source after its copy was written. `ETag`s are
+//! not compared, since providers compute them differently. Every pass is
+//! recorded in a [`MigrationReport`], which renders as JSON with
+//! [`MigrationReport::to_json`].
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::Path;
+//! use active_storage::{migration::Migration, StoreConfig};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let source = StoreConfig::InMem().build().await.unwrap();
+//!     let target = StoreConfig::InMem().build().await.unwrap();
+//!     source.write(Path::new("docs/a.txt"), "a").await.unwrap();
+//!
+//!     let migration = Migration::new(source, target).with_prefix("docs");
+//!     let report = migration.run(3).await.unwrap();
+//!     assert!(report.is_verified());
+//!     println!("{}", report.to_json());
+//! }
+//! ```
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use futures::{stream, StreamExt};
+
+use crate::{
+    clock,
+    errors::{DriverError, DriverResult},
+    key::ObjectPath,
+    store::Store,
+    transfer::TransferOptions,
+};
+
+/// Enum representing the kind of a migration pass.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PassKind {
+    /// The first pass, copying every file.
+    Copy,
+    /// A pass copying the files created or changed since the previous pass.
+    Delta,
+    /// A pass comparing the content of every file on both stores.
+    Verify,
+}
+
+impl PassKind {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Copy => "copy",
+            Self::Delta => "delta",
+            Self::Verify => "verify",
+        }
+    }
+}
+
+/// Struct representing the outcome of a single migration pass. Paths are
+/// relative to the store root, in key order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PassReport {
+    /// The kind of the pass.
+    pub kind: PassKind,
+    /// The time the pass started.
+    pub started_at: SystemTime,
+    /// The time the pass completed.
+    pub finished_at: SystemTime,
+    /// The number of files found on the source.
+    pub scanned: usize,
+    /// The files copied to the target.
+    pub copied: Vec<ObjectPath>,
+    /// The number of copied bytes.
+    pub bytes: u64,
+    /// The files deleted from the target as they are gone from the source,
+    /// see [`Migration::with_deletions`].
+    pub deleted: Vec<ObjectPath>,
+    /// The files whose content differs on the target, found by a verify pass.
+    pub mismatched: Vec<ObjectPath>,
+    /// The files found only on the target, when deletions are disabled.
+    pub extraneous: Vec<ObjectPath>,
+    /// The files that could not be copied or compared, with the failure
+    /// message.
+    pub failed: BTreeMap<ObjectPath, String>,
+}
+
+impl PassReport {
+    fn new(kind: PassKind) -> Self {
+        let now = clock::now();
+        Self {
+            kind,
+            started_at: now,
+            finished_at: now,
+            scanned: 0,
+            copied: Vec::new(),
+            bytes: 0,
+            deleted: Vec::new(),
+            mismatched: Vec::new(),
+            extraneous: Vec::new(),
+            failed: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the JSON rendering of the pass.
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        let paths =
+            |paths: &[ObjectPath]| paths.iter().map(ToString::to_string).collect::<Vec<_>>();
+        serde_json::json!({
+            "kind": self.kind.as_str(),
+            "started_at": seconds(self.started_at),
+            "finished_at": seconds(self.finished_at),
+            "scanned": self.scanned,
+            "copied": paths(&self.copied),
+            "bytes": self.bytes,
+            "deleted": paths(&self.deleted),
+            "mismatched": paths(&self.mismatched),
+            "extraneous": paths(&self.extraneous),
+            "failed": self
+                .failed
+                .iter()
+                .map(|(path, error)| (path.to_string(), error.clone()))
+                .collect::<BTreeMap<_, _>>(),
+        })
+    }
+}
+
+/// Struct representing the passes of a migration, in order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// The completed passes.
+    pub passes: Vec<PassReport>,
+}
+
+impl MigrationReport {
+    /// Returns true when the last pass is a verify pass that found the
+    /// target identical to the source.
+    #[must_use]
+    pub fn is_verified(&self) -> bool {
+        self.passes.last().is_some_and(|pass| {
+            pass.kind == PassKind::Verify
+                && pass.copied.is_empty()
+                && pass.mismatched.is_empty()
+                && pass.extraneous.is_empty()
+                && pass.failed.is_empty()
+        })
+    }
+
+    /// Returns the JSON rendering of the report.
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "verified": self.is_verified(),
+            "passes": self.passes.iter().map(PassReport::to_json).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Enum representing what a pass did with a single file.
+enum Outcome {
+    Unchanged,
+    Copied(u64),
+    Mismatched,
+}
+
+/// Struct moving the files under a prefix from a source store to a target
+/// store.
+#[derive(Clone)]
+pub struct Migration {
+    source: Store,
+    target: Store,
+    prefix: ObjectPath,
+    deletions: bool,
+    concurrency: usize,
+    transfer: TransferOptions,
+}
+
+impl Migration {
+    /// Creates a migration of every file of the source store to the target
+    /// store, 8 files at a time.
+    #[must_use]
+    pub fn new(source: Store, target: Store) -> Self {
+        Self {
+            source,
+            target,
+            prefix: ObjectPath::default(),
+            deletions: false,
+            concurrency: 8,
+            transfer: TransferOptions::default(),
+        }
+    }
+
+    /// Migrates only the files under the given prefix.
+    #[must_use]
+    pub fn with_prefix(mut self, prefix: impl Into<ObjectPath>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Deletes the files of the target that are gone from the source in the
+    /// delta passes. When disabled, they are only reported as extraneous.
+    #[must_use]
+    pub const fn with_deletions(mut self, deletions: bool) -> Self {
+        self.deletions = deletions;
+        self
+    }
+
+    /// Sets how many files are transferred at once.
+    #[must_use]
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Sets the options of the file transfers, e.g. a bandwidth limit shared
+    /// with the application.
+    #[must_use]
+    pub fn with_transfer_options(mut self, options: TransferOptions) -> Self {
+        self.transfer = options;
+        self
+    }
+
+    /// Runs a copy pass, up to the given number of delta passes, stopping
+    /// early once a pass copies nothing, and a verify pass.
+    ///
+    /// For the cutover, stop the writes to the source and call
+    /// [`Self::delta_pass`] and [`Self::verify_pass`] once more.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the files of either store could not be listed.
+    pub async fn run(&self, delta_passes: usize) -> DriverResult<MigrationReport> {
+        let mut report = MigrationReport {
+            passes: vec![self.copy_pass().await?],
+        };
+        for _ in 0..delta_passes {
+            let pass = self.delta_pass().await?;
+            let converged = pass.copied.is_empty() && pass.deleted.is_empty();
+            report.passes.push(pass);
+            if converged {
+                break;
+            }
+        }
+        report.passes.push(self.verify_pass().await?);
+        Ok(report)
+    }
+
+    /// Copies the files missing or outdated on the target. On an empty
+    /// target, every file is copied.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the files of either store could not be listed.
+    pub async fn copy_pass(&self) -> DriverResult<PassReport> {
+        self.pass(PassKind::Copy).await
+    }
+
+    /// Copies the files created or changed on the source since the previous
+    /// pass, and handles the files gone from the source.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the files of either store could not be listed.
+    pub async fn delta_pass(&self) -> DriverResult<PassReport> {
+        self.pass(PassKind::Delta).await
+    }
+
+    /// Compares the content of every file on both stores. Files missing on
+    /// the target are copied and reported, files whose content differs are
+    /// reported as mismatched and left for the next delta pass.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the files of either store could not be listed.
+    pub async fn verify_pass(&self) -> DriverResult<PassReport> {
+        self.pass(PassKind::Verify).await
+    }
+
+    async fn pass(&self, kind: PassKind) -> DriverResult<PassReport> {
+        let mut report = PassReport::new(kind);
+        let files = self.source.list(&self.prefix).await?;
+        let target_files = self
+            .target
+            .list(&self.prefix)
+            .await?
+            .into_iter()
+            .collect::<BTreeSet<_>>();
+        report.scanned = files.len();
+
+        let source_files = files.iter().cloned().collect::<BTreeSet<_>>();
+        let mut outcomes = stream::iter(files)
+            .map(|file| async move {
+                let outcome = self.migrate_file(&file, kind).await;
+                (file, outcome)
+            })
+            .buffer_unordered(self.concurrency)
+            .collect::<Vec<_>>()
+            .await;
+        outcomes.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        for (file, outcome) in outcomes {
+            match outcome {
+                Ok(Outcome::Unchanged) => {}
+                Ok(Outcome::Copied(bytes)) => {
+                    report.copied.push(file);
+                    report.bytes += bytes;
+                }
+                Ok(Outcome::Mismatched) => report.mismatched.push(file),
+                Err(error) => {
+                    report.failed.insert(file, error.to_string());
+                }
+            }
+        }
+
+        for file in target_files.difference(&source_files) {
+            if !self.deletions || kind != PassKind::Delta {
+                report.extraneous.push(file.clone());
+                continue;
+            }
+            match self.target.delete_if_exists(file).await {
+                Ok(_) => report.deleted.push(file.clone()),
+                Err(error) => {
+                    report.failed.insert(file.clone(), error.to_string());
+                }
+            }
+        }
+
+        report.finished_at = clock::now();
+        Ok(report)
+    }
+
+    async fn migrate_file(&self, file: &ObjectPath, kind: PassKind) -> DriverResult<Outcome> {
+        let source = self
+            .source
+            .stat(file)
+            .await?
+            .ok_or(DriverError::ResourceNotFound)?;
+        let Some(target) = self.target.stat(file).await? else {
+            return self.copy_file(file).await;
+        };
+
+        if kind == PassKind::Verify {
+            return if self.same_content(file).await? {
+                Ok(Outcome::Unchanged)
+            } else {
+                Ok(Outcome::Mismatched)
+            };
+        }
+        if source.size != target.size || source.last_modified > target.last_modified {
+            return self.copy_file(file).await;
+        }
+        Ok(Outcome::Unchanged)
+    }
+
+    async fn copy_file(&self, file: &ObjectPath) -> DriverResult<Outcome> {
+        let chunks = self.source.read_stream(file, &self.transfer).await?;
+        let bytes = self
+            .target
+            .write_stream(file, chunks, &self.transfer)
+            .await?;
+        Ok(Outcome::Copied(bytes))
+    }
+
+    /// Compares the file on both stores part by part. Both streams use the
+    /// same part size, so their parts line up.
+    async fn same_content(&self, file: &ObjectPath) -> DriverResult<bool> {
+        let mut source = self.source.read_stream(file, &self.transfer).await?;
+        let mut target = self.target.read_stream(file, &self.transfer).await?;
+        loop {
+            match (source.next().await, target.next().await) {
+                (None, None) => return Ok(true),
+                (Some(source), Some(target)) => {
+                    if source? != target? {
+                        return Ok(false);
+                    }
+                }
+                _ => return Ok(false),
+            }
+        }
+    }
+}
+
+/// Returns the seconds since the Unix epoch of the given time.
+fn seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    use super::*;
+    use crate::StoreConfig;
+
+    #[tokio::test]
+    async fn can_migrate_with_delta_passes() {
+        let source = StoreConfig::InMem().build().await.unwrap();
+        let target = StoreConfig::InMem().build().await.unwrap();
+        source.write(Path::new("docs/a.txt"), "a").await.unwrap();
+        source.write(Path::new("docs/b.txt"), "b").await.unwrap();
+        source.write(Path::new("other.txt"), "other").await.unwrap();
+        let migration = Migration::new(source.clone(), target.clone())
+            .with_prefix("docs")
+            .with_deletions(true);
+
+        let copy = migration.copy_pass().await.unwrap();
+        assert_eq!(copy.scanned, 2);
+        assert_eq!(
+            copy.copied,
+            vec![ObjectPath::new("docs/a.txt"), ObjectPath::new("docs/b.txt")]
+        );
+        assert_eq!(copy.bytes, 2);
+        assert!(!target.file_exists(Path::new("other.txt")).await.unwrap());
+
+        source.write(Path::new("docs/b.txt"), "bb").await.unwrap();
+        source.write(Path::new("docs/c.txt"), "c").await.unwrap();
+        source.delete(Path::new("docs/a.txt")).await.unwrap();
+        let delta = migration.delta_pass().await.unwrap();
+        assert_eq!(
+            delta.copied,
+            vec![ObjectPath::new("docs/b.txt"), ObjectPath::new("docs/c.txt")]
+        );
+        assert_eq!(delta.deleted, vec![ObjectPath::new("docs/a.txt")]);
+        assert!(migration.delta_pass().await.unwrap().copied.is_empty());
+
+        let report = migration.run(3).await.unwrap();
+        assert!(report.is_verified());
+        assert_eq!(
+            report
+                .passes
+                .iter()
+                .map(|pass| pass.kind)
+                .collect::<Vec<_>>(),
+            vec![PassKind::Copy, PassKind::Delta, PassKind::Verify]
+        );
+        assert_eq!(report.to_json()["passes"][2]["kind"], "verify");
+        assert_eq!(
+            target
+                .read::<String>(Path::new("docs/b.txt"))
+                .await
+                .unwrap(),
+            "bb"
+        );
+    }
+
+    #[tokio::test]
+    async fn can_report_mismatched_files() {
+        let source = StoreConfig::InMem().build().await.unwrap();
+        let target = StoreConfig::InMem().build().await.unwrap();
+        source.write(Path::new("a.txt"), "source").await.unwrap();
+        target.write(Path::new("a.txt"), "target").await.unwrap();
+        target.write(Path::new("stale.txt"), "stale").await.unwrap();
+
+        let verify = Migration::new(source, target).verify_pass().await.unwrap();
+        assert_eq!(verify.mismatched, vec![ObjectPath::new("a.txt")]);
+        assert_eq!(verify.extraneous, vec![ObjectPath::new("stale.txt")]);
+        assert!(!MigrationReport {
+            passes: vec![verify]
+        }
+        .is_verified());
+    }
+}
@@ -4,6 +4,11 @@
 //! stores, including a primary store and mirrors. It also introduces a `Mirror`
 //! struct for performing mirroring operations across multiple stores.
 //!
+//! With the `aws_s3` feature, [`MultiStore::s3_replicated`] sets up the
+//! disaster recovery pattern of two buckets in different regions: writes are
+//! mirrored to both and [`MultiStore::failover_read`] falls back to the
+//! replica when the primary region fails.
+//!
 //! ## Example
 //!
 //! ```rust
@@ -11,13 +16,13 @@
 #![doc = include_str!("../examples/multi.rs")]
 //! # }
 //! ```
-use std::{
-    collections::{BTreeMap, HashMap},
-    path::Path,
-};
+use std::collections::{BTreeMap, HashMap};
 
 use crate::{
-    errors::{DriverError, MirrorError, MirrorResult},
+    contents::{Contents, IntoBytes},
+    errors::{DriverError, DriverResult, MirrorError, MirrorResult},
+    health::HealthReport,
+    key::ObjectPath,
     store::Store,
 };
 
@@ -30,6 +35,10 @@ pub enum Policy {
     StopOnFailure,
 }
 
+/// The name of the replica store added by [`MultiStore::s3_replicated`].
+#[cfg(feature = "aws_s3")]
+pub const REPLICA_STORE: &str = "replica";
+
 /// Struct representing a [`MultiStore`] that manages multiple stores, including
 /// a primary store and mirrors.
 #[derive(Clone)]
@@ -52,6 +61,7 @@ impl MultiStore {
     /// async fn main() {
     ///     let config = drivers::disk::Config {
     ///         location: PathBuf::from("tmp").join("primary-storage"),
+    ///         public_url: None,
     ///     };
     ///     let disk_driver = StoreConfig::Disk(config).build().await.unwrap();
     ///
@@ -71,6 +81,62 @@ impl MultiStore {
         }
     }
 
+    /// Creates a [`MultiStore`] replicating the primary S3 bucket to a replica
+    /// bucket, usually in another region, added as the [`REPLICA_STORE`]
+    /// store.
+    ///
+    /// Write through [`Self::mirror_stores_from_primary`] to keep both
+    /// buckets in sync, and read with [`Self::failover_read`] to fall back to
+    /// the replica during a regional outage.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use std::path::Path;
+    /// use active_storage::{drivers::aws_s3::Config, multi_store::MultiStore};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let bucket = |bucket: &str, region: &str| Config {
+    ///         bucket: bucket.to_string(),
+    ///         region: region.to_string(),
+    ///         credentials: None,
+    ///         credentials_provider: None,
+    ///         anonymous: false,
+    ///         public_url: None,
+    ///         requester_pays: false,
+    ///         accelerate: false,
+    ///         read_endpoint: None,
+    ///         write_endpoint: None,
+    ///         pool: Default::default(),
+    ///     };
+    ///     let multi_store = MultiStore::s3_replicated(
+    ///         bucket("assets-us", "us-east-1"),
+    ///         bucket("assets-eu", "eu-west-1"),
+    ///     );
+    ///
+    ///     multi_store
+    ///         .mirror_stores_from_primary()
+    ///         .write(Path::new("logo.png"), b"content")
+    ///         .await
+    ///         .unwrap();
+    ///     let logo: Vec<u8> = multi_store.failover_read(Path::new("logo.png")).await.unwrap();
+    /// }
+    /// ```
+    #[cfg(feature = "aws_s3")]
+    #[must_use]
+    pub fn s3_replicated(
+        primary: crate::drivers::aws_s3::Config,
+        replica: crate::drivers::aws_s3::Config,
+    ) -> Self {
+        let store = |config| {
+            crate::StoreConfig::with_driver(Box::new(crate::drivers::aws_s3::AwsS3::new(config)))
+        };
+
+        let mut multi_store = Self::new(store(primary));
+        multi_store.add_stores(HashMap::from([(REPLICA_STORE, store(replica))]));
+        multi_store
+    }
+
     /// Adds a Stores to the [`MultiStore`].
     pub fn add_stores(&mut self, stores: HashMap<&str, Store>) -> &mut Self {
         for (name, stores) in stores {
@@ -119,6 +185,12 @@ impl MultiStore {
         Ok(self)
     }
 
+    /// Returns the added stores, keyed by name.
+    #[cfg(feature = "erasure")]
+    pub(crate) const fn stores(&self) -> &HashMap<String, Store> {
+        &self.stores
+    }
+
     /// Creates a Mirror struct for mirroring operations from the primary store.
     #[must_use]
     pub fn mirror_stores_from_primary(&self) -> Mirror<'_> {
@@ -149,6 +221,51 @@ impl MultiStore {
             stores,
         })
     }
+
+    /// Reads a file from the primary store, falling back to the added stores
+    /// in name order when the primary fails.
+    ///
+    /// A file missing from the primary is not looked up in the other stores,
+    /// since mirrors never hold files the primary lacks.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::ResourceNotFound`] if the primary doesn't hold
+    /// the file, or the error of the last store when every store fails.
+    pub async fn failover_read<T: TryFrom<Contents>>(
+        &self,
+        path: impl Into<ObjectPath>,
+    ) -> DriverResult<T> {
+        let path: &ObjectPath = &path.into();
+        let mut error = match self.primary.read(path).await {
+            Ok(content) => return Ok(content),
+            Err(DriverError::ResourceNotFound) => return Err(DriverError::ResourceNotFound),
+            Err(error) => error,
+        };
+
+        let fallbacks = self.stores.iter().collect::<BTreeMap<_, _>>();
+        for store in fallbacks.values() {
+            match store.read(path).await {
+                Ok(content) => return Ok(content),
+                Err(store_error) => error = store_error,
+            }
+        }
+
+        Err(error)
+    }
+
+    /// Probes the primary store and all the added stores, returning a
+    /// [`HealthReport`] keyed by store name. The primary store is reported
+    /// under the `primary` name.
+    pub async fn health_report(&self) -> HealthReport {
+        let mut stores =
+            BTreeMap::from([("primary".to_string(), self.primary.health_check().await)]);
+        for (name, store) in &self.stores {
+            stores.insert(name.clone(), store.health_check().await);
+        }
+
+        HealthReport { stores }
+    }
 }
 
 /// Struct representing a mirror for mirroring operations across multiple
@@ -172,6 +289,7 @@ impl<'a> Mirror<'a> {
     /// async fn main() {
     ///     let config = drivers::disk::Config {
     ///         location: PathBuf::from("tmp").join("primary-storage"),
+    ///         public_url: None,
     ///     };
     ///     let disk_driver = StoreConfig::Disk(config).build().await.unwrap();
     ///
@@ -190,13 +308,15 @@ impl<'a> Mirror<'a> {
     /// # Errors
     ///
     /// Depend of the mirror policy return operation failure
-    pub async fn write<C>(&self, path: &Path, content: C) -> MirrorResult<()>
+    pub async fn write<C>(&self, path: impl Into<ObjectPath>, content: C) -> MirrorResult<()>
     where
-        C: AsRef<[u8]> + Send,
+        C: IntoBytes + Send,
     {
+        let path: &ObjectPath = &path.into();
+        let content = content.into_bytes();
         let mut error_stores = BTreeMap::new();
         for (name, store) in &self.stores {
-            if let Err(error) = store.write(path, content.as_ref().to_vec()).await {
+            if let Err(error) = store.write(path, content.clone()).await {
                 self.handle_error_policy(name, error, &mut error_stores)?;
             }
         }
@@ -220,6 +340,7 @@ impl<'a> Mirror<'a> {
     /// async fn main() {
     ///     let config = drivers::disk::Config {
     ///         location: PathBuf::from("tmp").join("store-1"),
+    ///         public_url: None,
     ///     };
     ///     let disk_driver = StoreConfig::Disk(config).build().await.unwrap();
     ///
@@ -243,7 +364,8 @@ impl<'a> Mirror<'a> {
     /// # Errors
     ///
     /// Depend of the mirror policy return operation failure
-    pub async fn delete(&self, path: &Path) -> MirrorResult<()> {
+    pub async fn delete(&self, path: impl Into<ObjectPath>) -> MirrorResult<()> {
+        let path: &ObjectPath = &path.into();
         let mut error_stores = BTreeMap::new();
         for (name, store) in &self.stores {
             if let Err(error) = store.delete(path).await {
@@ -270,6 +392,7 @@ impl<'a> Mirror<'a> {
     /// async fn main() {
     ///     let config = drivers::disk::Config {
     ///         location: PathBuf::from("tmp").join("primary-storage"),
+    ///         public_url: None,
     ///     };
     ///     let disk_driver = StoreConfig::Disk(config).build().await.unwrap();
     ///
@@ -293,7 +416,8 @@ impl<'a> Mirror<'a> {
     /// # Errors
     ///
     /// Returns an error if any store fails to delete the directory.
-    pub async fn delete_directory(&self, path: &Path) -> MirrorResult<()> {
+    pub async fn delete_directory(&self, path: impl Into<ObjectPath>) -> MirrorResult<()> {
+        let path: &ObjectPath = &path.into();
         let mut error_stores = BTreeMap::new();
         for (name, store) in &self.stores {
             if let Err(error) = store.delete_directory(path).await {
@@ -310,6 +434,7 @@ impl<'a> Mirror<'a> {
 
     /// Handles the mirroring error policy based on the specified store's
     /// failure.
+    #[allow(clippy::result_large_err)]
     fn handle_error_policy(
         &self,
         store_name: &str,
@@ -332,6 +457,8 @@ impl<'a> Mirror<'a> {
 #[cfg(test)]
 mod tests {
 
+    use std::path::Path;
+
     use super::*;
     use crate::StoreConfig;
 
@@ -397,4 +524,76 @@ mod tests {
             Some("the stores: un-existing 1,un-existing 2 not defined".to_string())
         );
     }
+
+    #[tokio::test]
+    async fn can_failover_read() {
+        let location = std::env::temp_dir().join(format!(
+            "active-storage-failover-{}",
+            crate::drivers::unique_id()
+        ));
+        let primary = StoreConfig::Disk(crate::drivers::disk::Config {
+            location: location.clone(),
+            public_url: None,
+        })
+        .build()
+        .await
+        .unwrap();
+        let mut multi_store = MultiStore::new(primary);
+
+        let replica = StoreConfig::InMem().build().await.unwrap();
+        multi_store.add_stores(HashMap::from([("replica", replica)]));
+        multi_store
+            .mirror_stores_from_primary()
+            .write(Path::new("file.txt"), "content")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            multi_store
+                .failover_read::<String>(Path::new("file.txt"))
+                .await
+                .unwrap(),
+            "content"
+        );
+        assert!(matches!(
+            multi_store
+                .failover_read::<String>(Path::new("missing.txt"))
+                .await,
+            Err(DriverError::ResourceNotFound)
+        ));
+
+        // the primary root turns into a file, failing every read
+        std::fs::remove_dir_all(&location).unwrap();
+        std::fs::write(&location, "").unwrap();
+        assert!(multi_store
+            .primary
+            .read::<String>(Path::new("file.txt"))
+            .await
+            .is_err());
+        assert_eq!(
+            multi_store
+                .failover_read::<String>(Path::new("file.txt"))
+                .await
+                .unwrap(),
+            "content"
+        );
+        std::fs::remove_file(&location).unwrap();
+    }
+
+    #[tokio::test]
+    async fn can_report_health() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let mut multi_store = MultiStore::new(store);
+
+        let store_1 = StoreConfig::InMem().build().await.unwrap();
+        multi_store.add_stores(HashMap::from([("foo", store_1)]));
+
+        let report = multi_store.health_report().await;
+        assert!(report.is_healthy());
+        assert_eq!(
+            report.stores.keys().collect::<Vec<_>>(),
+            vec!["foo", "primary"]
+        );
+        assert!(report.unhealthy_stores().is_empty());
+    }
 }
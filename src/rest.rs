@@ -0,0 +1,433 @@
+//! # Rest Module
+//!
+//! The `rest` module defines [`RestServer`], serving a [`Store`] over a small
+//! REST API, so non-Rust services and scripts can reuse the configured
+//! backends without the provider SDKs.
+//!
+//! Every request must carry an `Authorization: Bearer <token>` header with
+//! the token the server was created with. Keys are the percent-encoded
+//! request path. Written bodies are limited to
+//! [`RestServer::with_max_body_size`] bytes, and read files are streamed:
+//!
+//! | Request          | Operation                                                        |
+//! |------------------|------------------------------------------------------------------|
+//! | `GET /{key}`     | Reads the file, honoring `Range`, `If-None-Match` and `If-Range` |
+//! | `PUT /{key}`     | Writes the request body to the file                              |
+//! | `DELETE /{key}`  | Deletes the file                                                 |
+//! | `GET /{prefix}/` | Lists the files under the prefix as a JSON array                 |
+//!
+//! ## Example
+//!
+//! ```rust
+//! use active_storage::{rest::RestServer, StoreConfig};
+//! use bytes::Bytes;
+//! use http_body_util::{BodyExt, Full};
+//! use hyper::Request;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!     let server = RestServer::new(store, "secret");
+//!
+//!     let request = Request::put("/docs/test.txt")
+//!         .header("Authorization", "Bearer secret")
+//!         .body(Full::new(Bytes::from("my content")))
+//!         .unwrap();
+//!     assert_eq!(server.handle(request).await.status(), 204);
+//!
+//!     let request = Request::get("/docs/")
+//!         .header("Authorization", "Bearer secret")
+//!         .body(Full::new(Bytes::new()))
+//!         .unwrap();
+//!     let body = server.handle(request).await.into_body().collect().await;
+//!     assert_eq!(body.unwrap().to_bytes(), r#"["docs/test.txt"]"#);
+//! }
+//! ```
+use std::{convert::Infallible, future::Future};
+
+use bytes::Bytes;
+use futures::TryStreamExt;
+use http_body_util::{
+    combinators::UnsyncBoxBody, BodyExt, Full, LengthLimitError, Limited, StreamBody,
+};
+use hyper::{
+    body::{Body, Frame, Incoming},
+    header::{self, HeaderValue},
+    server::conn::http1,
+    service::service_fn,
+    Method, Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use percent_encoding::percent_decode_str;
+use tokio::net::TcpListener;
+
+use crate::{
+    errors::{DriverError, DriverResult},
+    key::{Key, ObjectPath},
+    serve::{ByteStream, ServeRequest},
+    store::Store,
+    transfer::CancellationToken,
+};
+
+/// The methods answered on a file key.
+const ALLOWED_METHODS: &str = "GET, PUT, DELETE";
+
+/// The default maximum size of a request body, 64 MiB.
+pub const DEFAULT_MAX_BODY_SIZE: usize = 64 * 1024 * 1024;
+
+/// The body of the responses, streamed for the read files.
+pub type ResponseBody = UnsyncBoxBody<Bytes, DriverError>;
+
+/// Struct representing a server exposing a [`Store`] over REST.
+#[derive(Clone)]
+pub struct RestServer {
+    store: Store,
+    bearer_token: String,
+    max_body_size: usize,
+}
+
+impl RestServer {
+    /// Creates a new [`RestServer`] serving the given store to the requests
+    /// authenticated with the given bearer token.
+    #[must_use]
+    pub fn new<S: Into<String>>(store: Store, bearer_token: S) -> Self {
+        Self {
+            store,
+            bearer_token: bearer_token.into(),
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+        }
+    }
+
+    /// Sets the maximum size of a request body, [`DEFAULT_MAX_BODY_SIZE`] by
+    /// default. Larger bodies are answered with `413 Payload Too Large`.
+    #[must_use]
+    pub const fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Accepts HTTP/1 connections on the listener until the token is
+    /// cancelled, answering each connection on its own task.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a connection could not be accepted.
+    pub async fn serve(
+        self,
+        listener: TcpListener,
+        cancellation: CancellationToken,
+    ) -> std::io::Result<()> {
+        accept_loop(listener, cancellation, move |request| {
+            let server = self.clone();
+            async move { server.handle(request).await }
+        })
+        .await
+    }
+
+    /// Answers a single request, e.g. from a server built with another
+    /// framework.
+    pub async fn handle<B>(&self, request: Request<B>) -> Response<ResponseBody>
+    where
+        B: Body + Send,
+        B::Data: Send,
+        B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        if !self.is_authorized(request.headers().get(header::AUTHORIZATION)) {
+            let mut response = status_response(StatusCode::UNAUTHORIZED);
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            return response;
+        }
+
+        let Ok(raw_path) = percent_decode_str(request.uri().path()).decode_utf8() else {
+            return status_response(StatusCode::BAD_REQUEST);
+        };
+        let is_directory = raw_path.ends_with('/');
+        let path = match Key::new(&raw_path) {
+            Ok(key) => ObjectPath::from(&key),
+            Err(_) if is_directory && raw_path.trim_matches('/').is_empty() => {
+                ObjectPath::default()
+            }
+            Err(_) => return status_response(StatusCode::BAD_REQUEST),
+        };
+
+        let result = match (request.method(), is_directory) {
+            (&Method::GET, true) => self.list(&path).await,
+            (&Method::GET, false) => self.read(&path, request.headers()).await,
+            (&Method::PUT, false) => {
+                match Limited::new(request.into_body(), self.max_body_size)
+                    .collect()
+                    .await
+                {
+                    Ok(body) => self
+                        .store
+                        .write(&path, body.to_bytes())
+                        .await
+                        .map(|()| status_response(StatusCode::NO_CONTENT)),
+                    Err(err) if err.is::<LengthLimitError>() => {
+                        return status_response(StatusCode::PAYLOAD_TOO_LARGE)
+                    }
+                    Err(_) => return status_response(StatusCode::BAD_REQUEST),
+                }
+            }
+            (&Method::DELETE, false) => self
+                .store
+                .delete(&path)
+                .await
+                .map(|()| status_response(StatusCode::NO_CONTENT)),
+            _ => {
+                let mut response = status_response(StatusCode::METHOD_NOT_ALLOWED);
+                let allowed = if is_directory { "GET" } else { ALLOWED_METHODS };
+                response
+                    .headers_mut()
+                    .insert(header::ALLOW, HeaderValue::from_static(allowed));
+                return response;
+            }
+        };
+
+        result.unwrap_or_else(|err| status_response(error_status(&err)))
+    }
+
+    /// Returns `true` when the authorization header carries the bearer token.
+    fn is_authorized(&self, authorization: Option<&HeaderValue>) -> bool {
+        authorization
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|token| constant_time_eq(token.as_bytes(), self.bearer_token.as_bytes()))
+    }
+
+    /// Answers a read, honoring the `Range`, `If-None-Match` and `If-Range`
+    /// headers.
+    async fn read(
+        &self,
+        path: &ObjectPath,
+        headers: &hyper::HeaderMap,
+    ) -> DriverResult<Response<ResponseBody>> {
+        let header_value = |name| headers.get(name).and_then(|value| value.to_str().ok());
+        let served = self
+            .store
+            .serve_with(
+                path,
+                &ServeRequest {
+                    range: header_value(header::RANGE),
+                    if_none_match: header_value(header::IF_NONE_MATCH),
+                    if_range: header_value(header::IF_RANGE),
+                },
+            )
+            .await?;
+
+        let mut response = Response::new(stream_body(served.body));
+        *response.status_mut() =
+            StatusCode::from_u16(served.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        for (name, value) in served.headers {
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                response.headers_mut().insert(name, value);
+            }
+        }
+        Ok(response)
+    }
+
+    /// Answers a listing with the JSON array of the listed keys.
+    async fn list(&self, path: &ObjectPath) -> DriverResult<Response<ResponseBody>> {
+        let keys = self
+            .store
+            .list(path)
+            .await?
+            .iter()
+            .map(|path| path.to_key().map(|key| key.as_str().to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let body = serde_json::to_vec(&keys).map_err(|err| DriverError::Any(Box::new(err)))?;
+
+        let mut response = Response::new(full_body(body));
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        Ok(response)
+    }
+}
+
+/// Accepts HTTP/1 connections on the listener until the token is cancelled,
+/// answering the requests of each connection with the handler on its own
+/// task.
+pub(crate) async fn accept_loop<H, F>(
+    listener: TcpListener,
+    cancellation: CancellationToken,
+    handler: H,
+) -> std::io::Result<()>
+where
+    H: Fn(Request<Incoming>) -> F + Clone + Send + 'static,
+    F: Future<Output = Response<ResponseBody>> + Send,
+{
+    loop {
+        let (stream, _) = tokio::select! {
+            () = cancellation.cancelled() => return Ok(()),
+            accepted = listener.accept() => accepted?,
+        };
+
+        let handler = handler.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |request| {
+                let response = handler(request);
+                async move { Ok::<_, Infallible>(response.await) }
+            });
+            // a broken connection only concerns its own client
+            let _ = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await;
+        });
+    }
+}
+
+/// Returns an empty response with the given status.
+pub(crate) fn status_response(status: StatusCode) -> Response<ResponseBody> {
+    let mut response = Response::new(full_body(Bytes::new()));
+    *response.status_mut() = status;
+    response
+}
+
+/// Returns a response body of the given content.
+pub(crate) fn full_body(content: impl Into<Bytes>) -> ResponseBody {
+    Full::new(content.into())
+        .map_err(|never| match never {})
+        .boxed_unsync()
+}
+
+/// Returns a response body streaming the chunks of a served file.
+pub(crate) fn stream_body(chunks: ByteStream) -> ResponseBody {
+    StreamBody::new(chunks.map_ok(|chunk| Frame::data(Bytes::from(chunk)))).boxed_unsync()
+}
+
+/// Maps a store error onto the HTTP status answered to the client.
+fn error_status(err: &DriverError) -> StatusCode {
+    match err {
+        DriverError::ResourceNotFound => StatusCode::NOT_FOUND,
+        DriverError::InvalidPath | DriverError::RefusingToDeleteRoot => StatusCode::BAD_REQUEST,
+        DriverError::PermissionDenied | DriverError::ReservedPath => StatusCode::FORBIDDEN,
+        DriverError::PreconditionFailed => StatusCode::PRECONDITION_FAILED,
+        DriverError::Validation(_) | DriverError::Infected(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        DriverError::Unsupported(_) => StatusCode::NOT_IMPLEMENTED,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Compares the two values in a time independent of where they differ.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::StoreConfig;
+
+    fn request(method: Method, uri: &str, token: &str, body: &'static str) -> Request<Full<Bytes>> {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {token}"))
+            .body(Full::new(Bytes::from(body)))
+            .unwrap()
+    }
+
+    async fn body(response: Response<ResponseBody>) -> Bytes {
+        response.into_body().collect().await.unwrap().to_bytes()
+    }
+
+    #[tokio::test]
+    async fn can_serve_store() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let server = RestServer::new(store, "secret");
+
+        let response = server
+            .handle(request(
+                Method::PUT,
+                "/docs/hello%20world.txt",
+                "secret",
+                "hello",
+            ))
+            .await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let response = server
+            .handle(request(
+                Method::GET,
+                "/docs/hello%20world.txt",
+                "secret",
+                "",
+            ))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body(response).await, "hello");
+
+        let response = server.handle(request(Method::GET, "/", "secret", "")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body(response).await, r#"["docs/hello world.txt"]"#);
+
+        let response = server
+            .handle(request(
+                Method::DELETE,
+                "/docs/hello%20world.txt",
+                "secret",
+                "",
+            ))
+            .await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let response = server
+            .handle(request(
+                Method::GET,
+                "/docs/hello%20world.txt",
+                "secret",
+                "",
+            ))
+            .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn can_reject_requests() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let server = RestServer::new(store, "secret");
+
+        let response = server
+            .handle(request(Method::GET, "/test.txt", "wrong", ""))
+            .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+            "Bearer"
+        );
+
+        let response = server
+            .handle(request(Method::GET, "/docs/../test.txt", "secret", ""))
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = server
+            .handle(request(Method::DELETE, "/docs/", "secret", ""))
+            .await;
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(response.headers().get(header::ALLOW).unwrap(), "GET");
+    }
+
+    #[tokio::test]
+    async fn can_limit_request_bodies() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let server = RestServer::new(store.clone(), "secret").with_max_body_size(5);
+
+        let response = server
+            .handle(request(Method::PUT, "/test.txt", "secret", "hello world"))
+            .await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(!store.file_exists("test.txt").await.unwrap());
+
+        let response = server
+            .handle(request(Method::PUT, "/test.txt", "secret", "hello"))
+            .await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(store.read::<String>("test.txt").await.unwrap(), "hello");
+    }
+}
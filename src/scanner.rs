@@ -0,0 +1,565 @@
+//! # Scanner Module
+//!
+//! The `scanner` module defines [`ScanningStore`], a [`Store`] wrapper that
+//! runs every upload through a [`Scanner`], such as a virus scanner, before it
+//! is written.
+//!
+//! Infected uploads are rejected with [`DriverError::Infected`], or written
+//! under a quarantine prefix instead of their path when one is configured.
+//! Files written before scanning was enabled can be checked with
+//! [`ScanningStore::rescan`].
+//!
+//! Each quarantined file gets an audit record under
+//! [`QUARANTINE_RECORDS_PREFIX`], listed with [`ScanningStore::quarantined`].
+//! An administrator then releases a false positive to its path with
+//! [`ScanningStore::release`], or deletes the file for good with
+//! [`ScanningStore::purge`]. [`ScanningStore::list`] hides the quarantine from
+//! the users.
+//!
+//! With the `clamav` feature, [`ClamdScanner`] scans the contents with a
+//! `clamd` daemon.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::PathBuf;
+//! use active_storage::{
+//!     errors::DriverError,
+//!     scanner::{ScanVerdict, Scanner, ScanningStore},
+//!     StoreConfig,
+//! };
+//!
+//! struct DenyEmpty;
+//!
+//! #[async_trait::async_trait]
+//! impl Scanner for DenyEmpty {
+//!     async fn scan(&self, content: &[u8]) -> Result<ScanVerdict, DriverError> {
+//!         Ok(if content.is_empty() {
+//!             ScanVerdict::Infected("Empty".to_string())
+//!         } else {
+//!             ScanVerdict::Clean
+//!         })
+//!     }
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!     let scanning = ScanningStore::new(store, DenyEmpty);
+//!
+//!     let file_path = PathBuf::from("test.txt");
+//!     assert!(scanning.write(file_path.as_path(), "my content").await.is_ok());
+//!     assert!(matches!(
+//!         scanning.write(file_path.as_path(), "").await,
+//!         Err(DriverError::Infected(_))
+//!     ));
+//! }
+//! ```
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bytes::Bytes;
+
+use crate::{
+    clock,
+    contents::IntoBytes,
+    errors::{DriverError, DriverResult},
+    key::{Key, ObjectPath},
+    store::Store,
+};
+
+/// Enum representing the outcome of a scan.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScanVerdict {
+    /// Nothing was found.
+    Clean,
+    /// The content is infected with the named signature.
+    Infected(String),
+}
+
+/// The prefix of the audit records of the quarantined files.
+pub const QUARANTINE_RECORDS_PREFIX: &str = ".active-storage-quarantine";
+
+/// Struct representing the audit record of a quarantined file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuarantineRecord {
+    /// The path the file was written to, before it was quarantined.
+    pub path: ObjectPath,
+    /// The signature the scanner found in the file.
+    pub signature: String,
+    /// When the file was quarantined.
+    pub quarantined_at: SystemTime,
+}
+
+impl QuarantineRecord {
+    /// Returns the stored record, one `name=value` line per field.
+    fn encode(&self) -> String {
+        format!(
+            "signature={}\nquarantined_at={}\n",
+            self.signature.replace('\n', " "),
+            self.quarantined_at
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs())
+        )
+    }
+
+    /// Parses a stored record, returning `None` when it is malformed.
+    fn decode(path: ObjectPath, record: &str) -> Option<Self> {
+        let mut signature = None;
+        let mut quarantined_at = None;
+        for line in record.lines() {
+            match line.split_once('=')? {
+                ("signature", value) => signature = Some(value.to_string()),
+                ("quarantined_at", value) => {
+                    quarantined_at = Some(UNIX_EPOCH + Duration::from_secs(value.parse().ok()?));
+                }
+                _ => {}
+            }
+        }
+        Some(Self {
+            path,
+            signature: signature?,
+            quarantined_at: quarantined_at?,
+        })
+    }
+}
+
+/// Trait representing a content scanner.
+#[async_trait::async_trait]
+pub trait Scanner: Send + Sync {
+    /// Scans the given content.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the content could not be scanned. Uploads are
+    /// rejected when the scan fails.
+    async fn scan(&self, content: &[u8]) -> DriverResult<ScanVerdict>;
+}
+
+/// Struct representing a store scanning every upload before writing it.
+#[derive(Clone)]
+pub struct ScanningStore {
+    store: Store,
+    scanner: Arc<dyn Scanner>,
+    quarantine: Option<PathBuf>,
+}
+
+impl ScanningStore {
+    /// Creates a new [`ScanningStore`] rejecting the uploads the scanner finds
+    /// infected.
+    #[must_use]
+    pub fn new<S: Scanner + 'static>(store: Store, scanner: S) -> Self {
+        Self {
+            store,
+            scanner: Arc::new(scanner),
+            quarantine: None,
+        }
+    }
+
+    /// Writes infected uploads under the given prefix, keeping their path,
+    /// instead of discarding them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prefix is not a valid path.
+    pub fn with_quarantine(mut self, prefix: &Path) -> DriverResult<Self> {
+        Key::from_path(prefix)?;
+        self.quarantine = Some(prefix.to_path_buf());
+        Ok(self)
+    }
+
+    /// Returns the wrapped store, e.g. to read the uploaded files.
+    #[must_use]
+    pub const fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// Returns the path an infected file at the given path is quarantined at.
+    #[must_use]
+    pub fn quarantine_path(&self, path: &Path) -> Option<PathBuf> {
+        self.quarantine.as_ref().map(|prefix| prefix.join(path))
+    }
+
+    /// Scans the content and writes it to the specified path when it is
+    /// clean.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::Infected`] if the content is infected, whether
+    /// or not it was quarantined, or an error if the content could not be
+    /// scanned or written.
+    pub async fn write<C: IntoBytes + Send>(&self, path: &Path, content: C) -> DriverResult<()> {
+        let content = content.into_bytes();
+        match self.scanner.scan(&content).await? {
+            ScanVerdict::Clean => self.store.write(path, content).await,
+            ScanVerdict::Infected(signature) => {
+                self.quarantine(path, content, &signature).await?;
+                Err(DriverError::Infected(signature))
+            }
+        }
+    }
+
+    /// Scans a file already in the store, moving it to the quarantine prefix,
+    /// or deleting it when there is none, if it is infected.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist or could not be scanned or
+    /// moved.
+    pub async fn rescan(&self, path: &Path) -> DriverResult<ScanVerdict> {
+        let content = self.store.read_bytes(path).await?;
+        let verdict = self.scanner.scan(&content).await?;
+
+        if let ScanVerdict::Infected(signature) = &verdict {
+            self.quarantine(path, content, signature).await?;
+            self.store.delete(path).await?;
+        }
+
+        Ok(verdict)
+    }
+
+    /// Lists the files under the given directory like [`Store::list`],
+    /// without the quarantined files and their records.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the files could not be listed.
+    pub async fn list(&self, path: &Path) -> DriverResult<Vec<ObjectPath>> {
+        let mut files = self.store.list(path).await?;
+        files.retain(|file| {
+            !file.starts_with(QUARANTINE_RECORDS_PREFIX)
+                && self
+                    .quarantine
+                    .as_ref()
+                    .is_none_or(|quarantine| !file.starts_with(quarantine))
+        });
+        Ok(files)
+    }
+
+    /// Returns the audit records of the quarantined files.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::DecodeError`] if a record is malformed, or an
+    /// error if the records could not be read.
+    pub async fn quarantined(&self) -> DriverResult<Vec<QuarantineRecord>> {
+        let mut records = Vec::new();
+        for record_path in self
+            .store
+            .records()
+            .list(Path::new(QUARANTINE_RECORDS_PREFIX))
+            .await?
+        {
+            let record = self.store.records().read::<String>(&record_path).await?;
+            let path = record_path
+                .strip_prefix(QUARANTINE_RECORDS_PREFIX)
+                .ok_or(DriverError::DecodeError)?;
+            records.push(QuarantineRecord::decode(path, &record).ok_or(DriverError::DecodeError)?);
+        }
+        Ok(records)
+    }
+
+    /// Moves a quarantined file back to its path, without scanning it, once
+    /// an administrator found it safe, and deletes its record.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::ResourceNotFound`] if the file is not
+    /// quarantined, or an error if it could not be moved.
+    pub async fn release(&self, path: &Path) -> DriverResult<()> {
+        let quarantine = self
+            .quarantine_path(path)
+            .ok_or(DriverError::ResourceNotFound)?;
+        let content = self.store.read_bytes(&quarantine).await?;
+        self.store.write(path, content).await?;
+        self.store.delete(&quarantine).await?;
+        self.store
+            .records()
+            .delete_if_exists(record_path(path))
+            .await?;
+        Ok(())
+    }
+
+    /// Deletes a quarantined file and its record for good.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::ResourceNotFound`] if the file is not
+    /// quarantined, or an error if it could not be deleted.
+    pub async fn purge(&self, path: &Path) -> DriverResult<()> {
+        let quarantine = self
+            .quarantine_path(path)
+            .ok_or(DriverError::ResourceNotFound)?;
+        if !self.store.delete_if_exists(&quarantine).await? {
+            return Err(DriverError::ResourceNotFound);
+        }
+        self.store
+            .records()
+            .delete_if_exists(record_path(path))
+            .await?;
+        Ok(())
+    }
+
+    /// Writes infected content under the quarantine prefix with its audit
+    /// record, when a prefix is configured.
+    async fn quarantine(&self, path: &Path, content: Bytes, signature: &str) -> DriverResult<()> {
+        let Some(quarantine) = self.quarantine_path(path) else {
+            return Ok(());
+        };
+        let record = QuarantineRecord {
+            path: path.into(),
+            signature: signature.to_string(),
+            quarantined_at: clock::now(),
+        };
+        self.store.write(&quarantine, content).await?;
+        self.store
+            .records()
+            .write(record_path(path), record.encode())
+            .await
+    }
+}
+
+/// Returns the path of the audit record of the file quarantined from the
+/// given path.
+fn record_path(path: &Path) -> PathBuf {
+    Path::new(QUARANTINE_RECORDS_PREFIX).join(path)
+}
+
+/// Struct representing a [`Scanner`] sending the contents to a `clamd` daemon
+/// over TCP with the `INSTREAM` command.
+#[cfg(feature = "clamav")]
+#[derive(Clone, Debug)]
+pub struct ClamdScanner {
+    address: String,
+    chunk_size: usize,
+}
+
+#[cfg(feature = "clamav")]
+impl ClamdScanner {
+    /// The default size of the chunks the contents are streamed in.
+    const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+    /// Creates a new [`ClamdScanner`] connecting to the daemon at the given
+    /// address, e.g. `127.0.0.1:3310`.
+    #[must_use]
+    pub fn new(address: impl Into<String>) -> Self {
+        Self {
+            address: address.into(),
+            chunk_size: Self::DEFAULT_CHUNK_SIZE,
+        }
+    }
+
+    /// Sets the size of the chunks the contents are streamed in, which must
+    /// stay below the `StreamMaxLength` of the daemon.
+    #[must_use]
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+}
+
+#[cfg(feature = "clamav")]
+#[async_trait::async_trait]
+impl Scanner for ClamdScanner {
+    /// Streams the content to the daemon and parses its reply.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the daemon is unreachable or reports an error,
+    /// e.g. when the content exceeds its size limit.
+    async fn scan(&self, content: &[u8]) -> DriverResult<ScanVerdict> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let io_error = |err: std::io::Error| DriverError::Any(Box::new(err));
+        let mut stream = tokio::net::TcpStream::connect(&self.address)
+            .await
+            .map_err(|_| DriverError::Network())?;
+
+        stream.write_all(b"zINSTREAM\0").await.map_err(io_error)?;
+        for chunk in content.chunks(self.chunk_size) {
+            let len = u32::try_from(chunk.len()).map_err(|err| DriverError::Any(Box::new(err)))?;
+            stream
+                .write_all(&len.to_be_bytes())
+                .await
+                .map_err(io_error)?;
+            stream.write_all(chunk).await.map_err(io_error)?;
+        }
+        stream.write_all(&[0; 4]).await.map_err(io_error)?;
+
+        let mut reply = Vec::new();
+        stream.read_to_end(&mut reply).await.map_err(io_error)?;
+        parse_clamd_reply(&String::from_utf8_lossy(&reply))
+    }
+}
+
+/// Parses a `clamd` reply such as `stream: OK` or
+/// `stream: Eicar-Signature FOUND`.
+#[cfg(feature = "clamav")]
+fn parse_clamd_reply(reply: &str) -> DriverResult<ScanVerdict> {
+    let reply = reply.trim_end_matches(['\0', '\n']);
+    let result = reply.strip_prefix("stream: ").unwrap_or(reply);
+
+    if result == "OK" {
+        Ok(ScanVerdict::Clean)
+    } else if let Some(signature) = result.strip_suffix(" FOUND") {
+        Ok(ScanVerdict::Infected(signature.to_string()))
+    } else {
+        Err(DriverError::Any(format!("clamd: {result}").into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::StoreConfig;
+
+    struct SignatureScanner;
+
+    #[async_trait::async_trait]
+    impl Scanner for SignatureScanner {
+        async fn scan(&self, content: &[u8]) -> DriverResult<ScanVerdict> {
+            Ok(if content.starts_with(b"EICAR") {
+                ScanVerdict::Infected("Eicar-Signature".to_string())
+            } else {
+                ScanVerdict::Clean
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn can_reject_and_quarantine_infected_uploads() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let scanning = ScanningStore::new(store.clone(), SignatureScanner);
+        let path = Path::new("uploads/file.txt");
+
+        scanning.write(path, "content").await.unwrap();
+        assert!(matches!(
+            scanning.write(Path::new("uploads/virus.txt"), "EICAR").await,
+            Err(DriverError::Infected(signature)) if signature == "Eicar-Signature"
+        ));
+        assert_eq!(
+            store.list(Path::new("")).await.unwrap(),
+            vec![ObjectPath::from(path)]
+        );
+
+        let scanning = scanning.with_quarantine(Path::new("quarantine")).unwrap();
+        assert!(scanning
+            .write(Path::new("uploads/virus.txt"), "EICAR")
+            .await
+            .is_err());
+        assert!(store
+            .file_exists(Path::new("quarantine/uploads/virus.txt"))
+            .await
+            .unwrap());
+
+        store.write(path, "EICAR").await.unwrap();
+        assert_eq!(
+            scanning.rescan(path).await.unwrap(),
+            ScanVerdict::Infected("Eicar-Signature".to_string())
+        );
+        assert!(!store.file_exists(path).await.unwrap());
+        assert!(store
+            .file_exists(Path::new("quarantine/uploads/file.txt"))
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn can_release_and_purge_quarantined_files() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let scanning = ScanningStore::new(store.clone(), SignatureScanner)
+            .with_quarantine(Path::new("quarantine"))
+            .unwrap();
+
+        scanning
+            .write(Path::new("uploads/file.txt"), "content")
+            .await
+            .unwrap();
+        for name in ["uploads/false-positive.txt", "uploads/virus.txt"] {
+            assert!(scanning.write(Path::new(name), "EICAR").await.is_err());
+        }
+        assert_eq!(
+            scanning.list(Path::new("")).await.unwrap(),
+            vec![ObjectPath::new("uploads/file.txt")]
+        );
+
+        let records = scanning.quarantined().await.unwrap();
+        assert_eq!(
+            records
+                .iter()
+                .map(|record| (record.path.as_str(), record.signature.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("uploads/false-positive.txt", "Eicar-Signature"),
+                ("uploads/virus.txt", "Eicar-Signature"),
+            ]
+        );
+
+        scanning
+            .release(Path::new("uploads/false-positive.txt"))
+            .await
+            .unwrap();
+        scanning
+            .purge(Path::new("uploads/virus.txt"))
+            .await
+            .unwrap();
+        assert!(matches!(
+            scanning.purge(Path::new("uploads/virus.txt")).await,
+            Err(DriverError::ResourceNotFound)
+        ));
+        assert!(scanning.quarantined().await.unwrap().is_empty());
+        assert_eq!(
+            store.list(Path::new("")).await.unwrap(),
+            vec![
+                ObjectPath::new("uploads/false-positive.txt"),
+                ObjectPath::new("uploads/file.txt"),
+            ]
+        );
+    }
+
+    #[cfg(feature = "clamav")]
+    #[tokio::test]
+    async fn can_scan_with_clamd() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut command = [0; 10];
+                stream.read_exact(&mut command).await.unwrap();
+                assert_eq!(&command, b"zINSTREAM\0");
+
+                let mut content = Vec::new();
+                loop {
+                    let len = stream.read_u32().await.unwrap() as usize;
+                    if len == 0 {
+                        break;
+                    }
+                    let mut chunk = vec![0; len];
+                    stream.read_exact(&mut chunk).await.unwrap();
+                    content.extend(chunk);
+                }
+                let reply: &[u8] = if content.starts_with(b"EICAR") {
+                    b"stream: Eicar-Signature FOUND\0"
+                } else {
+                    b"stream: OK\0"
+                };
+                stream.write_all(reply).await.unwrap();
+            }
+        });
+
+        let scanner = ClamdScanner::new(address).with_chunk_size(2);
+        assert_eq!(scanner.scan(b"content").await.unwrap(), ScanVerdict::Clean);
+        assert_eq!(
+            scanner.scan(b"EICAR-TEST").await.unwrap(),
+            ScanVerdict::Infected("Eicar-Signature".to_string())
+        );
+        assert!(parse_clamd_reply("stream: INSTREAM size limit exceeded. ERROR\0").is_err());
+    }
+}
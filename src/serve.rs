@@ -0,0 +1,461 @@
+//! # Serve Module
+//!
+//! The `serve` module defines [`ServeResponse`], a framework agnostic HTTP
+//! response for proxying a stored file, returned by
+//! [`crate::store::Store::serve`].
+//!
+//! Conditional (`If-None-Match`, `If-Range`) and ranged (`Range`) requests
+//! are handled here once, so any HTTP framework only has to copy the status,
+//! headers and body into its own response type.
+//!
+//! The `ETag` is strong when the backend reports a checksum of the content,
+//! e.g. the S3 `ETag`, so CDNs and browsers can resume downloads with
+//! `If-Range`. Other backends get a weak `ETag` derived from the modification
+//! time and the size.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::PathBuf;
+//! use active_storage::StoreConfig;
+//! use futures::TryStreamExt;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!     let file_path = PathBuf::from("test.txt");
+//!     store.write(file_path.as_path(), "my content").await.unwrap();
+//!
+//!     let response = store
+//!         .serve(file_path.as_path(), Some("bytes=0-1"), None)
+//!         .await
+//!         .unwrap();
+//!     assert_eq!(response.status, 206);
+//!     assert_eq!(response.header("Content-Range"), Some("bytes 0-1/10"));
+//!
+//!     let body: Vec<Vec<u8>> = response.body.try_collect().await.unwrap();
+//!     assert_eq!(body.concat(), b"my");
+//! }
+//! ```
+use std::{fmt, ops::Range, pin::Pin, time::SystemTime};
+
+use futures::{stream, Stream};
+
+use crate::{drivers::ObjectMetadata, errors::DriverResult};
+
+/// A stream of the response body chunks.
+pub type ByteStream = Pin<Box<dyn Stream<Item = DriverResult<Vec<u8>>> + Send>>;
+
+/// Struct representing the conditional and range headers of a request for a
+/// served file, see [`crate::store::Store::serve_with`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ServeRequest<'a> {
+    /// The `Range` header value.
+    pub range: Option<&'a str>,
+    /// The `If-None-Match` header value.
+    pub if_none_match: Option<&'a str>,
+    /// The `If-Range` header value, an `ETag` or an HTTP date. The range is
+    /// served only when it matches the file, otherwise the whole file is.
+    pub if_range: Option<&'a str>,
+}
+
+impl<'a> ServeRequest<'a> {
+    /// Sets the `Range` header value.
+    #[must_use]
+    pub const fn with_range(mut self, range: &'a str) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    /// Sets the `If-None-Match` header value.
+    #[must_use]
+    pub const fn with_if_none_match(mut self, if_none_match: &'a str) -> Self {
+        self.if_none_match = Some(if_none_match);
+        self
+    }
+
+    /// Sets the `If-Range` header value.
+    #[must_use]
+    pub const fn with_if_range(mut self, if_range: &'a str) -> Self {
+        self.if_range = Some(if_range);
+        self
+    }
+}
+
+/// Struct representing the HTTP response of a served file.
+pub struct ServeResponse {
+    /// The HTTP status code: `200`, `206`, `304` or `416`.
+    pub status: u16,
+    /// The response headers.
+    pub headers: Vec<(&'static str, String)>,
+    /// The response body.
+    pub body: ByteStream,
+}
+
+impl fmt::Debug for ServeResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServeResponse")
+            .field("status", &self.status)
+            .field("headers", &self.headers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ServeResponse {
+    /// Returns the value of the given header, compared case-insensitively.
+    #[must_use]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// The response to a request for a file, decided from its metadata before
+/// reading it, so a `304` or a `416` doesn't read the file and a `206` reads
+/// only the requested range.
+pub(crate) struct ServePlan {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    range: Option<Range<u64>>,
+}
+
+impl ServePlan {
+    /// Plans the response of a file of the given length and `ETag`, honoring
+    /// the request header values.
+    pub(crate) fn new(
+        len: u64,
+        content_type: String,
+        etag: String,
+        last_modified: SystemTime,
+        request: &ServeRequest<'_>,
+    ) -> Self {
+        let mut headers = vec![
+            ("ETag", etag.clone()),
+            ("Last-Modified", httpdate::fmt_http_date(last_modified)),
+            ("Accept-Ranges", "bytes".to_string()),
+        ];
+
+        if request
+            .if_none_match
+            .is_some_and(|value| etag_matches(value, &etag))
+        {
+            return Self {
+                status: 304,
+                headers,
+                range: None,
+            };
+        }
+
+        headers.push(("Content-Type", content_type));
+
+        let range = request.range.filter(|_| {
+            request
+                .if_range
+                .is_none_or(|value| if_range_matches(value, &etag, last_modified))
+        });
+        let (status, range) = match range.map(|value| parse_range(value, len)) {
+            None | Some(RangeSpec::Ignored) => (200, 0..len),
+            Some(RangeSpec::Satisfiable(range)) => {
+                headers.push((
+                    "Content-Range",
+                    format!("bytes {}-{}/{len}", range.start, range.end - 1),
+                ));
+                (206, range)
+            }
+            Some(RangeSpec::Unsatisfiable) => {
+                headers.push(("Content-Range", format!("bytes */{len}")));
+                headers.push(("Content-Length", "0".to_string()));
+                return Self {
+                    status: 416,
+                    headers,
+                    range: None,
+                };
+            }
+        };
+
+        headers.push(("Content-Length", (range.end - range.start).to_string()));
+
+        Self {
+            status,
+            headers,
+            range: Some(range),
+        }
+    }
+
+    /// Returns the bytes of the file to send, `None` when the response has no
+    /// body.
+    pub(crate) fn range(&self) -> Option<Range<u64>> {
+        self.range.clone()
+    }
+
+    /// Builds the response with the bytes of [`Self::range`].
+    pub(crate) fn respond(self, body: Vec<u8>) -> ServeResponse {
+        ServeResponse {
+            status: self.status,
+            headers: self.headers,
+            body: if self.range.is_some() {
+                Box::pin(stream::once(async move { Ok(body) }))
+            } else {
+                Box::pin(stream::empty())
+            },
+        }
+    }
+}
+
+/// The result of parsing a `Range` header value.
+#[derive(Debug, PartialEq, Eq)]
+enum RangeSpec {
+    /// The range is satisfiable, with an exclusive end.
+    Satisfiable(Range<u64>),
+    /// The range is valid but lies outside of the content.
+    Unsatisfiable,
+    /// The range is malformed or has multiple ranges and the full content is
+    /// served.
+    Ignored,
+}
+
+/// Parses a single `bytes=` range against the given content length.
+fn parse_range(value: &str, len: u64) -> RangeSpec {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return RangeSpec::Ignored;
+    };
+    if spec.contains(',') {
+        return RangeSpec::Ignored;
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return RangeSpec::Ignored;
+    };
+
+    let range = match (start.trim(), end.trim()) {
+        ("", "") => return RangeSpec::Ignored,
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return RangeSpec::Unsatisfiable,
+            Ok(suffix) => len.saturating_sub(suffix)..len,
+            Err(_) => return RangeSpec::Ignored,
+        },
+        (start, end) => {
+            let Ok(start) = start.parse::<u64>() else {
+                return RangeSpec::Ignored;
+            };
+            let end = if end.is_empty() {
+                len
+            } else {
+                match end.parse::<u64>() {
+                    Ok(end) if end >= start => len.min(end + 1),
+                    _ => return RangeSpec::Ignored,
+                }
+            };
+            start..end
+        }
+    };
+
+    if range.start >= len {
+        RangeSpec::Unsatisfiable
+    } else {
+        RangeSpec::Satisfiable(range)
+    }
+}
+
+/// Returns a weak `ETag` derived from the modification time and the length.
+pub(crate) fn etag(last_modified: SystemTime, len: u64) -> String {
+    let modified = last_modified
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    format!(
+        "W/\"{:x}.{:x}-{len:x}\"",
+        modified.as_secs(),
+        modified.subsec_nanos()
+    )
+}
+
+/// Returns the `ETag` of the file: the backend checksum as a strong `ETag`
+/// when there is one, otherwise [`etag`].
+pub(crate) fn metadata_etag(metadata: &ObjectMetadata) -> String {
+    match metadata.etag.as_deref().map(str::trim) {
+        Some(tag) if tag.starts_with("W/") || (tag.len() > 1 && tag.starts_with('"')) => {
+            tag.to_string()
+        }
+        Some(tag) if !tag.is_empty() => format!("\"{tag}\""),
+        _ => etag(metadata.last_modified, metadata.size),
+    }
+}
+
+/// Returns `true` when the `If-Range` header value matches the file, using the
+/// strong comparison for an `ETag` and an exact match for an HTTP date.
+fn if_range_matches(if_range: &str, etag: &str, last_modified: SystemTime) -> bool {
+    let if_range = if_range.trim();
+    if if_range.starts_with('"') || if_range.starts_with("W/") {
+        return !etag.starts_with("W/") && if_range == etag;
+    }
+    httpdate::parse_http_date(if_range)
+        .is_ok_and(|date| httpdate::fmt_http_date(date) == httpdate::fmt_http_date(last_modified))
+}
+
+/// Returns `true` when the `If-None-Match` header value matches the `ETag`,
+/// using the weak comparison.
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    if_none_match.trim() == "*"
+        || if_none_match
+            .split(',')
+            .any(|tag| opaque(tag) == opaque(etag))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn can_parse_ranges() {
+        assert_eq!(parse_range("bytes=0-4", 10), RangeSpec::Satisfiable(0..5));
+        assert_eq!(parse_range("bytes=5-", 10), RangeSpec::Satisfiable(5..10));
+        assert_eq!(parse_range("bytes=-3", 10), RangeSpec::Satisfiable(7..10));
+        assert_eq!(parse_range("bytes=8-20", 10), RangeSpec::Satisfiable(8..10));
+        assert_eq!(parse_range("bytes=10-", 10), RangeSpec::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-0", 10), RangeSpec::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-1,4-5", 10), RangeSpec::Ignored);
+        assert_eq!(parse_range("items=0-1", 10), RangeSpec::Ignored);
+        assert_eq!(parse_range("bytes=4-1", 10), RangeSpec::Ignored);
+    }
+
+    #[test]
+    fn can_match_etags() {
+        let etag = etag(SystemTime::UNIX_EPOCH, 10);
+        assert!(etag_matches("*", &etag));
+        assert!(etag_matches(&format!("\"other\", {etag}"), &etag));
+        assert!(etag_matches(etag.trim_start_matches("W/"), &etag));
+        assert!(!etag_matches("\"other\"", &etag));
+    }
+
+    #[test]
+    fn can_match_if_range() {
+        let modified = SystemTime::UNIX_EPOCH;
+        let date = httpdate::fmt_http_date(modified);
+        assert!(if_range_matches("\"abc\"", "\"abc\"", modified));
+        assert!(!if_range_matches("\"abd\"", "\"abc\"", modified));
+        let weak = etag(modified, 10);
+        assert!(!if_range_matches(&weak, &weak, modified));
+        assert!(if_range_matches(&date, &weak, modified));
+        assert!(!if_range_matches(
+            &httpdate::fmt_http_date(modified + std::time::Duration::from_secs(1)),
+            &weak,
+            modified
+        ));
+
+        let mut metadata = ObjectMetadata {
+            size: 10,
+            last_modified: modified,
+            content_type: None,
+            etag: Some("\"abc\"".to_string()),
+            storage_class: None,
+            content_md5: None,
+        };
+        assert_eq!(metadata_etag(&metadata), "\"abc\"");
+        metadata.etag = Some("abc".to_string());
+        assert_eq!(metadata_etag(&metadata), "\"abc\"");
+        metadata.etag = None;
+        assert_eq!(metadata_etag(&metadata), weak);
+    }
+
+    /// Plans the response of the given content and sends the planned bytes.
+    fn respond(
+        content: Vec<u8>,
+        content_type: String,
+        etag: String,
+        last_modified: SystemTime,
+        request: &ServeRequest<'_>,
+    ) -> ServeResponse {
+        let plan = ServePlan::new(
+            content.len() as u64,
+            content_type,
+            etag,
+            last_modified,
+            request,
+        );
+        #[allow(clippy::cast_possible_truncation)]
+        let body = plan.range().map_or_else(Vec::new, |range| {
+            content[range.start as usize..range.end as usize].to_vec()
+        });
+        plan.respond(body)
+    }
+
+    #[test]
+    fn can_plan_reads() {
+        let modified = SystemTime::UNIX_EPOCH;
+        let text = "text/plain".to_string();
+        let etag = "\"abc\"".to_string();
+        let request = ServeRequest::default();
+
+        let plan = |request: &ServeRequest<'_>| {
+            ServePlan::new(10, text.clone(), etag.clone(), modified, request).range()
+        };
+        assert_eq!(plan(&request), Some(0..10));
+        assert_eq!(plan(&request.with_range("bytes=2-4")), Some(2..5));
+        assert_eq!(plan(&request.with_if_none_match(&etag)), None);
+        assert_eq!(plan(&request.with_range("bytes=20-")), None);
+    }
+
+    #[test]
+    fn can_build_responses() {
+        let content = b"0123456789".to_vec();
+        let modified = SystemTime::UNIX_EPOCH;
+        let text = "text/plain".to_string();
+
+        let etag = "\"abc\"".to_string();
+        let request = ServeRequest::default();
+
+        let response = respond(
+            content.clone(),
+            text.clone(),
+            etag.clone(),
+            modified,
+            &request,
+        );
+        assert_eq!(response.status, 200);
+        assert_eq!(response.header("content-length"), Some("10"));
+        assert_eq!(response.header("Content-Type"), Some("text/plain"));
+
+        assert_eq!(response.header("ETag"), Some("\"abc\""));
+        let response = respond(
+            content.clone(),
+            text.clone(),
+            etag.clone(),
+            modified,
+            &request.with_if_none_match(&etag),
+        );
+        assert_eq!(response.status, 304);
+        assert_eq!(response.header("Content-Type"), None);
+
+        let ranged = request.with_range("bytes=0-1");
+        let response = respond(
+            content.clone(),
+            text.clone(),
+            etag.clone(),
+            modified,
+            &ranged.with_if_range(&etag),
+        );
+        assert_eq!(response.status, 206);
+        let response = respond(
+            content.clone(),
+            text.clone(),
+            etag.clone(),
+            modified,
+            &ranged.with_if_range("\"changed\""),
+        );
+        assert_eq!(response.status, 200);
+        assert_eq!(response.header("Content-Length"), Some("10"));
+
+        let response = respond(
+            content,
+            text,
+            etag,
+            modified,
+            &request.with_range("bytes=20-"),
+        );
+        assert_eq!(response.status, 416);
+        assert_eq!(response.header("Content-Range"), Some("bytes */10"));
+    }
+}
@@ -0,0 +1,160 @@
+//! # Service Module
+//!
+//! The `service` module implements [`tower_service::Service`] for
+//! [`Store`], so tower middleware (retry, rate limit, timeout, load shed) can
+//! be composed around storage calls.
+//!
+//! Each storage operation is a [`Request`] variant, answered with the
+//! matching [`Response`] variant.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::PathBuf;
+//! use active_storage::{
+//!     service::{Request, Response},
+//!     StoreConfig,
+//! };
+//! use tower_service::Service;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let mut store = StoreConfig::InMem().build().await.unwrap();
+//!     let file_path = PathBuf::from("test.txt");
+//!
+//!     store
+//!         .call(Request::Write(file_path.clone(), b"my content".to_vec()))
+//!         .await
+//!         .unwrap();
+//!     let response = store.call(Request::Read(file_path)).await.unwrap();
+//!     assert_eq!(response, Response::Content(b"my content".to_vec()));
+//! }
+//! ```
+use std::{
+    future::Future,
+    path::PathBuf,
+    pin::Pin,
+    task::{Context, Poll},
+    time::SystemTime,
+};
+
+use crate::{
+    errors::{DriverError, DriverResult},
+    key::ObjectPath,
+    store::Store,
+};
+
+/// Enum representing a storage operation sent to the [`Store`] service.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Request {
+    /// Reads the file, answered with [`Response::Content`].
+    Read(PathBuf),
+    /// Writes the content to the file, answered with [`Response::Done`].
+    Write(PathBuf, Vec<u8>),
+    /// Deletes the file, answered with [`Response::Done`].
+    Delete(PathBuf),
+    /// Deletes the directory, answered with [`Response::Done`].
+    DeleteDirectory(PathBuf),
+    /// Checks whether the file exists, answered with [`Response::Exists`].
+    FileExists(PathBuf),
+    /// Returns the last modified time of the file, answered with
+    /// [`Response::LastModified`].
+    LastModified(PathBuf),
+    /// Returns the size of the file, answered with [`Response::Size`].
+    Size(PathBuf),
+    /// Lists the files under the path, answered with [`Response::Paths`].
+    List(PathBuf),
+}
+
+/// Enum representing the result of a successful [`Request`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Response {
+    /// The content of a read file.
+    Content(Vec<u8>),
+    /// The operation completed without a result.
+    Done,
+    /// Whether the file exists.
+    Exists(bool),
+    /// The last modified time of the file.
+    LastModified(SystemTime),
+    /// The size of the file in bytes.
+    Size(u64),
+    /// The listed files.
+    Paths(Vec<ObjectPath>),
+}
+
+impl tower_service::Service<Request> for Store {
+    type Response = Response;
+    type Error = DriverError;
+    type Future = Pin<Box<dyn Future<Output = DriverResult<Response>> + Send>>;
+
+    /// The store is always ready, backpressure is left to the middleware.
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<DriverResult<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let store = self.clone();
+        Box::pin(async move {
+            match request {
+                Request::Read(path) => store.read(&path).await.map(Response::Content),
+                Request::Write(path, content) => {
+                    store.write(&path, content).await.map(|()| Response::Done)
+                }
+                Request::Delete(path) => store.delete(&path).await.map(|()| Response::Done),
+                Request::DeleteDirectory(path) => {
+                    store.delete_directory(&path).await.map(|()| Response::Done)
+                }
+                Request::FileExists(path) => store.file_exists(&path).await.map(Response::Exists),
+                Request::LastModified(path) => {
+                    store.last_modified(&path).await.map(Response::LastModified)
+                }
+                Request::Size(path) => store.size(&path).await.map(Response::Size),
+                Request::List(path) => store.list(&path).await.map(Response::Paths),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use tower_service::Service;
+
+    use super::*;
+    use crate::StoreConfig;
+
+    #[tokio::test]
+    async fn can_call_store_service() {
+        let mut store = StoreConfig::InMem().build().await.unwrap();
+        let path = PathBuf::from("folder/file.txt");
+
+        futures::future::poll_fn(|cx| store.poll_ready(cx))
+            .await
+            .unwrap();
+        assert_eq!(
+            store
+                .call(Request::Write(path.clone(), b"content".to_vec()))
+                .await
+                .unwrap(),
+            Response::Done
+        );
+        assert_eq!(
+            store.call(Request::Size(path.clone())).await.unwrap(),
+            Response::Size(7)
+        );
+        assert_eq!(
+            store
+                .call(Request::List(PathBuf::from("folder")))
+                .await
+                .unwrap(),
+            Response::Paths(vec![ObjectPath::from(&path)])
+        );
+        assert!(matches!(
+            store
+                .call(Request::Read(PathBuf::from("missing.txt")))
+                .await,
+            Err(DriverError::ResourceNotFound)
+        ));
+    }
+}
@@ -0,0 +1,224 @@
+//! # Signing Module
+//!
+//! The `signing` module defines [`BlobSigner`], which generates and verifies
+//! tamper-proof, expiring signed blob ids, like Rails' `signed_id`.
+//!
+//! Web clients get a signed id instead of the storage key, e.g. after a direct
+//! upload, and send it back in a form. The server verifies it with
+//! [`BlobSigner::verify`] before attaching the blob, so clients can't
+//! reference blobs they were not given.
+//!
+//! A signed id has the form `<key>.<expiry>.<signature>`, where the key is
+//! base64url encoded, the expiry is a unix timestamp and the signature is hex
+//! encoded. The key is encoded, not encrypted: the id hides it from casual
+//! inspection but must not be relied on to keep it secret.
+//!
+//! Signers with different purposes, set with [`BlobSigner::with_purpose`],
+//! reject the ids of each other.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::{path::Path, time::Duration};
+//! use active_storage::{key::ObjectPath, signing::BlobSigner};
+//!
+//! let signer = BlobSigner::new("my secret").with_purpose("avatar");
+//! let signed_id = signer
+//!     .sign(Path::new("uploads/avatar.png"), Duration::from_secs(3600))
+//!     .unwrap();
+//!
+//! assert_eq!(
+//!     signer.verify(&signed_id).unwrap(),
+//!     ObjectPath::new("uploads/avatar.png")
+//! );
+//! ```
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::{
+    clock,
+    errors::{DriverError, DriverResult},
+    hex,
+    key::{Key, ObjectPath},
+    mac,
+};
+
+/// The separator of the signed id parts, which never appears in them.
+const SEPARATOR: char = '.';
+
+/// Struct signing and verifying expiring blob ids.
+#[derive(Clone)]
+pub struct BlobSigner {
+    secret: Vec<u8>,
+    purpose: String,
+}
+
+impl std::fmt::Debug for BlobSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlobSigner")
+            .field("purpose", &self.purpose)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BlobSigner {
+    /// Creates a new [`BlobSigner`] with the given secret. The secret must be
+    /// kept private and shared by every server verifying the ids.
+    #[must_use]
+    pub fn new<S: AsRef<[u8]>>(secret: S) -> Self {
+        Self {
+            secret: secret.as_ref().to_vec(),
+            purpose: String::new(),
+        }
+    }
+
+    /// Binds the signed ids to the given purpose, e.g. `avatar`, so an id
+    /// issued for one form can't be replayed in another.
+    #[must_use]
+    pub fn with_purpose<S: Into<String>>(mut self, purpose: S) -> Self {
+        self.purpose = purpose.into();
+        self
+    }
+
+    /// Returns a signed id of the blob at the given path, valid for the given
+    /// duration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is invalid.
+    pub fn sign(&self, path: impl Into<ObjectPath>, expires_in: Duration) -> DriverResult<String> {
+        self.sign_until(path, clock::now() + expires_in)
+    }
+
+    /// Returns a signed id of the blob at the given path, valid until the
+    /// given time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is invalid.
+    pub fn sign_until(
+        &self,
+        path: impl Into<ObjectPath>,
+        expires_at: SystemTime,
+    ) -> DriverResult<String> {
+        let key = path.into().to_key()?;
+        let expires = expires_at
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        let signature = self.mac(key.as_str(), expires).finalize().into_bytes();
+
+        Ok(format!(
+            "{}{SEPARATOR}{expires}{SEPARATOR}{}",
+            URL_SAFE_NO_PAD.encode(key.as_str()),
+            hex::encode(&signature)
+        ))
+    }
+
+    /// Verifies the given signed id, returning the path of the blob it
+    /// references.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::InvalidToken`] if the id is malformed, its
+    /// signature doesn't match or it was signed for another purpose, and
+    /// [`DriverError::TokenExpired`] if it is past its expiry.
+    pub fn verify(&self, signed_id: &str) -> DriverResult<ObjectPath> {
+        let mut parts = signed_id.split(SEPARATOR);
+        let (Some(key), Some(expires), Some(signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(DriverError::InvalidToken);
+        };
+        let key = URL_SAFE_NO_PAD
+            .decode(key)
+            .ok()
+            .and_then(|key| String::from_utf8(key).ok())
+            .ok_or(DriverError::InvalidToken)?;
+        let expires = expires
+            .parse::<u64>()
+            .map_err(|_| DriverError::InvalidToken)?;
+        let signature = hex::decode(signature).ok_or(DriverError::InvalidToken)?;
+
+        // the signature is checked first, so a forged id never reports
+        // whether it expired
+        self.mac(&key, expires)
+            .verify_slice(&signature)
+            .map_err(|_| DriverError::InvalidToken)?;
+
+        let now = clock::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        if now > expires {
+            return Err(DriverError::TokenExpired);
+        }
+
+        Ok(ObjectPath::from(&Key::new(&key)?))
+    }
+
+    /// Returns the MAC of the given key and expiry for the signer purpose.
+    fn mac(&self, key: &str, expires: u64) -> Hmac<Sha256> {
+        mac::hmac_sha256(
+            &self.secret,
+            &[
+                self.purpose.as_bytes(),
+                key.as_bytes(),
+                expires.to_string().as_bytes(),
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn can_sign_and_verify() {
+        let signer = BlobSigner::new("secret").with_purpose("avatar");
+        let path = Path::new("uploads/my avatar.png");
+
+        let signed_id = signer.sign(path, Duration::from_secs(60)).unwrap();
+        assert!(!signed_id.contains("uploads"));
+        assert_eq!(signer.verify(&signed_id).unwrap(), ObjectPath::from(path));
+
+        for other in [
+            BlobSigner::new("other").with_purpose("avatar"),
+            BlobSigner::new("secret").with_purpose("document"),
+        ] {
+            assert!(matches!(
+                other.verify(&signed_id),
+                Err(DriverError::InvalidToken)
+            ));
+        }
+        let forged = signer.sign(Path::new("uploads/other.png"), Duration::from_secs(60));
+        let (forged_key, _) = forged.as_deref().unwrap().split_once(SEPARATOR).unwrap();
+        let (_, rest) = signed_id.split_once(SEPARATOR).unwrap();
+        assert!(matches!(
+            signer.verify(&format!("{forged_key}{SEPARATOR}{rest}")),
+            Err(DriverError::InvalidToken)
+        ));
+        assert!(matches!(
+            signer.verify("invalid"),
+            Err(DriverError::InvalidToken)
+        ));
+    }
+
+    #[test]
+    fn can_reject_expired_ids() {
+        let signer = BlobSigner::new("secret");
+        let signed_id = signer
+            .sign_until(Path::new("file.txt"), UNIX_EPOCH + Duration::from_secs(1))
+            .unwrap();
+
+        assert!(matches!(
+            signer.verify(&signed_id),
+            Err(DriverError::TokenExpired)
+        ));
+    }
+}
@@ -0,0 +1,460 @@
+//! # Stats Module
+//!
+//! The `stats` module defines [`StoreStats`], the cumulative counters returned
+//! by [`crate::store::Store::stats`], so applications without a metrics stack
+//! can still expose their storage numbers, e.g. on an admin page.
+//!
+//! Every operation reaching the driver is counted by its
+//! [`crate::drivers::Driver`] method name, with the bytes transferred and the
+//! failures by [`DriverError::class`]. Views of a store, such as
+//! [`crate::store::Store::scoped`], share the counters of their store.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::Path;
+//! use active_storage::StoreConfig;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!     store.write(Path::new("file.txt"), "my content").await.unwrap();
+//!     assert!(store.read::<String>(Path::new("missing.txt")).await.is_err());
+//!
+//!     let stats = store.stats();
+//!     assert_eq!(stats.operations["write"], 1);
+//!     assert_eq!(stats.bytes_written, 10);
+//!     assert_eq!(stats.errors["not_found"], 1);
+//! }
+//! ```
+use std::{
+    collections::BTreeMap,
+    ops::Range,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use bytes::Bytes;
+
+use crate::{
+    drivers::{
+        capabilities::{Capabilities, ObjectVersion, PresignExt, VersioningExt},
+        Acl, ContainerOptions, Driver, ObjectMetadata, ReadOptions, RetentionPolicy, WriteOptions,
+    },
+    errors::{DriverError, DriverResult},
+    key::ObjectPath,
+    upload::{PendingUpload, UploadPart},
+};
+
+/// Struct representing the cumulative counters of a store.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StoreStats {
+    /// The number of operations, keyed by driver method name, e.g. `read`.
+    pub operations: BTreeMap<String, u64>,
+    /// The number of bytes read from the backend.
+    pub bytes_read: u64,
+    /// The number of bytes written to the backend.
+    pub bytes_written: u64,
+    /// The number of failed operations, keyed by [`DriverError::class`].
+    pub errors: BTreeMap<String, u64>,
+}
+
+/// The counters shared by a store and its views.
+pub(crate) type Counters = Arc<Mutex<StoreStats>>;
+
+/// The `InstrumentedDriver` struct wraps a [`Driver`] and updates the
+/// counters of its store on every operation.
+pub(crate) struct InstrumentedDriver {
+    inner: Box<dyn Driver>,
+    counters: Counters,
+}
+
+impl Clone for InstrumentedDriver {
+    fn clone(&self) -> Self {
+        Self {
+            inner: dyn_clone::clone_box(&*self.inner),
+            counters: self.counters.clone(),
+        }
+    }
+}
+
+impl InstrumentedDriver {
+    pub(crate) fn new(inner: Box<dyn Driver>, counters: Counters) -> Self {
+        Self { inner, counters }
+    }
+
+    /// Counts the operation and its failure, returning its result.
+    fn record<T>(&self, operation: &'static str, result: DriverResult<T>) -> DriverResult<T> {
+        let mut stats = self.counters.lock().unwrap();
+        *stats.operations.entry(operation.to_string()).or_default() += 1;
+        if let Err(err) = &result {
+            *stats.errors.entry(err.class().to_string()).or_default() += 1;
+        }
+        drop(stats);
+        result
+    }
+
+    /// Counts the operation and the bytes it read.
+    fn record_read<T: AsRef<[u8]>>(
+        &self,
+        operation: &'static str,
+        result: DriverResult<T>,
+    ) -> DriverResult<T> {
+        if let Ok(content) = &result {
+            self.counters.lock().unwrap().bytes_read += content.as_ref().len() as u64;
+        }
+        self.record(operation, result)
+    }
+
+    /// Counts the operation and, when it succeeded, the bytes it wrote.
+    fn record_write<T>(
+        &self,
+        operation: &'static str,
+        size: usize,
+        result: DriverResult<T>,
+    ) -> DriverResult<T> {
+        if result.is_ok() {
+            self.counters.lock().unwrap().bytes_written += size as u64;
+        }
+        self.record(operation, result)
+    }
+}
+
+#[async_trait::async_trait]
+impl Driver for InstrumentedDriver {
+    async fn read(&self, path: &ObjectPath) -> DriverResult<Bytes> {
+        self.record_read("read", self.inner.read(path).await)
+    }
+
+    async fn file_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.record("file_exists", self.inner.file_exists(path).await)
+    }
+
+    async fn write(&self, path: &ObjectPath, content: Bytes) -> DriverResult<()> {
+        let size = content.len();
+        self.record_write("write", size, self.inner.write(path, content).await)
+    }
+
+    async fn write_with_options(
+        &self,
+        path: &ObjectPath,
+        content: Bytes,
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        let size = content.len();
+        let result = self.inner.write_with_options(path, content, options).await;
+        self.record_write("write_with_options", size, result)
+    }
+
+    async fn write_with_acl(
+        &self,
+        path: &ObjectPath,
+        content: Bytes,
+        acl: &Acl,
+    ) -> DriverResult<()> {
+        let size = content.len();
+        let result = self.inner.write_with_acl(path, content, acl).await;
+        self.record_write("write_with_acl", size, result)
+    }
+
+    async fn set_acl(&self, path: &ObjectPath, acl: &Acl) -> DriverResult<()> {
+        self.record("set_acl", self.inner.set_acl(path, acl).await)
+    }
+
+    async fn delete(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.record("delete", self.inner.delete(path).await)
+    }
+
+    async fn delete_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.record("delete_directory", self.inner.delete_directory(path).await)
+    }
+
+    async fn last_modified(&self, path: &ObjectPath) -> DriverResult<SystemTime> {
+        self.record("last_modified", self.inner.last_modified(path).await)
+    }
+
+    async fn size(&self, path: &ObjectPath) -> DriverResult<u64> {
+        self.record("size", self.inner.size(path).await)
+    }
+
+    async fn create_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+        self.record("create_directory", self.inner.create_directory(path).await)
+    }
+
+    async fn directory_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.record("directory_exists", self.inner.directory_exists(path).await)
+    }
+
+    async fn purge_all(&self) -> DriverResult<()> {
+        self.record("purge_all", self.inner.purge_all().await)
+    }
+
+    async fn set_last_modified(
+        &self,
+        path: &ObjectPath,
+        last_modified: SystemTime,
+    ) -> DriverResult<()> {
+        let result = self.inner.set_last_modified(path, last_modified).await;
+        self.record("set_last_modified", result)
+    }
+
+    async fn delete_if_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.record("delete_if_exists", self.inner.delete_if_exists(path).await)
+    }
+
+    async fn stat(&self, path: &ObjectPath) -> DriverResult<Option<ObjectMetadata>> {
+        self.record("stat", self.inner.stat(path).await)
+    }
+
+    async fn read_if_modified_since(
+        &self,
+        path: &ObjectPath,
+        since: SystemTime,
+    ) -> DriverResult<Option<Bytes>> {
+        let result = self.inner.read_if_modified_since(path, since).await;
+        if let Ok(Some(content)) = &result {
+            self.counters.lock().unwrap().bytes_read += content.len() as u64;
+        }
+        self.record("read_if_modified_since", result)
+    }
+
+    async fn read_with_options(
+        &self,
+        path: &ObjectPath,
+        options: &ReadOptions,
+    ) -> DriverResult<Bytes> {
+        let result = self.inner.read_with_options(path, options).await;
+        self.record_read("read_with_options", result)
+    }
+
+    async fn read_range(&self, path: &ObjectPath, range: Range<u64>) -> DriverResult<Vec<u8>> {
+        self.record_read("read_range", self.inner.read_range(path, range).await)
+    }
+
+    async fn list(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        self.record("list", self.inner.list(path).await)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn as_presign(&self) -> Option<&dyn PresignExt> {
+        self.inner.as_presign().map(|_| self as &dyn PresignExt)
+    }
+
+    fn as_versioning(&self) -> Option<&dyn VersioningExt> {
+        self.inner
+            .as_versioning()
+            .map(|_| self as &dyn VersioningExt)
+    }
+
+    async fn public_url(&self, path: &ObjectPath) -> DriverResult<String> {
+        self.record("public_url", self.inner.public_url(path).await)
+    }
+
+    async fn container_exists(&self) -> DriverResult<bool> {
+        self.record("container_exists", self.inner.container_exists().await)
+    }
+
+    async fn ensure_container(&self, options: &ContainerOptions) -> DriverResult<()> {
+        self.record(
+            "ensure_container",
+            self.inner.ensure_container(options).await,
+        )
+    }
+
+    async fn health_check(&self) -> DriverResult<()> {
+        self.record("health_check", self.inner.health_check().await)
+    }
+
+    async fn apply_retention_policies(&self, policies: &[RetentionPolicy]) -> DriverResult<()> {
+        self.record(
+            "apply_retention_policies",
+            self.inner.apply_retention_policies(policies).await,
+        )
+    }
+
+    async fn set_legal_hold(&self, path: &ObjectPath, hold: bool) -> DriverResult<()> {
+        self.record(
+            "set_legal_hold",
+            self.inner.set_legal_hold(path, hold).await,
+        )
+    }
+
+    async fn legal_hold(&self, path: &ObjectPath) -> DriverResult<bool> {
+        self.record("legal_hold", self.inner.legal_hold(path).await)
+    }
+
+    async fn list_legal_holds(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+        self.record("list_legal_holds", self.inner.list_legal_holds(path).await)
+    }
+
+    async fn create_upload(&self, path: &ObjectPath) -> DriverResult<String> {
+        self.record("create_upload", self.inner.create_upload(path).await)
+    }
+
+    async fn create_upload_with_options(
+        &self,
+        path: &ObjectPath,
+        options: &WriteOptions,
+    ) -> DriverResult<String> {
+        self.record(
+            "create_upload",
+            self.inner.create_upload_with_options(path, options).await,
+        )
+    }
+
+    async fn upload_part(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        part_number: u32,
+        content: Bytes,
+    ) -> DriverResult<UploadPart> {
+        let size = content.len();
+        let result = self
+            .inner
+            .upload_part(path, upload_id, part_number, content)
+            .await;
+        self.record_write("upload_part", size, result)
+    }
+
+    async fn list_upload_parts(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+    ) -> DriverResult<Vec<UploadPart>> {
+        let result = self.inner.list_upload_parts(path, upload_id).await;
+        self.record("list_upload_parts", result)
+    }
+
+    async fn complete_upload(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+    ) -> DriverResult<()> {
+        let result = self.inner.complete_upload(path, upload_id, parts).await;
+        self.record("complete_upload", result)
+    }
+
+    async fn complete_upload_with_options(
+        &self,
+        path: &ObjectPath,
+        upload_id: &str,
+        parts: &[UploadPart],
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        let result = self
+            .inner
+            .complete_upload_with_options(path, upload_id, parts, options)
+            .await;
+        self.record("complete_upload", result)
+    }
+
+    async fn list_uploads(&self, prefix: &ObjectPath) -> DriverResult<Vec<PendingUpload>> {
+        self.record("list_uploads", self.inner.list_uploads(prefix).await)
+    }
+
+    async fn abort_upload(&self, path: &ObjectPath, upload_id: &str) -> DriverResult<()> {
+        self.record(
+            "abort_upload",
+            self.inner.abort_upload(path, upload_id).await,
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl PresignExt for InstrumentedDriver {
+    async fn presign_read(&self, path: &ObjectPath, expires_in: Duration) -> DriverResult<String> {
+        let result = match self.inner.as_presign() {
+            Some(presign) => presign.presign_read(path, expires_in).await,
+            None => Err(DriverError::Unsupported("presigned URLs")),
+        };
+        self.record("presign_read", result)
+    }
+
+    async fn presign_write(&self, path: &ObjectPath, expires_in: Duration) -> DriverResult<String> {
+        let result = match self.inner.as_presign() {
+            Some(presign) => presign.presign_write(path, expires_in).await,
+            None => Err(DriverError::Unsupported("presigned URLs")),
+        };
+        self.record("presign_write", result)
+    }
+}
+
+#[async_trait::async_trait]
+impl VersioningExt for InstrumentedDriver {
+    async fn list_versions(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectVersion>> {
+        let result = match self.inner.as_versioning() {
+            Some(versioning) => versioning.list_versions(path).await,
+            None => Err(DriverError::Unsupported("versioning")),
+        };
+        self.record("list_versions", result)
+    }
+
+    async fn read_version(&self, path: &ObjectPath, version_id: &str) -> DriverResult<Vec<u8>> {
+        let result = match self.inner.as_versioning() {
+            Some(versioning) => versioning.read_version(path, version_id).await,
+            None => Err(DriverError::Unsupported("versioning")),
+        };
+        self.record_read("read_version", result)
+    }
+
+    async fn delete_version(&self, path: &ObjectPath, version_id: &str) -> DriverResult<()> {
+        let result = match self.inner.as_versioning() {
+            Some(versioning) => versioning.delete_version(path, version_id).await,
+            None => Err(DriverError::Unsupported("versioning")),
+        };
+        self.record("delete_version", result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    use super::*;
+    use crate::StoreConfig;
+
+    #[tokio::test]
+    async fn can_count_operations() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let tenant = store.scoped(Path::new("tenant")).unwrap();
+
+        assert!(tenant.write(Path::new("file.txt"), "content").await.is_ok());
+        assert!(store
+            .read_range(Path::new("tenant/file.txt"), 0..4)
+            .await
+            .is_ok());
+        assert!(store.delete(Path::new("missing.txt")).await.is_err());
+        assert!(store
+            .read::<String>(Path::new("../file.txt"))
+            .await
+            .is_err());
+
+        let stats = store.stats();
+        assert_eq!(
+            stats.operations,
+            BTreeMap::from([
+                ("delete".to_string(), 1),
+                ("legal_hold".to_string(), 1),
+                ("read".to_string(), 1),
+                ("read_range".to_string(), 1),
+                ("write".to_string(), 1),
+            ])
+        );
+        assert_eq!(stats.bytes_written, 7);
+        assert_eq!(stats.bytes_read, 4);
+        assert_eq!(
+            stats.errors,
+            BTreeMap::from([
+                ("invalid_input".to_string(), 1),
+                ("not_found".to_string(), 1),
+            ])
+        );
+        assert_eq!(tenant.stats(), stats);
+    }
+}
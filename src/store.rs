@@ -10,21 +10,73 @@
 #![doc = include_str!("../examples/disk.rs")]
 //! # }
 //! ```
-use std::path::Path;
+use std::{
+    collections::BTreeSet,
+    ops::Range,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use bytes::Bytes;
+use futures::{stream, Stream, StreamExt};
+use tokio::io::AsyncWriteExt;
 
+#[cfg(feature = "archive")]
+use crate::archive::{self, ArchiveSource};
+#[cfg(feature = "history")]
+use crate::drivers::history::{self, HistoryDriver, HistoryEntry};
+#[cfg(feature = "memory_cache")]
+use crate::drivers::memory_cache::{MemoryCacheDriver, MemoryCacheOptions};
+#[cfg(feature = "inventory")]
+use crate::inventory::{self, InventoryFormat};
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use crate::transfer::ChecksumWriter;
 use crate::{
-    contents::Contents,
-    drivers::Driver,
+    clock,
+    contents::{Contents, IntoBytes},
+    drivers::{
+        self,
+        capabilities::{Capabilities, PresignExt, VersioningExt},
+        case_insensitive::CaseInsensitiveDriver,
+        coalescing::CoalescingDriver,
+        is_reserved,
+        negative_cache::NegativeCacheDriver,
+        retention::{self, RetentionDriver},
+        scoped::ScopedDriver,
+        Acl, ContainerOptions, Driver, ObjectMetadata, Precondition, ReadOptions, RetentionPolicy,
+        WriteOptions,
+    },
     errors::{DriverError, DriverResult},
+    filename::{self, Collision, SanitizeOptions},
+    health::{HealthCheck, HealthStatus},
+    image::ImageSanitizer,
+    key::ObjectPath,
+    serve::{self, ByteStream, ServePlan, ServeRequest, ServeResponse},
+    stats::{Counters, InstrumentedDriver, StoreStats},
+    tempfile::TempFile,
+    text::{self, LineStream},
+    transfer::{BandwidthLimiter, TransferOptions},
+    upload::UploadSession,
+    validators::{ValidationError, Validators},
 };
+/// The number of times [`Store::serve_with`] reads a file that changes while
+/// it is served.
+const SERVE_ATTEMPTS: usize = 3;
+
 pub struct Store {
     driver: Box<dyn Driver>,
+    bandwidth: Option<BandwidthLimiter>,
+    counters: Counters,
+    records: bool,
 }
 
 impl Clone for Store {
     fn clone(&self) -> Self {
         Self {
             driver: dyn_clone::clone_box(&*self.driver),
+            bandwidth: self.bandwidth.clone(),
+            counters: self.counters.clone(),
+            records: self.records,
         }
     }
 }
@@ -32,8 +84,46 @@ impl Clone for Store {
 impl Store {
     #[must_use]
     pub fn new(driver: Box<dyn Driver>) -> Self {
-        Self { driver }
+        let counters = Counters::default();
+        Self {
+            driver: Box::new(InstrumentedDriver::new(driver, counters.clone())),
+            bandwidth: None,
+            counters,
+            records: false,
+        }
+    }
+
+    /// Caps the throughput of the streaming transfers of the store (streaming
+    /// reads and writes and parallel downloads) with the given limiter.
+    /// Clones of the store share the limiter.
+    #[must_use]
+    pub fn with_bandwidth_limit(mut self, limiter: BandwidthLimiter) -> Self {
+        self.bandwidth = Some(limiter);
+        self
+    }
+
+    /// Returns a handle on the same store that also sees the records the
+    /// crate keeps under the reserved `.active-storage-` prefixes, for the
+    /// modules maintaining them.
+    #[must_use]
+    pub(crate) fn records(&self) -> Self {
+        Self {
+            records: true,
+            ..self.clone()
+        }
+    }
+
+    /// Converts the path given to a method of the store, refusing the paths
+    /// under the reserved prefixes unless the handle comes from
+    /// [`Self::records`].
+    fn checked(&self, path: impl Into<ObjectPath>) -> DriverResult<ObjectPath> {
+        let path = path.into();
+        if !self.records && is_reserved(&path) {
+            return Err(DriverError::ReservedPath);
+        }
+        Ok(path)
     }
+
     /// Checks if a file exists at the specified path within the storage.
     ///
     /// # Parameters
@@ -59,7 +149,8 @@ impl Store {
     ///
     /// Returns an error if the underlying `Driver` encounters an issue while
     /// checking file existence.
-    pub async fn file_exists(&self, path: &Path) -> DriverResult<bool> {
+    pub async fn file_exists(&self, path: impl Into<ObjectPath>) -> DriverResult<bool> {
+        let path: &ObjectPath = &self.checked(path)?;
         self.driver.file_exists(path).await
     }
 
@@ -89,8 +180,235 @@ impl Store {
     ///
     /// Returns an error if the underlying `Driver` encounters an issue while
     /// writing to the file.
-    pub async fn write<C: AsRef<[u8]> + Send>(&self, path: &Path, content: C) -> DriverResult<()> {
-        self.driver.write(path, content.as_ref().to_vec()).await
+    pub async fn write<C: IntoBytes + Send>(
+        &self,
+        path: impl Into<ObjectPath>,
+        content: C,
+    ) -> DriverResult<()> {
+        let path: &ObjectPath = &self.checked(path)?;
+        self.driver.write(path, content.into_bytes()).await
+    }
+
+    /// Writes the contents under the given directory, at a key derived from
+    /// a user provided file name with [`filename::sanitize`], and returns the
+    /// path of the written file.
+    ///
+    /// See the [`crate::filename`] module for an example.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::PreconditionFailed`] if the file exists and the
+    /// collision policy is [`Collision::Error`], [`DriverError::ReservedPath`]
+    /// if the directory is reserved, or an error if the file could not be
+    /// written.
+    pub async fn write_named<C: IntoBytes + Send>(
+        &self,
+        dir: impl Into<ObjectPath>,
+        name: &str,
+        content: C,
+        collision: Collision,
+    ) -> DriverResult<ObjectPath> {
+        const MAX_SUFFIX: usize = 10_000;
+
+        let dir = self.checked(dir)?;
+        let name = filename::sanitize(name, &SanitizeOptions::default());
+        let content = content.into_bytes();
+        if collision == Collision::Overwrite {
+            let path = dir.join(&name);
+            self.driver.write(&path, content).await?;
+            return Ok(path);
+        }
+
+        let options = WriteOptions::default().with_precondition(Precondition::IfNotExists);
+        for n in 0..=MAX_SUFFIX {
+            let path = match n {
+                0 => dir.join(&name),
+                n => dir.join(filename::with_suffix(&name, n)),
+            };
+            match self
+                .driver
+                .write_with_options(&path, content.clone(), &options)
+                .await
+            {
+                Err(DriverError::PreconditionFailed) if collision == Collision::Suffix => {}
+                result => return result.map(|()| path),
+            }
+        }
+
+        Err(DriverError::PreconditionFailed)
+    }
+
+    /// Runs the validators against the contents and writes them when they
+    /// pass.
+    ///
+    /// See the [`crate::validators`] module for an example.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::Validation`] with every failure if the contents
+    /// are invalid, or an error if the file could not be written.
+    pub async fn write_validated<C: IntoBytes + Send>(
+        &self,
+        path: impl Into<ObjectPath>,
+        content: C,
+        validators: &Validators,
+    ) -> DriverResult<()> {
+        let path: &ObjectPath = &self.checked(path)?;
+        let content = content.into_bytes();
+        validators.validate(&path.to_path_buf(), &content)?;
+        self.driver.write(path, content).await
+    }
+
+    /// Strips the metadata of an image content with the sanitizer and writes
+    /// the result. Other contents are written unchanged.
+    ///
+    /// See the [`crate::image`] module for an example.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::Validation`] if the image is malformed or has
+    /// too many pixels, or an error if the file could not be written.
+    pub async fn write_sanitized<C: IntoBytes + Send>(
+        &self,
+        path: impl Into<ObjectPath>,
+        content: C,
+        sanitizer: &ImageSanitizer,
+    ) -> DriverResult<()> {
+        let path: &ObjectPath = &self.checked(path)?;
+        let content = sanitizer
+            .sanitize(content.into_bytes())
+            .map_err(|failure| ValidationError {
+                failures: vec![failure],
+            })?;
+        self.driver.write(path, content).await
+    }
+
+    /// Returns the cumulative counters of the operations of the store and of
+    /// its views, such as [`Self::scoped`].
+    ///
+    /// See the [`crate::stats`] module for an example.
+    #[must_use]
+    pub fn stats(&self) -> StoreStats {
+        self.counters.lock().unwrap().clone()
+    }
+
+    /// Returns the optional features the store driver supports natively.
+    ///
+    /// See the [`crate::drivers::capabilities`] module for an example.
+    #[must_use]
+    pub fn capabilities(&self) -> Capabilities {
+        self.driver.capabilities()
+    }
+
+    /// Returns the presigning extension of the store driver, or `None` when
+    /// the backend can't sign expiring URLs.
+    #[must_use]
+    pub fn as_presign(&self) -> Option<&dyn PresignExt> {
+        self.driver.as_presign()
+    }
+
+    /// Returns the versioning extension of the store driver, or `None` when
+    /// the backend doesn't keep previous versions of files.
+    #[must_use]
+    pub fn as_versioning(&self) -> Option<&dyn VersioningExt> {
+        self.driver.as_versioning()
+    }
+
+    /// Writes the contents to a file with the given [`WriteOptions`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::PathBuf;
+    /// use active_storage::{
+    ///     drivers::{Precondition, WriteOptions},
+    ///     errors::DriverError,
+    ///     StoreConfig,
+    /// };
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     let file_path = PathBuf::from("test.txt");
+    ///     let options = WriteOptions::default()
+    ///         .with_content_type("text/plain")
+    ///         .with_precondition(Precondition::IfNotExists);
+    ///
+    ///     assert!(inmem_driver
+    ///         .write_with_options(file_path.as_path(), "my content", &options)
+    ///         .await
+    ///         .is_ok());
+    ///     assert!(matches!(
+    ///         inmem_driver.write_with_options(file_path.as_path(), "other", &options).await,
+    ///         Err(DriverError::PreconditionFailed)
+    ///     ));
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::PreconditionFailed`] if the precondition is not
+    /// satisfied, [`DriverError::Unsupported`] if the driver doesn't support
+    /// an option, or an error if the file could not be written.
+    pub async fn write_with_options<C: IntoBytes + Send>(
+        &self,
+        path: impl Into<ObjectPath>,
+        content: C,
+        options: &WriteOptions,
+    ) -> DriverResult<()> {
+        let path: &ObjectPath = &self.checked(path)?;
+        self.driver
+            .write_with_options(path, content.into_bytes(), options)
+            .await
+    }
+
+    /// Writes the contents to a file with the given access level, e.g. to
+    /// publish an asset from a private bucket. [`Self::write`] keeps the
+    /// container default.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::PathBuf;
+    /// use active_storage::{drivers::Acl, errors::DriverError, StoreConfig};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     let file_path = PathBuf::from("test.txt");
+    ///     assert!(matches!(
+    ///         inmem_driver.write_with_acl(file_path.as_path(), "my content", Acl::PublicRead).await,
+    ///         Err(DriverError::Unsupported(_))
+    ///     ));
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::Unsupported`] if the driver has no per object
+    /// access control (disk, in-memory and Azure, where access is set per
+    /// container), or an error if the file could not be written.
+    pub async fn write_with_acl<C: IntoBytes + Send>(
+        &self,
+        path: impl Into<ObjectPath>,
+        content: C,
+        acl: Acl,
+    ) -> DriverResult<()> {
+        let path: &ObjectPath = &self.checked(path)?;
+        self.driver
+            .write_with_acl(path, content.into_bytes(), &acl)
+            .await
+    }
+
+    /// Changes the access level of an existing file.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::Unsupported`] if the driver has no per object
+    /// access control, or an error if the file does not exist.
+    pub async fn set_acl(&self, path: impl Into<ObjectPath>, acl: Acl) -> DriverResult<()> {
+        let path: &ObjectPath = &self.checked(path)?;
+        self.driver.set_acl(path, &acl).await
     }
 
     /// Reads the contents of a file at the specified path within the storage.
@@ -121,12 +439,279 @@ impl Store {
     ///
     /// Returns an error if the underlying `Driver` encounters an issue while
     /// reading from the file.
-    pub async fn read<T: TryFrom<Contents>>(&self, path: &Path) -> DriverResult<T> {
+    pub async fn read<T: TryFrom<Contents>>(&self, path: impl Into<ObjectPath>) -> DriverResult<T> {
+        let path: &ObjectPath = &self.checked(path)?;
         Contents::from(self.driver.read(path).await?)
             .try_into()
             .map_or_else(|_| Err(DriverError::DecodeError), |content| Ok(content))
     }
 
+    /// Reads the contents of a file with the given [`ReadOptions`], e.g.
+    /// custom headers for provider features the driver doesn't cover.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::Path;
+    /// use active_storage::{drivers::ReadOptions, errors::DriverError, StoreConfig};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     let file_path = Path::new("test.txt");
+    ///     inmem_driver.write(file_path, "my content").await.unwrap();
+    ///
+    ///     let options = ReadOptions::default();
+    ///     let content: String = inmem_driver.read_with_options(file_path, &options).await.unwrap();
+    ///     assert_eq!(content, "my content");
+    ///
+    ///     // only drivers sending HTTP requests can attach custom headers
+    ///     let options = options.with_header("x-amz-request-payer", "requester");
+    ///     assert!(matches!(
+    ///         inmem_driver.read_with_options::<String>(file_path, &options).await,
+    ///         Err(DriverError::Unsupported(_))
+    ///     ));
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an option is not supported by the driver, or the
+    /// underlying `Driver` encounters an issue while reading from the file.
+    pub async fn read_with_options<T: TryFrom<Contents>>(
+        &self,
+        path: impl Into<ObjectPath>,
+        options: &ReadOptions,
+    ) -> DriverResult<T> {
+        let path: &ObjectPath = &self.checked(path)?;
+        Contents::from(self.driver.read_with_options(path, options).await?)
+            .try_into()
+            .map_or_else(|_| Err(DriverError::DecodeError), |content| Ok(content))
+    }
+
+    /// Reads the contents of a file, returning `None` when it does not exist.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::PathBuf;
+    /// use active_storage::StoreConfig;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     let file_path = PathBuf::from("test.txt");
+    ///     assert_eq!(inmem_driver.read_opt::<String>(file_path.as_path()).await.unwrap(), None);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but could not be read or decoded.
+    pub async fn read_opt<T: TryFrom<Contents>>(
+        &self,
+        path: impl Into<ObjectPath>,
+    ) -> DriverResult<Option<T>> {
+        let path: &ObjectPath = &self.checked(path)?;
+        match self.read(path).await {
+            Ok(content) => Ok(Some(content)),
+            Err(DriverError::ResourceNotFound) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Reads the contents of a file, first writing the contents returned by
+    /// `default` when it does not exist.
+    ///
+    /// When the driver supports [`Capabilities::CONDITIONAL_WRITES`], the
+    /// default is written only if no other writer created the file in the
+    /// meantime, in which case that file is read instead.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::PathBuf;
+    /// use active_storage::StoreConfig;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     let file_path = PathBuf::from("settings.txt");
+    ///     let settings: String = inmem_driver
+    ///         .read_or_write_with(file_path.as_path(), || "theme=dark")
+    ///         .await
+    ///         .unwrap();
+    ///     assert_eq!(settings, "theme=dark");
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file could not be read, written or decoded.
+    pub async fn read_or_write_with<T, C, F>(
+        &self,
+        path: impl Into<ObjectPath>,
+        default: F,
+    ) -> DriverResult<T>
+    where
+        T: TryFrom<Contents>,
+        C: IntoBytes + Send,
+        F: FnOnce() -> C + Send,
+    {
+        let path: &ObjectPath = &self.checked(path)?;
+        if let Some(content) = self.read_opt(path).await? {
+            return Ok(content);
+        }
+
+        let content = default().into_bytes();
+        if self
+            .capabilities()
+            .contains(Capabilities::CONDITIONAL_WRITES)
+        {
+            let options = WriteOptions::default().with_precondition(Precondition::IfNotExists);
+            match self
+                .driver
+                .write_with_options(path, content.clone(), &options)
+                .await
+            {
+                Err(DriverError::PreconditionFailed) => return self.read(path).await,
+                result => result?,
+            }
+        } else {
+            self.driver.write(path, content.clone()).await?;
+        }
+
+        Contents::from(content)
+            .try_into()
+            .map_err(|_| DriverError::DecodeError)
+    }
+
+    /// Reads the contents of a file as [`Bytes`], without the copy of the
+    /// typed [`Self::read`] conversions.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::PathBuf;
+    /// use active_storage::StoreConfig;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     let file_path = PathBuf::from("test.txt");
+    ///     inmem_driver.write(file_path.as_path(), "my content").await.unwrap();
+    ///     let content = inmem_driver.read_bytes(file_path.as_path()).await.unwrap();
+    ///     assert_eq!(content.as_ref(), b"my content");
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the underlying `Driver` encounters an issue while
+    /// reading from the file.
+    pub async fn read_bytes(&self, path: impl Into<ObjectPath>) -> DriverResult<Bytes> {
+        let path: &ObjectPath = &self.checked(path)?;
+        self.driver.read(path).await
+    }
+
+    /// Reads the contents of a file when it was modified after the given
+    /// time, returning `None` otherwise, so cache refreshes skip downloading
+    /// unchanged files. Object stores send a conditional `GET`, which
+    /// compares whole seconds.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::{path::Path, time::SystemTime};
+    /// use active_storage::StoreConfig;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     let file_path = Path::new("test.txt");
+    ///     inmem_driver.write(file_path, "my content").await.unwrap();
+    ///     let last_modified = inmem_driver.last_modified(file_path).await.unwrap();
+    ///
+    ///     let content = inmem_driver
+    ///         .read_if_modified_since(file_path, SystemTime::UNIX_EPOCH)
+    ///         .await
+    ///         .unwrap();
+    ///     assert_eq!(content.unwrap().as_ref(), b"my content");
+    ///     assert!(inmem_driver
+    ///         .read_if_modified_since(file_path, last_modified)
+    ///         .await
+    ///         .unwrap()
+    ///         .is_none());
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist or the underlying `Driver`
+    /// encounters an issue while reading from it.
+    pub async fn read_if_modified_since(
+        &self,
+        path: impl Into<ObjectPath>,
+        since: SystemTime,
+    ) -> DriverResult<Option<Bytes>> {
+        let path: &ObjectPath = &self.checked(path)?;
+        self.driver.read_if_modified_since(path, since).await
+    }
+
+    /// Serializes the value to compact JSON and writes it to the specified
+    /// path.
+    ///
+    /// See the [`crate::json`] module for an example.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value could not be serialized or the file
+    /// could not be written.
+    #[cfg(feature = "json")]
+    pub async fn write_json<T: serde::Serialize + Sync + ?Sized>(
+        &self,
+        path: impl Into<ObjectPath>,
+        value: &T,
+    ) -> DriverResult<()> {
+        let path: &ObjectPath = &self.checked(path)?;
+        self.write_json_with(path, value, crate::json::JsonFormat::Compact)
+            .await
+    }
+
+    /// Serializes the value to JSON in the given format and writes it to the
+    /// specified path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value could not be serialized or the file
+    /// could not be written.
+    #[cfg(feature = "json")]
+    pub async fn write_json_with<T: serde::Serialize + Sync + ?Sized>(
+        &self,
+        path: impl Into<ObjectPath>,
+        value: &T,
+        format: crate::json::JsonFormat,
+    ) -> DriverResult<()> {
+        let path: &ObjectPath = &self.checked(path)?;
+        let content = crate::json::to_vec(value, format)?;
+        self.driver.write(path, content.into()).await
+    }
+
+    /// Reads the JSON file at the specified path and deserializes it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::DecodeError`] if the contents are not valid JSON
+    /// for the type, or an error if the file could not be read.
+    #[cfg(feature = "json")]
+    pub async fn read_json<T: serde::de::DeserializeOwned>(
+        &self,
+        path: impl Into<ObjectPath>,
+    ) -> DriverResult<T> {
+        let path: &ObjectPath = &self.checked(path)?;
+        crate::json::from_slice(&self.driver.read(path).await?)
+    }
+
     /// Deletes a file at the specified path within the storage.
     ///
     /// # Parameters
@@ -151,11 +736,46 @@ impl Store {
     /// # Errors
     ///
     /// Returns an error if the underlying `Driver` encounters an issue while
-    /// deleting the file.
-    pub async fn delete(&self, path: &Path) -> DriverResult<()> {
+    /// deleting the file. Deleting a missing file returns
+    /// [`DriverError::ResourceNotFound`], except with S3 where deletes are
+    /// idempotent; use [`Self::delete_if_exists`] to tolerate missing files
+    /// with every driver. Returns [`DriverError::LegalHold`] if the file is
+    /// under legal hold.
+    pub async fn delete(&self, path: impl Into<ObjectPath>) -> DriverResult<()> {
+        let path: &ObjectPath = &self.checked(path)?;
+        drivers::check_legal_hold(&*self.driver, path).await?;
         self.driver.delete(path).await
     }
 
+    /// Deletes the file at the specified path when it exists, returning
+    /// whether it existed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::PathBuf;
+    /// use active_storage::StoreConfig;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     let file_path = PathBuf::from("test.txt");
+    ///     inmem_driver.write(file_path.as_path(), "my content").await.unwrap();
+    ///     assert!(inmem_driver.delete_if_exists(file_path.as_path()).await.unwrap());
+    ///     assert!(!inmem_driver.delete_if_exists(file_path.as_path()).await.unwrap());
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::LegalHold`] if the file is under legal hold, or
+    /// an error if the file exists and could not be deleted.
+    pub async fn delete_if_exists(&self, path: impl Into<ObjectPath>) -> DriverResult<bool> {
+        let path: &ObjectPath = &self.checked(path)?;
+        drivers::check_legal_hold(&*self.driver, path).await?;
+        self.driver.delete_if_exists(path).await
+    }
+
     /// Deletes a directory at the specified path within the storage.
     ///
     /// # Parameters
@@ -180,39 +800,2172 @@ impl Store {
     ///
     /// # Errors
     ///
-    /// Returns an error if the underlying `Driver` encounters an issue while
-    /// deleting the directory.
-    pub async fn delete_directory(&self, path: &Path) -> DriverResult<()> {
+    /// Returns [`DriverError::RefusingToDeleteRoot`] if the path refers to the
+    /// storage root, [`DriverError::LegalHold`] if a file under the directory
+    /// is under legal hold, or an error if the underlying `Driver` encounters
+    /// an issue while deleting the directory.
+    pub async fn delete_directory(&self, path: impl Into<ObjectPath>) -> DriverResult<()> {
+        let path: &ObjectPath = &self.checked(path)?;
+        drivers::check_no_legal_holds(&*self.driver, path).await?;
         self.driver.delete_directory(path).await
     }
 
-    /// Retrieves the last modified timestamp of a file at the specified path
-    /// within the storage.
+    /// Deletes every file of the storage. [`Self::delete_directory`] refuses
+    /// the storage root, so emptying the whole storage needs this explicit
+    /// call.
     ///
-    /// # Parameters
+    /// # Examples
     ///
-    /// - `path`: The path to the file for which the last modified timestamp is
-    ///   retrieved.
+    /// ```rust
+    /// use std::path::Path;
+    /// use active_storage::{errors::DriverError, StoreConfig};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     inmem_driver.write(Path::new("foo/bar.txt"), "my content").await.unwrap();
+    ///     assert!(matches!(
+    ///         inmem_driver.delete_directory(Path::new("")).await,
+    ///         Err(DriverError::RefusingToDeleteRoot)
+    ///     ));
+    ///
+    ///     inmem_driver.purge_all().await.unwrap();
+    ///     assert!(!inmem_driver.file_exists(Path::new("foo/bar.txt")).await.unwrap());
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::LegalHold`] if a file is under legal hold, or an
+    /// error if the underlying `Driver` encounters an issue while deleting the
+    /// files.
+    pub async fn purge_all(&self) -> DriverResult<()> {
+        drivers::check_no_legal_holds(&*self.driver, &ObjectPath::default()).await?;
+        self.driver.purge_all().await
+    }
+
+    /// Places or lifts a legal hold on the file at the specified path. The
+    /// store refuses to delete held files until the hold is lifted.
+    ///
+    /// S3 uses native Object Lock legal holds, which need a bucket with
+    /// Object Lock enabled. Other backends keep a marker file under the
+    /// [`drivers::LEGAL_HOLDS_PREFIX`] folder, and also refuse to overwrite
+    /// held files.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// use std::path::PathBuf;
-    /// use active_storage::StoreConfig;
+    /// use std::path::Path;
+    /// use active_storage::{errors::DriverError, StoreConfig};
     ///
     /// #[tokio::main]
     /// async fn main() {
     ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
-    ///     let file_path = PathBuf::from("test.txt");
-    ///     println!("{:#?}", inmem_driver.last_modified(file_path.as_path()).await);
+    ///     let file_path = Path::new("contracts/1.pdf");
+    ///     inmem_driver.write(file_path, "my content").await.unwrap();
+    ///     inmem_driver.set_legal_hold(file_path, true).await.unwrap();
+    ///     assert!(matches!(
+    ///         inmem_driver.delete(file_path).await,
+    ///         Err(DriverError::LegalHold)
+    ///     ));
     /// }
     /// ```
     ///
     /// # Errors
     ///
-    /// Returns an error if the underlying `Driver` encounters an issue while
-    /// retrieving the timestamp.
-    pub async fn last_modified(&self, path: &Path) -> DriverResult<std::time::SystemTime> {
-        self.driver.last_modified(path).await
+    /// Returns [`DriverError::ResourceNotFound`] if the file doesn't exist, or
+    /// an error if the hold could not be changed.
+    pub async fn set_legal_hold(
+        &self,
+        path: impl Into<ObjectPath>,
+        hold: bool,
+    ) -> DriverResult<()> {
+        let path: &ObjectPath = &self.checked(path)?;
+        self.driver.set_legal_hold(path, hold).await
+    }
+
+    /// Returns whether the file at the specified path is under legal hold.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the hold could not be checked.
+    pub async fn legal_hold(&self, path: impl Into<ObjectPath>) -> DriverResult<bool> {
+        let path: &ObjectPath = &self.checked(path)?;
+        self.driver.legal_hold(path).await
+    }
+
+    /// Retrieves the last modified timestamp of a file at the specified path
+    /// within the storage.
+    ///
+    /// # Parameters
+    ///
+    /// - `path`: The path to the file for which the last modified timestamp is
+    ///   retrieved.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::PathBuf;
+    /// use active_storage::StoreConfig;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     let file_path = PathBuf::from("test.txt");
+    ///     println!("{:#?}", inmem_driver.last_modified(file_path.as_path()).await);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the underlying `Driver` encounters an issue while
+    /// retrieving the timestamp.
+    pub async fn last_modified(
+        &self,
+        path: impl Into<ObjectPath>,
+    ) -> DriverResult<std::time::SystemTime> {
+        let path: &ObjectPath = &self.checked(path)?;
+        self.driver.last_modified(path).await
+    }
+
+    /// Returns the size, modification time, content type and `ETag` of a
+    /// file in a single round trip, or `None` when it does not exist.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::PathBuf;
+    /// use active_storage::StoreConfig;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     let file_path = PathBuf::from("test.txt");
+    ///     assert!(inmem_driver.stat(file_path.as_path()).await.unwrap().is_none());
+    ///
+    ///     inmem_driver.write(file_path.as_path(), "my content").await.unwrap();
+    ///     let metadata = inmem_driver.stat(file_path.as_path()).await.unwrap().unwrap();
+    ///     assert_eq!(metadata.size, 10);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the underlying `Driver` encounters an issue while
+    /// querying the file.
+    pub async fn stat(&self, path: impl Into<ObjectPath>) -> DriverResult<Option<ObjectMetadata>> {
+        let path: &ObjectPath = &self.checked(path)?;
+        self.driver.stat(path).await
+    }
+
+    /// Lists all the files under the given directory, recursively. The
+    /// returned paths are relative to the storage root.
+    ///
+    /// # Parameters
+    ///
+    /// - `path`: The directory to list. An empty path lists the whole storage.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use active_storage::{key::ObjectPath, StoreConfig};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     let folder = ObjectPath::new("foo");
+    ///     inmem_driver.write(folder.join("bar.txt"), "my content").await;
+    ///     assert_eq!(
+    ///         inmem_driver.list(&folder).await.unwrap(),
+    ///         vec![folder.join("bar.txt")]
+    ///     );
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the underlying `Driver` encounters an issue while
+    /// listing the files.
+    pub async fn list(&self, path: impl Into<ObjectPath>) -> DriverResult<Vec<ObjectPath>> {
+        let path: &ObjectPath = &self.checked(path)?;
+        let mut files = self.driver.list(path).await?;
+        if !self.records {
+            files.retain(|file| !is_reserved(file));
+        }
+        Ok(files)
+    }
+
+    /// Creates an empty directory at the specified path, so folder
+    /// structures can be prepared before any file is written. Object stores
+    /// emulate it with an empty [`DIRECTORY_MARKER`] file, hidden from
+    /// [`Self::list`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::Path;
+    /// use active_storage::StoreConfig;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     let folder = Path::new("foo/bar");
+    ///     assert!(!inmem_driver.directory_exists(folder).await.unwrap());
+    ///
+    ///     inmem_driver.create_directory(folder).await.unwrap();
+    ///     assert!(inmem_driver.directory_exists(folder).await.unwrap());
+    ///     assert!(inmem_driver.directory_exists(Path::new("foo")).await.unwrap());
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the underlying `Driver` encounters an issue while
+    /// creating the directory.
+    ///
+    /// [`DIRECTORY_MARKER`]: crate::drivers::DIRECTORY_MARKER
+    pub async fn create_directory(&self, path: impl Into<ObjectPath>) -> DriverResult<()> {
+        let path: &ObjectPath = &self.checked(path)?;
+        self.driver.create_directory(path).await
+    }
+
+    /// Checks whether a directory exists at the specified path, either
+    /// created with [`Self::create_directory`] or holding files.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the underlying `Driver` encounters an issue while
+    /// checking the directory.
+    pub async fn directory_exists(&self, path: impl Into<ObjectPath>) -> DriverResult<bool> {
+        let path: &ObjectPath = &self.checked(path)?;
+        self.driver.directory_exists(path).await
+    }
+
+    /// Returns a view of the store that prepends the given prefix to every
+    /// operation and strips it from listings, so each tenant of a multi-tenant
+    /// application can get a sandboxed store.
+    ///
+    /// # Parameters
+    ///
+    /// - `prefix`: The prefix all the operations are scoped under.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::PathBuf;
+    /// use active_storage::StoreConfig;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     let tenant = inmem_driver.scoped(PathBuf::from("tenant-1").as_path()).unwrap();
+    ///     tenant.write(PathBuf::from("test.txt").as_path(), "my content").await;
+    ///     assert!(inmem_driver
+    ///         .file_exists(PathBuf::from("tenant-1").join("test.txt").as_path())
+    ///         .await
+    ///         .unwrap());
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prefix is empty or contains `..` components.
+    pub fn scoped(&self, prefix: impl Into<ObjectPath>) -> DriverResult<Self> {
+        let prefix: &ObjectPath = &self.checked(prefix)?;
+        Ok(Self {
+            driver: Box::new(ScopedDriver::new(
+                dyn_clone::clone_box(&*self.driver),
+                prefix,
+            )?),
+            bandwidth: self.bandwidth.clone(),
+            counters: self.counters.clone(),
+            records: self.records,
+        })
+    }
+
+    /// Returns a view of the store that ignores the case of the paths, for
+    /// applications migrating from Windows shares where `Report.PDF` and
+    /// `report.pdf` are the same file.
+    ///
+    /// Paths are lowercased on write and lookup. Backends that keep metadata
+    /// record the original path in the
+    /// [`drivers::case_insensitive::ORIGINAL_PATH_METADATA`] metadata.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::Path;
+    /// use active_storage::StoreConfig;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let store = StoreConfig::InMem().build().await.unwrap().case_insensitive();
+    ///     store.write(Path::new("Report.PDF"), "my content").await.unwrap();
+    ///     assert!(store.file_exists(Path::new("report.pdf")).await.unwrap());
+    /// }
+    /// ```
+    #[must_use]
+    pub fn case_insensitive(&self) -> Self {
+        Self {
+            driver: Box::new(CaseInsensitiveDriver::new(dyn_clone::clone_box(
+                &*self.driver,
+            ))),
+            bandwidth: self.bandwidth.clone(),
+            counters: self.counters.clone(),
+            records: self.records,
+        }
+    }
+
+    /// Returns a view of the store that refuses to delete the files the
+    /// given retention policies still retain, with
+    /// [`DriverError::RetentionNotExpired`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::{path::Path, time::Duration};
+    /// use active_storage::{drivers::RetentionPolicy, errors::DriverError, StoreConfig};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let store = StoreConfig::InMem().build().await.unwrap().with_retention(vec![
+    ///         RetentionPolicy::new("invoices").with_min_retention(Duration::from_secs(3600)),
+    ///     ]);
+    ///     store.write(Path::new("invoices/1.pdf"), "my content").await.unwrap();
+    ///     assert!(matches!(
+    ///         store.delete(Path::new("invoices/1.pdf")).await,
+    ///         Err(DriverError::RetentionNotExpired(_))
+    ///     ));
+    /// }
+    /// ```
+    #[must_use]
+    pub fn with_retention(&self, policies: Vec<RetentionPolicy>) -> Self {
+        Self {
+            driver: Box::new(RetentionDriver::new(
+                dyn_clone::clone_box(&*self.driver),
+                policies,
+            )),
+            bandwidth: self.bandwidth.clone(),
+            counters: self.counters.clone(),
+            records: self.records,
+        }
+    }
+
+    /// Returns a view of the store coalescing the concurrent reads of a file
+    /// into a single backend read, see [`CoalescingDriver`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::Path;
+    /// use active_storage::StoreConfig;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let store = StoreConfig::InMem().build().await.unwrap().with_coalescing();
+    ///     store.write(Path::new("popular.txt"), "my content").await.unwrap();
+    ///
+    ///     let (a, b) = tokio::join!(
+    ///         store.read::<String>(Path::new("popular.txt")),
+    ///         store.read::<String>(Path::new("popular.txt")),
+    ///     );
+    ///     assert_eq!(a.unwrap(), b.unwrap());
+    /// }
+    /// ```
+    #[must_use]
+    pub fn with_coalescing(&self) -> Self {
+        Self {
+            driver: Box::new(CoalescingDriver::new(dyn_clone::clone_box(&*self.driver))),
+            bandwidth: self.bandwidth.clone(),
+            counters: self.counters.clone(),
+            records: self.records,
+        }
+    }
+
+    /// Returns a view of the store keeping the content of small files in
+    /// memory within the given limits, see [`MemoryCacheDriver`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::{path::Path, time::Duration};
+    /// use active_storage::{drivers::memory_cache::MemoryCacheOptions, StoreConfig};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let store = StoreConfig::InMem().build().await.unwrap().with_memory_cache(
+    ///         MemoryCacheOptions::default().with_ttl(Duration::from_secs(10)),
+    ///     );
+    ///     store.write(Path::new("config.toml"), "debug = true").await.unwrap();
+    ///     // read from the backend once, then from memory
+    ///     for _ in 0..3 {
+    ///         store.read::<String>(Path::new("config.toml")).await.unwrap();
+    ///     }
+    /// }
+    /// ```
+    #[cfg(feature = "memory_cache")]
+    #[must_use]
+    pub fn with_memory_cache(&self, options: MemoryCacheOptions) -> Self {
+        Self {
+            driver: Box::new(MemoryCacheDriver::new(
+                dyn_clone::clone_box(&*self.driver),
+                options,
+            )),
+            bandwidth: self.bandwidth.clone(),
+            counters: self.counters.clone(),
+            records: self.records,
+        }
+    }
+
+    /// Returns a view of the store remembering the files it found missing for
+    /// the given time, see [`NegativeCacheDriver`]. Lookups of those files
+    /// fail with [`DriverError::ResourceNotFound`] without reaching the
+    /// backend until the entry expires or the file is written through the
+    /// view.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::{path::Path, time::Duration};
+    /// use active_storage::StoreConfig;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let store = StoreConfig::InMem()
+    ///         .build()
+    ///         .await
+    ///         .unwrap()
+    ///         .with_negative_cache(Duration::from_secs(5));
+    ///     for _ in 0..3 {
+    ///         // only the first lookup reaches the backend
+    ///         assert!(!store.file_exists(Path::new("avatar.png")).await.unwrap());
+    ///     }
+    /// }
+    /// ```
+    #[must_use]
+    pub fn with_negative_cache(&self, ttl: Duration) -> Self {
+        Self {
+            driver: Box::new(NegativeCacheDriver::new(
+                dyn_clone::clone_box(&*self.driver),
+                ttl,
+            )),
+            bandwidth: self.bandwidth.clone(),
+            counters: self.counters.clone(),
+            records: self.records,
+        }
+    }
+
+    /// Returns a view of the store recording every mutation of a file, with
+    /// the checksums of its content before and after, see [`HistoryDriver`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::Path;
+    /// use active_storage::{drivers::history::Operation, StoreConfig};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let store = StoreConfig::InMem().build().await.unwrap().with_history();
+    ///     store.write(Path::new("test.txt"), "my content").await.unwrap();
+    ///     store.delete(Path::new("test.txt")).await.unwrap();
+    ///
+    ///     let history = store.history(Path::new("test.txt")).await.unwrap();
+    ///     assert_eq!(history.len(), 2);
+    ///     assert_eq!(history[1].operation, Operation::Delete);
+    /// }
+    /// ```
+    #[cfg(feature = "history")]
+    #[must_use]
+    pub fn with_history(&self) -> Self {
+        Self {
+            driver: Box::new(HistoryDriver::new(dyn_clone::clone_box(&*self.driver))),
+            bandwidth: self.bandwidth.clone(),
+            counters: self.counters.clone(),
+            records: self.records,
+        }
+    }
+
+    /// Returns the recorded mutations of the file at the given path, oldest
+    /// first. Only the mutations made through a [`Self::with_history`] view
+    /// are recorded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the records could not be listed or read.
+    #[cfg(feature = "history")]
+    pub async fn history(&self, path: impl Into<ObjectPath>) -> DriverResult<Vec<HistoryEntry>> {
+        let path: &ObjectPath = &self.checked(path)?;
+        history::entries(&*self.driver, path).await
+    }
+
+    /// Deletes the files past the maximum retention of the given policies,
+    /// returning their paths. A file is kept at least as long as the minimum
+    /// retention of its policies, and while it is under legal hold.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the files could not be listed or deleted.
+    pub async fn sweep_expired(
+        &self,
+        policies: &[RetentionPolicy],
+    ) -> DriverResult<Vec<ObjectPath>> {
+        let now = clock::now();
+        let mut files = BTreeSet::new();
+        for policy in policies
+            .iter()
+            .filter(|policy| policy.max_retention.is_some())
+        {
+            files.extend(self.list(&policy.prefix).await?);
+        }
+
+        let mut swept = Vec::new();
+        for file in files {
+            let last_modified = match self.driver.last_modified(&file).await {
+                Ok(last_modified) => last_modified,
+                Err(DriverError::ResourceNotFound) => continue,
+                Err(err) => return Err(err),
+            };
+            if retention::expires_at(policies, &file, last_modified)
+                .is_some_and(|expires_at| expires_at <= now)
+                && !self.driver.legal_hold(&file).await?
+                && self.driver.delete_if_exists(&file).await?
+            {
+                swept.push(file);
+            }
+        }
+        Ok(swept)
+    }
+
+    /// Spawns a task running [`Self::sweep_expired`] with the given policies
+    /// at the given interval, until it is aborted. A failed sweep is retried
+    /// at the next interval.
+    #[must_use]
+    pub fn spawn_retention_sweeper(
+        &self,
+        policies: Vec<RetentionPolicy>,
+        interval: Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        let store = self.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(_err) = store.sweep_expired(&policies).await {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(error = %_err, "retention sweep failed");
+                }
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+
+    /// Returns the file at the given path as an HTTP response, honoring the
+    /// `Range` and `If-None-Match` request header values.
+    ///
+    /// The response carries `ETag`, `Last-Modified`, `Content-Type` and, for
+    /// partial content, `Content-Range` headers. A `304` is returned when the
+    /// `ETag` matches, a `206` for a satisfiable single range and a `416` for
+    /// a range outside of the file.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::PathBuf;
+    /// use active_storage::StoreConfig;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     let file_path = PathBuf::from("test.txt");
+    ///     inmem_driver.write(file_path.as_path(), "my content").await.unwrap();
+    ///
+    ///     let response = inmem_driver
+    ///         .serve(file_path.as_path(), None, None)
+    ///         .await
+    ///         .unwrap();
+    ///     assert_eq!(response.status, 200);
+    ///     assert_eq!(response.header("Content-Type"), Some("text/plain"));
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the underlying `Driver` encounters an issue while
+    /// reading the file.
+    pub async fn serve(
+        &self,
+        path: impl Into<ObjectPath>,
+        range: Option<&str>,
+        if_none_match: Option<&str>,
+    ) -> DriverResult<ServeResponse> {
+        self.serve_with(
+            path,
+            &ServeRequest {
+                range,
+                if_none_match,
+                if_range: None,
+            },
+        )
+        .await
+    }
+
+    /// Builds the HTTP response serving the given file for a request with the
+    /// given `Range`, `If-None-Match` and `If-Range` header values.
+    ///
+    /// Like [`Self::serve`], with a strong `ETag` when the backend reports a
+    /// checksum of the file, see the [`crate::serve`] module.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::PathBuf;
+    /// use active_storage::{serve::ServeRequest, StoreConfig};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     let file_path = PathBuf::from("test.txt");
+    ///     inmem_driver.write(file_path.as_path(), "my content").await.unwrap();
+    ///
+    ///     let response = inmem_driver
+    ///         .serve_with(
+    ///             file_path.as_path(),
+    ///             &ServeRequest::default()
+    ///                 .with_range("bytes=0-1")
+    ///                 .with_if_range("\"outdated\""),
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    ///     assert_eq!(response.status, 200);
+    /// }
+    /// ```
+    ///
+    /// The response is planned from the metadata of the file: a `304` or a
+    /// `416` doesn't read it and a `206` reads only the requested range. The
+    /// metadata is looked up again after the read, which is retried when the
+    /// file was replaced in between, so the body always matches the `ETag`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::PreconditionFailed`] if the file kept changing
+    /// while it was read, or an error if the underlying `Driver` encounters an
+    /// issue while reading the file.
+    pub async fn serve_with(
+        &self,
+        path: impl Into<ObjectPath>,
+        request: &ServeRequest<'_>,
+    ) -> DriverResult<ServeResponse> {
+        let path: &ObjectPath = &self.checked(path)?;
+        let content_type = mime_guess::from_path(path.as_str())
+            .first_or_octet_stream()
+            .to_string();
+
+        for _ in 0..SERVE_ATTEMPTS {
+            let metadata = self
+                .driver
+                .stat(path)
+                .await?
+                .ok_or(DriverError::ResourceNotFound)?;
+            let plan = ServePlan::new(
+                metadata.size,
+                content_type.clone(),
+                serve::metadata_etag(&metadata),
+                metadata.last_modified,
+                request,
+            );
+            let Some(range) = plan.range() else {
+                return Ok(plan.respond(Vec::new()));
+            };
+
+            let body = if range == (0..metadata.size) {
+                self.driver.read(path).await?.into()
+            } else {
+                self.driver.read_range(path, range).await?
+            };
+            if self.driver.stat(path).await?.as_ref() == Some(&metadata) {
+                return Ok(plan.respond(body));
+            }
+        }
+
+        Err(DriverError::PreconditionFailed)
+    }
+
+    /// Streams the given files as a ZIP archive, reading each file only when
+    /// the stream reaches it.
+    ///
+    /// See the [`crate::archive`] module for an example.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the files could not be listed. Errors reading a
+    /// file, or an archive exceeding the ZIP limits (65535 files or 4 GiB),
+    /// are returned by the stream.
+    #[cfg(feature = "archive")]
+    pub async fn zip(&self, source: ArchiveSource) -> DriverResult<ByteStream> {
+        archive::zip(self, source).await
+    }
+
+    /// Writes all the files under the given prefix to the writer as a tar
+    /// archive, with paths relative to the prefix. Returns the number of
+    /// exported files.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the files could not be read or the archive could
+    /// not be written.
+    #[cfg(feature = "archive")]
+    pub async fn export_tar<W: tokio::io::AsyncWrite + Unpin + Send + ?Sized>(
+        &self,
+        prefix: impl Into<ObjectPath>,
+        writer: &mut W,
+    ) -> DriverResult<usize> {
+        let prefix: &ObjectPath = &prefix.into();
+        archive::export_tar(self, &prefix.to_path_buf(), writer).await
+    }
+
+    /// Same as [`Self::export_tar`], compressing the archive with zstd at the
+    /// given level.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the files could not be read or the archive could
+    /// not be written.
+    #[cfg(feature = "zstd")]
+    pub async fn export_tar_zstd<W: tokio::io::AsyncWrite + Unpin + Send + ?Sized>(
+        &self,
+        prefix: impl Into<ObjectPath>,
+        writer: &mut W,
+        level: i32,
+    ) -> DriverResult<usize> {
+        let prefix: &ObjectPath = &prefix.into();
+        archive::export_tar_zstd(self, &prefix.to_path_buf(), writer, level).await
+    }
+
+    /// Restores the files of a tar archive, plain or zstd compressed, under
+    /// the given prefix. Returns the number of imported files.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::{Path, PathBuf};
+    /// use active_storage::StoreConfig;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let source = StoreConfig::InMem().build().await.unwrap();
+    ///     let file_path = PathBuf::from("seed").join("test.txt");
+    ///     source.write(file_path.as_path(), "my content").await.unwrap();
+    ///
+    ///     let mut archive = Vec::new();
+    ///     source.export_tar(Path::new("seed"), &mut archive).await.unwrap();
+    ///
+    ///     let target = StoreConfig::InMem().build().await.unwrap();
+    ///     let imported = target
+    ///         .import_tar(&mut archive.as_slice(), Path::new("restored"))
+    ///         .await
+    ///         .unwrap();
+    ///     assert_eq!(imported, 1);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive is malformed, is compressed and the
+    /// `zstd` feature is disabled, or a file could not be written.
+    #[cfg(feature = "archive")]
+    pub async fn import_tar<R: tokio::io::AsyncRead + Unpin + Send + ?Sized>(
+        &self,
+        reader: &mut R,
+        prefix: impl Into<ObjectPath>,
+    ) -> DriverResult<usize> {
+        let prefix: &ObjectPath = &prefix.into();
+        archive::import_tar(self, reader, &prefix.to_path_buf()).await
+    }
+
+    /// Writes a manifest of the files under the given prefix to the writer,
+    /// in the given format. Returns the number of listed files.
+    ///
+    /// See the [`crate::inventory`] module for an example.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the files could not be listed or looked up, or the
+    /// manifest could not be written.
+    #[cfg(feature = "inventory")]
+    pub async fn inventory<W: tokio::io::AsyncWrite + Unpin + Send + ?Sized>(
+        &self,
+        prefix: impl Into<ObjectPath>,
+        format: InventoryFormat,
+        writer: &mut W,
+    ) -> DriverResult<usize> {
+        let prefix: &ObjectPath = &prefix.into();
+        inventory::write_inventory(self, prefix, format, writer).await
+    }
+
+    /// Same as [`Self::inventory`], writing the manifest to the given path of
+    /// the target store, e.g. a bucket collecting the manifests of several
+    /// stores.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the manifest could not be built or written.
+    #[cfg(feature = "inventory")]
+    pub async fn inventory_to(
+        &self,
+        prefix: impl Into<ObjectPath>,
+        format: InventoryFormat,
+        target: &Self,
+        path: impl Into<ObjectPath>,
+    ) -> DriverResult<usize> {
+        let mut manifest = Vec::new();
+        let count = self.inventory(prefix, format, &mut manifest).await?;
+        target.write(path, manifest).await?;
+        Ok(count)
+    }
+
+    /// Starts a resumable multipart upload of the file at the given path.
+    ///
+    /// See the [`crate::upload`] module for an example.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is invalid or the upload could not be
+    /// started.
+    pub async fn begin_upload(&self, path: impl Into<ObjectPath>) -> DriverResult<UploadSession> {
+        self.begin_upload_with_options(path, &WriteOptions::default())
+            .await
+    }
+
+    /// Same as [`Self::begin_upload`], with the completed file written with
+    /// the given options. See [`Driver::create_upload_with_options`] for the
+    /// options each driver supports.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is invalid or the upload could not be
+    /// started.
+    pub async fn begin_upload_with_options(
+        &self,
+        path: impl Into<ObjectPath>,
+        options: &WriteOptions,
+    ) -> DriverResult<UploadSession> {
+        let path: &ObjectPath = &self.checked(path)?;
+        let upload_id = self
+            .driver
+            .create_upload_with_options(path, options)
+            .await?;
+
+        Ok(UploadSession::new(
+            dyn_clone::clone_box(&*self.driver),
+            path.clone(),
+            upload_id,
+            Vec::new(),
+            options.clone(),
+        ))
+    }
+
+    /// Resumes an interrupted multipart upload, restoring the parts uploaded
+    /// so far. The options of [`Self::begin_upload_with_options`] are not
+    /// restored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the upload doesn't exist or its parts could not be
+    /// listed.
+    pub async fn resume_upload(
+        &self,
+        path: impl Into<ObjectPath>,
+        upload_id: &str,
+    ) -> DriverResult<UploadSession> {
+        let path: &ObjectPath = &self.checked(path)?;
+        let parts = self.driver.list_upload_parts(path, upload_id).await?;
+
+        Ok(UploadSession::new(
+            dyn_clone::clone_box(&*self.driver),
+            path.clone(),
+            upload_id.to_string(),
+            parts,
+            WriteOptions::default(),
+        ))
+    }
+
+    /// Aborts the multipart uploads under the given prefix started more than
+    /// `older_than` ago, discarding their parts. Abandoned parts are billed
+    /// by the backends, so this is meant to run periodically. Returns the
+    /// number of aborted uploads.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::{path::Path, time::Duration};
+    /// use active_storage::StoreConfig;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     let mut session = inmem_driver.begin_upload(Path::new("test.txt")).await.unwrap();
+    ///     session.write_part(1, "my content").await.unwrap();
+    ///
+    ///     let aborted = inmem_driver
+    ///         .cleanup_incomplete_uploads(Path::new(""), Duration::ZERO)
+    ///         .await
+    ///         .unwrap();
+    ///     assert_eq!(aborted, 1);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the uploads could not be listed or an upload could
+    /// not be aborted.
+    pub async fn cleanup_incomplete_uploads(
+        &self,
+        prefix: impl Into<ObjectPath>,
+        older_than: Duration,
+    ) -> DriverResult<usize> {
+        let prefix: &ObjectPath = &prefix.into();
+        let cutoff = clock::now()
+            .checked_sub(older_than)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+
+        let mut aborted = 0;
+        for upload in self.driver.list_uploads(prefix).await? {
+            if upload.initiated <= cutoff {
+                self.driver
+                    .abort_upload(&upload.path, &upload.upload_id)
+                    .await?;
+                aborted += 1;
+            }
+        }
+
+        Ok(aborted)
+    }
+
+    /// Returns a guard over a new scratch object under the given prefix,
+    /// deleted unless it is persisted.
+    ///
+    /// See the [`crate::tempfile`] module for an example.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::ReservedPath`] if the prefix is reserved, or an
+    /// error if it is not a valid path.
+    pub fn tempfile(&self, prefix: impl Into<ObjectPath>) -> DriverResult<TempFile> {
+        let path = self.checked(prefix)?.join(drivers::unique_id());
+        path.to_key()?;
+        Ok(TempFile::new(self.clone(), path))
+    }
+
+    /// Deletes the scratch objects under the given prefix last modified more
+    /// than `older_than` ago, which were leaked by a process exiting before
+    /// their guard was dropped. Returns the number of deleted objects.
+    ///
+    /// This is [`Self::sweep_expired`] with a maximum retention of
+    /// `older_than` on the prefix, so reserved paths and files under legal
+    /// hold are never deleted.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::ReservedPath`] if the prefix is reserved, or an
+    /// error if the objects could not be listed or deleted.
+    pub async fn cleanup_tempfiles(
+        &self,
+        prefix: impl Into<ObjectPath>,
+        older_than: Duration,
+    ) -> DriverResult<usize> {
+        let prefix = self.checked(prefix)?;
+        prefix.to_prefix()?;
+        let policy = RetentionPolicy::new(prefix).with_max_retention(older_than);
+        Ok(self.sweep_expired(&[policy]).await?.len())
+    }
+
+    /// Returns the size in bytes of the file at the specified path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist or could not be queried.
+    pub async fn size(&self, path: impl Into<ObjectPath>) -> DriverResult<u64> {
+        let path: &ObjectPath = &self.checked(path)?;
+        self.driver.size(path).await
+    }
+
+    /// Reads the given byte range of the file at the specified path. The range
+    /// end is clamped to the file size.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::PathBuf;
+    /// use active_storage::StoreConfig;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     let file_path = PathBuf::from("test.txt");
+    ///     inmem_driver.write(file_path.as_path(), "my content").await.unwrap();
+    ///
+    ///     let content = inmem_driver.read_range(file_path.as_path(), 3..10).await.unwrap();
+    ///     assert_eq!(content, b"content");
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist or could not be read.
+    pub async fn read_range(
+        &self,
+        path: impl Into<ObjectPath>,
+        range: Range<u64>,
+    ) -> DriverResult<Vec<u8>> {
+        let path: &ObjectPath = &self.checked(path)?;
+        self.driver.read_range(path, range).await
+    }
+
+    /// Downloads the file at the specified path into the writer, fetching
+    /// parts of `part_size` bytes with up to `concurrency` concurrent ranged
+    /// reads. Parts are written in order. Returns the number of bytes written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::PathBuf;
+    /// use active_storage::StoreConfig;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     let file_path = PathBuf::from("test.txt");
+    ///     inmem_driver.write(file_path.as_path(), "my content").await.unwrap();
+    ///
+    ///     let mut content = Vec::new();
+    ///     inmem_driver
+    ///         .download_parallel(file_path.as_path(), &mut content, 4, 2)
+    ///         .await
+    ///         .unwrap();
+    ///     assert_eq!(content, b"my content");
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist, a part could not be read,
+    /// or the writer fails.
+    pub async fn download_parallel<W: tokio::io::AsyncWrite + Unpin + Send + ?Sized>(
+        &self,
+        path: impl Into<ObjectPath>,
+        writer: &mut W,
+        part_size: u64,
+        concurrency: usize,
+    ) -> DriverResult<u64> {
+        let path: &ObjectPath = &self.checked(path)?;
+        let options = TransferOptions::default().with_part_size(part_size);
+        self.download_parallel_with_options(path, writer, concurrency, &options)
+            .await
+    }
+
+    /// Same as [`Self::download_parallel`], with the part size, progress
+    /// reporting, bandwidth limit and cancellation taken from the given
+    /// [`TransferOptions`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist, a part could not be read,
+    /// the writer fails or the transfer is cancelled.
+    pub async fn download_parallel_with_options<
+        W: tokio::io::AsyncWrite + Unpin + Send + ?Sized,
+    >(
+        &self,
+        path: impl Into<ObjectPath>,
+        writer: &mut W,
+        concurrency: usize,
+        options: &TransferOptions,
+    ) -> DriverResult<u64> {
+        let path: &ObjectPath = &self.checked(path)?;
+        let size = self.driver.size(path).await?;
+        let part_size = options.part_size.max(1);
+        let ranges = (0..size.div_ceil(part_size))
+            .map(|part| part * part_size..size.min((part + 1) * part_size));
+
+        let mut parts = stream::iter(ranges)
+            .map(|range| self.driver.read_range(path, range))
+            .buffered(concurrency.max(1));
+
+        let mut tracker = options.tracker(Some(size), self.bandwidth.as_ref());
+        tracker.check_cancelled()?;
+        while let Some(part) = parts.next().await {
+            let part = part?;
+            writer
+                .write_all(&part)
+                .await
+                .map_err(|err| DriverError::Any(Box::new(err)))?;
+            tracker.advance(part.len() as u64).await?;
+        }
+        writer
+            .flush()
+            .await
+            .map_err(|err| DriverError::Any(Box::new(err)))?;
+
+        Ok(size)
+    }
+
+    /// Downloads the file at the specified path to a local file, e.g. for a
+    /// worker processing it with an external tool. Returns the number of
+    /// bytes written.
+    ///
+    /// Missing parent directories are created. The content is written to a
+    /// `.part` file next to the local path, which is renamed once the download
+    /// is verified, and the modification time of the local file is set to the
+    /// one of the stored file. The download is verified against the size and
+    /// modification time of the stored file, and against its MD5 digest when
+    /// the backend reports one in [`ObjectMetadata::content_md5`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::{Path, PathBuf};
+    /// use active_storage::StoreConfig;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     let file_path = PathBuf::from("test.txt");
+    ///     inmem_driver.write(file_path.as_path(), "my content").await.unwrap();
+    ///
+    ///     let local_path = std::env::temp_dir().join("active-storage-download-to.txt");
+    ///     inmem_driver.download_to(file_path.as_path(), &local_path).await.unwrap();
+    ///     assert_eq!(std::fs::read(&local_path).unwrap(), b"my content");
+    ///     # std::fs::remove_file(&local_path).unwrap();
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::IntegrityCheckFailed`] if the stored file
+    /// changed during the download or the content doesn't match its checksum,
+    /// or an error if it does not exist or the local file could not be
+    /// written.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub async fn download_to(
+        &self,
+        path: impl Into<ObjectPath>,
+        local_path: &Path,
+    ) -> DriverResult<u64> {
+        let path: &ObjectPath = &self.checked(path)?;
+        self.download_to_with_options(path, local_path, &TransferOptions::default())
+            .await
+    }
+
+    /// Same as [`Self::download_to`], with the part size, progress reporting,
+    /// bandwidth limit and cancellation taken from the given
+    /// [`TransferOptions`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Self::download_to`]. The transfer may also be cancelled.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub async fn download_to_with_options(
+        &self,
+        path: impl Into<ObjectPath>,
+        local_path: &Path,
+        options: &TransferOptions,
+    ) -> DriverResult<u64> {
+        const CONCURRENCY: usize = 4;
+
+        let path: &ObjectPath = &self.checked(path)?;
+        let io_error = |err: std::io::Error| DriverError::Any(Box::new(err));
+        let metadata = self
+            .driver
+            .stat(path)
+            .await?
+            .ok_or(DriverError::ResourceNotFound)?;
+        let mut part_path = local_path.as_os_str().to_owned();
+        part_path.push(".part");
+        let part_path = PathBuf::from(part_path);
+
+        if let Some(parent) = local_path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(io_error)?;
+        }
+
+        let result = async {
+            let file = tokio::fs::File::create(&part_path)
+                .await
+                .map_err(io_error)?;
+            let mut writer = ChecksumWriter::new(tokio::io::BufWriter::new(file));
+            self.download_parallel_with_options(path, &mut writer, CONCURRENCY, options)
+                .await?;
+            let (writer, written, md5) = writer.finish();
+
+            let unchanged = self.driver.stat(path).await?.is_some_and(|current| {
+                current.size == written && current.last_modified == metadata.last_modified
+            });
+            if !unchanged
+                || metadata
+                    .content_md5
+                    .is_some_and(|content_md5| content_md5 != md5)
+            {
+                return Err(DriverError::IntegrityCheckFailed);
+            }
+
+            let file = writer.into_inner().into_std().await;
+            let last_modified = metadata.last_modified;
+            tokio::task::spawn_blocking(move || {
+                file.set_modified(last_modified)?;
+                file.sync_all()
+            })
+            .await
+            .map_err(|err| DriverError::Any(Box::new(err)))?
+            .map_err(io_error)?;
+            Ok(written)
+        }
+        .await;
+
+        match result {
+            Ok(written) => {
+                tokio::fs::rename(&part_path, local_path)
+                    .await
+                    .map_err(io_error)?;
+                Ok(written)
+            }
+            Err(err) => {
+                let _ = tokio::fs::remove_file(&part_path).await;
+                Err(err)
+            }
+        }
+    }
+
+    /// Uploads a local file to the specified path, writing it at once when it
+    /// fits in a single [`TransferOptions::part_size`] part and as a multipart
+    /// upload otherwise. Returns the number of bytes written.
+    ///
+    /// Parts are read from the file straight into the part buffer, so the
+    /// file is never buffered twice. Single part uploads keep the local
+    /// modification time when the driver supports
+    /// [`Capabilities::SET_LAST_MODIFIED`]; object stores record it in the
+    /// [`MTIME_METADATA_KEY`] metadata entry.
+    ///
+    /// [`MTIME_METADATA_KEY`]: crate::drivers::MTIME_METADATA_KEY
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::Path;
+    /// use active_storage::StoreConfig;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     let local_path = std::env::temp_dir().join("active-storage-upload-from.txt");
+    ///     std::fs::write(&local_path, "my content").unwrap();
+    ///
+    ///     inmem_driver.upload_from(&local_path, Path::new("test.txt")).await.unwrap();
+    ///     let content: String = inmem_driver.read(Path::new("test.txt")).await.unwrap();
+    ///     assert_eq!(content, "my content");
+    ///     # std::fs::remove_file(&local_path).unwrap();
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the local file could not be read or the upload
+    /// fails.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub async fn upload_from(
+        &self,
+        local_path: &Path,
+        path: impl Into<ObjectPath>,
+    ) -> DriverResult<u64> {
+        let path: &ObjectPath = &self.checked(path)?;
+        self.upload_from_with_options(local_path, path, &TransferOptions::default())
+            .await
+    }
+
+    /// Same as [`Self::upload_from`], with the part size, progress reporting,
+    /// bandwidth limit and cancellation taken from the given
+    /// [`TransferOptions`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Self::upload_from`]. The transfer may also be cancelled.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub async fn upload_from_with_options(
+        &self,
+        local_path: &Path,
+        path: impl Into<ObjectPath>,
+        options: &TransferOptions,
+    ) -> DriverResult<u64> {
+        use tokio::io::AsyncReadExt;
+
+        let path: &ObjectPath = &self.checked(path)?;
+        let io_error = |err: std::io::Error| DriverError::Any(Box::new(err));
+        options.check_cancelled()?;
+
+        let mut file = tokio::fs::File::open(local_path).await.map_err(io_error)?;
+        let metadata = file.metadata().await.map_err(io_error)?;
+        let size = metadata.len();
+        let mut tracker = options.tracker(Some(size), self.bandwidth.as_ref());
+
+        let write_options = match metadata.modified() {
+            Ok(mtime)
+                if self
+                    .capabilities()
+                    .contains(Capabilities::SET_LAST_MODIFIED) =>
+            {
+                WriteOptions::default().with_last_modified(mtime)
+            }
+            _ => WriteOptions::default(),
+        };
+
+        if size <= options.part_size {
+            let mut content = Vec::with_capacity(usize::try_from(size).unwrap_or_default());
+            file.read_to_end(&mut content).await.map_err(io_error)?;
+            let written = content.len() as u64;
+
+            if write_options.last_modified.is_some() {
+                self.driver
+                    .write_with_options(path, content.into(), &write_options)
+                    .await?;
+            } else {
+                self.driver.write(path, content.into()).await?;
+            }
+            tracker.advance(written).await?;
+            return Ok(written);
+        }
+
+        // grow the parts so the file fits in the maximum number of parts
+        let part_size = options
+            .part_size
+            .max(size.div_ceil(u64::from(crate::upload::MAX_PART_NUMBER)));
+        let mut session = self.begin_upload_with_options(path, &write_options).await?;
+        let mut part = Vec::with_capacity(usize::try_from(part_size).unwrap_or_default());
+        let mut written = 0;
+
+        let result = async {
+            for part_number in 1.. {
+                tracker.check_cancelled()?;
+                part.clear();
+                (&mut file)
+                    .take(part_size)
+                    .read_to_end(&mut part)
+                    .await
+                    .map_err(io_error)?;
+                if part.is_empty() {
+                    break;
+                }
+
+                session.write_part(part_number, &part).await?;
+                tracker.advance(part.len() as u64).await?;
+                written += part.len() as u64;
+            }
+            Ok(())
+        }
+        .await;
+
+        match result {
+            Ok(()) => {
+                session.complete().await?;
+                Ok(written)
+            }
+            Err(err) => {
+                // the upload error matters more than a failed cleanup
+                if let Err(_abort_err) = session.abort().await {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(error = %_abort_err, "aborting the upload failed");
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Reads the file at the specified path as a stream of chunks of
+    /// [`TransferOptions::part_size`] bytes, reporting the progress as the
+    /// stream is consumed.
+    ///
+    /// See the [`crate::transfer`] module for an example.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist. Errors reading a chunk are
+    /// returned by the stream.
+    pub async fn read_stream(
+        &self,
+        path: impl Into<ObjectPath>,
+        options: &TransferOptions,
+    ) -> DriverResult<ByteStream> {
+        let path: &ObjectPath = &self.checked(path)?;
+        let size = self.driver.size(path).await?;
+        let part_size = options.part_size.max(1);
+        let state = (
+            dyn_clone::clone_box(&*self.driver),
+            path.clone(),
+            0,
+            options.tracker(Some(size), self.bandwidth.as_ref()),
+        );
+
+        let stream = stream::try_unfold(
+            state,
+            move |(driver, path, offset, mut tracker)| async move {
+                if offset >= size {
+                    return Ok(None);
+                }
+                tracker.check_cancelled()?;
+
+                let end = size.min(offset + part_size);
+                let chunk = driver.read_range(&path, offset..end).await?;
+                tracker.advance(chunk.len() as u64).await?;
+                Ok(Some((chunk, (driver, path, end, tracker))))
+            },
+        );
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Reads the text file at the specified path as a stream of lines, built
+    /// on [`Self::read_stream`] so the file is never read whole.
+    ///
+    /// See the [`crate::text`] module for an example.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist. Errors reading a chunk or
+    /// decoding a line that is not valid UTF-8 are returned by the stream.
+    pub async fn read_lines(&self, path: impl Into<ObjectPath>) -> DriverResult<LineStream> {
+        let path: &ObjectPath = &self.checked(path)?;
+        let chunks = self.read_stream(path, &TransferOptions::default()).await?;
+        Ok(text::lines(chunks))
+    }
+
+    /// Reads the CSV file at the specified path as a stream of records, with
+    /// fields separated by the given delimiter.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::PathBuf;
+    /// use active_storage::StoreConfig;
+    /// use futures::TryStreamExt;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     let file_path = PathBuf::from("test.csv");
+    ///     inmem_driver.write(file_path.as_path(), "a,\"b,c\"\n").await.unwrap();
+    ///
+    ///     let records: Vec<Vec<String>> = inmem_driver
+    ///         .read_csv(file_path.as_path(), ',')
+    ///         .await
+    ///         .unwrap()
+    ///         .try_collect()
+    ///         .await
+    ///         .unwrap();
+    ///     assert_eq!(records, vec![vec!["a", "b,c"]]);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist. Errors reading the file or
+    /// parsing a record are returned by the stream.
+    #[cfg(feature = "csv")]
+    pub async fn read_csv(
+        &self,
+        path: impl Into<ObjectPath>,
+        delimiter: char,
+    ) -> DriverResult<text::RecordStream> {
+        let path: &ObjectPath = &self.checked(path)?;
+        Ok(text::csv_records(self.read_lines(path).await?, delimiter))
+    }
+
+    /// Writes the chunks of the given stream to the file at the specified
+    /// path, uploading them as parts of [`TransferOptions::part_size`] bytes
+    /// through an upload session so the whole file is never buffered. Returns
+    /// the number of bytes written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::PathBuf;
+    /// use active_storage::{transfer::TransferOptions, StoreConfig};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     let file_path = PathBuf::from("test.txt");
+    ///     let chunks = futures::stream::iter(vec![Ok(b"my ".to_vec()), Ok(b"content".to_vec())]);
+    ///
+    ///     inmem_driver
+    ///         .write_stream(file_path.as_path(), chunks, &TransferOptions::default())
+    ///         .await
+    ///         .unwrap();
+    ///     let content: String = inmem_driver.read(file_path.as_path()).await.unwrap();
+    ///     assert_eq!(content, "my content");
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stream fails, a part could not be uploaded or
+    /// the transfer is cancelled. The upload is aborted on failure.
+    pub async fn write_stream<S>(
+        &self,
+        path: impl Into<ObjectPath>,
+        chunks: S,
+        options: &TransferOptions,
+    ) -> DriverResult<u64>
+    where
+        S: Stream<Item = DriverResult<Vec<u8>>> + Send,
+    {
+        let path: &ObjectPath = &self.checked(path)?;
+        options.check_cancelled()?;
+        let mut session = self.begin_upload(path).await?;
+        match self.upload_stream(&mut session, chunks, options).await {
+            Ok(written) => {
+                session.complete().await?;
+                Ok(written)
+            }
+            Err(err) => {
+                // the upload error matters more than a failed cleanup
+                if let Err(_abort_err) = session.abort().await {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(error = %_abort_err, "aborting the upload failed");
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Uploads the chunks of the stream as parts of the given session.
+    async fn upload_stream<S>(
+        &self,
+        session: &mut UploadSession,
+        chunks: S,
+        options: &TransferOptions,
+    ) -> DriverResult<u64>
+    where
+        S: Stream<Item = DriverResult<Vec<u8>>> + Send,
+    {
+        let part_size = usize::try_from(options.part_size.max(1)).unwrap_or(usize::MAX);
+        let mut chunks = std::pin::pin!(chunks);
+        let mut tracker = options.tracker(None, self.bandwidth.as_ref());
+        let mut part = Vec::new();
+        let mut part_number = 1;
+        let mut written = 0;
+
+        loop {
+            tracker.check_cancelled()?;
+            let chunk = chunks.next().await.transpose()?;
+            if let Some(chunk) = &chunk {
+                part.extend_from_slice(chunk);
+            }
+
+            while part.len() >= part_size || (chunk.is_none() && !part.is_empty()) {
+                let rest = part.split_off(part_size.min(part.len()));
+                session.write_part(part_number, &part).await?;
+                tracker.advance(part.len() as u64).await?;
+                written += part.len() as u64;
+                part_number += 1;
+                part = rest;
+            }
+
+            if chunk.is_none() {
+                break;
+            }
+        }
+
+        // an empty stream still creates an empty file
+        if part_number == 1 {
+            session.write_part(part_number, &part).await?;
+        }
+
+        Ok(written)
+    }
+
+    /// Returns a stable public URL of the file at the given path, suitable for
+    /// serving public assets through a CDN without signing.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::PathBuf;
+    /// use active_storage::{drivers, StoreConfig};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let config = drivers::disk::Config {
+    ///         location: PathBuf::from("tmp"),
+    ///         public_url: Some("/static".to_string()),
+    ///     };
+    ///     let disk_driver = StoreConfig::Disk(config).build().await.unwrap();
+    ///
+    ///     let url = disk_driver
+    ///         .public_url(PathBuf::from("images").join("logo.png").as_path())
+    ///         .await
+    ///         .unwrap();
+    ///     assert_eq!(url, "/static/images/logo.png");
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the driver does not support public URLs, has no
+    /// public URL configured, or the path is invalid.
+    pub async fn public_url(&self, path: impl Into<ObjectPath>) -> DriverResult<String> {
+        let path: &ObjectPath = &self.checked(path)?;
+        self.driver.public_url(path).await
+    }
+
+    /// Checks whether the storage container (S3 bucket, Azure container or
+    /// disk root) exists.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use active_storage::StoreConfig;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     assert!(inmem_driver.container_exists().await.unwrap());
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the underlying `Driver` encounters an issue while
+    /// checking the container existence.
+    pub async fn container_exists(&self) -> DriverResult<bool> {
+        self.driver.container_exists().await
+    }
+
+    /// Creates the storage container (S3 bucket, Azure container or disk root)
+    /// when it doesn't exist, so the first write in a fresh environment
+    /// doesn't fail.
+    ///
+    /// # Parameters
+    ///
+    /// - `options`: The region and ACL to create the container with.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use active_storage::{drivers::ContainerOptions, StoreConfig};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     assert!(inmem_driver
+    ///         .ensure_container(&ContainerOptions::default())
+    ///         .await
+    ///         .is_ok());
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the underlying `Driver` encounters an issue while
+    /// creating the container.
+    pub async fn ensure_container(&self, options: &ContainerOptions) -> DriverResult<()> {
+        self.driver.ensure_container(options).await
+    }
+
+    /// Maps the retention policies to the native lifecycle rules of the
+    /// backend, replacing the existing ones, see
+    /// [`Driver::apply_retention_policies`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::Unsupported`] if the backend has no native
+    /// lifecycle rules, or an error if the rules could not be applied.
+    pub async fn apply_retention_policies(&self, policies: &[RetentionPolicy]) -> DriverResult<()> {
+        self.driver.apply_retention_policies(policies).await
+    }
+
+    /// Probes the underlying storage backend and reports its status together
+    /// with the probe latency.
+    ///
+    /// The probe is backend specific and designed to be cheap, which makes it
+    /// suitable for service readiness endpoints.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use active_storage::StoreConfig;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
+    ///     assert!(inmem_driver.health_check().await.is_healthy());
+    /// }
+    /// ```
+    pub async fn health_check(&self) -> HealthCheck {
+        let start = Instant::now();
+        let status = match self.driver.health_check().await {
+            Ok(()) => HealthStatus::Healthy,
+            Err(err) => HealthStatus::Unhealthy(err),
+        };
+
+        HealthCheck {
+            status,
+            latency: start.elapsed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{
+        upload::{UploadPart, UPLOADS_PREFIX},
+        StoreConfig,
+    };
+
+    #[tokio::test]
+    async fn can_hide_reserved_paths_from_listing() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        store.write(Path::new("folder/a.txt"), "a").await.unwrap();
+        store.write(Path::new("b.txt"), "b").await.unwrap();
+        store.create_directory(Path::new("empty")).await.unwrap();
+        store
+            .set_legal_hold(Path::new("b.txt"), true)
+            .await
+            .unwrap();
+        let mut session = store.begin_upload(Path::new("c.txt")).await.unwrap();
+        session.write_part(1, "c").await.unwrap();
+
+        let mut files = store.list(ObjectPath::default()).await.unwrap();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![ObjectPath::new("b.txt"), ObjectPath::new("folder/a.txt")]
+        );
+        assert!(!store
+            .records()
+            .list(ObjectPath::new(drivers::LEGAL_HOLDS_PREFIX))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn can_refuse_reserved_paths() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let path = Path::new("contracts/1.pdf");
+        store.write(path, "content").await.unwrap();
+        store.set_legal_hold(path, true).await.unwrap();
+        let marker = drivers::legal_hold_path(&ObjectPath::from(path)).unwrap();
+
+        assert!(matches!(
+            store.delete(&marker).await,
+            Err(DriverError::ReservedPath)
+        ));
+        assert!(matches!(
+            store.delete_directory(drivers::LEGAL_HOLDS_PREFIX).await,
+            Err(DriverError::ReservedPath)
+        ));
+        assert!(matches!(
+            store.read::<String>(&marker).await,
+            Err(DriverError::ReservedPath)
+        ));
+        assert!(matches!(
+            store
+                .write(Path::new(".active-storage-uploads/part"), "content")
+                .await,
+            Err(DriverError::ReservedPath)
+        ));
+        assert!(matches!(
+            store
+                .write(Path::new("folder/.active-storage-keep"), "")
+                .await,
+            Err(DriverError::ReservedPath)
+        ));
+        assert!(matches!(
+            store
+                .write_named(
+                    drivers::LEGAL_HOLDS_PREFIX,
+                    "1.pdf",
+                    "content",
+                    Collision::Overwrite
+                )
+                .await,
+            Err(DriverError::ReservedPath)
+        ));
+        assert!(store.scoped(drivers::LEGAL_HOLDS_PREFIX).is_err());
+
+        assert!(store.legal_hold(path).await.unwrap());
+        assert!(matches!(
+            store.delete(path).await,
+            Err(DriverError::LegalHold)
+        ));
+        assert!(store.records().file_exists(&marker).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn can_refuse_overwriting_held_files() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let path = Path::new("contracts/1.pdf");
+        store.write(path, "content").await.unwrap();
+        store.set_legal_hold(path, true).await.unwrap();
+
+        assert!(matches!(
+            store.write(path, "other").await,
+            Err(DriverError::LegalHold)
+        ));
+        assert!(matches!(
+            store.delete_if_exists(path).await,
+            Err(DriverError::LegalHold)
+        ));
+        let mut session = store.begin_upload(path).await.unwrap();
+        session.write_part(1, "other").await.unwrap();
+        assert!(matches!(
+            session.complete().await,
+            Err(DriverError::LegalHold)
+        ));
+        assert_eq!(store.read::<String>(path).await.unwrap(), "content");
+
+        store.set_legal_hold(path, false).await.unwrap();
+        store.write(path, "other").await.unwrap();
+        assert_eq!(store.read::<String>(path).await.unwrap(), "other");
+    }
+
+    #[tokio::test]
+    async fn can_complete_uploads_in_part_order() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let path = Path::new("docs/a.txt");
+        let mut session = store.begin_upload(path).await.unwrap();
+        session.write_part(2, "b").await.unwrap();
+        session.write_part(1, "stale").await.unwrap();
+        session.write_part(1, "a").await.unwrap();
+        assert!(matches!(
+            session.write_part(0, "c").await,
+            Err(DriverError::InvalidPartNumber(0))
+        ));
+        assert_eq!(
+            session
+                .parts()
+                .iter()
+                .map(|part| part.part_number)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+
+        session.complete().await.unwrap();
+        assert_eq!(store.read::<String>(path).await.unwrap(), "ab");
+        assert_eq!(
+            store.list(ObjectPath::default()).await.unwrap(),
+            vec![ObjectPath::from(path)]
+        );
+        assert!(store
+            .records()
+            .list(ObjectPath::new(UPLOADS_PREFIX))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn can_clean_up_incomplete_uploads() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let mut aborted = store.begin_upload(Path::new("a.txt")).await.unwrap();
+        aborted.write_part(1, "a").await.unwrap();
+        aborted.abort().await.unwrap();
+        let mut stale = store.begin_upload(Path::new("docs/b.txt")).await.unwrap();
+        stale.write_part(1, "b").await.unwrap();
+        let mut other = store.begin_upload(Path::new("c.txt")).await.unwrap();
+        other.write_part(1, "c").await.unwrap();
+
+        assert_eq!(
+            store
+                .cleanup_incomplete_uploads(Path::new("docs"), Duration::ZERO)
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            store
+                .cleanup_incomplete_uploads(Path::new(""), Duration::from_secs(3600))
+                .await
+                .unwrap(),
+            0
+        );
+        other.complete().await.unwrap();
+
+        assert!(store
+            .records()
+            .list(ObjectPath::new(UPLOADS_PREFIX))
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            store.list(ObjectPath::default()).await.unwrap(),
+            vec![ObjectPath::new("c.txt")]
+        );
+    }
+
+    /// Returns the number of calls of the given driver method.
+    fn operations(store: &Store, method: &str) -> u64 {
+        store
+            .stats()
+            .operations
+            .get(method)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn can_serve_conditionals_and_ranges() {
+        use futures::TryStreamExt;
+
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let path = Path::new("test.txt");
+        store.write(path, "my content").await.unwrap();
+
+        let response = store
+            .serve_with(path, &ServeRequest::default())
+            .await
+            .unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(operations(&store, "read"), 1);
+        let etag = response.header("ETag").unwrap().to_string();
+        let body: Vec<Vec<u8>> = response.body.try_collect().await.unwrap();
+        assert_eq!(body.concat(), b"my content");
+
+        let response = store
+            .serve_with(path, &ServeRequest::default().with_if_none_match(&etag))
+            .await
+            .unwrap();
+        assert_eq!(response.status, 304);
+        assert_eq!(operations(&store, "read"), 1);
+        assert_eq!(operations(&store, "read_range"), 0);
+
+        let response = store
+            .serve_with(
+                path,
+                &ServeRequest::default()
+                    .with_range("bytes=3-")
+                    .with_if_range(&etag),
+            )
+            .await
+            .unwrap();
+        // the weak `ETag` of the in-memory driver never matches `If-Range`
+        assert_eq!(response.status, 200);
+
+        let response = store
+            .serve_with(path, &ServeRequest::default().with_range("bytes=3-"))
+            .await
+            .unwrap();
+        assert_eq!(response.status, 206);
+        assert_eq!(response.header("Content-Range"), Some("bytes 3-9/10"));
+        assert_eq!(operations(&store, "read_range"), 1);
+        let body: Vec<Vec<u8>> = response.body.try_collect().await.unwrap();
+        assert_eq!(body.concat(), b"content");
+
+        let response = store
+            .serve_with(path, &ServeRequest::default().with_range("bytes=20-"))
+            .await
+            .unwrap();
+        assert_eq!(response.status, 416);
+        assert_eq!(operations(&store, "read"), 2);
+        assert_eq!(operations(&store, "read_range"), 1);
+
+        assert!(matches!(
+            store
+                .serve_with(Path::new("missing.txt"), &ServeRequest::default())
+                .await,
+            Err(DriverError::ResourceNotFound)
+        ));
+    }
+
+    /// A driver over an in-memory store reporting the given MD5 digest for
+    /// every file.
+    #[derive(Clone)]
+    struct ChecksumDriver {
+        inner: Store,
+        content_md5: [u8; 16],
+    }
+
+    #[async_trait::async_trait]
+    impl Driver for ChecksumDriver {
+        async fn read(&self, path: &ObjectPath) -> DriverResult<Bytes> {
+            self.inner.read_bytes(path).await
+        }
+
+        async fn file_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+            self.inner.file_exists(path).await
+        }
+
+        async fn write(&self, path: &ObjectPath, content: Bytes) -> DriverResult<()> {
+            self.inner.write(path, content).await
+        }
+
+        async fn delete(&self, path: &ObjectPath) -> DriverResult<()> {
+            self.inner.delete(path).await
+        }
+
+        async fn delete_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+            self.inner.delete_directory(path).await
+        }
+
+        async fn last_modified(&self, path: &ObjectPath) -> DriverResult<SystemTime> {
+            self.inner.last_modified(path).await
+        }
+
+        async fn read_range(&self, path: &ObjectPath, range: Range<u64>) -> DriverResult<Vec<u8>> {
+            self.inner.read_range(path, range).await
+        }
+
+        async fn list(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+            self.inner.list(path).await
+        }
+
+        async fn stat(&self, path: &ObjectPath) -> DriverResult<Option<ObjectMetadata>> {
+            Ok(self.inner.stat(path).await?.map(|metadata| ObjectMetadata {
+                content_md5: Some(self.content_md5),
+                ..metadata
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn can_verify_downloads_against_checksums() {
+        use md5::{Digest, Md5};
+
+        let inner = StoreConfig::InMem().build().await.unwrap();
+        let path = Path::new("test.txt");
+        inner.write(path, "my content").await.unwrap();
+        let local =
+            std::env::temp_dir().join(format!("active-storage-checksum-{}", drivers::unique_id()));
+
+        let mut content_md5 = [0; 16];
+        content_md5.copy_from_slice(Md5::digest(b"my content").as_slice());
+        let store = StoreConfig::with_driver(Box::new(ChecksumDriver {
+            inner: inner.clone(),
+            content_md5,
+        }));
+        assert_eq!(
+            store
+                .download_to(path, &local.join("valid.txt"))
+                .await
+                .unwrap(),
+            10
+        );
+        assert_eq!(
+            tokio::fs::read(local.join("valid.txt")).await.unwrap(),
+            b"my content"
+        );
+
+        let store = StoreConfig::with_driver(Box::new(ChecksumDriver {
+            inner,
+            content_md5: [0; 16],
+        }));
+        assert!(matches!(
+            store.download_to(path, &local.join("corrupted.txt")).await,
+            Err(DriverError::IntegrityCheckFailed)
+        ));
+        assert!(!local.join("corrupted.txt").exists());
+        assert!(!local.join("corrupted.txt.part").exists());
+        let _ = tokio::fs::remove_dir_all(&local).await;
+    }
+
+    #[tokio::test]
+    async fn can_keep_the_local_mtime_of_multipart_uploads() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let local =
+            std::env::temp_dir().join(format!("active-storage-upload-{}", drivers::unique_id()));
+        std::fs::write(&local, "my content").unwrap();
+        let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        std::fs::File::options()
+            .write(true)
+            .open(&local)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+
+        let options = TransferOptions::default().with_part_size(3);
+        assert_eq!(
+            store
+                .upload_from_with_options(&local, Path::new("test.txt"), &options)
+                .await
+                .unwrap(),
+            10
+        );
+        assert_eq!(
+            store.read::<String>("test.txt").await.unwrap(),
+            "my content"
+        );
+        assert_eq!(store.last_modified("test.txt").await.unwrap(), mtime);
+        let _ = std::fs::remove_file(&local);
+    }
+
+    /// A driver over an in-memory store starting uploads and failing every
+    /// part upload and abort.
+    #[derive(Clone)]
+    struct FailingUploadDriver {
+        inner: Store,
+    }
+
+    #[async_trait::async_trait]
+    impl Driver for FailingUploadDriver {
+        async fn read(&self, path: &ObjectPath) -> DriverResult<Bytes> {
+            self.inner.read_bytes(path).await
+        }
+
+        async fn file_exists(&self, path: &ObjectPath) -> DriverResult<bool> {
+            self.inner.file_exists(path).await
+        }
+
+        async fn write(&self, path: &ObjectPath, content: Bytes) -> DriverResult<()> {
+            self.inner.write(path, content).await
+        }
+
+        async fn delete(&self, path: &ObjectPath) -> DriverResult<()> {
+            self.inner.delete(path).await
+        }
+
+        async fn delete_directory(&self, path: &ObjectPath) -> DriverResult<()> {
+            self.inner.delete_directory(path).await
+        }
+
+        async fn last_modified(&self, path: &ObjectPath) -> DriverResult<SystemTime> {
+            self.inner.last_modified(path).await
+        }
+
+        async fn read_range(&self, path: &ObjectPath, range: Range<u64>) -> DriverResult<Vec<u8>> {
+            self.inner.read_range(path, range).await
+        }
+
+        async fn list(&self, path: &ObjectPath) -> DriverResult<Vec<ObjectPath>> {
+            self.inner.list(path).await
+        }
+
+        async fn create_upload(&self, _path: &ObjectPath) -> DriverResult<String> {
+            Ok(drivers::unique_id())
+        }
+
+        async fn upload_part(
+            &self,
+            _path: &ObjectPath,
+            _upload_id: &str,
+            part_number: u32,
+            _content: Bytes,
+        ) -> DriverResult<UploadPart> {
+            Err(DriverError::InvalidPartNumber(part_number))
+        }
+
+        async fn abort_upload(&self, _path: &ObjectPath, _upload_id: &str) -> DriverResult<()> {
+            Err(DriverError::ResourceNotFound)
+        }
+    }
+
+    #[tokio::test]
+    async fn can_return_the_upload_error_when_the_abort_fails() {
+        let inner = StoreConfig::InMem().build().await.unwrap();
+        let store = StoreConfig::with_driver(Box::new(FailingUploadDriver { inner }));
+        let chunks = stream::iter(vec![Ok(b"my content".to_vec())]);
+
+        assert!(matches!(
+            store
+                .write_stream(Path::new("test.txt"), chunks, &TransferOptions::default())
+                .await,
+            Err(DriverError::InvalidPartNumber(1))
+        ));
     }
 }
@@ -0,0 +1,195 @@
+//! # Tempfile Module
+//!
+//! The `tempfile` module defines [`TempFile`], a guard over a scratch object
+//! created with [`Store::tempfile`], for staging flows such as uploading a
+//! file, validating it and only then moving it to its final path.
+//!
+//! The object is deleted when the guard is dropped, unless it was moved with
+//! [`TempFile::persist`]. Dropping deletes in the background of the current
+//! tokio runtime, objects leaked by a crash are removed by
+//! [`Store::cleanup_tempfiles`].
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::Path;
+//! use active_storage::StoreConfig;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!
+//!     let tempfile = store.tempfile(Path::new("staging")).unwrap();
+//!     tempfile.write("my content").await.unwrap();
+//!     tempfile.persist(Path::new("test.txt")).await.unwrap();
+//!
+//!     let content: String = store.read(Path::new("test.txt")).await.unwrap();
+//!     assert_eq!(content, "my content");
+//! }
+//! ```
+//!
+//! [`Store::tempfile`]: crate::store::Store::tempfile
+//! [`Store::cleanup_tempfiles`]: crate::store::Store::cleanup_tempfiles
+use crate::{
+    contents::{Contents, IntoBytes},
+    errors::DriverResult,
+    key::ObjectPath,
+    store::Store,
+};
+
+/// Struct representing a scratch object deleted when dropped.
+pub struct TempFile {
+    store: Store,
+    path: ObjectPath,
+    /// Whether the object was persisted or discarded, so dropping must not
+    /// delete it.
+    released: bool,
+}
+
+impl TempFile {
+    pub(crate) const fn new(store: Store, path: ObjectPath) -> Self {
+        Self {
+            store,
+            path,
+            released: false,
+        }
+    }
+
+    /// Returns the path of the scratch object.
+    #[must_use]
+    pub const fn path(&self) -> &ObjectPath {
+        &self.path
+    }
+
+    /// Writes the contents to the scratch object.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the contents could not be written.
+    pub async fn write<C: IntoBytes + Send>(&self, content: C) -> DriverResult<()> {
+        self.store.write(&self.path, content).await
+    }
+
+    /// Reads the contents of the scratch object.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the object was not written yet or could not be
+    /// read.
+    pub async fn read<T: TryFrom<Contents>>(&self) -> DriverResult<T> {
+        self.store.read(&self.path).await
+    }
+
+    /// Moves the scratch object to its final path, which keeps it from being
+    /// deleted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the object could not be moved, in which case the
+    /// scratch object is deleted when the guard is dropped.
+    pub async fn persist(mut self, path: impl Into<ObjectPath>) -> DriverResult<ObjectPath> {
+        let path = path.into();
+        let content = self.store.read_bytes(&self.path).await?;
+        self.store.write(&path, content).await?;
+        self.released = true;
+        self.store.delete(&self.path).await?;
+        Ok(path)
+    }
+
+    /// Deletes the scratch object now rather than in the background.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the object exists and could not be deleted.
+    pub async fn discard(mut self) -> DriverResult<()> {
+        self.released = true;
+        self.store.delete_if_exists(&self.path).await.map(|_| ())
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if self.released {
+            return;
+        }
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            let store = self.store.clone();
+            let path = std::mem::take(&mut self.path);
+            handle.spawn(async move {
+                let _ = store.delete_if_exists(&path).await;
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{path::Path, time::Duration};
+
+    use crate::{errors::DriverError, StoreConfig};
+
+    #[tokio::test]
+    async fn can_clean_up_tempfiles() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let prefix = Path::new("staging");
+
+        let tempfile = store.tempfile(prefix).unwrap();
+        assert!(tempfile.path().starts_with(prefix));
+        tempfile.write("content").await.unwrap();
+        assert_eq!(tempfile.read::<String>().await.unwrap(), "content");
+        drop(tempfile);
+        tokio::task::yield_now().await;
+        assert!(store.list(prefix).await.unwrap().is_empty());
+
+        let tempfile = store.tempfile(prefix).unwrap();
+        tempfile.write("content").await.unwrap();
+        tempfile.discard().await.unwrap();
+        assert!(store.list(prefix).await.unwrap().is_empty());
+
+        let tempfile = store.tempfile(prefix).unwrap();
+        tempfile.write("content").await.unwrap();
+        std::mem::forget(tempfile);
+        assert_eq!(
+            store
+                .cleanup_tempfiles(prefix, Duration::from_secs(60))
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            store
+                .cleanup_tempfiles(prefix, Duration::ZERO)
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(store.list(prefix).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn can_keep_held_and_reserved_files() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let held = Path::new("contracts/1.pdf");
+        store.write(held, "content").await.unwrap();
+        store.set_legal_hold(held, true).await.unwrap();
+
+        assert!(matches!(
+            store.tempfile(".active-storage-uploads"),
+            Err(DriverError::ReservedPath)
+        ));
+        assert!(matches!(
+            store
+                .cleanup_tempfiles(".active-storage-legal-holds", Duration::ZERO)
+                .await,
+            Err(DriverError::ReservedPath)
+        ));
+
+        assert_eq!(
+            store.cleanup_tempfiles("", Duration::ZERO).await.unwrap(),
+            0
+        );
+        assert!(store.file_exists(held).await.unwrap());
+        assert!(store.legal_hold(held).await.unwrap());
+    }
+}
@@ -0,0 +1,229 @@
+//! # Tenant Module
+//!
+//! The `tenant` module defines [`TenantStoreProvider`], which maps a tenant id
+//! to its own [`Store`] following a configurable [`TenantStrategy`], and
+//! caches the built stores.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::PathBuf;
+//! use active_storage::{
+//!     tenant::{TenantStoreProvider, TenantStrategy},
+//!     StoreConfig,
+//! };
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!     let provider = TenantStoreProvider::new(TenantStrategy::Prefix(store));
+//!
+//!     let tenant_store = provider.store("tenant-1").await.unwrap();
+//!     tenant_store
+//!         .write(PathBuf::from("test.txt").as_path(), "my content")
+//!         .await
+//!         .unwrap();
+//! }
+//! ```
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    errors::{DriverError, DriverResult},
+    key::Key,
+    store::Store,
+    StoreConfig,
+};
+
+/// A factory returning the [`StoreConfig`] of a given tenant id.
+pub type TenantConfigFactory = Arc<dyn Fn(&str) -> StoreConfig + Send + Sync>;
+
+/// Enum representing how a tenant id is mapped to its store.
+#[derive(Clone)]
+pub enum TenantStrategy {
+    /// All the tenants share the given store, each one scoped under a prefix
+    /// equal to its tenant id.
+    Prefix(Store),
+    /// Each tenant gets a store built from the configuration returned by the
+    /// factory, e.g. a distinct bucket per tenant.
+    Config(TenantConfigFactory),
+    /// Each tenant is mapped to an explicitly registered store, e.g. a
+    /// distinct driver per tenant.
+    Stores(HashMap<String, Store>),
+}
+
+/// Struct representing a provider of per tenant stores.
+///
+/// Built stores are cached, so the store of a tenant is built only once.
+pub struct TenantStoreProvider {
+    strategy: TenantStrategy,
+    cache: Mutex<HashMap<String, Store>>,
+}
+
+impl TenantStoreProvider {
+    /// Creates a new [`TenantStoreProvider`] with the given strategy.
+    #[must_use]
+    pub fn new(strategy: TenantStrategy) -> Self {
+        Self {
+            strategy,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the store of the given tenant, building and caching it on
+    /// first use.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::InvalidPath`] if the tenant id is not a single
+    /// path segment with the [`TenantStrategy::Prefix`] strategy, or an error
+    /// if the tenant id is not a valid prefix, the tenant is
+    /// not registered with the [`TenantStrategy::Stores`] strategy, or the
+    /// tenant store could not be built.
+    pub async fn store(&self, tenant_id: &str) -> DriverResult<Store> {
+        if let Some(store) = self.cached_store(tenant_id) {
+            return Ok(store);
+        }
+
+        let store = match &self.strategy {
+            TenantStrategy::Prefix(store) => store.scoped(&tenant_prefix(tenant_id)?)?,
+            TenantStrategy::Config(factory) => factory(tenant_id).build().await?,
+            TenantStrategy::Stores(stores) => stores
+                .get(tenant_id)
+                .cloned()
+                .ok_or(DriverError::ResourceNotFound)?,
+        };
+
+        Ok(self
+            .cache
+            .lock()
+            .expect("tenant cache failed getting a lock")
+            .entry(tenant_id.to_string())
+            .or_insert(store)
+            .clone())
+    }
+
+    /// Removes the given tenant store from the cache, so it is built again on
+    /// next use.
+    pub fn evict(&self, tenant_id: &str) {
+        self.cache
+            .lock()
+            .expect("tenant cache failed getting a lock")
+            .remove(tenant_id);
+    }
+
+    fn cached_store(&self, tenant_id: &str) -> Option<Store> {
+        self.cache
+            .lock()
+            .expect("tenant cache failed getting a lock")
+            .get(tenant_id)
+            .cloned()
+    }
+}
+
+/// Returns the prefix of the given tenant id, refusing the ids that are not a
+/// single path segment, e.g. `a/b` or `..`, so a tenant can't reach the files
+/// of another one.
+fn tenant_prefix(tenant_id: &str) -> DriverResult<Key> {
+    let key = Key::new(tenant_id)?;
+    if key.as_str() != tenant_id || tenant_id.contains(['/', '\\']) {
+        return Err(DriverError::InvalidPath);
+    }
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{
+        path::PathBuf,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn can_isolate_prefixed_tenants() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let provider = TenantStoreProvider::new(TenantStrategy::Prefix(store.clone()));
+
+        let file_path = PathBuf::from("test.txt");
+        let tenant_1 = provider.store("tenant-1").await.unwrap();
+        let tenant_2 = provider.store("tenant-2").await.unwrap();
+
+        tenant_1
+            .write(file_path.as_path(), "content")
+            .await
+            .unwrap();
+
+        assert!(tenant_1.file_exists(file_path.as_path()).await.unwrap());
+        assert!(!tenant_2.file_exists(file_path.as_path()).await.unwrap());
+        assert!(store
+            .file_exists(PathBuf::from("tenant-1").join("test.txt").as_path())
+            .await
+            .unwrap());
+
+        assert!(provider.store("..").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn can_reject_multi_segment_tenant_ids() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let provider = TenantStoreProvider::new(TenantStrategy::Prefix(store));
+
+        for tenant_id in [
+            "",
+            ".",
+            "..",
+            "tenant-1/..",
+            "tenant-1/files",
+            "/tenant-1",
+            "tenant-1/",
+            "a\\b",
+        ] {
+            assert!(
+                matches!(
+                    provider.store(tenant_id).await,
+                    Err(DriverError::InvalidPath)
+                ),
+                "{tenant_id}"
+            );
+        }
+        assert!(provider.store("tenant-1").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn can_cache_built_tenant_stores() {
+        let builds = Arc::new(AtomicUsize::new(0));
+        let factory_builds = builds.clone();
+        let provider = TenantStoreProvider::new(TenantStrategy::Config(Arc::new(move |_| {
+            factory_builds.fetch_add(1, Ordering::SeqCst);
+            StoreConfig::InMem()
+        })));
+
+        provider.store("tenant-1").await.unwrap();
+        provider.store("tenant-1").await.unwrap();
+        assert_eq!(builds.load(Ordering::SeqCst), 1);
+
+        provider.evict("tenant-1");
+        provider.store("tenant-1").await.unwrap();
+        assert_eq!(builds.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn can_map_registered_tenant_stores() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let provider = TenantStoreProvider::new(TenantStrategy::Stores(HashMap::from([(
+            "tenant-1".to_string(),
+            store,
+        )])));
+
+        assert!(provider.store("tenant-1").await.is_ok());
+        assert!(matches!(
+            provider.store("tenant-2").await,
+            Err(DriverError::ResourceNotFound)
+        ));
+    }
+}
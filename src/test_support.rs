@@ -0,0 +1,208 @@
+//! # Test Support Module
+//!
+//! The `test_support` module starts a storage emulator in docker and hands a
+//! ready [`Store`] to a test body, so crates built on the store can
+//! integration-test their storage code against S3 and Azure without cloud
+//! credentials.
+//!
+//! The container is removed once the test body returns. There is no GCS
+//! driver, so no fake-gcs emulator is provided.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use std::path::Path;
+//! use active_storage::test_support::{self, Emulator};
+//!
+//! #[test]
+//! fn can_store_avatars() {
+//!     test_support::run(Emulator::MinIO { port: 9000 }, |store| async move {
+//!         store.write(Path::new("avatar.png"), "content").await.unwrap();
+//!         assert!(store.file_exists(Path::new("avatar.png")).await.unwrap());
+//!     });
+//! }
+//! ```
+use std::{collections::HashMap, future::Future};
+
+use dockertest::{waitfor, Composition, Source};
+use dockertest_server::{Config, ContainerConfig, Test};
+
+use crate::{drivers::ContainerOptions, store::Store, StoreConfig};
+
+/// The bucket or container the stores handed to test bodies are bound to.
+pub const TEST_CONTAINER: &str = "test-bucket";
+
+/// The seconds to wait for an emulator to report it is ready.
+const STARTUP_TIMEOUT: u16 = 60;
+
+/// Enum representing the storage emulators, each listening on the given host
+/// port.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Emulator {
+    /// `LocalStack` serving S3.
+    #[cfg(feature = "aws_s3")]
+    LocalStack { port: u16 },
+    /// `MinIO`, an S3 compatible server.
+    #[cfg(feature = "aws_s3")]
+    MinIO { port: u16 },
+    /// Azurite, the Azure blob storage emulator.
+    #[cfg(feature = "azure")]
+    Azurite { port: u16 },
+}
+
+impl Emulator {
+    /// Returns the host port the emulator listens on.
+    #[must_use]
+    pub const fn port(&self) -> u16 {
+        match self {
+            #[cfg(feature = "aws_s3")]
+            Self::LocalStack { port } | Self::MinIO { port } => *port,
+            #[cfg(feature = "azure")]
+            Self::Azurite { port } => *port,
+        }
+    }
+
+    /// Builds a store bound to [`TEST_CONTAINER`] on the running emulator,
+    /// creating the container.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the container could not be created.
+    pub async fn store(&self) -> Store {
+        let store = match self {
+            #[cfg(feature = "aws_s3")]
+            Self::LocalStack { port } => s3_store(*port, "test", "test"),
+            #[cfg(feature = "aws_s3")]
+            Self::MinIO { port } => s3_store(*port, "minioadmin", "minioadmin"),
+            #[cfg(feature = "azure")]
+            Self::Azurite { port } => StoreConfig::with_driver(Box::new(
+                crate::drivers::azure::AzureDriver::with_emulator(
+                    TEST_CONTAINER,
+                    "127.0.0.1",
+                    *port,
+                ),
+            )),
+        };
+
+        store
+            .ensure_container(&ContainerOptions::default())
+            .await
+            .expect("emulator container should be created");
+        store
+    }
+}
+
+/// Struct representing the docker container of an [`Emulator`].
+#[derive(Clone)]
+struct EmulatorConfig {
+    emulator: Emulator,
+    handle: String,
+}
+
+impl Config for EmulatorConfig {
+    fn into_composition(self) -> Composition {
+        let (image, version, internal_port, args, env, ready_message) = match self.emulator {
+            #[cfg(feature = "aws_s3")]
+            Emulator::LocalStack { .. } => (
+                "localstack/localstack",
+                "3.1",
+                4566,
+                vec![],
+                HashMap::from([("SERVICES".to_string(), "s3".to_string())]),
+                "Ready.",
+            ),
+            #[cfg(feature = "aws_s3")]
+            Emulator::MinIO { .. } => (
+                "minio/minio",
+                "latest",
+                9000,
+                vec!["server".to_string(), "/data".to_string()],
+                HashMap::new(),
+                "API:",
+            ),
+            #[cfg(feature = "azure")]
+            Emulator::Azurite { .. } => (
+                "mcr.microsoft.com/azure-storage/azurite",
+                "latest",
+                10000,
+                vec![
+                    "azurite-blob".to_string(),
+                    "--blobHost".to_string(),
+                    "0.0.0.0".to_string(),
+                ],
+                HashMap::new(),
+                "Azurite Blob service successfully listens",
+            ),
+        };
+
+        ContainerConfig {
+            args,
+            env,
+            handle: self.handle,
+            name: image.to_string(),
+            source: Source::DockerHub,
+            version: version.to_string(),
+            ports: Some(vec![(internal_port, u32::from(self.emulator.port()))]),
+            wait: Some(Box::new(waitfor::MessageWait {
+                message: ready_message.to_string(),
+                source: waitfor::MessageSource::Stdout,
+                timeout: STARTUP_TIMEOUT,
+            })),
+            bind_mounts: HashMap::new(),
+        }
+        .into()
+    }
+
+    fn handle(&self) -> &str {
+        &self.handle
+    }
+}
+
+/// Starts the emulator, runs the test body with a store bound to
+/// [`TEST_CONTAINER`] on it, and removes the emulator.
+///
+/// Like `#[test]` bodies, it blocks the calling thread, and must not be
+/// called from within a tokio runtime.
+///
+/// # Panics
+///
+/// Panics if docker is unavailable, the emulator doesn't start or the test
+/// body panics.
+pub fn run<T, F>(emulator: Emulator, test: T)
+where
+    T: FnOnce(Store) -> F + Send + 'static,
+    F: Future<Output = ()> + Send + 'static,
+{
+    let mut docker_test = Test::new();
+    docker_test.register(EmulatorConfig {
+        emulator,
+        handle: dockertest_server::new_handle("active-storage-emulator"),
+    });
+
+    docker_test.run(move |_instance| async move {
+        test(emulator.store().await).await;
+    });
+}
+
+/// Returns a store bound to [`TEST_CONTAINER`] on an S3 emulator.
+#[cfg(feature = "aws_s3")]
+fn s3_store(port: u16, access_key: &str, secret_key: &str) -> Store {
+    let config = aws_sdk_s3::Config::builder()
+        .force_path_style(true)
+        .endpoint_url(format!("http://127.0.0.1:{port}/"))
+        .region(aws_types::region::Region::new("us-east-1"))
+        .credentials_provider(aws_sdk_s3::config::Credentials::new(
+            access_key,
+            secret_key,
+            None,
+            None,
+            "active-storage-test-support",
+        ))
+        .build();
+    let client = aws_sdk_s3::Client::from_conf(config);
+
+    StoreConfig::with_driver(Box::new(crate::drivers::aws_s3::AwsS3::with_client(
+        client,
+        TEST_CONTAINER,
+    )))
+}
@@ -0,0 +1,181 @@
+//! # Text Module
+//!
+//! The `text` module splits the chunks of a streaming read into lines, and
+//! with the `csv` feature into CSV records, so large text files are
+//! processed without downloading them whole.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::PathBuf;
+//! use active_storage::StoreConfig;
+//! use futures::TryStreamExt;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!     let file_path = PathBuf::from("test.txt");
+//!     store.write(file_path.as_path(), "first\r\nsecond\n").await.unwrap();
+//!
+//!     let lines: Vec<String> = store
+//!         .read_lines(file_path.as_path())
+//!         .await
+//!         .unwrap()
+//!         .try_collect()
+//!         .await
+//!         .unwrap();
+//!     assert_eq!(lines, vec!["first", "second"]);
+//! }
+//! ```
+use std::pin::Pin;
+
+use futures::{stream, Stream, StreamExt};
+
+use crate::{
+    errors::{DriverError, DriverResult},
+    serve::ByteStream,
+};
+
+/// A stream of the lines of a file, without their line terminator.
+pub type LineStream = Pin<Box<dyn Stream<Item = DriverResult<String>> + Send>>;
+
+/// A stream of the records of a CSV file.
+#[cfg(feature = "csv")]
+pub type RecordStream = Pin<Box<dyn Stream<Item = DriverResult<Vec<String>>> + Send>>;
+
+/// Splits the chunks into lines terminated by `\n` or `\r\n`. A last line
+/// without a terminator is still returned.
+pub(crate) fn lines(chunks: ByteStream) -> LineStream {
+    let state = (chunks, Vec::new(), false);
+    let lines = stream::try_unfold(state, |(mut chunks, mut buffer, mut done)| async move {
+        loop {
+            if let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+                let rest = buffer.split_off(end + 1);
+                let line = decode_line(std::mem::replace(&mut buffer, rest))?;
+                return Ok(Some((line, (chunks, buffer, done))));
+            }
+            if done {
+                if buffer.is_empty() {
+                    return Ok(None);
+                }
+                let line = decode_line(std::mem::take(&mut buffer))?;
+                return Ok(Some((line, (chunks, buffer, done))));
+            }
+
+            match chunks.next().await.transpose()? {
+                Some(chunk) => buffer.extend(chunk),
+                None => done = true,
+            }
+        }
+    });
+
+    Box::pin(lines)
+}
+
+/// Decodes a line, stripping its line terminator.
+fn decode_line(mut line: Vec<u8>) -> DriverResult<String> {
+    if line.ends_with(b"\n") {
+        line.pop();
+        if line.ends_with(b"\r") {
+            line.pop();
+        }
+    }
+    String::from_utf8(line).map_err(|_| DriverError::DecodeError)
+}
+
+/// Parses the lines into RFC 4180 records. Quoted fields may contain
+/// separators, escaped `""` quotes and line breaks.
+#[cfg(feature = "csv")]
+pub(crate) fn csv_records(lines: LineStream, delimiter: char) -> RecordStream {
+    let records = stream::try_unfold(lines, move |mut lines| async move {
+        let mut record = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+
+        loop {
+            let Some(line) = lines.next().await.transpose()? else {
+                if quoted {
+                    return Err(DriverError::DecodeError);
+                }
+                return Ok(None);
+            };
+
+            let mut chars = line.chars().peekable();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' if quoted && chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    '"' if quoted => quoted = false,
+                    '"' if field.is_empty() => quoted = true,
+                    c if c == delimiter && !quoted => record.push(std::mem::take(&mut field)),
+                    c => field.push(c),
+                }
+            }
+
+            if quoted {
+                field.push('\n');
+            } else {
+                record.push(field);
+                return Ok(Some((record, lines)));
+            }
+        }
+    });
+
+    Box::pin(records)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use futures::TryStreamExt;
+
+    use super::*;
+
+    fn chunks(chunks: &[&str]) -> ByteStream {
+        let chunks = chunks
+            .iter()
+            .map(|chunk| Ok(chunk.as_bytes().to_vec()))
+            .collect::<Vec<_>>();
+        Box::pin(stream::iter(chunks))
+    }
+
+    #[tokio::test]
+    async fn can_split_lines_across_chunks() {
+        let split: Vec<String> = lines(chunks(&["fir", "st\r", "\nsec", "ond\n\nla", "st"]))
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(split, vec!["first", "second", "", "last"]);
+
+        assert!(lines(chunks(&[]))
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[cfg(feature = "csv")]
+    #[tokio::test]
+    async fn can_parse_csv_records() {
+        let content = "name,note\nfile.txt,\"a, \"\"quoted\"\"\nnote\"\n,\n";
+        let records: Vec<Vec<String>> = csv_records(lines(chunks(&[content])), ',')
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(
+            records,
+            vec![
+                vec!["name", "note"],
+                vec!["file.txt", "a, \"quoted\"\nnote"],
+                vec!["", ""],
+            ]
+        );
+
+        assert!(csv_records(lines(chunks(&["\"open"])), ',')
+            .try_collect::<Vec<_>>()
+            .await
+            .is_err());
+    }
+}
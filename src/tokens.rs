@@ -0,0 +1,413 @@
+//! # Tokens Module
+//!
+//! The `tokens` module defines [`DownloadTokens`], which issues limited-use
+//! download tokens for sharing private files, e.g. a link that can be used
+//! once, or a few times within a day, from a given address.
+//!
+//! A token has the form `<id>.<signature>`, where the signature is the hex
+//! encoded HMAC of the id, so tokens can't be guessed without the secret.
+//! The download count, expiry and address of each token are kept as a JSON
+//! record in the store, under [`TOKEN_RECORDS_PREFIX`]. The web layer checks a
+//! token with [`DownloadTokens::redeem`] before serving the file.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use active_storage::{
+//!     store::Store,
+//!     tokens::{DownloadTokens, TokenOptions},
+//! };
+//!
+//! async fn share(store: Store) -> String {
+//!     let tokens = DownloadTokens::new(store, "my secret");
+//!     tokens
+//!         .issue(
+//!             "reports/2024.pdf",
+//!             &TokenOptions::default()
+//!                 .with_max_downloads(3)
+//!                 .with_expires_in(Duration::from_secs(24 * 60 * 60)),
+//!         )
+//!         .await
+//!         .unwrap()
+//! }
+//! ```
+use std::{
+    net::IpAddr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tokio::sync::Mutex;
+
+use crate::{
+    clock, drivers,
+    errors::{DriverError, DriverResult},
+    hex,
+    key::{Key, ObjectPath},
+    mac,
+    store::Store,
+};
+
+/// The prefix of the token records.
+pub const TOKEN_RECORDS_PREFIX: &str = ".active-storage-tokens";
+
+/// The separator of the token id and signature, which never appears in them.
+const SEPARATOR: char = '.';
+
+/// Struct representing the limits of an issued token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenOptions {
+    /// How many times the token can be redeemed.
+    pub max_downloads: u32,
+    /// How long the token is valid.
+    pub expires_in: Duration,
+    /// The only client address the token can be redeemed from.
+    pub ip: Option<IpAddr>,
+}
+
+impl Default for TokenOptions {
+    /// A single download within an hour, from any address.
+    fn default() -> Self {
+        Self {
+            max_downloads: 1,
+            expires_in: Duration::from_secs(60 * 60),
+            ip: None,
+        }
+    }
+}
+
+impl TokenOptions {
+    /// Sets how many times the token can be redeemed.
+    #[must_use]
+    pub const fn with_max_downloads(mut self, max_downloads: u32) -> Self {
+        self.max_downloads = max_downloads;
+        self
+    }
+
+    /// Sets how long the token is valid.
+    #[must_use]
+    pub const fn with_expires_in(mut self, expires_in: Duration) -> Self {
+        self.expires_in = expires_in;
+        self
+    }
+
+    /// Binds the token to the given client address.
+    #[must_use]
+    pub const fn with_ip(mut self, ip: IpAddr) -> Self {
+        self.ip = Some(ip);
+        self
+    }
+}
+
+/// Struct representing the stored record of a token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct TokenRecord {
+    path: String,
+    downloads: u32,
+    max_downloads: u32,
+    /// The expiry, in milliseconds since the Unix epoch.
+    expires_at: u64,
+    ip: Option<IpAddr>,
+}
+
+impl TokenRecord {
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "path": self.path,
+            "downloads": self.downloads,
+            "max_downloads": self.max_downloads,
+            "expires_at": self.expires_at,
+            "ip": self.ip.map(|ip| ip.to_string()),
+        })
+    }
+
+    /// Parses a stored record, returning `None` when it is malformed.
+    fn from_json(record: &serde_json::Value) -> Option<Self> {
+        Some(Self {
+            path: record["path"].as_str()?.to_string(),
+            downloads: u32::try_from(record["downloads"].as_u64()?).ok()?,
+            max_downloads: u32::try_from(record["max_downloads"].as_u64()?).ok()?,
+            expires_at: record["expires_at"].as_u64()?,
+            ip: match &record["ip"] {
+                serde_json::Value::Null => None,
+                ip => Some(ip.as_str()?.parse().ok()?),
+            },
+        })
+    }
+}
+
+/// Struct issuing and redeeming limited-use download tokens.
+#[derive(Clone)]
+pub struct DownloadTokens {
+    store: Store,
+    secret: Vec<u8>,
+    /// Serializes the redemptions, so concurrent downloads in this process
+    /// can't exceed the limit of a token.
+    lock: Arc<Mutex<()>>,
+}
+
+impl DownloadTokens {
+    /// Creates a new [`DownloadTokens`] keeping the token records in the
+    /// given store and signing the tokens with the given secret.
+    #[must_use]
+    pub fn new<S: AsRef<[u8]>>(store: Store, secret: S) -> Self {
+        Self {
+            store,
+            secret: secret.as_ref().to_vec(),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Issues a token for the file at the given path with the given limits.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is invalid or the record could not be
+    /// stored.
+    pub async fn issue(
+        &self,
+        path: impl Into<ObjectPath>,
+        options: &TokenOptions,
+    ) -> DriverResult<String> {
+        let key = path.into().to_key()?;
+        let id = drivers::unique_id();
+        let record = TokenRecord {
+            path: key.to_string(),
+            downloads: 0,
+            max_downloads: options.max_downloads,
+            expires_at: millis(clock::now() + options.expires_in),
+            ip: options.ip,
+        };
+        self.store
+            .records()
+            .write(record_path(&id), record.to_json().to_string())
+            .await?;
+
+        Ok(format!(
+            "{id}{SEPARATOR}{}",
+            hex::encode(&self.mac(&id).finalize().into_bytes())
+        ))
+    }
+
+    /// Checks the token for a client with the given address without using a
+    /// download, e.g. to answer a `HEAD` request, returning the path of the
+    /// file it grants.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`Self::redeem`].
+    pub async fn verify(&self, token: &str, ip: Option<IpAddr>) -> DriverResult<ObjectPath> {
+        let (_, record) = self.find(token, ip).await?;
+        Ok(ObjectPath::from(&Key::new(&record.path)?))
+    }
+
+    /// Uses one download of the token for a client with the given address,
+    /// returning the path of the file it grants. The record of a token is
+    /// deleted with its last download.
+    ///
+    /// Redemptions are serialized within this [`DownloadTokens`] and its
+    /// clones; processes sharing the records may together exceed the limit
+    /// by their number of concurrent downloads.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::InvalidToken`] if the token is malformed,
+    /// forged, revoked or used up, [`DriverError::TokenExpired`] if it is
+    /// past its expiry, and [`DriverError::PermissionDenied`] if it is bound
+    /// to another address.
+    pub async fn redeem(&self, token: &str, ip: Option<IpAddr>) -> DriverResult<ObjectPath> {
+        let _guard = self.lock.lock().await;
+        let (id, mut record) = self.find(token, ip).await?;
+
+        record.downloads += 1;
+        if record.downloads >= record.max_downloads {
+            self.store.records().delete(record_path(id)).await?;
+        } else {
+            self.store
+                .records()
+                .write(record_path(id), record.to_json().to_string())
+                .await?;
+        }
+        Ok(ObjectPath::from(&Key::new(&record.path)?))
+    }
+
+    /// Revokes the token, so it can't be redeemed anymore. Revoking a used
+    /// up or revoked token does nothing.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::InvalidToken`] if the token is malformed or
+    /// forged, or an error if the record could not be deleted.
+    pub async fn revoke(&self, token: &str) -> DriverResult<()> {
+        let id = self.token_id(token)?;
+        self.store
+            .records()
+            .delete_if_exists(record_path(id))
+            .await?;
+        Ok(())
+    }
+
+    /// Returns the id and the record of a valid token for the given address.
+    async fn find<'a>(
+        &self,
+        token: &'a str,
+        ip: Option<IpAddr>,
+    ) -> DriverResult<(&'a str, TokenRecord)> {
+        let id = self.token_id(token)?;
+        let record = match self.store.records().read::<String>(record_path(id)).await {
+            Ok(record) => record,
+            Err(DriverError::ResourceNotFound) => return Err(DriverError::InvalidToken),
+            Err(err) => return Err(err),
+        };
+        let record = serde_json::from_str(&record)
+            .ok()
+            .as_ref()
+            .and_then(TokenRecord::from_json)
+            .ok_or(DriverError::InvalidToken)?;
+
+        if millis(clock::now()) > record.expires_at {
+            self.store
+                .records()
+                .delete_if_exists(record_path(id))
+                .await?;
+            return Err(DriverError::TokenExpired);
+        }
+        if record.downloads >= record.max_downloads {
+            return Err(DriverError::InvalidToken);
+        }
+        if record.ip.is_some_and(|bound| ip != Some(bound)) {
+            return Err(DriverError::PermissionDenied);
+        }
+        Ok((id, record))
+    }
+
+    /// Returns the id of the token after checking its signature.
+    fn token_id<'a>(&self, token: &'a str) -> DriverResult<&'a str> {
+        let (id, signature) = token
+            .split_once(SEPARATOR)
+            .ok_or(DriverError::InvalidToken)?;
+        let signature = hex::decode(signature).ok_or(DriverError::InvalidToken)?;
+        self.mac(id)
+            .verify_slice(&signature)
+            .map_err(|_| DriverError::InvalidToken)?;
+        Ok(id)
+    }
+
+    fn mac(&self, id: &str) -> Hmac<Sha256> {
+        mac::hmac_sha256(&self.secret, &[id.as_bytes()])
+    }
+}
+
+/// Returns the path of the record of the token with the given id.
+fn record_path(id: &str) -> ObjectPath {
+    ObjectPath::new(TOKEN_RECORDS_PREFIX).join(id)
+}
+
+/// Returns the milliseconds since the Unix epoch of the given time.
+fn millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |duration| {
+        u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    use super::*;
+    use crate::StoreConfig;
+
+    #[tokio::test]
+    async fn can_redeem_limited_use_tokens() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let tokens = DownloadTokens::new(store.clone(), "secret");
+
+        let token = tokens
+            .issue(
+                Path::new("reports/2024.pdf"),
+                &TokenOptions::default().with_max_downloads(2),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            tokens.verify(&token, None).await.unwrap(),
+            ObjectPath::new("reports/2024.pdf")
+        );
+        for _ in 0..2 {
+            assert_eq!(
+                tokens.redeem(&token, None).await.unwrap(),
+                ObjectPath::new("reports/2024.pdf")
+            );
+        }
+        assert!(matches!(
+            tokens.redeem(&token, None).await,
+            Err(DriverError::InvalidToken)
+        ));
+        assert!(store
+            .records()
+            .list(Path::new(TOKEN_RECORDS_PREFIX))
+            .await
+            .unwrap()
+            .is_empty());
+
+        let token = tokens
+            .issue(Path::new("file.txt"), &TokenOptions::default())
+            .await
+            .unwrap();
+        let (id, _) = token.split_once(SEPARATOR).unwrap();
+        for forged in [
+            id.to_string(),
+            format!("{id}{SEPARATOR}00"),
+            DownloadTokens::new(store.clone(), "other")
+                .issue(Path::new("file.txt"), &TokenOptions::default())
+                .await
+                .unwrap(),
+        ] {
+            assert!(matches!(
+                tokens.redeem(&forged, None).await,
+                Err(DriverError::InvalidToken)
+            ));
+        }
+        assert!(tokens.revoke(&token).await.is_ok());
+        assert!(matches!(
+            tokens.redeem(&token, None).await,
+            Err(DriverError::InvalidToken)
+        ));
+    }
+
+    #[tokio::test]
+    async fn can_reject_expired_and_unbound_tokens() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let tokens = DownloadTokens::new(store, "secret");
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+
+        let token = tokens
+            .issue(Path::new("file.txt"), &TokenOptions::default().with_ip(ip))
+            .await
+            .unwrap();
+        for other in [None, Some("10.0.0.2".parse().unwrap())] {
+            assert!(matches!(
+                tokens.redeem(&token, other).await,
+                Err(DriverError::PermissionDenied)
+            ));
+        }
+        assert!(tokens.redeem(&token, Some(ip)).await.is_ok());
+
+        let token = tokens
+            .issue(
+                Path::new("file.txt"),
+                &TokenOptions::default().with_expires_in(Duration::ZERO),
+            )
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert!(matches!(
+            tokens.redeem(&token, None).await,
+            Err(DriverError::TokenExpired)
+        ));
+    }
+}
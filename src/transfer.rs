@@ -0,0 +1,475 @@
+//! # Transfer Module
+//!
+//! The `transfer` module defines [`TransferOptions`], the options shared by
+//! the long-running transfer APIs of [`crate::store::Store`]: streaming reads
+//! and writes and parallel downloads.
+//!
+//! Transfers can be throttled with a [`BandwidthLimiter`], either per transfer
+//! with [`TransferOptions::with_bandwidth_limit`] or for every transfer of a
+//! store with [`crate::store::Store::with_bandwidth_limit`]. A limiter shared
+//! by several transfers caps their combined throughput.
+//!
+//! Transfers can be aborted with a [`CancellationToken`] set with
+//! [`TransferOptions::with_cancellation`]. A cancelled transfer fails with
+//! [`DriverError::Cancelled`] at the next part boundary, and an interrupted
+//! upload is aborted on the backend so no parts are left behind.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::PathBuf;
+//! use active_storage::{transfer::TransferOptions, StoreConfig};
+//! use futures::TryStreamExt;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!     let file_path = PathBuf::from("test.txt");
+//!     store.write(file_path.as_path(), "my content").await.unwrap();
+//!
+//!     let options = TransferOptions::default().with_progress(|progress| {
+//!         println!("{}/{:?} bytes", progress.transferred, progress.total);
+//!     });
+//!     let content: Vec<Vec<u8>> = store
+//!         .read_stream(file_path.as_path(), &options)
+//!         .await
+//!         .unwrap()
+//!         .try_collect()
+//!         .await
+//!         .unwrap();
+//!     assert_eq!(content.concat(), b"my content");
+//! }
+//! ```
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::{
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use futures::future::{self, Either};
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use md5::{Digest, Md5};
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use tokio::io::AsyncWrite;
+use tokio::time::Instant;
+pub use tokio_util::sync::CancellationToken;
+
+use crate::errors::{DriverError, DriverResult};
+
+/// The default size of the chunks transfers are split into.
+pub const DEFAULT_PART_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Struct representing the progress of a transfer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Progress {
+    /// The number of bytes transferred so far.
+    pub transferred: u64,
+    /// The total number of bytes to transfer, when known.
+    pub total: Option<u64>,
+    /// The time elapsed since the transfer started.
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// Returns the average throughput since the transfer started, in bytes
+    /// per second.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn throughput(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.transferred as f64 / secs
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Struct representing a token bucket bandwidth limiter.
+///
+/// The bucket holds up to one second of transfer, so short bursts are allowed
+/// and the average throughput never exceeds the configured rate. Clones share
+/// the same bucket.
+#[derive(Clone, Debug)]
+pub struct BandwidthLimiter {
+    bytes_per_second: f64,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl BandwidthLimiter {
+    /// Creates a new [`BandwidthLimiter`] capping the throughput to the given
+    /// number of bytes per second.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn new(bytes_per_second: u64) -> Self {
+        let bytes_per_second = bytes_per_second.max(1) as f64;
+        Self {
+            bytes_per_second,
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens: bytes_per_second,
+                updated: Instant::now(),
+            })),
+        }
+    }
+
+    /// Waits until the given number of bytes can be transferred.
+    #[allow(clippy::cast_precision_loss)]
+    pub async fn acquire(&self, bytes: u64) {
+        let wait = {
+            let mut bucket = self
+                .bucket
+                .lock()
+                .expect("bandwidth limiter failed getting a lock");
+            let now = Instant::now();
+            let refill = now.duration_since(bucket.updated).as_secs_f64() * self.bytes_per_second;
+            bucket.tokens = (bucket.tokens + refill).min(self.bytes_per_second) - bytes as f64;
+            bucket.updated = now;
+
+            // a negative balance is the debt to pay back before transferring
+            if bucket.tokens < 0.0 {
+                Duration::from_secs_f64(-bucket.tokens / self.bytes_per_second)
+            } else {
+                Duration::ZERO
+            }
+        };
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// A callback invoked each time a transfer makes progress.
+pub type ProgressCallback = Arc<dyn Fn(Progress) + Send + Sync>;
+
+/// Struct representing the options of a transfer.
+#[derive(Clone)]
+pub struct TransferOptions {
+    /// The size of the chunks the transfer is split into.
+    pub part_size: u64,
+    progress: Option<ProgressCallback>,
+    bandwidth: Option<BandwidthLimiter>,
+    cancellation: Option<CancellationToken>,
+}
+
+impl Default for TransferOptions {
+    fn default() -> Self {
+        Self {
+            part_size: DEFAULT_PART_SIZE,
+            progress: None,
+            bandwidth: None,
+            cancellation: None,
+        }
+    }
+}
+
+impl fmt::Debug for TransferOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransferOptions")
+            .field("part_size", &self.part_size)
+            .field("progress", &self.progress.is_some())
+            .field("bandwidth", &self.bandwidth)
+            .field("cancellation", &self.cancellation)
+            .finish()
+    }
+}
+
+impl TransferOptions {
+    /// Sets the size of the chunks the transfer is split into. With S3
+    /// uploads, parts must be at least 5 MiB.
+    #[must_use]
+    pub const fn with_part_size(mut self, part_size: u64) -> Self {
+        self.part_size = part_size;
+        self
+    }
+
+    /// Sets a callback invoked each time the transfer makes progress.
+    #[must_use]
+    pub fn with_progress<F>(mut self, progress: F) -> Self
+    where
+        F: Fn(Progress) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Caps the throughput of the transfer with the given limiter, taking
+    /// precedence over the store limiter.
+    #[must_use]
+    pub fn with_bandwidth_limit(mut self, limiter: BandwidthLimiter) -> Self {
+        self.bandwidth = Some(limiter);
+        self
+    }
+
+    /// Aborts the transfer once the given token is cancelled.
+    #[must_use]
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Returns an error if the transfer was cancelled.
+    pub(crate) fn check_cancelled(&self) -> DriverResult<()> {
+        check_cancelled(self.cancellation.as_ref())
+    }
+
+    /// Returns a tracker throttling and reporting the progress of a new
+    /// transfer, falling back to the given store limiter.
+    pub(crate) fn tracker(
+        &self,
+        total: Option<u64>,
+        store_bandwidth: Option<&BandwidthLimiter>,
+    ) -> Tracker {
+        Tracker {
+            callback: self.progress.clone(),
+            bandwidth: self.bandwidth.clone().or_else(|| store_bandwidth.cloned()),
+            cancellation: self.cancellation.clone(),
+            // the clock is only read when reporting progress, as it is not
+            // available in browsers
+            started: self.progress.as_ref().map(|_| Instant::now()),
+            transferred: 0,
+            total,
+        }
+    }
+}
+
+/// Struct tracking the progress of a single transfer.
+pub(crate) struct Tracker {
+    callback: Option<ProgressCallback>,
+    bandwidth: Option<BandwidthLimiter>,
+    cancellation: Option<CancellationToken>,
+    started: Option<Instant>,
+    transferred: u64,
+    total: Option<u64>,
+}
+
+impl Tracker {
+    /// Returns an error if the transfer was cancelled.
+    pub(crate) fn check_cancelled(&self) -> DriverResult<()> {
+        check_cancelled(self.cancellation.as_ref())
+    }
+
+    /// Waits for the bandwidth limit, if any, then records the given number
+    /// of transferred bytes and reports the progress.
+    ///
+    /// Fails when the transfer is cancelled, including while throttled.
+    pub(crate) async fn advance(&mut self, bytes: u64) -> DriverResult<()> {
+        if let Some(bandwidth) = &self.bandwidth {
+            match &self.cancellation {
+                Some(token) => {
+                    let cancelled = std::pin::pin!(token.cancelled());
+                    let acquire = std::pin::pin!(bandwidth.acquire(bytes));
+                    if let Either::Left(_) = future::select(cancelled, acquire).await {
+                        return Err(DriverError::Cancelled);
+                    }
+                }
+                None => bandwidth.acquire(bytes).await,
+            }
+        }
+
+        self.transferred += bytes;
+        if let (Some(callback), Some(started)) = (&self.callback, self.started) {
+            callback(Progress {
+                transferred: self.transferred,
+                total: self.total,
+                elapsed: started.elapsed(),
+            });
+        }
+        self.check_cancelled()
+    }
+}
+
+/// Struct wrapping a writer to compute the size and the MD5 digest of the
+/// content written through it, so a download is verified without reading the
+/// local file back.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) struct ChecksumWriter<W> {
+    inner: W,
+    md5: Md5,
+    written: u64,
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl<W> ChecksumWriter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Self {
+            inner,
+            md5: Md5::new(),
+            written: 0,
+        }
+    }
+
+    /// Returns the inner writer, the number of written bytes and their MD5
+    /// digest.
+    pub(crate) fn finish(self) -> (W, u64, [u8; 16]) {
+        let mut md5 = [0; 16];
+        md5.copy_from_slice(self.md5.finalize().as_slice());
+        (self.inner, self.written, md5)
+    }
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl<W: AsyncWrite + Unpin> AsyncWrite for ChecksumWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        let written = ready!(Pin::new(&mut this.inner).poll_write(cx, buf))?;
+        this.md5.update(&buf[..written]);
+        this.written += written as u64;
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+/// Returns [`DriverError::Cancelled`] when the given token is cancelled.
+fn check_cancelled(token: Option<&CancellationToken>) -> DriverResult<()> {
+    if token.is_some_and(CancellationToken::is_cancelled) {
+        Err(DriverError::Cancelled)
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    use futures::{stream, StreamExt, TryStreamExt};
+
+    use super::*;
+    use crate::StoreConfig;
+
+    #[tokio::test]
+    async fn can_track_progress() {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let reported = reports.clone();
+        let options = TransferOptions::default()
+            .with_progress(move |progress| reported.lock().unwrap().push(progress.transferred));
+
+        let mut tracker = options.tracker(Some(10), None);
+        tracker.advance(4).await.unwrap();
+        tracker.advance(6).await.unwrap();
+
+        assert_eq!(*reports.lock().unwrap(), vec![4, 10]);
+    }
+
+    #[test]
+    fn can_compute_throughput() {
+        let progress = Progress {
+            transferred: 1000,
+            total: None,
+            elapsed: Duration::from_secs(2),
+        };
+        assert!((progress.throughput() - 500.0).abs() < f64::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn can_stream_write_and_read() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let path = Path::new("file.txt");
+        let options = TransferOptions::default().with_part_size(4);
+
+        let chunks = stream::iter(vec![Ok(b"hello".to_vec()), Ok(b" world".to_vec())]);
+        assert_eq!(
+            store.write_stream(path, chunks, &options).await.unwrap(),
+            11
+        );
+        assert_eq!(store.read::<String>(path).await.unwrap(), "hello world");
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let reported = reports.clone();
+        let options =
+            options.with_progress(move |progress| reported.lock().unwrap().push(progress));
+        let chunks = store
+            .read_stream(path, &options)
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(
+            chunks,
+            vec![b"hell".to_vec(), b"o wo".to_vec(), b"rld".to_vec()]
+        );
+
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 3);
+        assert_eq!(reports[2].transferred, 11);
+        assert_eq!(reports[2].total, Some(11));
+    }
+
+    #[tokio::test]
+    async fn can_cancel_transfers() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let path = Path::new("file.txt");
+        let token = CancellationToken::new();
+        let options = TransferOptions::default()
+            .with_part_size(4)
+            .with_cancellation(token.clone());
+
+        let cancel = token.clone();
+        let chunks = stream::iter(vec![b"hello".to_vec(), b" world".to_vec()]).map(move |chunk| {
+            cancel.cancel();
+            Ok(chunk)
+        });
+        assert!(matches!(
+            store.write_stream(path, chunks, &options).await,
+            Err(DriverError::Cancelled)
+        ));
+        assert!(!store.file_exists(path).await.unwrap());
+        assert!(store
+            .records()
+            .list(Path::new(crate::upload::UPLOADS_PREFIX))
+            .await
+            .unwrap()
+            .is_empty());
+
+        store.write(path, b"hello world").await.unwrap();
+        let mut content = Vec::new();
+        assert!(matches!(
+            store
+                .download_parallel_with_options(path, &mut content, 2, &options)
+                .await,
+            Err(DriverError::Cancelled)
+        ));
+        assert!(content.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn can_limit_bandwidth() {
+        let limiter = BandwidthLimiter::new(1000);
+        let started = Instant::now();
+
+        // the first second is available as a burst
+        limiter.acquire(1000).await;
+        assert!(started.elapsed() < Duration::from_millis(10));
+
+        limiter.acquire(500).await;
+        limiter.acquire(500).await;
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(999), "elapsed {elapsed:?}");
+        assert!(elapsed < Duration::from_millis(1100), "elapsed {elapsed:?}");
+    }
+}
@@ -0,0 +1,279 @@
+//! # Upload Module
+//!
+//! The `upload` module defines [`UploadSession`], a resumable multipart upload
+//! of a single file.
+//!
+//! Each driver maps sessions to its native mechanism: S3 multipart uploads,
+//! Azure block lists, or part files assembled on completion for the disk and
+//! in-memory drivers. Since parts are persisted by the backend, an interrupted
+//! upload can be resumed with [`crate::store::Store::resume_upload`] and only
+//! the missing parts uploaded.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::PathBuf;
+//! use active_storage::StoreConfig;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!     let file_path = PathBuf::from("video.mp4");
+//!
+//!     let mut session = store.begin_upload(file_path.as_path()).await.unwrap();
+//!     session.write_part(1, "first ").await.unwrap();
+//!
+//!     // resume the upload, e.g. after a restart
+//!     let mut session = store
+//!         .resume_upload(file_path.as_path(), session.upload_id())
+//!         .await
+//!         .unwrap();
+//!     session.write_part(2, "second").await.unwrap();
+//!     session.complete().await.unwrap();
+//!
+//!     let content: String = store.read(file_path.as_path()).await.unwrap();
+//!     assert_eq!(content, "first second");
+//! }
+//! ```
+use std::{collections::BTreeMap, time::SystemTime};
+
+use crate::{
+    contents::IntoBytes,
+    drivers::{Driver, WriteOptions},
+    errors::{DriverError, DriverResult},
+    key::ObjectPath,
+};
+
+/// The folder emulated multipart uploads keep their parts under.
+pub const UPLOADS_PREFIX: &str = ".active-storage-uploads";
+
+/// The highest part number of a multipart upload.
+pub const MAX_PART_NUMBER: u32 = 10_000;
+
+/// Struct representing an uploaded part of a multipart upload.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UploadPart {
+    /// The part number, from 1 to [`MAX_PART_NUMBER`].
+    pub part_number: u32,
+    /// The backend identifier of the part content (S3 `ETag`, Azure block id).
+    pub etag: String,
+}
+
+/// Struct representing a multipart upload that was started and neither
+/// completed nor aborted, listed by [`Driver::list_uploads`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PendingUpload {
+    /// The path of the uploaded file.
+    pub path: ObjectPath,
+    /// The upload id.
+    pub upload_id: String,
+    /// The time the upload was started.
+    pub initiated: SystemTime,
+}
+
+/// Struct representing an in progress multipart upload.
+///
+/// Parts can be written in any order and are assembled by ascending part
+/// number. With S3, every part except the last must be at least 5 MiB.
+pub struct UploadSession {
+    driver: Box<dyn Driver>,
+    path: ObjectPath,
+    upload_id: String,
+    parts: BTreeMap<u32, UploadPart>,
+    options: WriteOptions,
+}
+
+impl UploadSession {
+    pub(crate) fn new(
+        driver: Box<dyn Driver>,
+        path: ObjectPath,
+        upload_id: String,
+        parts: Vec<UploadPart>,
+        options: WriteOptions,
+    ) -> Self {
+        Self {
+            driver,
+            path,
+            upload_id,
+            parts: parts
+                .into_iter()
+                .map(|part| (part.part_number, part))
+                .collect(),
+            options,
+        }
+    }
+
+    /// Returns the path of the uploaded file.
+    #[must_use]
+    pub fn path(&self) -> &ObjectPath {
+        &self.path
+    }
+
+    /// Returns the upload id, to persist for resuming the upload.
+    #[must_use]
+    pub fn upload_id(&self) -> &str {
+        &self.upload_id
+    }
+
+    /// Returns the parts uploaded so far, ordered by part number.
+    #[must_use]
+    pub fn parts(&self) -> Vec<UploadPart> {
+        self.parts.values().cloned().collect()
+    }
+
+    /// Returns `true` when the given part was already uploaded.
+    #[must_use]
+    pub fn has_part(&self, part_number: u32) -> bool {
+        self.parts.contains_key(&part_number)
+    }
+
+    /// Uploads the given part, replacing any part with the same number.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DriverError::InvalidPartNumber`] if the part number is out of
+    /// range, or an error if the part could not be uploaded.
+    pub async fn write_part<C: IntoBytes + Send>(
+        &mut self,
+        part_number: u32,
+        content: C,
+    ) -> DriverResult<UploadPart> {
+        if !(1..=MAX_PART_NUMBER).contains(&part_number) {
+            return Err(DriverError::InvalidPartNumber(part_number));
+        }
+
+        let part = self
+            .driver
+            .upload_part(
+                &self.path,
+                &self.upload_id,
+                part_number,
+                content.into_bytes(),
+            )
+            .await?;
+        self.parts.insert(part_number, part.clone());
+
+        Ok(part)
+    }
+
+    /// Completes the upload, assembling the uploaded parts into the file
+    /// written with the options the upload was started with.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the parts could not be assembled.
+    pub async fn complete(self) -> DriverResult<()> {
+        self.driver
+            .complete_upload_with_options(&self.path, &self.upload_id, &self.parts(), &self.options)
+            .await
+    }
+
+    /// Aborts the upload, discarding the uploaded parts.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the parts could not be discarded.
+    pub async fn abort(self) -> DriverResult<()> {
+        self.driver.abort_upload(&self.path, &self.upload_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{
+        path::{Path, PathBuf},
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::StoreConfig;
+
+    #[tokio::test]
+    async fn can_upload_parts_out_of_order() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let path = PathBuf::from("file.txt");
+
+        let mut session = store.begin_upload(&path).await.unwrap();
+        session.write_part(2, "world").await.unwrap();
+        session.write_part(1, "hello ").await.unwrap();
+        assert!(matches!(
+            session.write_part(0, "invalid").await,
+            Err(DriverError::InvalidPartNumber(0))
+        ));
+        session.complete().await.unwrap();
+
+        assert_eq!(store.read::<String>(&path).await.unwrap(), "hello world");
+        assert!(store
+            .records()
+            .list(Path::new(UPLOADS_PREFIX))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn can_resume_and_abort_upload() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let path = PathBuf::from("file.txt");
+
+        let mut session = store.begin_upload(&path).await.unwrap();
+        let part = session.write_part(1, "hello").await.unwrap();
+
+        let session = store
+            .resume_upload(&path, session.upload_id())
+            .await
+            .unwrap();
+        assert_eq!(session.parts(), vec![part]);
+        assert!(session.has_part(1));
+
+        session.abort().await.unwrap();
+        assert!(!store.file_exists(&path).await.unwrap());
+        assert!(store
+            .records()
+            .list(Path::new(UPLOADS_PREFIX))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn can_cleanup_incomplete_uploads() {
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let tenant = store.scoped(Path::new("tenant")).unwrap();
+
+        let mut session = tenant.begin_upload(Path::new("a/file.txt")).await.unwrap();
+        session.write_part(1, "hello").await.unwrap();
+        let mut other = tenant.begin_upload(Path::new("b/file.txt")).await.unwrap();
+        other.write_part(1, "world").await.unwrap();
+
+        assert_eq!(
+            tenant
+                .cleanup_incomplete_uploads(Path::new("a"), Duration::from_secs(3600))
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            tenant
+                .cleanup_incomplete_uploads(Path::new("a"), Duration::ZERO)
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(tenant
+            .resume_upload(Path::new("a/file.txt"), session.upload_id())
+            .await
+            .unwrap()
+            .parts()
+            .is_empty());
+        assert!(other.complete().await.is_ok());
+        assert_eq!(
+            tenant
+                .cleanup_incomplete_uploads(Path::new(""), Duration::ZERO)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+}
@@ -0,0 +1,427 @@
+//! # Validators Module
+//!
+//! The `validators` module defines [`Validators`], a list of [`Validator`]s
+//! that uploads must pass before [`Store::write_validated`] writes them.
+//!
+//! Every validator runs, so the returned [`ValidationError`] lists all the
+//! failures at once, ready to be shown to the user.
+//!
+//! Built-in validators cover the size ([`MaxSize`]), the content type
+//! ([`AllowedContentTypes`]), the image headers ([`ImageSanity`]) and the file
+//! name ([`FilenamePolicy`]).
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::PathBuf;
+//! use active_storage::{
+//!     errors::DriverError,
+//!     validators::{AllowedContentTypes, MaxSize, Validators},
+//!     StoreConfig,
+//! };
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!     let validators = Validators::new()
+//!         .with(MaxSize(5))
+//!         .with(AllowedContentTypes::new(["image/*"]));
+//!
+//!     let file_path = PathBuf::from("test.txt");
+//!     let Err(DriverError::Validation(error)) = store
+//!         .write_validated(file_path.as_path(), "my content", &validators)
+//!         .await
+//!     else {
+//!         panic!("the upload is invalid");
+//!     };
+//!     assert_eq!(error.failures.len(), 2);
+//! }
+//! ```
+//!
+//! [`Store::write_validated`]: crate::store::Store::write_validated
+use std::{fmt, path::Path, sync::Arc};
+
+/// Struct representing an upload being validated.
+#[derive(Clone, Copy, Debug)]
+pub struct Upload<'a> {
+    /// The path the upload is written to.
+    pub path: &'a Path,
+    /// The uploaded content.
+    pub content: &'a [u8],
+}
+
+impl Upload<'_> {
+    /// Returns the content type of the upload, sniffed from its content and
+    /// falling back to its extension.
+    #[must_use]
+    pub fn content_type(&self) -> String {
+        sniff_content_type(self.content).map_or_else(
+            || {
+                mime_guess::from_path(self.path)
+                    .first_or_octet_stream()
+                    .to_string()
+            },
+            ToString::to_string,
+        )
+    }
+}
+
+/// Enum representing a single validation failure.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ValidationFailure {
+    #[error("The file size {size} exceeds the maximum of {max} bytes")]
+    TooLarge { size: u64, max: u64 },
+
+    #[error("The content type {0} is not allowed")]
+    ContentTypeNotAllowed(String),
+
+    #[error("The image is invalid: {0}")]
+    InvalidImage(&'static str),
+
+    #[error("The image has {pixels} pixels, exceeding the maximum of {max}")]
+    TooManyPixels { pixels: u64, max: u64 },
+
+    #[error("The file name is invalid: {0}")]
+    InvalidFilename(&'static str),
+
+    #[error("{0}")]
+    Custom(String),
+}
+
+/// Struct representing the failures of an upload that did not pass
+/// validation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationError {
+    pub failures: Vec<ValidationFailure>,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Validation failed: ")?;
+        for (index, failure) in self.failures.iter().enumerate() {
+            if index > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{failure}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Trait representing a check an upload must pass.
+pub trait Validator: Send + Sync {
+    /// Validates the upload.
+    ///
+    /// # Errors
+    ///
+    /// Returns the failure when the upload is invalid.
+    fn validate(&self, upload: &Upload<'_>) -> Result<(), ValidationFailure>;
+}
+
+impl<F> Validator for F
+where
+    F: Fn(&Upload<'_>) -> Result<(), ValidationFailure> + Send + Sync,
+{
+    fn validate(&self, upload: &Upload<'_>) -> Result<(), ValidationFailure> {
+        self(upload)
+    }
+}
+
+/// Struct representing the validators an upload must pass.
+#[derive(Clone, Default)]
+pub struct Validators {
+    validators: Vec<Arc<dyn Validator>>,
+}
+
+impl Validators {
+    /// Creates an empty [`Validators`] list, which accepts every upload.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a validator to the list.
+    #[must_use]
+    pub fn with<V: Validator + 'static>(mut self, validator: V) -> Self {
+        self.validators.push(Arc::new(validator));
+        self
+    }
+
+    /// Runs every validator against the upload.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ValidationError`] holding the failures of all the
+    /// validators that rejected the upload.
+    pub fn validate(&self, path: &Path, content: &[u8]) -> Result<(), ValidationError> {
+        let upload = Upload { path, content };
+        let failures = self
+            .validators
+            .iter()
+            .filter_map(|validator| validator.validate(&upload).err())
+            .collect::<Vec<_>>();
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError { failures })
+        }
+    }
+}
+
+/// Rejects uploads larger than the given size in bytes.
+#[derive(Clone, Copy, Debug)]
+pub struct MaxSize(pub u64);
+
+impl Validator for MaxSize {
+    fn validate(&self, upload: &Upload<'_>) -> Result<(), ValidationFailure> {
+        let size = upload.content.len() as u64;
+        if size > self.0 {
+            return Err(ValidationFailure::TooLarge { size, max: self.0 });
+        }
+        Ok(())
+    }
+}
+
+/// Rejects uploads whose content type is not in the allowlist. Entries may
+/// end with a `/*` wildcard, such as `image/*`.
+///
+/// The content type is sniffed from the content for common formats, so
+/// renaming a file does not bypass the allowlist.
+#[derive(Clone, Debug)]
+pub struct AllowedContentTypes {
+    allowed: Vec<String>,
+}
+
+impl AllowedContentTypes {
+    /// Creates a new [`AllowedContentTypes`] validator with the given
+    /// allowlist.
+    #[must_use]
+    pub fn new<I, S>(allowed: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            allowed: allowed.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    fn is_allowed(&self, content_type: &str) -> bool {
+        self.allowed.iter().any(|allowed| {
+            allowed.strip_suffix("/*").map_or_else(
+                || allowed.eq_ignore_ascii_case(content_type),
+                |kind| {
+                    content_type
+                        .split_once('/')
+                        .is_some_and(|(prefix, _)| prefix.eq_ignore_ascii_case(kind))
+                },
+            )
+        })
+    }
+}
+
+impl Validator for AllowedContentTypes {
+    fn validate(&self, upload: &Upload<'_>) -> Result<(), ValidationFailure> {
+        let content_type = upload.content_type();
+        if !self.is_allowed(&content_type) {
+            return Err(ValidationFailure::ContentTypeNotAllowed(content_type));
+        }
+        Ok(())
+    }
+}
+
+/// Rejects image uploads (by content or extension) whose headers are
+/// malformed, truncated or have zero dimensions.
+///
+/// Only the container structure is checked, the pixel data is not decoded.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ImageSanity;
+
+impl Validator for ImageSanity {
+    fn validate(&self, upload: &Upload<'_>) -> Result<(), ValidationFailure> {
+        let content_type = upload.content_type();
+        if !content_type.starts_with("image/") {
+            return Ok(());
+        }
+
+        let content = upload.content;
+        let (width, height) = match content_type.as_str() {
+            "image/png" => {
+                if content.len() < 24 || &content[12..16] != b"IHDR" {
+                    return Err(ValidationFailure::InvalidImage("missing PNG header"));
+                }
+                (be_u32(&content[16..20]), be_u32(&content[20..24]))
+            }
+            "image/gif" => {
+                if content.len() < 10 || !content.starts_with(b"GIF8") {
+                    return Err(ValidationFailure::InvalidImage("missing GIF header"));
+                }
+                (
+                    u32::from(u16::from_le_bytes([content[6], content[7]])),
+                    u32::from(u16::from_le_bytes([content[8], content[9]])),
+                )
+            }
+            "image/jpeg" => {
+                if !content.starts_with(&[0xFF, 0xD8]) || !content.ends_with(&[0xFF, 0xD9]) {
+                    return Err(ValidationFailure::InvalidImage("truncated JPEG"));
+                }
+                (1, 1)
+            }
+            "image/webp" => {
+                if content.len() < 16 || &content[12..15] != b"VP8" {
+                    return Err(ValidationFailure::InvalidImage("missing WebP header"));
+                }
+                (1, 1)
+            }
+            _ => return Ok(()),
+        };
+
+        if width == 0 || height == 0 {
+            return Err(ValidationFailure::InvalidImage("zero dimensions"));
+        }
+        Ok(())
+    }
+}
+
+/// Rejects uploads whose file name is empty, too long, hidden, contains
+/// control characters or has an extension outside the allowlist.
+#[derive(Clone, Debug)]
+pub struct FilenamePolicy {
+    /// The maximum length of the file name in bytes.
+    pub max_length: usize,
+    /// The allowed extensions, without the leading dot. Every extension is
+    /// allowed when empty.
+    pub allowed_extensions: Vec<String>,
+}
+
+impl Default for FilenamePolicy {
+    fn default() -> Self {
+        Self {
+            max_length: 255,
+            allowed_extensions: Vec::new(),
+        }
+    }
+}
+
+impl Validator for FilenamePolicy {
+    fn validate(&self, upload: &Upload<'_>) -> Result<(), ValidationFailure> {
+        let Some(name) = upload.path.file_name().and_then(|name| name.to_str()) else {
+            return Err(ValidationFailure::InvalidFilename("missing file name"));
+        };
+
+        if name.len() > self.max_length {
+            return Err(ValidationFailure::InvalidFilename("too long"));
+        }
+        if name.starts_with('.') {
+            return Err(ValidationFailure::InvalidFilename("hidden file"));
+        }
+        if name.chars().any(char::is_control) {
+            return Err(ValidationFailure::InvalidFilename("control characters"));
+        }
+        if !self.allowed_extensions.is_empty() {
+            let extension = upload
+                .path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .unwrap_or_default();
+            if !self
+                .allowed_extensions
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(extension))
+            {
+                return Err(ValidationFailure::InvalidFilename("extension not allowed"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the content type of common formats from their magic bytes.
+pub(crate) fn sniff_content_type(content: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xFF\xD8\xFF", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1F\x8B", "application/gzip"),
+    ];
+
+    if content.len() >= 12 && content.starts_with(b"RIFF") && &content[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+    SIGNATURES
+        .iter()
+        .find(|(signature, _)| content.starts_with(signature))
+        .map(|(_, content_type)| *content_type)
+}
+
+pub(crate) fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x10\0\0\0\x10";
+
+    #[test]
+    fn can_collect_all_validation_failures() {
+        let validators = Validators::new()
+            .with(MaxSize(10))
+            .with(AllowedContentTypes::new(["image/*", "text/plain"]))
+            .with(ImageSanity)
+            .with(FilenamePolicy {
+                allowed_extensions: vec!["png".to_string(), "txt".to_string()],
+                ..FilenamePolicy::default()
+            });
+
+        assert!(validators.validate(Path::new("a.png"), PNG).is_err());
+        assert!(Validators::new()
+            .with(ImageSanity)
+            .validate(Path::new("a.png"), PNG)
+            .is_ok());
+        assert!(validators.validate(Path::new("a.txt"), b"content").is_ok());
+
+        let error = validators
+            .validate(Path::new("folder/.a.exe"), b"%PDF-1.7 content")
+            .unwrap_err();
+        assert_eq!(
+            error.failures,
+            vec![
+                ValidationFailure::TooLarge { size: 16, max: 10 },
+                ValidationFailure::ContentTypeNotAllowed("application/pdf".to_string()),
+                ValidationFailure::InvalidFilename("hidden file"),
+            ]
+        );
+
+        let error = validators
+            .validate(Path::new("a.png"), b"\x89PNG\r\n\x1a\n")
+            .unwrap_err();
+        assert_eq!(
+            error.failures,
+            vec![ValidationFailure::InvalidImage("missing PNG header")]
+        );
+
+        let custom = Validators::new().with(|upload: &Upload<'_>| {
+            if upload.content.is_empty() {
+                Err(ValidationFailure::Custom("empty".to_string()))
+            } else {
+                Ok(())
+            }
+        });
+        assert_eq!(
+            custom
+                .validate(Path::new("a.txt"), b"")
+                .unwrap_err()
+                .to_string(),
+            "Validation failed: empty"
+        );
+    }
+}
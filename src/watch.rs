@@ -0,0 +1,374 @@
+//! # Watch Module
+//!
+//! The `watch` module defines [`Watcher`], which mirrors the creations,
+//! modifications and deletions of a local directory into a [`Store`], a
+//! lightweight bridge from a local folder to a bucket.
+//!
+//! The directory is polled, comparing the size and modification time of its
+//! files between scans. Files modified within the
+//! [`WatchOptions::debounce`] window are still being written and are pushed
+//! by a later scan, and paths matching [`WatchOptions::ignore`] patterns are
+//! never pushed.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::path::Path;
+//! use active_storage::{
+//!     watch::{Change, WatchOptions, Watcher},
+//!     StoreConfig,
+//! };
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let store = StoreConfig::InMem().build().await.unwrap();
+//!     let local = std::env::temp_dir().join("active-storage-watch-example");
+//!     std::fs::create_dir_all(&local).unwrap();
+//!     std::fs::write(local.join("notes.txt"), "my content").unwrap();
+//!     std::fs::write(local.join("notes.txt.swp"), "editor state").unwrap();
+//!
+//!     let options = WatchOptions::default()
+//!         .with_debounce(std::time::Duration::ZERO)
+//!         .with_ignore("*.swp");
+//!     let mut watcher = Watcher::new(&local, store.clone()).with_options(options);
+//!
+//!     let changes = watcher.scan().await.unwrap();
+//!     assert_eq!(changes, vec![Change::Created("notes.txt".into())]);
+//!     assert!(store.file_exists(Path::new("notes.txt")).await.unwrap());
+//!     # std::fs::remove_dir_all(&local).unwrap();
+//! }
+//! ```
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use tokio::fs;
+
+use crate::{
+    clock,
+    errors::{DriverError, DriverResult},
+    store::Store,
+    transfer::CancellationToken,
+};
+
+/// Options of a [`Watcher`].
+#[derive(Clone, Debug)]
+pub struct WatchOptions {
+    /// The delay between two scans of the directory.
+    pub interval: Duration,
+    /// How long a file must stay unmodified before it is pushed, so files
+    /// are not uploaded while being written.
+    pub debounce: Duration,
+    /// Patterns of the paths never pushed. `*` matches any characters but
+    /// `/` and `?` matches a single one. Patterns without a `/` are matched
+    /// against every path component, e.g. `.git` or `*.tmp`, others against
+    /// the whole path relative to the directory.
+    pub ignore: Vec<String>,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            debounce: Duration::from_secs(2),
+            ignore: Vec::new(),
+        }
+    }
+}
+
+impl WatchOptions {
+    /// Sets the delay between two scans of the directory.
+    #[must_use]
+    pub const fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets how long a file must stay unmodified before it is pushed.
+    #[must_use]
+    pub const fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Adds a pattern of paths never pushed.
+    #[must_use]
+    pub fn with_ignore<S: Into<String>>(mut self, pattern: S) -> Self {
+        self.ignore.push(pattern.into());
+        self
+    }
+
+    /// Returns `true` when the relative path matches an ignore pattern.
+    fn is_ignored(&self, path: &Path) -> bool {
+        let components = path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>();
+        let full_path = components.join("/");
+
+        self.ignore.iter().any(|pattern| {
+            if pattern.contains('/') {
+                matches_pattern(pattern.as_bytes(), full_path.as_bytes())
+            } else {
+                components
+                    .iter()
+                    .any(|component| matches_pattern(pattern.as_bytes(), component.as_bytes()))
+            }
+        })
+    }
+}
+
+/// Enum representing a change pushed to the store, with the path relative to
+/// the watched directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// A new file was uploaded.
+    Created(PathBuf),
+    /// A modified file was uploaded again.
+    Modified(PathBuf),
+    /// A deleted file was deleted from the store.
+    Deleted(PathBuf),
+}
+
+/// The size and modification time a file was pushed with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct FileState {
+    size: u64,
+    modified: SystemTime,
+}
+
+/// Struct representing a watcher mirroring a local directory into a store.
+///
+/// The files already in the directory are pushed by the first scan.
+pub struct Watcher {
+    local: PathBuf,
+    store: Store,
+    options: WatchOptions,
+    pushed: BTreeMap<PathBuf, FileState>,
+}
+
+impl Watcher {
+    /// Creates a new [`Watcher`] mirroring the given local directory into the
+    /// root of the given store. Use [`Store::scoped`] to mirror it under a
+    /// prefix.
+    #[must_use]
+    pub fn new(local: &Path, store: Store) -> Self {
+        Self {
+            local: local.to_path_buf(),
+            store,
+            options: WatchOptions::default(),
+            pushed: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the watch options.
+    #[must_use]
+    pub fn with_options(mut self, options: WatchOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Scans the directory once and pushes the changes since the previous
+    /// scan, returning them.
+    ///
+    /// A change the store failed to apply is retried by the next scan.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory could not be read, or the first
+    /// error of the store.
+    pub async fn scan(&mut self) -> DriverResult<Vec<Change>> {
+        let files = self.local_files().await?;
+        let now = clock::now();
+        let mut changes = Vec::new();
+
+        for (path, state) in &files {
+            let previous = self.pushed.get(path).copied();
+            if previous.as_ref() == Some(state) {
+                continue;
+            }
+            let quiet_for = now.duration_since(state.modified).unwrap_or_default();
+            if quiet_for < self.options.debounce {
+                continue;
+            }
+
+            let content = fs::read(self.local.join(path)).await.map_err(io_error)?;
+            self.store.write(path, content).await?;
+            self.pushed.insert(path.clone(), *state);
+            changes.push(if previous.is_some() {
+                Change::Modified(path.clone())
+            } else {
+                Change::Created(path.clone())
+            });
+        }
+
+        let deleted = self
+            .pushed
+            .keys()
+            .filter(|path| !files.contains_key(*path))
+            .cloned()
+            .collect::<Vec<_>>();
+        for path in deleted {
+            self.store.delete_if_exists(&path).await?;
+            self.pushed.remove(&path);
+            changes.push(Change::Deleted(path));
+        }
+
+        Ok(changes)
+    }
+
+    /// Scans the directory every [`WatchOptions::interval`] until the token
+    /// is cancelled, calling the callback with the changes of every scan.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of a scan.
+    pub async fn run<F>(
+        mut self,
+        cancellation: CancellationToken,
+        mut on_changes: F,
+    ) -> DriverResult<()>
+    where
+        F: FnMut(&[Change]) + Send,
+    {
+        loop {
+            let changes = self.scan().await?;
+            if !changes.is_empty() {
+                on_changes(&changes);
+            }
+
+            tokio::select! {
+                () = cancellation.cancelled() => return Ok(()),
+                () = tokio::time::sleep(self.options.interval) => {}
+            }
+        }
+    }
+
+    /// Returns the state of the files of the directory that are not ignored,
+    /// keyed by their path relative to the directory.
+    async fn local_files(&self) -> DriverResult<BTreeMap<PathBuf, FileState>> {
+        let mut files = BTreeMap::new();
+        let mut pending = vec![PathBuf::new()];
+
+        while let Some(directory) = pending.pop() {
+            let mut entries = fs::read_dir(self.local.join(&directory))
+                .await
+                .map_err(io_error)?;
+            while let Some(entry) = entries.next_entry().await.map_err(io_error)? {
+                let path = directory.join(entry.file_name());
+                if self.options.is_ignored(&path) {
+                    continue;
+                }
+
+                let metadata = entry.metadata().await.map_err(io_error)?;
+                if metadata.is_dir() {
+                    pending.push(path);
+                } else if metadata.is_file() {
+                    files.insert(
+                        path,
+                        FileState {
+                            size: metadata.len(),
+                            modified: metadata.modified().map_err(io_error)?,
+                        },
+                    );
+                }
+            }
+        }
+
+        Ok(files)
+    }
+}
+
+/// Matches the text against a pattern where `*` matches any characters but
+/// `/` and `?` matches a single one.
+fn matches_pattern(pattern: &[u8], text: &[u8]) -> bool {
+    match (pattern.first(), text.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            matches_pattern(&pattern[1..], text)
+                || (text.first().is_some_and(|c| *c != b'/')
+                    && matches_pattern(pattern, &text[1..]))
+        }
+        (Some(b'?'), Some(c)) if *c != b'/' => matches_pattern(&pattern[1..], &text[1..]),
+        (Some(p), Some(c)) if p == c => matches_pattern(&pattern[1..], &text[1..]),
+        _ => false,
+    }
+}
+
+/// Converts a local I/O error.
+fn io_error(err: std::io::Error) -> DriverError {
+    DriverError::Any(Box::new(err))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::StoreConfig;
+
+    #[test]
+    fn can_ignore_paths() {
+        let options = WatchOptions::default()
+            .with_ignore(".git")
+            .with_ignore("*.tmp")
+            .with_ignore("build/out?.log");
+
+        assert!(options.is_ignored(Path::new(".git/config")));
+        assert!(options.is_ignored(Path::new("docs/draft.tmp")));
+        assert!(options.is_ignored(Path::new("build/out1.log")));
+        assert!(!options.is_ignored(Path::new("build/out12.log")));
+        assert!(!options.is_ignored(Path::new("docs/draft.txt")));
+        assert!(!options.is_ignored(Path::new("nested/build/out1.log")));
+    }
+
+    #[tokio::test]
+    async fn can_mirror_changes() {
+        let local = std::env::temp_dir().join(format!(
+            "active-storage-watch-{}",
+            crate::drivers::unique_id()
+        ));
+        std::fs::create_dir_all(local.join("docs")).unwrap();
+        std::fs::write(local.join("docs").join("a.txt"), "a").unwrap();
+        std::fs::write(local.join("b.tmp"), "b").unwrap();
+
+        let store = StoreConfig::InMem().build().await.unwrap();
+        let options = WatchOptions::default()
+            .with_debounce(Duration::ZERO)
+            .with_ignore("*.tmp");
+        let mut watcher = Watcher::new(&local, store.clone()).with_options(options);
+
+        let a_path = PathBuf::from("docs").join("a.txt");
+        assert_eq!(
+            watcher.scan().await.unwrap(),
+            vec![Change::Created(a_path.clone())]
+        );
+        assert!(watcher.scan().await.unwrap().is_empty());
+        assert!(!store.file_exists(Path::new("b.tmp")).await.unwrap());
+
+        std::fs::write(local.join("docs").join("a.txt"), "updated").unwrap();
+        assert_eq!(
+            watcher.scan().await.unwrap(),
+            vec![Change::Modified(a_path.clone())]
+        );
+        assert_eq!(
+            store.read::<String>(a_path.as_path()).await.unwrap(),
+            "updated"
+        );
+
+        std::fs::remove_file(local.join("docs").join("a.txt")).unwrap();
+        assert_eq!(
+            watcher.scan().await.unwrap(),
+            vec![Change::Deleted(a_path.clone())]
+        );
+        assert!(!store.file_exists(a_path.as_path()).await.unwrap());
+
+        // files modified within the debounce window wait for a later scan
+        let mut watcher = watcher.with_options(WatchOptions::default());
+        std::fs::write(local.join("c.txt"), "c").unwrap();
+        assert!(watcher.scan().await.unwrap().is_empty());
+
+        std::fs::remove_dir_all(&local).unwrap();
+    }
+}
@@ -1,25 +1,69 @@
-use std::{path::PathBuf, time::SystemTime};
+use std::{
+    collections::BTreeMap,
+    ops::Range,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
 
 use active_storage::{
-    drivers::{self, azure, Driver},
+    driver_conformance,
+    drivers::{
+        self,
+        azure::{self, BlobTier},
+        BlobType, Driver, WriteOptions,
+    },
+    errors::DriverError,
+    key::ObjectPath,
     StoreConfig,
 };
-use azure_storage_blobs::blob::operations::DeleteBlobResponse;
-
-use super::flow;
+use azure_storage_blobs::{
+    blob::operations::DeleteBlobResponse,
+    prelude::{AccessTier, PublicAccess},
+};
+use bytes::Bytes;
+use md5::{Digest, Md5};
 
 const CONTAINER_NAME: &str = "test-container";
 
-#[derive(Clone)]
+/// Staged blocks, keyed by blob name and block id.
+type Blocks = Arc<Mutex<BTreeMap<(String, String), Vec<u8>>>>;
+
+#[derive(Clone, Default)]
 struct MockClient {
     inner: drivers::inmem::InMemoryDriver,
+    blocks: Blocks,
+    /// The access tiers set on blobs, keyed by blob name.
+    tiers: Arc<Mutex<BTreeMap<String, String>>>,
+    /// Reports a wrong MD5 for every upload, as a response corrupted on the
+    /// wire.
+    corrupt_md5: bool,
+}
+
+impl MockClient {
+    /// Checks the `Content-MD5` of the content like Azure, returning the MD5
+    /// reported in the response.
+    fn check_md5(&self, content: &[u8], md5: [u8; 16]) -> azure_core::Result<Option<[u8; 16]>> {
+        if Md5::digest(content).as_slice() != md5 {
+            let kind = azure_storage::ErrorKind::HttpResponse {
+                status: azure_core::StatusCode::BadRequest,
+                error_code: Some("Md5Mismatch".to_string()),
+            };
+            return Err(azure_core::error::Error::message(kind, ""));
+        }
+        let mut reported = md5;
+        if self.corrupt_md5 {
+            reported[0] ^= 1;
+        }
+        Ok(Some(reported))
+    }
 }
 
 #[async_trait::async_trait]
 impl azure::ClientBuilderTrait for MockClient {
     async fn get_blob_content(&self, container: &str, path: &str) -> azure_core::Result<Vec<u8>> {
         assert_eq!(container, CONTAINER_NAME);
-        let path: PathBuf = PathBuf::from(path);
+        let path = ObjectPath::from(path);
 
         (self.inner.read(&path).await).map_or_else(
             |_| {
@@ -30,15 +74,27 @@ impl azure::ClientBuilderTrait for MockClient {
 
                 Err(azure_core::error::Error::message(kind, ""))
             },
-            Ok,
+            |content| Ok(content.into()),
         )
     }
 
+    async fn get_blob_range(
+        &self,
+        container: &str,
+        path: &str,
+        range: Range<u64>,
+    ) -> azure_core::Result<Vec<u8>> {
+        let content = self.get_blob_content(container, path).await?;
+        let end = usize::try_from(range.end).unwrap().min(content.len());
+        let start = usize::try_from(range.start).unwrap().min(end);
+        Ok(content[start..end].to_vec())
+    }
+
     async fn blob_exists(&self, container: &str, path: &str) -> azure_core::Result<bool> {
         assert_eq!(container, CONTAINER_NAME);
-        let path = PathBuf::from(path);
+        let path = ObjectPath::from(path);
 
-        (self.inner.file_exists(path.as_path()).await).map_or_else(
+        (self.inner.file_exists(&path).await).map_or_else(
             |_| {
                 let kind = azure_storage::ErrorKind::HttpResponse {
                     status: azure_core::StatusCode::NotFound,
@@ -55,20 +111,22 @@ impl azure::ClientBuilderTrait for MockClient {
         &self,
         container: &str,
         path: &str,
-        content: Vec<u8>,
-    ) -> azure_core::Result<()> {
+        content: Bytes,
+        md5: [u8; 16],
+    ) -> azure_core::Result<Option<[u8; 16]>> {
         assert_eq!(container, CONTAINER_NAME);
+        let reported = self.check_md5(&content, md5)?;
 
-        let path: PathBuf = PathBuf::from(path);
+        let path = ObjectPath::from(path);
         let _ = self.inner.write(&path, content).await;
-        Ok(())
+        Ok(reported)
     }
 
     async fn delete(&self, container: &str, path: &str) -> azure_core::Result<DeleteBlobResponse> {
         assert_eq!(container, CONTAINER_NAME);
-        let path = PathBuf::from(path);
+        let path = ObjectPath::from(path);
 
-        if (self.inner.delete(path.as_path()).await).is_err() {
+        if (self.inner.delete(&path).await).is_err() {
             let kind = azure_storage::ErrorKind::HttpResponse {
                 status: azure_core::StatusCode::NotFound,
                 error_code: Some("BlobNotFound".to_string()),
@@ -90,11 +148,17 @@ impl azure::ClientBuilderTrait for MockClient {
         path: &str,
     ) -> azure_core::Result<azure::BlobProperties> {
         assert_eq!(container, CONTAINER_NAME);
-        let path = PathBuf::from(path);
+        let path = ObjectPath::from(path);
 
-        if self.inner.file_exists(path.as_path()).await.unwrap() {
+        if self.inner.file_exists(&path).await.unwrap() {
+            let content = self.inner.read(&path).await.unwrap();
+            let mut content_md5 = [0; 16];
+            content_md5.copy_from_slice(Md5::digest(&content).as_slice());
             Ok(azure::BlobProperties {
-                date: SystemTime::now(),
+                date: self.inner.last_modified(&path).await.unwrap(),
+                content_length: content.len() as u64,
+                access_tier: self.tiers.lock().unwrap().get(path.as_str()).cloned(),
+                content_md5: Some(content_md5),
             })
         } else {
             let kind = azure_storage::ErrorKind::HttpResponse {
@@ -111,17 +175,298 @@ impl azure::ClientBuilderTrait for MockClient {
         let keys: Vec<PathBuf> = self.inner.files.lock().unwrap().keys().cloned().collect();
         Ok(keys)
     }
+
+    async fn container_exists(&self, container: &str) -> azure_core::Result<bool> {
+        Ok(container == CONTAINER_NAME)
+    }
+
+    async fn create_container(
+        &self,
+        container: &str,
+        _public_access: PublicAccess,
+    ) -> azure_core::Result<()> {
+        assert_eq!(container, CONTAINER_NAME);
+        Ok(())
+    }
+
+    async fn put_block(
+        &self,
+        container: &str,
+        path: &str,
+        block_id: &str,
+        content: Bytes,
+        md5: [u8; 16],
+    ) -> azure_core::Result<Option<[u8; 16]>> {
+        assert_eq!(container, CONTAINER_NAME);
+        let reported = self.check_md5(&content, md5)?;
+        self.blocks
+            .lock()
+            .unwrap()
+            .insert((path.to_string(), block_id.to_string()), content.to_vec());
+        Ok(reported)
+    }
+
+    async fn put_block_list(
+        &self,
+        container: &str,
+        path: &str,
+        block_ids: Vec<String>,
+    ) -> azure_core::Result<()> {
+        assert_eq!(container, CONTAINER_NAME);
+        let mut content = Vec::new();
+        for block_id in block_ids {
+            let block = self
+                .blocks
+                .lock()
+                .unwrap()
+                .remove(&(path.to_string(), block_id))
+                .unwrap_or_default();
+            content.extend(block);
+        }
+
+        let _ = self
+            .inner
+            .write(&ObjectPath::from(path), content.into())
+            .await;
+        Ok(())
+    }
+
+    async fn set_blob_tier(
+        &self,
+        container: &str,
+        path: &str,
+        tier: AccessTier,
+    ) -> azure_core::Result<()> {
+        if !self.blob_exists(container, path).await? {
+            let kind = azure_storage::ErrorKind::HttpResponse {
+                status: azure_core::StatusCode::NotFound,
+                error_code: Some("BlobNotFound".to_string()),
+            };
+            return Err(azure_core::error::Error::message(kind, ""));
+        }
+        self.tiers
+            .lock()
+            .unwrap()
+            .insert(path.to_string(), tier.as_ref().to_string());
+        Ok(())
+    }
+
+    async fn put_append_blob(&self, container: &str, path: &str) -> azure_core::Result<()> {
+        assert_eq!(container, CONTAINER_NAME);
+        let _ = self
+            .inner
+            .write(&ObjectPath::from(path), Bytes::new())
+            .await;
+        Ok(())
+    }
+
+    async fn append_block(
+        &self,
+        container: &str,
+        path: &str,
+        content: Bytes,
+        md5: [u8; 16],
+    ) -> azure_core::Result<Option<[u8; 16]>> {
+        let reported = self.check_md5(&content, md5)?;
+        let mut blob = self.get_blob_content(container, path).await?;
+        blob.extend_from_slice(&content);
+
+        let _ = self.inner.write(&ObjectPath::from(path), blob.into()).await;
+        Ok(reported)
+    }
+
+    async fn put_page_blob(
+        &self,
+        container: &str,
+        path: &str,
+        length: u64,
+    ) -> azure_core::Result<()> {
+        assert_eq!(container, CONTAINER_NAME);
+        assert_eq!(length % 512, 0);
+        let pages = vec![0; usize::try_from(length).unwrap()];
+        let _ = self
+            .inner
+            .write(&ObjectPath::from(path), pages.into())
+            .await;
+        Ok(())
+    }
+
+    async fn put_page(
+        &self,
+        container: &str,
+        path: &str,
+        offset: u64,
+        content: Bytes,
+        md5: [u8; 16],
+    ) -> azure_core::Result<Option<[u8; 16]>> {
+        assert_eq!(offset % 512, 0);
+        assert_eq!(content.len() % 512, 0);
+        let reported = self.check_md5(&content, md5)?;
+        let mut blob = self.get_blob_content(container, path).await?;
+        let offset = usize::try_from(offset).unwrap();
+        blob[offset..offset + content.len()].copy_from_slice(&content);
+
+        let _ = self.inner.write(&ObjectPath::from(path), blob.into()).await;
+        Ok(reported)
+    }
+
+    async fn get_uncommitted_blocks(
+        &self,
+        container: &str,
+        path: &str,
+    ) -> azure_core::Result<Vec<String>> {
+        assert_eq!(container, CONTAINER_NAME);
+        Ok(self
+            .blocks
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|(blob, _)| blob == path)
+            .map(|(_, block_id)| block_id.clone())
+            .collect())
+    }
 }
 
 #[tokio::test]
 async fn inmem() {
-    let mock_client = Box::new(MockClient {
-        inner: drivers::inmem::InMemoryDriver::default(),
-    });
+    let mock_client = Box::<MockClient>::default();
     let azure_driver =
         Box::new(azure::AzureDriver::with_client(CONTAINER_NAME, mock_client)) as Box<dyn Driver>;
 
     let store = StoreConfig::with_driver(azure_driver);
 
-    flow::test_driver(&store, PathBuf::new()).await;
+    driver_conformance::run(&store, PathBuf::new()).await;
+    driver_conformance::run_randomized(&store, PathBuf::from("random"), 20, 1).await;
+    driver_conformance::assert_strict_delete(&store).await;
+}
+
+#[tokio::test]
+async fn can_detect_checksum_mismatch() {
+    let mock_client = Box::new(MockClient {
+        corrupt_md5: true,
+        ..MockClient::default()
+    });
+    let azure_driver = azure::AzureDriver::with_client(CONTAINER_NAME, mock_client);
+    let path = ObjectPath::from("test.txt");
+
+    assert!(matches!(
+        azure_driver.write(&path, Bytes::from("content")).await,
+        Err(DriverError::ChecksumMismatch)
+    ));
+    assert!(matches!(
+        azure_driver
+            .upload_part(&path, "upload", 1, Bytes::from("content"))
+            .await,
+        Err(DriverError::ChecksumMismatch)
+    ));
+}
+
+#[tokio::test]
+async fn can_write_append_and_page_blobs() {
+    let azure_driver =
+        azure::AzureDriver::with_client(CONTAINER_NAME, Box::<MockClient>::default());
+    let log = ObjectPath::from("app.log");
+    let append = WriteOptions::default().with_blob_type(BlobType::AppendBlob);
+
+    for line in ["first\n", "second\n"] {
+        azure_driver
+            .write_with_options(&log, Bytes::from(line), &append)
+            .await
+            .unwrap();
+    }
+    assert_eq!(azure_driver.read(&log).await.unwrap(), "first\nsecond\n");
+    assert!(matches!(
+        azure_driver
+            .write_with_options(
+                &log,
+                Bytes::from("third\n"),
+                &append.clone().with_metadata("a", "b")
+            )
+            .await,
+        Err(DriverError::Unsupported(_))
+    ));
+
+    let disk = ObjectPath::from("disk.vhd");
+    let page = WriteOptions::default().with_blob_type(BlobType::PageBlob);
+    azure_driver
+        .write_with_options(&disk, Bytes::from(vec![7; 1024]), &page)
+        .await
+        .unwrap();
+    assert_eq!(azure_driver.read(&disk).await.unwrap(), vec![7; 1024]);
+    assert!(matches!(
+        azure_driver
+            .write_with_options(&disk, Bytes::from(vec![7; 100]), &page)
+            .await,
+        Err(DriverError::UnalignedPageBlob(100))
+    ));
+
+    let inmem_driver = drivers::inmem::InMemoryDriver::default();
+    assert!(matches!(
+        inmem_driver
+            .write_with_options(&log, Bytes::from("first\n"), &append)
+            .await,
+        Err(DriverError::Unsupported(_))
+    ));
+}
+
+#[tokio::test]
+async fn can_manage_blob_tiers() {
+    let azure_driver =
+        azure::AzureDriver::with_client(CONTAINER_NAME, Box::<MockClient>::default());
+    let path = ObjectPath::from("report.pdf");
+
+    azure_driver
+        .write_with_options(
+            &path,
+            Bytes::from("content"),
+            &WriteOptions::default().with_storage_class(BlobTier::Cool),
+        )
+        .await
+        .unwrap();
+    let metadata = azure_driver.stat(&path).await.unwrap().unwrap();
+    assert_eq!(metadata.storage_class.as_deref(), Some("Cool"));
+
+    azure_driver
+        .set_blob_tier(&path, BlobTier::Archive)
+        .await
+        .unwrap();
+    let metadata = azure_driver.stat(&path).await.unwrap().unwrap();
+    assert_eq!(metadata.storage_class.as_deref(), Some("Archive"));
+
+    assert!(matches!(
+        azure_driver
+            .write_with_options(
+                &path,
+                Bytes::from("content"),
+                &WriteOptions::default().with_storage_class("GLACIER"),
+            )
+            .await,
+        Err(DriverError::Unsupported(_))
+    ));
+    assert!(matches!(
+        azure_driver
+            .set_blob_tier(&ObjectPath::from("missing.pdf"), BlobTier::Hot)
+            .await,
+        Err(DriverError::ResourceNotFound)
+    ));
+}
+
+#[tokio::test]
+async fn can_list_encoded_keys() {
+    let azure_driver =
+        azure::AzureDriver::with_client(CONTAINER_NAME, Box::<MockClient>::default());
+
+    for path in ["docs/a%41", "docs/a\u{1}"] {
+        azure_driver
+            .write(&ObjectPath::from(path), Bytes::from("content"))
+            .await
+            .unwrap();
+    }
+
+    let mut files = azure_driver.list(&ObjectPath::from("docs")).await.unwrap();
+    files.sort();
+    assert_eq!(
+        files,
+        vec![ObjectPath::new("docs/a\u{1}"), ObjectPath::new("docs/a%41")]
+    );
 }
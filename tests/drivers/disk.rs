@@ -1,6 +1,12 @@
-use active_storage::{drivers::disk::Config, StoreConfig};
+use std::path::{Path, PathBuf};
 
-use super::flow;
+use active_storage::{
+    driver_conformance,
+    drivers::{disk::Config, ContainerOptions},
+    errors::DriverError,
+    key::ObjectPath,
+    StoreConfig,
+};
 
 #[tokio::test]
 async fn disk() {
@@ -12,10 +18,229 @@ async fn disk() {
     .unwrap();
     let config = Config {
         location: location.clone(),
+        public_url: None,
     };
     // let disk_driver: Store<DiskDriver> =
     // Store::<DiskDriver>::new(config).await.unwrap();
     let disk_driver = StoreConfig::Disk(config).build().await.unwrap();
 
-    flow::test_driver(&disk_driver, location).await;
+    driver_conformance::run(&disk_driver, location.clone()).await;
+    driver_conformance::run_randomized(&disk_driver, location.join("random"), 20, 1).await;
+    driver_conformance::assert_strict_delete(&disk_driver).await;
+}
+
+#[tokio::test]
+async fn ensure_container() {
+    let location = tree_fs::from_yaml_str(
+        r"
+        files:
+        ",
+    )
+    .unwrap();
+    let config = Config {
+        location: location.join("store"),
+        public_url: None,
+    };
+    let disk_driver = StoreConfig::Disk(config).build().await.unwrap();
+
+    std::fs::remove_dir_all(location.join("store")).unwrap();
+    assert!(!disk_driver.container_exists().await.unwrap());
+
+    assert!(disk_driver
+        .ensure_container(&ContainerOptions::default())
+        .await
+        .is_ok());
+    assert!(disk_driver.container_exists().await.unwrap());
+}
+
+#[tokio::test]
+async fn public_url() {
+    let location = tree_fs::from_yaml_str(
+        r"
+        files:
+        ",
+    )
+    .unwrap();
+    let config = Config {
+        location: location.clone(),
+        public_url: Some("https://cdn.example.com/assets/".to_string()),
+    };
+    let disk_driver = StoreConfig::Disk(config).build().await.unwrap();
+
+    assert_eq!(
+        disk_driver
+            .public_url(PathBuf::from("images").join("my logo#1.png").as_path())
+            .await
+            .unwrap(),
+        "https://cdn.example.com/assets/images/my%20logo%231.png"
+    );
+
+    let config = Config {
+        location,
+        public_url: None,
+    };
+    let disk_driver = StoreConfig::Disk(config).build().await.unwrap();
+    assert!(matches!(
+        disk_driver
+            .public_url(PathBuf::from("file.txt").as_path())
+            .await,
+        Err(DriverError::Unsupported(_))
+    ));
+}
+
+#[tokio::test]
+async fn purge_all() {
+    let location = tree_fs::from_yaml_str(
+        r"
+        files:
+        ",
+    )
+    .unwrap();
+    std::fs::create_dir_all(location.join("foo")).unwrap();
+    std::fs::write(location.join("foo").join("file.txt"), "content").unwrap();
+    std::fs::write(location.join("root.txt"), "content").unwrap();
+    let config = Config {
+        location: location.clone(),
+        public_url: None,
+    };
+    let disk_driver = StoreConfig::Disk(config).build().await.unwrap();
+
+    // absolute paths are resolved under the root, so they can't delete it
+    for root in [location.as_path(), location.parent().unwrap()] {
+        assert!(matches!(
+            disk_driver.delete_directory(root).await,
+            Err(DriverError::ResourceNotFound)
+        ));
+    }
+    assert!(matches!(
+        disk_driver.delete_directory(Path::new("/")).await,
+        Err(DriverError::RefusingToDeleteRoot)
+    ));
+    assert!(location.join("root.txt").is_file());
+
+    assert!(disk_driver.purge_all().await.is_ok());
+    assert!(location.is_dir());
+    assert!(std::fs::read_dir(&location).unwrap().next().is_none());
+}
+
+#[tokio::test]
+async fn legal_hold() {
+    let location = tree_fs::from_yaml_str(
+        r"
+        files:
+        ",
+    )
+    .unwrap();
+    let config = Config {
+        location: location.clone(),
+        public_url: None,
+    };
+    let disk_driver = StoreConfig::Disk(config).build().await.unwrap();
+    let file_path = Path::new("contracts/1.pdf");
+    disk_driver.write(file_path, "content").await.unwrap();
+
+    assert!(matches!(
+        disk_driver
+            .set_legal_hold(Path::new("missing.pdf"), true)
+            .await,
+        Err(DriverError::ResourceNotFound)
+    ));
+    assert!(disk_driver.set_legal_hold(file_path, true).await.is_ok());
+    assert!(disk_driver.legal_hold(file_path).await.unwrap());
+    assert!(matches!(
+        disk_driver.delete(file_path).await,
+        Err(DriverError::LegalHold)
+    ));
+    assert!(matches!(
+        disk_driver.delete_directory(Path::new("contracts")).await,
+        Err(DriverError::LegalHold)
+    ));
+    assert!(matches!(
+        disk_driver.purge_all().await,
+        Err(DriverError::LegalHold)
+    ));
+    assert!(matches!(
+        disk_driver.write(file_path, "other").await,
+        Err(DriverError::LegalHold)
+    ));
+    assert_eq!(
+        disk_driver.read::<String>(file_path).await.unwrap(),
+        "content"
+    );
+    assert!(location.join("contracts").join("1.pdf").is_file());
+
+    assert!(disk_driver.set_legal_hold(file_path, false).await.is_ok());
+    assert!(!disk_driver.legal_hold(file_path).await.unwrap());
+    assert!(disk_driver.delete(file_path).await.is_ok());
+}
+
+#[tokio::test]
+async fn encoded_keys() {
+    let location = tree_fs::from_yaml_str(
+        r"
+        files:
+        ",
+    )
+    .unwrap();
+    let config = Config {
+        location: location.clone(),
+        public_url: Some("https://cdn.example.com".to_string()),
+    };
+    let disk_driver = StoreConfig::Disk(config).build().await.unwrap();
+
+    disk_driver.write("docs/a:b", "reserved").await.unwrap();
+    disk_driver.write("docs/a%3Ab", "literal").await.unwrap();
+
+    assert!(location.join("docs").join("a%3Ab").is_file());
+    assert!(location.join("docs").join("a%253Ab").is_file());
+    assert_eq!(
+        disk_driver.read::<String>("docs/a:b").await.unwrap(),
+        "reserved"
+    );
+    assert_eq!(
+        disk_driver.read::<String>("docs/a%3Ab").await.unwrap(),
+        "literal"
+    );
+    assert_eq!(
+        disk_driver.list("docs").await.unwrap(),
+        vec![ObjectPath::new("docs/a%3Ab"), ObjectPath::new("docs/a:b")]
+    );
+    assert_eq!(
+        disk_driver.public_url("docs/a:b").await.unwrap(),
+        "https://cdn.example.com/docs/a%253Ab"
+    );
+}
+
+#[tokio::test]
+async fn percent_encoded_names_round_trip() {
+    let location = tree_fs::from_yaml_str(
+        r"
+        files:
+        ",
+    )
+    .unwrap();
+    let config = Config {
+        location: location.clone(),
+        public_url: None,
+    };
+    let disk_driver = StoreConfig::Disk(config).build().await.unwrap();
+
+    disk_driver
+        .write(Path::new("a%20b.txt"), "content")
+        .await
+        .unwrap();
+
+    let files = disk_driver.list(ObjectPath::default()).await.unwrap();
+    assert_eq!(files, vec![ObjectPath::new("a%20b.txt")]);
+    assert_eq!(
+        disk_driver.read::<String>("a%20b.txt").await.unwrap(),
+        "content"
+    );
+    assert_eq!(
+        disk_driver
+            .read::<String>(files[0].to_path_buf())
+            .await
+            .unwrap(),
+        "content"
+    );
 }
@@ -0,0 +1,131 @@
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use active_storage::{drivers::http, errors::DriverError, StoreConfig};
+
+type Files = Arc<Mutex<BTreeMap<String, (Vec<u8>, SystemTime)>>>;
+
+/// Starts a minimal file server on a random port, returning its base URL.
+fn start_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let files = Files::default();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            handle(stream, &files);
+        }
+    });
+
+    format!("http://{address}/files")
+}
+
+/// Serves a single request and closes the connection.
+fn handle(mut stream: TcpStream, files: &Files) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = BTreeMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let Some((name, value)) = line.trim_end().split_once(':') else {
+            break;
+        };
+        headers.insert(name.to_ascii_lowercase(), value.trim().to_string());
+    }
+    let mut body = vec![
+        0;
+        headers
+            .get("content-length")
+            .map_or(0, |len| len.parse().unwrap())
+    ];
+    reader.read_exact(&mut body).unwrap();
+
+    let mut files = files.lock().unwrap();
+    let (status, mut extra, content) = match (method.as_str(), files.get(&path)) {
+        ("PUT", _) => {
+            files.insert(path, (body, SystemTime::now()));
+            (200, Vec::new(), Vec::new())
+        }
+        ("DELETE", Some(_)) => {
+            files.remove(&path);
+            (204, Vec::new(), Vec::new())
+        }
+        ("GET" | "HEAD", Some((content, modified))) => {
+            let last_modified = format!("Last-Modified: {}", httpdate::fmt_http_date(*modified));
+            match headers
+                .get("range")
+                .and_then(|range| range.strip_prefix("bytes="))
+            {
+                Some(range) => {
+                    let (start, end) = range.split_once('-').unwrap();
+                    let start: usize = start.parse().unwrap();
+                    let end = content.len().min(end.parse::<usize>().unwrap() + 1);
+                    (206, vec![last_modified], content[start..end].to_vec())
+                }
+                None => (200, vec![last_modified], content.clone()),
+            }
+        }
+        _ => (404, Vec::new(), Vec::new()),
+    };
+
+    extra.push(format!("Content-Length: {}", content.len()));
+    let mut response = format!("HTTP/1.1 {status} OK\r\nConnection: close\r\n");
+    for header in extra {
+        response.push_str(&header);
+        response.push_str("\r\n");
+    }
+    response.push_str("\r\n");
+    stream.write_all(response.as_bytes()).unwrap();
+    if method != "HEAD" {
+        stream.write_all(&content).unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_http_driver() {
+    let base_url = start_server();
+    let store = StoreConfig::Http(http::Config {
+        base_url: base_url.clone(),
+        ..http::Config::default()
+    })
+    .build()
+    .await
+    .unwrap();
+    let file = Path::new("folder/my file.txt");
+
+    assert!(!store.file_exists(file).await.unwrap());
+    assert!(matches!(
+        store.read::<String>(file).await,
+        Err(DriverError::ResourceNotFound)
+    ));
+
+    store.write(file, "my content").await.unwrap();
+    assert!(store.file_exists(file).await.unwrap());
+    assert_eq!(store.read::<String>(file).await.unwrap(), "my content");
+    assert_eq!(store.size(file).await.unwrap(), 10);
+    assert_eq!(store.read_range(file, 3..10).await.unwrap(), b"content");
+    assert!(store.last_modified(file).await.is_ok());
+    assert_eq!(
+        store.public_url(file).await.unwrap(),
+        format!("{base_url}/folder/my%20file.txt")
+    );
+    assert!(matches!(
+        store.list(Path::new("folder")).await,
+        Err(DriverError::Unsupported(_))
+    ));
+
+    store.delete(file).await.unwrap();
+    assert!(!store.file_exists(file).await.unwrap());
+}
@@ -1,12 +1,12 @@
 use std::path::PathBuf;
 
-use active_storage::StoreConfig;
-
-use super::flow;
+use active_storage::{driver_conformance, StoreConfig};
 
 #[tokio::test]
 async fn inmem() {
     let inmem_driver = StoreConfig::InMem().build().await.unwrap();
 
-    flow::test_driver(&inmem_driver, PathBuf::new()).await;
+    driver_conformance::run(&inmem_driver, PathBuf::new()).await;
+    driver_conformance::run_randomized(&inmem_driver, PathBuf::from("random"), 100, 1).await;
+    driver_conformance::assert_strict_delete(&inmem_driver).await;
 }
@@ -1,9 +1,10 @@
-#[cfg(feature = "aws_s3")]
+#[cfg(all(feature = "aws_s3", feature = "test-util"))]
 mod aws_s3;
 #[cfg(feature = "azure")]
 mod azure;
 #[cfg(feature = "disk")]
 mod disk;
-mod flow;
+#[cfg(feature = "http")]
+mod http;
 #[cfg(feature = "inmem")]
 mod inmem;
@@ -24,12 +24,14 @@ fn create_temp_folder() -> PathBuf {
 async fn init_multi_store(root_path_store_1: &Path, root_path_store_2: &Path) -> MultiStore {
     let config = drivers::disk::Config {
         location: root_path_store_1.join("store-1"),
+        public_url: None,
     };
 
     let store_one = StoreConfig::Disk(config).build().await.unwrap();
 
     let config = drivers::disk::Config {
         location: root_path_store_2.join("store-2"),
+        public_url: None,
     };
     let secondary_store = StoreConfig::Disk(config).build().await.unwrap();
 
@@ -146,17 +148,20 @@ async fn can_failure_policy(#[case] policy: Policy) {
 
     let config = drivers::disk::Config {
         location: root_location_first_store.join("store-1"),
+        public_url: None,
     };
 
     let first_store = StoreConfig::Disk(config).build().await.unwrap();
 
     let config = drivers::disk::Config {
         location: root_location_second_store.join("store-2"),
+        public_url: None,
     };
     let second_store = StoreConfig::Disk(config).build().await.unwrap();
 
     let config = drivers::disk::Config {
         location: root_location_third_store.join("store-3"),
+        public_url: None,
     };
     let third_store = StoreConfig::Disk(config).build().await.unwrap();
 
@@ -192,17 +197,20 @@ async fn can_mirror() {
 
     let config = drivers::disk::Config {
         location: root_location_first_store.join("store-1"),
+        public_url: None,
     };
 
     let first_store = StoreConfig::Disk(config).build().await.unwrap();
 
     let config = drivers::disk::Config {
         location: root_location_second_store.join("store-2"),
+        public_url: None,
     };
     let second_store = StoreConfig::Disk(config).build().await.unwrap();
 
     let config = drivers::disk::Config {
         location: root_location_third_store.join("store-3"),
+        public_url: None,
     };
     let third_store = StoreConfig::Disk(config).build().await.unwrap();
 